@@ -198,7 +198,7 @@ impl Account {
     ) -> Result<Self, BasicErrorWith404> {
         // Use the latest ledger version, or the requested associated version
         let (latest_ledger_info, requested_ledger_version) = context
-            .get_latest_ledger_info_and_verify_lookup_version(
+            .get_latest_ledger_info_and_verify_state_lookup_version(
                 requested_ledger_version.map(|inner| inner.0),
             )?;
 
@@ -0,0 +1,198 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    accept_type::AcceptType,
+    failpoint::fail_point_poem,
+    response::{
+        api_forbidden, AptosErrorResponse, BadRequestError, BasicErrorWith404, BasicResponse,
+        BasicResponseStatus, BasicResultWith404, InternalError,
+    },
+    state::StateApi,
+    view_function::ViewFunctionApi,
+    ApiTags, Context,
+};
+use anyhow::Context as AnyhowContext;
+use aptos_api_types::{
+    AptosErrorCode, BatchReadRequest, BatchReadResult, BatchResourceRequest, BatchTableItemRequest,
+    LedgerInfo, VerifyInput, U64,
+};
+use aptos_storage_interface::state_view::DbStateView;
+use move_core_types::language_storage::StructTag;
+use poem_openapi::{param::Query, payload::Json, OpenApi};
+use serde::Serialize;
+use std::{convert::TryInto, sync::Arc};
+
+/// API for running many reads against the same ledger version
+pub struct BatchApi {
+    pub context: Arc<Context>,
+}
+
+#[OpenApi]
+impl BatchApi {
+    /// Batch read
+    ///
+    /// Runs a list of account resource, table item and view function reads against a single
+    /// ledger version, and returns their results in the same order as the reads. A failing
+    /// read does not fail the whole batch; its result carries the error instead.
+    ///
+    /// Only JSON is supported as an AcceptType.
+    ///
+    /// The Aptos nodes prune account state history, via a configurable time window.
+    /// If the requested ledger version has been pruned, the server responds with a 410.
+    #[oai(
+        path = "/batch",
+        method = "post",
+        operation_id = "batch_read",
+        tag = "ApiTags::General"
+    )]
+    async fn batch_read(
+        &self,
+        accept_type: AcceptType,
+        /// Reads to run, in order
+        requests: Json<Vec<BatchReadRequest>>,
+        /// Ledger version to run the reads at
+        ///
+        /// If not provided, it will be the latest version
+        ledger_version: Query<Option<U64>>,
+    ) -> BasicResultWith404<Vec<BatchReadResult>> {
+        fail_point_poem("endpoint_batch_read")?;
+
+        if AcceptType::Bcs == accept_type {
+            return Err(api_forbidden(
+                "Batch read",
+                "Only JSON is supported as an AcceptType.",
+            ));
+        }
+        self.context
+            .check_api_output_enabled("Batch read", &accept_type)?;
+
+        if requests.0.len() > self.context.max_batch_read_size() {
+            return Err(BasicErrorWith404::bad_request_with_code_no_info(
+                format!(
+                    "Requested too many reads: {}, while limit is {}",
+                    requests.0.len(),
+                    self.context.max_batch_read_size(),
+                ),
+                AptosErrorCode::InvalidInput,
+            ));
+        }
+
+        let (ledger_info, ledger_version, state_view) = self
+            .context
+            .state_view(ledger_version.0.map(|inner| inner.0))?;
+
+        let results = requests
+            .0
+            .into_iter()
+            .map(
+                |request| match self.read(&ledger_info, ledger_version, &state_view, request) {
+                    Ok(value) => BatchReadResult::value(value),
+                    Err(mut err) => BatchReadResult::error(err.inner_mut().clone()),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        BasicResponse::try_from_json((results, &ledger_info, BasicResponseStatus::Ok))
+    }
+}
+
+impl BatchApi {
+    /// Run a single read of the batch, reusing the logic of the individual APIs
+    fn read(
+        &self,
+        ledger_info: &LedgerInfo,
+        ledger_version: u64,
+        state_view: &DbStateView,
+        request: BatchReadRequest,
+    ) -> Result<serde_json::Value, BasicErrorWith404> {
+        request
+            .verify()
+            .context("'request' invalid")
+            .map_err(|err| {
+                BasicErrorWith404::bad_request_with_code(
+                    err,
+                    AptosErrorCode::InvalidInput,
+                    ledger_info,
+                )
+            })?;
+
+        match request {
+            BatchReadRequest::Resource(BatchResourceRequest {
+                address,
+                resource_type,
+            }) => {
+                let resource_type: StructTag = resource_type
+                    .try_into()
+                    .context("Failed to parse given resource type")
+                    .map_err(|err| {
+                        BasicErrorWith404::bad_request_with_code(
+                            err,
+                            AptosErrorCode::InvalidInput,
+                            ledger_info,
+                        )
+                    })?;
+
+                let state_api = self.state_api();
+                let bytes = state_api.resource_bytes(
+                    ledger_info,
+                    ledger_version,
+                    state_view,
+                    address,
+                    &resource_type,
+                )?;
+                let resource =
+                    state_api.resource_to_json(ledger_info, state_view, &resource_type, &bytes)?;
+                to_json_value(&resource, ledger_info)
+            },
+            BatchReadRequest::TableItem(BatchTableItemRequest {
+                table_handle,
+                request,
+            }) => {
+                let state_api = self.state_api();
+                let (value_type, bytes) = state_api.table_item_bytes(
+                    ledger_info,
+                    ledger_version,
+                    state_view,
+                    table_handle,
+                    request,
+                )?;
+                let move_value =
+                    state_api.table_item_to_json(ledger_info, state_view, &value_type, &bytes)?;
+                to_json_value(&move_value, ledger_info)
+            },
+            BatchReadRequest::View(request) => {
+                let view_function_api = ViewFunctionApi {
+                    context: self.context.clone(),
+                };
+                // All reads, including the module lookups, happen at the batch's ledger version.
+                let (entry_func, return_vals) =
+                    view_function_api.execute(ledger_info, state_view, state_view, request)?;
+                let move_vals = view_function_api.return_values_to_json(
+                    ledger_info,
+                    state_view,
+                    &entry_func,
+                    return_vals,
+                )?;
+                to_json_value(&move_vals, ledger_info)
+            },
+        }
+    }
+
+    fn state_api(&self) -> StateApi {
+        StateApi {
+            context: self.context.clone(),
+        }
+    }
+}
+
+fn to_json_value<T: Serialize>(
+    value: &T,
+    ledger_info: &LedgerInfo,
+) -> Result<serde_json::Value, BasicErrorWith404> {
+    serde_json::to_value(value)
+        .context("Failed to serialize read result")
+        .map_err(|err| {
+            BasicErrorWith404::internal_with_code(err, AptosErrorCode::InternalError, ledger_info)
+        })
+}
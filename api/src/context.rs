@@ -6,8 +6,9 @@ use crate::{
     accept_type::AcceptType,
     response::{
         bcs_api_disabled, block_not_found_by_height, block_not_found_by_version,
-        block_pruned_by_height, json_api_disabled, version_not_found, version_pruned,
-        ForbiddenError, InternalError, NotFoundError, ServiceUnavailableError, StdApiError,
+        block_pruned_by_height, json_api_disabled, state_version_pruned, version_not_found,
+        version_pruned, ForbiddenError, InternalError, NotFoundError, ServiceUnavailableError,
+        StdApiError,
     },
 };
 use anyhow::{bail, ensure, format_err, Context as AnyhowContext, Result};
@@ -56,6 +57,8 @@ use std::{
     sync::{Arc, RwLock, RwLockWriteGuard},
     time::Instant,
 };
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
+use url::Url;
 
 // Context holds application scope context
 #[derive(Clone)]
@@ -67,6 +70,8 @@ pub struct Context {
     gas_schedule_cache: Arc<RwLock<GasScheduleCache>>,
     gas_estimation_cache: Arc<RwLock<GasEstimationCache>>,
     gas_limit_cache: Arc<RwLock<GasLimitCache>>,
+    committed_transactions: Option<broadcast::Sender<Arc<Vec<TransactionOnChainData>>>>,
+    subscription_permits: Arc<Semaphore>,
 }
 
 impl std::fmt::Debug for Context {
@@ -82,6 +87,7 @@ impl Context {
         mp_sender: MempoolClientSender,
         node_config: NodeConfig,
     ) -> Self {
+        let subscription_permits = Arc::new(Semaphore::new(node_config.api.max_subscriptions));
         Self {
             chain_id,
             db,
@@ -101,9 +107,35 @@ impl Context {
                 last_updated_epoch: None,
                 block_gas_limit: None,
             })),
+            committed_transactions: None,
+            subscription_permits,
         }
     }
 
+    /// Sets the channel carrying the batches of newly committed transactions, which all the
+    /// subscriptions read from.
+    pub fn with_committed_transactions(
+        mut self,
+        committed_transactions: broadcast::Sender<Arc<Vec<TransactionOnChainData>>>,
+    ) -> Self {
+        self.committed_transactions = Some(committed_transactions);
+        self
+    }
+
+    pub fn subscribe_to_committed_transactions(
+        &self,
+    ) -> Option<broadcast::Receiver<Arc<Vec<TransactionOnChainData>>>> {
+        self.committed_transactions
+            .as_ref()
+            .map(|committed_transactions| committed_transactions.subscribe())
+    }
+
+    /// Reserves one of the `max_subscriptions` open subscription slots, which is released when
+    /// the returned permit is dropped.
+    pub fn try_acquire_subscription_permit(&self) -> Option<OwnedSemaphorePermit> {
+        self.subscription_permits.clone().try_acquire_owned().ok()
+    }
+
     pub fn max_transactions_page_size(&self) -> u16 {
         self.node_config.api.max_transactions_page_size
     }
@@ -139,7 +171,7 @@ impl Context {
         requested_ledger_version: Option<u64>,
     ) -> Result<(LedgerInfo, u64, DbStateView), E> {
         let (latest_ledger_info, requested_ledger_version) =
-            self.get_latest_ledger_info_and_verify_lookup_version(requested_ledger_version)?;
+            self.get_latest_ledger_info_and_verify_state_lookup_version(requested_ledger_version)?;
 
         let state_view = self
             .state_view_at_version(requested_ledger_version)
@@ -166,14 +198,38 @@ impl Context {
         self.node_config.api.content_length_limit()
     }
 
+    pub fn archival_upstream_url(&self) -> Option<Url> {
+        self.node_config.api.archival_upstream_url.clone()
+    }
+
     pub fn failpoints_enabled(&self) -> bool {
         self.node_config.api.failpoints_enabled
     }
 
+    pub fn mempool_introspection_enabled(&self) -> bool {
+        self.node_config.api.mempool_introspection_enabled
+    }
+
+    pub fn subscriptions_enabled(&self) -> bool {
+        self.node_config.api.subscriptions_enabled
+    }
+
+    pub fn graphql_enabled(&self) -> bool {
+        self.node_config.api.graphql_enabled
+    }
+
     pub fn max_submit_transaction_batch_size(&self) -> usize {
         self.node_config.api.max_submit_transaction_batch_size
     }
 
+    pub fn max_batch_read_size(&self) -> usize {
+        self.node_config.api.max_batch_read_size
+    }
+
+    pub fn max_simulation_bundle_size(&self) -> usize {
+        self.node_config.api.max_simulation_bundle_size
+    }
+
     pub async fn submit_transaction(&self, txn: SignedTransaction) -> Result<SubmissionStatus> {
         let (req_sender, callback) = oneshot::channel();
         self.mp_sender
@@ -254,6 +310,42 @@ impl Context {
         Ok((latest_ledger_info, requested_ledger_version))
     }
 
+    /// Same as [`Self::get_latest_ledger_info_and_verify_lookup_version`], but also makes sure
+    /// the state at the requested version hasn't been pruned
+    pub fn get_latest_ledger_info_and_verify_state_lookup_version<E: StdApiError>(
+        &self,
+        requested_ledger_version: Option<Version>,
+    ) -> Result<(LedgerInfo, Version), E> {
+        let (latest_ledger_info, requested_ledger_version) =
+            self.get_latest_ledger_info_and_verify_lookup_version(requested_ledger_version)?;
+
+        let oldest_state_version = self.get_oldest_state_version(&latest_ledger_info)?;
+        if requested_ledger_version < oldest_state_version {
+            return Err(state_version_pruned(
+                requested_ledger_version,
+                oldest_state_version,
+                &latest_ledger_info,
+            ));
+        }
+
+        Ok((latest_ledger_info, requested_ledger_version))
+    }
+
+    /// The oldest version at which state can be read, which is never older than the oldest
+    /// ledger version
+    pub fn get_oldest_state_version<E: InternalError>(
+        &self,
+        ledger_info: &LedgerInfo,
+    ) -> Result<Version, E> {
+        let oldest_state_value_version = self
+            .db
+            .get_first_state_value_version()
+            .context("Failed to retrieve oldest state version in DB")
+            .map_err(|e| E::internal_with_code(e, AptosErrorCode::InternalError, ledger_info))?
+            .unwrap_or(0);
+        Ok(oldest_state_value_version.max(ledger_info.oldest_version()))
+    }
+
     pub fn get_latest_ledger_info_with_signatures(&self) -> Result<LedgerInfoWithSignatures> {
         self.db.get_latest_ledger_info()
     }
@@ -770,6 +862,22 @@ impl Context {
         callback.await.map_err(anyhow::Error::from)
     }
 
+    /// Sends an introspection request to mempool, built from the callback it should answer on.
+    pub async fn mempool_introspection<T>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<T>) -> MempoolClientRequest,
+    ) -> Result<T> {
+        let (req_sender, callback) = oneshot::channel();
+
+        self.mp_sender
+            .clone()
+            .send(request(req_sender))
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        callback.await.map_err(anyhow::Error::from)
+    }
+
     pub fn get_transaction_by_version(
         &self,
         version: u64,
@@ -0,0 +1,283 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Context as AnyhowContext;
+use aptos_logger::{
+    prelude::{sample, SampleRate},
+    warn,
+};
+use bytes::Bytes;
+use poem::{
+    http::{header, HeaderMap, Method, StatusCode, Uri},
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+use std::time::Duration;
+use url::Url;
+
+/// How long to wait for the archival node before giving up and returning the local response
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// This middleware forwards requests for pruned data to an archival node. Every pruned
+/// error is a 410 Gone, so when the local response has that status the request is replayed
+/// against the archival node and its response is returned instead. If the archival node
+/// can't be reached, the local response is returned.
+pub struct ForwardPruned {
+    upstream: Option<Url>,
+}
+
+impl ForwardPruned {
+    pub fn new(upstream: Option<Url>) -> Self {
+        Self { upstream }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ForwardPruned {
+    type Output = ForwardPrunedEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ForwardPrunedEndpoint {
+            inner: ep,
+            upstream: self.upstream.clone(),
+            client: reqwest::Client::builder()
+                .timeout(FORWARD_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client for the archival node"),
+        }
+    }
+}
+
+/// Endpoint for ForwardPruned middleware.
+pub struct ForwardPrunedEndpoint<E> {
+    inner: E,
+    upstream: Option<Url>,
+    client: reqwest::Client,
+}
+
+impl<E> ForwardPrunedEndpoint<E> {
+    async fn forward(
+        &self,
+        upstream: &Url,
+        method: Method,
+        uri: &Uri,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> anyhow::Result<Response> {
+        let mut url = upstream.clone();
+        url.set_path(&upstream_path(upstream, uri));
+        url.set_query(uri.query());
+
+        let upstream_response = self
+            .client
+            .request(method, url)
+            .headers(without_hop_by_hop_headers(headers))
+            .body(body)
+            .send()
+            .await
+            .context("Failed to send request to archival node")?;
+
+        let status = upstream_response.status();
+        let headers = without_hop_by_hop_headers(upstream_response.headers().clone());
+        let body = upstream_response
+            .bytes()
+            .await
+            .context("Failed to read response from archival node")?;
+
+        let mut response = Response::builder().status(status).body(body);
+        *response.headers_mut() = headers;
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for ForwardPrunedEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let upstream = match &self.upstream {
+            Some(upstream) if may_read_pruned_data(&req) => upstream,
+            _ => return self.inner.call(req).await.map(IntoResponse::into_response),
+        };
+
+        // The body can only be read once, so keep a copy around in case the request has to be
+        // replayed against the archival node.
+        let method = req.method().clone();
+        let uri = req.uri().clone();
+        let headers = req.headers().clone();
+        let body = req.take_body().into_bytes().await?;
+        req.set_body(body.clone());
+
+        let result = self.inner.call(req).await.map(IntoResponse::into_response);
+        let status = match &result {
+            Ok(response) => response.status(),
+            Err(err) => err.status(),
+        };
+        if status != StatusCode::GONE {
+            return result;
+        }
+
+        match self.forward(upstream, method, &uri, headers, body).await {
+            Ok(response) => Ok(response),
+            Err(err) => {
+                sample!(
+                    SampleRate::Duration(Duration::from_secs(60)),
+                    warn!(
+                        "Failed to forward request for {} to archival node: {:#}",
+                        uri.path(),
+                        err
+                    )
+                );
+                result
+            },
+        }
+    }
+}
+
+/// Whether the request can be answered with a pruned error, and so may have to be forwarded.
+/// Requests without a ledger version only read the latest data, which is never pruned; GET
+/// requests also address versions, blocks and events in their path, and carry no body to buffer.
+fn may_read_pruned_data(req: &Request) -> bool {
+    req.method() == Method::GET
+        || req.uri().query().map_or(false, |query| {
+            url::form_urlencoded::parse(query.as_bytes()).any(|(key, _)| key == "ledger_version")
+        })
+}
+
+/// Joins the path of the request onto the base path of the archival node's URL, if it has one
+fn upstream_path(upstream: &Url, uri: &Uri) -> String {
+    format!("{}{}", upstream.path().trim_end_matches('/'), uri.path())
+}
+
+/// Removes the headers that only make sense for a single connection, and must not be copied
+/// over when forwarding a request or its response.
+fn without_hop_by_hop_headers(mut headers: HeaderMap) -> HeaderMap {
+    for name in [
+        header::CONNECTION,
+        header::CONTENT_LENGTH,
+        header::HOST,
+        header::TRANSFER_ENCODING,
+        header::UPGRADE,
+    ] {
+        headers.remove(name);
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{upstream_path, ForwardPruned};
+    use poem::{
+        handler,
+        http::{Method, StatusCode, Uri},
+        listener::{Acceptor, Listener, TcpListener},
+        web::Json,
+        Body, Endpoint, EndpointExt, Request, Response, Server,
+    };
+    use serde_json::{json, Value};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use url::Url;
+
+    #[test]
+    fn test_upstream_path_keeps_base_path() {
+        let uri = Uri::from_static("/v1/accounts/0x1?ledger_version=5");
+        for (upstream, expected) in [
+            ("http://archive:8080", "/v1/accounts/0x1"),
+            ("http://archive:8080/", "/v1/accounts/0x1"),
+            ("http://archive/aptos", "/aptos/v1/accounts/0x1"),
+            ("http://archive/aptos/", "/aptos/v1/accounts/0x1"),
+        ] {
+            let upstream = Url::parse(upstream).unwrap();
+            assert_eq!(upstream_path(&upstream, &uri), expected);
+        }
+    }
+
+    /// Echoes the request it receives, so the test can check what was forwarded.
+    #[handler]
+    fn echo(req: &Request, body: String) -> Json<Value> {
+        Json(json!({
+            "method": req.method().as_str(),
+            "path": req.uri().path(),
+            "query": req.uri().query(),
+            "body": body,
+        }))
+    }
+
+    /// Starts a mock archival node, and returns its URL.
+    async fn start_upstream() -> Url {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let address = acceptor.local_addr()[0].as_socket_addr().cloned().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(echo));
+        Url::parse(&format!("http://{}/archive", address)).unwrap()
+    }
+
+    /// A local endpoint that answers every request with `status`, and counts the requests.
+    fn local_endpoint(
+        status: StatusCode,
+        upstream: Url,
+    ) -> (impl Endpoint<Output = Response>, Arc<AtomicUsize>) {
+        let num_requests = Arc::new(AtomicUsize::new(0));
+        let counter = num_requests.clone();
+        let endpoint = poem::endpoint::make_sync(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Response::builder().status(status).finish()
+        })
+        .with(ForwardPruned::new(Some(upstream)));
+        (endpoint, num_requests)
+    }
+
+    #[tokio::test]
+    async fn test_forwards_pruned_version_request() {
+        let upstream = start_upstream().await;
+        let (endpoint, num_requests) = local_endpoint(StatusCode::GONE, upstream);
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(Uri::from_static("/v1/view?ledger_version=5"))
+            .body(Body::from_string(
+                r#"{"function":"0x1::coin::balance"}"#.to_string(),
+            ));
+        let response = endpoint.call(request).await.unwrap();
+        assert_eq!(num_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(response.status(), StatusCode::OK);
+        let forwarded: Value =
+            serde_json::from_slice(&response.into_body().into_vec().await.unwrap()).unwrap();
+        assert_eq!(
+            forwarded,
+            json!({
+                "method": "POST",
+                "path": "/archive/v1/view",
+                "query": "ledger_version=5",
+                "body": r#"{"function":"0x1::coin::balance"}"#,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_does_not_forward_other_requests() {
+        let upstream = start_upstream().await;
+
+        // Only pruned errors are forwarded.
+        let (endpoint, _) = local_endpoint(StatusCode::NOT_FOUND, upstream.clone());
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(Uri::from_static("/v1/transactions/by_version/5"))
+            .finish();
+        let response = endpoint.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Requests without a ledger version read the latest data, so they are never forwarded.
+        let (endpoint, num_requests) = local_endpoint(StatusCode::GONE, upstream);
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(Uri::from_static("/v1/view"))
+            .body(Body::from_string("{}".to_string()));
+        let response = endpoint.call(request).await.unwrap();
+        assert_eq!(num_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(response.status(), StatusCode::GONE);
+    }
+}
@@ -0,0 +1,605 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Optional GraphQL read API over the same node state as the REST API. It lets clients fetch
+//! nested data (e.g. an account with some of its resources, modules and transactions) in a
+//! single round trip, all at one ledger version.
+//!
+//! Like the other non OpenAPI endpoints, it's added manually to the routes, and only if
+//! `graphql_enabled` is set in the API config, which it isn't by default. Queries are bounded
+//! by a maximum depth and a maximum complexity, where list fields cost their `limit` times the
+//! cost of their items, and every list `limit` is capped by the matching REST page size.
+
+use crate::{
+    context::Context,
+    page::determine_limit,
+    response::{AptosErrorResponse, BadRequestError, BasicErrorWith404, InternalError},
+    state::StateApi,
+};
+use anyhow::Context as AnyhowContext;
+use aptos_api_types::{
+    Address, AptosErrorCode, AsConverter, Block, HashValue, LedgerInfo, MoveModuleBytecode,
+    MoveResource, MoveStructTag, MoveType, MoveValue, TableItemRequest, Transaction,
+    TransactionOnChainData, VersionedEvent,
+};
+use aptos_state_view::TStateView;
+use aptos_storage_interface::state_view::DbStateView;
+use aptos_types::{access_path::AccessPath, event::EventKey, state_store::state_key::StateKey};
+use aptos_vm::data_cache::AsMoveResolver;
+use async_graphql::{
+    http::GraphiQLSource, EmptyMutation, EmptySubscription, Error, ErrorExtensions, Json, Object,
+    Schema,
+};
+use async_graphql_poem::{GraphQLRequest, GraphQLResponse};
+use move_core_types::language_storage::{ModuleId, StructTag};
+use poem::{
+    handler,
+    web::{Data, Html},
+};
+use std::{str::FromStr, sync::Arc};
+
+/// Default number of items returned by list fields, if no `limit` is given.
+const DEFAULT_LIST_LIMIT: u16 = 25;
+
+pub type GraphQLSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Builds the GraphQL schema, with the query limits from the API config
+pub fn build_schema(context: Arc<Context>) -> GraphQLSchema {
+    let max_depth = context.node_config.api.graphql_max_depth;
+    let max_complexity = context.node_config.api.graphql_max_complexity;
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(context)
+        .limit_depth(max_depth)
+        .limit_complexity(max_complexity)
+        .finish()
+}
+
+#[handler]
+pub async fn graphql(
+    context: Data<&Arc<Context>>,
+    schema: Data<&GraphQLSchema>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    execute(&schema, &context, request.0).await.into()
+}
+
+/// Runs a query. Every field of the query is resolved at the same ledger version, the latest one
+/// when the query starts, unless a field explicitly asks for another version.
+pub async fn execute(
+    schema: &GraphQLSchema,
+    context: &Context,
+    request: async_graphql::Request,
+) -> async_graphql::Response {
+    let ledger_info = match context.get_latest_ledger_info::<BasicErrorWith404>() {
+        Ok(ledger_info) => ledger_info,
+        Err(err) => {
+            let error = api_error(err).into_server_error(Default::default());
+            return async_graphql::Response::from_errors(vec![error]);
+        },
+    };
+    schema.execute(request.data(ledger_info)).await
+}
+
+/// Serves the GraphiQL IDE, to explore the schema from a browser
+#[handler]
+pub async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/v1/graphql").finish())
+}
+
+/// Converts an API error into a GraphQL error, keeping the error code as an extension
+fn api_error(mut err: BasicErrorWith404) -> Error {
+    let error = err.inner_mut();
+    let error_code = serde_json::to_value(error.error_code)
+        .ok()
+        .and_then(|code| code.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let vm_error_code = error.vm_error_code;
+    let oldest_available_version = error.oldest_available_version.map(|version| version.0);
+    Error::new(error.message.clone()).extend_with(|_, extensions| {
+        extensions.set("error_code", error_code.clone());
+        if let Some(vm_error_code) = vm_error_code {
+            extensions.set("vm_error_code", vm_error_code);
+        }
+        if let Some(oldest_available_version) = oldest_available_version {
+            extensions.set("oldest_available_version", oldest_available_version);
+        }
+    })
+}
+
+/// Turns a "not found" error into `None`, so missing data is a null field rather than an error
+fn optional<T>(result: Result<T, BasicErrorWith404>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(mut err) => {
+            if matches!(
+                err.inner_mut().error_code,
+                AptosErrorCode::ResourceNotFound
+                    | AptosErrorCode::ModuleNotFound
+                    | AptosErrorCode::TableItemNotFound
+                    | AptosErrorCode::TransactionNotFound
+                    | AptosErrorCode::BlockNotFound
+            ) {
+                Ok(None)
+            } else {
+                Err(api_error(err))
+            }
+        },
+    }
+}
+
+fn parse<T: FromStr>(name: &str, value: &str, ledger_info: &LedgerInfo) -> Result<T, Error>
+where
+    T::Err: std::fmt::Display,
+{
+    T::from_str(value)
+        .map_err(|err| format!("Failed to parse {}: {}", name, err))
+        .map_err(|err| {
+            api_error(BasicErrorWith404::bad_request_with_code(
+                err,
+                AptosErrorCode::InvalidInput,
+                ledger_info,
+            ))
+        })
+}
+
+fn internal_error(err: anyhow::Error, ledger_info: &LedgerInfo) -> Error {
+    api_error(BasicErrorWith404::internal_with_code(
+        err,
+        AptosErrorCode::InternalError,
+        ledger_info,
+    ))
+}
+
+fn limit(requested: Option<u16>, max: u16, ledger_info: &LedgerInfo) -> Result<u16, Error> {
+    determine_limit::<BasicErrorWith404>(requested, DEFAULT_LIST_LIMIT.min(max), max, ledger_info)
+        .map_err(api_error)
+}
+
+/// Renders transactions read from storage in the JSON format of the REST API
+fn render_transactions(
+    context: &Context,
+    ledger_info: &LedgerInfo,
+    data: Vec<TransactionOnChainData>,
+) -> Result<Vec<Json<Transaction>>, Error> {
+    Ok(context
+        .render_transactions_non_sequential::<BasicErrorWith404>(ledger_info, data)
+        .map_err(api_error)?
+        .into_iter()
+        .map(Json)
+        .collect())
+}
+
+fn list_events(
+    context: &Context,
+    ledger_info: &LedgerInfo,
+    event_key: EventKey,
+    start: Option<u64>,
+    limit: Option<u16>,
+) -> Result<Vec<Json<VersionedEvent>>, Error> {
+    let limit = self::limit(limit, context.max_events_page_size(), ledger_info)?;
+    let events = context
+        .get_events(&event_key, start, limit, ledger_info.version())
+        .context(format!("Failed to find events by key {}", event_key))
+        .map_err(|err| internal_error(err, ledger_info))?;
+    let state_view = context
+        .state_view_at_version(ledger_info.version())
+        .map_err(|err| internal_error(err, ledger_info))?;
+    let events = state_view
+        .as_move_resolver()
+        .as_converter(context.db.clone())
+        .try_into_versioned_events(&events)
+        .context("Failed to convert events from storage into response")
+        .map_err(|err| internal_error(err, ledger_info))?;
+    Ok(events.into_iter().map(Json).collect())
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The ledger information the query is resolved at
+    async fn ledger_info(&self, ctx: &async_graphql::Context<'_>) -> Json<LedgerInfo> {
+        Json(ctx.data_unchecked::<LedgerInfo>().clone())
+    }
+
+    /// An account, read at the given ledger version or the one of the query
+    async fn account(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        address: String,
+        ledger_version: Option<u64>,
+    ) -> Result<AccountNode, Error> {
+        let context = ctx.data_unchecked::<Arc<Context>>().clone();
+        let ledger_info = ctx.data_unchecked::<LedgerInfo>().clone();
+        let (_, ledger_version, _) = context
+            .state_view::<BasicErrorWith404>(Some(
+                ledger_version.unwrap_or_else(|| ledger_info.version()),
+            ))
+            .map_err(api_error)?;
+        let address = parse("address", &address, &ledger_info)?;
+        Ok(AccountNode {
+            context,
+            ledger_info,
+            ledger_version,
+            address,
+        })
+    }
+
+    /// A table item, read at the given ledger version or the one of the query
+    async fn table_item(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        table_handle: String,
+        key_type: String,
+        value_type: String,
+        key: Json<serde_json::Value>,
+        ledger_version: Option<u64>,
+    ) -> Result<Option<Json<MoveValue>>, Error> {
+        let context = ctx.data_unchecked::<Arc<Context>>();
+        let ledger_info = ctx.data_unchecked::<LedgerInfo>();
+        let (_, ledger_version, state_view) = context
+            .state_view::<BasicErrorWith404>(Some(
+                ledger_version.unwrap_or_else(|| ledger_info.version()),
+            ))
+            .map_err(api_error)?;
+        let table_handle = parse("table_handle", &table_handle, ledger_info)?;
+        let request = TableItemRequest {
+            key_type: parse::<MoveType>("key_type", &key_type, ledger_info)?,
+            value_type: parse::<MoveType>("value_type", &value_type, ledger_info)?,
+            key: key.0,
+        };
+
+        let state_api = StateApi {
+            context: context.clone(),
+        };
+        let item = optional(state_api.table_item_bytes(
+            ledger_info,
+            ledger_version,
+            &state_view,
+            table_handle,
+            request,
+        ))?;
+        item.map(|(value_type, bytes)| {
+            state_api
+                .table_item_to_json(ledger_info, &state_view, &value_type, &bytes)
+                .map(Json)
+                .map_err(api_error)
+        })
+        .transpose()
+    }
+
+    /// A transaction, by version or by hash
+    async fn transaction(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        version: Option<u64>,
+        hash: Option<String>,
+    ) -> Result<Option<Json<Transaction>>, Error> {
+        let context = ctx.data_unchecked::<Arc<Context>>();
+        let ledger_info = ctx.data_unchecked::<LedgerInfo>();
+        let data = match (version, hash) {
+            (Some(version), None) => {
+                if version > ledger_info.version() {
+                    return Ok(None);
+                }
+                Some(
+                    context
+                        .get_transaction_by_version(version, ledger_info.version())
+                        .context(format!("Failed to get transaction by version {}", version))
+                        .map_err(|err| internal_error(err, ledger_info))?,
+                )
+            },
+            (None, Some(hash)) => {
+                let hash: HashValue = parse("hash", &hash, ledger_info)?;
+                context
+                    .get_transaction_by_hash(hash.into(), ledger_info.version())
+                    .context(format!("Failed to get transaction by hash {}", hash))
+                    .map_err(|err| internal_error(err, ledger_info))?
+            },
+            _ => {
+                return Err(api_error(BasicErrorWith404::bad_request_with_code(
+                    "Exactly one of `version` and `hash` must be provided",
+                    AptosErrorCode::InvalidInput,
+                    ledger_info,
+                )))
+            },
+        };
+        Ok(render_transactions(context, ledger_info, data.into_iter().collect())?.pop())
+    }
+
+    /// Committed transactions, starting at the given version
+    #[graphql(
+        complexity = "limit.unwrap_or(DEFAULT_LIST_LIMIT) as usize * child_complexity.max(1)"
+    )]
+    async fn transactions(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        start: Option<u64>,
+        limit: Option<u16>,
+    ) -> Result<Vec<Json<Transaction>>, Error> {
+        let context = ctx.data_unchecked::<Arc<Context>>();
+        let ledger_info = ctx.data_unchecked::<LedgerInfo>();
+        let limit = self::limit(limit, context.max_transactions_page_size(), ledger_info)?;
+        let start =
+            start.unwrap_or_else(|| (ledger_info.version() + 1).saturating_sub(limit as u64));
+        if start > ledger_info.version() {
+            return Ok(vec![]);
+        }
+        let data = context
+            .get_transactions(start, limit, ledger_info.version())
+            .context("Failed to read raw transactions from storage")
+            .map_err(|err| internal_error(err, ledger_info))?;
+        render_transactions(context, ledger_info, data)
+    }
+
+    /// A block, by height or by version of one of its transactions
+    async fn block(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        height: Option<u64>,
+        version: Option<u64>,
+        #[graphql(default)] with_transactions: bool,
+    ) -> Result<Option<Json<Block>>, Error> {
+        let context = ctx.data_unchecked::<Arc<Context>>();
+        let ledger_info = ctx.data_unchecked::<LedgerInfo>();
+        let bcs_block = match (height, version) {
+            (Some(height), None) => {
+                context.get_block_by_height(height, ledger_info, with_transactions)
+            },
+            (None, Some(version)) => {
+                context.get_block_by_version(version, ledger_info, with_transactions)
+            },
+            _ => {
+                return Err(api_error(BasicErrorWith404::bad_request_with_code(
+                    "Exactly one of `height` and `version` must be provided",
+                    AptosErrorCode::InvalidInput,
+                    ledger_info,
+                )))
+            },
+        };
+        let bcs_block = match optional(bcs_block)? {
+            Some(bcs_block) => bcs_block,
+            None => return Ok(None),
+        };
+
+        let transactions = bcs_block
+            .transactions
+            .map(|txns| {
+                context
+                    .render_transactions_sequential::<BasicErrorWith404>(
+                        ledger_info,
+                        txns,
+                        bcs_block.block_timestamp,
+                    )
+                    .map_err(api_error)
+            })
+            .transpose()?;
+        Ok(Some(Json(Block {
+            block_height: bcs_block.block_height.into(),
+            block_hash: bcs_block.block_hash.into(),
+            block_timestamp: bcs_block.block_timestamp.into(),
+            first_version: bcs_block.first_version.into(),
+            last_version: bcs_block.last_version.into(),
+            transactions,
+        })))
+    }
+
+    /// Events of an event handle, identified by its account and creation number
+    #[graphql(
+        complexity = "limit.unwrap_or(DEFAULT_LIST_LIMIT) as usize * child_complexity.max(1)"
+    )]
+    async fn events(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        address: String,
+        creation_number: u64,
+        start: Option<u64>,
+        limit: Option<u16>,
+    ) -> Result<Vec<Json<VersionedEvent>>, Error> {
+        let context = ctx.data_unchecked::<Arc<Context>>();
+        let ledger_info = ctx.data_unchecked::<LedgerInfo>();
+        let address: Address = parse("address", &address, ledger_info)?;
+        list_events(
+            context,
+            ledger_info,
+            EventKey::new(creation_number, address.into()),
+            start,
+            limit,
+        )
+    }
+}
+
+/// An account, with all of its fields read at the same ledger version
+pub struct AccountNode {
+    context: Arc<Context>,
+    ledger_info: LedgerInfo,
+    ledger_version: u64,
+    address: Address,
+}
+
+impl AccountNode {
+    fn state_view(&self) -> Result<DbStateView, Error> {
+        self.context
+            .state_view_at_version(self.ledger_version)
+            .map_err(|err| internal_error(err, &self.ledger_info))
+    }
+
+    fn state_api(&self) -> StateApi {
+        StateApi {
+            context: self.context.clone(),
+        }
+    }
+}
+
+#[Object]
+impl AccountNode {
+    async fn address(&self) -> String {
+        self.address.to_string()
+    }
+
+    async fn ledger_version(&self) -> u64 {
+        self.ledger_version
+    }
+
+    /// A single resource of the account
+    async fn resource(&self, resource_type: String) -> Result<Option<Json<MoveResource>>, Error> {
+        let resource_type: MoveStructTag =
+            parse("resource_type", &resource_type, &self.ledger_info)?;
+        let resource_type: StructTag = resource_type
+            .try_into()
+            .context("Failed to parse given resource type")
+            .map_err(|err| {
+                api_error(BasicErrorWith404::bad_request_with_code(
+                    err,
+                    AptosErrorCode::InvalidInput,
+                    &self.ledger_info,
+                ))
+            })?;
+
+        let state_view = self.state_view()?;
+        let state_api = self.state_api();
+        let bytes = optional(state_api.resource_bytes(
+            &self.ledger_info,
+            self.ledger_version,
+            &state_view,
+            self.address,
+            &resource_type,
+        ))?;
+        bytes
+            .map(|bytes| {
+                state_api
+                    .resource_to_json(&self.ledger_info, &state_view, &resource_type, &bytes)
+                    .map(Json)
+                    .map_err(api_error)
+            })
+            .transpose()
+    }
+
+    /// The first resources of the account
+    #[graphql(
+        complexity = "limit.unwrap_or(DEFAULT_LIST_LIMIT) as usize * child_complexity.max(1)"
+    )]
+    async fn resources(&self, limit: Option<u16>) -> Result<Vec<Json<MoveResource>>, Error> {
+        let limit = self::limit(
+            limit,
+            self.context.max_account_resources_page_size(),
+            &self.ledger_info,
+        )?;
+        let (resources, _) = self
+            .context
+            .get_resources_by_pagination(
+                self.address.into(),
+                None,
+                self.ledger_version,
+                limit as u64,
+            )
+            .context("Failed to get resources from storage")
+            .map_err(|err| internal_error(err, &self.ledger_info))?;
+        let resources = self
+            .state_view()?
+            .as_move_resolver()
+            .as_converter(self.context.db.clone())
+            .try_into_resources(resources.iter().map(|(k, v)| (k.clone(), v.as_slice())))
+            .context("Failed to build move resource response from data in DB")
+            .map_err(|err| internal_error(err, &self.ledger_info))?;
+        Ok(resources.into_iter().map(Json).collect())
+    }
+
+    /// A single module of the account, with its ABI
+    async fn module(&self, name: String) -> Result<Option<Json<MoveModuleBytecode>>, Error> {
+        let name = parse("name", &name, &self.ledger_info)?;
+        let state_key = StateKey::access_path(AccessPath::code_access_path(ModuleId::new(
+            self.address.into(),
+            name,
+        )));
+        let bytes = self
+            .state_view()?
+            .get_state_value_bytes(&state_key)
+            .context(format!("Failed to query DB to check for {:?}", state_key))
+            .map_err(|err| internal_error(err, &self.ledger_info))?;
+        bytes
+            .map(|bytes| {
+                MoveModuleBytecode::new(bytes.to_vec())
+                    .try_parse_abi()
+                    .map(Json)
+                    .context("Failed to parse move module ABI from bytes retrieved from storage")
+                    .map_err(|err| internal_error(err, &self.ledger_info))
+            })
+            .transpose()
+    }
+
+    /// The first modules of the account, with their ABIs
+    #[graphql(
+        complexity = "limit.unwrap_or(DEFAULT_LIST_LIMIT) as usize * child_complexity.max(1)"
+    )]
+    async fn modules(&self, limit: Option<u16>) -> Result<Vec<Json<MoveModuleBytecode>>, Error> {
+        let limit = self::limit(
+            limit,
+            self.context.max_account_modules_page_size(),
+            &self.ledger_info,
+        )?;
+        let (modules, _) = self
+            .context
+            .get_modules_by_pagination(self.address.into(), None, self.ledger_version, limit as u64)
+            .context("Failed to get modules from storage")
+            .map_err(|err| internal_error(err, &self.ledger_info))?;
+        modules
+            .into_iter()
+            .map(|(_, module)| {
+                MoveModuleBytecode::new(module)
+                    .try_parse_abi()
+                    .map(Json)
+                    .context("Failed to parse move module ABI")
+                    .map_err(|err| internal_error(err, &self.ledger_info))
+            })
+            .collect()
+    }
+
+    /// Transactions sent by the account, starting at the given sequence number
+    ///
+    /// If no start is given, the latest transactions are returned.
+    #[graphql(
+        complexity = "limit.unwrap_or(DEFAULT_LIST_LIMIT) as usize * child_complexity.max(1)"
+    )]
+    async fn transactions(
+        &self,
+        start: Option<u64>,
+        limit: Option<u16>,
+    ) -> Result<Vec<Json<Transaction>>, Error> {
+        let limit = self::limit(
+            limit,
+            self.context.max_transactions_page_size(),
+            &self.ledger_info,
+        )?;
+        let data = self
+            .context
+            .get_account_transactions::<BasicErrorWith404>(
+                self.address.into(),
+                start,
+                limit,
+                self.ledger_version,
+                &self.ledger_info,
+            )
+            .map_err(api_error)?;
+        render_transactions(&self.context, &self.ledger_info, data)
+    }
+
+    /// Events of one of the account's event handles
+    #[graphql(
+        complexity = "limit.unwrap_or(DEFAULT_LIST_LIMIT) as usize * child_complexity.max(1)"
+    )]
+    async fn events(
+        &self,
+        creation_number: u64,
+        start: Option<u64>,
+        limit: Option<u16>,
+    ) -> Result<Vec<Json<VersionedEvent>>, Error> {
+        list_events(
+            &self.context,
+            &self.ledger_info,
+            EventKey::new(creation_number, self.address.into()),
+            start,
+            limit,
+        )
+    }
+}
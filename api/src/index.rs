@@ -33,6 +33,7 @@ impl IndexApi {
         self.context
             .check_api_output_enabled("Get ledger info", &accept_type)?;
         let ledger_info = self.context.get_latest_ledger_info()?;
+        let oldest_state_version = self.context.get_oldest_state_version(&ledger_info)?;
 
         let node_role = self.context.node_role();
 
@@ -40,6 +41,7 @@ impl IndexApi {
             AcceptType::Json => {
                 let index_response = IndexResponse::new(
                     ledger_info.clone(),
+                    oldest_state_version,
                     node_role,
                     Some(aptos_build_info::get_git_hash()),
                 );
@@ -7,6 +7,7 @@ use poem_openapi::Tags;
 mod accept_type;
 mod accounts;
 mod basic;
+mod batch;
 mod bcs_payload;
 mod blocks;
 mod check_size;
@@ -14,14 +15,19 @@ pub mod context;
 mod error_converter;
 mod events;
 mod failpoint;
+mod forward_pruned;
+mod graphql;
 mod index;
 mod log;
+mod mempool_introspection;
 pub mod metrics;
 mod page;
 mod response;
 mod runtime;
+mod scratch_state_view;
 mod set_failpoints;
 mod state;
+mod subscriptions;
 #[cfg(test)]
 pub mod tests;
 mod transactions;
@@ -59,3 +65,4 @@ pub enum ApiTags {
 pub use context::Context;
 pub use response::BasicError;
 pub use runtime::{attach_poem_to_runtime, bootstrap, get_api_service};
+pub use subscriptions::spawn_committed_transactions_reader;
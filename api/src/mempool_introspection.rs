@@ -0,0 +1,61 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Operator-facing endpoints exposing the internal state of mempool, to help figure out why a
+//! transaction is stuck. They are not part of the OpenAPI spec and are only mounted if
+//! `mempool_introspection_enabled` is set in the API config, which it isn't by default.
+
+use crate::context::Context;
+use aptos_api_types::Address;
+use aptos_mempool::{
+    AccountMempoolInfo, MempoolClientRequest, ParkedAccount, PeerBroadcastState,
+    RejectedTransaction,
+};
+use aptos_types::account_address::AccountAddress;
+use poem::{
+    handler,
+    web::{Data, Json, Path},
+};
+use std::sync::Arc;
+
+#[handler]
+pub async fn account_transactions(
+    context: Data<&Arc<Context>>,
+    Path(address): Path<Address>,
+) -> poem::Result<Json<AccountMempoolInfo>> {
+    let address: AccountAddress = address.into();
+    let info = context
+        .mempool_introspection(|callback| {
+            MempoolClientRequest::GetAccountTransactions(address, callback)
+        })
+        .await?;
+    Ok(Json(info))
+}
+
+#[handler]
+pub async fn parking_lot(context: Data<&Arc<Context>>) -> poem::Result<Json<Vec<ParkedAccount>>> {
+    let parking_lot = context
+        .mempool_introspection(MempoolClientRequest::GetParkingLot)
+        .await?;
+    Ok(Json(parking_lot))
+}
+
+#[handler]
+pub async fn peer_broadcast_states(
+    context: Data<&Arc<Context>>,
+) -> poem::Result<Json<Vec<PeerBroadcastState>>> {
+    let states = context
+        .mempool_introspection(MempoolClientRequest::GetPeerBroadcastStates)
+        .await?;
+    Ok(Json(states))
+}
+
+#[handler]
+pub async fn rejected_transactions(
+    context: Data<&Arc<Context>>,
+) -> poem::Result<Json<Vec<RejectedTransaction>>> {
+    let rejected = context
+        .mempool_introspection(MempoolClientRequest::GetRejectedTransactions)
+        .await?;
+    Ok(Json(rejected))
+}
@@ -471,6 +471,17 @@ macro_rules! generate_success_response {
                 }
                 self
             }
+
+            pub fn with_version_cursor(mut self, next_version: Option<u64>) -> Self {
+                match self {
+                    $(
+                    [<$enum_name>]::$name(_, _, _, _, _, _, _, _, ref mut cursor) => {
+                        *cursor = next_version.map(|version| version.to_string());
+                    }
+                    )*
+                }
+                self
+            }
         }
         }
     };
@@ -605,9 +616,52 @@ pub fn transaction_not_found_by_hash<E: NotFoundError>(
 }
 
 pub fn version_pruned<E: GoneError>(ledger_version: u64, ledger_info: &LedgerInfo) -> E {
-    E::gone_with_code(
-        format!("Ledger version({}) has been pruned", ledger_version),
-        AptosErrorCode::VersionPruned,
+    E::gone_from_aptos_error(
+        AptosError::new_pruned(
+            format!(
+                "Ledger version({}) has been pruned, oldest available version is {}",
+                ledger_version,
+                ledger_info.oldest_version()
+            ),
+            AptosErrorCode::VersionPruned,
+            ledger_info.oldest_version(),
+        ),
+        ledger_info,
+    )
+}
+
+pub fn state_version_pruned<E: GoneError>(
+    ledger_version: u64,
+    oldest_state_version: u64,
+    ledger_info: &LedgerInfo,
+) -> E {
+    E::gone_from_aptos_error(
+        AptosError::new_pruned(
+            format!(
+                "State at ledger version({}) has been pruned, oldest available version is {}",
+                ledger_version, oldest_state_version
+            ),
+            AptosErrorCode::StateVersionPruned,
+            oldest_state_version,
+        ),
+        ledger_info,
+    )
+}
+
+pub fn version_not_indexed<E: GoneError>(
+    ledger_version: u64,
+    oldest_indexed_version: u64,
+    ledger_info: &LedgerInfo,
+) -> E {
+    E::gone_from_aptos_error(
+        AptosError::new_pruned(
+            format!(
+                "Ledger version({}) is not indexed, oldest indexed version is {}",
+                ledger_version, oldest_indexed_version
+            ),
+            AptosErrorCode::VersionNotIndexed,
+            oldest_indexed_version,
+        ),
         ledger_info,
     )
 }
@@ -722,9 +776,18 @@ pub fn block_not_found_by_version<E: NotFoundError>(
 }
 
 pub fn block_pruned_by_height<E: GoneError>(block_height: u64, ledger_info: &LedgerInfo) -> E {
-    E::gone_with_code(
-        format!("Block({}) has been pruned", block_height),
-        AptosErrorCode::BlockPruned,
+    E::gone_from_aptos_error(
+        AptosError::new_pruned(
+            format!(
+                "Block({}) has been pruned, oldest available block height is {}, oldest available \
+                version is {}",
+                block_height,
+                ledger_info.oldest_block_height,
+                ledger_info.oldest_version()
+            ),
+            AptosErrorCode::BlockPruned,
+            ledger_info.oldest_version(),
+        ),
         ledger_info,
     )
 }
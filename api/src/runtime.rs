@@ -3,14 +3,16 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    accounts::AccountsApi, basic::BasicApi, blocks::BlocksApi, check_size::PostSizeLimit,
-    context::Context, error_converter::convert_error, events::EventsApi, index::IndexApi,
-    log::middleware_log, set_failpoints, state::StateApi, transactions::TransactionsApi,
-    view_function::ViewFunctionApi,
+    accounts::AccountsApi, basic::BasicApi, batch::BatchApi, blocks::BlocksApi,
+    check_size::PostSizeLimit, context::Context, error_converter::convert_error, events::EventsApi,
+    forward_pruned::ForwardPruned, graphql, index::IndexApi, log::middleware_log,
+    mempool_introspection, set_failpoints, state::StateApi, subscriptions,
+    transactions::TransactionsApi, view_function::ViewFunctionApi,
 };
 use anyhow::Context as AnyhowContext;
 use aptos_api_types::X_APTOS_CLIENT;
 use aptos_config::config::{ApiConfig, NodeConfig};
+use aptos_event_notifications::CommitNotificationListener;
 use aptos_logger::info;
 use aptos_mempool::MempoolClientSender;
 use aptos_storage_interface::DbReader;
@@ -33,11 +35,20 @@ pub fn bootstrap(
     chain_id: ChainId,
     db: Arc<dyn DbReader>,
     mp_sender: MempoolClientSender,
+    commit_listener: Option<CommitNotificationListener>,
 ) -> anyhow::Result<Runtime> {
     let max_runtime_workers = get_max_runtime_workers(&config.api);
     let runtime = aptos_runtimes::spawn_named_runtime("api".into(), Some(max_runtime_workers));
 
-    let context = Context::new(chain_id, db, mp_sender, config.clone());
+    let mut context = Context::new(chain_id, db, mp_sender, config.clone());
+    if context.subscriptions_enabled() {
+        let committed_transactions = subscriptions::spawn_committed_transactions_reader(
+            runtime.handle(),
+            context.clone(),
+            commit_listener,
+        );
+        context = context.with_committed_transactions(committed_transactions);
+    }
 
     attach_poem_to_runtime(runtime.handle(), context, config, false)
         .context("Failed to attach poem to runtime")?;
@@ -58,6 +69,7 @@ pub fn get_api_service(
     (
         AccountsApi,
         BasicApi,
+        BatchApi,
         BlocksApi,
         EventsApi,
         IndexApi,
@@ -75,6 +87,9 @@ pub fn get_api_service(
         BasicApi {
             context: context.clone(),
         },
+        BatchApi {
+            context: context.clone(),
+        },
         BlocksApi {
             context: context.clone(),
         },
@@ -124,6 +139,8 @@ pub fn attach_poem_to_runtime(
     let spec_json = api_service.spec_endpoint();
     let spec_yaml = api_service.spec_endpoint_yaml();
 
+    let archival_upstream_url = context.archival_upstream_url();
+
     let mut address = config.api.address;
 
     if random_port {
@@ -177,20 +194,65 @@ pub fn attach_poem_to_runtime(
             ]);
 
         // Build routes for the API
-        let route = Route::new()
-            .nest(
-                "/v1",
+        let mut v1_route = Route::new()
+            .nest("/", api_service)
+            .at("/spec.json", spec_json)
+            .at("/spec.yaml", spec_yaml)
+            // TODO: We add this manually outside of the OpenAPI spec for now.
+            // https://github.com/poem-web/poem/issues/364
+            .at(
+                "/set_failpoint",
+                poem::get(set_failpoints::set_failpoint_poem).data(context.clone()),
+            );
+        if context.graphql_enabled() {
+            v1_route = v1_route.at(
+                "/graphql",
+                poem::get(graphql::graphiql)
+                    .post(graphql::graphql)
+                    .data(context.clone())
+                    .data(graphql::build_schema(context.clone())),
+            );
+        }
+        if context.subscriptions_enabled() {
+            // Streaming endpoints aren't representable in the OpenAPI spec either.
+            v1_route = v1_route.nest(
+                "/subscribe",
                 Route::new()
-                    .nest("/", api_service)
-                    .at("/spec.json", spec_json)
-                    .at("/spec.yaml", spec_yaml)
-                    // TODO: We add this manually outside of the OpenAPI spec for now.
-                    // https://github.com/poem-web/poem/issues/364
                     .at(
-                        "/set_failpoint",
-                        poem::get(set_failpoints::set_failpoint_poem).data(context.clone()),
-                    ),
-            )
+                        "/transactions/by_hash/:txn_hash",
+                        poem::get(subscriptions::transaction_by_hash),
+                    )
+                    .at("/events", poem::get(subscriptions::events))
+                    .at("/blocks", poem::get(subscriptions::blocks))
+                    .data(context.clone()),
+            );
+        }
+        if context.mempool_introspection_enabled() {
+            v1_route = v1_route.nest(
+                "/mempool",
+                Route::new()
+                    .at(
+                        "/accounts/:address",
+                        poem::get(mempool_introspection::account_transactions),
+                    )
+                    .at(
+                        "/parking_lot",
+                        poem::get(mempool_introspection::parking_lot),
+                    )
+                    .at(
+                        "/peers",
+                        poem::get(mempool_introspection::peer_broadcast_states),
+                    )
+                    .at(
+                        "/rejected_transactions",
+                        poem::get(mempool_introspection::rejected_transactions),
+                    )
+                    .data(context.clone()),
+            );
+        }
+        let route = Route::new()
+            .nest("/v1", v1_route)
+            .with(ForwardPruned::new(archival_upstream_url))
             .with(cors)
             .with(PostSizeLimit::new(size_limit))
             // NOTE: Make sure to keep this after all the `with` middleware.
@@ -283,6 +345,7 @@ mod tests {
             ChainId::test(),
             context.db.clone(),
             context.mempool.ac_client.clone(),
+            None,
         );
         assert!(ret.is_ok());
 
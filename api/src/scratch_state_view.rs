@@ -0,0 +1,60 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use aptos_state_view::{StateView, TStateView};
+use aptos_types::{
+    state_store::{
+        state_key::StateKey, state_storage_usage::StateStorageUsage, state_value::StateValue,
+    },
+    write_set::{WriteOp, WriteSet},
+};
+use std::collections::HashMap;
+
+/// A state view that overlays the write sets of simulated transactions on top of committed
+/// state, so that a sequence of transactions can be simulated as if each one was committed
+/// before the next one runs. Nothing is ever written to storage.
+pub struct ScratchStateView<'a, S> {
+    base: &'a S,
+    writes: HashMap<StateKey, Option<StateValue>>,
+}
+
+impl<'a, S: StateView> ScratchStateView<'a, S> {
+    pub fn new(base: &'a S) -> Self {
+        Self {
+            base,
+            writes: HashMap::new(),
+        }
+    }
+
+    /// Applies the write set of a simulated transaction on top of the current state
+    pub fn apply_write_set(&mut self, write_set: &WriteSet) {
+        for (state_key, op) in write_set.iter() {
+            self.writes.insert(state_key.clone(), state_value(op));
+        }
+    }
+}
+
+fn state_value(op: &WriteOp) -> Option<StateValue> {
+    let bytes = op.bytes()?.clone();
+    Some(match op.metadata() {
+        Some(metadata) => StateValue::new_with_metadata(bytes, metadata.clone()),
+        None => StateValue::new_legacy(bytes),
+    })
+}
+
+impl<'a, S: StateView> TStateView for ScratchStateView<'a, S> {
+    type Key = StateKey;
+
+    fn get_state_value(&self, state_key: &StateKey) -> Result<Option<StateValue>> {
+        match self.writes.get(state_key) {
+            Some(value) => Ok(value.clone()),
+            None => self.base.get_state_value(state_key),
+        }
+    }
+
+    fn get_usage(&self) -> Result<StateStorageUsage> {
+        // The usage isn't tracked for the scratch writes, which is fine for simulations.
+        self.base.get_usage()
+    }
+}
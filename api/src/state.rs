@@ -13,18 +13,20 @@ use crate::{
 };
 use anyhow::Context as AnyhowContext;
 use aptos_api_types::{
-    verify_module_identifier, Address, AptosErrorCode, AsConverter, IdentifierWrapper,
+    verify_module_identifier, Address, AptosErrorCode, AsConverter, IdentifierWrapper, LedgerInfo,
     MoveModuleBytecode, MoveResource, MoveStructTag, MoveValue, RawStateValueRequest,
     RawTableItemRequest, TableItemRequest, VerifyInput, VerifyInputWithRecursion, U64,
 };
 use aptos_state_view::TStateView;
+use aptos_storage_interface::state_view::DbStateView;
 use aptos_types::{
     access_path::AccessPath,
     state_store::{state_key::StateKey, table::TableHandle},
 };
 use aptos_vm::data_cache::AsMoveResolver;
+use bytes::Bytes;
 use move_core_types::{
-    language_storage::{ModuleId, StructTag},
+    language_storage::{ModuleId, StructTag, TypeTag},
     resolver::MoveResolver,
 };
 use poem_openapi::{
@@ -274,38 +276,18 @@ impl StateApi {
             })?;
 
         let (ledger_info, ledger_version, state_view) = self.context.state_view(ledger_version)?;
-        let bytes = state_view
-            .as_move_resolver()
-            .get_resource(&address.into(), &resource_type)
-            .context(format!(
-                "Failed to query DB to check for {} at {}",
-                resource_type, address
-            ))
-            .map_err(|err| {
-                BasicErrorWith404::internal_with_code(
-                    err,
-                    AptosErrorCode::InternalError,
-                    &ledger_info,
-                )
-            })?
-            .ok_or_else(|| {
-                resource_not_found(address, &resource_type, ledger_version, &ledger_info)
-            })?;
+        let bytes = self.resource_bytes(
+            &ledger_info,
+            ledger_version,
+            &state_view,
+            address,
+            &resource_type,
+        )?;
 
         match accept_type {
             AcceptType::Json => {
-                let resource = state_view
-                    .as_move_resolver()
-                    .as_converter(self.context.db.clone())
-                    .try_into_resource(&resource_type, &bytes)
-                    .context("Failed to deserialize resource data retrieved from DB")
-                    .map_err(|err| {
-                        BasicErrorWith404::internal_with_code(
-                            err,
-                            AptosErrorCode::InternalError,
-                            &ledger_info,
-                        )
-                    })?;
+                let resource =
+                    self.resource_to_json(&ledger_info, &state_view, &resource_type, &bytes)?;
 
                 BasicResponse::try_from_json((resource, &ledger_info, BasicResponseStatus::Ok))
             },
@@ -317,6 +299,54 @@ impl StateApi {
         }
     }
 
+    /// Read the BCS encoded resource from the given state view
+    pub fn resource_bytes(
+        &self,
+        ledger_info: &LedgerInfo,
+        ledger_version: u64,
+        state_view: &DbStateView,
+        address: Address,
+        resource_type: &StructTag,
+    ) -> Result<Bytes, BasicErrorWith404> {
+        state_view
+            .as_move_resolver()
+            .get_resource(&address.into(), resource_type)
+            .context(format!(
+                "Failed to query DB to check for {} at {}",
+                resource_type, address
+            ))
+            .map_err(|err| {
+                BasicErrorWith404::internal_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    ledger_info,
+                )
+            })?
+            .ok_or_else(|| resource_not_found(address, resource_type, ledger_version, ledger_info))
+    }
+
+    /// Convert the BCS encoded resource to a MoveResource
+    pub fn resource_to_json(
+        &self,
+        ledger_info: &LedgerInfo,
+        state_view: &DbStateView,
+        resource_type: &StructTag,
+        bytes: &[u8],
+    ) -> Result<MoveResource, BasicErrorWith404> {
+        state_view
+            .as_move_resolver()
+            .as_converter(self.context.db.clone())
+            .try_into_resource(resource_type, bytes)
+            .context("Failed to deserialize resource data retrieved from DB")
+            .map_err(|err| {
+                BasicErrorWith404::internal_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    ledger_info,
+                )
+            })
+    }
+
     /// Retrieve the module
     ///
     /// JSON: Parse ABI and bytecode
@@ -379,6 +409,43 @@ impl StateApi {
         table_item_request: TableItemRequest,
         ledger_version: Option<U64>,
     ) -> BasicResultWith404<MoveValue> {
+        // Retrieve local state
+        let (ledger_info, ledger_version, state_view) = self
+            .context
+            .state_view(ledger_version.map(|inner| inner.0))?;
+
+        let (value_type, bytes) = self.table_item_bytes(
+            &ledger_info,
+            ledger_version,
+            &state_view,
+            table_handle,
+            table_item_request,
+        )?;
+
+        match accept_type {
+            AcceptType::Json => {
+                let move_value =
+                    self.table_item_to_json(&ledger_info, &state_view, &value_type, &bytes)?;
+
+                BasicResponse::try_from_json((move_value, &ledger_info, BasicResponseStatus::Ok))
+            },
+            AcceptType::Bcs => BasicResponse::try_from_encoded((
+                bytes.to_vec(),
+                &ledger_info,
+                BasicResponseStatus::Ok,
+            )),
+        }
+    }
+
+    /// Read the BCS encoded table item from the given state view, along with its value type
+    pub fn table_item_bytes(
+        &self,
+        ledger_info: &LedgerInfo,
+        ledger_version: u64,
+        state_view: &DbStateView,
+        table_handle: Address,
+        table_item_request: TableItemRequest,
+    ) -> Result<(TypeTag, Bytes), BasicErrorWith404> {
         // Parse the key and value types for the table
         let key_type = table_item_request
             .key_type
@@ -396,11 +463,6 @@ impl StateApi {
                 BasicErrorWith404::bad_request_with_code_no_info(err, AptosErrorCode::InvalidInput)
             })?;
 
-        // Retrieve local state
-        let (ledger_info, ledger_version, state_view) = self
-            .context
-            .state_view(ledger_version.map(|inner| inner.0))?;
-
         let resolver = state_view.as_move_resolver();
         let converter = resolver.as_converter(self.context.db.clone());
 
@@ -411,14 +473,14 @@ impl StateApi {
                 BasicErrorWith404::bad_request_with_code(
                     err,
                     AptosErrorCode::InvalidInput,
-                    &ledger_info,
+                    ledger_info,
                 )
             })?;
         let raw_key = vm_key.undecorate().simple_serialize().ok_or_else(|| {
             BasicErrorWith404::bad_request_with_code(
                 "Failed to serialize table key",
                 AptosErrorCode::InvalidInput,
-                &ledger_info,
+                ledger_info,
             )
         })?;
 
@@ -434,34 +496,34 @@ impl StateApi {
                 BasicErrorWith404::internal_with_code(
                     err,
                     AptosErrorCode::InternalError,
-                    &ledger_info,
+                    ledger_info,
                 )
             })?
-            .ok_or_else(|| {
-                table_item_not_found(table_handle, &key, ledger_version, &ledger_info)
-            })?;
+            .ok_or_else(|| table_item_not_found(table_handle, &key, ledger_version, ledger_info))?;
 
-        match accept_type {
-            AcceptType::Json => {
-                let move_value = converter
-                    .try_into_move_value(&value_type, &bytes)
-                    .context("Failed to deserialize table item retrieved from DB")
-                    .map_err(|err| {
-                        BasicErrorWith404::internal_with_code(
-                            err,
-                            AptosErrorCode::InternalError,
-                            &ledger_info,
-                        )
-                    })?;
+        Ok((value_type, bytes))
+    }
 
-                BasicResponse::try_from_json((move_value, &ledger_info, BasicResponseStatus::Ok))
-            },
-            AcceptType::Bcs => BasicResponse::try_from_encoded((
-                bytes.to_vec(),
-                &ledger_info,
-                BasicResponseStatus::Ok,
-            )),
-        }
+    /// Convert the BCS encoded table item to a MoveValue
+    pub fn table_item_to_json(
+        &self,
+        ledger_info: &LedgerInfo,
+        state_view: &DbStateView,
+        value_type: &TypeTag,
+        bytes: &[u8],
+    ) -> Result<MoveValue, BasicErrorWith404> {
+        state_view
+            .as_move_resolver()
+            .as_converter(self.context.db.clone())
+            .try_into_move_value(value_type, bytes)
+            .context("Failed to deserialize table item retrieved from DB")
+            .map_err(|err| {
+                BasicErrorWith404::internal_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    ledger_info,
+                )
+            })
     }
 
     /// Retrieve table item for a specific ledger version
@@ -0,0 +1,439 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Server-sent event (SSE) streams of newly committed data, so that clients don't have to poll
+//! for transactions, events and blocks. A single reader, woken up by the commit notifications
+//! emitted by state sync, reads the newly committed transactions from storage and broadcasts
+//! them to all the open streams.
+//!
+//! These endpoints are added manually outside of the OpenAPI spec, since it has no notion of
+//! streaming responses. They are only served if `api.subscriptions_enabled` is set, and at most
+//! `api.max_subscriptions` streams can be open at the same time.
+
+use crate::context::Context;
+use anyhow::{bail, format_err, Result};
+use aptos_api_types::{Address, AsConverter, HashValue, TransactionOnChainData, U64};
+use aptos_event_notifications::CommitNotificationListener;
+use aptos_logger::warn;
+use aptos_storage_interface::state_view::LatestDbStateCheckpointView;
+use aptos_types::{contract_event::EventWithVersion, event::EventKey, transaction::Version};
+use aptos_vm::data_cache::AsMoveResolver;
+use futures::{stream, Stream, StreamExt};
+use move_core_types::{language_storage::TypeTag, parser::parse_type_tag};
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{
+        sse::{Event, SSE},
+        Data, Path, Query,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    runtime::Handle,
+    sync::{broadcast, broadcast::error::RecvError, watch, OwnedSemaphorePermit},
+};
+
+/// Maximum number of transactions read from storage at once.
+const MAX_TRANSACTIONS_PER_READ: u16 = 100;
+/// Number of batches of transactions buffered for each stream. Streams that fall further behind
+/// are closed.
+const MAX_BUFFERED_BATCHES: usize = 100;
+/// How often storage is checked for new transactions if there are no commit notifications.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+type Batch = Arc<Vec<TransactionOnChainData>>;
+
+/// Spawns the reader of newly committed transactions, and returns the channel it broadcasts
+/// them on. The reader is woken up by the commit notifications from state sync, or polls storage
+/// if there are none. Storage is only read while there are open streams.
+pub fn spawn_committed_transactions_reader(
+    runtime_handle: &Handle,
+    context: Context,
+    commit_listener: Option<CommitNotificationListener>,
+) -> broadcast::Sender<Batch> {
+    let (sender, _) = broadcast::channel(MAX_BUFFERED_BATCHES);
+    let mut commits = commit_listener.map(|commit_listener| {
+        spawn_commit_notification_forwarder(runtime_handle, commit_listener)
+    });
+    let reader_sender = sender.clone();
+    runtime_handle.spawn(async move {
+        let mut next_version = None;
+        loop {
+            if let Err(e) = send_committed_transactions(&context, &mut next_version, &reader_sender)
+            {
+                warn!(
+                    "Failed to read committed transactions for subscriptions: {:?}",
+                    e
+                );
+            }
+            let notified = match commits.as_mut() {
+                Some(commits) => commits.changed().await.is_ok(),
+                None => false,
+            };
+            if !notified {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    });
+    sender
+}
+
+/// Forwards the commit notifications from state sync into a watch channel, so that the reader
+/// only wakes up once for a burst of commits.
+fn spawn_commit_notification_forwarder(
+    runtime_handle: &Handle,
+    mut commit_listener: CommitNotificationListener,
+) -> watch::Receiver<Version> {
+    let (sender, receiver) = watch::channel(0);
+    runtime_handle.spawn(async move {
+        while let Some(notification) = commit_listener.next().await {
+            sender.send_replace(notification.version);
+        }
+    });
+    receiver
+}
+
+/// Broadcasts all transactions committed from `next_version` on, in batches.
+fn send_committed_transactions(
+    context: &Context,
+    next_version: &mut Option<Version>,
+    sender: &broadcast::Sender<Batch>,
+) -> Result<()> {
+    let ledger_version = context.db.get_latest_version()?;
+    let next_version = next_version.get_or_insert(ledger_version + 1);
+    if sender.receiver_count() == 0 {
+        // Nobody is listening, and streams opened later start after the current ledger version.
+        *next_version = ledger_version + 1;
+        return Ok(());
+    }
+    while *next_version <= ledger_version {
+        let txns =
+            context.get_transactions(*next_version, MAX_TRANSACTIONS_PER_READ, ledger_version)?;
+        if txns.is_empty() {
+            break;
+        }
+        *next_version += txns.len() as u64;
+        // Fails only if all the streams were closed in the meantime.
+        let _ = sender.send(Arc::new(txns));
+    }
+    Ok(())
+}
+
+/// The transactions committed from `next_version` on, as received from the reader.
+struct CommittedTransactions {
+    receiver: broadcast::Receiver<Batch>,
+    next_version: Version,
+    /// Holds one of the open subscription slots for as long as the stream is open.
+    _permit: OwnedSemaphorePermit,
+}
+
+impl CommittedTransactions {
+    /// Returns the next (non-empty) batch of committed transactions.
+    async fn next_batch(&mut self) -> Result<Vec<TransactionOnChainData>> {
+        loop {
+            let batch = match self.receiver.recv().await {
+                Ok(batch) => batch,
+                Err(RecvError::Lagged(skipped)) => bail!(
+                    "Subscription fell behind, {} batches of transactions were dropped",
+                    skipped
+                ),
+                Err(RecvError::Closed) => bail!("Committed transactions are no longer read"),
+            };
+            // The first batches received may overlap with what was committed before the stream
+            // was opened.
+            let txns: Vec<_> = batch
+                .iter()
+                .filter(|txn| txn.version >= self.next_version)
+                .cloned()
+                .collect();
+            if let Some(last) = txns.last() {
+                self.next_version = last.version + 1;
+                return Ok(txns);
+            }
+        }
+    }
+}
+
+/// The state every subscription starts with: a slot among the open subscriptions, the receiver
+/// of committed transactions and the first version to stream.
+struct Subscription {
+    permit: OwnedSemaphorePermit,
+    receiver: broadcast::Receiver<Batch>,
+    start_version: Version,
+}
+
+impl Subscription {
+    fn new(context: &Context, permit: OwnedSemaphorePermit) -> Result<Self> {
+        // Subscribe before reading the ledger version, so that no transactions are missed.
+        let receiver = context
+            .subscribe_to_committed_transactions()
+            .ok_or_else(|| format_err!("Committed transactions are not read"))?;
+        Ok(Self {
+            permit,
+            receiver,
+            start_version: context.db.get_latest_version()? + 1,
+        })
+    }
+}
+
+/// Reserves a slot for a new subscription, failing with 503 if there are too many open already.
+fn acquire_permit(context: &Context) -> poem::Result<OwnedSemaphorePermit> {
+    context.try_acquire_subscription_permit().ok_or_else(|| {
+        poem::Error::from_string(
+            "Too many open subscriptions, try again later",
+            StatusCode::SERVICE_UNAVAILABLE,
+        )
+    })
+}
+
+/// Turns the stream of committed transactions into a stream of SSE events.
+/// `to_events` renders the events to send for each batch of transactions, and whether the
+/// subscription is fulfilled (which ends the stream). The stream also ends on any error, after
+/// sending it as an `error` event.
+fn committed_data_stream<F>(
+    context: Arc<Context>,
+    subscription: Subscription,
+    to_events: F,
+) -> impl Stream<Item = Event> + Send + 'static
+where
+    F: FnMut(&Context, Vec<TransactionOnChainData>) -> Result<(Vec<Event>, bool)> + Send + 'static,
+{
+    let committed = CommittedTransactions {
+        receiver: subscription.receiver,
+        next_version: subscription.start_version,
+        _permit: subscription.permit,
+    };
+    stream::unfold(Some((committed, context, to_events)), |state| async move {
+        let (mut committed, context, mut to_events) = state?;
+        let result = match committed.next_batch().await {
+            Ok(txns) => to_events(&context, txns),
+            Err(e) => Err(e),
+        };
+        Some(match result {
+            Ok((sse_events, false)) => (sse_events, Some((committed, context, to_events))),
+            Ok((sse_events, true)) => (sse_events, None),
+            Err(e) => (
+                vec![Event::message(e.to_string()).event_type("error")],
+                None,
+            ),
+        })
+    })
+    .flat_map(stream::iter)
+}
+
+/// Renders the transactions in the JSON format of the REST API.
+fn to_json_events(
+    context: &Context,
+    txns: Vec<TransactionOnChainData>,
+    event_type: &str,
+) -> Result<Vec<Event>> {
+    if txns.is_empty() {
+        return Ok(vec![]);
+    }
+    let state_view = context.db.latest_state_checkpoint_view()?;
+    let resolver = state_view.as_move_resolver();
+    let converter = resolver.as_converter(context.db.clone());
+    txns.into_iter()
+        .map(|txn| {
+            let version = txn.version;
+            let timestamp = context.db.get_block_timestamp(version)?;
+            let txn = converter.try_into_onchain_transaction(timestamp, txn)?;
+            Ok(Event::message(serde_json::to_string(&txn)?)
+                .event_type(event_type)
+                .id(version.to_string()))
+        })
+        .collect()
+}
+
+fn error_stream(error: anyhow::Error) -> SSE {
+    SSE::new(stream::once(async move {
+        Event::message(error.to_string()).event_type("error")
+    }))
+}
+
+fn committed_transaction_stream(context: &Context, txn: TransactionOnChainData) -> SSE {
+    match to_json_events(context, vec![txn], "transaction") {
+        Ok(sse_events) => SSE::new(stream::iter(sse_events)),
+        Err(e) => error_stream(e),
+    }
+}
+
+/// Streams the transaction with the given hash once it's committed, then ends. If the
+/// transaction expires instead, an `expired` event is sent before ending.
+#[handler]
+pub async fn transaction_by_hash(
+    context: Data<&Arc<Context>>,
+    Path(hash): Path<HashValue>,
+) -> poem::Result<SSE> {
+    let context = context.clone();
+    let hash = hash.into();
+    let subscription = match Subscription::new(&context, acquire_permit(&context)?) {
+        Ok(subscription) => subscription,
+        Err(e) => return Ok(error_stream(e)),
+    };
+    match context.get_transaction_by_hash(hash, subscription.start_version - 1) {
+        Ok(Some(txn)) => return Ok(committed_transaction_stream(&context, txn)),
+        Ok(None) => {},
+        Err(e) => return Ok(error_stream(e)),
+    }
+
+    // Only the transactions in mempool can still be committed, and only until they expire.
+    let expiration_timestamp_usecs = match context.get_pending_transaction_by_hash(hash).await {
+        Ok(Some(txn)) => txn.expiration_timestamp_secs().saturating_mul(1_000_000),
+        Ok(None) => {
+            // The transaction may have been committed, and removed from mempool, in the meantime.
+            let committed = context
+                .db
+                .get_latest_version()
+                .and_then(|ledger_version| context.get_transaction_by_hash(hash, ledger_version));
+            return Ok(match committed {
+                Ok(Some(txn)) => committed_transaction_stream(&context, txn),
+                Ok(None) => error_stream(format_err!(
+                    "Transaction {} is neither committed nor pending",
+                    hash
+                )),
+                Err(e) => error_stream(e),
+            });
+        },
+        Err(e) => return Ok(error_stream(e)),
+    };
+
+    let stream = committed_data_stream(context, subscription, move |context, txns| {
+        let last_version = match txns.last() {
+            Some(txn) => txn.version,
+            None => return Ok((vec![], false)),
+        };
+        let found: Vec<_> = txns
+            .into_iter()
+            .filter(|txn| txn.info.transaction_hash() == hash)
+            .collect();
+        if !found.is_empty() {
+            return Ok((to_json_events(context, found, "transaction")?, true));
+        }
+
+        // Transactions are rejected once the block timestamp reaches their expiration, and block
+        // timestamps only increase, so the transaction can't be committed anymore.
+        if context.db.get_block_timestamp(last_version)? >= expiration_timestamp_usecs {
+            let expired = Event::message(hash.to_string()).event_type("expired");
+            return Ok((vec![expired], true));
+        }
+        Ok((vec![], false))
+    });
+    Ok(SSE::new(stream).keep_alive(KEEP_ALIVE_INTERVAL))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EventsQuery {
+    /// Address of the account holding the event handle
+    address: Option<Address>,
+    /// Creation number of the event handle
+    creation_number: Option<U64>,
+    /// Move type of the events, e.g. `0x1::coin::DepositEvent`
+    event_type: Option<String>,
+}
+
+enum EventFilter {
+    Key(EventKey),
+    Type(TypeTag),
+}
+
+impl EventFilter {
+    fn new(query: EventsQuery) -> Result<Self> {
+        Ok(match query {
+            EventsQuery {
+                address: Some(address),
+                creation_number: Some(creation_number),
+                event_type: None,
+            } => Self::Key(EventKey::new(creation_number.0, address.into())),
+            EventsQuery {
+                address: None,
+                creation_number: None,
+                event_type: Some(event_type),
+            } => Self::Type(parse_type_tag(&event_type)?),
+            _ => bail!("Either `address` and `creation_number`, or `event_type` must be provided"),
+        })
+    }
+
+    fn matches(&self, event: &EventWithVersion) -> bool {
+        match self {
+            Self::Key(key) => event.event.event_key() == Some(key),
+            Self::Type(type_tag) => event.event.type_tag() == type_tag,
+        }
+    }
+}
+
+/// Streams newly committed events, either of an event handle or of a given type.
+#[handler]
+pub async fn events(
+    context: Data<&Arc<Context>>,
+    Query(query): Query<EventsQuery>,
+) -> poem::Result<SSE> {
+    let context = context.clone();
+    let filter = match EventFilter::new(query) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(error_stream(e)),
+    };
+    let subscription = match Subscription::new(&context, acquire_permit(&context)?) {
+        Ok(subscription) => subscription,
+        Err(e) => return Ok(error_stream(e)),
+    };
+
+    let stream = committed_data_stream(context, subscription, move |context, txns| {
+        let versioned_events: Vec<_> = txns
+            .into_iter()
+            .flat_map(|txn| {
+                let version = txn.version;
+                txn.events
+                    .into_iter()
+                    .map(move |event| EventWithVersion::new(version, event))
+            })
+            .filter(|event| filter.matches(event))
+            .collect();
+        if versioned_events.is_empty() {
+            return Ok((vec![], false));
+        }
+
+        let state_view = context.db.latest_state_checkpoint_view()?;
+        let resolver = state_view.as_move_resolver();
+        let sse_events = resolver
+            .as_converter(context.db.clone())
+            .try_into_versioned_events(&versioned_events)?
+            .into_iter()
+            .map(|event| {
+                let version = event.version.0;
+                Ok(Event::message(serde_json::to_string(&event)?)
+                    .event_type("event")
+                    .id(version.to_string()))
+            })
+            .collect::<Result<_>>()?;
+        Ok((sse_events, false))
+    });
+    Ok(SSE::new(stream).keep_alive(KEEP_ALIVE_INTERVAL))
+}
+
+/// Streams the block metadata transaction of every newly committed block.
+#[handler]
+pub async fn blocks(context: Data<&Arc<Context>>) -> poem::Result<SSE> {
+    let context = context.clone();
+    let subscription = match Subscription::new(&context, acquire_permit(&context)?) {
+        Ok(subscription) => subscription,
+        Err(e) => return Ok(error_stream(e)),
+    };
+
+    let stream = committed_data_stream(context, subscription, |context, txns| {
+        let blocks = txns
+            .into_iter()
+            .filter(|txn| {
+                matches!(
+                    txn.transaction,
+                    aptos_types::transaction::Transaction::BlockMetadata(_)
+                )
+            })
+            .collect();
+        Ok((to_json_events(context, blocks, "block")?, false))
+    });
+    Ok(SSE::new(stream).keep_alive(KEEP_ALIVE_INTERVAL))
+}
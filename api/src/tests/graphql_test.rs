@@ -0,0 +1,82 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use super::new_test_context_with_config;
+use aptos_api_test_context::{current_function_name, TestContext};
+use aptos_config::config::NodeConfig;
+use serde_json::{json, Value};
+
+fn new_graphql_test_context(test_name: String) -> TestContext {
+    let mut node_config = NodeConfig::default();
+    node_config.api.graphql_enabled = true;
+    new_test_context_with_config(test_name, node_config)
+}
+
+async fn post_query(context: &TestContext, query: &str) -> Value {
+    context.post("/graphql", json!({ "query": query })).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_graphql_account_query() {
+    let mut context = new_graphql_test_context(current_function_name!());
+    let account = &mut context.gen_account();
+    let txn = context.mint_user_account(account).await;
+    context.commit_block(&vec![txn]).await;
+
+    let query = format!(
+        r#"{{
+            account(address: "{}") {{
+                resource(resourceType: "0x1::account::Account")
+                missing: resource(resourceType: "0x1::account::Missing")
+                transactions(limit: 5)
+            }}
+        }}"#,
+        account.address().to_hex_literal()
+    );
+    let response = post_query(&context, &query).await;
+    assert_eq!(response["errors"], Value::Null, "{}", response);
+
+    let data = &response["data"];
+    assert_eq!(
+        data["account"]["resource"]["type"],
+        json!("0x1::account::Account")
+    );
+    assert_eq!(data["account"]["missing"], json!(null));
+    assert_eq!(data["account"]["transactions"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_graphql_query_complexity_limit() {
+    let context = new_graphql_test_context(current_function_name!());
+
+    let max_complexity = context.context.node_config.api.graphql_max_complexity;
+    let response = post_query(
+        &context,
+        &format!("{{ transactions(limit: {}) }}", max_complexity + 1),
+    )
+    .await;
+    let errors = response["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0]["message"].as_str().unwrap().contains("complex"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_graphql_query_resolved_at_one_ledger_version() {
+    let mut context = new_graphql_test_context(current_function_name!());
+    let account = &mut context.gen_account();
+    let txn = context.mint_user_account(account).await;
+    context.commit_block(&vec![txn]).await;
+
+    let query = r#"{
+        ledgerInfo
+        account(address: "0x1") { ledgerVersion }
+    }"#;
+    let response = post_query(&context, query).await;
+    assert_eq!(response["errors"], Value::Null, "{}", response);
+
+    let data = &response["data"];
+    assert_eq!(
+        data["ledgerInfo"]["ledger_version"],
+        json!(data["account"]["ledgerVersion"].to_string())
+    );
+}
@@ -13,6 +13,24 @@ async fn test_get_index() {
     context.check_golden_output(resp);
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_index_oldest_versions() {
+    let mut context = new_test_context(current_function_name!());
+    let account = context.gen_account();
+    let txn = context.create_user_account(&account).await;
+    context.commit_block(&vec![txn]).await;
+
+    let resp = context.get("/").await;
+    let version = |field: &str| -> u64 { resp[field].as_str().unwrap().parse().unwrap() };
+    let ledger_version = version("ledger_version");
+    let oldest_ledger_version = version("oldest_ledger_version");
+    // Nothing is pruned in tests, so every type of data is available from genesis
+    assert_eq!(oldest_ledger_version, 0);
+    assert_eq!(version("oldest_state_version"), oldest_ledger_version);
+    assert_eq!(version("oldest_event_version"), oldest_ledger_version);
+    assert!(ledger_version > oldest_ledger_version);
+}
+
 // TODO: Un-ignore this pending https://github.com/poem-web/poem/issues/343.
 #[ignore]
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
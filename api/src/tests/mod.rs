@@ -3,9 +3,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 mod accounts_test;
+mod batch_test;
 mod blocks_test;
 mod converter_test;
 mod events_test;
+mod graphql_test;
 mod index_test;
 mod invalid_post_request_test;
 mod modules;
@@ -15,6 +17,7 @@ mod resource_groups;
 mod secp256k1_ecdsa;
 mod state_test;
 mod string_resource_test;
+mod subscriptions_test;
 mod transaction_vector_test;
 mod transactions_test;
 mod view_function;
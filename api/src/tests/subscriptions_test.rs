@@ -0,0 +1,209 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{new_test_context, new_test_context_with_config};
+use aptos_api_test_context::{current_function_name, ApiSpecificConfig, TestContext};
+use aptos_config::config::NodeConfig;
+use aptos_crypto::HashValue;
+use serde_json::Value;
+use std::{net::SocketAddr, time::Duration};
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Starts a test context with the subscriptions enabled, and returns it along with the address
+/// of its API server.
+fn new_subscriptions_test_context(test_name: String) -> (TestContext, SocketAddr) {
+    let mut node_config = NodeConfig::default();
+    node_config.api.subscriptions_enabled = true;
+    let context = new_test_context_with_config(test_name, node_config);
+    let address = api_address(&context);
+    (context, address)
+}
+
+fn api_address(context: &TestContext) -> SocketAddr {
+    match context.api_specific_config {
+        ApiSpecificConfig::V1(address) => address,
+    }
+}
+
+/// A server-sent event stream, as read by a client.
+struct EventStream {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl EventStream {
+    async fn open(address: SocketAddr, path: &str) -> Self {
+        let response = reqwest::get(format!("http://{}/v1{}", address, path))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        Self {
+            response,
+            buffer: String::new(),
+        }
+    }
+
+    /// Returns the type and data of the next event, skipping keep-alive comments.
+    async fn next(&mut self) -> (String, String) {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let raw_event: String = self.buffer.drain(..end + 2).collect();
+                let mut event_type = String::from("message");
+                let mut data = None;
+                for line in raw_event.lines() {
+                    if let Some(value) = line.strip_prefix("event: ") {
+                        event_type = value.to_string();
+                    } else if let Some(value) = line.strip_prefix("data: ") {
+                        data = Some(value.to_string());
+                    }
+                }
+                if let Some(data) = data {
+                    return (event_type, data);
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(EVENT_TIMEOUT, self.response.chunk())
+                .await
+                .expect("timed out waiting for an event")
+                .unwrap()
+                .expect("stream ended before the next event");
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    async fn next_json(&mut self, expected_type: &str) -> Value {
+        let (event_type, data) = self.next().await;
+        assert_eq!(event_type, expected_type, "data: {}", data);
+        serde_json::from_str(&data).unwrap()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_subscribe_to_committed_transaction_by_hash() {
+    let (mut context, address) = new_subscriptions_test_context(current_function_name!());
+    let account = context.gen_account();
+    let txn = context.create_user_account(&account).await;
+    let hash = txn.clone().committed_hash().to_hex_literal();
+    context.commit_block(&vec![txn]).await;
+
+    let mut stream = EventStream::open(
+        address,
+        &format!("/subscribe/transactions/by_hash/{}", hash),
+    )
+    .await;
+    let txn = stream.next_json("transaction").await;
+    assert_eq!(txn["hash"], hash);
+    assert_eq!(txn["success"], true);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_subscribe_to_pending_transaction_by_hash() {
+    let (mut context, address) = new_subscriptions_test_context(current_function_name!());
+    let account = context.gen_account();
+    let txn = context.create_user_account(&account).await;
+    let hash = txn.clone().committed_hash().to_hex_literal();
+    context
+        .expect_status_code(202)
+        .post_bcs_txn("/transactions", bcs::to_bytes(&txn).unwrap())
+        .await;
+
+    let mut stream = EventStream::open(
+        address,
+        &format!("/subscribe/transactions/by_hash/{}", hash),
+    )
+    .await;
+    context.commit_block(&vec![txn]).await;
+    let txn = stream.next_json("transaction").await;
+    assert_eq!(txn["hash"], hash);
+    assert_eq!(
+        txn["version"],
+        context.get_latest_ledger_info().version().to_string()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_subscribe_to_unknown_transaction_by_hash() {
+    let (_context, address) = new_subscriptions_test_context(current_function_name!());
+    let hash = HashValue::zero().to_hex_literal();
+
+    let mut stream = EventStream::open(
+        address,
+        &format!("/subscribe/transactions/by_hash/{}", hash),
+    )
+    .await;
+    let (event_type, data) = stream.next().await;
+    assert_eq!(event_type, "error");
+    assert!(data.contains("neither committed nor pending"), "{}", data);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_subscribe_to_events() {
+    let (mut context, address) = new_subscriptions_test_context(current_function_name!());
+    let account = context.gen_account();
+    let mut stream = EventStream::open(
+        address,
+        "/subscribe/events?event_type=0x1::coin::DepositEvent",
+    )
+    .await;
+
+    let create_txn = context.create_user_account(&account).await;
+    let mint_txn = context.mint_user_account(&account).await;
+    context.commit_block(&vec![create_txn, mint_txn]).await;
+
+    let event = stream.next_json("event").await;
+    assert_eq!(event["type"], "0x1::coin::DepositEvent");
+    assert_eq!(
+        event["guid"]["account_address"],
+        account.address().to_hex_literal()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_subscribe_to_events_without_filter() {
+    let (_context, address) = new_subscriptions_test_context(current_function_name!());
+
+    let mut stream = EventStream::open(address, "/subscribe/events").await;
+    let (event_type, data) = stream.next().await;
+    assert_eq!(event_type, "error");
+    assert!(data.contains("must be provided"), "{}", data);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_subscribe_to_blocks() {
+    let (mut context, address) = new_subscriptions_test_context(current_function_name!());
+    let mut stream = EventStream::open(address, "/subscribe/blocks").await;
+
+    let account = context.gen_account();
+    let txn = context.create_user_account(&account).await;
+    context.commit_block(&vec![txn]).await;
+
+    let block = stream.next_json("block").await;
+    assert_eq!(block["type"], "block_metadata_transaction");
+    let first_version: u64 = block["version"].as_str().unwrap().parse().unwrap();
+
+    context.commit_block(&[]).await;
+    let block = stream.next_json("block").await;
+    let second_version: u64 = block["version"].as_str().unwrap().parse().unwrap();
+    assert!(second_version > first_version);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_subscriptions_disabled() {
+    let context = new_test_context(current_function_name!());
+    let address = api_address(&context);
+
+    for path in [
+        format!(
+            "/subscribe/transactions/by_hash/{}",
+            HashValue::zero().to_hex_literal()
+        ),
+        "/subscribe/events?event_type=0x1::coin::DepositEvent".to_string(),
+        "/subscribe/blocks".to_string(),
+    ] {
+        let response = reqwest::get(format!("http://{}/v1{}", address, path))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404, "{}", path);
+    }
+}
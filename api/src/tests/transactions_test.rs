@@ -1306,3 +1306,320 @@ fn gen_string(len: u64) -> String {
 fn build_path(path: &str) -> String {
     format!("/v1/transactions{}", path)
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_account_transaction_history() {
+    let mut context = aptos_api_test_context::new_test_context(
+        current_function_name!(),
+        NodeConfig::default(),
+        true, /* use_db_with_indexer */
+    );
+    let creator = &mut context.gen_account();
+    let owner = &mut context.gen_account();
+    let txn1 = context.mint_user_account(creator).await;
+    let txn2 = context.account_transfer(creator, owner, 100_000);
+    context.commit_block(&vec![txn1, txn2]).await;
+
+    // The owner never sent a transaction, but received coins.
+    let history = context
+        .get(&format!(
+            "/accounts/{}/transaction_history",
+            owner.address()
+        ))
+        .await;
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(
+        history[0]["sender"],
+        json!(creator.address().to_hex_literal())
+    );
+
+    let version: u64 = history[0]["version"].as_str().unwrap().parse().unwrap();
+    let history = context
+        .get(&format!(
+            "/accounts/{}/transaction_history?start_version={}",
+            owner.address(),
+            version + 1
+        ))
+        .await;
+    assert_eq!(history, json!([]));
+
+    // A full page returns the version to continue from.
+    let req = warp::test::request().method("GET").path(&format!(
+        "/v1/accounts/{}/transaction_history?limit=1",
+        creator.address()
+    ));
+    let resp = context.reply(req).await;
+    assert_eq!(resp.status(), 200);
+    let page: Vec<serde_json::Value> = serde_json::from_slice(resp.body()).unwrap();
+    let version: u64 = page[0]["version"].as_str().unwrap().parse().unwrap();
+    let cursor = resp
+        .headers()
+        .get("X-Aptos-Cursor")
+        .expect("Cursor header was missing");
+    assert_eq!(cursor.to_str().unwrap(), (version + 1).to_string());
+
+    let history = context
+        .get(&format!(
+            "/accounts/{}/transaction_history?event_type=0x1::coin::DepositEvent",
+            owner.address()
+        ))
+        .await;
+    assert_eq!(history.as_array().unwrap().len(), 1);
+    let history = context
+        .get(&format!(
+            "/accounts/{}/transaction_history?event_type=0x1::block::NewBlockEvent",
+            owner.address()
+        ))
+        .await;
+    assert_eq!(history, json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_account_transaction_history_not_indexed() {
+    // Versions 1 and 2 are the block metadata and the minting, which aren't indexed.
+    let mut context = aptos_api_test_context::new_test_context_with_indexer_from(
+        current_function_name!(),
+        NodeConfig::default(),
+        3,
+    );
+    let creator = &mut context.gen_account();
+    let owner = &mut context.gen_account();
+    let txn1 = context.mint_user_account(creator).await;
+    let txn2 = context.account_transfer(creator, owner, 100_000);
+    context.commit_block(&vec![txn1, txn2]).await;
+
+    // By default, the history starts from the first indexed version.
+    let history = context
+        .get(&format!(
+            "/accounts/{}/transaction_history",
+            creator.address()
+        ))
+        .await;
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["version"], json!("3"));
+
+    let resp = context
+        .expect_status_code(410)
+        .get(&format!(
+            "/accounts/{}/transaction_history?start_version=0",
+            creator.address()
+        ))
+        .await;
+    assert_eq!(resp["error_code"], json!("version_not_indexed"));
+    assert_eq!(resp["oldest_available_version"], json!("3"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_account_transaction_history_pruned() {
+    let mut context = aptos_api_test_context::new_test_context_with_ledger_prune_window(
+        current_function_name!(),
+        NodeConfig::default(),
+        2,
+    );
+    let creator = &mut context.gen_account();
+    let owner = &mut context.gen_account();
+    // Versions 1 to 3, then 4 to 6, so only the second block is left in the window.
+    let txn = context.mint_user_account(creator).await;
+    context.commit_block(&vec![txn]).await;
+    let txn = context.account_transfer(creator, owner, 100_000);
+    context.commit_block(&vec![txn]).await;
+
+    // By default, the history starts from the oldest version that isn't pruned.
+    let history = context
+        .get(&format!(
+            "/accounts/{}/transaction_history",
+            creator.address()
+        ))
+        .await;
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["version"], json!("5"));
+
+    let resp = context
+        .expect_status_code(410)
+        .get(&format!(
+            "/accounts/{}/transaction_history?start_version=2",
+            creator.address()
+        ))
+        .await;
+    assert_eq!(resp["error_code"], json!("version_pruned"));
+    assert_eq!(resp["oldest_available_version"], json!("4"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_account_transaction_history_without_indexer() {
+    let context = new_test_context(current_function_name!());
+    context
+        .expect_status_code(403)
+        .get(&format!(
+            "/accounts/{}/transaction_history",
+            context.root_account().await.address()
+        ))
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_simulate_transaction_preview() {
+    let mut context = new_test_context(current_function_name!());
+    let sender = &mut context.create_account().await;
+    let receiver = context.gen_account();
+
+    let preview = context
+        .simulate_transaction_preview(
+            sender,
+            json!({
+                "type": "entry_function_payload",
+                "function": "0x1::aptos_account::transfer",
+                "type_arguments": [],
+                "arguments": [receiver.address().to_hex_literal(), "1000"]
+            }),
+            200,
+        )
+        .await;
+    assert!(preview["transaction"]["success"].as_bool().unwrap());
+
+    let coin_store = "0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>";
+    let sender_coin_store = preview["state_diff"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|diff| {
+            diff["type"] == json!("resource")
+                && diff["address"] == json!(sender.address().to_hex_literal())
+                && diff["resource_type"] == json!(coin_store)
+        })
+        .unwrap();
+    let balance = |resource: &serde_json::Value| {
+        resource["data"]["coin"]["value"]
+            .as_str()
+            .unwrap()
+            .parse::<u64>()
+            .unwrap()
+    };
+    assert_eq!(
+        balance(&sender_coin_store["before"]) - balance(&sender_coin_store["after"]),
+        1000
+    );
+
+    // The receiver doesn't exist yet, so its account is created
+    assert!(preview["state_diff"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|diff| {
+            diff["address"] == json!(receiver.address().to_hex_literal())
+                && diff["resource_type"] == json!("0x1::account::Account")
+                && diff["before"].is_null()
+                && !diff["after"].is_null()
+        }));
+    assert!(preview["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|event| event["type"] == json!("0x1::coin::WithdrawEvent")));
+
+    let gas_breakdown = &preview["gas_breakdown"];
+    assert!(
+        gas_breakdown["execution_and_io"]
+            .as_str()
+            .unwrap()
+            .parse::<u64>()
+            .unwrap()
+            > 0
+    );
+    assert!(!gas_breakdown["execution"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_simulate_transactions_bundle() {
+    let mut context = new_test_context(current_function_name!());
+    let sender = &mut context.create_account().await;
+    let receiver = &mut context.gen_account();
+
+    // The receiver only exists once the first transaction ran, and then spends what it got
+    let create_receiver = context
+        .simulation_request(
+            sender,
+            json!({
+                "type": "entry_function_payload",
+                "function": "0x1::aptos_account::transfer",
+                "type_arguments": [],
+                "arguments": [receiver.address().to_hex_literal(), "1000"]
+            }),
+        )
+        .await;
+    let spend = context
+        .simulation_request(
+            receiver,
+            json!({
+                "type": "entry_function_payload",
+                "function": "0x1::aptos_account::transfer",
+                "type_arguments": [],
+                "arguments": [sender.address().to_hex_literal(), "400"]
+            }),
+        )
+        .await;
+
+    let resp = context
+        .post(
+            "/transactions/simulate/bundle",
+            json!([create_receiver, spend]),
+        )
+        .await;
+    let transactions = resp["transactions"].as_array().unwrap();
+    assert_eq!(transactions.len(), 2);
+    assert!(transactions[0]["success"].as_bool().unwrap());
+    assert!(transactions[1]["success"].as_bool().unwrap());
+
+    let receiver_coin_store = resp["write_set"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|change| {
+            change["type"] == json!("write_resource")
+                && change["address"] == json!(receiver.address().to_hex_literal())
+                && change["data"]["type"]
+                    == json!("0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>")
+        })
+        .unwrap();
+    assert_eq!(
+        receiver_coin_store["data"]["data"]["coin"]["value"],
+        json!("600")
+    );
+
+    // Nothing was committed
+    context
+        .expect_status_code(404)
+        .get(&format!("/accounts/{}", receiver.address()))
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_simulate_transactions_bundle_too_large() {
+    let mut context = new_test_context(current_function_name!());
+    let sender = &mut context.create_account().await;
+    let mut requests = vec![];
+    for _ in 0..=context.context.max_simulation_bundle_size() {
+        requests.push(
+            context
+                .simulation_request(
+                    sender,
+                    json!({
+                        "type": "entry_function_payload",
+                        "function": "0x1::aptos_account::transfer",
+                        "type_arguments": [],
+                        "arguments": [sender.address().to_hex_literal(), "1"]
+                    }),
+                )
+                .await,
+        );
+        sender.increment_sequence_number();
+    }
+
+    context
+        .expect_status_code(400)
+        .post("/transactions/simulate/bundle", json!(requests))
+        .await;
+}
@@ -12,39 +12,52 @@ use crate::{
     page::Page,
     response::{
         api_disabled, api_forbidden, transaction_not_found_by_hash,
-        transaction_not_found_by_version, version_pruned, BadRequestError, BasicError,
-        BasicErrorWith404, BasicResponse, BasicResponseStatus, BasicResult, BasicResultWith404,
-        InsufficientStorageError, InternalError,
+        transaction_not_found_by_version, version_not_indexed, version_pruned, BadRequestError,
+        BasicError, BasicErrorWith404, BasicResponse, BasicResponseStatus, BasicResult,
+        BasicResultWith404, InsufficientStorageError, InternalError,
     },
+    scratch_state_view::ScratchStateView,
     ApiTags,
 };
 use anyhow::{anyhow, Context as AnyhowContext};
 use aptos_api_types::{
     verify_function_identifier, verify_module_identifier, Address, AptosError, AptosErrorCode,
-    AsConverter, EncodeSubmissionRequest, GasEstimation, GasEstimationBcs, HashValue,
-    HexEncodedBytes, LedgerInfo, MoveType, PendingTransaction, SubmitTransactionRequest,
-    Transaction, TransactionData, TransactionOnChainData, TransactionsBatchSingleSubmissionFailure,
-    TransactionsBatchSubmissionResult, UserTransaction, VerifyInput, VerifyInputWithRecursion,
-    MAX_RECURSIVE_TYPES_ALLOWED, U64,
+    AsConverter, EncodeSubmissionRequest, GasBreakdown, GasCost, GasEstimation, GasEstimationBcs,
+    HashValue, HexEncodedBytes, LedgerInfo, MoveStructTag, MoveType, PendingTransaction,
+    SubmitTransactionRequest, Transaction, TransactionData, TransactionOnChainData,
+    TransactionSimulationPreview, TransactionsBatchSingleSubmissionFailure,
+    TransactionsBatchSubmissionResult, TransactionsBundleSimulationResult, UserTransaction,
+    VerifyInput, VerifyInputWithRecursion, MAX_RECURSIVE_TYPES_ALLOWED, U64,
 };
 use aptos_crypto::{hash::CryptoHash, signing_message};
+use aptos_gas_algebra::InternalGas;
+use aptos_gas_meter::{StandardGasAlgebra, StandardGasMeter};
+use aptos_gas_profiling::{GasProfiler, TransactionGasLog};
+use aptos_memory_usage_tracker::MemoryTrackedGasMeter;
+use aptos_state_view::TStateView;
 use aptos_types::{
     account_config::CoinStoreResource,
     account_view::AccountView,
     mempool_status::MempoolStatusCode,
     transaction::{
         EntryFunction, ExecutionStatus, MultisigTransactionPayload, RawTransaction,
-        RawTransactionWithData, SignedTransaction, TransactionPayload, TransactionStatus,
+        RawTransactionWithData, SignedTransaction, TransactionOutput, TransactionPayload,
+        TransactionStatus,
     },
     vm_status::StatusCode,
+    write_set::WriteSetMut,
 };
 use aptos_vm::{data_cache::AsMoveResolver, storage_adapter::AsExecutorView, AptosVM};
+use move_core_types::language_storage::TypeTag;
 use poem_openapi::{
     param::{Path, Query},
     payload::Json,
     ApiRequest, OpenApi,
 };
-use std::sync::Arc;
+use std::{convert::TryInto, sync::Arc};
+
+/// Maximum number of transactions looked at when filtering account history by event type.
+const MAX_ACCOUNT_HISTORY_SCANNED_TXNS: usize = 1_000;
 
 generate_success_response!(SubmitTransactionResponse, (202, Accepted));
 
@@ -267,6 +280,138 @@ impl TransactionsApi {
         self.list_by_account(&accept_type, page, address.0)
     }
 
+    /// Get account transaction history
+    ///
+    /// Retrieves on-chain committed transactions that touched an account, i.e. that changed any
+    /// resource or module under its address. Unlike /accounts/{address}/transactions, this also
+    /// covers transactions the account did not send, e.g. ones depositing coins into it.
+    ///
+    /// Transactions are returned in version order, and can be restricted to a version range
+    /// and to a range of block timestamps, and to transactions that emitted an event of a given
+    /// type. If there may be more transactions, the version to continue from is returned in the
+    /// X-Aptos-Cursor header; pass it as `start_version` to get the next page. When filtering by
+    /// event type, a page can stop early, or even be empty, after scanning many transactions.
+    ///
+    /// This requires the node to run with the internal indexer enabled, and only covers
+    /// transactions committed since the indexer started tracking account transactions, and not
+    /// pruned yet. Asking for earlier versions returns a 410, with the oldest available version.
+    #[oai(
+        path = "/accounts/:address/transaction_history",
+        method = "get",
+        operation_id = "get_account_transaction_history",
+        tag = "ApiTags::Transactions"
+    )]
+    async fn get_account_transaction_history(
+        &self,
+        accept_type: AcceptType,
+        /// Address of account with or without a `0x` prefix
+        address: Path<Address>,
+        /// First version to include
+        ///
+        /// If not provided, starts from the oldest version that is indexed and not pruned
+        start_version: Query<Option<U64>>,
+        /// Version to stop at (exclusive)
+        ///
+        /// If not provided, includes up to the latest version
+        end_version: Query<Option<U64>>,
+        /// Only include transactions in blocks at or after this timestamp, in microseconds
+        start_timestamp: Query<Option<U64>>,
+        /// Only include transactions in blocks before this timestamp, in microseconds
+        end_timestamp: Query<Option<U64>>,
+        /// Only include transactions that emitted an event of this type,
+        /// e.g. `0x1::coin::DepositEvent`
+        event_type: Query<Option<MoveStructTag>>,
+        /// Max number of transactions to retrieve.
+        ///
+        /// If not provided, defaults to default page size
+        limit: Query<Option<u16>>,
+    ) -> BasicResultWith404<Vec<Transaction>> {
+        fail_point_poem("endpoint_get_account_transaction_history")?;
+        self.context
+            .check_api_output_enabled("Get account transaction history", &accept_type)?;
+        if !self.context.db.indexer_enabled() {
+            return Err(api_forbidden(
+                "Get account transaction history",
+                "The internal indexer is not enabled on this node.",
+            ));
+        }
+
+        let event_type = event_type
+            .0
+            .map(|event_type| {
+                event_type.verify(0)?;
+                Ok::<_, anyhow::Error>(TypeTag::Struct(Box::new(event_type.try_into()?)))
+            })
+            .transpose()
+            .context("'event_type' invalid")
+            .map_err(|err| {
+                BasicErrorWith404::bad_request_with_code_no_info(err, AptosErrorCode::InvalidInput)
+            })?;
+
+        let latest_ledger_info = self.context.get_latest_ledger_info()?;
+        let oldest_indexed_version = self
+            .context
+            .db
+            .get_account_transactions_start_version()
+            .context("Failed to read the first indexed version from the indexer")
+            .map_err(|err| {
+                BasicErrorWith404::internal_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    &latest_ledger_info,
+                )
+            })?;
+        let mut start = match start_version.0 {
+            Some(version) if version.0 < latest_ledger_info.oldest_version() => {
+                return Err(version_pruned(version.0, &latest_ledger_info));
+            },
+            Some(version) if version.0 < oldest_indexed_version => {
+                return Err(version_not_indexed(
+                    version.0,
+                    oldest_indexed_version,
+                    &latest_ledger_info,
+                ));
+            },
+            Some(version) => version.0,
+            None => oldest_indexed_version.max(latest_ledger_info.oldest_version()),
+        };
+        let mut end = end_version
+            .0
+            .map_or(u64::MAX, |v| v.0)
+            .min(latest_ledger_info.version() + 1);
+        if let Some(timestamp) = start_timestamp.0 {
+            start = start
+                .max(self.first_version_at_or_after_timestamp(timestamp.0, &latest_ledger_info)?);
+        }
+        if let Some(timestamp) = end_timestamp.0 {
+            end = end
+                .min(self.first_version_at_or_after_timestamp(timestamp.0, &latest_ledger_info)?);
+        }
+
+        let limit = Page::new(None, limit.0, self.context.max_transactions_page_size())
+            .limit(&latest_ledger_info)?;
+        let (data, next_version) = self.list_account_history(
+            &latest_ledger_info,
+            address.0,
+            start,
+            end,
+            event_type,
+            limit as usize,
+        )?;
+        match accept_type {
+            AcceptType::Json => BasicResponse::try_from_json((
+                self.context
+                    .render_transactions_non_sequential(&latest_ledger_info, data)?,
+                &latest_ledger_info,
+                BasicResponseStatus::Ok,
+            )),
+            AcceptType::Bcs => {
+                BasicResponse::try_from_bcs((data, &latest_ledger_info, BasicResponseStatus::Ok))
+            },
+        }
+        .map(|response| response.with_version_cursor(next_version))
+    }
+
     /// Submit transaction
     ///
     /// This endpoint accepts transaction submissions in two formats.
@@ -429,100 +574,137 @@ impl TransactionsApi {
         self.context
             .check_api_output_enabled("Simulate transaction", &accept_type)?;
         let ledger_info = self.context.get_latest_ledger_info()?;
-        let mut signed_transaction = self.get_signed_transaction(&ledger_info, data)?;
-
-        let estimated_gas_unit_price = match (
+        let signed_transaction = self.get_simulated_transaction(
+            &ledger_info,
+            data,
+            estimate_max_gas_amount.0.unwrap_or_default(),
             estimate_gas_unit_price.0.unwrap_or_default(),
             estimate_prioritized_gas_unit_price.0.unwrap_or_default(),
-        ) {
-            (_, true) => {
-                let gas_estimation = self.context.estimate_gas_price(&ledger_info)?;
-                // The prioritized gas estimate should always be set, but if it's not use the gas estimate
-                Some(
-                    gas_estimation
-                        .prioritized_gas_estimate
-                        .unwrap_or(gas_estimation.gas_estimate),
-                )
-            },
-            (true, false) => Some(self.context.estimate_gas_price(&ledger_info)?.gas_estimate),
-            (false, false) => None,
-        };
-
-        // If estimate max gas amount is provided, we will just make it the maximum value
-        let estimated_max_gas_amount = if estimate_max_gas_amount.0.unwrap_or_default() {
-            // Retrieve max possible gas units
-            let (_, gas_params) = self.context.get_gas_schedule(&ledger_info)?;
-            let min_number_of_gas_units = u64::from(gas_params.vm.txn.min_transaction_gas_units)
-                / u64::from(gas_params.vm.txn.gas_unit_scaling_factor);
-            let max_number_of_gas_units = u64::from(gas_params.vm.txn.maximum_number_of_gas_units);
-
-            // Retrieve account balance to determine max gas available
-            let account_state = self
-                .context
-                .get_account_state(
-                    signed_transaction.sender(),
-                    ledger_info.version(),
-                    &ledger_info,
-                )?
-                .ok_or_else(|| {
-                    SubmitTransactionError::bad_request_with_code(
-                        "Account not found",
-                        AptosErrorCode::InvalidInput,
-                        &ledger_info,
-                    )
-                })?;
-            let coin_store: CoinStoreResource = account_state
-                .get_coin_store_resource()
-                .and_then(|inner| {
-                    inner.ok_or_else(|| {
-                        anyhow!(
-                            "No coin store found for account {}",
-                            signed_transaction.sender()
-                        )
-                    })
-                })
-                .map_err(|err| {
-                    SubmitTransactionError::internal_with_code(
-                        format!("Failed to get coin store resource {}", err),
-                        AptosErrorCode::InternalError,
-                        &ledger_info,
-                    )
-                })?;
-
-            let gas_unit_price =
-                estimated_gas_unit_price.unwrap_or_else(|| signed_transaction.gas_unit_price());
+        )?;
 
-            // With 0 gas price, we set it to max gas units, since we can't divide by 0
-            let max_account_gas_units = if gas_unit_price == 0 {
-                coin_store.coin()
-            } else {
-                coin_store.coin() / gas_unit_price
-            };
+        self.simulate(&accept_type, ledger_info, signed_transaction)
+            .await
+    }
 
-            // To give better error messaging, we should not go below the minimum number of gas units
-            let max_account_gas_units =
-                std::cmp::max(min_number_of_gas_units, max_account_gas_units);
+    /// Simulate transaction with a preview of its effects
+    ///
+    /// Simulates a transaction like the SimulateTransaction API, and additionally returns
+    /// what the transaction would do: every resource, module and table item it would
+    /// change, with the values before and after, the events it would emit, and a breakdown
+    /// of where its gas would go. This is intended for wallets to show previews of
+    /// transactions before they are signed.
+    ///
+    /// Only JSON is supported as an AcceptType.
+    #[oai(
+        path = "/transactions/simulate/preview",
+        method = "post",
+        operation_id = "simulate_transaction_preview",
+        tag = "ApiTags::Transactions"
+    )]
+    async fn simulate_transaction_preview(
+        &self,
+        accept_type: AcceptType,
+        /// If set to true, the max gas value in the transaction will be ignored
+        /// and the maximum possible gas will be used
+        estimate_max_gas_amount: Query<Option<bool>>,
+        /// If set to true, the gas unit price in the transaction will be ignored
+        /// and the estimated value will be used
+        estimate_gas_unit_price: Query<Option<bool>>,
+        /// If set to true, the transaction will use a higher price than the original
+        /// estimate.
+        estimate_prioritized_gas_unit_price: Query<Option<bool>>,
+        data: SubmitTransactionPost,
+    ) -> SimulateTransactionResult<TransactionSimulationPreview> {
+        data.verify()
+            .context("Simulated transaction invalid")
+            .map_err(|err| {
+                SubmitTransactionError::bad_request_with_code_no_info(
+                    err,
+                    AptosErrorCode::InvalidInput,
+                )
+            })?;
+        fail_point_poem("endpoint_simulate_transaction_preview")?;
+        if !self.context.node_config.api.transaction_simulation_enabled {
+            return Err(api_disabled("Simulate transaction preview"));
+        }
+        if AcceptType::Bcs == accept_type {
+            return Err(api_forbidden(
+                "Simulate transaction preview",
+                "Only JSON is supported as an AcceptType.",
+            ));
+        }
+        self.context
+            .check_api_output_enabled("Simulate transaction preview", &accept_type)?;
+        let ledger_info = self.context.get_latest_ledger_info()?;
+        let signed_transaction = self.get_simulated_transaction(
+            &ledger_info,
+            data,
+            estimate_max_gas_amount.0.unwrap_or_default(),
+            estimate_gas_unit_price.0.unwrap_or_default(),
+            estimate_prioritized_gas_unit_price.0.unwrap_or_default(),
+        )?;
 
-            // Minimum of the max account and the max total needs to be used for estimation
-            Some(std::cmp::min(
-                max_account_gas_units,
-                max_number_of_gas_units,
-            ))
-        } else {
-            None
-        };
+        self.simulate_preview(ledger_info, signed_transaction)
+    }
 
-        // If there is an estimation of either, replace the values
-        if estimated_max_gas_amount.is_some() || estimated_gas_unit_price.is_some() {
-            signed_transaction = override_gas_parameters(
-                &signed_transaction,
-                estimated_max_gas_amount,
-                estimated_gas_unit_price,
-            );
+    /// Simulate a bundle of transactions
+    ///
+    /// Simulates the given transactions one after the other, each one running on the state
+    /// left by the previous ones, as if they were committed in this order. This can be used
+    /// to preview transactions that depend on each other, e.g. creating an account and then
+    /// using it. Nothing is committed.
+    ///
+    /// The response contains every simulated transaction, as returned by the
+    /// SimulateTransaction API, and the changes of all of them combined. As with the
+    /// SimulateTransaction API, all transactions must have non-valid signatures.
+    ///
+    /// Only JSON is supported as an AcceptType.
+    #[oai(
+        path = "/transactions/simulate/bundle",
+        method = "post",
+        operation_id = "simulate_transactions_bundle",
+        tag = "ApiTags::Transactions"
+    )]
+    async fn simulate_transactions_bundle(
+        &self,
+        accept_type: AcceptType,
+        data: SubmitTransactionsBatchPost,
+    ) -> SimulateTransactionResult<TransactionsBundleSimulationResult> {
+        data.verify()
+            .context("Simulated transactions invalid")
+            .map_err(|err| {
+                SubmitTransactionError::bad_request_with_code_no_info(
+                    err,
+                    AptosErrorCode::InvalidInput,
+                )
+            })?;
+        fail_point_poem("endpoint_simulate_transactions_bundle")?;
+        if !self.context.node_config.api.transaction_simulation_enabled {
+            return Err(api_disabled("Simulate transactions bundle"));
+        }
+        if AcceptType::Bcs == accept_type {
+            return Err(api_forbidden(
+                "Simulate transactions bundle",
+                "Only JSON is supported as an AcceptType.",
+            ));
+        }
+        self.context
+            .check_api_output_enabled("Simulate transactions bundle", &accept_type)?;
+        let ledger_info = self.context.get_latest_ledger_info()?;
+        let signed_transactions = self.get_signed_transactions_batch(&ledger_info, data)?;
+        if self.context.max_simulation_bundle_size() < signed_transactions.len() {
+            return Err(SubmitTransactionError::bad_request_with_code(
+                format!(
+                    "Simulated too many transactions: {}, while limit is {}",
+                    signed_transactions.len(),
+                    self.context.max_simulation_bundle_size(),
+                ),
+                AptosErrorCode::InvalidInput,
+                &ledger_info,
+            ));
         }
 
-        self.simulate(&accept_type, ledger_info, signed_transaction)
-            .await
+        self.simulate_bundle(ledger_info, signed_transactions)
     }
 
     /// Encode submission
@@ -838,6 +1020,109 @@ impl TransactionsApi {
         }
     }
 
+    /// List the transactions that touched the account in `[start, end)`, according to the
+    /// internal indexer, along with the version to continue from if the page stopped early
+    fn list_account_history(
+        &self,
+        ledger_info: &LedgerInfo,
+        address: Address,
+        start: u64,
+        end: u64,
+        event_type: Option<TypeTag>,
+        limit: usize,
+    ) -> Result<(Vec<TransactionOnChainData>, Option<u64>), BasicErrorWith404> {
+        let mut data = vec![];
+        let mut next = start;
+        let mut num_scanned = 0;
+        while data.len() < limit && next < end && num_scanned < MAX_ACCOUNT_HISTORY_SCANNED_TXNS {
+            // When filtering by event type, some of the transactions will be dropped.
+            let batch_size = match event_type {
+                Some(_) => limit.min(MAX_ACCOUNT_HISTORY_SCANNED_TXNS - num_scanned),
+                None => limit - data.len(),
+            };
+            let versions = self
+                .context
+                .db
+                .get_account_transaction_versions(address.into(), next, batch_size as u64, end - 1)
+                .context("Failed to read account transactions from the indexer")
+                .map_err(|err| {
+                    BasicErrorWith404::internal_with_code(
+                        err,
+                        AptosErrorCode::InternalError,
+                        ledger_info,
+                    )
+                })?;
+            if versions.is_empty() {
+                break;
+            }
+
+            for version in versions {
+                num_scanned += 1;
+                next = version + 1;
+                let txn = self
+                    .context
+                    .get_transaction_by_version(version, ledger_info.version())
+                    .context(format!("Failed to get transaction at version {}", version))
+                    .map_err(|err| {
+                        BasicErrorWith404::internal_with_code(
+                            err,
+                            AptosErrorCode::InternalError,
+                            ledger_info,
+                        )
+                    })?;
+                let matches = event_type.as_ref().map_or(true, |event_type| {
+                    txn.events
+                        .iter()
+                        .any(|event| event.type_tag() == event_type)
+                });
+                if matches {
+                    data.push(txn);
+                    if data.len() == limit {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // The page stopped before the end of the range, either because it's full or because
+        // too many transactions were scanned, so the rest starts at the first unscanned version.
+        let stopped_early = data.len() == limit || num_scanned >= MAX_ACCOUNT_HISTORY_SCANNED_TXNS;
+        let next_version = (stopped_early && next < end).then_some(next);
+        Ok((data, next_version))
+    }
+
+    /// Version of the first transaction in a block with a timestamp at or after `timestamp`
+    /// (or one past the ledger version, if there's none yet)
+    fn first_version_at_or_after_timestamp(
+        &self,
+        timestamp: u64,
+        ledger_info: &LedgerInfo,
+    ) -> Result<u64, BasicErrorWith404> {
+        if timestamp > ledger_info.timestamp() {
+            return Ok(ledger_info.version() + 1);
+        }
+
+        let db = &self.context.db;
+        (|| {
+            // Blocks before the oldest available one may have been pruned, so the search can't
+            // go further back.
+            let (oldest_block_version, _, oldest_block) =
+                db.get_block_info_by_height(ledger_info.oldest_block_height.0)?;
+            if timestamp <= oldest_block.proposed_time() {
+                return Ok(oldest_block_version);
+            }
+            db.get_last_version_before_timestamp(timestamp, ledger_info.version())
+                .map(|version| version + 1)
+        })()
+        .context(format!(
+            "Failed to find the version at timestamp {}",
+            timestamp
+        ))
+        .map_err(|err: anyhow::Error| {
+            BasicErrorWith404::internal_with_code(err, AptosErrorCode::InternalError, ledger_info)
+        })
+    }
+
     /// Parses a single signed transaction
     fn get_signed_transaction(
         &self,
@@ -963,6 +1248,109 @@ impl TransactionsApi {
         Ok(())
     }
 
+    /// Parse the transaction to simulate, and replace its gas parameters with estimates if
+    /// requested
+    fn get_simulated_transaction(
+        &self,
+        ledger_info: &LedgerInfo,
+        data: SubmitTransactionPost,
+        estimate_max_gas_amount: bool,
+        estimate_gas_unit_price: bool,
+        estimate_prioritized_gas_unit_price: bool,
+    ) -> Result<SignedTransaction, SubmitTransactionError> {
+        let mut signed_transaction = self.get_signed_transaction(ledger_info, data)?;
+
+        let estimated_gas_unit_price =
+            match (estimate_gas_unit_price, estimate_prioritized_gas_unit_price) {
+                (_, true) => {
+                    let gas_estimation = self.context.estimate_gas_price(ledger_info)?;
+                    // The prioritized gas estimate should always be set, but if it's not use the gas estimate
+                    Some(
+                        gas_estimation
+                            .prioritized_gas_estimate
+                            .unwrap_or(gas_estimation.gas_estimate),
+                    )
+                },
+                (true, false) => Some(self.context.estimate_gas_price(ledger_info)?.gas_estimate),
+                (false, false) => None,
+            };
+
+        // If estimate max gas amount is provided, we will just make it the maximum value
+        let estimated_max_gas_amount = if estimate_max_gas_amount {
+            // Retrieve max possible gas units
+            let (_, gas_params) = self.context.get_gas_schedule(ledger_info)?;
+            let min_number_of_gas_units = u64::from(gas_params.vm.txn.min_transaction_gas_units)
+                / u64::from(gas_params.vm.txn.gas_unit_scaling_factor);
+            let max_number_of_gas_units = u64::from(gas_params.vm.txn.maximum_number_of_gas_units);
+
+            // Retrieve account balance to determine max gas available
+            let account_state = self
+                .context
+                .get_account_state(
+                    signed_transaction.sender(),
+                    ledger_info.version(),
+                    ledger_info,
+                )?
+                .ok_or_else(|| {
+                    SubmitTransactionError::bad_request_with_code(
+                        "Account not found",
+                        AptosErrorCode::InvalidInput,
+                        ledger_info,
+                    )
+                })?;
+            let coin_store: CoinStoreResource = account_state
+                .get_coin_store_resource()
+                .and_then(|inner| {
+                    inner.ok_or_else(|| {
+                        anyhow!(
+                            "No coin store found for account {}",
+                            signed_transaction.sender()
+                        )
+                    })
+                })
+                .map_err(|err| {
+                    SubmitTransactionError::internal_with_code(
+                        format!("Failed to get coin store resource {}", err),
+                        AptosErrorCode::InternalError,
+                        ledger_info,
+                    )
+                })?;
+
+            let gas_unit_price =
+                estimated_gas_unit_price.unwrap_or_else(|| signed_transaction.gas_unit_price());
+
+            // With 0 gas price, we set it to max gas units, since we can't divide by 0
+            let max_account_gas_units = if gas_unit_price == 0 {
+                coin_store.coin()
+            } else {
+                coin_store.coin() / gas_unit_price
+            };
+
+            // To give better error messaging, we should not go below the minimum number of gas units
+            let max_account_gas_units =
+                std::cmp::max(min_number_of_gas_units, max_account_gas_units);
+
+            // Minimum of the max account and the max total needs to be used for estimation
+            Some(std::cmp::min(
+                max_account_gas_units,
+                max_number_of_gas_units,
+            ))
+        } else {
+            None
+        };
+
+        // If there is an estimation of either, replace the values
+        if estimated_max_gas_amount.is_some() || estimated_gas_unit_price.is_some() {
+            signed_transaction = override_gas_parameters(
+                &signed_transaction,
+                estimated_max_gas_amount,
+                estimated_gas_unit_price,
+            );
+        }
+
+        Ok(signed_transaction)
+    }
+
     /// Parses a batch of signed transactions
     fn get_signed_transactions_batch(
         &self,
@@ -1189,35 +1577,7 @@ impl TransactionsApi {
         let state_view = self.context.latest_state_view_poem(&ledger_info)?;
         let (_, output) =
             AptosVM::simulate_signed_transaction(&txn, &state_view.as_executor_view());
-        let version = ledger_info.version();
-
-        // Ensure that all known statuses return their values in the output (even if they aren't supposed to)
-        let exe_status = match output.status().clone() {
-            TransactionStatus::Keep(exec_status) => exec_status,
-            TransactionStatus::Discard(status) => ExecutionStatus::MiscellaneousError(Some(status)),
-            _ => ExecutionStatus::MiscellaneousError(None),
-        };
-
-        // Build up a transaction from the outputs
-        // All state hashes are invalid, and will be filled with 0s
-        let txn = aptos_types::transaction::Transaction::UserTransaction(txn);
-        let zero_hash = aptos_crypto::HashValue::zero();
-        let info = aptos_types::transaction::TransactionInfo::new(
-            txn.hash(),
-            zero_hash,
-            zero_hash,
-            None,
-            output.gas_used(),
-            exe_status,
-        );
-        let simulated_txn = TransactionOnChainData {
-            version,
-            transaction: txn,
-            info,
-            events: output.events().to_vec(),
-            accumulator_root_hash: zero_hash,
-            changes: output.write_set().clone(),
-        };
+        let simulated_txn = simulated_transaction_data(ledger_info.version(), txn, &output);
 
         match accept_type {
             AcceptType::Json => {
@@ -1252,6 +1612,205 @@ impl TransactionsApi {
         }
     }
 
+    /// Simulate a transaction in the VM while profiling its gas, and diff the state it changes
+    /// against the state it was simulated on
+    pub fn simulate_preview(
+        &self,
+        ledger_info: LedgerInfo,
+        txn: SignedTransaction,
+    ) -> SimulateTransactionResult<TransactionSimulationPreview> {
+        // Transactions shouldn't have a valid signature or this could be used to attack
+        if txn.signature_is_valid() {
+            return Err(SubmitTransactionError::bad_request_with_code(
+                "Simulated transactions must have a non-valid signature",
+                AptosErrorCode::InvalidInput,
+                &ledger_info,
+            ));
+        }
+
+        let state_view = self.context.latest_state_view_poem(&ledger_info)?;
+        let (_, output, gas_profiler) = AptosVM::simulate_signed_transaction_with_custom_gas_meter(
+            &txn,
+            &state_view.as_executor_view(),
+            |gas_feature_version, gas_params, storage_gas_params, balance| {
+                let gas_meter =
+                    MemoryTrackedGasMeter::new(StandardGasMeter::new(StandardGasAlgebra::new(
+                        gas_feature_version,
+                        gas_params,
+                        storage_gas_params,
+                        balance,
+                    )));
+                Ok(new_gas_profiler(gas_meter, txn.payload()))
+            },
+        );
+        let gas_breakdown = gas_profiler.map(|gas_profiler| gas_breakdown(gas_profiler.finish()));
+
+        let resolver = state_view.as_move_resolver();
+        let converter = resolver.as_converter(self.context.db.clone());
+        let state_diff = output
+            .write_set()
+            .iter()
+            .map(|(state_key, op)| {
+                let before = state_view.get_state_value_bytes(state_key)?;
+                converter.try_into_state_diffs(
+                    state_key.clone(),
+                    before.as_deref(),
+                    op.bytes().map(|bytes| bytes.as_ref()),
+                )
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("Failed to diff the state changed by the simulated transaction")
+            .map_err(|err| {
+                SubmitTransactionError::internal_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    &ledger_info,
+                )
+            })?
+            .into_iter()
+            .flatten()
+            .collect();
+        let events = converter
+            .try_into_events(output.events())
+            .context("Failed to convert the events of the simulated transaction")
+            .map_err(|err| {
+                SubmitTransactionError::internal_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    &ledger_info,
+                )
+            })?;
+
+        let simulated_txn = simulated_transaction_data(ledger_info.version(), txn, &output);
+        let transaction = match self
+            .context
+            .render_transactions_non_sequential(&ledger_info, vec![simulated_txn])?
+            .pop()
+        {
+            Some(Transaction::UserTransaction(user_txn)) => *user_txn,
+            _ => {
+                return Err(SubmitTransactionError::internal_with_code(
+                    "Simulation transaction resulted in a non-UserTransaction",
+                    AptosErrorCode::InternalError,
+                    &ledger_info,
+                ))
+            },
+        };
+
+        BasicResponse::try_from_json((
+            TransactionSimulationPreview {
+                transaction,
+                state_diff,
+                events,
+                gas_breakdown,
+            },
+            &ledger_info,
+            BasicResponseStatus::Ok,
+        ))
+    }
+
+    /// Simulate the transactions in the VM one after the other, each one on the state left
+    /// by the previous ones
+    pub fn simulate_bundle(
+        &self,
+        ledger_info: LedgerInfo,
+        txns: Vec<SignedTransaction>,
+    ) -> SimulateTransactionResult<TransactionsBundleSimulationResult> {
+        // Transactions shouldn't have a valid signature or this could be used to attack
+        if let Some(index) = txns.iter().position(|txn| txn.signature_is_valid()) {
+            return Err(SubmitTransactionError::bad_request_with_code(
+                format!(
+                    "Simulated transactions must have a non-valid signature, but #{} is valid",
+                    index
+                ),
+                AptosErrorCode::InvalidInput,
+                &ledger_info,
+            ));
+        }
+
+        let state_view = self.context.latest_state_view_poem(&ledger_info)?;
+        let mut scratch_state_view = ScratchStateView::new(&state_view);
+        let mut write_set = WriteSetMut::default();
+        let mut simulated_txns = Vec::with_capacity(txns.len());
+        for txn in txns {
+            let (_, output) =
+                AptosVM::simulate_signed_transaction(&txn, &scratch_state_view.as_executor_view());
+            scratch_state_view.apply_write_set(output.write_set());
+            write_set = write_set
+                .squash(output.write_set().clone().into_mut())
+                .context("Failed to combine the changes of the simulated transactions")
+                .map_err(|err| {
+                    SubmitTransactionError::internal_with_code(
+                        err,
+                        AptosErrorCode::InternalError,
+                        &ledger_info,
+                    )
+                })?;
+            simulated_txns.push(simulated_transaction_data(
+                ledger_info.version(),
+                txn,
+                &output,
+            ));
+        }
+
+        // Convert on top of the simulated state, so that anything published by the bundle can
+        // be decoded
+        let timestamp = self
+            .context
+            .get_block_timestamp(&ledger_info, ledger_info.version())?;
+        let resolver = scratch_state_view.as_move_resolver();
+        let converter = resolver.as_converter(self.context.db.clone());
+        let transactions = simulated_txns
+            .into_iter()
+            .map(
+                |txn| match converter.try_into_onchain_transaction(timestamp, txn)? {
+                    Transaction::UserTransaction(user_txn) => Ok(*user_txn),
+                    _ => Err(anyhow!(
+                        "Simulation transaction resulted in a non-UserTransaction"
+                    )),
+                },
+            )
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("Failed to convert simulated transactions")
+            .map_err(|err| {
+                SubmitTransactionError::internal_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    &ledger_info,
+                )
+            })?;
+        let write_set = write_set
+            .freeze()
+            .and_then(|write_set| {
+                write_set
+                    .iter()
+                    .map(|(state_key, op)| {
+                        converter.try_into_write_set_changes(state_key.clone(), op.clone())
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .context("Failed to convert the changes of the simulated transactions")
+            .map_err(|err| {
+                SubmitTransactionError::internal_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    &ledger_info,
+                )
+            })?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        BasicResponse::try_from_json((
+            TransactionsBundleSimulationResult {
+                transactions,
+                write_set,
+            },
+            &ledger_info,
+            BasicResponseStatus::Ok,
+        ))
+    }
+
     /// Encode message as BCS
     pub fn get_signing_message(
         &self,
@@ -1337,3 +1896,93 @@ enum GetByVersionResponse {
     VersionTooOld,
     Found(TransactionData),
 }
+
+/// Builds the on chain representation of a simulated transaction from its output
+///
+/// All state hashes are invalid, and will be filled with 0s
+fn simulated_transaction_data(
+    version: u64,
+    txn: SignedTransaction,
+    output: &TransactionOutput,
+) -> TransactionOnChainData {
+    // Ensure that all known statuses return their values in the output (even if they aren't supposed to)
+    let exe_status = match output.status().clone() {
+        TransactionStatus::Keep(exec_status) => exec_status,
+        TransactionStatus::Discard(status) => ExecutionStatus::MiscellaneousError(Some(status)),
+        _ => ExecutionStatus::MiscellaneousError(None),
+    };
+
+    let txn = aptos_types::transaction::Transaction::UserTransaction(txn);
+    let zero_hash = aptos_crypto::HashValue::zero();
+    let info = aptos_types::transaction::TransactionInfo::new(
+        txn.hash(),
+        zero_hash,
+        zero_hash,
+        None,
+        output.gas_used(),
+        exe_status,
+    );
+    TransactionOnChainData {
+        version,
+        transaction: txn,
+        info,
+        events: output.events().to_vec(),
+        accumulator_root_hash: zero_hash,
+        changes: output.write_set().clone(),
+    }
+}
+
+/// Wraps the gas meter in a profiler, which records where the gas of the transaction goes
+fn new_gas_profiler<G>(gas_meter: G, payload: &TransactionPayload) -> GasProfiler<G> {
+    let entry_function = match payload {
+        TransactionPayload::EntryFunction(entry_function) => Some(entry_function),
+        TransactionPayload::Multisig(multisig) => multisig
+            .transaction_payload
+            .as_ref()
+            .map(|MultisigTransactionPayload::EntryFunction(entry_function)| entry_function),
+        TransactionPayload::Script(_) | TransactionPayload::ModuleBundle(_) => None,
+    };
+    match entry_function {
+        Some(entry_function) => GasProfiler::new_function(
+            gas_meter,
+            entry_function.module().clone(),
+            entry_function.function().to_owned(),
+            entry_function.ty_args().to_vec(),
+        ),
+        None => GasProfiler::new_script(gas_meter),
+    }
+}
+
+/// Summarizes the gas log of a simulated transaction by category
+fn gas_breakdown(gas_log: TransactionGasLog) -> GasBreakdown {
+    let aggregated = gas_log.exec_io.aggregate_gas_events();
+    let to_gas_costs = |costs: Vec<(String, usize, InternalGas)>| {
+        costs
+            .into_iter()
+            .map(|(name, count, cost)| GasCost {
+                name,
+                count: (count as u64).into(),
+                cost: u64::from(cost).into(),
+            })
+            .collect()
+    };
+    let event_storage_fee = gas_log
+        .storage
+        .events
+        .iter()
+        .map(|event| u64::from(event.cost))
+        .sum::<u64>()
+        .saturating_sub(u64::from(gas_log.storage.event_discount));
+
+    GasBreakdown {
+        gas_scaling_factor: u64::from(gas_log.exec_io.gas_scaling_factor).into(),
+        execution_and_io: u64::from(gas_log.exec_io.total).into(),
+        intrinsic: u64::from(gas_log.exec_io.intrinsic_cost).into(),
+        execution: to_gas_costs(aggregated.ops),
+        storage_reads: to_gas_costs(aggregated.storage_reads),
+        storage_writes: to_gas_costs(aggregated.storage_writes),
+        storage_fee: u64::from(gas_log.storage.total).into(),
+        transaction_storage_fee: u64::from(gas_log.storage.txn_storage).into(),
+        event_storage_fee: event_storage_fee.into(),
+    }
+}
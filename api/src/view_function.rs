@@ -9,7 +9,9 @@ use crate::{
     },
     ApiTags, Context,
 };
-use aptos_api_types::{AptosErrorCode, AsConverter, MoveValue, ViewRequest, U64};
+use aptos_api_types::{AptosErrorCode, AsConverter, LedgerInfo, MoveValue, ViewRequest, U64};
+use aptos_storage_interface::state_view::DbStateView;
+use aptos_types::transaction::EntryFunction;
 use aptos_vm::{data_cache::AsMoveResolver, AptosVM};
 use move_core_types::language_storage::TypeTag;
 use poem_openapi::{param::Query, payload::Json, OpenApi};
@@ -50,36 +52,67 @@ impl ViewFunctionApi {
 
         let (ledger_info, requested_version) = self
             .context
-            .get_latest_ledger_info_and_verify_lookup_version(
+            .get_latest_ledger_info_and_verify_state_lookup_version(
                 ledger_version.map(|inner| inner.0),
             )?;
 
-        let state_view = self.context.latest_state_view_poem(&ledger_info)?;
-        let resolver = state_view.as_move_resolver();
-
-        let entry_func = resolver
-            .as_converter(self.context.db.clone())
-            .convert_view_function(request.0)
+        let latest_state_view = self.context.latest_state_view_poem(&ledger_info)?;
+        let state_view = self
+            .context
+            .state_view_at_version(requested_version)
             .map_err(|err| {
                 BasicErrorWith404::bad_request_with_code(
                     err,
-                    AptosErrorCode::InvalidInput,
+                    AptosErrorCode::InternalError,
                     &ledger_info,
                 )
             })?;
-        let state_view = self
-            .context
-            .state_view_at_version(requested_version)
+
+        let (entry_func, return_vals) =
+            self.execute(&ledger_info, &latest_state_view, &state_view, request.0)?;
+        match accept_type {
+            AcceptType::Bcs => {
+                BasicResponse::try_from_bcs((return_vals, &ledger_info, BasicResponseStatus::Ok))
+            },
+            AcceptType::Json => {
+                let move_vals = self.return_values_to_json(
+                    &ledger_info,
+                    &latest_state_view,
+                    &entry_func,
+                    return_vals,
+                )?;
+
+                BasicResponse::try_from_json((move_vals, &ledger_info, BasicResponseStatus::Ok))
+            },
+        }
+    }
+}
+
+impl ViewFunctionApi {
+    /// Execute the view function against `state_view`, returning the BCS encoded return values
+    ///
+    /// The request is converted using the modules in `latest_state_view`.
+    pub fn execute(
+        &self,
+        ledger_info: &LedgerInfo,
+        latest_state_view: &DbStateView,
+        state_view: &DbStateView,
+        request: ViewRequest,
+    ) -> Result<(EntryFunction, Vec<Vec<u8>>), BasicErrorWith404> {
+        let entry_func = latest_state_view
+            .as_move_resolver()
+            .as_converter(self.context.db.clone())
+            .convert_view_function(request)
             .map_err(|err| {
                 BasicErrorWith404::bad_request_with_code(
                     err,
-                    AptosErrorCode::InternalError,
-                    &ledger_info,
+                    AptosErrorCode::InvalidInput,
+                    ledger_info,
                 )
             })?;
 
         let return_vals = AptosVM::execute_view_function(
-            &state_view,
+            state_view,
             entry_func.module().clone(),
             entry_func.function().to_owned(),
             entry_func.ty_args().to_owned(),
@@ -89,46 +122,49 @@ impl ViewFunctionApi {
         .map_err(|err| {
             BasicErrorWith404::bad_request_with_code_no_info(err, AptosErrorCode::InvalidInput)
         })?;
-        match accept_type {
-            AcceptType::Bcs => {
-                BasicResponse::try_from_bcs((return_vals, &ledger_info, BasicResponseStatus::Ok))
-            },
-            AcceptType::Json => {
-                let return_types = resolver
-                    .as_converter(self.context.db.clone())
-                    .function_return_types(&entry_func)
-                    .and_then(|tys| {
-                        tys.into_iter()
-                            .map(TypeTag::try_from)
-                            .collect::<anyhow::Result<Vec<_>>>()
-                    })
-                    .map_err(|err| {
-                        BasicErrorWith404::bad_request_with_code(
-                            err,
-                            AptosErrorCode::InternalError,
-                            &ledger_info,
-                        )
-                    })?;
+        Ok((entry_func, return_vals))
+    }
 
-                let move_vals = return_vals
-                    .into_iter()
-                    .zip(return_types.into_iter())
-                    .map(|(v, ty)| {
-                        resolver
-                            .as_converter(self.context.db.clone())
-                            .try_into_move_value(&ty, &v)
-                    })
+    /// Convert the BCS encoded return values of the view function to MoveValues
+    pub fn return_values_to_json(
+        &self,
+        ledger_info: &LedgerInfo,
+        state_view: &DbStateView,
+        entry_func: &EntryFunction,
+        return_vals: Vec<Vec<u8>>,
+    ) -> Result<Vec<MoveValue>, BasicErrorWith404> {
+        let resolver = state_view.as_move_resolver();
+        let return_types = resolver
+            .as_converter(self.context.db.clone())
+            .function_return_types(entry_func)
+            .and_then(|tys| {
+                tys.into_iter()
+                    .map(TypeTag::try_from)
                     .collect::<anyhow::Result<Vec<_>>>()
-                    .map_err(|err| {
-                        BasicErrorWith404::bad_request_with_code(
-                            err,
-                            AptosErrorCode::InternalError,
-                            &ledger_info,
-                        )
-                    })?;
+            })
+            .map_err(|err| {
+                BasicErrorWith404::bad_request_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    ledger_info,
+                )
+            })?;
 
-                BasicResponse::try_from_json((move_vals, &ledger_info, BasicResponseStatus::Ok))
-            },
-        }
+        return_vals
+            .into_iter()
+            .zip(return_types.into_iter())
+            .map(|(v, ty)| {
+                resolver
+                    .as_converter(self.context.db.clone())
+                    .try_into_move_value(&ty, &v)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|err| {
+                BasicErrorWith404::bad_request_with_code(
+                    err,
+                    AptosErrorCode::InternalError,
+                    ledger_info,
+                )
+            })
     }
 }
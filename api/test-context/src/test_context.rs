@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::{golden_output::GoldenOutputs, pretty};
-use aptos_api::{attach_poem_to_runtime, BasicError, Context};
+use aptos_api::{attach_poem_to_runtime, spawn_committed_transactions_reader, BasicError, Context};
 use aptos_api_types::{
     mime_types, HexEncodedBytes, TransactionOnChainData, X_APTOS_CHAIN_ID,
     X_APTOS_LEDGER_TIMESTAMP, X_APTOS_LEDGER_VERSION,
@@ -10,7 +10,7 @@ use aptos_api_types::{
 use aptos_cached_packages::aptos_stdlib;
 use aptos_config::{
     config::{
-        NodeConfig, RocksdbConfigs, BUFFERED_STATE_TARGET_ITEMS,
+        LedgerPrunerConfig, NodeConfig, PrunerConfig, RocksdbConfigs, BUFFERED_STATE_TARGET_ITEMS,
         DEFAULT_MAX_NUM_NODES_PER_LRU_CACHE_SHARD, NO_OP_STORAGE_PRUNER_CONFIG,
     },
     keys::ConfigKey,
@@ -41,7 +41,7 @@ use aptos_types::{
     ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
     transaction::{
         signature_verified_transaction::into_signature_verified_block, Transaction,
-        TransactionPayload, TransactionStatus,
+        TransactionPayload, TransactionStatus, Version,
     },
 };
 use aptos_vm::AptosVM;
@@ -95,6 +95,46 @@ pub fn new_test_context(
     test_name: String,
     node_config: NodeConfig,
     use_db_with_indexer: bool,
+) -> TestContext {
+    new_test_context_inner(
+        test_name,
+        node_config,
+        use_db_with_indexer.then_some(0),
+        None,
+    )
+}
+
+/// Creates a test context whose indexer only indexes account transactions from
+/// `account_transactions_start_version` on, like on a node that ran the indexer before it
+/// tracked account transactions.
+pub fn new_test_context_with_indexer_from(
+    test_name: String,
+    node_config: NodeConfig,
+    account_transactions_start_version: Version,
+) -> TestContext {
+    new_test_context_inner(
+        test_name,
+        node_config,
+        Some(account_transactions_start_version),
+        None,
+    )
+}
+
+/// Creates a test context with the indexer, whose ledger only keeps the last
+/// `ledger_prune_window` versions, like on a node with the ledger pruner enabled.
+pub fn new_test_context_with_ledger_prune_window(
+    test_name: String,
+    node_config: NodeConfig,
+    ledger_prune_window: Version,
+) -> TestContext {
+    new_test_context_inner(test_name, node_config, Some(0), Some(ledger_prune_window))
+}
+
+fn new_test_context_inner(
+    test_name: String,
+    node_config: NodeConfig,
+    account_transactions_start_version: Option<Version>,
+    ledger_prune_window: Option<Version>,
 ) -> TestContext {
     // Speculative logging uses a global variable and when many instances use it together, they
     // panic, so we disable this to run tests.
@@ -117,8 +157,35 @@ pub fn new_test_context(
     let (validator_identity, _, _, _) = validators[0].get_key_objects(None).unwrap();
     let validator_owner = validator_identity.account_address.unwrap();
 
-    let (db, db_rw) = if use_db_with_indexer {
-        DbReaderWriter::wrap(AptosDB::new_for_test_with_indexer(&tmp_dir))
+    let (db, db_rw) = if let Some(prune_window) = ledger_prune_window {
+        let pruner_config = PrunerConfig {
+            ledger_pruner_config: LedgerPrunerConfig {
+                enable: true,
+                prune_window,
+                // Big enough for the pruner to never wake up, the versions out of the window are
+                // unavailable to readers either way.
+                batch_size: 1_000_000,
+                user_pruning_window_offset: 0,
+            },
+            ..NO_OP_STORAGE_PRUNER_CONFIG
+        };
+        DbReaderWriter::wrap(
+            AptosDB::open(
+                &tmp_dir,
+                false, /* readonly */
+                pruner_config,
+                RocksdbConfigs::default(),
+                true, /* indexer */
+                BUFFERED_STATE_TARGET_ITEMS,
+                DEFAULT_MAX_NUM_NODES_PER_LRU_CACHE_SHARD,
+            )
+            .unwrap(),
+        )
+    } else if let Some(start_version) = account_transactions_start_version {
+        DbReaderWriter::wrap(AptosDB::new_for_test_with_indexer_from(
+            &tmp_dir,
+            start_version,
+        ))
     } else {
         DbReaderWriter::wrap(
             AptosDB::open(
@@ -139,12 +206,20 @@ pub fn new_test_context(
 
     let mempool = MockSharedMempool::new_in_runtime(&db_rw, VMValidator::new(db.clone()));
 
-    let context = Context::new(
+    let mut context = Context::new(
         ChainId::test(),
         db.clone(),
         mempool.ac_client.clone(),
         node_config.clone(),
     );
+    if context.subscriptions_enabled() {
+        let committed_transactions = spawn_committed_transactions_reader(
+            &tokio::runtime::Handle::current(),
+            context.clone(),
+            None,
+        );
+        context = context.with_committed_transactions(committed_transactions);
+    }
 
     // Configure the testing depending on which API version we're testing.
     let runtime_handle = tokio::runtime::Handle::current();
@@ -840,6 +915,40 @@ impl TestContext {
         payload: Value,
         status_code: u16,
     ) -> Value {
+        self.simulate_transaction_at("/transactions/simulate", sender, payload, status_code)
+            .await
+    }
+
+    pub async fn simulate_transaction_preview(
+        &mut self,
+        sender: &LocalAccount,
+        payload: Value,
+        status_code: u16,
+    ) -> Value {
+        self.simulate_transaction_at(
+            "/transactions/simulate/preview",
+            sender,
+            payload,
+            status_code,
+        )
+        .await
+    }
+
+    async fn simulate_transaction_at(
+        &mut self,
+        path: &str,
+        sender: &LocalAccount,
+        payload: Value,
+        status_code: u16,
+    ) -> Value {
+        let request = self.simulation_request(sender, payload).await;
+        self.expect_status_code(status_code)
+            .post(path, request)
+            .await
+    }
+
+    /// Builds a transaction request for the simulation APIs, which must not be validly signed
+    pub async fn simulation_request(&mut self, sender: &LocalAccount, payload: Value) -> Value {
         let mut request = json!({
             "sender": sender.address(),
             "sequence_number": sender.sequence_number().to_string(),
@@ -870,10 +979,7 @@ impl TestContext {
             "public_key": HexEncodedBytes::from(sender.public_key().to_bytes().to_vec()),
             "signature": HexEncodedBytes::from(sig.to_bytes().to_vec()),
         });
-
-        self.expect_status_code(status_code)
-            .post("/transactions/simulate", request)
-            .await
+        request
     }
 
     pub fn prepend_path(&self, path: &str) -> String {
@@ -0,0 +1,72 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    Address, AptosError, MoveStructTag, TableItemRequest, VerifyInput, VerifyInputWithRecursion,
+    ViewRequest,
+};
+use poem_openapi::{Object, Union};
+use serde::{Deserialize, Serialize};
+
+/// A single read in a batch read request
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Union)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[oai(one_of, discriminator_name = "type", rename_all = "snake_case")]
+pub enum BatchReadRequest {
+    Resource(BatchResourceRequest),
+    TableItem(BatchTableItemRequest),
+    View(ViewRequest),
+}
+
+impl VerifyInput for BatchReadRequest {
+    fn verify(&self) -> anyhow::Result<()> {
+        match self {
+            BatchReadRequest::Resource(request) => request.resource_type.verify(0),
+            BatchReadRequest::TableItem(request) => request.request.verify(),
+            BatchReadRequest::View(_) => Ok(()),
+        }
+    }
+}
+
+/// Read of an account resource, as in the GetAccountResource API
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct BatchResourceRequest {
+    pub address: Address,
+    pub resource_type: MoveStructTag,
+}
+
+/// Read of a table item, as in the GetTableItem API
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct BatchTableItemRequest {
+    pub table_handle: Address,
+    #[serde(flatten)]
+    #[oai(flatten)]
+    pub request: TableItemRequest,
+}
+
+/// Result of a single read in a batch read request
+///
+/// Exactly one of `value` and `error` is set.
+#[derive(Clone, Debug, Serialize, Deserialize, Object)]
+pub struct BatchReadResult {
+    /// The read value: a `MoveResource` for resources, a `MoveValue` for table items,
+    /// and the list of returned `MoveValue`s for view functions
+    pub value: Option<serde_json::Value>,
+    pub error: Option<AptosError>,
+}
+
+impl BatchReadResult {
+    pub fn value(value: serde_json::Value) -> Self {
+        Self {
+            value: Some(value),
+            error: None,
+        }
+    }
+
+    pub fn error(error: AptosError) -> Self {
+        Self {
+            value: None,
+            error: Some(error),
+        }
+    }
+}
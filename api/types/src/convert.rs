@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    simulation::{DecodedTableItemDiff, ModuleDiff, ResourceDiff, StateDiff, TableItemDiff},
     transaction::{
         DecodedTableData, DeleteModule, DeleteResource, DeleteTableItem, DeletedTableData,
         ModuleBundlePayload, MultisigPayload, MultisigTransactionPayload,
@@ -10,11 +11,12 @@ use crate::{
         WriteTableItem,
     },
     view::ViewRequest,
-    Bytecode, DirectWriteSet, EntryFunctionId, EntryFunctionPayload, Event, HexEncodedBytes,
-    MoveFunction, MoveModuleBytecode, MoveResource, MoveScriptBytecode, MoveType, MoveValue,
-    PendingTransaction, ResourceGroup, ScriptPayload, ScriptWriteSet, SubmitTransactionRequest,
-    Transaction, TransactionInfo, TransactionOnChainData, TransactionPayload,
-    UserTransactionRequest, VersionedEvent, WriteSet, WriteSetChange, WriteSetPayload,
+    Address, Bytecode, DirectWriteSet, EntryFunctionId, EntryFunctionPayload, Event,
+    HexEncodedBytes, MoveFunction, MoveModuleBytecode, MoveResource, MoveScriptBytecode, MoveType,
+    MoveValue, PendingTransaction, ResourceGroup, ScriptPayload, ScriptWriteSet,
+    SubmitTransactionRequest, Transaction, TransactionInfo, TransactionOnChainData,
+    TransactionPayload, UserTransactionRequest, VersionedEvent, WriteSet, WriteSetChange,
+    WriteSetPayload,
 };
 use anyhow::{bail, ensure, format_err, Context as AnyhowContext, Result};
 use aptos_crypto::{hash::CryptoHash, HashValue};
@@ -46,6 +48,7 @@ use move_core_types::{
 use move_resource_viewer::MoveValueAnnotator;
 use serde_json::Value;
 use std::{
+    collections::BTreeSet,
     convert::{TryFrom, TryInto},
     iter::IntoIterator,
     rc::Rc,
@@ -470,6 +473,136 @@ impl<'a, R: MoveResolver + ?Sized> MoveConverter<'a, R> {
         }))
     }
 
+    /// Diffs a state item before and after a (simulated) transaction
+    ///
+    /// `before` and `after` are the raw state values, if the item existed.
+    pub fn try_into_state_diffs(
+        &self,
+        state_key: StateKey,
+        before: Option<&[u8]>,
+        after: Option<&[u8]>,
+    ) -> Result<Vec<StateDiff>> {
+        let state_key_hash = state_key.hash().to_hex_literal();
+        let diffs = match state_key.into_inner() {
+            StateKeyInner::AccessPath(access_path) => {
+                let address: Address = access_path.address.into();
+                match access_path.get_path() {
+                    Path::Code(module_id) => vec![StateDiff::Module(ModuleDiff {
+                        address,
+                        state_key_hash,
+                        module: module_id.into(),
+                        before: before.map(|bytes| bytes.to_vec().into()),
+                        after: after.map(|bytes| bytes.to_vec().into()),
+                    })],
+                    Path::Resource(typ) => vec![StateDiff::Resource(ResourceDiff {
+                        address,
+                        state_key_hash,
+                        resource_type: typ.clone().into(),
+                        before: self.try_into_optional_resource(&typ, before)?,
+                        after: self.try_into_optional_resource(&typ, after)?,
+                    })],
+                    Path::ResourceGroup(_) => {
+                        let before = Self::resource_group_members(before)?;
+                        let after = Self::resource_group_members(after)?;
+                        let tags: BTreeSet<&StructTag> =
+                            before.keys().chain(after.keys()).collect();
+                        tags.into_iter()
+                            .filter(|tag| before.get(*tag) != after.get(*tag))
+                            .map(|tag| {
+                                Ok(StateDiff::Resource(ResourceDiff {
+                                    address,
+                                    state_key_hash: state_key_hash.clone(),
+                                    resource_type: tag.clone().into(),
+                                    before: self.try_into_optional_resource(
+                                        tag,
+                                        before.get(tag).map(Vec::as_slice),
+                                    )?,
+                                    after: self.try_into_optional_resource(
+                                        tag,
+                                        after.get(tag).map(Vec::as_slice),
+                                    )?,
+                                }))
+                            })
+                            .collect::<Result<_>>()?
+                    },
+                }
+            },
+            StateKeyInner::TableItem { handle, key } => {
+                let decoded = self.try_into_decoded_table_item_diff(handle, &key, before, after)?;
+                vec![StateDiff::TableItem(TableItemDiff {
+                    state_key_hash,
+                    handle: handle.0.to_vec().into(),
+                    key: key.into(),
+                    before: before.map(|bytes| bytes.to_vec().into()),
+                    after: after.map(|bytes| bytes.to_vec().into()),
+                    decoded,
+                })]
+            },
+            StateKeyInner::Raw(_) => bail!(
+                "Can't convert raw state key {} to StateDiff",
+                state_key_hash
+            ),
+        };
+        Ok(diffs)
+    }
+
+    fn try_into_optional_resource(
+        &self,
+        typ: &StructTag,
+        bytes: Option<&[u8]>,
+    ) -> Result<Option<MoveResource>> {
+        bytes
+            .map(|bytes| self.try_into_resource(typ, bytes))
+            .transpose()
+    }
+
+    fn resource_group_members(bytes: Option<&[u8]>) -> Result<ResourceGroup> {
+        Ok(bytes
+            .map(bcs::from_bytes::<ResourceGroup>)
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    fn try_into_decoded_table_item_diff(
+        &self,
+        handle: TableHandle,
+        key: &[u8],
+        before: Option<&[u8]>,
+        after: Option<&[u8]>,
+    ) -> Result<Option<DecodedTableItemDiff>> {
+        if !self.db.indexer_enabled() {
+            return Ok(None);
+        }
+        let table_info = match self.db.get_table_info(handle) {
+            Ok(ti) => ti,
+            Err(_) => {
+                aptos_logger::warn!(
+                    "Table info not found for handle {:?}, can't decode table item. OK for simulation",
+                    handle
+                );
+                return Ok(None); // if table item not found return None anyway to avoid crash
+            },
+        };
+        let decode_value = |bytes: Option<&[u8]>| {
+            bytes
+                .map(|bytes| {
+                    self.try_into_move_value(&table_info.value_type, bytes)?
+                        .json()
+                })
+                .transpose()
+        };
+
+        Ok(Some(DecodedTableItemDiff {
+            key: self
+                .try_into_move_value(&table_info.key_type, key)?
+                .json()?,
+            key_type: table_info.key_type.to_string(),
+            before: decode_value(before)?,
+            after: decode_value(after)?,
+            value_type: table_info.value_type.to_string(),
+        }))
+    }
+
     pub fn try_into_events(&self, events: &[ContractEvent]) -> Result<Vec<Event>> {
         let mut ret = vec![];
         for event in events {
@@ -2,6 +2,7 @@
 // Parts of the project are originally copyright © Meta Platforms, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::U64;
 use aptos_types::vm_status::StatusCode;
 use poem_openapi::{Enum, Object};
 use serde::{Deserialize, Serialize};
@@ -16,6 +17,10 @@ pub struct AptosError {
     pub error_code: AptosErrorCode,
     /// A code providing VM error details when submitting transactions to the VM
     pub vm_error_code: Option<u64>,
+    /// The oldest version still available on this node, set when the requested data has
+    /// been pruned
+    #[oai(skip_serializing_if_is_none)]
+    pub oldest_available_version: Option<U64>,
 }
 
 impl std::fmt::Display for AptosError {
@@ -35,6 +40,7 @@ impl AptosError {
             message: format!("{:#}", error),
             error_code,
             vm_error_code: None,
+            oldest_available_version: None,
         }
     }
 
@@ -47,6 +53,20 @@ impl AptosError {
             message: format!("{:#}", error),
             error_code,
             vm_error_code: Some(vm_error_code as u64),
+            oldest_available_version: None,
+        }
+    }
+
+    pub fn new_pruned<ErrorType: std::fmt::Display>(
+        error: ErrorType,
+        error_code: AptosErrorCode,
+        oldest_available_version: u64,
+    ) -> AptosError {
+        Self {
+            message: format!("{:#}", error),
+            error_code,
+            vm_error_code: None,
+            oldest_available_version: Some(oldest_available_version.into()),
         }
     }
 }
@@ -86,6 +106,16 @@ pub enum AptosErrorCode {
     VersionPruned = 200,
     /// Block is fully or partially pruned
     BlockPruned = 201,
+    /// State at the requested version is pruned
+    ///
+    /// State may be pruned more aggressively than the rest of the ledger, so this can be
+    /// returned for versions whose transactions are still available
+    StateVersionPruned = 202,
+    /// The requested versions aren't covered by the internal indexer
+    ///
+    /// The indexer may have started tracking some data after the node started, so this can be
+    /// returned for versions whose transactions are still available
+    VersionNotIndexed = 203,
 
     /// The API's inputs were invalid
     InvalidInput = 300,
@@ -22,7 +22,12 @@ pub struct IndexResponse {
     pub node_role: RoleType,
     pub oldest_block_height: U64,
     pub block_height: U64,
-    // This must be optional to be backwards compatible
+    // These must be optional to be backwards compatible
+    /// Oldest version at which account state (resources, modules and table items) can be
+    /// read. State may be pruned more aggressively than transactions.
+    pub oldest_state_version: Option<U64>,
+    /// Oldest version whose events can be read. Events are pruned along with transactions.
+    pub oldest_event_version: Option<U64>,
     /// Git hash of the build of the API endpoint.  Can be used to determine the exact
     /// software version used by the API endpoint.
     pub git_hash: Option<String>,
@@ -31,6 +36,7 @@ pub struct IndexResponse {
 impl IndexResponse {
     pub fn new(
         ledger_info: LedgerInfo,
+        oldest_state_version: u64,
         node_role: RoleType,
         git_hash: Option<String>,
     ) -> IndexResponse {
@@ -42,6 +48,8 @@ impl IndexResponse {
             ledger_timestamp: ledger_info.ledger_timestamp,
             oldest_block_height: ledger_info.oldest_block_height,
             block_height: ledger_info.block_height,
+            oldest_state_version: Some(oldest_state_version.into()),
+            oldest_event_version: Some(ledger_info.oldest_ledger_version),
             node_role,
             git_hash,
         }
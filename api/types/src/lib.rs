@@ -6,6 +6,7 @@
 
 mod account;
 mod address;
+mod batch;
 mod block;
 mod bytecode;
 mod convert;
@@ -17,6 +18,7 @@ mod index;
 mod ledger_info;
 pub mod mime_types;
 mod move_types;
+mod simulation;
 mod state;
 mod table;
 pub mod transaction;
@@ -25,6 +27,7 @@ mod wrappers;
 
 pub use account::AccountData;
 pub use address::Address;
+pub use batch::{BatchReadRequest, BatchReadResult, BatchResourceRequest, BatchTableItemRequest};
 pub use block::{BcsBlock, Block};
 pub use bytecode::Bytecode;
 pub use convert::{new_vm_utf8_string, AsConverter, ExplainVMStatus, MoveConverter};
@@ -41,6 +44,10 @@ pub use move_types::{
     ResourceGroup, MAX_RECURSIVE_TYPES_ALLOWED, U128, U256, U64,
 };
 use serde::{Deserialize, Deserializer};
+pub use simulation::{
+    DecodedTableItemDiff, GasBreakdown, GasCost, ModuleDiff, ResourceDiff, StateDiff,
+    TableItemDiff, TransactionSimulationPreview, TransactionsBundleSimulationResult,
+};
 pub use state::RawStateValueRequest;
 use std::str::FromStr;
 pub use table::{RawTableItemRequest, TableItemRequest};
@@ -0,0 +1,136 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    Address, Event, HexEncodedBytes, MoveModuleId, MoveResource, MoveStructTag, UserTransaction,
+    WriteSetChange, U64,
+};
+use poem_openapi::{Object, Union};
+use serde::{Deserialize, Serialize};
+
+/// A simulated transaction, along with a preview of everything it would do
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct TransactionSimulationPreview {
+    /// The simulated transaction, as returned by the SimulateTransaction API
+    pub transaction: UserTransaction,
+    /// Every piece of state the transaction would change, with its value before and after
+    pub state_diff: Vec<StateDiff>,
+    /// Events the transaction would emit, in order
+    pub events: Vec<Event>,
+    /// Where the gas would go
+    ///
+    /// Not set if the transaction is discarded before it starts executing
+    pub gas_breakdown: Option<GasBreakdown>,
+}
+
+/// Result of simulating a bundle of transactions, one after the other
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct TransactionsBundleSimulationResult {
+    /// The simulated transactions, in order. Each one ran on the state left by the previous ones.
+    pub transactions: Vec<UserTransaction>,
+    /// Changes of all the transactions combined, as they would be committed
+    pub write_set: Vec<WriteSetChange>,
+}
+
+/// Change to a single piece of state by a simulated transaction
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Union)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[oai(one_of, discriminator_name = "type", rename_all = "snake_case")]
+pub enum StateDiff {
+    Resource(ResourceDiff),
+    Module(ModuleDiff),
+    TableItem(TableItemDiff),
+}
+
+/// Change to a resource
+///
+/// Resources in resource groups are diffed one by one, and unchanged members of the group
+/// are left out.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct ResourceDiff {
+    pub address: Address,
+    pub state_key_hash: String,
+    pub resource_type: MoveStructTag,
+    /// The resource before the transaction, if it existed
+    pub before: Option<MoveResource>,
+    /// The resource after the transaction, if it still exists
+    pub after: Option<MoveResource>,
+}
+
+/// Change to a module
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct ModuleDiff {
+    pub address: Address,
+    pub state_key_hash: String,
+    pub module: MoveModuleId,
+    /// The module bytecode before the transaction, if it existed
+    pub before: Option<HexEncodedBytes>,
+    /// The module bytecode after the transaction, if it still exists
+    pub after: Option<HexEncodedBytes>,
+}
+
+/// Change to a table item
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct TableItemDiff {
+    pub state_key_hash: String,
+    pub handle: HexEncodedBytes,
+    pub key: HexEncodedBytes,
+    /// The BCS encoded value before the transaction, if it existed
+    pub before: Option<HexEncodedBytes>,
+    /// The BCS encoded value after the transaction, if it still exists
+    pub after: Option<HexEncodedBytes>,
+    /// The decoded key and values, only possible to populate if the table indexer is enabled
+    /// for this node
+    pub decoded: Option<DecodedTableItemDiff>,
+}
+
+/// Decoded key and values of a table item change
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct DecodedTableItemDiff {
+    /// Key of table in JSON
+    pub key: serde_json::Value,
+    /// Type of key
+    pub key_type: String,
+    /// Value before the transaction in JSON
+    pub before: Option<serde_json::Value>,
+    /// Value after the transaction in JSON
+    pub after: Option<serde_json::Value>,
+    /// Type of value
+    pub value_type: String,
+}
+
+/// Breakdown of the gas used by a simulated transaction, by category
+///
+/// Gas costs are in internal gas units, which are `gas_scaling_factor` times smaller than the
+/// gas units `gas_used` is expressed in. Storage fees are in octas, and aren't charged in gas.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct GasBreakdown {
+    pub gas_scaling_factor: U64,
+    /// Total execution and IO gas, including the intrinsic cost
+    pub execution_and_io: U64,
+    /// Intrinsic cost of the transaction, charged based on its size
+    pub intrinsic: U64,
+    /// Gas used by bytecode instructions and native functions, most expensive first
+    pub execution: Vec<GasCost>,
+    /// Gas used by storage reads, most expensive first
+    pub storage_reads: Vec<GasCost>,
+    /// Gas used by storage writes, most expensive first
+    pub storage_writes: Vec<GasCost>,
+    /// Total storage fee in octas
+    pub storage_fee: U64,
+    /// Storage fee in octas for the bytes of the transaction itself
+    pub transaction_storage_fee: U64,
+    /// Storage fee in octas for the events emitted
+    pub event_storage_fee: U64,
+}
+
+/// Aggregated gas cost of one kind of operation
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct GasCost {
+    /// Name of the operation, e.g. a bytecode instruction, a native function or a resource type
+    pub name: String,
+    /// Number of times the operation was performed
+    pub count: U64,
+    /// Total cost in internal gas units
+    pub cost: U64,
+}
@@ -1399,18 +1399,49 @@ impl AptosVM {
         txn: &SignedTransaction,
         executor_view: &impl ExecutorView,
     ) -> (VMStatus, TransactionOutput) {
+        let (vm_status, output, _) = Self::simulate_signed_transaction_with_custom_gas_meter(
+            txn,
+            executor_view,
+            |gas_feature_version, gas_params, storage_gas_params, balance| {
+                Ok(MemoryTrackedGasMeter::new(StandardGasMeter::new(
+                    StandardGasAlgebra::new(
+                        gas_feature_version,
+                        gas_params,
+                        storage_gas_params,
+                        balance,
+                    ),
+                )))
+            },
+        );
+        (vm_status, output)
+    }
+
+    /// Executes a SignedTransaction without performing signature verification, charging gas
+    /// with the gas meter built by `make_gas_meter`, e.g. a gas profiler.
+    ///
+    /// The gas meter is returned, unless the transaction got discarded before it was created.
+    pub fn simulate_signed_transaction_with_custom_gas_meter<G, F>(
+        txn: &SignedTransaction,
+        executor_view: &impl ExecutorView,
+        make_gas_meter: F,
+    ) -> (VMStatus, TransactionOutput, Option<G>)
+    where
+        G: AptosGasMeter,
+        F: FnOnce(u64, VMGasParameters, StorageGasParameters, Gas) -> Result<G, VMStatus>,
+    {
         let vm = AptosVM::new_from_executor_view(executor_view);
         let simulation_vm = AptosSimulationVM(vm);
         let log_context = AdapterLogSchema::new(executor_view.id(), 0);
 
         let resolver = simulation_vm.0.as_move_resolver(executor_view);
-        let (vm_status, vm_output) =
-            simulation_vm.simulate_signed_transaction(&resolver, txn, &log_context);
+        let (vm_status, vm_output, gas_meter) =
+            simulation_vm.simulate_signed_transaction(&resolver, txn, &log_context, make_gas_meter);
         (
             vm_status,
             vm_output
                 .try_into_transaction_output(&resolver)
                 .expect("Simulation cannot fail"),
+            gas_meter,
         )
     }
 
@@ -1835,16 +1866,26 @@ impl AptosSimulationVM {
         )
     }
 
-    fn simulate_signed_transaction(
+    fn simulate_signed_transaction<G, F>(
         &self,
         resolver: &impl AptosMoveResolver,
         txn: &SignedTransaction,
         log_context: &AdapterLogSchema,
-    ) -> (VMStatus, VMOutput) {
+        make_gas_meter: F,
+    ) -> (VMStatus, VMOutput, Option<G>)
+    where
+        G: AptosGasMeter,
+        F: FnOnce(u64, VMGasParameters, StorageGasParameters, Gas) -> Result<G, VMStatus>,
+    {
+        let discarded = |err| {
+            let (vm_status, output) = discard_error_vm_status(err);
+            (vm_status, output, None)
+        };
+
         // simulation transactions should not carry valid signatures, otherwise malicious fullnodes
         // may execute them without user's explicit permission.
         if txn.signature_is_valid() {
-            return discard_error_vm_status(VMStatus::error(StatusCode::INVALID_SIGNATURE, None));
+            return discarded(VMStatus::error(StatusCode::INVALID_SIGNATURE, None));
         }
 
         // Revalidate the transaction.
@@ -1853,25 +1894,27 @@ impl AptosSimulationVM {
         if let Err(err) =
             self.validate_simulated_transaction(&mut session, resolver, txn, &txn_data, log_context)
         {
-            return discard_error_vm_status(err);
+            return discarded(err);
         };
 
         let gas_params = match self.0 .0.get_gas_parameters(log_context) {
-            Err(err) => return discard_error_vm_status(err),
+            Err(err) => return discarded(err),
             Ok(s) => s,
         };
         let storage_gas_params = match self.0 .0.get_storage_gas_parameters(log_context) {
-            Err(err) => return discard_error_vm_status(err),
+            Err(err) => return discarded(err),
             Ok(s) => s,
         };
 
-        let mut gas_meter =
-            MemoryTrackedGasMeter::new(StandardGasMeter::new(StandardGasAlgebra::new(
-                self.0 .0.get_gas_feature_version(),
-                gas_params.vm.clone(),
-                storage_gas_params.clone(),
-                txn_data.max_gas_amount(),
-            )));
+        let mut gas_meter = match make_gas_meter(
+            self.0 .0.get_gas_feature_version(),
+            gas_params.vm.clone(),
+            storage_gas_params.clone(),
+            txn_data.max_gas_amount(),
+        ) {
+            Err(err) => return discarded(err),
+            Ok(gas_meter) => gas_meter,
+        };
 
         let mut new_published_modules_loaded = false;
         let result = match txn.payload() {
@@ -1941,7 +1984,7 @@ impl AptosSimulationVM {
             ),
         };
 
-        match result {
+        let (vm_status, output) = match result {
             Ok(output) => output,
             Err(err) => {
                 // Invalidate the loader cache in case there was a new module loaded from a module
@@ -1973,6 +2016,7 @@ impl AptosSimulationVM {
                     (vm_status, output)
                 }
             },
-        }
+        };
+        (vm_status, output, Some(gas_meter))
     }
 }
@@ -585,6 +585,13 @@ pub fn setup_environment_and_start_node(
         db_rw.reader.clone(),
     );
 
+    // Subscribe the API to commit notifications (to feed its streaming endpoints)
+    let api_commit_listener = if node_config.api.enabled && node_config.api.subscriptions_enabled {
+        Some(event_subscription_service.subscribe_to_commits()?)
+    } else {
+        None
+    };
+
     // Start state sync and get the notification endpoints for mempool and consensus
     let (state_sync_runtimes, mempool_listener, consensus_notifier) =
         state_sync::start_state_sync_and_get_notification_handles(
@@ -597,7 +604,7 @@ pub fn setup_environment_and_start_node(
 
     // Bootstrap the API and indexer
     let (mempool_client_receiver, api_runtime, indexer_runtime, indexer_grpc_runtime) =
-        services::bootstrap_api_and_indexer(&node_config, aptos_db, chain_id)?;
+        services::bootstrap_api_and_indexer(&node_config, aptos_db, chain_id, api_commit_listener)?;
 
     // Create mempool and get the consensus to mempool sender
     let (mempool_runtime, consensus_to_mempool_sender) =
@@ -12,6 +12,7 @@ use crate::{
 use aptos_types::chain_id::ChainId;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use url::Url;
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(default, deny_unknown_fields)]
@@ -33,6 +34,27 @@ pub struct ApiConfig {
     /// Enables failpoints for error testing
     #[serde(default = "default_disabled")]
     pub failpoints_enabled: bool,
+    /// Enables the mempool introspection endpoints, used by operators to debug stuck transactions
+    #[serde(default = "default_disabled")]
+    pub mempool_introspection_enabled: bool,
+    /// Enables the GraphQL read API, served next to the REST API
+    #[serde(default = "default_disabled")]
+    pub graphql_enabled: bool,
+    /// Enables the server-sent event streams of newly committed transactions, events and blocks
+    #[serde(default = "default_disabled")]
+    pub subscriptions_enabled: bool,
+    /// Maximum number of subscription streams that can be open at the same time
+    pub max_subscriptions: usize,
+    /// Maximum nesting depth of a GraphQL query
+    pub graphql_max_depth: usize,
+    /// Maximum cost of a GraphQL query. Every field costs 1, and list fields cost the
+    /// number of requested items times the cost of an item.
+    pub graphql_max_complexity: usize,
+    /// URL of an archival node to forward requests for pruned data to. When set, requests that
+    /// this node answers with 410 Gone are replayed against the same path on the archival node,
+    /// and its response is returned instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archival_upstream_url: Option<Url>,
     /// Enables JSON output of APIs that support it
     #[serde(default = "default_enabled")]
     pub json_output_enabled: bool,
@@ -50,6 +72,10 @@ pub struct ApiConfig {
     pub transaction_simulation_enabled: bool,
     /// Maximum number of transactions that can be sent with the Batch submit API
     pub max_submit_transaction_batch_size: usize,
+    /// Maximum number of reads that can be sent with the Batch read API
+    pub max_batch_read_size: usize,
+    /// Maximum number of transactions that can be simulated together as a bundle
+    pub max_simulation_bundle_size: usize,
     /// Maximum page size for transaction paginated APIs
     pub max_transactions_page_size: u16,
     /// Maximum page size for event paginated APIs
@@ -78,9 +104,14 @@ const DEFAULT_ADDRESS: &str = "127.0.0.1";
 const DEFAULT_PORT: u16 = 8080;
 const DEFAULT_REQUEST_CONTENT_LENGTH_LIMIT: u64 = 8 * 1024 * 1024; // 8 MB
 pub const DEFAULT_MAX_SUBMIT_TRANSACTION_BATCH_SIZE: usize = 10;
+pub const DEFAULT_MAX_BATCH_READ_SIZE: usize = 100;
+pub const DEFAULT_MAX_SIMULATION_BUNDLE_SIZE: usize = 10;
 pub const DEFAULT_MAX_PAGE_SIZE: u16 = 100;
 const DEFAULT_MAX_ACCOUNT_RESOURCES_PAGE_SIZE: u16 = 9999;
 const DEFAULT_MAX_ACCOUNT_MODULES_PAGE_SIZE: u16 = 9999;
+const DEFAULT_MAX_SUBSCRIPTIONS: usize = 100;
+const DEFAULT_GRAPHQL_MAX_DEPTH: usize = 10;
+const DEFAULT_GRAPHQL_MAX_COMPLEXITY: usize = 2_000;
 const DEFAULT_MAX_VIEW_GAS: u64 = 2_000_000; // We keep this value the same as the max number of gas allowed for one single transaction defined in aptos-gas.
 
 fn default_enabled() -> bool {
@@ -102,12 +133,21 @@ impl Default for ApiConfig {
             tls_key_path: None,
             content_length_limit: None,
             failpoints_enabled: default_disabled(),
+            mempool_introspection_enabled: default_disabled(),
+            graphql_enabled: default_disabled(),
+            subscriptions_enabled: default_disabled(),
+            max_subscriptions: DEFAULT_MAX_SUBSCRIPTIONS,
+            graphql_max_depth: DEFAULT_GRAPHQL_MAX_DEPTH,
+            graphql_max_complexity: DEFAULT_GRAPHQL_MAX_COMPLEXITY,
+            archival_upstream_url: None,
             bcs_output_enabled: default_enabled(),
             json_output_enabled: default_enabled(),
             encode_submission_enabled: default_enabled(),
             transaction_submission_enabled: default_enabled(),
             transaction_simulation_enabled: default_enabled(),
             max_submit_transaction_batch_size: DEFAULT_MAX_SUBMIT_TRANSACTION_BATCH_SIZE,
+            max_batch_read_size: DEFAULT_MAX_BATCH_READ_SIZE,
+            max_simulation_bundle_size: DEFAULT_MAX_SIMULATION_BUNDLE_SIZE,
             max_transactions_page_size: DEFAULT_MAX_PAGE_SIZE,
             max_events_page_size: DEFAULT_MAX_PAGE_SIZE,
             max_account_resources_page_size: DEFAULT_MAX_ACCOUNT_RESOURCES_PAGE_SIZE,
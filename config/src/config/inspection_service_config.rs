@@ -18,6 +18,7 @@ pub struct InspectionServiceConfig {
     pub address: String,
     pub port: u16,
     pub expose_configuration: bool,
+    pub expose_consensus_timeline: bool,
     pub expose_peer_information: bool,
     pub expose_system_information: bool,
 }
@@ -28,6 +29,7 @@ impl Default for InspectionServiceConfig {
             address: "0.0.0.0".to_string(),
             port: 9101,
             expose_configuration: false,
+            expose_consensus_timeline: false,
             expose_peer_information: true,
             expose_system_information: true,
         }
@@ -82,6 +84,11 @@ impl ConfigOptimizer for InspectionServiceConfig {
                 modified_config = true;
             }
 
+            if local_inspection_config_yaml["expose_consensus_timeline"].is_null() {
+                inspection_service_config.expose_consensus_timeline = true;
+                modified_config = true;
+            }
+
             if local_inspection_config_yaml["expose_system_information"].is_null() {
                 inspection_service_config.expose_system_information = true;
                 modified_config = true;
@@ -102,6 +109,7 @@ mod tests {
         let mut node_config = NodeConfig {
             inspection_service: InspectionServiceConfig {
                 expose_configuration: false,
+                expose_consensus_timeline: false,
                 expose_system_information: false,
                 ..Default::default()
             },
@@ -118,8 +126,9 @@ mod tests {
         .unwrap();
         assert!(!modified_config);
 
-        // Verify both endpoints are still disabled
+        // Verify all endpoints are still disabled
         assert!(!node_config.inspection_service.expose_configuration);
+        assert!(!node_config.inspection_service.expose_consensus_timeline);
         assert!(!node_config.inspection_service.expose_system_information);
     }
 
@@ -129,6 +138,7 @@ mod tests {
         let mut node_config = NodeConfig {
             inspection_service: InspectionServiceConfig {
                 expose_configuration: false,
+                expose_consensus_timeline: false,
                 expose_system_information: false,
                 ..Default::default()
             },
@@ -145,8 +155,9 @@ mod tests {
         .unwrap();
         assert!(modified_config);
 
-        // Verify both endpoints are now enabled
+        // Verify all endpoints are now enabled
         assert!(node_config.inspection_service.expose_configuration);
+        assert!(node_config.inspection_service.expose_consensus_timeline);
         assert!(node_config.inspection_service.expose_system_information);
     }
 
@@ -156,6 +167,7 @@ mod tests {
         let mut node_config = NodeConfig {
             inspection_service: InspectionServiceConfig {
                 expose_configuration: false,
+                expose_consensus_timeline: false,
                 expose_system_information: false,
                 ..Default::default()
             },
@@ -181,8 +193,9 @@ mod tests {
         .unwrap();
         assert!(modified_config);
 
-        // Verify only the system information endpoint is now enabled
+        // Verify only the configuration endpoint is still disabled
         assert!(!node_config.inspection_service.expose_configuration);
+        assert!(node_config.inspection_service.expose_consensus_timeline);
         assert!(node_config.inspection_service.expose_system_information);
     }
 
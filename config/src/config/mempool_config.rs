@@ -53,6 +53,15 @@ pub struct MempoolConfig {
     pub broadcast_buckets: Vec<u64>,
     pub eager_expire_threshold_ms: Option<u64>,
     pub eager_expire_time_ms: u64,
+    /// Minimum percentage by which a transaction must increase the gas unit price to replace a
+    /// transaction with the same sender and sequence number already in the Mempool.
+    pub replace_by_fee_min_gas_price_bump_pct: u64,
+    /// Journal accepted transactions under the data directory, so they survive a node restart.
+    pub persist_transactions: bool,
+    /// Upper bound on the time spent re-validating and re-inserting journaled transactions on startup.
+    ///
+    /// Transactions that could not be replayed within this time are dropped.
+    pub persistence_max_replay_ms: u64,
 }
 
 impl Default for MempoolConfig {
@@ -77,6 +86,9 @@ impl Default for MempoolConfig {
             broadcast_buckets: DEFAULT_BUCKETS.to_vec(),
             eager_expire_threshold_ms: Some(10_000),
             eager_expire_time_ms: 3_000,
+            replace_by_fee_min_gas_price_bump_pct: 10,
+            persist_transactions: false,
+            persistence_max_replay_ms: 10_000,
         }
     }
 }
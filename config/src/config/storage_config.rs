@@ -112,6 +112,8 @@ pub struct StorageConfig {
     /// Try to enable the internal indexer. The indexer expects to have seen all transactions
     /// since genesis. To recover operation after data loss, or to bootstrap a node in fast sync
     /// mode, the indexer db needs to be copied in from another node.
+    /// Besides table info, the indexer keeps the transactions that touched each account, which
+    /// backs the account transaction history API.
     pub enable_indexer: bool,
 }
 
@@ -15,6 +15,7 @@ pub mod proof_of_store;
 pub mod proposal_msg;
 pub mod quorum_cert;
 pub mod request_response;
+pub mod round_timeline;
 pub mod safety_data;
 pub mod sync_info;
 pub mod timeout_2chain;
@@ -0,0 +1,245 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::common::{Author, Round};
+use aptos_infallible::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Number of rounds kept by default in the timeline of a validator
+pub const DEFAULT_MAX_TIMELINE_ROUNDS: usize = 1_000;
+
+/// A step of a round, or of the block proposed in that round, observed by a validator.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundEvent {
+    /// The validator entered the round
+    RoundStarted,
+    /// The proposal for the round was received
+    ProposalReceived,
+    /// The transactions of the block were fetched from the payload (e.g. quorum store batches)
+    PayloadFetched,
+    /// The validator voted for the block
+    VoteSent,
+    /// The validator aggregated a QC for the block
+    QcFormed,
+    /// The validator timed out in the round
+    RoundTimeout,
+    /// The block was ordered
+    Ordered,
+    /// The block was executed
+    Executed,
+    /// The validator signed the commit vote for the block
+    Signed,
+    /// The block was committed
+    Committed,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimelineEvent {
+    pub event: RoundEvent,
+    pub timestamp_usecs: u64,
+}
+
+/// The events observed by a validator for a round, in the order they were recorded.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoundTimeline {
+    pub epoch: u64,
+    pub round: Round,
+    pub events: Vec<TimelineEvent>,
+}
+
+/// The timeline of a validator, as exported by its inspection service.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ValidatorTimeline {
+    pub author: Option<Author>,
+    pub rounds: Vec<RoundTimeline>,
+}
+
+/// Records the timeline of the most recent rounds. Only the first occurrence of an event is
+/// kept for a round, e.g. the first vote and not the timeout vote that repeats it.
+pub struct RoundTimelineRecorder {
+    max_rounds: usize,
+    rounds: Mutex<BTreeMap<(u64, Round), Vec<TimelineEvent>>>,
+}
+
+impl RoundTimelineRecorder {
+    pub fn new(max_rounds: usize) -> Self {
+        assert!(max_rounds > 0, "max_rounds must be positive");
+        Self {
+            max_rounds,
+            rounds: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn record(&self, epoch: u64, round: Round, event: RoundEvent, timestamp_usecs: u64) {
+        let mut rounds = self.rounds.lock();
+        let key = (epoch, round);
+        if rounds.len() >= self.max_rounds && !rounds.contains_key(&key) {
+            match rounds.keys().next() {
+                // Too old to be kept
+                Some(oldest) if *oldest > key => return,
+                Some(oldest) => {
+                    let oldest = *oldest;
+                    rounds.remove(&oldest);
+                },
+                None => {},
+            }
+        }
+        let events = rounds.entry(key).or_default();
+        if events.iter().all(|e| e.event != event) {
+            events.push(TimelineEvent {
+                event,
+                timestamp_usecs,
+            });
+        }
+    }
+
+    /// The recorded rounds, oldest first.
+    pub fn timelines(&self) -> Vec<RoundTimeline> {
+        self.rounds
+            .lock()
+            .iter()
+            .map(|((epoch, round), events)| RoundTimeline {
+                epoch: *epoch,
+                round: *round,
+                events: events.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Default for RoundTimelineRecorder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TIMELINE_ROUNDS)
+    }
+}
+
+/// An event observed by one of the validators whose timelines were merged.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MergedTimelineEvent {
+    pub author: Option<Author>,
+    pub event: RoundEvent,
+    pub timestamp_usecs: u64,
+    /// Time since the first event of the round, across all validators
+    pub offset_usecs: u64,
+}
+
+/// A round as seen by all the validators, with the events sorted by timestamp.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MergedRoundTimeline {
+    pub epoch: u64,
+    pub round: Round,
+    pub events: Vec<MergedTimelineEvent>,
+    /// For each event, the number of validators that observed it
+    pub event_counts: BTreeMap<RoundEvent, usize>,
+}
+
+/// Merges the timelines of several validators into one timeline per round, ordered by epoch
+/// and round. Timestamps come from the clock of each validator, so the order of events observed
+/// by different validators is only as accurate as the clock synchronization between them.
+pub fn merge_timelines(timelines: &[ValidatorTimeline]) -> Vec<MergedRoundTimeline> {
+    let mut rounds: BTreeMap<(u64, Round), Vec<MergedTimelineEvent>> = BTreeMap::new();
+    for timeline in timelines {
+        for round_timeline in &timeline.rounds {
+            rounds
+                .entry((round_timeline.epoch, round_timeline.round))
+                .or_default()
+                .extend(round_timeline.events.iter().map(|e| MergedTimelineEvent {
+                    author: timeline.author,
+                    event: e.event,
+                    timestamp_usecs: e.timestamp_usecs,
+                    offset_usecs: 0,
+                }));
+        }
+    }
+
+    rounds
+        .into_iter()
+        .map(|((epoch, round), mut events)| {
+            events.sort_by_key(|e| (e.timestamp_usecs, e.event));
+            let start = events.first().map_or(0, |e| e.timestamp_usecs);
+            let mut event_counts = BTreeMap::new();
+            let mut seen = HashSet::new();
+            for e in &mut events {
+                e.offset_usecs = e.timestamp_usecs - start;
+                if seen.insert((e.author, e.event)) {
+                    *event_counts.entry(e.event).or_insert(0) += 1;
+                }
+            }
+            MergedRoundTimeline {
+                epoch,
+                round,
+                events,
+                event_counts,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_keeps_most_recent_rounds() {
+        let recorder = RoundTimelineRecorder::new(2);
+        recorder.record(1, 1, RoundEvent::RoundStarted, 10);
+        recorder.record(1, 2, RoundEvent::RoundStarted, 20);
+        recorder.record(1, 2, RoundEvent::VoteSent, 25);
+        recorder.record(1, 2, RoundEvent::VoteSent, 30);
+        recorder.record(2, 1, RoundEvent::RoundStarted, 40);
+        // Older than every kept round
+        recorder.record(1, 1, RoundEvent::Committed, 50);
+
+        let timelines = recorder.timelines();
+        assert_eq!(timelines.len(), 2);
+        assert_eq!((timelines[0].epoch, timelines[0].round), (1, 2));
+        assert_eq!(timelines[0].events, vec![
+            TimelineEvent {
+                event: RoundEvent::RoundStarted,
+                timestamp_usecs: 20,
+            },
+            TimelineEvent {
+                event: RoundEvent::VoteSent,
+                timestamp_usecs: 25,
+            },
+        ]);
+        assert_eq!((timelines[1].epoch, timelines[1].round), (2, 1));
+    }
+
+    #[test]
+    fn test_merge_timelines() {
+        let first = Author::random();
+        let second = Author::random();
+        let timeline = |author, rounds: Vec<(Round, RoundEvent, u64)>| ValidatorTimeline {
+            author: Some(author),
+            rounds: rounds
+                .into_iter()
+                .map(|(round, event, timestamp_usecs)| RoundTimeline {
+                    epoch: 1,
+                    round,
+                    events: vec![TimelineEvent {
+                        event,
+                        timestamp_usecs,
+                    }],
+                })
+                .collect(),
+        };
+        let merged = merge_timelines(&[
+            timeline(first, vec![
+                (2, RoundEvent::ProposalReceived, 110),
+                (3, RoundEvent::RoundStarted, 200),
+            ]),
+            timeline(second, vec![(2, RoundEvent::ProposalReceived, 100)]),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].round, 2);
+        let authors: Vec<_> = merged[0].events.iter().map(|e| e.author).collect();
+        assert_eq!(authors, vec![Some(second), Some(first)]);
+        assert_eq!(merged[0].events[1].offset_usecs, 10);
+        assert_eq!(merged[0].event_counts[&RoundEvent::ProposalReceived], 2);
+        assert_eq!(merged[1].round, 3);
+    }
+}
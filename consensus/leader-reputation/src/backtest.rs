@@ -0,0 +1,713 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Replays committed `NewBlockEvent` history through alternative leader reputation configs, to
+//! estimate how a config change would affect proposer selection before proposing it on chain.
+//!
+//! For every round of the replayed epochs, the proposer the config would have elected is
+//! computed from the actual history, the way `LeaderReputation` does on a validator. The
+//! history itself is not rewritten, so the reputation windows see the proposers and failures
+//! that really happened: the report estimates the first order effect of the config, not the
+//! chain that would have resulted from it.
+
+use crate::{
+    choose_proposer, CandidateWeighting, MetadataBackend, ProposerAndVoterWeighting,
+    ReputationHeuristic, SuccessRateWeighting, WeightedStatsHeuristic,
+};
+use anyhow::{ensure, Result};
+use aptos_consensus_types::common::{Author, Round};
+use aptos_crypto::HashValue;
+use aptos_types::{
+    account_address::AccountAddress, account_config::NewBlockEvent,
+    on_chain_config::LeaderReputationType,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::max,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    ops::RangeInclusive,
+    sync::Arc,
+};
+
+/// Number of proposers listed when a report is displayed
+const DISPLAYED_PROPOSERS: usize = 20;
+
+/// The validator set of an epoch.
+#[derive(Clone, Debug)]
+pub struct EpochValidators {
+    /// The epoch
+    pub epoch: u64,
+    /// Validators, in the order of the validator set
+    pub validators: Vec<Author>,
+    /// Voting power of each validator
+    pub voting_powers: Vec<u64>,
+}
+
+/// A committed block.
+#[derive(Clone, Debug)]
+pub struct HistoricalBlock {
+    /// The event emitted when the block was committed
+    pub event: NewBlockEvent,
+    /// Root hash of the transaction accumulator at the version of the event, used as part of
+    /// the seed of the election by `ProposerAndVoterV2`. Without it, the seed differs from the
+    /// one used on chain, which only changes which proposer is picked among the weighted ones.
+    pub accumulator_root_hash: Option<HashValue>,
+}
+
+/// The `CandidateWeighting` turning the statistics of each candidate into its weight. The
+/// weights it is given come from the `leader_reputation_type` of the config.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BacktestWeighting {
+    /// `ProposerAndVoterWeighting`, the weighting used on chain
+    #[default]
+    ProposerAndVoter,
+    /// `SuccessRateWeighting`
+    SuccessRate,
+}
+
+/// An alternative leader reputation config to replay the history with.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BacktestConfig {
+    /// Name of the config in the report
+    pub name: String,
+    /// The config to evaluate
+    pub leader_reputation_type: LeaderReputationType,
+    /// The weighting of the candidates, the one used on chain when omitted
+    #[serde(default)]
+    pub weighting: BacktestWeighting,
+    /// Number of most recent rounds excluded from the reputation window
+    pub exclude_round: u64,
+}
+
+/// A round of the history, as it happened on chain.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct HistoricalRound {
+    epoch: u64,
+    round: Round,
+    /// Unknown for failed rounds older than the failed proposers stored in the next block
+    proposer: Option<Author>,
+    failed: bool,
+}
+
+/// Committed history of one or more complete epochs.
+pub struct BacktestHistory {
+    epochs: BTreeMap<u64, EpochValidators>,
+    blocks: Arc<Vec<HistoricalBlock>>,
+}
+
+impl BacktestHistory {
+    /// Blocks must be ordered by epoch and round, and cover every round of their epochs, with
+    /// the validator set of each of these epochs.
+    pub fn new(epochs: Vec<EpochValidators>, blocks: Vec<HistoricalBlock>) -> Result<Self> {
+        let epochs: BTreeMap<_, _> = epochs.into_iter().map(|e| (e.epoch, e)).collect();
+        for validators in epochs.values() {
+            ensure!(
+                validators.validators.len() == validators.voting_powers.len(),
+                "Epoch {} has {} validators but {} voting powers",
+                validators.epoch,
+                validators.validators.len(),
+                validators.voting_powers.len()
+            );
+        }
+        for window in blocks.windows(2) {
+            let (previous, next) = (&window[0].event, &window[1].event);
+            ensure!(
+                (previous.epoch(), previous.round()) < (next.epoch(), next.round()),
+                "Blocks are not ordered, epoch {} round {} is followed by epoch {} round {}",
+                previous.epoch(),
+                previous.round(),
+                next.epoch(),
+                next.round()
+            );
+        }
+        for block in &blocks {
+            ensure!(
+                epochs.contains_key(&block.event.epoch()),
+                "Missing the validators of epoch {}",
+                block.event.epoch()
+            );
+        }
+        Ok(Self {
+            epochs,
+            blocks: Arc::new(blocks),
+        })
+    }
+
+    /// Reports the proposers that were elected on chain.
+    pub fn observed_report(&self, epochs: RangeInclusive<u64>) -> BacktestReport {
+        let rounds = self.rounds(&epochs);
+        let elected: Vec<_> = rounds.iter().map(|r| r.proposer).collect();
+        let mut report = self.report("observed".to_string(), &rounds, &elected);
+        report.expected_failed_rounds = report.failed_rounds as f64;
+        report
+    }
+
+    /// Replays the rounds of the given epochs through the config, and reports the proposers it
+    /// would have elected.
+    pub fn backtest(&self, config: &BacktestConfig, epochs: RangeInclusive<u64>) -> BacktestReport {
+        let rounds = self.rounds(&epochs);
+        let mut elected = Vec::with_capacity(rounds.len());
+        let mut epoch_rounds = rounds.iter().peekable();
+        while let Some(epoch) = epoch_rounds.peek().map(|r| r.epoch) {
+            let election = self.proposer_election(config, epoch);
+            while let Some(round) = epoch_rounds.next_if(|r| r.epoch == epoch) {
+                elected.push(Some(election.proposer(round.round)));
+            }
+        }
+
+        let failure_rates = failure_rates(&rounds);
+        let mean_failure_rate = if rounds.is_empty() {
+            0.0
+        } else {
+            rounds.iter().filter(|r| r.failed).count() as f64 / rounds.len() as f64
+        };
+        let mut report = self.report(config.name.clone(), &rounds, &elected);
+        for (round, proposer) in rounds.iter().zip(elected.iter().flatten()) {
+            if round.failed && round.proposer.map_or(false, |p| p != *proposer) {
+                report.failed_rounds_avoided += 1;
+            }
+            report.expected_failed_rounds += failure_rates
+                .get(proposer)
+                .copied()
+                .unwrap_or(mean_failure_rate);
+        }
+        report
+    }
+
+    fn proposer_election(&self, config: &BacktestConfig, epoch: u64) -> EpochElection {
+        let validators = &self.epochs[&epoch];
+        let proposer_and_voter_config = match &config.leader_reputation_type {
+            LeaderReputationType::ProposerAndVoter(config)
+            | LeaderReputationType::ProposerAndVoterV2(config) => config,
+        };
+        let proposer_window_size = validators.validators.len()
+            * proposer_and_voter_config.proposer_window_num_validators_multiplier;
+        let voter_window_size = validators.validators.len()
+            * proposer_and_voter_config.voter_window_num_validators_multiplier;
+        let from_stale_end = config
+            .leader_reputation_type
+            .use_reputation_window_from_stale_end();
+        let heuristic: Box<dyn ReputationHeuristic> = match config.weighting {
+            BacktestWeighting::ProposerAndVoter => weighted_stats_heuristic(
+                ProposerAndVoterWeighting::new(
+                    proposer_and_voter_config.active_weight,
+                    proposer_and_voter_config.inactive_weight,
+                    proposer_and_voter_config.failed_weight,
+                    proposer_and_voter_config.failure_threshold_percent,
+                ),
+                voter_window_size,
+                proposer_window_size,
+                from_stale_end,
+            ),
+            BacktestWeighting::SuccessRate => weighted_stats_heuristic(
+                SuccessRateWeighting::new(
+                    proposer_and_voter_config.active_weight,
+                    proposer_and_voter_config.inactive_weight,
+                    proposer_and_voter_config.failed_weight,
+                ),
+                voter_window_size,
+                proposer_window_size,
+                from_stale_end,
+            ),
+        };
+        let epoch_to_proposers = self
+            .epochs
+            .range(..=epoch)
+            .rev()
+            .take(proposer_and_voter_config.use_history_from_previous_epoch_max_count as usize + 1)
+            .map(|(epoch, validators)| (*epoch, validators.validators.clone()))
+            .collect();
+        let voting_powers = if proposer_and_voter_config.weight_by_voting_power {
+            validators.voting_powers.clone()
+        } else {
+            vec![1; validators.validators.len()]
+        };
+        EpochElection {
+            epoch,
+            epoch_to_proposers,
+            voting_powers,
+            backend: HistoryBackend {
+                window_size: max(proposer_window_size, voter_window_size),
+                blocks: self.blocks.clone(),
+            },
+            heuristic,
+            exclude_round: config.exclude_round,
+            use_root_hash: config.leader_reputation_type.use_root_hash_for_seed(),
+        }
+    }
+
+    /// Every round of the given epochs, in order. A round without a block failed, as did the
+    /// round of a nil block. The proposers of the failed rounds come from the
+    /// `failed_proposer_indices` of the next block, which only stores the most recent ones.
+    fn rounds(&self, epochs: &RangeInclusive<u64>) -> Vec<HistoricalRound> {
+        let mut rounds = vec![];
+        let mut previous: Option<&NewBlockEvent> = None;
+        for block in self.blocks.iter() {
+            let event = &block.event;
+            let previous_round = previous
+                .filter(|p| p.epoch() == event.epoch())
+                .map_or(0, |p| p.round());
+            previous = Some(event);
+            if !epochs.contains(&event.epoch()) {
+                continue;
+            }
+
+            let validators = &self.epochs[&event.epoch()].validators;
+            let nil_block = event.proposer() == AccountAddress::ZERO;
+            let last_failed_round = if nil_block {
+                event.round()
+            } else {
+                event.round().saturating_sub(1)
+            };
+            let failed_proposers = event.failed_proposer_indices();
+            for round in previous_round + 1..=last_failed_round {
+                // The failed proposers are those of the most recent failed rounds
+                let index = (failed_proposers.len() as u64)
+                    .checked_sub(last_failed_round - round + 1)
+                    .map(|i| failed_proposers[i as usize] as usize);
+                rounds.push(HistoricalRound {
+                    epoch: event.epoch(),
+                    round,
+                    proposer: index.and_then(|i| validators.get(i).copied()),
+                    failed: true,
+                });
+            }
+            if !nil_block {
+                rounds.push(HistoricalRound {
+                    epoch: event.epoch(),
+                    round: event.round(),
+                    proposer: Some(event.proposer()),
+                    failed: false,
+                });
+            }
+        }
+        rounds
+    }
+
+    fn report(
+        &self,
+        name: String,
+        rounds: &[HistoricalRound],
+        elected: &[Option<Author>],
+    ) -> BacktestReport {
+        let observed_failure_rates = failure_rates(rounds);
+        let mut proposers: HashMap<Author, (u64, f64)> = HashMap::new();
+        for (round, proposer) in rounds.iter().zip(elected) {
+            if let Some(proposer) = proposer {
+                proposers.entry(*proposer).or_default().0 += 1;
+            }
+            // The share of the round each validator would get if proposers were only weighted
+            // by voting power
+            let validators = &self.epochs[&round.epoch];
+            let total_voting_power: u128 =
+                validators.voting_powers.iter().map(|v| *v as u128).sum();
+            for (validator, voting_power) in validators
+                .validators
+                .iter()
+                .zip(validators.voting_powers.iter())
+            {
+                if total_voting_power > 0 {
+                    proposers.entry(*validator).or_default().1 +=
+                        *voting_power as f64 / total_voting_power as f64;
+                }
+            }
+        }
+
+        let num_rounds = rounds.len() as u64;
+        let mut proposers: Vec<_> = proposers
+            .into_iter()
+            .map(|(author, (elected_rounds, stake_rounds))| ProposerShare {
+                author,
+                rounds: elected_rounds,
+                share: ratio(elected_rounds as f64, num_rounds as f64),
+                stake_share: ratio(stake_rounds, num_rounds as f64),
+                observed_failure_rate: observed_failure_rates.get(&author).copied(),
+            })
+            .collect();
+        proposers.sort_by(|a, b| b.rounds.cmp(&a.rounds).then(a.author.cmp(&b.author)));
+
+        BacktestReport {
+            name,
+            rounds: num_rounds,
+            failed_rounds: rounds.iter().filter(|r| r.failed).count() as u64,
+            failed_rounds_avoided: 0,
+            expected_failed_rounds: 0.0,
+            matching_rounds: rounds
+                .iter()
+                .zip(elected)
+                .filter(|(round, proposer)| {
+                    round.proposer.is_some() && round.proposer == **proposer
+                })
+                .count() as u64,
+            stake_deviation: proposers
+                .iter()
+                .map(|p| (p.share - p.stake_share).abs())
+                .sum::<f64>()
+                / 2.0,
+            gini_coefficient: gini_coefficient(
+                proposers
+                    .iter()
+                    .filter(|p| p.stake_share > 0.0)
+                    .map(|p| p.share / p.stake_share)
+                    .collect(),
+            ),
+            proposers,
+        }
+    }
+}
+
+/// The rate of rounds each validator failed to propose in, among the rounds it was the
+/// proposer of.
+fn failure_rates(rounds: &[HistoricalRound]) -> HashMap<Author, f64> {
+    let mut counts: HashMap<Author, (u64, u64)> = HashMap::new();
+    for round in rounds {
+        if let Some(proposer) = round.proposer {
+            let (failed, total) = counts.entry(proposer).or_default();
+            *failed += u64::from(round.failed);
+            *total += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(author, (failed, total))| (author, failed as f64 / total as f64))
+        .collect()
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 {
+        numerator / denominator
+    } else {
+        0.0
+    }
+}
+
+/// 0 when all the values are equal, and close to 1 when one value holds the total.
+fn gini_coefficient(mut values: Vec<f64>) -> f64 {
+    let total: f64 = values.iter().sum();
+    if values.is_empty() || total <= 0.0 {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = values.len() as f64;
+    let weighted_sum: f64 = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i + 1) as f64 * v)
+        .sum();
+    2.0 * weighted_sum / (n * total) - (n + 1.0) / n
+}
+
+fn weighted_stats_heuristic<W: CandidateWeighting + 'static>(
+    weighting: W,
+    voter_window_size: usize,
+    proposer_window_size: usize,
+    reputation_window_from_stale_end: bool,
+) -> Box<dyn ReputationHeuristic> {
+    Box::new(WeightedStatsHeuristic::with_weighting(
+        AccountAddress::ZERO,
+        weighting,
+        voter_window_size,
+        proposer_window_size,
+        reputation_window_from_stale_end,
+    ))
+}
+
+/// Elects the proposers of an epoch like `LeaderReputation` does, without its chain health
+/// metrics.
+struct EpochElection {
+    epoch: u64,
+    epoch_to_proposers: HashMap<u64, Vec<Author>>,
+    voting_powers: Vec<u64>,
+    backend: HistoryBackend,
+    heuristic: Box<dyn ReputationHeuristic>,
+    exclude_round: u64,
+    use_root_hash: bool,
+}
+
+impl EpochElection {
+    fn proposer(&self, round: Round) -> Author {
+        let target_round = round.saturating_sub(self.exclude_round);
+        let (window, root_hash) = self.backend.get_block_metadata(self.epoch, target_round);
+        let weights = self
+            .heuristic
+            .get_weights(self.epoch, &self.epoch_to_proposers, &window);
+        let index = choose_proposer(
+            self.epoch,
+            round,
+            &weights,
+            &self.voting_powers,
+            self.use_root_hash.then_some(root_hash),
+        );
+        self.epoch_to_proposers[&self.epoch][index]
+    }
+}
+
+/// Serves the committed history to the election, like `AptosDBBackend` does from the DB.
+struct HistoryBackend {
+    window_size: usize,
+    blocks: Arc<Vec<HistoricalBlock>>,
+}
+
+impl MetadataBackend for HistoryBackend {
+    fn get_block_metadata(
+        &self,
+        target_epoch: u64,
+        target_round: Round,
+    ) -> (Vec<NewBlockEvent>, HashValue) {
+        let end = self.blocks.partition_point(|block| {
+            (block.event.epoch(), block.event.round()) <= (target_epoch, target_round)
+        });
+        let start = end.saturating_sub(self.window_size);
+        let window = &self.blocks[start..end];
+        let root_hash = window
+            .last()
+            .and_then(|block| block.accumulator_root_hash)
+            .unwrap_or_else(HashValue::zero);
+        (
+            window
+                .iter()
+                .rev()
+                .map(|block| block.event.clone())
+                .collect(),
+            root_hash,
+        )
+    }
+}
+
+/// How often a validator was elected.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProposerShare {
+    /// The validator
+    pub author: Author,
+    /// Number of rounds the validator was elected in
+    pub rounds: u64,
+    /// Fraction of the rounds the validator was elected in
+    pub share: f64,
+    /// Fraction of the rounds the validator would be elected in, if proposers were only
+    /// weighted by voting power
+    pub stake_share: f64,
+    /// Fraction of the rounds the validator failed in, among the ones it was the proposer of
+    /// on chain
+    pub observed_failure_rate: Option<f64>,
+}
+
+/// The outcome of replaying the history with a config.
+#[derive(Clone, Debug, Serialize)]
+pub struct BacktestReport {
+    /// Name of the config
+    pub name: String,
+    /// Number of rounds replayed
+    pub rounds: u64,
+    /// Number of rounds that failed on chain
+    pub failed_rounds: u64,
+    /// Number of rounds that failed on chain, for which the config elects another proposer
+    /// than the one that failed
+    pub failed_rounds_avoided: u64,
+    /// Number of failed rounds expected with the config, assuming each validator fails at the
+    /// rate observed on chain, or at the average rate when it never was the proposer
+    pub expected_failed_rounds: f64,
+    /// Number of rounds for which the config elects the proposer elected on chain
+    pub matching_rounds: u64,
+    /// Fraction of the rounds that would need another proposer for every validator to be
+    /// elected in proportion to its voting power
+    pub stake_deviation: f64,
+    /// Gini coefficient of the ratios between the share of rounds and the stake share of the
+    /// validators, 0 when every validator is elected in proportion to its voting power
+    pub gini_coefficient: f64,
+    /// The validators, most elected first
+    pub proposers: Vec<ProposerShare>,
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}:", self.name)?;
+        writeln!(f, "  rounds: {}", self.rounds)?;
+        writeln!(f, "  failed rounds: {}", self.failed_rounds)?;
+        writeln!(f, "  failed rounds avoided: {}", self.failed_rounds_avoided)?;
+        writeln!(
+            f,
+            "  expected failed rounds: {:.1}",
+            self.expected_failed_rounds
+        )?;
+        writeln!(f, "  rounds matching on chain: {}", self.matching_rounds)?;
+        writeln!(f, "  stake deviation: {:.4}", self.stake_deviation)?;
+        writeln!(f, "  gini coefficient: {:.4}", self.gini_coefficient)?;
+        writeln!(
+            f,
+            "  proposers (share / stake share / observed failure rate):"
+        )?;
+        for proposer in self.proposers.iter().take(DISPLAYED_PROPOSERS) {
+            writeln!(
+                f,
+                "    {}: {} rounds, {:.4} / {:.4} / {}",
+                proposer.author,
+                proposer.rounds,
+                proposer.share,
+                proposer.stake_share,
+                proposer
+                    .observed_failure_rate
+                    .map_or_else(|| "-".to_string(), |rate| format!("{:.4}", rate))
+            )?;
+        }
+        if self.proposers.len() > DISPLAYED_PROPOSERS {
+            writeln!(
+                f,
+                "    ... {} more",
+                self.proposers.len() - DISPLAYED_PROPOSERS
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_types::on_chain_config::ProposerAndVoterConfig;
+
+    fn block(epoch: u64, round: Round, proposer: Author, failed: Vec<u64>) -> HistoricalBlock {
+        HistoricalBlock {
+            event: NewBlockEvent::new(
+                AccountAddress::random(),
+                epoch,
+                round,
+                round,
+                // All 4 validators voted
+                vec![0b1111_0000],
+                proposer,
+                failed,
+                round * 1_000_000,
+            ),
+            accumulator_root_hash: None,
+        }
+    }
+
+    fn config(failed_weight: u64) -> BacktestConfig {
+        BacktestConfig {
+            name: format!("failed_weight_{}", failed_weight),
+            leader_reputation_type: LeaderReputationType::ProposerAndVoter(
+                ProposerAndVoterConfig {
+                    active_weight: 1000,
+                    inactive_weight: 10,
+                    failed_weight,
+                    failure_threshold_percent: 10,
+                    proposer_window_num_validators_multiplier: 10,
+                    voter_window_num_validators_multiplier: 1,
+                    weight_by_voting_power: true,
+                    use_history_from_previous_epoch_max_count: 5,
+                },
+            ),
+            weighting: BacktestWeighting::ProposerAndVoter,
+            exclude_round: 0,
+        }
+    }
+
+    /// Validator 3 never manages to propose, the others always do.
+    fn history(num_rounds: Round) -> (Vec<Author>, BacktestHistory) {
+        let validators: Vec<_> = (0..4).map(|_| Author::random()).collect();
+        let mut blocks = vec![];
+        let mut failed = vec![];
+        for round in 1..=num_rounds {
+            let proposer = (round % 4) as usize;
+            if proposer == 3 {
+                failed.push(3);
+            } else {
+                blocks.push(block(
+                    1,
+                    round,
+                    validators[proposer],
+                    std::mem::take(&mut failed),
+                ));
+            }
+        }
+        let history = BacktestHistory::new(
+            vec![EpochValidators {
+                epoch: 1,
+                validators: validators.clone(),
+                voting_powers: vec![1; 4],
+            }],
+            blocks,
+        )
+        .unwrap();
+        (validators, history)
+    }
+
+    #[test]
+    fn test_observed_rounds() {
+        let (validators, history) = history(8);
+        let rounds = history.rounds(&(1..=1));
+        assert_eq!(rounds.len(), 8);
+        assert_eq!(rounds[2], HistoricalRound {
+            epoch: 1,
+            round: 3,
+            proposer: Some(validators[3]),
+            failed: true,
+        });
+
+        let report = history.observed_report(1..=1);
+        assert_eq!(report.rounds, 8);
+        assert_eq!(report.failed_rounds, 2);
+        assert_eq!(report.expected_failed_rounds, 2.0);
+        assert_eq!(report.matching_rounds, 8);
+        assert_eq!(report.proposers.len(), 4);
+        // Every validator was the proposer of 2 rounds
+        assert!(report.stake_deviation.abs() < 1e-9);
+        assert!(report.gini_coefficient.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_backtest_avoids_failing_proposer() {
+        let (validators, history) = history(400);
+        let penalizing = history.backtest(&config(1), 1..=1);
+        let not_penalizing = history.backtest(&config(1000), 1..=1);
+
+        let rounds_of = |report: &BacktestReport, author| {
+            report
+                .proposers
+                .iter()
+                .find(|p| p.author == author)
+                .map_or(0, |p| p.rounds)
+        };
+        assert_eq!(penalizing.rounds, 400);
+        assert_eq!(penalizing.failed_rounds, 100);
+        assert!(rounds_of(&penalizing, validators[3]) < 10);
+        assert!(rounds_of(&not_penalizing, validators[3]) > 50);
+        assert!(penalizing.failed_rounds_avoided > not_penalizing.failed_rounds_avoided);
+        assert!(penalizing.expected_failed_rounds < not_penalizing.expected_failed_rounds);
+        assert!(penalizing.gini_coefficient > not_penalizing.gini_coefficient);
+    }
+
+    #[test]
+    fn test_backtest_weighting() {
+        let (validators, history) = history(400);
+        let mut success_rate = config(1);
+        success_rate.weighting = BacktestWeighting::SuccessRate;
+        let report = history.backtest(&success_rate, 1..=1);
+
+        // Validator 3 never proposes successfully, so it gets the failed weight
+        let rounds_of_3 = report
+            .proposers
+            .iter()
+            .find(|p| p.author == validators[3])
+            .map_or(0, |p| p.rounds);
+        assert_eq!(report.rounds, 400);
+        assert!(rounds_of_3 < 10);
+
+        // Configs without a weighting use the one used on chain
+        let mut value = serde_yaml::to_value(config(1)).unwrap();
+        value
+            .as_mapping_mut()
+            .unwrap()
+            .remove(&serde_yaml::Value::String("weighting".to_string()));
+        let parsed: BacktestConfig = serde_yaml::from_value(value).unwrap();
+        assert_eq!(parsed.weighting, BacktestWeighting::ProposerAndVoter);
+    }
+
+    #[test]
+    fn test_gini_coefficient() {
+        assert_eq!(gini_coefficient(vec![1.0, 1.0, 1.0]), 0.0);
+        assert!((gini_coefficient(vec![0.0, 0.0, 0.0, 4.0]) - 0.75).abs() < 1e-9);
+    }
+}
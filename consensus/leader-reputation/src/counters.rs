@@ -0,0 +1,42 @@
+// Copyright © Aptos Foundation
+// Parts of the project are originally copyright © Meta Platforms, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use aptos_metrics_core::{register_int_gauge, IntGauge};
+use once_cell::sync::Lazy;
+
+/// Committed proposals map when using LeaderReputation as the ProposerElection
+pub static COMMITTED_PROPOSALS_IN_WINDOW: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_committed_proposals_in_window",
+        "Total number committed proposals in the current reputation window",
+    )
+    .unwrap()
+});
+
+/// Failed proposals map when using LeaderReputation as the ProposerElection
+pub static FAILED_PROPOSALS_IN_WINDOW: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_failed_proposals_in_window",
+        "Total number of failed proposals in the current reputation window",
+    )
+    .unwrap()
+});
+
+/// Committed votes map when using LeaderReputation as the ProposerElection
+pub static COMMITTED_VOTES_IN_WINDOW: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_committed_votes_in_window",
+        "Total number of committed votes in the current reputation window",
+    )
+    .unwrap()
+});
+
+/// The number of block events the LeaderReputation uses
+pub static LEADER_REPUTATION_ROUND_HISTORY_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_leader_reputation_round_history_size",
+        "Total number of new block events in the current reputation window"
+    )
+    .unwrap()
+});
@@ -0,0 +1,102 @@
+// Copyright © Aptos Foundation
+// Parts of the project are originally copyright © Meta Platforms, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use aptos_consensus_types::common::Round;
+use aptos_crypto::HashValue;
+use aptos_fallible::copy_from_slice::copy_slice_to_vec;
+use aptos_types::account_config::NewBlockEvent;
+use num_traits::CheckedAdd;
+use std::cmp::Ordering;
+
+/// Interface to query committed NewBlockEvent.
+pub trait MetadataBackend: Send + Sync {
+    /// Return a contiguous NewBlockEvent window in which last one is at target_round or
+    /// latest committed, return all previous one if not enough.
+    fn get_block_metadata(
+        &self,
+        target_epoch: u64,
+        target_round: Round,
+    ) -> (Vec<NewBlockEvent>, HashValue);
+}
+
+// next consumes seed and returns random deterministic u64 value in [0, max) range
+fn next_in_range(state: Vec<u8>, max: u128) -> u128 {
+    // hash = SHA-3-256(state)
+    let hash = aptos_crypto::HashValue::sha3_256_of(&state).to_vec();
+    let mut temp = [0u8; 16];
+    copy_slice_to_vec(&hash[..16], &mut temp).expect("next failed");
+    // return hash[0..16]
+    u128::from_le_bytes(temp) % max
+}
+
+// chose index randomly, with given weight distribution
+pub fn choose_index(mut weights: Vec<u128>, state: Vec<u8>) -> usize {
+    let mut total_weight = 0;
+    // Create cumulative weights vector
+    // Since we own the vector, we can safely modify it in place
+    for w in &mut weights {
+        total_weight = total_weight
+            .checked_add(w)
+            .expect("Total stake shouldn't exceed u128::MAX");
+        *w = total_weight;
+    }
+    choose_index_cumulative(&weights, state)
+}
+
+/// Same as `choose_index`, given the running totals of the weights instead, i.e. the i-th one
+/// is the sum of the weights up to the i-th included. Saves summing them up again when choosing
+/// many times with the same weights.
+pub fn choose_index_cumulative(cumulative_weights: &[u128], state: Vec<u8>) -> usize {
+    let total_weight = cumulative_weights.last().copied().unwrap_or(0);
+    let chosen_weight = next_in_range(state, total_weight);
+    cumulative_weights
+        .binary_search_by(|w| {
+            if *w <= chosen_weight {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        })
+        .unwrap_err()
+}
+
+/// Chooses the index of the proposer of the round, with a probability proportional to its
+/// reputation weight multiplied by its voting power. The choice is seeded with the epoch and the
+/// round, preceded by the accumulator root hash of the history window when given.
+pub fn choose_proposer(
+    epoch: u64,
+    round: Round,
+    weights: &[u64],
+    voting_powers: &[u64],
+    root_hash: Option<HashValue>,
+) -> usize {
+    assert_eq!(weights.len(), voting_powers.len());
+    let stake_weights: Vec<u128> = weights
+        .iter()
+        .zip(voting_powers.iter())
+        .map(|(weight, voting_power)| *weight as u128 * *voting_power as u128)
+        .collect();
+    let state = [
+        root_hash.map_or(vec![], |root_hash| root_hash.to_vec()),
+        epoch.to_le_bytes().to_vec(),
+        round.to_le_bytes().to_vec(),
+    ]
+    .concat();
+    choose_index(stake_weights, state)
+}
+
+#[test]
+fn test_bounds() {
+    // check that bounds are correct, and both first and last weight can be selected.
+    let mut selected = [0, 0];
+    let weights = [u64::MAX as u128 * 1000, u64::MAX as u128 * 1000].to_vec();
+    // 10 is enough to get one of each.
+    for i in 0i32..10 {
+        let state = i.to_le_bytes().to_vec();
+        selected[choose_index(weights.clone(), state)] += 1;
+    }
+
+    assert!(selected[0] >= 1);
+    assert!(selected[1] >= 1);
+}
@@ -0,0 +1,490 @@
+// Copyright © Aptos Foundation
+// Parts of the project are originally copyright © Meta Platforms, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::counters::{
+    COMMITTED_PROPOSALS_IN_WINDOW, COMMITTED_VOTES_IN_WINDOW, FAILED_PROPOSALS_IN_WINDOW,
+    LEADER_REPUTATION_ROUND_HISTORY_SIZE,
+};
+use aptos_bitvec::BitVec;
+use aptos_consensus_types::common::Author;
+use aptos_logger::prelude::*;
+use aptos_types::{account_config::NewBlockEvent, on_chain_config::LeaderReputationType};
+use std::{cmp::max, collections::HashMap, convert::TryFrom};
+
+/// Interface to calculate weights for proposers based on history.
+pub trait ReputationHeuristic: Send + Sync {
+    /// Return the weights of all candidates based on the history.
+    fn get_weights(
+        &self,
+        epoch: u64,
+        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
+        history: &[NewBlockEvent],
+    ) -> Vec<u64>;
+}
+
+pub struct NewBlockEventAggregation {
+    // Window sizes are in number of succesfull blocks, not number of rounds.
+    // i.e. we can be looking at different number of rounds for the same window,
+    // dependig on how many failures we have.
+    voter_window_size: usize,
+    proposer_window_size: usize,
+    reputation_window_from_stale_end: bool,
+}
+
+impl NewBlockEventAggregation {
+    pub fn new(
+        voter_window_size: usize,
+        proposer_window_size: usize,
+        reputation_window_from_stale_end: bool,
+    ) -> Self {
+        Self {
+            voter_window_size,
+            proposer_window_size,
+            reputation_window_from_stale_end,
+        }
+    }
+
+    pub fn bitvec_to_voters<'a>(
+        validators: &'a [Author],
+        bitvec: &BitVec,
+    ) -> Result<Vec<&'a Author>, String> {
+        if BitVec::required_buckets(validators.len() as u16) != bitvec.num_buckets() {
+            return Err(format!(
+                "bitvec bucket {} does not match validators len {}",
+                bitvec.num_buckets(),
+                validators.len()
+            ));
+        }
+
+        Ok(validators
+            .iter()
+            .enumerate()
+            .filter_map(|(index, validator)| {
+                if bitvec.is_set(index as u16) {
+                    Some(validator)
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+
+    pub fn indices_to_validators<'a>(
+        validators: &'a [Author],
+        indices: &[u64],
+    ) -> Result<Vec<&'a Author>, String> {
+        indices
+            .iter()
+            .map(|index| {
+                usize::try_from(*index)
+                    .map_err(|_err| format!("index {} out of bounds", index))
+                    .and_then(|index| {
+                        validators.get(index).ok_or(format!(
+                            "index {} is larger than number of validators {}",
+                            index,
+                            validators.len()
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    fn history_iter<'a>(
+        history: &'a [NewBlockEvent],
+        epoch_to_candidates: &'a HashMap<u64, Vec<Author>>,
+        window_size: usize,
+        from_stale_end: bool,
+    ) -> impl Iterator<Item = &'a NewBlockEvent> {
+        let sub_history = if from_stale_end {
+            let start = if history.len() > window_size {
+                history.len() - window_size
+            } else {
+                0
+            };
+
+            &history[start..]
+        } else {
+            if !history.is_empty() {
+                assert!(
+                    (
+                        history.first().unwrap().epoch(),
+                        history.first().unwrap().round()
+                    ) >= (
+                        history.last().unwrap().epoch(),
+                        history.last().unwrap().round()
+                    )
+                );
+            }
+            let end = if history.len() > window_size {
+                window_size
+            } else {
+                history.len()
+            };
+
+            &history[..end]
+        };
+        sub_history
+            .iter()
+            .filter(move |&meta| epoch_to_candidates.contains_key(&meta.epoch()))
+    }
+
+    pub fn get_aggregated_metrics(
+        &self,
+        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
+        history: &[NewBlockEvent],
+        author: &Author,
+    ) -> (
+        HashMap<Author, u32>,
+        HashMap<Author, u32>,
+        HashMap<Author, u32>,
+    ) {
+        let votes = self.count_votes(epoch_to_candidates, history);
+        let proposals = self.count_proposals(epoch_to_candidates, history);
+        let failed_proposals = self.count_failed_proposals(epoch_to_candidates, history);
+
+        COMMITTED_PROPOSALS_IN_WINDOW.set(*proposals.get(author).unwrap_or(&0) as i64);
+        FAILED_PROPOSALS_IN_WINDOW.set(*failed_proposals.get(author).unwrap_or(&0) as i64);
+        COMMITTED_VOTES_IN_WINDOW.set(*votes.get(author).unwrap_or(&0) as i64);
+
+        LEADER_REPUTATION_ROUND_HISTORY_SIZE.set(
+            proposals.values().sum::<u32>() as i64 + failed_proposals.values().sum::<u32>() as i64,
+        );
+
+        (votes, proposals, failed_proposals)
+    }
+
+    pub fn count_votes(
+        &self,
+        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
+        history: &[NewBlockEvent],
+    ) -> HashMap<Author, u32> {
+        Self::count_votes_custom(
+            epoch_to_candidates,
+            history,
+            self.voter_window_size,
+            self.reputation_window_from_stale_end,
+        )
+    }
+
+    pub fn count_votes_custom(
+        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
+        history: &[NewBlockEvent],
+        window_size: usize,
+        from_stale_end: bool,
+    ) -> HashMap<Author, u32> {
+        Self::history_iter(history, epoch_to_candidates, window_size, from_stale_end).fold(
+            HashMap::new(),
+            |mut map, meta| {
+                match Self::bitvec_to_voters(
+                    &epoch_to_candidates[&meta.epoch()],
+                    &meta.previous_block_votes_bitvec().clone().into(),
+                ) {
+                    Ok(voters) => {
+                        for &voter in voters {
+                            let count = map.entry(voter).or_insert(0);
+                            *count += 1;
+                        }
+                    },
+                    Err(msg) => {
+                        error!(
+                            "Voter conversion from bitmap failed at epoch {}, round {}: {}",
+                            meta.epoch(),
+                            meta.round(),
+                            msg
+                        )
+                    },
+                }
+                map
+            },
+        )
+    }
+
+    pub fn count_proposals(
+        &self,
+        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
+        history: &[NewBlockEvent],
+    ) -> HashMap<Author, u32> {
+        Self::count_proposals_custom(
+            epoch_to_candidates,
+            history,
+            self.proposer_window_size,
+            self.reputation_window_from_stale_end,
+        )
+    }
+
+    pub fn count_proposals_custom(
+        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
+        history: &[NewBlockEvent],
+        window_size: usize,
+        from_stale_end: bool,
+    ) -> HashMap<Author, u32> {
+        Self::history_iter(history, epoch_to_candidates, window_size, from_stale_end).fold(
+            HashMap::new(),
+            |mut map, meta| {
+                let count = map.entry(meta.proposer()).or_insert(0);
+                *count += 1;
+                map
+            },
+        )
+    }
+
+    pub fn count_failed_proposals(
+        &self,
+        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
+        history: &[NewBlockEvent],
+    ) -> HashMap<Author, u32> {
+        Self::history_iter(
+            history,
+            epoch_to_candidates,
+            self.proposer_window_size,
+            self.reputation_window_from_stale_end,
+        )
+        .fold(HashMap::new(), |mut map, meta| {
+            match Self::indices_to_validators(
+                &epoch_to_candidates[&meta.epoch()],
+                meta.failed_proposer_indices(),
+            ) {
+                Ok(failed_proposers) => {
+                    for &failed_proposer in failed_proposers {
+                        let count = map.entry(failed_proposer).or_insert(0);
+                        *count += 1;
+                    }
+                },
+                Err(msg) => {
+                    error!(
+                        "Failed proposer conversion from indices failed at epoch {}, round {}: {}",
+                        meta.epoch(),
+                        meta.round(),
+                        msg
+                    )
+                },
+            }
+            map
+        })
+    }
+}
+
+/// Statistics of a candidate over the reputation windows.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CandidateStats {
+    /// Number of committed blocks whose QC the candidate signed, within the voter window
+    pub votes: u32,
+    /// Number of committed blocks the candidate proposed, within the proposer window
+    pub proposals: u32,
+    /// Number of rounds the candidate failed to propose in, within the proposer window
+    pub failed_proposals: u32,
+}
+
+/// Interface to turn the statistics of a candidate into its selection weight.
+///
+/// Implementations only decide the weight, the windows the statistics are aggregated over are
+/// handled by `WeightedStatsHeuristic`, so a new heuristic can be added without touching how
+/// history is read.
+pub trait CandidateWeighting: Send + Sync {
+    /// Return the selection weight of a candidate, before being multiplied by its voting power.
+    fn weight(&self, stats: &CandidateStats) -> u64;
+}
+
+/// Reputation heuristic that aggregates votes, proposals and failed proposals of every
+/// candidate over the history, and weights each candidate independently.
+pub struct WeightedStatsHeuristic<W> {
+    author: Author,
+    weighting: W,
+    aggregation: NewBlockEventAggregation,
+}
+
+impl<W: CandidateWeighting> WeightedStatsHeuristic<W> {
+    pub fn with_weighting(
+        author: Author,
+        weighting: W,
+        voter_window_size: usize,
+        proposer_window_size: usize,
+        reputation_window_from_stale_end: bool,
+    ) -> Self {
+        Self {
+            author,
+            weighting,
+            aggregation: NewBlockEventAggregation::new(
+                voter_window_size,
+                proposer_window_size,
+                reputation_window_from_stale_end,
+            ),
+        }
+    }
+}
+
+impl<W: CandidateWeighting> ReputationHeuristic for WeightedStatsHeuristic<W> {
+    fn get_weights(
+        &self,
+        epoch: u64,
+        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
+        history: &[NewBlockEvent],
+    ) -> Vec<u64> {
+        assert!(epoch_to_candidates.contains_key(&epoch));
+
+        let (votes, proposals, failed_proposals) =
+            self.aggregation
+                .get_aggregated_metrics(epoch_to_candidates, history, &self.author);
+
+        epoch_to_candidates[&epoch]
+            .iter()
+            .map(|author| {
+                self.weighting.weight(&CandidateStats {
+                    votes: *votes.get(author).unwrap_or(&0),
+                    proposals: *proposals.get(author).unwrap_or(&0),
+                    failed_proposals: *failed_proposals.get(author).unwrap_or(&0),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Weighting that looks at successful and failed proposals, as well as voting history,
+/// to define node reputation, used for leader selection.
+///
+/// We want to optimize leader selection to primarily maximize network's throughput,
+/// but we also, in combinatoin with staking rewards logic, need to be reasonably fair.
+///
+/// Logic is:
+///  * if proposer round failure rate within the proposer window is strictly above threshold, use failed_weight (default 1).
+///  * otherwise, if node had no proposal rounds and no successful votes, use inactive_weight (default 10).
+///  * otherwise, use the default active_weight (default 100).
+///
+/// We primarily want to avoid failed rounds, as they have a largest negative effect on the network.
+/// So if we see a node having failures to propose, when it was the leader, we want to avoid that node.
+/// We add a threshold (instead of penalizing on a single failure), so that transient issues in the network,
+/// or malicious behaviour of the next leader is avoided. In general, we expect there to be
+/// proposer_window_size/num_validators opportunities for a node to be a leader, so a single failure, or a
+/// subset of following leaders being malicious will not be enough to exclude a node.
+/// On the other hand, single failure, without any successes before will exclude the note.
+/// Threshold probably makes the most sense to be between:
+///  * 10% (aggressive exclusion with 1 failure in 10 proposals being enough for exclusion)
+///  * and 33% (much less aggressive exclusion, with 1 failure for every 2 successes, should still reduce failed
+///    rounds by at least 66%, and is enough to avoid byzantine attacks as well as the rest of the protocol)
+pub struct ProposerAndVoterWeighting {
+    active_weight: u64,
+    inactive_weight: u64,
+    failed_weight: u64,
+    failure_threshold_percent: u32,
+}
+
+impl ProposerAndVoterWeighting {
+    pub fn new(
+        active_weight: u64,
+        inactive_weight: u64,
+        failed_weight: u64,
+        failure_threshold_percent: u32,
+    ) -> Self {
+        Self {
+            active_weight,
+            inactive_weight,
+            failed_weight,
+            failure_threshold_percent,
+        }
+    }
+}
+
+impl CandidateWeighting for ProposerAndVoterWeighting {
+    fn weight(&self, stats: &CandidateStats) -> u64 {
+        if stats.failed_proposals * 100
+            > (stats.proposals + stats.failed_proposals) * self.failure_threshold_percent
+        {
+            self.failed_weight
+        } else if stats.proposals > 0 || stats.votes > 0 {
+            self.active_weight
+        } else {
+            self.inactive_weight
+        }
+    }
+}
+
+/// Weighting that scales the active weight by the share of successful proposals of a candidate,
+/// down to the failed weight, instead of switching to the failed weight past a failure
+/// threshold. A candidate failing now and then loses a proportional share of its proposals,
+/// rather than all of them or none.
+pub struct SuccessRateWeighting {
+    active_weight: u64,
+    inactive_weight: u64,
+    failed_weight: u64,
+}
+
+impl SuccessRateWeighting {
+    pub fn new(active_weight: u64, inactive_weight: u64, failed_weight: u64) -> Self {
+        Self {
+            active_weight,
+            inactive_weight,
+            failed_weight,
+        }
+    }
+}
+
+impl CandidateWeighting for SuccessRateWeighting {
+    fn weight(&self, stats: &CandidateStats) -> u64 {
+        let attempts = stats.proposals as u128 + stats.failed_proposals as u128;
+        if attempts > 0 {
+            let weight = self.active_weight as u128 * stats.proposals as u128 / attempts;
+            max(weight as u64, self.failed_weight)
+        } else if stats.votes > 0 {
+            self.active_weight
+        } else {
+            self.inactive_weight
+        }
+    }
+}
+
+/// Heuristic combining `ProposerAndVoterWeighting` with the proposer and voter windows.
+pub type ProposerAndVoterHeuristic = WeightedStatsHeuristic<ProposerAndVoterWeighting>;
+
+impl ProposerAndVoterHeuristic {
+    pub fn new(
+        author: Author,
+        active_weight: u64,
+        inactive_weight: u64,
+        failed_weight: u64,
+        failure_threshold_percent: u32,
+        voter_window_size: usize,
+        proposer_window_size: usize,
+        reputation_window_from_stale_end: bool,
+    ) -> Self {
+        Self::with_weighting(
+            author,
+            ProposerAndVoterWeighting::new(
+                active_weight,
+                inactive_weight,
+                failed_weight,
+                failure_threshold_percent,
+            ),
+            voter_window_size,
+            proposer_window_size,
+            reputation_window_from_stale_end,
+        )
+    }
+
+    /// Creates the heuristic configured on chain for an epoch with `num_validators` validators,
+    /// returning it with the size of the history window it needs.
+    pub fn from_config(
+        author: Author,
+        leader_reputation_type: &LeaderReputationType,
+        num_validators: usize,
+    ) -> (Self, usize) {
+        let config = match leader_reputation_type {
+            LeaderReputationType::ProposerAndVoter(config)
+            | LeaderReputationType::ProposerAndVoterV2(config) => config,
+        };
+        let proposer_window_size =
+            num_validators * config.proposer_window_num_validators_multiplier;
+        let voter_window_size = num_validators * config.voter_window_num_validators_multiplier;
+        let heuristic = Self::new(
+            author,
+            config.active_weight,
+            config.inactive_weight,
+            config.failed_weight,
+            config.failure_threshold_percent,
+            voter_window_size,
+            proposer_window_size,
+            leader_reputation_type.use_reputation_window_from_stale_end(),
+        );
+        (heuristic, max(proposer_window_size, voter_window_size))
+    }
+}
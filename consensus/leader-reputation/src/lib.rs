@@ -0,0 +1,20 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+//! Reputation based proposer election: the heuristics weighting the candidates from the
+//! committed history, and the seeded choice among them. `LeaderReputation` in consensus elects
+//! the proposers with them, and `backtest` replays the committed history through alternative
+//! configs of them.
+
+pub mod backtest;
+mod counters;
+mod election;
+mod heuristic;
+
+pub use election::{choose_index, choose_index_cumulative, choose_proposer, MetadataBackend};
+pub use heuristic::{
+    CandidateStats, CandidateWeighting, NewBlockEventAggregation, ProposerAndVoterHeuristic,
+    ProposerAndVoterWeighting, ReputationHeuristic, SuccessRateWeighting, WeightedStatsHeuristic,
+};
@@ -5,7 +5,7 @@
 use crate::{
     block_storage::{
         block_tree::BlockTree,
-        tracing::{observe_block, BlockStage},
+        tracing::{observe_block, observe_round, BlockStage},
         BlockReader,
     },
     counters,
@@ -19,7 +19,7 @@ use crate::{
 use anyhow::{bail, ensure, format_err, Context};
 use aptos_consensus_types::{
     block::Block, common::Round, executed_block::ExecutedBlock, quorum_cert::QuorumCert,
-    sync_info::SyncInfo, timeout_2chain::TwoChainTimeoutCertificate,
+    round_timeline::RoundEvent, sync_info::SyncInfo, timeout_2chain::TwoChainTimeoutCertificate,
 };
 use aptos_crypto::{hash::ACCUMULATOR_PLACEHOLDER_HASH, HashValue};
 use aptos_executor_types::{ExecutorError, ExecutorResult, StateComputeResult};
@@ -45,6 +45,7 @@ const MAX_ORDERING_PIPELINE_LATENCY_REDUCTION: Duration = Duration::from_secs(1)
 fn update_counters_for_ordered_blocks(ordered_blocks: &[Arc<ExecutedBlock>]) {
     for block in ordered_blocks {
         observe_block(block.block().timestamp_usecs(), BlockStage::ORDERED);
+        observe_round(block.epoch(), block.round(), RoundEvent::Ordered);
     }
 }
 
@@ -3,8 +3,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::counters;
+use aptos_consensus_types::{
+    common::Round,
+    round_timeline::{RoundEvent, RoundTimelineRecorder},
+};
 use aptos_infallible::duration_since_epoch;
-use std::time::Duration;
+use once_cell::sync::Lazy;
+use std::{sync::Arc, time::Duration};
+
+/// Timeline of the most recent rounds, exported by the inspection service
+pub static ROUND_TIMELINES: Lazy<Arc<RoundTimelineRecorder>> =
+    Lazy::new(|| Arc::new(RoundTimelineRecorder::default()));
 
 pub struct BlockStage;
 
@@ -32,3 +41,13 @@ pub fn observe_block(timestamp: u64, stage: &'static str) {
             .observe(t.as_secs_f64());
     }
 }
+
+/// Record the time of an event of a round in the round timeline.
+pub fn observe_round(epoch: u64, round: Round, event: RoundEvent) {
+    ROUND_TIMELINES.record(
+        epoch,
+        round,
+        event,
+        duration_since_epoch().as_micros() as u64,
+    );
+}
@@ -196,42 +196,6 @@ pub static PROPOSER_COLLECTED_TIMEOUT_VOTING_POWER: Lazy<Counter> = Lazy::new(||
         .unwrap()
 });
 
-/// Committed proposals map when using LeaderReputation as the ProposerElection
-pub static COMMITTED_PROPOSALS_IN_WINDOW: Lazy<IntGauge> = Lazy::new(|| {
-    register_int_gauge!(
-        "aptos_committed_proposals_in_window",
-        "Total number committed proposals in the current reputation window",
-    )
-    .unwrap()
-});
-
-/// Failed proposals map when using LeaderReputation as the ProposerElection
-pub static FAILED_PROPOSALS_IN_WINDOW: Lazy<IntGauge> = Lazy::new(|| {
-    register_int_gauge!(
-        "aptos_failed_proposals_in_window",
-        "Total number of failed proposals in the current reputation window",
-    )
-    .unwrap()
-});
-
-/// Committed votes map when using LeaderReputation as the ProposerElection
-pub static COMMITTED_VOTES_IN_WINDOW: Lazy<IntGauge> = Lazy::new(|| {
-    register_int_gauge!(
-        "aptos_committed_votes_in_window",
-        "Total number of committed votes in the current reputation window",
-    )
-    .unwrap()
-});
-
-/// The number of block events the LeaderReputation uses
-pub static LEADER_REPUTATION_ROUND_HISTORY_SIZE: Lazy<IntGauge> = Lazy::new(|| {
-    register_int_gauge!(
-        "aptos_leader_reputation_round_history_size",
-        "Total number of new block events in the current reputation window"
-    )
-    .unwrap()
-});
-
 /// Counts when chain_health backoff is triggered
 pub static CHAIN_HEALTH_BACKOFF_TRIGGERED: Lazy<Histogram> = Lazy::new(|| {
     register_avg_counter(
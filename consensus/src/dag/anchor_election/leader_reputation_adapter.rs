@@ -0,0 +1,139 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    dag::{anchor_election::AnchorElection, storage::CommitEvent},
+    liveness::{leader_reputation::ReputationHeuristic, proposer_election::choose_index_cumulative},
+};
+use aptos_bitvec::BitVec;
+use aptos_consensus_types::common::{Author, Round};
+use aptos_types::{account_address::AccountAddress, account_config::NewBlockEvent};
+use std::collections::{HashMap, VecDeque};
+
+/// Anchor election based on how validators behaved in the most recently ordered anchors,
+/// using the same heuristics as leader reputation: validators whose anchors failed get
+/// picked less often, and so do the ones that don't show up as parents of anchors.
+///
+/// Reputation only changes when an anchor is ordered, and every validator orders the same
+/// anchors, so the anchor elected for a round that isn't ordered yet is the same everywhere.
+pub struct LeaderReputationAdapter {
+    epoch: u64,
+    epoch_to_validators: HashMap<u64, Vec<Author>>,
+    voting_powers: Vec<u64>,
+    heuristic: Box<dyn ReputationHeuristic>,
+    window_size: usize,
+    // Ordered anchors, the most recent one first
+    history: VecDeque<NewBlockEvent>,
+    // Running totals of the selection weights of the validators of the current epoch, given the
+    // history, only recomputed when the history changes
+    cumulative_weights: Vec<u128>,
+}
+
+impl LeaderReputationAdapter {
+    /// `commit_events` are the latest committed anchors, oldest first, which must be read from
+    /// storage with a window at least as large as `window_size` so that a restarted validator
+    /// has the same history as the others.
+    pub fn new(
+        epoch: u64,
+        epoch_to_validators: HashMap<u64, Vec<Author>>,
+        voting_powers: Vec<u64>,
+        heuristic: Box<dyn ReputationHeuristic>,
+        window_size: usize,
+        commit_events: Vec<CommitEvent>,
+    ) -> Self {
+        assert!(epoch_to_validators.contains_key(&epoch));
+        assert_eq!(epoch_to_validators[&epoch].len(), voting_powers.len());
+
+        let mut anchor_election = Self {
+            epoch,
+            epoch_to_validators,
+            voting_powers,
+            heuristic,
+            window_size,
+            history: VecDeque::with_capacity(window_size),
+            cumulative_weights: vec![],
+        };
+        for commit_event in commit_events {
+            anchor_election.push(commit_event);
+        }
+        anchor_election.update_weights();
+        anchor_election
+    }
+
+    /// Adds an ordered anchor to the history. Returns false if the anchor is already in the
+    /// history, or is from an epoch whose validators are unknown.
+    fn push(&mut self, commit_event: CommitEvent) -> bool {
+        if let Some(latest) = self.history.front() {
+            if (commit_event.epoch(), commit_event.round()) <= (latest.epoch(), latest.round()) {
+                return false;
+            }
+        }
+        let validators = match self.epoch_to_validators.get(&commit_event.epoch()) {
+            Some(validators) => validators,
+            None => return false,
+        };
+        let index_of = |author: &Author| validators.iter().position(|v| v == author);
+
+        let mut parents_bitvec = BitVec::with_num_bits(validators.len() as u16);
+        for index in commit_event.parents().iter().filter_map(index_of) {
+            parents_bitvec.set(index as u16);
+        }
+        let failed_author_indices = commit_event
+            .failed_authors()
+            .iter()
+            .filter_map(index_of)
+            .map(|index| index as u64)
+            .collect();
+
+        self.history.push_front(NewBlockEvent::new(
+            AccountAddress::ZERO,
+            commit_event.epoch(),
+            commit_event.round(),
+            0,
+            parents_bitvec.into(),
+            *commit_event.author(),
+            failed_author_indices,
+            0,
+        ));
+        self.history.truncate(self.window_size);
+        true
+    }
+
+    fn update_weights(&mut self) {
+        let weights = self.heuristic.get_weights(
+            self.epoch,
+            &self.epoch_to_validators,
+            self.history.make_contiguous(),
+        );
+        assert_eq!(weights.len(), self.voting_powers.len());
+        let mut total_weight: u128 = 0;
+        self.cumulative_weights = weights
+            .iter()
+            .zip(self.voting_powers.iter())
+            .map(|(weight, voting_power)| {
+                total_weight = total_weight
+                    .checked_add(*weight as u128 * *voting_power as u128)
+                    .expect("Total stake shouldn't exceed u128::MAX");
+                total_weight
+            })
+            .collect();
+    }
+}
+
+impl AnchorElection for LeaderReputationAdapter {
+    fn get_anchor(&self, round: Round) -> Author {
+        let state = [
+            self.epoch.to_le_bytes().to_vec(),
+            round.to_le_bytes().to_vec(),
+        ]
+        .concat();
+        let chosen_index = choose_index_cumulative(&self.cumulative_weights, state);
+        self.epoch_to_validators[&self.epoch][chosen_index]
+    }
+
+    fn update_reputation(&mut self, commit_event: CommitEvent) {
+        if self.push(commit_event) {
+            self.update_weights();
+        }
+    }
+}
@@ -0,0 +1,19 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::dag::storage::CommitEvent;
+use aptos_consensus_types::common::{Author, Round};
+
+mod leader_reputation_adapter;
+mod round_robin;
+
+pub use leader_reputation_adapter::LeaderReputationAdapter;
+pub use round_robin::RoundRobinAnchorElection;
+
+pub trait AnchorElection: Send + Sync {
+    fn get_anchor(&self, round: Round) -> Author;
+
+    /// Updates the election with an ordered anchor. Anchors are only ever elected from the
+    /// ordered history, so that every validator elects the same anchor for a round.
+    fn update_reputation(&mut self, commit_event: CommitEvent);
+}
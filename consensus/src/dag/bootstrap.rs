@@ -2,7 +2,7 @@
 
 use super::{
     adapter::{OrderedNotifier, OrderedNotifierAdapter, TLedgerInfoProvider},
-    anchor_election::RoundRobinAnchorElection,
+    anchor_election::{AnchorElection, LeaderReputationAdapter, RoundRobinAnchorElection},
     dag_driver::DagDriver,
     dag_fetcher::{DagFetcher, DagFetcherService, FetchRequestHandler},
     dag_handler::NetworkHandler,
@@ -22,10 +22,12 @@ use crate::{
         observability::logging::{LogEvent, LogSchema},
     },
     experimental::buffer_manager::OrderedBlocks,
+    liveness::leader_reputation::ProposerAndVoterHeuristic,
     network::IncomingDAGRequest,
     payload_manager::PayloadManager,
     state_replication::{PayloadClient, StateComputer},
 };
+use anyhow::{anyhow, Context};
 use aptos_channels::{
     aptos_channel::{self, Receiver},
     message_queues::QueueStyle,
@@ -35,16 +37,22 @@ use aptos_infallible::RwLock;
 use aptos_logger::{debug, error};
 use aptos_reliable_broadcast::{RBNetworkSender, ReliableBroadcast};
 use aptos_types::{
-    epoch_state::EpochState, ledger_info::LedgerInfo, validator_signer::ValidatorSigner,
+    epoch_state::EpochState,
+    ledger_info::LedgerInfo,
+    on_chain_config::{AnchorElectionMode, DagConsensusConfigV2, LeaderReputationType},
+    validator_signer::ValidatorSigner,
 };
 use futures_channel::{
     mpsc::{UnboundedReceiver, UnboundedSender},
     oneshot,
 };
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::{select, task::JoinHandle};
 use tokio_retry::strategy::ExponentialBackoff;
 
+/// How long to wait before bootstrapping again, if it failed
+const BOOTSTRAP_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct DagBootstrapper {
     self_peer: Author,
     signer: Arc<ValidatorSigner>,
@@ -57,6 +65,8 @@ pub struct DagBootstrapper {
     payload_manager: Arc<PayloadManager>,
     payload_client: Arc<dyn PayloadClient>,
     state_computer: Arc<dyn StateComputer>,
+    onchain_config: DagConsensusConfigV2,
+    epoch_to_validators: HashMap<u64, Vec<Author>>,
 }
 
 impl DagBootstrapper {
@@ -72,6 +82,8 @@ impl DagBootstrapper {
         payload_manager: Arc<PayloadManager>,
         payload_client: Arc<dyn PayloadClient>,
         state_computer: Arc<dyn StateComputer>,
+        onchain_config: DagConsensusConfigV2,
+        epoch_to_validators: HashMap<u64, Vec<Author>>,
     ) -> Self {
         Self {
             self_peer,
@@ -85,15 +97,65 @@ impl DagBootstrapper {
             payload_manager,
             payload_client,
             state_computer,
+            onchain_config,
+            epoch_to_validators,
         }
     }
 
+    fn build_anchor_election(&self) -> anyhow::Result<Box<dyn AnchorElection>> {
+        let validators = self.epoch_state.verifier.get_ordered_account_addresses();
+        Ok(match &self.onchain_config.anchor_election_mode {
+            AnchorElectionMode::RoundRobin => Box::new(RoundRobinAnchorElection::new(validators)),
+            AnchorElectionMode::LeaderReputation(leader_reputation_type) => {
+                let config = match leader_reputation_type {
+                    LeaderReputationType::ProposerAndVoter(config)
+                    | LeaderReputationType::ProposerAndVoterV2(config) => config,
+                };
+                let (heuristic, window_size) = ProposerAndVoterHeuristic::from_config(
+                    self.self_peer,
+                    leader_reputation_type,
+                    validators.len(),
+                );
+                let voting_powers = if config.weight_by_voting_power {
+                    validators
+                        .iter()
+                        .map(|validator| {
+                            self.epoch_state
+                                .verifier
+                                .get_voting_power(validator)
+                                .ok_or_else(|| {
+                                    anyhow!("no voting power for validator {}", validator)
+                                })
+                        })
+                        .collect::<anyhow::Result<_>>()?
+                } else {
+                    vec![1; validators.len()]
+                };
+                // The whole window has to come from storage, as anchors ordered before this
+                // validator (re)started must count the same as on the other validators.
+                let commit_events = self
+                    .storage
+                    .get_latest_k_committed_events(window_size as u64)
+                    .context("failed to read commit events from storage")?;
+
+                Box::new(LeaderReputationAdapter::new(
+                    self.epoch_state.epoch,
+                    self.epoch_to_validators.clone(),
+                    voting_powers,
+                    Box::new(heuristic),
+                    window_size,
+                    commit_events,
+                ))
+            },
+        })
+    }
+
     fn bootstrap_dag_store(
         &self,
         initial_ledger_info: LedgerInfo,
         notifier: Arc<dyn OrderedNotifier>,
         dag_window_size_config: usize,
-    ) -> (Arc<RwLock<Dag>>, OrderRule) {
+    ) -> anyhow::Result<(Arc<RwLock<Dag>>, OrderRule)> {
         let initial_round = if initial_ledger_info.round() <= dag_window_size_config as Round {
             1
         } else {
@@ -109,8 +171,7 @@ impl DagBootstrapper {
             dag_window_size_config,
         )));
 
-        let validators = self.epoch_state.verifier.get_ordered_account_addresses();
-        let anchor_election = Box::new(RoundRobinAnchorElection::new(validators));
+        let anchor_election = self.build_anchor_election()?;
 
         let order_rule = OrderRule::new(
             self.epoch_state.clone(),
@@ -121,7 +182,7 @@ impl DagBootstrapper {
             self.storage.clone(),
         );
 
-        (dag, order_rule)
+        Ok((dag, order_rule))
     }
 
     fn bootstrap_components(
@@ -223,14 +284,26 @@ impl DagBootstrapper {
                 ledger_info_provider.clone(),
             ));
 
-            let (dag_store, order_rule) = self.bootstrap_dag_store(
+            let (dag_store, order_rule) = match self.bootstrap_dag_store(
                 ledger_info_provider
                     .get_latest_ledger_info()
                     .ledger_info()
                     .clone(),
                 adapter.clone(),
                 DAG_WINDOW,
-            );
+            ) {
+                Ok(result) => result,
+                Err(e) => {
+                    error!(error = ?e, "unable to bootstrap dag store, retrying");
+                    select! {
+                        Ok(ack_tx) = &mut shutdown_rx => {
+                            let _ = ack_tx.send(());
+                            return;
+                        },
+                        _ = tokio::time::sleep(BOOTSTRAP_RETRY_INTERVAL) => continue,
+                    }
+                },
+            };
 
             let state_sync_trigger = StateSyncTrigger::new(
                 self.epoch_state.clone(),
@@ -318,6 +391,7 @@ pub(super) fn bootstrap_dag_for_test(
     payload_manager: Arc<PayloadManager>,
     payload_client: Arc<dyn PayloadClient>,
     state_computer: Arc<dyn StateComputer>,
+    anchor_election_mode: AnchorElectionMode,
 ) -> (
     JoinHandle<StateSyncStatus>,
     JoinHandle<()>,
@@ -336,6 +410,14 @@ pub(super) fn bootstrap_dag_for_test(
         payload_manager,
         payload_client,
         state_computer,
+        DagConsensusConfigV2 {
+            anchor_election_mode,
+            ..Default::default()
+        },
+        HashMap::from([(
+            epoch_state.epoch,
+            epoch_state.verifier.get_ordered_account_addresses(),
+        )]),
     );
 
     let ledger_info_from_storage = storage
@@ -355,8 +437,9 @@ pub(super) fn bootstrap_dag_for_test(
     ));
     let (dag_rpc_tx, dag_rpc_rx) = aptos_channel::new(QueueStyle::FIFO, 64, None);
 
-    let (dag_store, order_rule) =
-        bootstraper.bootstrap_dag_store(latest_ledger_info, adapter.clone(), DAG_WINDOW);
+    let (dag_store, order_rule) = bootstraper
+        .bootstrap_dag_store(latest_ledger_info, adapter.clone(), DAG_WINDOW)
+        .expect("dag store must bootstrap");
 
     let state_sync_trigger = StateSyncTrigger::new(
         epoch_state,
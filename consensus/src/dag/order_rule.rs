@@ -10,7 +10,7 @@ use crate::dag::{
         logging::{LogEvent, LogSchema},
         tracing::{observe_node, NodeStage},
     },
-    storage::DAGStorage,
+    storage::{CommitEvent, DAGStorage},
     types::NodeMetadata,
     CertifiedNode,
 };
@@ -62,12 +62,7 @@ impl OrderRule {
                         .for_each(|node_status| node_status.mark_as_ordered());
                 }
             }
-            anchor_election.update_reputation(
-                event.round(),
-                event.author(),
-                event.parents(),
-                event.failed_authors(),
-            );
+            anchor_election.update_reputation(event);
         }
         let mut order_rule = Self {
             epoch_state,
@@ -182,12 +177,11 @@ impl OrderRule {
             .iter()
             .map(|cert| *cert.metadata().author())
             .collect();
-        self.anchor_election.update_reputation(
-            anchor.round(),
-            anchor.author(),
+        self.anchor_election.update_reputation(CommitEvent::new(
+            anchor.id(),
             parents,
             failed_authors.iter().map(|(_, author)| *author).collect(),
-        );
+        ));
 
         let mut dag_writer = self.dag.write();
         let mut ordered_nodes: Vec<_> = dag_writer
@@ -0,0 +1,118 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    dag::{
+        anchor_election::{AnchorElection, LeaderReputationAdapter},
+        storage::CommitEvent,
+        NodeId,
+    },
+    liveness::leader_reputation::ProposerAndVoterHeuristic,
+};
+use aptos_consensus_types::common::{Author, Round};
+use aptos_types::validator_verifier::random_validator_verifier;
+use std::collections::HashMap;
+
+const EPOCH: u64 = 1;
+
+fn new_anchor_election(
+    validators: &[Author],
+    commit_events: Vec<CommitEvent>,
+) -> LeaderReputationAdapter {
+    let window_size = validators.len() * 10;
+    LeaderReputationAdapter::new(
+        EPOCH,
+        HashMap::from([(EPOCH, validators.to_vec())]),
+        vec![1; validators.len()],
+        Box::new(ProposerAndVoterHeuristic::new(
+            validators[0],
+            1000,
+            10,
+            1,
+            10,
+            validators.len(),
+            window_size,
+            false,
+        )),
+        window_size,
+        commit_events,
+    )
+}
+
+/// Anchors of every other round, where `crashed` never shows up: its anchor rounds fail, and
+/// it is never a parent.
+fn commit_events_with_crashed_validator(
+    validators: &[Author],
+    crashed: &Author,
+    num_rounds: Round,
+) -> Vec<CommitEvent> {
+    let live: Vec<_> = validators
+        .iter()
+        .filter(|v| *v != crashed)
+        .cloned()
+        .collect();
+    (1..num_rounds)
+        .step_by(2)
+        .map(|round| {
+            let author = live[(round as usize / 2) % live.len()];
+            let failed_authors = if round % 4 == 1 {
+                vec![*crashed]
+            } else {
+                vec![]
+            };
+            CommitEvent::new(
+                NodeId::new(EPOCH, round, author),
+                live.clone(),
+                failed_authors,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_leader_reputation_avoids_failed_anchors() {
+    let (_, validator_verifier) = random_validator_verifier(4, None, false);
+    let validators = validator_verifier.get_ordered_account_addresses();
+    let crashed = validators[1];
+
+    let anchor_election = new_anchor_election(
+        &validators,
+        commit_events_with_crashed_validator(&validators, &crashed, 40),
+    );
+
+    let crashed_anchors = (100..1100)
+        .filter(|round| anchor_election.get_anchor(*round) == crashed)
+        .count();
+    // The crashed validator has failed weight 1, against 1000 for each of the others
+    assert!(
+        crashed_anchors < 10,
+        "{} anchors for the crashed validator",
+        crashed_anchors
+    );
+}
+
+#[test]
+fn test_leader_reputation_is_deterministic() {
+    let (_, validator_verifier) = random_validator_verifier(4, None, false);
+    let validators = validator_verifier.get_ordered_account_addresses();
+    let commit_events = commit_events_with_crashed_validator(&validators, &validators[2], 40);
+
+    // A validator that ordered every anchor, and one that restarted and read them from storage
+    let mut live = new_anchor_election(&validators, vec![]);
+    for commit_event in commit_events_with_crashed_validator(&validators, &validators[2], 40) {
+        live.update_reputation(commit_event);
+    }
+    let mut restarted = new_anchor_election(&validators, commit_events);
+    // Anchors that are replayed after a restart don't count twice
+    for commit_event in commit_events_with_crashed_validator(&validators, &validators[2], 40)
+        .into_iter()
+        .rev()
+        .take(2)
+    {
+        restarted.update_reputation(commit_event);
+    }
+
+    for round in 40..200 {
+        assert_eq!(live.get_anchor(round), restarted.get_anchor(round));
+    }
+}
@@ -0,0 +1,511 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{
+    dag_test,
+    integration_tests::{connect_peer, create_network, create_storage, DagBootstrapUnit},
+};
+use crate::{
+    error::StateSyncError,
+    experimental::buffer_manager::OrderedBlocks,
+    network_tests::{NetworkPlayground, TwinId},
+    payload_manager::PayloadManager,
+    state_replication::{StateComputer, StateComputerCommitCallBackType},
+    test_utils::MockStorage,
+    transaction_deduper::TransactionDeduper,
+    transaction_shuffler::TransactionShuffler,
+};
+use aptos_consensus_types::{block::Block, executed_block::ExecutedBlock};
+use aptos_crypto::HashValue;
+use aptos_executor_types::{ExecutorResult, StateComputeResult};
+use aptos_infallible::Mutex;
+use aptos_logger::debug;
+use aptos_time_service::{MockTimeService, TimeService};
+use aptos_types::{
+    aggregate_signature::AggregateSignature,
+    block_info::{BlockInfo, Round},
+    epoch_state::EpochState,
+    ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+    on_chain_config::AnchorElectionMode,
+    validator_signer::ValidatorSigner,
+    validator_verifier::{random_validator_verifier, ValidatorVerifier},
+};
+use futures::StreamExt;
+use std::{collections::HashSet, fmt::Debug, sync::Arc, time::Duration};
+use tokio::{runtime::Handle, sync::Notify, task::AbortHandle};
+
+/// How long, in virtual time, the harness waits for the nodes to order before failing the test
+const WAIT_FOR_ORDERED_TIMEOUT: Duration = Duration::from_secs(60);
+/// Unix time of the nodes when the harness is created
+const START_TIMESTAMP: Duration = Duration::from_secs(1_000_000);
+/// Step by which the time service of the nodes follows the paused tokio clock
+const TICK: Duration = Duration::from_millis(1);
+
+/// A DAG instance run by the harness. A twin runs with the signer of another node, but with
+/// its own storage and network identity, so that the two of them equivocate.
+struct DagTwinsNode {
+    twin_id: TwinId,
+    signer: ValidatorSigner,
+    twin_of: Option<usize>,
+    storage: Arc<MockStorage>,
+    dag_storage: Arc<dag_test::MockStorage>,
+    runs: Vec<Arc<Mutex<NodeRun>>>,
+    abort_handles: Vec<AbortHandle>,
+}
+
+/// What a run of a node ordered and committed, a crash ends a run.
+#[derive(Default)]
+struct NodeRun {
+    ordered: Vec<BlockInfo>,
+    committed_blocks: Vec<BlockInfo>,
+    committed_ledger_infos: Vec<LedgerInfo>,
+}
+
+/// Stands in for the execution of a node: blocks execute to a dummy result, and committing
+/// records the blocks and the ledger info in the run of the node.
+///
+/// The commit callback isn't called. It would advance the ledger info that the DAG sends along
+/// with its nodes, and peers lagging behind that ledger info switch to DAG state sync, which
+/// the harness doesn't run.
+struct CommittingStateComputer {
+    run: Arc<Mutex<NodeRun>>,
+}
+
+impl CommittingStateComputer {
+    /// Executes the ordered blocks and commits them, like the buffer manager would once the
+    /// commit votes are aggregated.
+    async fn execute_and_commit(&self, ordered_blocks: OrderedBlocks) {
+        let OrderedBlocks {
+            ordered_blocks,
+            ordered_proof,
+            callback,
+        } = ordered_blocks;
+        self.run
+            .lock()
+            .ordered
+            .extend(ordered_blocks.iter().map(|block| block.block_info()));
+
+        let mut executed_blocks = vec![];
+        for block in ordered_blocks {
+            let result = self
+                .compute(block.block(), block.parent_id())
+                .await
+                .expect("Mock execution never fails");
+            executed_blocks.push(Arc::new(ExecutedBlock::new(block.block().clone(), result)));
+        }
+        let commit_info = executed_blocks
+            .last()
+            .expect("Ordered blocks are never empty")
+            .block_info();
+        let commit_proof = LedgerInfoWithSignatures::new(
+            LedgerInfo::new(
+                commit_info,
+                ordered_proof.ledger_info().consensus_data_hash(),
+            ),
+            AggregateSignature::empty(),
+        );
+        self.commit(&executed_blocks, commit_proof, callback)
+            .await
+            .expect("Mock commit never fails");
+    }
+}
+
+#[async_trait::async_trait]
+impl StateComputer for CommittingStateComputer {
+    async fn compute(
+        &self,
+        _block: &Block,
+        _parent_block_id: HashValue,
+    ) -> ExecutorResult<StateComputeResult> {
+        Ok(StateComputeResult::new_dummy())
+    }
+
+    async fn commit(
+        &self,
+        blocks: &[Arc<ExecutedBlock>],
+        finality_proof: LedgerInfoWithSignatures,
+        _callback: StateComputerCommitCallBackType,
+    ) -> ExecutorResult<()> {
+        let mut run = self.run.lock();
+        if let Some(last_committed) = run.committed_blocks.last() {
+            assert_eq!(
+                blocks[0].parent_id(),
+                last_committed.id(),
+                "Committed blocks don't extend the committed ledger"
+            );
+        }
+        run.committed_blocks
+            .extend(blocks.iter().map(|block| block.block_info()));
+        run.committed_ledger_infos
+            .push(finality_proof.ledger_info().clone());
+        Ok(())
+    }
+
+    async fn sync_to(&self, _target: LedgerInfoWithSignatures) -> Result<(), StateSyncError> {
+        unreachable!("The harness doesn't run state sync")
+    }
+
+    fn new_epoch(
+        &self,
+        _: &EpochState,
+        _: Arc<PayloadManager>,
+        _: Arc<dyn TransactionShuffler>,
+        _: Option<u64>,
+        _: Arc<dyn TransactionDeduper>,
+    ) {
+    }
+
+    fn end_epoch(&self) {}
+}
+
+/// Runs several DAG instances over the `NetworkPlayground`, and lets the test partition the
+/// network, delay messages, and crash and restart nodes, while checking that the honest nodes
+/// order the same anchors, and commit the same blocks and ledger infos.
+///
+/// Nodes are identified by their index: the validators first, then the twins in the order
+/// they were given. A node is honest if its validator has no twin.
+///
+/// The harness must run on a paused tokio runtime, i.e. `#[tokio::test(start_paused = true)]`:
+/// the time service of the nodes is a mock that advances with the tokio clock, so the tests
+/// run in virtual time.
+///
+/// Anchors are elected round robin, unless `with_anchor_election_mode` says otherwise.
+pub(super) struct DagTwinsHarness {
+    playground: NetworkPlayground,
+    anchor_election_mode: AnchorElectionMode,
+    time_service: TimeService,
+    ticker_handle: AbortHandle,
+    ordered_notify: Arc<Notify>,
+    signers: Vec<ValidatorSigner>,
+    validators: ValidatorVerifier,
+    nodes: Vec<DagTwinsNode>,
+    partitions: Vec<Vec<usize>>,
+    crashed: HashSet<usize>,
+}
+
+impl DagTwinsHarness {
+    /// Creates `num_validators` nodes, plus one twin for each of the validators in `twins_of`.
+    pub(super) fn new(num_validators: usize, twins_of: &[usize]) -> Self {
+        let (signers, validators) = random_validator_verifier(num_validators, None, false);
+        let playground = NetworkPlayground::new(Handle::current());
+
+        let mock_time_service = MockTimeService::new();
+        mock_time_service.advance(START_TIMESTAMP);
+        let time_service = TimeService::from_mock(mock_time_service);
+        let ticker = time_service.clone().into_mock();
+        let ticker_handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(TICK).await;
+                ticker.advance(TICK);
+            }
+        })
+        .abort_handle();
+
+        let nodes = (0..num_validators)
+            .map(|index| (index, None))
+            .chain(twins_of.iter().map(|twin_of| (*twin_of, Some(*twin_of))))
+            .enumerate()
+            .map(|(id, (validator, twin_of))| {
+                let signer = signers[validator].clone();
+                let (storage, dag_storage) = create_storage(&signers, &validators);
+                DagTwinsNode {
+                    twin_id: TwinId {
+                        id,
+                        author: signer.author(),
+                    },
+                    signer,
+                    twin_of,
+                    storage,
+                    dag_storage,
+                    runs: vec![],
+                    abort_handles: vec![],
+                }
+            })
+            .collect();
+
+        for signer in &signers {
+            connect_peer(&playground, signer.author());
+        }
+
+        Self {
+            playground,
+            anchor_election_mode: AnchorElectionMode::RoundRobin,
+            time_service,
+            ticker_handle,
+            ordered_notify: Arc::new(Notify::new()),
+            signers,
+            validators,
+            nodes,
+            partitions: vec![],
+            crashed: HashSet::new(),
+        }
+    }
+
+    /// Sets how all the nodes elect anchors, before they are started.
+    pub(super) fn with_anchor_election_mode(
+        mut self,
+        anchor_election_mode: AnchorElectionMode,
+    ) -> Self {
+        self.anchor_election_mode = anchor_election_mode;
+        self
+    }
+
+    pub(super) fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The nodes whose validator has no twin.
+    pub(super) fn honest_nodes(&self) -> Vec<usize> {
+        let equivocating: HashSet<_> = self.nodes.iter().filter_map(|n| n.twin_of).collect();
+        (0..self.signers.len())
+            .filter(|index| !equivocating.contains(index))
+            .collect()
+    }
+
+    /// Starts all the nodes.
+    pub(super) fn start(&mut self) {
+        for index in 0..self.nodes.len() {
+            self.start_node(index);
+        }
+    }
+
+    fn start_node(&mut self, index: usize) {
+        let node = &self.nodes[index];
+        let (network, network_events) = create_network(
+            &mut self.playground,
+            node.twin_id.id,
+            node.twin_id.author,
+            self.validators.clone(),
+        );
+        let (unit, mut ordered_rx) = DagBootstrapUnit::make(
+            node.twin_id.author,
+            1,
+            node.signer.clone(),
+            node.storage.clone(),
+            node.dag_storage.clone(),
+            network,
+            self.time_service.clone(),
+            network_events,
+            self.anchor_election_mode.clone(),
+        );
+
+        let run = Arc::new(Mutex::new(NodeRun::default()));
+        let state_computer = CommittingStateComputer { run: run.clone() };
+        let ordered_notify = self.ordered_notify.clone();
+        let collect_handle = tokio::spawn(async move {
+            while let Some(ordered_blocks) = ordered_rx.next().await {
+                state_computer.execute_and_commit(ordered_blocks).await;
+                ordered_notify.notify_waiters();
+            }
+        });
+
+        let node = &mut self.nodes[index];
+        node.abort_handles = unit.abort_handles().to_vec();
+        node.abort_handles
+            .push(tokio::spawn(unit.start()).abort_handle());
+        node.abort_handles.push(collect_handle.abort_handle());
+        node.runs.push(run);
+
+        // Adding the node to the playground again resets the messages dropped for it
+        self.apply_network();
+    }
+
+    /// Splits the network in the given partitions, replacing the previous ones.
+    pub(super) fn partition(&mut self, partitions: Vec<Vec<usize>>) {
+        self.partitions = partitions;
+        self.apply_network();
+    }
+
+    /// Removes all the partitions.
+    pub(super) fn heal(&mut self) {
+        self.partitions.clear();
+        self.apply_network();
+    }
+
+    /// Delays the messages sent from `src` to `dst`.
+    pub(super) fn delay(&self, src: usize, dst: usize, delay: Duration) {
+        self.playground
+            .delay_rpc(self.nodes[src].twin_id, self.nodes[dst].twin_id, delay);
+    }
+
+    /// Stops the node, which keeps its storage, and disconnects it from the network.
+    pub(super) fn crash(&mut self, index: usize) {
+        debug!("Crashing node {}", index);
+        for handle in self.nodes[index].abort_handles.drain(..) {
+            handle.abort();
+        }
+        self.crashed.insert(index);
+        self.apply_network();
+    }
+
+    /// Starts a crashed node again from its storage.
+    pub(super) fn restart(&mut self, index: usize) {
+        debug!("Restarting node {}", index);
+        assert!(self.crashed.remove(&index), "node {} is not crashed", index);
+        self.start_node(index);
+    }
+
+    fn apply_network(&self) {
+        self.playground.heal_network();
+        for (i, first) in self.partitions.iter().enumerate() {
+            for second in self.partitions.iter().skip(i + 1) {
+                self.playground
+                    .split_network(self.twin_ids(first), self.twin_ids(second));
+            }
+        }
+        for index in &self.crashed {
+            let others: Vec<_> = (0..self.nodes.len()).filter(|i| i != index).collect();
+            self.playground
+                .split_network(self.twin_ids(&[*index]), self.twin_ids(&others));
+        }
+    }
+
+    fn twin_ids(&self, indices: &[usize]) -> Vec<TwinId> {
+        indices.iter().map(|i| self.nodes[*i].twin_id).collect()
+    }
+
+    /// Blocks ordered by the current run of the node.
+    pub(super) fn ordered_blocks(&self, index: usize) -> Vec<BlockInfo> {
+        self.nodes[index]
+            .runs
+            .last()
+            .map_or(vec![], |run| run.lock().ordered.clone())
+    }
+
+    /// Blocks committed by the current run of the node.
+    pub(super) fn committed_blocks(&self, index: usize) -> Vec<BlockInfo> {
+        self.nodes[index]
+            .runs
+            .last()
+            .map_or(vec![], |run| run.lock().committed_blocks.clone())
+    }
+
+    /// Round of the last block ordered by the current run of the node.
+    pub(super) fn highest_ordered_round(&self, index: usize) -> Option<Round> {
+        self.ordered_blocks(index).last().map(|block| block.round())
+    }
+
+    /// Waits until the current run of each of the given nodes ordered at least `num_blocks`
+    /// blocks, and fails the test if that takes too long.
+    pub(super) async fn wait_for_ordered(&self, indices: &[usize], num_blocks: usize) {
+        self.wait_until(indices, "ordered blocks", num_blocks, |index| {
+            self.ordered_blocks(index).len() >= num_blocks
+        })
+        .await;
+    }
+
+    /// Waits until the current run of each of the given nodes ordered a block of a round
+    /// higher than `round`, and fails the test if that takes too long.
+    pub(super) async fn wait_for_round(&self, indices: &[usize], round: Round) {
+        self.wait_until(indices, "ordered round above", round, |index| {
+            self.highest_ordered_round(index)
+                .map_or(false, |highest| highest > round)
+        })
+        .await;
+    }
+
+    async fn wait_until(
+        &self,
+        indices: &[usize],
+        waiting_for: &str,
+        target: impl std::fmt::Debug,
+        done: impl Fn(usize) -> bool,
+    ) {
+        let wait = async {
+            loop {
+                // Registered before checking, so that blocks ordered in between wake us up
+                let notified = self.ordered_notify.notified();
+                if indices.iter().all(|index| done(*index)) {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        if tokio::time::timeout(WAIT_FOR_ORDERED_TIMEOUT, wait)
+            .await
+            .is_err()
+        {
+            let progress: Vec<_> = indices
+                .iter()
+                .map(|index| {
+                    (
+                        *index,
+                        self.ordered_blocks(*index).len(),
+                        self.highest_ordered_round(*index),
+                    )
+                })
+                .collect();
+            panic!(
+                "Timed out waiting for {} {:?}, (node, ordered, highest round): {:?}",
+                waiting_for, target, progress
+            );
+        }
+    }
+
+    /// Checks that every run of the honest nodes ordered the same anchors, and committed the
+    /// same blocks and ledger infos, i.e. that of any two runs, one ordered and committed a
+    /// prefix of what the other one did.
+    pub(super) fn check_safety(&self) {
+        let runs: Vec<(usize, Arc<Mutex<NodeRun>>)> = self
+            .honest_nodes()
+            .into_iter()
+            .flat_map(|index| {
+                self.nodes[index]
+                    .runs
+                    .iter()
+                    .map(move |run| (index, run.clone()))
+            })
+            .collect();
+
+        let ordered: Vec<_> = runs
+            .iter()
+            .map(|(index, run)| (*index, run.lock().ordered.clone()))
+            .collect();
+        check_prefixes("ordered blocks", &ordered);
+        let committed_blocks: Vec<_> = runs
+            .iter()
+            .map(|(index, run)| (*index, run.lock().committed_blocks.clone()))
+            .collect();
+        check_prefixes("committed blocks", &committed_blocks);
+        let committed_ledger_infos: Vec<_> = runs
+            .iter()
+            .map(|(index, run)| (*index, run.lock().committed_ledger_infos.clone()))
+            .collect();
+        check_prefixes("committed ledger infos", &committed_ledger_infos);
+
+        // Each run only commits what it ordered.
+        for ((index, ordered), (_, committed)) in ordered.iter().zip(committed_blocks.iter()) {
+            assert!(
+                ordered.starts_with(committed),
+                "Node {} committed blocks it didn't order",
+                index
+            );
+        }
+    }
+}
+
+/// Checks that of any two of the sequences, one is a prefix of the other.
+fn check_prefixes<T: Debug + PartialEq>(what: &str, sequences: &[(usize, Vec<T>)]) {
+    for (i, (first_node, first)) in sequences.iter().enumerate() {
+        for (second_node, second) in sequences.iter().skip(i + 1) {
+            for (position, (a, b)) in first.iter().zip(second.iter()).enumerate() {
+                assert_eq!(
+                    a, b,
+                    "Nodes {} and {} have different {} at position {}",
+                    first_node, second_node, what, position
+                );
+            }
+        }
+    }
+}
+
+impl Drop for DagTwinsHarness {
+    fn drop(&mut self) {
+        self.ticker_handle.abort();
+        for node in &self.nodes {
+            for handle in &node.abort_handles {
+                handle.abort();
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use super::dag_twins_harness::DagTwinsHarness;
+use aptos_types::on_chain_config::{AnchorElectionMode, DagConsensusConfigV2};
+use std::time::Duration;
+
+/// 4 validators, validator 0 has a twin. Both twins broadcast their own node for every round,
+/// the honest validators must keep ordering the same anchors and committing the same blocks.
+#[tokio::test(start_paused = true)]
+async fn test_dag_twins_equivocation() {
+    let mut harness = DagTwinsHarness::new(4, &[0]);
+    harness.start();
+
+    let honest = harness.honest_nodes();
+    harness.wait_for_ordered(&honest, 10).await;
+    harness.check_safety();
+    for index in honest {
+        assert_eq!(
+            harness.committed_blocks(index),
+            harness.ordered_blocks(index)
+        );
+    }
+}
+
+/// 4 validators, validator 0 has a twin (node 4). The network is split in [0, 1] and [2, 3, 4],
+/// so only the second partition has a quorum thanks to the twin. Once the network heals,
+/// validator 1 must catch up with the same ordered anchors, and all the honest validators must
+/// order past the rounds ordered during the partition.
+#[tokio::test(start_paused = true)]
+async fn test_dag_twins_partition() {
+    let mut harness = DagTwinsHarness::new(4, &[0]);
+    harness.partition(vec![vec![0, 1], vec![2, 3, 4]]);
+    harness.start();
+
+    harness.wait_for_ordered(&[2, 3], 5).await;
+    assert!(harness.ordered_blocks(1).is_empty());
+    harness.check_safety();
+    let partitioned_round = [2, 3]
+        .iter()
+        .filter_map(|index| harness.highest_ordered_round(*index))
+        .max()
+        .unwrap();
+
+    harness.heal();
+    let honest = harness.honest_nodes();
+    harness.wait_for_round(&honest, partitioned_round).await;
+    harness.check_safety();
+}
+
+/// 4 validators and no twins, with messages from validator 0 and between some of the other
+/// validators delayed.
+#[tokio::test(start_paused = true)]
+async fn test_dag_twins_delays() {
+    let mut harness = DagTwinsHarness::new(4, &[]);
+    for dst in 1..harness.num_nodes() {
+        harness.delay(0, dst, Duration::from_millis(300));
+    }
+    harness.delay(1, 2, Duration::from_millis(50));
+    harness.delay(3, 1, Duration::from_millis(100));
+    harness.start();
+
+    let honest = harness.honest_nodes();
+    harness.wait_for_ordered(&honest, 10).await;
+    harness.check_safety();
+}
+
+/// 4 validators and no twins. Validator 3 crashes while the others keep ordering, and is
+/// restarted from its storage, after which it must order the same anchors again and all the
+/// validators must order past the round reached when it restarted.
+#[tokio::test(start_paused = true)]
+async fn test_dag_twins_crash_restart() {
+    let mut harness = DagTwinsHarness::new(4, &[]);
+    harness.start();
+
+    harness.wait_for_ordered(&[0, 1, 2, 3], 3).await;
+    harness.crash(3);
+    let crashed_round = harness.highest_ordered_round(3).unwrap();
+    harness.wait_for_round(&[0, 1, 2], crashed_round).await;
+    harness.check_safety();
+
+    let restarted_round = [0, 1, 2]
+        .iter()
+        .filter_map(|index| harness.highest_ordered_round(*index))
+        .max()
+        .unwrap();
+    harness.restart(3);
+    harness.wait_for_round(&[0, 1, 2, 3], restarted_round).await;
+    harness.check_safety();
+}
+
+/// 4 validators and no twins, electing anchors by reputation. Messages from validator 0 are
+/// delayed, so its anchors tend to fail and its reputation changes while ordering. Validator 3
+/// crashes and is restarted. The harness doesn't move the ledger of the nodes, so it orders
+/// from the start again, and must rebuild the same reputation and elect the same anchors.
+#[tokio::test(start_paused = true)]
+async fn test_dag_twins_leader_reputation() {
+    let anchor_election_mode = DagConsensusConfigV2::default().anchor_election_mode;
+    assert!(matches!(
+        anchor_election_mode,
+        AnchorElectionMode::LeaderReputation(_)
+    ));
+    let mut harness = DagTwinsHarness::new(4, &[]).with_anchor_election_mode(anchor_election_mode);
+    for dst in 1..harness.num_nodes() {
+        harness.delay(0, dst, Duration::from_millis(300));
+    }
+    harness.start();
+
+    harness.wait_for_ordered(&[0, 1, 2, 3], 5).await;
+    harness.check_safety();
+    harness.crash(3);
+    let crashed_round = harness.highest_ordered_round(3).unwrap();
+    harness.wait_for_round(&[0, 1, 2], crashed_round).await;
+    harness.check_safety();
+
+    let restarted_round = [0, 1, 2]
+        .iter()
+        .filter_map(|index| harness.highest_ordered_round(*index))
+        .max()
+        .unwrap();
+    harness.restart(3);
+    harness.wait_for_round(&[0, 1, 2, 3], restarted_round).await;
+    harness.check_safety();
+}
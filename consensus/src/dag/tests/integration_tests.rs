@@ -28,6 +28,7 @@ use aptos_time_service::TimeService;
 use aptos_types::{
     epoch_state::EpochState,
     ledger_info::generate_ledger_info_with_sig,
+    on_chain_config::AnchorElectionMode,
     validator_signer::ValidatorSigner,
     validator_verifier::{random_validator_verifier, ValidatorVerifier},
 };
@@ -39,9 +40,9 @@ use futures::{
 use futures_channel::mpsc::UnboundedReceiver;
 use maplit::hashmap;
 use std::sync::Arc;
-use tokio::task::JoinHandle;
+use tokio::task::{AbortHandle, JoinHandle};
 
-struct DagBootstrapUnit {
+pub(super) struct DagBootstrapUnit {
     nh_task_handle: JoinHandle<StateSyncStatus>,
     df_task_handle: JoinHandle<()>,
     dag_rpc_tx: aptos_channel::Sender<Author, IncomingDAGRequest>,
@@ -50,24 +51,23 @@ struct DagBootstrapUnit {
 }
 
 impl DagBootstrapUnit {
-    fn make(
+    pub(super) fn make(
         self_peer: Author,
         epoch: u64,
         signer: ValidatorSigner,
         storage: Arc<MockStorage>,
+        dag_storage: Arc<dag_test::MockStorage>,
         network: NetworkSender,
         time_service: TimeService,
         network_events: Box<
             Select<NetworkEvents<ConsensusMsg>, aptos_channels::Receiver<Event<ConsensusMsg>>>,
         >,
-        all_signers: Vec<ValidatorSigner>,
+        anchor_election_mode: AnchorElectionMode,
     ) -> (Self, UnboundedReceiver<OrderedBlocks>) {
         let epoch_state = EpochState {
             epoch,
             verifier: storage.get_validator_set().into(),
         };
-        let ledger_info = generate_ledger_info_with_sig(&all_signers, storage.get_ledger_info());
-        let dag_storage = dag_test::MockStorage::new_with_ledger_info(ledger_info);
 
         let network = Arc::new(network);
 
@@ -82,7 +82,7 @@ impl DagBootstrapUnit {
                 signer,
                 Arc::new(epoch_state),
                 storage.get_ledger_info(),
-                Arc::new(dag_storage),
+                dag_storage,
                 network.clone(),
                 network.clone(),
                 network.clone(),
@@ -90,6 +90,7 @@ impl DagBootstrapUnit {
                 payload_manager,
                 payload_client,
                 state_computer,
+                anchor_election_mode,
             );
 
         (
@@ -103,7 +104,16 @@ impl DagBootstrapUnit {
         )
     }
 
-    async fn start(mut self) {
+    /// Handles to stop the DAG tasks, e.g. to simulate a crash. The network event loop is
+    /// stopped by aborting the task running `start`.
+    pub(super) fn abort_handles(&self) -> [AbortHandle; 2] {
+        [
+            self.nh_task_handle.abort_handle(),
+            self.df_task_handle.abort_handle(),
+        ]
+    }
+
+    pub(super) async fn start(mut self) {
         loop {
             match self.network_events.next().await.unwrap() {
                 Event::RpcRequest(sender, msg, protocol, response_sender) => match msg {
@@ -125,7 +135,7 @@ impl DagBootstrapUnit {
     }
 }
 
-fn create_network(
+pub(super) fn create_network(
     playground: &mut NetworkPlayground,
     id: usize,
     author: Author,
@@ -164,29 +174,44 @@ fn create_network(
     (network, all_network_events)
 }
 
+/// Registers the connection to the given peer, so that consensus messages can be sent to it.
+pub(super) fn connect_peer(playground: &NetworkPlayground, peer_id: Author) {
+    let mut conn_meta = ConnectionMetadata::mock(peer_id);
+    conn_meta.application_protocols = ProtocolIdSet::from_iter([
+        ProtocolId::ConsensusDirectSendJson,
+        ProtocolId::ConsensusDirectSendBcs,
+        ProtocolId::ConsensusRpcBcs,
+    ]);
+    let peer_network_id = PeerNetworkId::new(NetworkId::Validator, peer_id);
+    playground
+        .peer_protocols()
+        .insert_connection_metadata(peer_network_id, conn_meta)
+        .unwrap();
+}
+
+/// Creates the storages of a validator at genesis.
+pub(super) fn create_storage(
+    signers: &[ValidatorSigner],
+    validators: &ValidatorVerifier,
+) -> (Arc<MockStorage>, Arc<dag_test::MockStorage>) {
+    let (_, storage) = MockStorage::start_for_testing(validators.into());
+    let ledger_info = generate_ledger_info_with_sig(signers, storage.get_ledger_info());
+    let dag_storage = Arc::new(dag_test::MockStorage::new_with_ledger_info(ledger_info));
+    (storage, dag_storage)
+}
+
 fn bootstrap_nodes(
     playground: &mut NetworkPlayground,
     signers: Vec<ValidatorSigner>,
     validators: ValidatorVerifier,
 ) -> (Vec<DagBootstrapUnit>, Vec<UnboundedReceiver<OrderedBlocks>>) {
-    let peers_and_metadata = playground.peer_protocols();
     let (nodes, ordered_node_receivers) = signers
         .iter()
         .enumerate()
         .map(|(id, signer)| {
-            let peer_id = signer.author();
-            let mut conn_meta = ConnectionMetadata::mock(peer_id);
-            conn_meta.application_protocols = ProtocolIdSet::from_iter([
-                ProtocolId::ConsensusDirectSendJson,
-                ProtocolId::ConsensusDirectSendBcs,
-                ProtocolId::ConsensusRpcBcs,
-            ]);
-            let peer_network_id = PeerNetworkId::new(NetworkId::Validator, peer_id);
-            peers_and_metadata
-                .insert_connection_metadata(peer_network_id, conn_meta)
-                .unwrap();
-
-            let (_, storage) = MockStorage::start_for_testing((&validators).into());
+            connect_peer(playground, signer.author());
+
+            let (storage, dag_storage) = create_storage(&signers, &validators);
             let (network, network_events) =
                 create_network(playground, id, signer.author(), validators.clone());
 
@@ -195,10 +220,11 @@ fn bootstrap_nodes(
                 1,
                 signer.clone(),
                 storage,
+                dag_storage,
                 network,
                 aptos_time_service::TimeService::real(),
                 network_events,
-                signers.clone(),
+                AnchorElectionMode::RoundRobin,
             )
         })
         .unzip();
@@ -75,8 +75,8 @@ use aptos_types::{
     epoch_change::EpochChangeProof,
     epoch_state::EpochState,
     on_chain_config::{
-        LeaderReputationType, OnChainConfigPayload, OnChainConfigProvider, OnChainConsensusConfig,
-        OnChainExecutionConfig, ProposerElectionType, ValidatorSet,
+        AnchorElectionMode, LeaderReputationType, OnChainConfigPayload, OnChainConfigProvider,
+        OnChainConsensusConfig, OnChainExecutionConfig, ProposerElectionType, ValidatorSet,
     },
     validator_signer::ValidatorSigner,
     validator_verifier::ValidatorVerifier,
@@ -257,24 +257,15 @@ impl<P: OnChainConfigProvider> EpochManager<P> {
                 ) = match &leader_reputation_type {
                     LeaderReputationType::ProposerAndVoter(proposer_and_voter_config)
                     | LeaderReputationType::ProposerAndVoterV2(proposer_and_voter_config) => {
-                        let proposer_window_size = proposers.len()
-                            * proposer_and_voter_config.proposer_window_num_validators_multiplier;
-                        let voter_window_size = proposers.len()
-                            * proposer_and_voter_config.voter_window_num_validators_multiplier;
-                        let heuristic: Box<dyn ReputationHeuristic> =
-                            Box::new(ProposerAndVoterHeuristic::new(
-                                self.author,
-                                proposer_and_voter_config.active_weight,
-                                proposer_and_voter_config.inactive_weight,
-                                proposer_and_voter_config.failed_weight,
-                                proposer_and_voter_config.failure_threshold_percent,
-                                voter_window_size,
-                                proposer_window_size,
-                                leader_reputation_type.use_reputation_window_from_stale_end(),
-                            ));
+                        let (heuristic, window_size) = ProposerAndVoterHeuristic::from_config(
+                            self.author,
+                            leader_reputation_type,
+                            proposers.len(),
+                        );
+                        let heuristic: Box<dyn ReputationHeuristic> = Box::new(heuristic);
                         (
                             heuristic,
-                            std::cmp::max(proposer_window_size, voter_window_size),
+                            window_size,
                             proposer_and_voter_config.weight_by_voting_power,
                             proposer_and_voter_config.use_history_from_previous_epoch_max_count,
                         )
@@ -1046,16 +1037,37 @@ impl<P: OnChainConfigProvider> EpochManager<P> {
             reset_tx,
         ));
 
-        let onchain_dag_consensus_config = onchain_consensus_config.unwrap_dag_config_v1();
+        let onchain_dag_consensus_config = onchain_consensus_config.unwrap_dag_config();
+        let (use_history_from_previous_epoch_max_count, reputation_window_size) =
+            match &onchain_dag_consensus_config.anchor_election_mode {
+                AnchorElectionMode::RoundRobin => (0, 0),
+                AnchorElectionMode::LeaderReputation(
+                    LeaderReputationType::ProposerAndVoter(config)
+                    | LeaderReputationType::ProposerAndVoterV2(config),
+                ) => (
+                    config.use_history_from_previous_epoch_max_count,
+                    epoch_state.verifier.len()
+                        * std::cmp::max(
+                            config.proposer_window_num_validators_multiplier,
+                            config.voter_window_num_validators_multiplier,
+                        ),
+                ),
+            };
         let epoch_to_validators = self.extract_epoch_proposers(
             &epoch_state,
-            onchain_dag_consensus_config.dag_ordering_causal_history_window as u32,
+            std::cmp::max(
+                onchain_dag_consensus_config.dag_ordering_causal_history_window as u32,
+                use_history_from_previous_epoch_max_count,
+            ),
             epoch_state.verifier.get_ordered_account_addresses(),
-            onchain_dag_consensus_config.dag_ordering_causal_history_window as u64,
+            std::cmp::max(
+                onchain_dag_consensus_config.dag_ordering_causal_history_window,
+                reputation_window_size,
+            ) as u64,
         );
         let dag_storage = Arc::new(StorageAdapter::new(
             epoch,
-            epoch_to_validators,
+            epoch_to_validators.clone(),
             self.storage.consensus_db(),
             self.storage.aptos_db(),
         ));
@@ -1075,6 +1087,8 @@ impl<P: OnChainConfigProvider> EpochManager<P> {
             payload_manager,
             payload_client,
             state_computer,
+            onchain_dag_consensus_config,
+            epoch_to_validators,
         );
 
         let (dag_rpc_tx, dag_rpc_rx) = aptos_channel::new(QueueStyle::FIFO, 10, None);
@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    block_storage::tracing::{observe_block, BlockStage},
+    block_storage::tracing::{observe_block, observe_round, BlockStage},
     counters,
     experimental::{
         buffer::{Buffer, Cursor},
@@ -22,6 +22,7 @@ use crate::{
 };
 use aptos_consensus_types::{
     common::Author, executed_block::ExecutedBlock, experimental::commit_decision::CommitDecision,
+    round_timeline::RoundEvent,
 };
 use aptos_crypto::HashValue;
 use aptos_logger::prelude::*;
@@ -429,6 +430,9 @@ impl BufferManager {
             );
             return;
         }
+        for block in &executed_blocks {
+            observe_round(block.epoch(), block.round(), RoundEvent::Executed);
+        }
 
         // Handle reconfiguration timestamp reconciliation.
         // end epoch timestamp is set to the first block that causes the reconfiguration.
@@ -487,6 +491,9 @@ impl BufferManager {
                 // we have found the buffer item
                 let mut signed_item = item.advance_to_signed(self.author, signature);
                 let signed_item_mut = signed_item.unwrap_signed_mut();
+                for block in &signed_item_mut.executed_blocks {
+                    observe_round(block.epoch(), block.round(), RoundEvent::Signed);
+                }
                 let maybe_proposer = signed_item_mut
                     .executed_blocks
                     .last()
@@ -3,10 +3,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    block_storage::tracing::observe_round,
     experimental::pipeline_phase::StatelessPipeline,
     state_replication::{StateComputer, StateComputerCommitCallBackType},
 };
-use aptos_consensus_types::executed_block::ExecutedBlock;
+use aptos_consensus_types::{executed_block::ExecutedBlock, round_timeline::RoundEvent};
 use aptos_executor_types::ExecutorResult;
 use aptos_types::ledger_info::LedgerInfoWithSignatures;
 use async_trait::async_trait;
@@ -68,8 +69,15 @@ impl StatelessPipeline for PersistingPhase {
             callback,
         } = req;
 
-        self.persisting_handle
+        let result = self
+            .persisting_handle
             .commit(&blocks, commit_ledger_info, callback)
-            .await
+            .await;
+        if result.is_ok() {
+            for block in &blocks {
+                observe_round(block.epoch(), block.round(), RoundEvent::Committed);
+            }
+        }
+        result
     }
 }
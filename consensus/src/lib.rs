@@ -36,6 +36,8 @@ mod persistent_liveness_storage;
 mod quorum_store;
 mod recovery_manager;
 mod round_manager;
+#[cfg(test)]
+mod simulation;
 mod state_computer;
 mod state_replication;
 #[cfg(any(test, feature = "fuzzing"))]
@@ -59,6 +61,8 @@ mod transaction_shuffler;
 mod txn_hash_and_authenticator_deduper;
 
 use aptos_metrics_core::IntGauge;
+/// Required by the inspection service
+pub use block_storage::tracing::ROUND_TIMELINES;
 pub use consensusdb::create_checkpoint;
 /// Required by the smoke tests
 pub use consensusdb::CONSENSUS_DB_NAME;
@@ -7,17 +7,20 @@ use crate::{
         CHAIN_HEALTH_PARTICIPATING_NUM_VALIDATORS, CHAIN_HEALTH_PARTICIPATING_VOTING_POWER,
         CHAIN_HEALTH_REPUTATION_PARTICIPATING_VOTING_POWER_FRACTION,
         CHAIN_HEALTH_TOTAL_NUM_VALIDATORS, CHAIN_HEALTH_TOTAL_VOTING_POWER,
-        CHAIN_HEALTH_WINDOW_SIZES, COMMITTED_PROPOSALS_IN_WINDOW, COMMITTED_VOTES_IN_WINDOW,
-        CONSENSUS_PARTICIPATION_STATUS, FAILED_PROPOSALS_IN_WINDOW,
-        LEADER_REPUTATION_ROUND_HISTORY_SIZE,
+        CHAIN_HEALTH_WINDOW_SIZES, CONSENSUS_PARTICIPATION_STATUS,
     },
-    liveness::proposer_election::{choose_index, ProposerElection},
+    liveness::proposer_election::ProposerElection,
 };
 use anyhow::{ensure, Result};
-use aptos_bitvec::BitVec;
 use aptos_consensus_types::common::{Author, Round};
 use aptos_crypto::HashValue;
 use aptos_infallible::{Mutex, MutexGuard};
+use aptos_leader_reputation::choose_proposer;
+pub use aptos_leader_reputation::{
+    CandidateStats, CandidateWeighting, MetadataBackend, NewBlockEventAggregation,
+    ProposerAndVoterHeuristic, ProposerAndVoterWeighting, ReputationHeuristic,
+    SuccessRateWeighting, WeightedStatsHeuristic,
+};
 use aptos_logger::prelude::*;
 use aptos_storage_interface::{DbReader, Order};
 use aptos_types::{
@@ -28,21 +31,9 @@ use aptos_types::{
 use std::{
     cmp::max,
     collections::{HashMap, HashSet},
-    convert::TryFrom,
     sync::Arc,
 };
 
-/// Interface to query committed NewBlockEvent.
-pub trait MetadataBackend: Send + Sync {
-    /// Return a contiguous NewBlockEvent window in which last one is at target_round or
-    /// latest committed, return all previous one if not enough.
-    fn get_block_metadata(
-        &self,
-        target_epoch: u64,
-        target_round: Round,
-    ) -> (Vec<NewBlockEvent>, HashValue);
-}
-
 #[derive(Debug, Clone)]
 pub struct VersionedNewBlockEvent {
     /// event
@@ -206,350 +197,6 @@ impl MetadataBackend for AptosDBBackend {
     }
 }
 
-/// Interface to calculate weights for proposers based on history.
-pub trait ReputationHeuristic: Send + Sync {
-    /// Return the weights of all candidates based on the history.
-    fn get_weights(
-        &self,
-        epoch: u64,
-        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
-        history: &[NewBlockEvent],
-    ) -> Vec<u64>;
-}
-
-pub struct NewBlockEventAggregation {
-    // Window sizes are in number of succesfull blocks, not number of rounds.
-    // i.e. we can be looking at different number of rounds for the same window,
-    // dependig on how many failures we have.
-    voter_window_size: usize,
-    proposer_window_size: usize,
-    reputation_window_from_stale_end: bool,
-}
-
-impl NewBlockEventAggregation {
-    pub fn new(
-        voter_window_size: usize,
-        proposer_window_size: usize,
-        reputation_window_from_stale_end: bool,
-    ) -> Self {
-        Self {
-            voter_window_size,
-            proposer_window_size,
-            reputation_window_from_stale_end,
-        }
-    }
-
-    pub fn bitvec_to_voters<'a>(
-        validators: &'a [Author],
-        bitvec: &BitVec,
-    ) -> Result<Vec<&'a Author>, String> {
-        if BitVec::required_buckets(validators.len() as u16) != bitvec.num_buckets() {
-            return Err(format!(
-                "bitvec bucket {} does not match validators len {}",
-                bitvec.num_buckets(),
-                validators.len()
-            ));
-        }
-
-        Ok(validators
-            .iter()
-            .enumerate()
-            .filter_map(|(index, validator)| {
-                if bitvec.is_set(index as u16) {
-                    Some(validator)
-                } else {
-                    None
-                }
-            })
-            .collect())
-    }
-
-    pub fn indices_to_validators<'a>(
-        validators: &'a [Author],
-        indices: &[u64],
-    ) -> Result<Vec<&'a Author>, String> {
-        indices
-            .iter()
-            .map(|index| {
-                usize::try_from(*index)
-                    .map_err(|_err| format!("index {} out of bounds", index))
-                    .and_then(|index| {
-                        validators.get(index).ok_or(format!(
-                            "index {} is larger than number of validators {}",
-                            index,
-                            validators.len()
-                        ))
-                    })
-            })
-            .collect()
-    }
-
-    fn history_iter<'a>(
-        history: &'a [NewBlockEvent],
-        epoch_to_candidates: &'a HashMap<u64, Vec<Author>>,
-        window_size: usize,
-        from_stale_end: bool,
-    ) -> impl Iterator<Item = &'a NewBlockEvent> {
-        let sub_history = if from_stale_end {
-            let start = if history.len() > window_size {
-                history.len() - window_size
-            } else {
-                0
-            };
-
-            &history[start..]
-        } else {
-            if !history.is_empty() {
-                assert!(
-                    (
-                        history.first().unwrap().epoch(),
-                        history.first().unwrap().round()
-                    ) >= (
-                        history.last().unwrap().epoch(),
-                        history.last().unwrap().round()
-                    )
-                );
-            }
-            let end = if history.len() > window_size {
-                window_size
-            } else {
-                history.len()
-            };
-
-            &history[..end]
-        };
-        sub_history
-            .iter()
-            .filter(move |&meta| epoch_to_candidates.contains_key(&meta.epoch()))
-    }
-
-    pub fn get_aggregated_metrics(
-        &self,
-        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
-        history: &[NewBlockEvent],
-        author: &Author,
-    ) -> (
-        HashMap<Author, u32>,
-        HashMap<Author, u32>,
-        HashMap<Author, u32>,
-    ) {
-        let votes = self.count_votes(epoch_to_candidates, history);
-        let proposals = self.count_proposals(epoch_to_candidates, history);
-        let failed_proposals = self.count_failed_proposals(epoch_to_candidates, history);
-
-        COMMITTED_PROPOSALS_IN_WINDOW.set(*proposals.get(author).unwrap_or(&0) as i64);
-        FAILED_PROPOSALS_IN_WINDOW.set(*failed_proposals.get(author).unwrap_or(&0) as i64);
-        COMMITTED_VOTES_IN_WINDOW.set(*votes.get(author).unwrap_or(&0) as i64);
-
-        LEADER_REPUTATION_ROUND_HISTORY_SIZE.set(
-            proposals.values().sum::<u32>() as i64 + failed_proposals.values().sum::<u32>() as i64,
-        );
-
-        (votes, proposals, failed_proposals)
-    }
-
-    pub fn count_votes(
-        &self,
-        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
-        history: &[NewBlockEvent],
-    ) -> HashMap<Author, u32> {
-        Self::count_votes_custom(
-            epoch_to_candidates,
-            history,
-            self.voter_window_size,
-            self.reputation_window_from_stale_end,
-        )
-    }
-
-    pub fn count_votes_custom(
-        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
-        history: &[NewBlockEvent],
-        window_size: usize,
-        from_stale_end: bool,
-    ) -> HashMap<Author, u32> {
-        Self::history_iter(history, epoch_to_candidates, window_size, from_stale_end).fold(
-            HashMap::new(),
-            |mut map, meta| {
-                match Self::bitvec_to_voters(
-                    &epoch_to_candidates[&meta.epoch()],
-                    &meta.previous_block_votes_bitvec().clone().into(),
-                ) {
-                    Ok(voters) => {
-                        for &voter in voters {
-                            let count = map.entry(voter).or_insert(0);
-                            *count += 1;
-                        }
-                    },
-                    Err(msg) => {
-                        error!(
-                            "Voter conversion from bitmap failed at epoch {}, round {}: {}",
-                            meta.epoch(),
-                            meta.round(),
-                            msg
-                        )
-                    },
-                }
-                map
-            },
-        )
-    }
-
-    pub fn count_proposals(
-        &self,
-        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
-        history: &[NewBlockEvent],
-    ) -> HashMap<Author, u32> {
-        Self::count_proposals_custom(
-            epoch_to_candidates,
-            history,
-            self.proposer_window_size,
-            self.reputation_window_from_stale_end,
-        )
-    }
-
-    pub fn count_proposals_custom(
-        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
-        history: &[NewBlockEvent],
-        window_size: usize,
-        from_stale_end: bool,
-    ) -> HashMap<Author, u32> {
-        Self::history_iter(history, epoch_to_candidates, window_size, from_stale_end).fold(
-            HashMap::new(),
-            |mut map, meta| {
-                let count = map.entry(meta.proposer()).or_insert(0);
-                *count += 1;
-                map
-            },
-        )
-    }
-
-    pub fn count_failed_proposals(
-        &self,
-        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
-        history: &[NewBlockEvent],
-    ) -> HashMap<Author, u32> {
-        Self::history_iter(
-            history,
-            epoch_to_candidates,
-            self.proposer_window_size,
-            self.reputation_window_from_stale_end,
-        )
-        .fold(HashMap::new(), |mut map, meta| {
-            match Self::indices_to_validators(
-                &epoch_to_candidates[&meta.epoch()],
-                meta.failed_proposer_indices(),
-            ) {
-                Ok(failed_proposers) => {
-                    for &failed_proposer in failed_proposers {
-                        let count = map.entry(failed_proposer).or_insert(0);
-                        *count += 1;
-                    }
-                },
-                Err(msg) => {
-                    error!(
-                        "Failed proposer conversion from indices failed at epoch {}, round {}: {}",
-                        meta.epoch(),
-                        meta.round(),
-                        msg
-                    )
-                },
-            }
-            map
-        })
-    }
-}
-
-/// Heuristic that looks at successful and failed proposals, as well as voting history,
-/// to define node reputation, used for leader selection.
-///
-/// We want to optimize leader selection to primarily maximize network's throughput,
-/// but we also, in combinatoin with staking rewards logic, need to be reasonably fair.
-///
-/// Logic is:
-///  * if proposer round failure rate within the proposer window is strictly above threshold, use failed_weight (default 1).
-///  * otherwise, if node had no proposal rounds and no successful votes, use inactive_weight (default 10).
-///  * otherwise, use the default active_weight (default 100).
-///
-/// We primarily want to avoid failed rounds, as they have a largest negative effect on the network.
-/// So if we see a node having failures to propose, when it was the leader, we want to avoid that node.
-/// We add a threshold (instead of penalizing on a single failure), so that transient issues in the network,
-/// or malicious behaviour of the next leader is avoided. In general, we expect there to be
-/// proposer_window_size/num_validators opportunities for a node to be a leader, so a single failure, or a
-/// subset of following leaders being malicious will not be enough to exclude a node.
-/// On the other hand, single failure, without any successes before will exclude the note.
-/// Threshold probably makes the most sense to be between:
-///  * 10% (aggressive exclusion with 1 failure in 10 proposals being enough for exclusion)
-///  * and 33% (much less aggressive exclusion, with 1 failure for every 2 successes, should still reduce failed
-///    rounds by at least 66%, and is enough to avoid byzantine attacks as well as the rest of the protocol)
-pub struct ProposerAndVoterHeuristic {
-    author: Author,
-    active_weight: u64,
-    inactive_weight: u64,
-    failed_weight: u64,
-    failure_threshold_percent: u32,
-    aggregation: NewBlockEventAggregation,
-}
-
-impl ProposerAndVoterHeuristic {
-    pub fn new(
-        author: Author,
-        active_weight: u64,
-        inactive_weight: u64,
-        failed_weight: u64,
-        failure_threshold_percent: u32,
-        voter_window_size: usize,
-        proposer_window_size: usize,
-        reputation_window_from_stale_end: bool,
-    ) -> Self {
-        Self {
-            author,
-            active_weight,
-            inactive_weight,
-            failed_weight,
-            failure_threshold_percent,
-            aggregation: NewBlockEventAggregation::new(
-                voter_window_size,
-                proposer_window_size,
-                reputation_window_from_stale_end,
-            ),
-        }
-    }
-}
-
-impl ReputationHeuristic for ProposerAndVoterHeuristic {
-    fn get_weights(
-        &self,
-        epoch: u64,
-        epoch_to_candidates: &HashMap<u64, Vec<Author>>,
-        history: &[NewBlockEvent],
-    ) -> Vec<u64> {
-        assert!(epoch_to_candidates.contains_key(&epoch));
-
-        let (votes, proposals, failed_proposals) =
-            self.aggregation
-                .get_aggregated_metrics(epoch_to_candidates, history, &self.author);
-
-        epoch_to_candidates[&epoch]
-            .iter()
-            .map(|author| {
-                let cur_votes = *votes.get(author).unwrap_or(&0);
-                let cur_proposals = *proposals.get(author).unwrap_or(&0);
-                let cur_failed_proposals = *failed_proposals.get(author).unwrap_or(&0);
-
-                if cur_failed_proposals * 100
-                    > (cur_proposals + cur_failed_proposals) * self.failure_threshold_percent
-                {
-                    self.failed_weight
-                } else if cur_proposals > 0 || cur_votes > 0 {
-                    self.active_weight
-                } else {
-                    self.inactive_weight
-                }
-            })
-            .collect()
-    }
-}
-
 /// Committed history based proposer election implementation that could help bias towards
 /// successful leaders to help improve performance.
 pub struct LeaderReputation {
@@ -692,35 +339,19 @@ impl ProposerElection for LeaderReputation {
         let (sliding_window, root_hash) = self.backend.get_block_metadata(self.epoch, target_round);
         let voting_power_participation_ratio =
             self.compute_chain_health_and_add_metrics(&sliding_window, round);
-        let mut weights =
+        let weights =
             self.heuristic
                 .get_weights(self.epoch, &self.epoch_to_proposers, &sliding_window);
         let proposers = &self.epoch_to_proposers[&self.epoch];
         assert_eq!(weights.len(), proposers.len());
 
-        // Multiply weights by voting power:
-        let stake_weights: Vec<u128> = weights
-            .iter_mut()
-            .enumerate()
-            .map(|(i, w)| *w as u128 * self.voting_powers[i] as u128)
-            .collect();
-
-        let state = if self.use_root_hash {
-            [
-                root_hash.to_vec(),
-                self.epoch.to_le_bytes().to_vec(),
-                round.to_le_bytes().to_vec(),
-            ]
-            .concat()
-        } else {
-            [
-                self.epoch.to_le_bytes().to_vec(),
-                round.to_le_bytes().to_vec(),
-            ]
-            .concat()
-        };
-
-        let chosen_index = choose_index(stake_weights, state);
+        let chosen_index = choose_proposer(
+            self.epoch,
+            round,
+            &weights,
+            &self.voting_powers,
+            self.use_root_hash.then_some(root_hash),
+        );
         (proposers[chosen_index], voting_power_participation_ratio)
     }
 
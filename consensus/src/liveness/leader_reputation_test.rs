@@ -3,19 +3,21 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::leader_reputation::{
-    extract_epoch_to_proposers_impl, AptosDBBackend, ProposerAndVoterHeuristic,
+    extract_epoch_to_proposers_impl, AptosDBBackend, CandidateStats, CandidateWeighting,
+    ProposerAndVoterHeuristic, SuccessRateWeighting, WeightedStatsHeuristic,
 };
 use crate::liveness::{
     leader_reputation::{
         LeaderReputation, MetadataBackend, NewBlockEventAggregation, ReputationHeuristic,
     },
-    proposer_election::{choose_index, ProposerElection},
+    proposer_election::ProposerElection,
 };
 use aptos_bitvec::BitVec;
 use aptos_consensus_types::common::{Author, Round};
 use aptos_crypto::{bls12381, HashValue};
 use aptos_infallible::Mutex;
 use aptos_keygen::KeyGen;
+use aptos_leader_reputation::choose_index;
 use aptos_storage_interface::{DbReader, Order};
 use aptos_types::{
     account_address::AccountAddress,
@@ -276,6 +278,69 @@ fn test_proposer_and_voter_heuristic() {
     );
 }
 
+struct StatsEncodingWeighting;
+
+impl CandidateWeighting for StatsEncodingWeighting {
+    fn weight(&self, stats: &CandidateStats) -> u64 {
+        stats.votes as u64 * 100 + stats.proposals as u64 * 10 + stats.failed_proposals as u64
+    }
+}
+
+#[test]
+fn test_weighted_stats_heuristic() {
+    let mut example1 = Example1::new(5);
+    let validators0 = example1.validators0.clone();
+    let epoch_to_validators0 = HashMap::from([(0u64, validators0.clone())]);
+    let heuristic = WeightedStatsHeuristic::with_weighting(
+        example1.validators0[0],
+        StatsEncodingWeighting,
+        2,
+        5,
+        false,
+    );
+    let aggregation = NewBlockEventAggregation::new(2, 5, false);
+
+    example1.step1();
+    example1.step2();
+    let history = example1.history();
+    let votes = aggregation.count_votes(&epoch_to_validators0, &history);
+    let proposals = aggregation.count_proposals(&epoch_to_validators0, &history);
+    let failed_proposals = aggregation.count_failed_proposals(&epoch_to_validators0, &history);
+    let expected: Vec<u64> = validators0
+        .iter()
+        .map(|v| {
+            StatsEncodingWeighting.weight(&CandidateStats {
+                votes: *votes.get(v).unwrap_or(&0),
+                proposals: *proposals.get(v).unwrap_or(&0),
+                failed_proposals: *failed_proposals.get(v).unwrap_or(&0),
+            })
+        })
+        .collect();
+    assert!(expected.iter().any(|weight| *weight > 0));
+    assert_eq!(
+        heuristic.get_weights(0, &epoch_to_validators0, &history),
+        expected
+    );
+}
+
+#[test]
+fn test_success_rate_weighting() {
+    let weighting = SuccessRateWeighting::new(1000, 10, 1);
+    let weight = |votes, proposals, failed_proposals| {
+        weighting.weight(&CandidateStats {
+            votes,
+            proposals,
+            failed_proposals,
+        })
+    };
+    assert_eq!(weight(0, 0, 0), 10);
+    assert_eq!(weight(3, 0, 0), 1000);
+    assert_eq!(weight(0, 4, 0), 1000);
+    assert_eq!(weight(0, 3, 1), 750);
+    assert_eq!(weight(5, 1, 9), 100);
+    assert_eq!(weight(5, 0, 2), 1);
+}
+
 /// #### LeaderReputation test ####
 
 #[test]
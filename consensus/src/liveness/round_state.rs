@@ -3,13 +3,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    block_storage::tracing::observe_round,
     counters,
     pending_votes::{PendingVotes, VoteReceptionResult},
     util::time_service::{SendTask, TimeService},
 };
 use aptos_consensus_types::{
-    common::Round, sync_info::SyncInfo, timeout_2chain::TwoChainTimeoutWithPartialSignatures,
-    vote::Vote,
+    common::Round, round_timeline::RoundEvent, sync_info::SyncInfo,
+    timeout_2chain::TwoChainTimeoutWithPartialSignatures, vote::Vote,
 };
 use aptos_crypto::HashValue;
 use aptos_logger::{prelude::*, Schema};
@@ -147,6 +148,8 @@ pub struct RoundState {
     highest_committed_round: Round,
     // Current round is max{highest_qc, highest_tc} + 1.
     current_round: Round,
+    // Epoch of the current round, as reported by the certificates.
+    current_epoch: u64,
     // The deadline for the next local timeout event. It is reset every time a new round start, or
     // a previous deadline expires.
     // Represents as Duration since UNIX_EPOCH.
@@ -200,6 +203,7 @@ impl RoundState {
             time_interval,
             highest_committed_round: 0,
             current_round: 0,
+            current_epoch: 0,
             current_round_deadline: time_service.get_current_timestamp(),
             time_service,
             timeout_sender,
@@ -232,6 +236,7 @@ impl RoundState {
         }
         warn!(round = round, "Local timeout");
         counters::TIMEOUT_COUNT.inc();
+        observe_round(self.current_epoch, round, RoundEvent::RoundTimeout);
         self.setup_timeout(1);
         true
     }
@@ -248,6 +253,8 @@ impl RoundState {
 
             // Start a new round.
             self.current_round = new_round;
+            self.current_epoch = sync_info.epoch();
+            observe_round(self.current_epoch, new_round, RoundEvent::RoundStarted);
             self.pending_votes = PendingVotes::new();
             self.vote_sent = None;
             let timeout = self.setup_timeout(1);
@@ -79,6 +79,8 @@ pub struct NetworkPlayground {
     drop_config: Arc<RwLock<DropConfig>>,
     /// Allow test code to drop direct-send messages between peers per round.
     drop_config_round: DropConfigRound,
+    /// Allow test code to delay rpc messages between peers.
+    rpc_delay_config: Arc<RwLock<DelayConfig>>,
     /// An executor for spawning node outbound network event handlers
     executor: Handle,
     /// Maps authors to twins IDs
@@ -98,6 +100,7 @@ impl NetworkPlayground {
             outbound_msgs_rx,
             drop_config: Arc::new(RwLock::new(DropConfig::default())),
             drop_config_round: DropConfigRound::default(),
+            rpc_delay_config: Arc::new(RwLock::new(DelayConfig::default())),
             executor,
             author_to_twin_ids: Arc::new(RwLock::new(AuthorToTwinIds::default())),
             peers_and_metadata: PeersAndMetadata::new(&[NetworkId::Validator]),
@@ -120,9 +123,10 @@ impl NetworkPlayground {
     /// `deliver_messages` and `wait_for_messages` API's.
     ///
     /// Rpc messages are immediately sent to the destination for handling, so
-    /// they don't block.
+    /// they don't block, unless a delay is configured between the two peers.
     async fn start_node_outbound_handler(
         drop_config: Arc<RwLock<DropConfig>>,
+        rpc_delay_config: Arc<RwLock<DelayConfig>>,
         src_twin_id: TwinId,
         mut network_reqs_rx: aptos_channel::Receiver<(PeerId, ProtocolId), PeerManagerRequest>,
         mut outbound_msgs_tx: mpsc::Sender<(TwinId, PeerManagerRequest)>,
@@ -169,12 +173,20 @@ impl NetworkPlayground {
                         res_tx: outbound_req.res_tx,
                     };
 
-                    node_consensus_tx
-                        .push(
-                            (src_twin_id.author, ProtocolId::ConsensusRpcBcs),
-                            PeerManagerNotification::RecvRpc(src_twin_id.author, inbound_req),
-                        )
-                        .unwrap();
+                    let key = (src_twin_id.author, ProtocolId::ConsensusRpcBcs);
+                    let notif = PeerManagerNotification::RecvRpc(src_twin_id.author, inbound_req);
+                    let delay = rpc_delay_config.read().get_delay(&src_twin_id, dst_twin_id);
+                    match delay {
+                        // The destination may be gone by the time a delayed message is
+                        // delivered, in which case the message is lost.
+                        Some(delay) => {
+                            tokio::spawn(async move {
+                                tokio::time::sleep(delay).await;
+                                let _ = node_consensus_tx.push(key, notif);
+                            });
+                        },
+                        None => node_consensus_tx.push(key, notif).unwrap(),
+                    }
                 },
                 // Other PeerManagerRequest get buffered for `deliver_messages` to
                 // synchronously drain.
@@ -200,6 +212,7 @@ impl NetworkPlayground {
 
         let fut1 = NetworkPlayground::start_node_outbound_handler(
             Arc::clone(&self.drop_config),
+            Arc::clone(&self.rpc_delay_config),
             twin_id,
             network_reqs_rx,
             self.outbound_msgs_tx.clone(),
@@ -350,6 +363,21 @@ impl NetworkPlayground {
             .split_network(&partition_first, &partition_second)
     }
 
+    /// Removes all the partitions created with `split_network`
+    pub fn heal_network(&self) {
+        self.drop_config.write().clear();
+    }
+
+    /// Delays the rpc messages from 'src_twin_id' to 'dst_twin_id' by the given duration
+    pub fn delay_rpc(&self, src: TwinId, dst: TwinId, delay: Duration) {
+        self.rpc_delay_config.write().set_delay(src, dst, delay);
+    }
+
+    /// Removes all the delays created with `delay_rpc`
+    pub fn clear_rpc_delays(&self) {
+        self.rpc_delay_config.write().clear();
+    }
+
     /// Check if the message from 'src_twin_id' to 'dst_twin_id' should be dropped in the given round
     pub fn is_message_dropped_round(&self, src: &TwinId, dst: &TwinId, round: u64) -> bool {
         self.drop_config_round.is_message_dropped(src, dst, round)
@@ -411,9 +439,12 @@ struct AuthorToTwinIds(HashMap<Author, Vec<TwinId>>);
 
 impl AuthorToTwinIds {
     pub fn extend_author_to_twin_ids(&mut self, author: Author, twin_id: TwinId) {
-        self.0.entry(author).or_insert_with(Vec::new);
+        let twin_ids = self.0.entry(author).or_insert_with(Vec::new);
 
-        self.0.get_mut(&author).unwrap().push(twin_id)
+        // A node that is added again, e.g. after a restart, keeps its place
+        if !twin_ids.contains(&twin_id) {
+            twin_ids.push(twin_id)
+        }
     }
 
     pub fn get_twin_ids(&self, author: Author) -> Vec<TwinId> {
@@ -451,6 +482,28 @@ impl DropConfig {
     fn add_node(&mut self, src: TwinId) {
         self.0.insert(src, HashSet::new());
     }
+
+    fn clear(&mut self) {
+        self.0.values_mut().for_each(HashSet::clear);
+    }
+}
+
+/// Table of rpc message delays between peers
+#[derive(Default)]
+struct DelayConfig(HashMap<(TwinId, TwinId), Duration>);
+
+impl DelayConfig {
+    fn get_delay(&self, src: &TwinId, dst: &TwinId) -> Option<Duration> {
+        self.0.get(&(*src, *dst)).copied()
+    }
+
+    fn set_delay(&mut self, src: TwinId, dst: TwinId, delay: Duration) {
+        self.0.insert((src, dst), delay);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
 }
 
 /// Table of per round message dropping rules
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    block_storage::tracing::observe_round,
     counters,
     network::NetworkSender,
     quorum_store::{
@@ -13,6 +14,7 @@ use aptos_consensus_types::{
     block::Block,
     common::{DataStatus, Payload},
     proof_of_store::ProofOfStore,
+    round_timeline::RoundEvent,
 };
 use aptos_crypto::HashValue;
 use aptos_executor_types::{ExecutorError::DataNotFound, *};
@@ -189,6 +191,7 @@ impl PayloadManager {
                             }
                         }
                         let ret: Vec<SignedTransaction> = vec_ret.into_iter().flatten().collect();
+                        observe_round(block.epoch(), block.round(), RoundEvent::PayloadFetched);
                         // execution asks for the data twice, so data is cached here for the second time.
                         proof_with_data
                             .status
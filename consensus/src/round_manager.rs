@@ -4,7 +4,7 @@
 
 use crate::{
     block_storage::{
-        tracing::{observe_block, BlockStage},
+        tracing::{observe_block, observe_round, BlockStage},
         BlockReader, BlockRetriever, BlockStore,
     },
     counters,
@@ -33,6 +33,7 @@ use aptos_consensus_types::{
     proof_of_store::{ProofOfStoreMsg, SignedBatchInfoMsg},
     proposal_msg::ProposalMsg,
     quorum_cert::QuorumCert,
+    round_timeline::RoundEvent,
     sync_info::SyncInfo,
     timeout_2chain::TwoChainTimeoutCertificate,
     vote::Vote,
@@ -396,6 +397,11 @@ impl RoundManager {
             proposal_msg.proposal().timestamp_usecs(),
             BlockStage::ROUND_MANAGER_RECEIVED,
         );
+        observe_round(
+            proposal_msg.proposal().epoch(),
+            proposal_msg.proposal().round(),
+            RoundEvent::ProposalReceived,
+        );
         info!(
             self.new_log(LogEvent::ReceiveProposal)
                 .remote_peer(proposal_msg.proposer()),
@@ -736,6 +742,7 @@ impl RoundManager {
         self.round_state.record_vote(vote.clone());
         let vote_msg = VoteMsg::new(vote, self.block_store.sync_info());
         self.network.send_vote(vote_msg, vec![recipient]).await;
+        observe_round(self.epoch_state.epoch, proposal_round, RoundEvent::VoteSent);
         Ok(())
     }
 
@@ -859,6 +866,11 @@ impl RoundManager {
                         qc.certified_block().timestamp_usecs(),
                         BlockStage::QC_AGGREGATED,
                     );
+                    observe_round(
+                        qc.certified_block().epoch(),
+                        qc.certified_block().round(),
+                        RoundEvent::QcFormed,
+                    );
                 }
                 self.new_qc_aggregated(qc, vote.author()).await
             },
@@ -0,0 +1,8 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+mod network;
+mod node;
+mod simulation_tests;
+mod simulator;
+mod time_service;
@@ -0,0 +1,513 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::network_interface::ConsensusMsg;
+use aptos_channels::aptos_channel;
+use aptos_consensus_types::common::Author;
+use aptos_crypto::HashValue;
+use aptos_infallible::Mutex;
+use aptos_network::{
+    peer_manager::{PeerManagerNotification, PeerManagerRequest},
+    protocols::{
+        direct_send::Message,
+        network::SerializedRequest,
+        rpc::{error::RpcError, InboundRpcRequest},
+    },
+    ProtocolId,
+};
+use bytes::Bytes;
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// The simulated network delivers at most one message per tick, so that every message is
+/// fully processed before the next one is delivered.
+pub const TICK: Duration = Duration::from_millis(1);
+
+/// How a message travels over the simulated network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessageKind {
+    DirectSend,
+    RpcRequest,
+    RpcResponse,
+    /// The rpc didn't get a response before its timeout
+    RpcTimeout,
+}
+
+/// A message sent over the simulated network, for fault injectors to decide what happens to it.
+pub struct MessageContext<'a> {
+    /// Time since the start of the simulation
+    pub now: Duration,
+    /// Index of the sending node
+    pub from: usize,
+    /// Index of the receiving node
+    pub to: usize,
+    pub kind: MessageKind,
+    pub msg: &'a ConsensusMsg,
+}
+
+/// What happens to a message instead of being delivered after the usual latency.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// The message is lost. If it's part of an rpc, the rpc times out.
+    Drop,
+    /// The message is delivered after the usual latency plus the given delay.
+    Delay(Duration),
+}
+
+/// Decides the fault injected on a message, if any. The random number generator is seeded from
+/// the simulation seed and the message itself, so the same message always gets the same fault.
+pub type FaultInjector = Box<dyn FnMut(&MessageContext, &mut StdRng) -> Option<Fault> + Send>;
+
+/// Drops all the messages between the given groups of nodes during `[start, end)`.
+pub fn partition(groups: Vec<Vec<usize>>, start: Duration, end: Duration) -> FaultInjector {
+    Box::new(move |ctx, _| {
+        let group_of = |index| groups.iter().position(|group| group.contains(&index));
+        let split = ctx.now >= start && ctx.now < end && group_of(ctx.from) != group_of(ctx.to);
+        split.then_some(Fault::Drop)
+    })
+}
+
+/// Drops each message with the given probability.
+pub fn drop_randomly(probability: f64) -> FaultInjector {
+    Box::new(move |_, rng| rng.gen_bool(probability).then_some(Fault::Drop))
+}
+
+/// Drops the messages for which `filter` returns true.
+pub fn drop_messages<F>(filter: F) -> FaultInjector
+where
+    F: Fn(&MessageContext) -> bool + Send + 'static,
+{
+    Box::new(move |ctx, _| filter(ctx).then_some(Fault::Drop))
+}
+
+/// A message handled by the simulated network, either delivered or dropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Time since the start of the simulation
+    pub time: Duration,
+    pub from: usize,
+    pub to: usize,
+    pub kind: MessageKind,
+    pub name: String,
+    pub digest: HashValue,
+    pub dropped: bool,
+}
+
+/// The response channel of an rpc, taken by either the response or the timeout, whichever is
+/// delivered first.
+type PendingRpc = Arc<Mutex<Option<oneshot::Sender<Result<Bytes, RpcError>>>>>;
+
+/// Messages handed to the network by the nodes.
+enum Outbound {
+    Request {
+        from: usize,
+        request: PeerManagerRequest,
+    },
+    Response {
+        from: usize,
+        to: usize,
+        protocol_id: ProtocolId,
+        result: Result<Bytes, RpcError>,
+        pending: PendingRpc,
+    },
+}
+
+enum Delivery {
+    DirectSend(Message),
+    RpcRequest {
+        protocol_id: ProtocolId,
+        data: Bytes,
+        pending: PendingRpc,
+    },
+    RpcResponse {
+        result: Result<Bytes, RpcError>,
+        pending: PendingRpc,
+    },
+    RpcTimeout(PendingRpc),
+}
+
+/// Messages are delivered in the order of their keys. Apart from the delivery time, keys only
+/// depend on the content of the messages, so the order doesn't depend on the order in which
+/// messages sent at the same time were handed to the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct DeliveryKey {
+    time: Duration,
+    from: usize,
+    to: usize,
+    kind: MessageKind,
+    digest: HashValue,
+    occurrence: u64,
+}
+
+/// Network between the simulated nodes, which replaces the peer manager of each node. Every
+/// message, rpc responses included, is delivered after a latency drawn from a random number
+/// generator seeded from the simulation seed and the message, unless a fault injector decides
+/// otherwise.
+///
+/// Faults are injected below `network_interface` rather than in it, which is equivalent: every
+/// message `ConsensusNetworkClient` sends to a peer becomes exactly one request to the peer
+/// manager, and the fault injectors get the `ConsensusMsg` decoded back from that request, with
+/// the direct send or rpc it went through. Messages a node sends to itself never reach
+/// `network_interface` either, they go through the self sender of `NetworkSender`. Hooking the
+/// faults here also covers rpc responses and timeouts, and leaves the consensus code untouched.
+pub struct SimulatedNetwork {
+    seed: u64,
+    min_latency: Duration,
+    max_latency: Duration,
+    faults: Vec<FaultInjector>,
+    start: Instant,
+    authors: Vec<Author>,
+    node_consensus_txs: Vec<aptos_channel::Sender<(Author, ProtocolId), PeerManagerNotification>>,
+    outbound_tx: mpsc::UnboundedSender<Outbound>,
+    outbound_rx: mpsc::UnboundedReceiver<Outbound>,
+    // The name of each message is kept along with it for the trace
+    queue: BTreeMap<DeliveryKey, (String, Delivery)>,
+    occurrences: HashMap<(usize, usize, MessageKind, HashValue), u64>,
+    last_delivery: Option<Duration>,
+    trace: Arc<Mutex<Vec<TraceEntry>>>,
+}
+
+impl SimulatedNetwork {
+    pub fn new(
+        seed: u64,
+        min_latency: Duration,
+        max_latency: Duration,
+        faults: Vec<FaultInjector>,
+        start: Instant,
+    ) -> Self {
+        assert!(min_latency >= TICK, "latency must be at least one tick");
+        assert!(min_latency <= max_latency);
+        let (outbound_tx, outbound_rx) = mpsc::unbounded();
+        Self {
+            seed,
+            min_latency,
+            max_latency,
+            faults,
+            start,
+            authors: vec![],
+            node_consensus_txs: vec![],
+            outbound_tx,
+            outbound_rx,
+            queue: BTreeMap::new(),
+            occurrences: HashMap::new(),
+            last_delivery: None,
+            trace: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// The messages handled so far, in the order they were delivered or dropped.
+    pub fn trace(&self) -> Arc<Mutex<Vec<TraceEntry>>> {
+        self.trace.clone()
+    }
+
+    /// Adds a node to the network, and returns its index. Must be called from the runtime
+    /// running the simulation.
+    pub fn add_node(
+        &mut self,
+        author: Author,
+        consensus_tx: aptos_channel::Sender<(Author, ProtocolId), PeerManagerNotification>,
+        mut network_reqs_rx: aptos_channel::Receiver<(Author, ProtocolId), PeerManagerRequest>,
+    ) -> usize {
+        let from = self.authors.len();
+        self.authors.push(author);
+        self.node_consensus_txs.push(consensus_tx);
+
+        let outbound_tx = self.outbound_tx.clone();
+        tokio::spawn(async move {
+            while let Some(request) = network_reqs_rx.next().await {
+                let _ = outbound_tx.unbounded_send(Outbound::Request { from, request });
+            }
+        });
+        from
+    }
+
+    pub async fn start(mut self) {
+        loop {
+            let next_delivery = self.next_delivery_time();
+            tokio::select! {
+                biased;
+                Some(outbound) = self.outbound_rx.next() => self.process_outbound(outbound),
+                _ = tokio::time::sleep_until(self.start + next_delivery.unwrap_or_default()),
+                    if next_delivery.is_some() => self.deliver_next(),
+            }
+        }
+    }
+
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn next_delivery_time(&self) -> Option<Duration> {
+        let (key, _) = self.queue.first_key_value()?;
+        Some(match self.last_delivery {
+            Some(last_delivery) => key.time.max(last_delivery + TICK),
+            None => key.time,
+        })
+    }
+
+    fn process_outbound(&mut self, outbound: Outbound) {
+        let now = self.now();
+        match outbound {
+            Outbound::Request {
+                from,
+                request: PeerManagerRequest::SendDirectSend(peer, message),
+            } => {
+                let to = self.index_of(peer);
+                let digest = HashValue::sha3_256_of(&message.mdata);
+                let msg = message.to_message().ok();
+                self.schedule(
+                    from,
+                    to,
+                    MessageKind::DirectSend,
+                    digest,
+                    msg,
+                    Delivery::DirectSend(message),
+                );
+            },
+            Outbound::Request {
+                from,
+                request: PeerManagerRequest::SendRpc(peer, request),
+            } => {
+                let to = self.index_of(peer);
+                let digest = HashValue::sha3_256_of(&request.data);
+                let msg: Option<ConsensusMsg> = request.to_message().ok();
+                let name = msg.as_ref().map_or("Unknown", |msg| msg.name()).to_string();
+                let pending = Arc::new(Mutex::new(Some(request.res_tx)));
+                let occurrence = self.schedule(
+                    from,
+                    to,
+                    MessageKind::RpcRequest,
+                    digest,
+                    msg,
+                    Delivery::RpcRequest {
+                        protocol_id: request.protocol_id,
+                        data: request.data,
+                        pending: pending.clone(),
+                    },
+                );
+                // Whatever happens to the request and its response, the rpc must complete by
+                // its timeout
+                self.queue.insert(
+                    DeliveryKey {
+                        time: now + request.timeout.max(TICK),
+                        from,
+                        to,
+                        kind: MessageKind::RpcTimeout,
+                        digest,
+                        occurrence,
+                    },
+                    (name, Delivery::RpcTimeout(pending)),
+                );
+            },
+            Outbound::Response {
+                from,
+                to,
+                protocol_id,
+                result,
+                pending,
+            } => {
+                let (digest, msg) = match &result {
+                    Ok(bytes) => (
+                        HashValue::sha3_256_of(bytes),
+                        protocol_id.from_bytes(bytes).ok(),
+                    ),
+                    Err(err) => (HashValue::sha3_256_of(err.to_string().as_bytes()), None),
+                };
+                self.schedule(
+                    from,
+                    to,
+                    MessageKind::RpcResponse,
+                    digest,
+                    msg,
+                    Delivery::RpcResponse { result, pending },
+                );
+            },
+        }
+    }
+
+    /// Queues the message for delivery, unless it's dropped, and returns its occurrence, i.e.
+    /// the number of identical messages that were sent before it on the same link.
+    fn schedule(
+        &mut self,
+        from: usize,
+        to: usize,
+        kind: MessageKind,
+        digest: HashValue,
+        msg: Option<ConsensusMsg>,
+        delivery: Delivery,
+    ) -> u64 {
+        let now = self.now();
+        let occurrence = {
+            let count = self
+                .occurrences
+                .entry((from, to, kind, digest))
+                .or_insert(0);
+            *count += 1;
+            *count - 1
+        };
+        let mut rng = self.message_rng(from, to, kind, digest, occurrence);
+
+        let jitter_ms = (self.max_latency - self.min_latency).as_millis() as u64;
+        let mut latency = self.min_latency + Duration::from_millis(rng.gen_range(0, jitter_ms + 1));
+        let fault = msg.as_ref().and_then(|msg| {
+            let ctx = MessageContext {
+                now,
+                from,
+                to,
+                kind,
+                msg,
+            };
+            self.faults
+                .iter_mut()
+                .find_map(|fault_injector| fault_injector(&ctx, &mut rng))
+        });
+        let name = msg.as_ref().map_or("Unknown", |msg| msg.name()).to_string();
+        match fault {
+            Some(Fault::Drop) => {
+                self.trace.lock().push(TraceEntry {
+                    time: now,
+                    from,
+                    to,
+                    kind,
+                    name,
+                    digest,
+                    dropped: true,
+                });
+                return occurrence;
+            },
+            Some(Fault::Delay(delay)) => latency += delay,
+            None => (),
+        }
+
+        self.queue.insert(
+            DeliveryKey {
+                time: now + latency,
+                from,
+                to,
+                kind,
+                digest,
+                occurrence,
+            },
+            (name, delivery),
+        );
+        occurrence
+    }
+
+    fn message_rng(
+        &self,
+        from: usize,
+        to: usize,
+        kind: MessageKind,
+        digest: HashValue,
+        occurrence: u64,
+    ) -> StdRng {
+        let seed = bcs::to_bytes(&(
+            self.seed,
+            from as u64,
+            to as u64,
+            kind as u8,
+            digest.to_vec(),
+            occurrence,
+        ))
+        .expect("Failed to serialize message seed");
+        let mut rng_seed = [0u8; HashValue::LENGTH];
+        rng_seed.copy_from_slice(HashValue::sha3_256_of(&seed).as_ref());
+        StdRng::from_seed(rng_seed)
+    }
+
+    fn deliver_next(&mut self) {
+        let (key, (name, delivery)) = match self.queue.pop_first() {
+            Some(entry) => entry,
+            None => return,
+        };
+        let now = self.now();
+        let sender = self.authors[key.from];
+
+        let delivered = match delivery {
+            Delivery::DirectSend(message) => {
+                // The node may have shut down
+                let _ = self.node_consensus_txs[key.to].push(
+                    (sender, message.protocol_id),
+                    PeerManagerNotification::RecvMessage(sender, message),
+                );
+                true
+            },
+            Delivery::RpcRequest {
+                protocol_id,
+                data,
+                pending,
+            } => {
+                let (res_tx, res_rx) = oneshot::channel();
+                let _ = self.node_consensus_txs[key.to].push(
+                    (sender, protocol_id),
+                    PeerManagerNotification::RecvRpc(sender, InboundRpcRequest {
+                        protocol_id,
+                        data,
+                        res_tx,
+                    }),
+                );
+                // Route the response back through the network
+                let outbound_tx = self.outbound_tx.clone();
+                tokio::spawn(async move {
+                    let result = res_rx
+                        .await
+                        .unwrap_or(Err(RpcError::UnexpectedResponseChannelCancel));
+                    let _ = outbound_tx.unbounded_send(Outbound::Response {
+                        from: key.to,
+                        to: key.from,
+                        protocol_id,
+                        result,
+                        pending,
+                    });
+                });
+                true
+            },
+            Delivery::RpcResponse { result, pending } => match pending.lock().take() {
+                Some(res_tx) => {
+                    let _ = res_tx.send(result);
+                    true
+                },
+                // The rpc already timed out
+                None => false,
+            },
+            Delivery::RpcTimeout(pending) => match pending.lock().take() {
+                Some(res_tx) => {
+                    let _ = res_tx.send(Err(RpcError::TimedOut));
+                    true
+                },
+                // The response was delivered in time
+                None => false,
+            },
+        };
+        if !delivered {
+            return;
+        }
+
+        self.last_delivery = Some(now);
+        self.trace.lock().push(TraceEntry {
+            time: now,
+            from: key.from,
+            to: key.to,
+            kind: key.kind,
+            name,
+            digest: key.digest,
+            dropped: false,
+        });
+    }
+
+    fn index_of(&self, author: Author) -> usize {
+        self.authors
+            .iter()
+            .position(|a| *a == author)
+            .expect("Message sent to a node outside of the simulation")
+    }
+}
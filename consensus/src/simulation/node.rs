@@ -0,0 +1,295 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    counters,
+    epoch_manager::EpochManager,
+    experimental::buffer_manager::OrderedBlocks,
+    network::NetworkTask,
+    network_interface::{ConsensusNetworkClient, DIRECT_SEND, RPC},
+    quorum_store::quorum_store_db::MockQuorumStoreDB,
+    simulation::{network::SimulatedNetwork, time_service::VirtualTimeService},
+    test_utils::{MockStateComputer, MockStorage},
+};
+use aptos_bounded_executor::BoundedExecutor;
+use aptos_channels::{self, aptos_channel, message_queues::QueueStyle};
+use aptos_config::{config::NodeConfig, network_id::NetworkId};
+use aptos_consensus_types::common::{Author, TransactionInProgress};
+use aptos_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519Signature},
+    PrivateKey, Uniform,
+};
+use aptos_event_notifications::{ReconfigNotification, ReconfigNotificationListener};
+use aptos_infallible::Mutex;
+use aptos_mempool::{QuorumStoreRequest, QuorumStoreResponse};
+use aptos_network::{
+    application::{
+        interface::{NetworkClient, NetworkServiceEvents},
+        storage::PeersAndMetadata,
+    },
+    peer_manager::{conn_notifs_channel, ConnectionRequestSender, PeerManagerRequestSender},
+    protocols::network::{self, NetworkEvents, NewNetworkEvents, NewNetworkSender},
+};
+use aptos_types::{
+    block_info::BlockInfo,
+    chain_id::ChainId,
+    on_chain_config::{
+        InMemoryOnChainConfig, OnChainConfig, OnChainConfigPayload, OnChainConsensusConfig,
+        ValidatorSet,
+    },
+    transaction::{RawTransaction, Script, SignedTransaction, TransactionPayload},
+};
+use futures::{channel::mpsc, StreamExt};
+use maplit::hashmap;
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Capacity of the channels between a node and the simulated network, large enough that no
+/// message is dropped by a full channel.
+const NETWORK_CHANNEL_SIZE: usize = 1_024;
+
+/// Stands in for the mempool of a node. It holds synthetic transactions, all sent by the node's
+/// own account so that no two nodes pull the same transaction, hands them to quorum store, and
+/// drops them once the node commits them.
+struct SyntheticMempool {
+    /// Transactions not committed yet, by sequence number
+    pending: BTreeMap<u64, SignedTransaction>,
+}
+
+impl SyntheticMempool {
+    /// Creates the transactions of the sender. They only depend on the seed and the sender, so
+    /// two simulations with the same seed propose the same payloads.
+    fn new(seed: u64, sender: Author, num_transactions: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let public_key = Ed25519PrivateKey::generate(&mut rng).public_key();
+        let pending = (0..num_transactions)
+            .map(|sequence_number| {
+                let raw_txn = RawTransaction::new(
+                    sender,
+                    sequence_number,
+                    TransactionPayload::Script(Script::new(vec![], vec![], vec![])),
+                    0,
+                    0,
+                    0,
+                    ChainId::test(),
+                );
+                let txn = SignedTransaction::new(
+                    raw_txn,
+                    public_key.clone(),
+                    Ed25519Signature::dummy_signature(),
+                );
+                (sequence_number, txn)
+            })
+            .collect();
+        Self { pending }
+    }
+
+    /// Pending transactions, in order of sequence number, that quorum store doesn't have in
+    /// flight yet, within the limits of the request.
+    fn get_batch(
+        &self,
+        max_txns: u64,
+        max_bytes: u64,
+        exclude: &[TransactionInProgress],
+    ) -> Vec<SignedTransaction> {
+        let exclude: HashSet<_> = exclude
+            .iter()
+            .map(|txn| (txn.summary.sender, txn.summary.sequence_number))
+            .collect();
+        let mut bytes = 0;
+        let mut batch = vec![];
+        for txn in self.pending.values() {
+            if exclude.contains(&(txn.sender(), txn.sequence_number())) {
+                continue;
+            }
+            bytes += txn.raw_txn_bytes_len() as u64;
+            if batch.len() as u64 == max_txns || bytes > max_bytes {
+                break;
+            }
+            batch.push(txn.clone());
+        }
+        batch
+    }
+
+    fn commit(&mut self, txns: &[SignedTransaction]) {
+        for txn in txns {
+            self.pending.remove(&txn.sequence_number());
+        }
+    }
+}
+
+/// A validator running the full consensus stack in the simulation: the epoch manager starts the
+/// round manager, quorum store and buffer manager, on top of mock execution and storage, with
+/// a mempool of synthetic transactions.
+pub struct SimulatedNode {
+    /// Blocks committed by the node, in order
+    pub committed: Arc<Mutex<Vec<BlockInfo>>>,
+    /// Transactions committed by the node, in order
+    pub committed_txns: Arc<Mutex<Vec<SignedTransaction>>>,
+}
+
+impl SimulatedNode {
+    /// Starts the node, with `num_transactions` synthetic transactions in its mempool. Must be
+    /// called from the runtime running the simulation.
+    pub fn start(
+        seed: u64,
+        num_transactions: u64,
+        config: NodeConfig,
+        consensus_config: OnChainConsensusConfig,
+        storage: Arc<MockStorage>,
+        network: &mut SimulatedNetwork,
+        peers_and_metadata: Arc<PeersAndMetadata>,
+        time_service: Arc<VirtualTimeService>,
+        aptos_time_service: aptos_time_service::TimeService,
+    ) -> Self {
+        let author = config.validator_network.as_ref().unwrap().peer_id();
+
+        let (network_reqs_tx, network_reqs_rx) =
+            aptos_channel::new(QueueStyle::FIFO, NETWORK_CHANNEL_SIZE, None);
+        let (connection_reqs_tx, _) = aptos_channel::new(QueueStyle::FIFO, 8, None);
+        let (consensus_tx, consensus_rx) =
+            aptos_channel::new(QueueStyle::FIFO, NETWORK_CHANNEL_SIZE, None);
+        let (_, conn_notifs_channel) = conn_notifs_channel::new();
+        let network_sender = network::NetworkSender::new(
+            PeerManagerRequestSender::new(network_reqs_tx),
+            ConnectionRequestSender::new(connection_reqs_tx),
+        );
+        let network_client = NetworkClient::new(
+            DIRECT_SEND.into(),
+            RPC.into(),
+            hashmap! {NetworkId::Validator => network_sender},
+            peers_and_metadata,
+        );
+        let consensus_network_client = ConsensusNetworkClient::new(network_client);
+        let network_events = NetworkEvents::new(consensus_rx, conn_notifs_channel, None);
+        let network_service_events =
+            NetworkServiceEvents::new(hashmap! {NetworkId::Validator => network_events});
+
+        network.add_node(author, consensus_tx, network_reqs_rx);
+
+        let (state_sync_client, mut state_sync) = mpsc::unbounded::<Vec<SignedTransaction>>();
+        let (ordered_blocks_tx, mut ordered_blocks_events) = mpsc::unbounded::<OrderedBlocks>();
+        let state_computer = Arc::new(MockStateComputer::new(
+            state_sync_client,
+            ordered_blocks_tx,
+            Arc::clone(&storage),
+        ));
+
+        let mempool = Arc::new(Mutex::new(SyntheticMempool::new(
+            seed,
+            author,
+            num_transactions,
+        )));
+        let (quorum_store_to_mempool_sender, mut quorum_store_to_mempool_receiver) =
+            mpsc::channel(1_024);
+        let mempool_clone = mempool.clone();
+        tokio::spawn(async move {
+            while let Some(request) = quorum_store_to_mempool_receiver.next().await {
+                match request {
+                    QuorumStoreRequest::GetBatchRequest(
+                        max_txns,
+                        max_bytes,
+                        _,
+                        _,
+                        exclude,
+                        callback,
+                    ) => {
+                        let batch = mempool_clone
+                            .lock()
+                            .get_batch(max_txns, max_bytes, &exclude);
+                        let _ = callback.send(Ok(QuorumStoreResponse::GetBatchResponse(batch)));
+                    },
+                    QuorumStoreRequest::RejectNotification(_, callback) => {
+                        let _ = callback.send(Ok(QuorumStoreResponse::CommitResponse()));
+                    },
+                }
+            }
+        });
+
+        let (reconfig_sender, reconfig_events) = aptos_channel::new(QueueStyle::LIFO, 1, None);
+        let reconfig_listener = ReconfigNotificationListener {
+            notification_receiver: reconfig_events,
+        };
+        let mut configs = HashMap::new();
+        configs.insert(
+            ValidatorSet::CONFIG_ID,
+            bcs::to_bytes(storage.get_validator_set()).unwrap(),
+        );
+        configs.insert(
+            OnChainConsensusConfig::CONFIG_ID,
+            // Requires double serialization, check deserialize_into_config for more details
+            bcs::to_bytes(&bcs::to_bytes(&consensus_config).unwrap()).unwrap(),
+        );
+        let payload = OnChainConfigPayload::new(1, InMemoryOnChainConfig::new(configs));
+        reconfig_sender
+            .push((), ReconfigNotification {
+                version: 1,
+                on_chain_configs: payload,
+            })
+            .unwrap();
+
+        let (timeout_sender, timeout_receiver) =
+            aptos_channels::new(1_024, &counters::PENDING_ROUND_TIMEOUTS);
+        let (self_sender, self_receiver) =
+            aptos_channels::new(1_024, &counters::PENDING_SELF_MESSAGES);
+
+        let quorum_store_storage = Arc::new(MockQuorumStoreDB::new());
+        let bounded_executor = BoundedExecutor::new(2, tokio::runtime::Handle::current());
+
+        let epoch_mgr = EpochManager::new(
+            &config,
+            time_service,
+            self_sender,
+            consensus_network_client,
+            timeout_sender,
+            quorum_store_to_mempool_sender,
+            state_computer.clone(),
+            storage,
+            quorum_store_storage,
+            reconfig_listener,
+            bounded_executor,
+            aptos_time_service,
+        );
+        let (network_task, network_receiver) =
+            NetworkTask::new(network_service_events, self_receiver);
+
+        tokio::spawn(network_task.start());
+        tokio::spawn(epoch_mgr.start(timeout_receiver, network_receiver));
+
+        let committed = Arc::new(Mutex::new(vec![]));
+        let committed_clone = committed.clone();
+        tokio::spawn(async move {
+            while let Some(ordered_blocks) = ordered_blocks_events.next().await {
+                let blocks: Vec<_> = ordered_blocks
+                    .ordered_blocks
+                    .iter()
+                    .map(|block| block.block_info())
+                    .collect();
+                state_computer
+                    .commit_to_storage(ordered_blocks)
+                    .await
+                    .unwrap();
+                committed_clone.lock().extend(blocks);
+            }
+        });
+
+        // The mock state computer notifies state sync of the committed transactions, which
+        // takes the place of the commit notifications to mempool
+        let committed_txns = Arc::new(Mutex::new(vec![]));
+        let committed_txns_clone = committed_txns.clone();
+        tokio::spawn(async move {
+            while let Some(txns) = state_sync.next().await {
+                mempool.lock().commit(&txns);
+                committed_txns_clone.lock().extend(txns);
+            }
+        });
+
+        Self {
+            committed,
+            committed_txns,
+        }
+    }
+}
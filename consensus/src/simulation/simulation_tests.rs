@@ -0,0 +1,111 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    network_interface::ConsensusMsg,
+    simulation::{
+        network::{drop_messages, drop_randomly, partition, MessageKind},
+        simulator::{SimulationConfig, Simulator},
+    },
+};
+use std::{collections::HashSet, time::Duration};
+
+/// Virtual time given to the nodes to commit the expected rounds
+const COMMIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[test]
+/// 4 honest nodes commit the same blocks, for several seeds.
+///
+/// Run the test:
+/// cargo xtest -p consensus simulation_commit_test -- --nocapture
+fn simulation_commit_test() {
+    for seed in 0..3 {
+        let simulator = Simulator::new(SimulationConfig::new(seed, 4));
+        assert!(
+            simulator.run_until_committed(10, COMMIT_TIMEOUT),
+            "[seed {}] Round 10 not committed",
+            seed
+        );
+        simulator.check_safety();
+    }
+}
+
+#[test]
+/// Two simulations with the same seed deliver the same messages at the same time, and commit
+/// the same blocks.
+fn simulation_determinism_test() {
+    let run = |seed| {
+        let simulator = Simulator::new(SimulationConfig::new(seed, 4));
+        simulator.run_for(Duration::from_secs(10));
+        let committed: Vec<_> = (0..simulator.num_nodes())
+            .map(|index| simulator.committed_blocks(index))
+            .collect();
+        (simulator.trace(), committed)
+    };
+
+    let (trace, committed) = run(7);
+    assert!(!committed[0].is_empty());
+    assert_eq!((trace.clone(), committed.clone()), run(7));
+    assert_ne!(trace, run(8).0);
+}
+
+#[test]
+/// Node 0 is cut off from the other nodes for 20 seconds. The other nodes keep committing,
+/// and node 0 catches up once the partition ends.
+fn simulation_partition_test() {
+    let mut config = SimulationConfig::new(0, 4);
+    config.faults.push(partition(
+        vec![vec![0], vec![1, 2, 3]],
+        Duration::from_secs(0),
+        Duration::from_secs(20),
+    ));
+    let simulator = Simulator::new(config);
+
+    simulator.run_for(Duration::from_secs(20));
+    assert!(simulator.committed_blocks(0).is_empty());
+    assert!(!simulator.committed_blocks(1).is_empty());
+
+    let round = simulator.committed_blocks(1).last().unwrap().round() + 5;
+    assert!(simulator.run_until_committed(round, COMMIT_TIMEOUT));
+    assert!(simulator.now() > Duration::from_secs(20));
+    simulator.check_safety();
+}
+
+#[test]
+/// 10% of the messages are lost, and node 3 never gets a vote through, the nodes still make
+/// progress thanks to timeouts.
+fn simulation_message_loss_test() {
+    let mut config = SimulationConfig::new(0, 4);
+    config.faults.push(drop_messages(|ctx| {
+        ctx.from == 3
+            && ctx.kind == MessageKind::DirectSend
+            && matches!(ctx.msg, ConsensusMsg::VoteMsg(_))
+    }));
+    config.faults.push(drop_randomly(0.1));
+    let simulator = Simulator::new(config);
+
+    assert!(simulator.run_until_committed(10, COMMIT_TIMEOUT));
+    simulator.check_safety();
+}
+
+#[test]
+/// Every node hands the synthetic transactions of its mempool to quorum store, and every node
+/// commits the transactions of all the nodes once, in the same order.
+fn simulation_transactions_test() {
+    let config = SimulationConfig::new(0, 4);
+    let num_transactions = config.num_nodes * config.num_transactions_per_node as usize;
+    let simulator = Simulator::new(config);
+    assert!(simulator.run_until_transactions_committed(COMMIT_TIMEOUT));
+
+    let committed = simulator.committed_transactions(0);
+    let unique: HashSet<_> = committed
+        .iter()
+        .map(|txn| (txn.sender(), txn.sequence_number()))
+        .collect();
+    assert_eq!(unique.len(), committed.len());
+    assert_eq!(committed.len(), num_transactions);
+    for index in 1..simulator.num_nodes() {
+        assert_eq!(simulator.committed_transactions(index), committed);
+    }
+    simulator.check_safety();
+}
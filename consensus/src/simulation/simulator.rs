@@ -0,0 +1,308 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    simulation::{
+        network::{FaultInjector, SimulatedNetwork, TraceEntry, TICK},
+        node::SimulatedNode,
+        time_service::VirtualTimeService,
+    },
+    test_utils::MockStorage,
+};
+use aptos_config::{
+    config::{NodeConfig, WaypointConfig},
+    generator::{self, ValidatorSwarm},
+    network_id::{NetworkId, PeerNetworkId},
+};
+use aptos_consensus_types::common::{Author, Round};
+use aptos_infallible::Mutex;
+use aptos_network::{
+    application::storage::PeersAndMetadata, protocols::wire::handshake::v1::ProtocolIdSet,
+    transport::ConnectionMetadata, ProtocolId,
+};
+use aptos_types::{
+    block_info::BlockInfo,
+    on_chain_config::{
+        ConsensusConfigV1, OnChainConsensusConfig, ProposerElectionType, ValidatorSet,
+    },
+    transaction::SignedTransaction,
+    validator_info::ValidatorInfo,
+    waypoint::Waypoint,
+};
+use std::{collections::HashMap, iter::FromIterator, sync::Arc, time::Duration};
+use tokio::{
+    runtime::{Builder, Runtime},
+    time::Instant,
+};
+
+/// Unix timestamp of the start of the simulation
+const START_TIMESTAMP: Duration = Duration::from_secs(1_000_000);
+
+/// How often `run_until_committed` checks the committed blocks
+const COMMIT_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct SimulationConfig {
+    /// Seeds every random decision of the network, two simulations with the same config run
+    /// exactly the same way
+    pub seed: u64,
+    pub num_nodes: usize,
+    pub min_latency: Duration,
+    pub max_latency: Duration,
+    pub proposer_election_type: ProposerElectionType,
+    /// Synthetic transactions in the mempool of each node
+    pub num_transactions_per_node: u64,
+    /// Fault injectors run on every message, in order, until one of them returns a fault
+    pub faults: Vec<FaultInjector>,
+}
+
+impl SimulationConfig {
+    pub fn new(seed: u64, num_nodes: usize) -> Self {
+        Self {
+            seed,
+            num_nodes,
+            min_latency: Duration::from_millis(5),
+            max_latency: Duration::from_millis(50),
+            proposer_election_type: ProposerElectionType::RotatingProposer(1),
+            num_transactions_per_node: 20,
+            faults: vec![],
+        }
+    }
+}
+
+/// Runs the full consensus stack of several validators in a single thread, with virtual time.
+///
+/// All the nodes run in one current thread runtime whose clock is paused: time only advances
+/// when every task is waiting on a timer, so rounds that would take seconds are simulated in
+/// milliseconds. The messages between the nodes go through a `SimulatedNetwork` that delivers
+/// them one at a time in an order only depending on the seed, which makes a failing seed
+/// reproducible. Each node proposes the synthetic transactions of its own mempool.
+pub struct Simulator {
+    seed: u64,
+    runtime: Runtime,
+    start: Instant,
+    nodes: Vec<SimulatedNode>,
+    num_transactions: usize,
+    trace: Arc<Mutex<Vec<TraceEntry>>>,
+}
+
+fn author_from_config(config: &NodeConfig) -> Author {
+    config.validator_network.as_ref().unwrap().peer_id()
+}
+
+impl Simulator {
+    /// Creates the nodes and starts them.
+    pub fn new(config: SimulationConfig) -> Self {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .expect("Failed to create the simulation runtime");
+        let _entered_runtime = runtime.enter();
+        let start = Instant::now();
+
+        let ValidatorSwarm {
+            nodes: mut node_configs,
+        } = generator::validator_swarm_for_testing(config.num_nodes);
+        node_configs.sort_by_key(author_from_config);
+        let validator_set = ValidatorSet::new(
+            node_configs
+                .iter()
+                .enumerate()
+                .map(|(index, config)| {
+                    let sr_test_config = config.consensus.safety_rules.test.as_ref().unwrap();
+                    ValidatorInfo::new_with_test_network_keys(
+                        sr_test_config.author,
+                        sr_test_config.consensus_key.as_ref().unwrap().public_key(),
+                        1,
+                        index as u64,
+                    )
+                })
+                .collect(),
+        );
+
+        let peers_and_metadata = PeersAndMetadata::new(&[NetworkId::Validator]);
+        for config in &node_configs {
+            let peer_id = author_from_config(config);
+            let mut conn_meta = ConnectionMetadata::mock(peer_id);
+            conn_meta.application_protocols = ProtocolIdSet::from_iter([
+                ProtocolId::ConsensusDirectSendJson,
+                ProtocolId::ConsensusDirectSendBcs,
+                ProtocolId::ConsensusRpcBcs,
+            ]);
+            peers_and_metadata
+                .insert_connection_metadata(
+                    PeerNetworkId::new(NetworkId::Validator, peer_id),
+                    conn_meta,
+                )
+                .unwrap();
+        }
+
+        let time_service = Arc::new(VirtualTimeService::new(
+            runtime.handle().clone(),
+            start,
+            START_TIMESTAMP,
+        ));
+        // Components using the aptos time service follow the runtime's clock as well
+        let mock_time_service = aptos_time_service::MockTimeService::new();
+        mock_time_service.advance(START_TIMESTAMP);
+        let aptos_time_service = aptos_time_service::TimeService::from_mock(mock_time_service);
+        let ticker = aptos_time_service.clone().into_mock();
+        runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(TICK).await;
+                ticker.advance(TICK);
+            }
+        });
+
+        let consensus_config = OnChainConsensusConfig::V2(ConsensusConfigV1 {
+            proposer_election_type: config.proposer_election_type,
+            ..ConsensusConfigV1::default()
+        });
+
+        let mut network = SimulatedNetwork::new(
+            config.seed,
+            config.min_latency,
+            config.max_latency,
+            config.faults,
+            start,
+        );
+        let trace = network.trace();
+        let nodes = node_configs
+            .into_iter()
+            .map(|mut node_config| {
+                let (_, storage) = MockStorage::start_for_testing(validator_set.clone());
+                let waypoint = Waypoint::new_epoch_boundary(&storage.get_ledger_info())
+                    .expect("Unable to produce waypoint with the provided LedgerInfo");
+                node_config
+                    .consensus
+                    .safety_rules
+                    .test
+                    .as_mut()
+                    .unwrap()
+                    .waypoint = Some(waypoint);
+                node_config.base.waypoint = WaypointConfig::FromConfig(waypoint);
+
+                SimulatedNode::start(
+                    config.seed,
+                    config.num_transactions_per_node,
+                    node_config,
+                    consensus_config.clone(),
+                    storage,
+                    &mut network,
+                    peers_and_metadata.clone(),
+                    time_service.clone(),
+                    aptos_time_service.clone(),
+                )
+            })
+            .collect();
+        runtime.spawn(network.start());
+
+        Self {
+            seed: config.seed,
+            runtime,
+            start,
+            nodes,
+            num_transactions: config.num_nodes * config.num_transactions_per_node as usize,
+            trace,
+        }
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Virtual time elapsed since the start of the simulation.
+    pub fn now(&self) -> Duration {
+        let _entered_runtime = self.runtime.enter();
+        self.start.elapsed()
+    }
+
+    /// Runs the simulation for the given virtual duration.
+    pub fn run_for(&self, duration: Duration) {
+        self.runtime.block_on(tokio::time::sleep(duration));
+    }
+
+    /// Runs the simulation until every node committed a block of at least the given round, or
+    /// the given virtual duration elapsed. Returns whether the round was committed.
+    pub fn run_until_committed(&self, round: Round, timeout: Duration) -> bool {
+        self.run_until(timeout, || {
+            self.nodes.iter().all(|node| {
+                node.committed
+                    .lock()
+                    .last()
+                    .map_or(false, |block| block.round() >= round)
+            })
+        })
+    }
+
+    /// Runs the simulation until every node committed as many transactions as there are
+    /// synthetic transactions in all the mempools, or the given virtual duration elapsed.
+    /// Returns whether the transactions were committed.
+    pub fn run_until_transactions_committed(&self, timeout: Duration) -> bool {
+        self.run_until(timeout, || {
+            self.nodes
+                .iter()
+                .all(|node| node.committed_txns.lock().len() >= self.num_transactions)
+        })
+    }
+
+    fn run_until<F: Fn() -> bool>(&self, timeout: Duration, done: F) -> bool {
+        self.runtime.block_on(async {
+            let wait = async {
+                while !done() {
+                    tokio::time::sleep(COMMIT_CHECK_INTERVAL).await;
+                }
+            };
+            tokio::time::timeout(timeout, wait).await.is_ok()
+        })
+    }
+
+    /// Blocks committed by the node, in order.
+    pub fn committed_blocks(&self, index: usize) -> Vec<BlockInfo> {
+        self.nodes[index].committed.lock().clone()
+    }
+
+    /// Transactions committed by the node, in order.
+    pub fn committed_transactions(&self, index: usize) -> Vec<SignedTransaction> {
+        self.nodes[index].committed_txns.lock().clone()
+    }
+
+    /// The messages handled by the network so far.
+    pub fn trace(&self) -> Vec<TraceEntry> {
+        self.trace.lock().clone()
+    }
+
+    /// Checks that every node committed blocks in increasing rounds, and that the nodes
+    /// committed the same block for every round. A node that fell behind syncs to the latest
+    /// commit without committing the blocks in between, so it may miss some rounds.
+    pub fn check_safety(&self) {
+        let mut committed_rounds: HashMap<Round, (usize, BlockInfo)> = HashMap::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let committed = node.committed.lock().clone();
+            for window in committed.windows(2) {
+                assert!(
+                    window[0].round() < window[1].round(),
+                    "[seed {}] Node {} committed round {} after round {}",
+                    self.seed,
+                    index,
+                    window[1].round(),
+                    window[0].round()
+                );
+            }
+            for block in committed {
+                let (other_index, other_block) = committed_rounds
+                    .entry(block.round())
+                    .or_insert_with(|| (index, block.clone()));
+                assert_eq!(
+                    *other_block,
+                    block,
+                    "[seed {}] Nodes {} and {} committed different blocks for round {}",
+                    self.seed,
+                    other_index,
+                    index,
+                    block.round()
+                );
+            }
+        }
+    }
+}
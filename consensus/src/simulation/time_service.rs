@@ -0,0 +1,51 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::util::time_service::{ScheduledTask, TimeService};
+use async_trait::async_trait;
+use futures::future::{AbortHandle, Abortable};
+use std::time::Duration;
+use tokio::{runtime::Handle, time::Instant};
+
+/// TimeService for a runtime with paused time, where time only advances when every task is
+/// idle. Unlike `ClockTimeService`, timestamps follow the runtime's clock instead of the system
+/// clock, starting from `start_timestamp` at `start`.
+pub struct VirtualTimeService {
+    executor: Handle,
+    start: Instant,
+    start_timestamp: Duration,
+}
+
+impl VirtualTimeService {
+    pub fn new(executor: Handle, start: Instant, start_timestamp: Duration) -> Self {
+        Self {
+            executor,
+            start,
+            start_timestamp,
+        }
+    }
+}
+
+#[async_trait]
+impl TimeService for VirtualTimeService {
+    fn run_after(&self, timeout: Duration, mut t: Box<dyn ScheduledTask>) -> AbortHandle {
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let task = Abortable::new(
+            async move {
+                tokio::time::sleep(timeout).await;
+                t.run().await;
+            },
+            abort_registration,
+        );
+        self.executor.spawn(task);
+        abort_handle
+    }
+
+    fn get_current_timestamp(&self) -> Duration {
+        self.start_timestamp + self.start.elapsed()
+    }
+
+    async fn sleep(&self, t: Duration) {
+        tokio::time::sleep(t).await
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::server::utils::{CONTENT_TYPE_JSON, CONTENT_TYPE_TEXT};
+use aptos_config::config::NodeConfig;
+use aptos_consensus_types::round_timeline::{RoundTimelineRecorder, ValidatorTimeline};
+use hyper::{Body, StatusCode};
+use std::sync::Arc;
+
+// The message to display when the consensus timeline endpoint is disabled
+pub const CONSENSUS_TIMELINE_DISABLED_MESSAGE: &str =
+    "This endpoint is disabled! Enable it in the node config at inspection_service.expose_consensus_timeline: true";
+
+/// Handles a new consensus timeline request
+pub fn handle_consensus_timeline_request(
+    node_config: &NodeConfig,
+    round_timelines: Arc<RoundTimelineRecorder>,
+) -> (StatusCode, Body, String) {
+    // Only return the consensus timeline if the endpoint is enabled
+    if node_config.inspection_service.expose_consensus_timeline {
+        (
+            StatusCode::OK,
+            Body::from(get_consensus_timeline_json(node_config, round_timelines)),
+            CONTENT_TYPE_JSON.into(),
+        )
+    } else {
+        (
+            StatusCode::FORBIDDEN,
+            Body::from(CONSENSUS_TIMELINE_DISABLED_MESSAGE),
+            CONTENT_TYPE_TEXT.into(),
+        )
+    }
+}
+
+/// Returns the timeline of the recent consensus rounds as a JSON string
+fn get_consensus_timeline_json(
+    node_config: &NodeConfig,
+    round_timelines: Arc<RoundTimelineRecorder>,
+) -> String {
+    let validator_timeline = ValidatorTimeline {
+        author: node_config.get_peer_id(),
+        rounds: round_timelines.timelines(),
+    };
+
+    // Return the timeline as a JSON string
+    match serde_json::to_string(&validator_timeline) {
+        Ok(validator_timeline) => validator_timeline,
+        Err(error) => format!("Failed to get the consensus timeline! Error: {}", error),
+    }
+}
@@ -3,6 +3,7 @@
 
 use crate::server::utils::CONTENT_TYPE_TEXT;
 use aptos_config::config::NodeConfig;
+use aptos_consensus_types::round_timeline::RoundTimelineRecorder;
 use aptos_logger::debug;
 use aptos_network::application::storage::PeersAndMetadata;
 use hyper::{
@@ -17,6 +18,7 @@ use std::{
 };
 
 mod configuration;
+mod consensus_timeline;
 mod index;
 mod json_encoder;
 mod metrics;
@@ -29,6 +31,7 @@ mod tests;
 
 // The list of endpoints offered by the inspection service
 pub const CONFIGURATION_PATH: &str = "/configuration";
+pub const CONSENSUS_TIMELINE_PATH: &str = "/consensus_timeline";
 pub const FORGE_METRICS_PATH: &str = "/forge_metrics";
 pub const INDEX_PATH: &str = "/";
 pub const JSON_METRICS_PATH: &str = "/json_metrics";
@@ -46,6 +49,7 @@ pub const UNEXPECTED_ERROR_MESSAGE: &str = "An unexpected error was encountered!
 pub fn start_inspection_service(
     node_config: NodeConfig,
     peers_and_metadata: Arc<PeersAndMetadata>,
+    round_timelines: Arc<RoundTimelineRecorder>,
 ) {
     // Fetch the service port and address
     let service_port = node_config.inspection_service.port;
@@ -72,9 +76,15 @@ pub fn start_inspection_service(
         let make_service = make_service_fn(move |_conn| {
             let node_config = node_config.clone();
             let peers_and_metadata = peers_and_metadata.clone();
+            let round_timelines = round_timelines.clone();
             async move {
                 Ok::<_, Infallible>(service_fn(move |request| {
-                    serve_requests(request, node_config.clone(), peers_and_metadata.clone())
+                    serve_requests(
+                        request,
+                        node_config.clone(),
+                        peers_and_metadata.clone(),
+                        round_timelines.clone(),
+                    )
                 }))
             }
         });
@@ -94,6 +104,7 @@ async fn serve_requests(
     req: Request<Body>,
     node_config: NodeConfig,
     peers_and_metadata: Arc<PeersAndMetadata>,
+    round_timelines: Arc<RoundTimelineRecorder>,
 ) -> Result<Response<Body>, hyper::Error> {
     // Process the request and get the response components
     let (status_code, body, content_type) = match req.uri().path() {
@@ -102,6 +113,11 @@ async fn serve_requests(
             // Exposes the node configuration
             configuration::handle_configuration_request(&node_config)
         },
+        CONSENSUS_TIMELINE_PATH => {
+            // /consensus_timeline
+            // Exposes the timeline of the recent consensus rounds
+            consensus_timeline::handle_consensus_timeline_request(&node_config, round_timelines)
+        },
         FORGE_METRICS_PATH => {
             // /forge_metrics
             // Exposes forge encoded metrics
@@ -4,13 +4,15 @@
 use crate::{
     server::{
         configuration::CONFIGURATION_DISABLED_MESSAGE,
+        consensus_timeline::CONSENSUS_TIMELINE_DISABLED_MESSAGE,
         peer_information::PEER_INFO_DISABLED_MESSAGE, serve_requests,
         system_information::SYS_INFO_DISABLED_MESSAGE, utils::get_all_metrics,
     },
-    CONFIGURATION_PATH, FORGE_METRICS_PATH, INDEX_PATH, JSON_METRICS_PATH, METRICS_PATH,
-    PEER_INFORMATION_PATH, SYSTEM_INFORMATION_PATH,
+    CONFIGURATION_PATH, CONSENSUS_TIMELINE_PATH, FORGE_METRICS_PATH, INDEX_PATH, JSON_METRICS_PATH,
+    METRICS_PATH, PEER_INFORMATION_PATH, SYSTEM_INFORMATION_PATH,
 };
 use aptos_config::config::NodeConfig;
+use aptos_consensus_types::round_timeline::{RoundEvent, RoundTimelineRecorder, ValidatorTimeline};
 use aptos_network::application::storage::PeersAndMetadata;
 use assert_approx_eq::assert_approx_eq;
 use futures::executor::block_on;
@@ -18,7 +20,7 @@ use hyper::{body, Body, Method, Request, Response, StatusCode};
 use once_cell::sync::Lazy;
 use prometheus::{proto::MetricFamily, register_int_counter, Counter, IntCounter, Opts, Registry};
 use rusty_fork::rusty_fork_test;
-use std::{io::read_to_string, string::String};
+use std::{io::read_to_string, string::String, sync::Arc};
 
 // This metrics counter only exists in this test context; the rest of the
 // system's metrics counters don't exist, so we need to add this for tests.
@@ -26,6 +28,10 @@ const INT_COUNTER_NAME: &str = "INT_COUNTER";
 static INT_COUNTER: Lazy<IntCounter> =
     Lazy::new(|| register_int_counter!(INT_COUNTER_NAME, "An integer counter").unwrap());
 
+// The consensus timeline served in this test context
+static ROUND_TIMELINES: Lazy<Arc<RoundTimelineRecorder>> =
+    Lazy::new(|| Arc::new(RoundTimelineRecorder::default()));
+
 #[tokio::test]
 async fn test_inspect_configuration() {
     // Create a validator config
@@ -53,6 +59,38 @@ async fn test_inspect_configuration() {
     assert!(response_body_string.contains("expose_configuration: true"));
 }
 
+#[tokio::test]
+async fn test_inspect_consensus_timeline() {
+    // Create a validator config
+    let mut node_config = NodeConfig::get_default_validator_config();
+
+    // Disable the consensus timeline endpoint and ping it
+    node_config.inspection_service.expose_consensus_timeline = false;
+    let mut response = send_get_request_to_path(&node_config, CONSENSUS_TIMELINE_PATH).await;
+    let response_body = body::to_bytes(response.body_mut()).await.unwrap();
+
+    // Verify that the response contains an error
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response_body, CONSENSUS_TIMELINE_DISABLED_MESSAGE);
+
+    // Record a round, enable the consensus timeline endpoint and ping it
+    ROUND_TIMELINES.record(1, 5, RoundEvent::ProposalReceived, 1_000);
+    node_config.inspection_service.expose_consensus_timeline = true;
+    let mut response = send_get_request_to_path(&node_config, CONSENSUS_TIMELINE_PATH).await;
+    let response_body = body::to_bytes(response.body_mut()).await.unwrap();
+    let validator_timeline: ValidatorTimeline = serde_json::from_slice(&response_body).unwrap();
+
+    // Verify that the response contains the expected information
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(validator_timeline.author, node_config.get_peer_id());
+    assert_eq!(validator_timeline.rounds.len(), 1);
+    assert_eq!(validator_timeline.rounds[0].round, 5);
+    assert_eq!(
+        validator_timeline.rounds[0].events[0].event,
+        RoundEvent::ProposalReceived
+    );
+}
+
 #[tokio::test]
 async fn test_inspect_forge_metrics() {
     // Create a VFN config
@@ -255,6 +293,7 @@ async fn send_get_request_to_path(config: &NodeConfig, endpoint: &str) -> Respon
             .unwrap(),
         config.clone(),
         PeersAndMetadata::new(&[]),
+        ROUND_TIMELINES.clone(),
     )
     .await
     .unwrap()
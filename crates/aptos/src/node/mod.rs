@@ -25,6 +25,9 @@ use aptos_backup_cli::{
     utils::GlobalRestoreOpt,
 };
 use aptos_cached_packages::aptos_stdlib;
+use aptos_consensus_types::round_timeline::{
+    merge_timelines, MergedRoundTimeline, ValidatorTimeline,
+};
 use aptos_crypto::{bls12381, bls12381::PublicKey, x25519, ValidCryptoMaterialStringExt};
 use aptos_genesis::config::{HostAndPort, OperatorConfiguration};
 use aptos_logger::Level;
@@ -72,6 +75,7 @@ pub enum NodeTool {
     InitializeValidator(InitializeValidator),
     JoinValidatorSet(JoinValidatorSet),
     LeaveValidatorSet(LeaveValidatorSet),
+    MergeConsensusTimelines(MergeConsensusTimelines),
     ShowEpochInfo(ShowEpochInfo),
     ShowValidatorConfig(ShowValidatorConfig),
     ShowValidatorSet(ShowValidatorSet),
@@ -96,6 +100,7 @@ impl NodeTool {
             InitializeValidator(tool) => tool.execute_serialized().await,
             JoinValidatorSet(tool) => tool.execute_serialized().await,
             LeaveValidatorSet(tool) => tool.execute_serialized().await,
+            MergeConsensusTimelines(tool) => tool.execute_serialized().await,
             ShowEpochInfo(tool) => tool.execute_serialized().await,
             ShowValidatorSet(tool) => tool.execute_serialized().await,
             ShowValidatorStake(tool) => tool.execute_serialized().await,
@@ -1366,6 +1371,63 @@ impl CliCommand<String> for CheckNetworkConnectivity {
     }
 }
 
+/// Merge the consensus timelines of several validators
+///
+/// Takes the timelines exported by the `/consensus_timeline` endpoint of the inspection
+/// service of each validator, and shows for each round the events of all the validators
+/// ordered by time.
+#[derive(Parser)]
+pub struct MergeConsensusTimelines {
+    /// Files containing the timeline exported by each validator
+    #[clap(long, value_parser, num_args = 1.., required = true)]
+    pub(crate) timeline_files: Vec<PathBuf>,
+
+    /// Only show the rounds of this epoch
+    #[clap(long)]
+    pub(crate) epoch: Option<u64>,
+
+    /// First round to show
+    #[clap(long)]
+    pub(crate) start_round: Option<u64>,
+
+    /// Last round to show
+    #[clap(long)]
+    pub(crate) end_round: Option<u64>,
+}
+
+#[async_trait]
+impl CliCommand<Vec<MergedRoundTimeline>> for MergeConsensusTimelines {
+    fn command_name(&self) -> &'static str {
+        "MergeConsensusTimelines"
+    }
+
+    async fn execute(self) -> CliTypedResult<Vec<MergedRoundTimeline>> {
+        let timelines = self
+            .timeline_files
+            .iter()
+            .map(|file| {
+                serde_json::from_slice::<ValidatorTimeline>(&read_from_file(file)?).map_err(|e| {
+                    CliError::UnableToParse(
+                        "ValidatorTimeline",
+                        format!("{}: {}", file.display(), e),
+                    )
+                })
+            })
+            .collect::<CliTypedResult<Vec<_>>>()?;
+
+        Ok(merge_timelines(&timelines)
+            .into_iter()
+            .filter(|timeline| {
+                self.epoch.map_or(true, |epoch| timeline.epoch == epoch)
+                    && self
+                        .start_round
+                        .map_or(true, |round| timeline.round >= round)
+                    && self.end_round.map_or(true, |round| timeline.round <= round)
+            })
+            .collect())
+    }
+}
+
 /// Show epoch information
 ///
 /// Displays the current epoch, the epoch length, and the estimated time of the next epoch
@@ -54,6 +54,28 @@ impl PriorityIndex {
         self.data.contains(&self.make_key(txn))
     }
 
+    /// Returns the positions in the queue of the given transactions of `address` that are in it,
+    /// by sequence number, 0 being the first one pulled. This walks the queue once, up to the
+    /// last of them.
+    pub(crate) fn positions<'a>(
+        &self,
+        address: &AccountAddress,
+        txns: impl IntoIterator<Item = &'a MempoolTransaction>,
+    ) -> HashMap<u64, usize> {
+        let mut remaining = txns.into_iter().filter(|txn| self.contains(txn)).count();
+        let mut positions = HashMap::new();
+        for (position, key) in self.iter().enumerate() {
+            if remaining == 0 {
+                break;
+            }
+            if key.address == *address {
+                positions.insert(key.sequence_number.transaction_sequence_number, position);
+                remaining -= 1;
+            }
+        }
+        positions
+    }
+
     fn make_key(&self, txn: &MempoolTransaction) -> OrderedQueueKey {
         OrderedQueueKey {
             gas_ranking_score: txn.ranking_score,
@@ -464,6 +486,10 @@ impl ParkingLotIndex {
             .map_or(false, |(_account, txns)| txns.contains(seq_num))
     }
 
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &(AccountAddress, BTreeSet<u64>)> {
+        self.data.iter()
+    }
+
     /// Returns a random "non-ready" transaction (with highest sequence number for that account).
     pub(crate) fn get_poppable(&self) -> Option<TxnPointer> {
         let mut rng = rand::thread_rng();
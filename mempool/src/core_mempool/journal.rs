@@ -0,0 +1,148 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Append-only journal of transactions accepted into mempool, so they can be
+//! re-validated and re-inserted after a node restart.
+//!
+//! Each record is a little-endian `u32` length followed by the BCS bytes of a
+//! `JournalEntry`. Removals are not recorded; instead the journal is
+//! periodically rewritten from the current mempool content. A torn record at
+//! the end of the file (e.g. from a crash mid-append) is dropped on open.
+use crate::core_mempool::transaction::{MempoolTransaction, SubmittedBy, TimelineState};
+use anyhow::Result;
+use aptos_types::transaction::SignedTransaction;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Name of the journal file under the node's data directory.
+pub const MEMPOOL_JOURNAL_FILE: &str = "mempool_journal";
+
+const LEN_PREFIX_BYTES: usize = std::mem::size_of::<u32>();
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) struct JournalEntry {
+    pub txn: SignedTransaction,
+    pub ranking_score: u64,
+    // System expiration time assigned on the original insertion.
+    pub expiration_time: Duration,
+    pub client_submitted: bool,
+    // False if the transaction was never qualified for broadcast (`TimelineState::NonQualified`).
+    pub broadcastable: bool,
+}
+
+impl JournalEntry {
+    pub(crate) fn timeline_state(&self) -> TimelineState {
+        if self.broadcastable {
+            TimelineState::NotReady
+        } else {
+            TimelineState::NonQualified
+        }
+    }
+}
+
+impl From<&MempoolTransaction> for JournalEntry {
+    fn from(txn: &MempoolTransaction) -> Self {
+        Self {
+            txn: txn.txn.clone(),
+            ranking_score: txn.ranking_score,
+            expiration_time: txn.expiration_time,
+            client_submitted: txn.insertion_info.submitted_by == SubmittedBy::Client,
+            broadcastable: txn.timeline_state != TimelineState::NonQualified,
+        }
+    }
+}
+
+pub(crate) struct MempoolJournal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    num_records: usize,
+}
+
+impl MempoolJournal {
+    /// Opens (or creates) the journal at `path`, returning it together with all the entries
+    /// that could be recovered from a previous run.
+    pub(crate) fn open(path: &Path) -> Result<(Self, Vec<JournalEntry>)> {
+        let bytes = if path.exists() {
+            fs::read(path)?
+        } else {
+            vec![]
+        };
+
+        let mut entries = vec![];
+        let mut offset = 0;
+        while offset + LEN_PREFIX_BYTES <= bytes.len() {
+            let len = u32::from_le_bytes(
+                bytes[offset..offset + LEN_PREFIX_BYTES]
+                    .try_into()
+                    .expect("Slice has the length of a u32."),
+            ) as usize;
+            let start = offset + LEN_PREFIX_BYTES;
+            let end = start + len;
+            if end > bytes.len() {
+                break;
+            }
+            match bcs::from_bytes(&bytes[start..end]) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => break,
+            }
+            offset = end;
+        }
+
+        let mut file = OpenOptions::new().create(true).write(true).open(path)?;
+        // Drop whatever could not be decoded so new records are appended after the last good one.
+        file.set_len(offset as u64)?;
+        file.seek(SeekFrom::End(0))?;
+
+        let journal = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            num_records: entries.len(),
+        };
+        Ok((journal, entries))
+    }
+
+    /// Appends one record. The record is flushed to the OS, but not fsync'ed.
+    pub(crate) fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        Self::write_record(&mut self.writer, entry)?;
+        self.writer.flush()?;
+        self.num_records += 1;
+        Ok(())
+    }
+
+    /// Atomically replaces the journal content with `entries`.
+    pub(crate) fn rewrite(&mut self, entries: impl Iterator<Item = JournalEntry>) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        let mut num_records = 0;
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            for entry in entries {
+                Self::write_record(&mut writer, &entry)?;
+                num_records += 1;
+            }
+            writer.into_inner()?.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+
+        let file = OpenOptions::new().append(true).open(&self.path)?;
+        self.writer = BufWriter::new(file);
+        self.num_records = num_records;
+        Ok(())
+    }
+
+    /// Number of records in the journal, including ones for transactions no longer in mempool.
+    pub(crate) fn num_records(&self) -> usize {
+        self.num_records
+    }
+
+    fn write_record(writer: &mut impl Write, entry: &JournalEntry) -> Result<()> {
+        let bytes = bcs::to_bytes(entry)?;
+        writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+}
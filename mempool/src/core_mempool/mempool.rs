@@ -7,12 +7,15 @@
 use crate::{
     core_mempool::{
         index::TxnPointer,
+        journal::{JournalEntry, MempoolJournal, MEMPOOL_JOURNAL_FILE},
         transaction::{InsertionInfo, MempoolTransaction, TimelineState},
         transaction_store::TransactionStore,
     },
     counters,
     logging::{LogEntry, LogSchema, TxnsLog},
-    shared_mempool::types::MultiBucketTimelineIndexIds,
+    shared_mempool::types::{
+        AccountMempoolInfo, MultiBucketTimelineIndexIds, ParkedAccount, RejectedTransaction,
+    },
 };
 use aptos_config::config::NodeConfig;
 use aptos_consensus_types::common::TransactionInProgress;
@@ -25,24 +28,57 @@ use aptos_types::{
     vm_status::DiscardedVMStatus,
 };
 use std::{
+    cmp::max,
     collections::{HashMap, HashSet},
     time::{Duration, SystemTime},
 };
 
+/// The journal is only compacted once it holds at least this many records.
+const JOURNAL_COMPACTION_MIN_RECORDS: usize = 10_000;
+
 pub struct Mempool {
     // Stores the metadata of all transactions in mempool (of all states).
     transactions: TransactionStore,
 
     pub system_transaction_timeout: Duration,
+
+    // Journal of accepted transactions, only present if persistence is enabled.
+    journal: Option<MempoolJournal>,
+    // Transactions recovered from the journal on startup, waiting to be replayed.
+    journaled_txns: Vec<JournalEntry>,
 }
 
 impl Mempool {
     pub fn new(config: &NodeConfig) -> Self {
+        let (journal, journaled_txns) = if config.mempool.persist_transactions {
+            let path = config.base.data_dir.join(MEMPOOL_JOURNAL_FILE);
+            match MempoolJournal::open(&path) {
+                Ok((journal, entries)) => {
+                    info!(
+                        LogSchema::new(LogEntry::Journal).num_txns(entries.len()),
+                        "Opened mempool journal at {:?}.", path,
+                    );
+                    (Some(journal), entries)
+                },
+                Err(e) => {
+                    error!(
+                        LogSchema::new(LogEntry::Journal).error(&e),
+                        "Failed to open mempool journal at {:?}, persistence disabled.", path,
+                    );
+                    (None, vec![])
+                },
+            }
+        } else {
+            (None, vec![])
+        };
+
         Mempool {
             transactions: TransactionStore::new(&config.mempool),
             system_transaction_timeout: Duration::from_secs(
                 config.mempool.system_transaction_timeout_secs,
             ),
+            journal,
+            journaled_txns,
         }
     }
 
@@ -114,6 +150,11 @@ impl Mempool {
             counters::COMMIT_REJECTED_LABEL
         };
         self.log_reject_transaction(sender, sequence_number, label);
+        self.transactions.record_rejection(
+            *sender,
+            sequence_number,
+            format!("Rejected after consensus: {:?}", reason),
+        );
         self.transactions
             .reject_transaction(sender, sequence_number, hash);
     }
@@ -189,13 +230,17 @@ impl Mempool {
 
         // don't accept old transactions (e.g. seq is less than account's current seq_number)
         if txn.sequence_number() < db_sequence_number {
-            return MempoolStatus::new(MempoolStatusCode::InvalidSeqNumber).with_message(format!(
-                "transaction sequence number is {}, current sequence number is  {}",
-                txn.sequence_number(),
-                db_sequence_number,
-            ));
+            let status =
+                MempoolStatus::new(MempoolStatusCode::InvalidSeqNumber).with_message(format!(
+                    "transaction sequence number is {}, current sequence number is  {}",
+                    txn.sequence_number(),
+                    db_sequence_number,
+                ));
+            self.record_rejection(txn.sender(), txn.sequence_number(), &status);
+            return status;
         }
 
+        let (sender, sequence_number) = (txn.sender(), txn.sequence_number());
         let now = SystemTime::now();
         let expiration_time =
             aptos_infallible::duration_since_epoch_at(&now) + self.system_transaction_timeout;
@@ -209,7 +254,82 @@ impl Mempool {
             now,
             client_submitted,
         );
+        let journal_entry = self.journal.as_ref().map(|_| JournalEntry::from(&txn_info));
+
+        let status = self.insert(txn_info);
+        if status.code == MempoolStatusCode::Accepted {
+            if let Some(entry) = journal_entry {
+                self.append_to_journal(&entry);
+            }
+        } else {
+            self.record_rejection(sender, sequence_number, &status);
+        }
+        status
+    }
+
+    fn record_rejection(
+        &mut self,
+        sender: AccountAddress,
+        sequence_number: u64,
+        status: &MempoolStatus,
+    ) {
+        self.transactions.record_rejection(
+            sender,
+            sequence_number,
+            format!("{}: {}", status.code, status.message),
+        );
+    }
+
+    /// Records a transaction that was rejected before reaching mempool, e.g. by VM validation.
+    pub(crate) fn record_validation_failure(
+        &mut self,
+        txn: &SignedTransaction,
+        status: DiscardedVMStatus,
+    ) {
+        self.transactions.record_rejection(
+            txn.sender(),
+            txn.sequence_number(),
+            format!("VM validation failed: {:?}", status),
+        );
+    }
+
+    pub(crate) fn get_account_info(&self, address: &AccountAddress) -> AccountMempoolInfo {
+        self.transactions.get_account_info(address)
+    }
+
+    pub(crate) fn get_parking_lot(&self) -> Vec<ParkedAccount> {
+        self.transactions.get_parking_lot()
+    }
 
+    pub(crate) fn get_rejected_transactions(&self) -> Vec<RejectedTransaction> {
+        self.transactions.get_rejected_transactions()
+    }
+
+    /// Re-inserts a transaction recovered from the journal, keeping its original ranking score
+    /// and system expiration time. The caller is expected to have re-validated it.
+    pub(crate) fn add_journaled_txn(
+        &mut self,
+        entry: JournalEntry,
+        db_sequence_number: u64,
+    ) -> MempoolStatus {
+        if entry.txn.sequence_number() < db_sequence_number {
+            return MempoolStatus::new(MempoolStatusCode::InvalidSeqNumber);
+        }
+
+        let txn_info = MempoolTransaction::new(
+            entry.txn.clone(),
+            entry.expiration_time,
+            entry.ranking_score,
+            entry.timeline_state(),
+            db_sequence_number,
+            SystemTime::now(),
+            entry.client_submitted,
+        );
+        self.insert(txn_info)
+    }
+
+    fn insert(&mut self, txn_info: MempoolTransaction) -> MempoolStatus {
+        let ranking_score = txn_info.ranking_score;
         let status = self.transactions.insert(txn_info);
         counters::core_mempool_txn_ranking_score(
             counters::INSERT_LABEL,
@@ -220,6 +340,42 @@ impl Mempool {
         status
     }
 
+    /// Takes the transactions recovered from the journal on startup, so they can be replayed.
+    pub(crate) fn take_journaled_txns(&mut self) -> Vec<JournalEntry> {
+        std::mem::take(&mut self.journaled_txns)
+    }
+
+    fn append_to_journal(&mut self, entry: &JournalEntry) {
+        if let Some(journal) = self.journal.as_mut() {
+            if let Err(e) = journal.append(entry) {
+                error!(
+                    LogSchema::new(LogEntry::Journal).error(&e),
+                    "Failed to append to mempool journal."
+                );
+            }
+        }
+    }
+
+    /// Rewrites the journal so that it only holds the transactions currently in mempool.
+    pub(crate) fn compact_journal(&mut self) {
+        if let Some(journal) = self.journal.as_mut() {
+            let entries = self
+                .transactions
+                .iter_transactions()
+                .map(JournalEntry::from);
+            match journal.rewrite(entries) {
+                Ok(()) => debug!(
+                    LogSchema::new(LogEntry::Journal).num_txns(journal.num_records()),
+                    "Compacted mempool journal."
+                ),
+                Err(e) => error!(
+                    LogSchema::new(LogEntry::Journal).error(&e),
+                    "Failed to compact mempool journal."
+                ),
+            }
+        }
+    }
+
     /// Fetches next block of transactions for consensus.
     /// `return_non_full` - if false, only return transactions when max_txns or max_bytes is reached
     ///                     Should always be true for Quorum Store.
@@ -379,6 +535,16 @@ impl Mempool {
     pub(crate) fn gc(&mut self) {
         let now = aptos_infallible::duration_since_epoch();
         self.transactions.gc_by_system_ttl(now);
+
+        if let Some(journal) = &self.journal {
+            let threshold = max(
+                2 * self.transactions.num_transactions(),
+                JOURNAL_COMPACTION_MIN_RECORDS,
+            );
+            if journal.num_records() > threshold {
+                self.compact_journal();
+            }
+        }
     }
 
     /// Garbage collection based on client-specified expiration time.
@@ -0,0 +1,39 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::shared_mempool::types::RejectedTransaction;
+use aptos_types::account_address::AccountAddress;
+use std::collections::VecDeque;
+
+/// Number of most recent rejections kept for introspection.
+const REJECTION_LOG_CAPACITY: usize = 1_000;
+
+/// Bounded log of the most recent transactions that were rejected by or evicted from mempool.
+pub(crate) struct RejectionLog {
+    entries: VecDeque<RejectedTransaction>,
+}
+
+impl RejectionLog {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(REJECTION_LOG_CAPACITY),
+        }
+    }
+
+    pub(crate) fn record(&mut self, sender: AccountAddress, sequence_number: u64, reason: String) {
+        if self.entries.len() == REJECTION_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(RejectedTransaction {
+            sender,
+            sequence_number,
+            reason,
+            timestamp_usecs: aptos_infallible::duration_since_epoch().as_micros() as u64,
+        });
+    }
+
+    /// Returns the logged rejections, most recent first.
+    pub(crate) fn recent(&self) -> Vec<RejectedTransaction> {
+        self.entries.iter().rev().cloned().collect()
+    }
+}
@@ -9,13 +9,17 @@ use crate::{
             PriorityQueueIter, TTLIndex,
         },
         mempool::Mempool,
+        rejection_log::RejectionLog,
         transaction::{InsertionInfo, MempoolTransaction, TimelineState},
         TxnPointer,
     },
     counters,
     counters::{BROADCAST_BATCHED_LABEL, BROADCAST_READY_LABEL, CONSENSUS_READY_LABEL},
     logging::{LogEntry, LogEvent, LogSchema, TxnsLog},
-    shared_mempool::types::MultiBucketTimelineIndexIds,
+    shared_mempool::types::{
+        AccountMempoolInfo, MempoolTransactionInfo, MultiBucketTimelineIndexIds, ParkedAccount,
+        RejectedTransaction,
+    },
 };
 use aptos_config::config::MempoolConfig;
 use aptos_crypto::HashValue;
@@ -69,12 +73,15 @@ pub struct TransactionStore {
     size_bytes: usize,
     // keeps track of txns that were resubmitted with higher gas
     gas_upgraded_index: HashMap<TxnPointer, u64>,
+    // most recent rejections and evictions, for introspection
+    rejection_log: RejectionLog,
 
     // configuration
     capacity: usize,
     capacity_bytes: usize,
     capacity_per_user: usize,
     max_batch_bytes: u64,
+    min_replacement_gas_bump_pct: u64,
 
     // eager expiration
     eager_expire_threshold: Option<Duration>,
@@ -101,12 +108,14 @@ impl TransactionStore {
             // estimated size in bytes
             size_bytes: 0,
             gas_upgraded_index: HashMap::new(),
+            rejection_log: RejectionLog::new(),
 
             // configuration
             capacity: config.capacity,
             capacity_bytes: config.capacity_bytes,
             capacity_per_user: config.capacity_per_user,
             max_batch_bytes: config.shared_mempool_max_batch_bytes,
+            min_replacement_gas_bump_pct: config.replace_by_fee_min_gas_price_bump_pct,
 
             // eager expiration
             eager_expire_threshold: config.eager_expire_threshold_ms.map(Duration::from_millis),
@@ -200,11 +209,14 @@ impl TransactionStore {
 
         // If the transaction is already in Mempool, we only allow the user to
         // increase the gas unit price to speed up a transaction, but not the max gas.
+        // A replacement must bump the gas unit price by at least `min_replacement_gas_bump_pct`
+        // percent (replace-by-fee).
         //
         // Transactions with all the same inputs (but possibly signed differently) are idempotent
         // since the raw transaction is the same
-        if let Some(txns) = self.transactions.get_mut(&address) {
-            if let Some(current_version) = txns.get_mut(&txn_seq_num) {
+        if let Some(txns) = self.transactions.get(&address) {
+            if let Some(current_version) = txns.get(&txn_seq_num) {
+                let current_gas_price = current_version.get_gas_price();
                 if current_version.txn.payload() != txn.txn.payload() {
                     return MempoolStatus::new(MempoolStatusCode::InvalidUpdate).with_message(
                         "Transaction already in mempool with a different payload".to_string(),
@@ -221,26 +233,39 @@ impl TransactionStore {
                         "Transaction already in mempool with a different max gas amount"
                             .to_string(),
                     );
-                } else if current_version.get_gas_price() < txn.get_gas_price() {
-                    // Update txn if gas unit price is a larger value than before
-                    if let Some(txn) = txns.remove(&txn_seq_num) {
-                        self.index_remove(&txn);
-                    };
-                    gas_upgraded = true;
-                } else if current_version.get_gas_price() > txn.get_gas_price() {
+                } else if current_gas_price > txn.get_gas_price() {
                     return MempoolStatus::new(MempoolStatusCode::InvalidUpdate).with_message(
                         "Transaction already in mempool with a higher gas price".to_string(),
                     );
-                } else {
+                } else if current_gas_price == txn.get_gas_price() {
                     // If the transaction is the same, it's an idempotent call
                     // Updating signers is not supported, the previous submission must fail
                     counters::CORE_MEMPOOL_IDEMPOTENT_TXNS.inc();
                     return MempoolStatus::new(MempoolStatusCode::Accepted);
+                } else if Self::is_sufficient_gas_bump(
+                    current_gas_price,
+                    txn.get_gas_price(),
+                    self.min_replacement_gas_bump_pct,
+                ) {
+                    // The current version is only removed once the replacement is sure to be
+                    // accepted, so a rejected replacement leaves it in place.
+                    gas_upgraded = true;
+                } else {
+                    return MempoolStatus::new(MempoolStatusCode::InvalidUpdate).with_message(
+                        format!(
+                            "Transaction already in mempool with gas unit price {}. A replacement must increase it by at least {}%, got {}",
+                            current_gas_price,
+                            self.min_replacement_gas_bump_pct,
+                            txn.get_gas_price(),
+                        ),
+                    );
                 }
             }
         }
 
-        if self.check_is_full_after_eviction(&txn, acc_seq_num) {
+        // A replacement doesn't change the number of transactions, so the capacity checks don't
+        // apply to it.
+        if !gas_upgraded && self.check_is_full_after_eviction(&txn, acc_seq_num) {
             return MempoolStatus::new(MempoolStatusCode::MempoolIsFull).with_message(format!(
                 "Mempool is full. Mempool size: {}, Capacity: {}",
                 self.system_ttl_index.size(),
@@ -250,22 +275,34 @@ impl TransactionStore {
 
         self.clean_committed_transactions(&address, acc_seq_num);
 
+        // capacity check
+        let num_account_txns = self.transactions.get(&address).map_or(0, |txns| txns.len());
+        if !gas_upgraded && num_account_txns >= self.capacity_per_user {
+            return MempoolStatus::new(MempoolStatusCode::TooManyTransactions).with_message(
+                format!(
+                    "Mempool over capacity for account. Number of transactions from account: {} Capacity per account: {}",
+                    num_account_txns,
+                    self.capacity_per_user,
+                ),
+            );
+        }
+
+        if gas_upgraded {
+            if let Some(replaced) = self
+                .transactions
+                .get_mut(&address)
+                .and_then(|txns| txns.remove(&txn_seq_num))
+            {
+                self.index_remove(&replaced);
+                counters::CORE_MEMPOOL_REPLACED_TXNS.inc();
+            }
+        }
+
         self.transactions
             .entry(address)
             .or_insert_with(AccountTransactions::new);
 
         if let Some(txns) = self.transactions.get_mut(&address) {
-            // capacity check
-            if txns.len() >= self.capacity_per_user {
-                return MempoolStatus::new(MempoolStatusCode::TooManyTransactions).with_message(
-                    format!(
-                        "Mempool over capacity for account. Number of transactions from account: {} Capacity per account: {}",
-                        txns.len(),
-                        self.capacity_per_user,
-                    ),
-                );
-            }
-
             // insert into storage and other indexes
             self.system_ttl_index.insert(&txn);
             self.expiration_time_index.insert(&txn);
@@ -284,6 +321,17 @@ impl TransactionStore {
         MempoolStatus::new(MempoolStatusCode::Accepted)
     }
 
+    /// Whether `new_gas_price` exceeds `current_gas_price` by at least `min_bump_pct` percent.
+    fn is_sufficient_gas_bump(
+        current_gas_price: u64,
+        new_gas_price: u64,
+        min_bump_pct: u64,
+    ) -> bool {
+        new_gas_price > current_gas_price
+            && new_gas_price as u128 * 100
+                >= current_gas_price as u128 * (100 + min_bump_pct as u128)
+    }
+
     fn track_indices(&self) {
         counters::core_mempool_index_size(
             counters::SYSTEM_TTL_INDEX_LABEL,
@@ -335,6 +383,11 @@ impl TransactionStore {
                             txn.sequence_info.transaction_sequence_number
                         ))
                     );
+                    self.rejection_log.record(
+                        txn.get_sender(),
+                        txn.sequence_info.transaction_sequence_number,
+                        "Evicted from parking lot because mempool is full".to_string(),
+                    );
                     self.index_remove(&txn);
                 }
             }
@@ -733,6 +786,15 @@ impl TransactionStore {
                     let account = txn.get_sender();
                     let txn_sequence_number = txn.sequence_info.transaction_sequence_number;
                     gc_txns_log.add_with_status(account, txn_sequence_number, status);
+                    self.rejection_log.record(
+                        account,
+                        txn_sequence_number,
+                        if by_system_ttl {
+                            "Exceeded system TTL".to_string()
+                        } else {
+                            "Expired".to_string()
+                        },
+                    );
                     if let Ok(time_delta) =
                         SystemTime::now().duration_since(txn.insertion_info.insertion_time)
                     {
@@ -759,6 +821,77 @@ impl TransactionStore {
         self.priority_index.iter()
     }
 
+    /// Iterates over all transactions in mempool, in no particular order.
+    pub(crate) fn iter_transactions(&self) -> impl Iterator<Item = &MempoolTransaction> {
+        self.transactions
+            .values()
+            .flat_map(|account_txns| account_txns.values())
+    }
+
+    pub(crate) fn num_transactions(&self) -> usize {
+        self.system_ttl_index.size()
+    }
+
+    pub(crate) fn record_rejection(
+        &mut self,
+        sender: AccountAddress,
+        sequence_number: u64,
+        reason: String,
+    ) {
+        self.rejection_log.record(sender, sequence_number, reason);
+    }
+
+    pub(crate) fn get_rejected_transactions(&self) -> Vec<RejectedTransaction> {
+        self.rejection_log.recent()
+    }
+
+    pub(crate) fn get_account_info(&self, address: &AccountAddress) -> AccountMempoolInfo {
+        let transactions = self
+            .transactions
+            .get(address)
+            .map(|txns| {
+                let positions = self.priority_index.positions(address, txns.values());
+                txns.values()
+                    .map(|txn| {
+                        let sequence_number = txn.sequence_info.transaction_sequence_number;
+                        MempoolTransactionInfo {
+                            hash: txn.get_committed_hash(),
+                            sequence_number,
+                            gas_unit_price: txn.get_gas_price(),
+                            ranking_score: txn.ranking_score,
+                            expiration_timestamp_secs: txn.txn.expiration_timestamp_secs(),
+                            system_expiration_timestamp_secs: txn.expiration_time.as_secs(),
+                            insertion_timestamp_usecs: aptos_infallible::duration_since_epoch_at(
+                                &txn.insertion_info.insertion_time,
+                            )
+                            .as_micros()
+                                as u64,
+                            timeline_state: txn.timeline_state,
+                            parked: self.parking_lot_index.contains(address, &sequence_number),
+                            priority_queue_position: positions.get(&sequence_number).copied(),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        AccountMempoolInfo {
+            address: *address,
+            account_sequence_number: self.sequence_numbers.get(address).copied(),
+            transactions,
+        }
+    }
+
+    pub(crate) fn get_parking_lot(&self) -> Vec<ParkedAccount> {
+        self.parking_lot_index
+            .iter()
+            .map(|(address, sequence_numbers)| ParkedAccount {
+                address: *address,
+                sequence_numbers: sequence_numbers.iter().copied().collect(),
+            })
+            .collect()
+    }
+
     pub(crate) fn gen_snapshot(&self) -> TxnsLog {
         let mut txns_log = TxnsLog::new();
         for (account, txns) in self.transactions.iter() {
@@ -58,12 +58,14 @@
 
 #[cfg(any(test, feature = "fuzzing"))]
 mod tests;
+pub use core_mempool::TimelineState;
 pub use shared_mempool::{
     bootstrap, network,
     network::MempoolSyncMsg,
     types::{
-        MempoolClientRequest, MempoolClientSender, MempoolEventsReceiver, QuorumStoreRequest,
-        QuorumStoreResponse, SubmissionStatus,
+        AccountMempoolInfo, MempoolClientRequest, MempoolClientSender, MempoolEventsReceiver,
+        MempoolTransactionInfo, ParkedAccount, PeerBroadcastState, QuorumStoreRequest,
+        QuorumStoreResponse, RejectedTransaction, SubmissionStatus,
     },
 };
 #[cfg(any(test, feature = "fuzzing"))]
@@ -173,6 +173,8 @@ pub enum LogEntry {
     DBError,
     UnexpectedNetworkMsg,
     MempoolSnapshot,
+    Journal,
+    Introspection,
 }
 
 #[derive(Clone, Copy, Serialize)]
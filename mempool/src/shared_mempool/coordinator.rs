@@ -33,7 +33,7 @@ use aptos_network::{
 use aptos_types::on_chain_config::{OnChainConfigPayload, OnChainConfigProvider};
 use aptos_vm_validator::vm_validator::TransactionValidation;
 use futures::{
-    channel::mpsc,
+    channel::{mpsc, oneshot},
     stream::{select_all, FuturesUnordered},
     FutureExt, StreamExt,
 };
@@ -177,6 +177,31 @@ async fn handle_client_request<NetworkClient, TransactionValidator>(
                 ))
                 .await;
         },
+        MempoolClientRequest::GetAccountTransactions(address, callback) => {
+            let account_info = smp.mempool.lock().get_account_info(&address);
+            send_introspection_response(callback, account_info);
+        },
+        MempoolClientRequest::GetParkingLot(callback) => {
+            let parking_lot = smp.mempool.lock().get_parking_lot();
+            send_introspection_response(callback, parking_lot);
+        },
+        MempoolClientRequest::GetPeerBroadcastStates(callback) => {
+            send_introspection_response(callback, smp.network_interface.peer_broadcast_states());
+        },
+        MempoolClientRequest::GetRejectedTransactions(callback) => {
+            let rejected_txns = smp.mempool.lock().get_rejected_transactions();
+            send_introspection_response(callback, rejected_txns);
+        },
+    }
+}
+
+fn send_introspection_response<T>(callback: oneshot::Sender<T>, response: T) {
+    if callback.send(response).is_err() {
+        warn!(LogSchema::event_log(
+            LogEntry::Introspection,
+            LogEvent::CallbackFail
+        ));
+        counters::CLIENT_CALLBACK_FAIL.inc();
     }
 }
 
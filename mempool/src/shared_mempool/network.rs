@@ -10,7 +10,7 @@ use crate::{
     shared_mempool::{
         tasks,
         types::{
-            notify_subscribers, MultiBatchId, PeerSyncState, SharedMempool,
+            notify_subscribers, MultiBatchId, PeerBroadcastState, PeerSyncState, SharedMempool,
             SharedMempoolNotification,
         },
     },
@@ -508,6 +508,20 @@ impl<NetworkClient: NetworkClientInterface<MempoolSyncMsg>> MempoolNetworkInterf
     pub fn sync_states_exists(&self, peer: &PeerNetworkId) -> bool {
         self.sync_states.read().get(peer).is_some()
     }
+
+    pub fn peer_broadcast_states(&self) -> Vec<PeerBroadcastState> {
+        self.sync_states
+            .read()
+            .iter()
+            .map(|(peer, state)| PeerBroadcastState {
+                peer: *peer,
+                timeline_ids: state.timeline_id.id_per_bucket.clone(),
+                num_pending_batches: state.broadcast_info.sent_batches.len(),
+                num_retry_batches: state.broadcast_info.retry_batches.len(),
+                backoff_mode: state.broadcast_info.backoff_mode,
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug)]
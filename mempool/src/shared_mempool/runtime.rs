@@ -7,6 +7,7 @@ use crate::{
     network::MempoolSyncMsg,
     shared_mempool::{
         coordinator::{coordinator, gc_coordinator, snapshot_job},
+        tasks::replay_journaled_transactions,
         types::{MempoolEventsReceiver, SharedMempool, SharedMempoolNotification},
     },
     QuorumStoreRequest,
@@ -24,10 +25,11 @@ use aptos_storage_interface::DbReader;
 use aptos_types::on_chain_config::OnChainConfigProvider;
 use aptos_vm_validator::vm_validator::{TransactionValidation, VMValidator};
 use futures::channel::mpsc::{Receiver, UnboundedSender};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::runtime::{Handle, Runtime};
 
 /// Bootstrap of SharedMempool.
+/// Replays the mempool journal (if persistence is enabled) before starting any routine.
 /// Creates a separate Tokio Runtime that runs the following routines:
 ///   - outbound_sync_task (task that periodically broadcasts transactions to peers).
 ///   - inbound_network_task (task that handles inbound mempool messages and network events).
@@ -61,6 +63,11 @@ pub(crate) fn start_shared_mempool<TransactionValidator, ConfigProvider>(
             config.base.role,
         );
 
+    replay_journaled_transactions(
+        &smp,
+        Duration::from_millis(config.mempool.persistence_max_replay_ms),
+    );
+
     executor.spawn(coordinator(
         smp,
         executor.clone(),
@@ -368,6 +368,7 @@ fn validate_and_add_transactions<NetworkClient, TransactionValidator>(
                         statuses.push((transaction, (mempool_status, None)));
                     },
                     Some(validation_status) => {
+                        mempool.record_validation_failure(&transaction, validation_status);
                         statuses.push((
                             transaction.clone(),
                             (
@@ -619,3 +620,90 @@ pub(crate) async fn process_config_update<V, P>(
         },
     }
 }
+
+// ==================== //
+// journal replay tasks //
+// ==================== //
+
+/// Re-validates the transactions recovered from the mempool journal and re-inserts the ones that
+/// are still valid, keeping their original ranking score and expiration time.
+/// Stops after `max_replay_time`; whatever was not replayed by then is dropped from the journal.
+pub(crate) fn replay_journaled_transactions<NetworkClient, TransactionValidator>(
+    smp: &SharedMempool<NetworkClient, TransactionValidator>,
+    max_replay_time: Duration,
+) where
+    NetworkClient: NetworkClientInterface<MempoolSyncMsg>,
+    TransactionValidator: TransactionValidation,
+{
+    let entries = smp.mempool.lock().take_journaled_txns();
+    if entries.is_empty() {
+        return;
+    }
+
+    let state_view = match smp.db.latest_state_checkpoint_view() {
+        Ok(state_view) => state_view,
+        Err(e) => {
+            error!(
+                LogSchema::new(LogEntry::Journal).error(&e),
+                "Failed to get state view, skipping mempool journal replay."
+            );
+            return;
+        },
+    };
+
+    let num_journaled = entries.len();
+    let mut num_replayed = 0;
+    let deadline = Instant::now() + max_replay_time;
+    let now = aptos_infallible::duration_since_epoch();
+    for entry in entries {
+        if Instant::now() >= deadline {
+            warn!(
+                LogSchema::new(LogEntry::Journal),
+                "Mempool journal replay timed out after {:?}.", max_replay_time,
+            );
+            break;
+        }
+        if entry.expiration_time <= now
+            || Duration::from_secs(entry.txn.expiration_timestamp_secs()) <= now
+        {
+            continue;
+        }
+
+        let sequence_number = match get_account_sequence_number(&state_view, entry.txn.sender()) {
+            Ok(sequence_number) => sequence_number,
+            Err(e) => {
+                error!(LogSchema::new(LogEntry::DBError).error(&e));
+                counters::DB_ERROR.inc();
+                continue;
+            },
+        };
+        if entry.txn.sequence_number() < sequence_number {
+            continue;
+        }
+
+        let is_valid = smp
+            .validator
+            .read()
+            .validate_transaction(entry.txn.clone())
+            .map_or(false, |result| result.status().is_none());
+        if is_valid
+            && smp
+                .mempool
+                .lock()
+                .add_journaled_txn(entry, sequence_number)
+                .code
+                == MempoolStatusCode::Accepted
+        {
+            num_replayed += 1;
+        }
+    }
+
+    // Drop records of transactions that were not replayed.
+    smp.mempool.lock().compact_journal();
+    info!(
+        LogSchema::new(LogEntry::Journal).num_txns(num_replayed),
+        num_journaled = num_journaled,
+        "Replayed mempool journal."
+    );
+    notify_subscribers(SharedMempoolNotification::NewTransactions, &smp.subscribers);
+}
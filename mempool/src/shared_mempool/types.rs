@@ -4,7 +4,7 @@
 
 //! Objects used by/related to shared mempool
 use crate::{
-    core_mempool::CoreMempool,
+    core_mempool::{CoreMempool, TimelineState},
     network::{MempoolNetworkInterface, MempoolSyncMsg},
 };
 use anyhow::Result;
@@ -20,7 +20,8 @@ use aptos_network::{
 };
 use aptos_storage_interface::DbReader;
 use aptos_types::{
-    mempool_status::MempoolStatus, transaction::SignedTransaction, vm_status::DiscardedVMStatus,
+    account_address::AccountAddress, mempool_status::MempoolStatus, transaction::SignedTransaction,
+    vm_status::DiscardedVMStatus,
 };
 use aptos_vm_validator::vm_validator::TransactionValidation;
 use futures::{
@@ -230,6 +231,64 @@ pub type SubmissionStatusBundle = (SignedTransaction, SubmissionStatus);
 pub enum MempoolClientRequest {
     SubmitTransaction(SignedTransaction, oneshot::Sender<Result<SubmissionStatus>>),
     GetTransactionByHash(HashValue, oneshot::Sender<Option<SignedTransaction>>),
+    // Introspection requests, used by operators to debug stuck transactions.
+    GetAccountTransactions(AccountAddress, oneshot::Sender<AccountMempoolInfo>),
+    GetParkingLot(oneshot::Sender<Vec<ParkedAccount>>),
+    GetPeerBroadcastStates(oneshot::Sender<Vec<PeerBroadcastState>>),
+    GetRejectedTransactions(oneshot::Sender<Vec<RejectedTransaction>>),
+}
+
+/// Transactions of a single account currently in mempool.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccountMempoolInfo {
+    pub address: AccountAddress,
+    /// Account sequence number last seen by mempool, if the account has transactions in mempool.
+    pub account_sequence_number: Option<u64>,
+    pub transactions: Vec<MempoolTransactionInfo>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MempoolTransactionInfo {
+    pub hash: HashValue,
+    pub sequence_number: u64,
+    pub gas_unit_price: u64,
+    pub ranking_score: u64,
+    pub expiration_timestamp_secs: u64,
+    pub system_expiration_timestamp_secs: u64,
+    pub insertion_timestamp_usecs: u64,
+    pub timeline_state: TimelineState,
+    /// Whether the transaction is in the parking lot, i.e. waiting for a sequence number gap to be filled.
+    pub parked: bool,
+    /// Position in the priority queue consensus pulls from, 0 being pulled first. Only set if the
+    /// transaction is ready.
+    pub priority_queue_position: Option<usize>,
+}
+
+/// Parked (non-ready) transactions of a single account.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ParkedAccount {
+    pub address: AccountAddress,
+    pub sequence_numbers: Vec<u64>,
+}
+
+/// Broadcast progress towards a single peer.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PeerBroadcastState {
+    pub peer: PeerNetworkId,
+    /// Last broadcast position in the timeline of each bucket.
+    pub timeline_ids: Vec<u64>,
+    pub num_pending_batches: usize,
+    pub num_retry_batches: usize,
+    pub backoff_mode: bool,
+}
+
+/// A transaction that was rejected by or removed from mempool, along with the reason.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RejectedTransaction {
+    pub sender: AccountAddress,
+    pub sequence_number: u64,
+    pub reason: String,
+    pub timestamp_usecs: u64,
 }
 
 pub type MempoolClientSender = mpsc::Sender<MempoolClientRequest>;
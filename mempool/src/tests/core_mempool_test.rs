@@ -3,7 +3,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    core_mempool::{CoreMempool, MempoolTransaction, SubmittedBy, TimelineState},
+    core_mempool::{
+        CoreMempool, MempoolTransaction, SubmittedBy, TimelineState, MEMPOOL_JOURNAL_FILE,
+    },
     tests::common::{
         add_signed_txn, add_txn, add_txns_to_mempool, setup_mempool,
         setup_mempool_with_broadcast_buckets, TestTransaction,
@@ -12,11 +14,16 @@ use crate::{
 use aptos_config::config::NodeConfig;
 use aptos_consensus_types::common::{TransactionInProgress, TransactionSummary};
 use aptos_crypto::HashValue;
+use aptos_temppath::TempPath;
 use aptos_types::{
     mempool_status::MempoolStatusCode, transaction::SignedTransaction, vm_status::DiscardedVMStatus,
 };
 use itertools::Itertools;
-use std::time::{Duration, SystemTime};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    time::{Duration, SystemTime},
+};
 
 #[test]
 fn test_transaction_ordering_only_seqnos() {
@@ -172,6 +179,74 @@ fn test_update_invalid_transaction_in_mempool() {
     assert_eq!(next_tnx[0].gas_unit_price(), 1);
 }
 
+#[test]
+fn test_replace_by_fee_min_gas_price_bump() {
+    let (mut mempool, _) = setup_mempool();
+    let txns = add_txns_to_mempool(&mut mempool, vec![TestTransaction::new(0, 0, 100)]);
+
+    // Less than the default 10% bump is rejected.
+    assert!(add_txn(&mut mempool, TestTransaction::new(0, 0, 109)).is_err());
+    let (timeline, _) = mempool.read_timeline(&vec![0].into(), 10);
+    assert_eq!(timeline, txns);
+
+    // The replacement takes the place of the original in the timeline, so it is broadcast again.
+    let replacement = TestTransaction::new(0, 0, 110).make_signed_transaction();
+    assert!(add_signed_txn(&mut mempool, replacement.clone()).is_ok());
+    let (timeline, _) = mempool.read_timeline(&vec![0].into(), 10);
+    assert_eq!(timeline, vec![replacement.clone()]);
+    assert_eq!(
+        mempool.get_by_hash(replacement.clone().committed_hash()),
+        Some(replacement)
+    );
+    assert_eq!(mempool.get_by_hash(txns[0].clone().committed_hash()), None);
+
+    // A lower gas price never replaces.
+    assert!(add_txn(&mut mempool, TestTransaction::new(0, 0, 50)).is_err());
+}
+
+#[test]
+fn test_replace_by_fee_keeps_max_gas_amount() {
+    let (mut mempool, _) = setup_mempool();
+    let txns = add_txns_to_mempool(&mut mempool, vec![TestTransaction::new(0, 0, 100)]);
+
+    // A sufficient bump doesn't allow changing anything but the gas unit price.
+    let updated_txn = TestTransaction::make_signed_transaction_with_max_gas_amount(
+        &TestTransaction::new(0, 0, 200),
+        200,
+    );
+    assert!(add_signed_txn(&mut mempool, updated_txn.clone()).is_err());
+    let updated_txn = TestTransaction::make_signed_transaction_with_expiration_time(
+        &TestTransaction::new(0, 0, 200),
+        u64::MAX - 1,
+    );
+    assert!(add_signed_txn(&mut mempool, updated_txn).is_err());
+
+    let (timeline, _) = mempool.read_timeline(&vec![0].into(), 10);
+    assert_eq!(timeline, txns);
+}
+
+#[test]
+fn test_replace_by_fee_when_full() {
+    let mut config = NodeConfig::generate_random_config();
+    config.mempool.capacity = 2;
+    let mut pool = CoreMempool::new(&config);
+    // A ready and a parked transaction fill up mempool.
+    add_txn(&mut pool, TestTransaction::new(0, 0, 100)).unwrap();
+    add_txn(&mut pool, TestTransaction::new(1, 1, 100)).unwrap();
+
+    // A replacement doesn't need room, so it is neither rejected nor evicts the parked one.
+    let replacement = TestTransaction::new(0, 0, 110).make_signed_transaction();
+    assert!(add_signed_txn(&mut pool, replacement.clone()).is_ok());
+    assert_eq!(
+        pool.get_by_hash(replacement.clone().committed_hash()),
+        Some(replacement)
+    );
+    assert_eq!(pool.get_parking_lot().len(), 1);
+
+    // A new transaction still finds mempool full.
+    assert!(add_txn(&mut pool, TestTransaction::new(2, 1, 100)).is_err());
+}
+
 #[test]
 fn test_commit_transaction() {
     let (mut pool, mut consensus) = setup_mempool();
@@ -276,6 +351,67 @@ fn test_reject_transaction() {
         .is_none());
 }
 
+#[test]
+fn test_journal_replay() {
+    let data_dir = TempPath::new();
+    data_dir.create_as_dir().unwrap();
+    let mut config = NodeConfig::generate_random_config();
+    config.base.data_dir = data_dir.path().to_path_buf();
+    config.mempool.persist_transactions = true;
+
+    let transactions = {
+        let mut mempool = CoreMempool::new(&config);
+        assert!(mempool.take_journaled_txns().is_empty());
+        add_txns_to_mempool(&mut mempool, vec![
+            TestTransaction::new(0, 0, 1),
+            TestTransaction::new(1, 0, 5),
+        ])
+    };
+
+    // Simulate a crash in the middle of an append.
+    OpenOptions::new()
+        .append(true)
+        .open(data_dir.path().join(MEMPOOL_JOURNAL_FILE))
+        .unwrap()
+        .write_all(&[100, 0, 0, 0, 1, 2])
+        .unwrap();
+
+    let mut mempool = CoreMempool::new(&config);
+    let entries = mempool.take_journaled_txns();
+    assert_eq!(
+        entries.iter().map(|e| e.txn.clone()).collect::<Vec<_>>(),
+        transactions
+    );
+    assert_eq!(
+        entries.iter().map(|e| e.ranking_score).collect::<Vec<_>>(),
+        vec![1, 5]
+    );
+    let expiration_times: Vec<_> = entries.iter().map(|e| e.expiration_time).collect();
+    for entry in entries {
+        assert_eq!(
+            mempool.add_journaled_txn(entry, 0).code,
+            MempoolStatusCode::Accepted
+        );
+    }
+    mempool.compact_journal();
+
+    // Replayed transactions keep their original system expiration time.
+    let block = mempool.get_batch(2, 1024, true, false, vec![]);
+    assert_eq!(block, vec![
+        transactions[1].clone(),
+        transactions[0].clone()
+    ]);
+    let replayed = CoreMempool::new(&config).take_journaled_txns();
+    assert_eq!(
+        replayed
+            .iter()
+            .map(|e| e.expiration_time)
+            .sorted()
+            .collect::<Vec<_>>(),
+        expiration_times.into_iter().sorted().collect::<Vec<_>>()
+    );
+}
+
 #[test]
 fn test_system_ttl() {
     // Created mempool with system_transaction_timeout = 0.
@@ -623,6 +759,48 @@ fn test_parking_lot_eviction() {
     assert!(add_txn(&mut pool, TestTransaction::new(0, 2, 1)).is_err());
 }
 
+#[test]
+fn test_introspection() {
+    let mut config = NodeConfig::generate_random_config();
+    config.mempool.capacity = 3;
+    let mut pool = CoreMempool::new(&config);
+    for seq in &[0, 1, 5] {
+        add_txn(&mut pool, TestTransaction::new(1, *seq, 1)).unwrap();
+    }
+
+    let address = TestTransaction::get_address(1);
+    let info = pool.get_account_info(&address);
+    assert_eq!(info.account_sequence_number, Some(0));
+    let summary: Vec<_> = info
+        .transactions
+        .iter()
+        .map(|t| (t.sequence_number, t.parked, t.priority_queue_position))
+        .collect();
+    assert_eq!(summary, vec![
+        (0, false, Some(0)),
+        (1, false, Some(1)),
+        (5, true, None)
+    ]);
+    let parking_lot = pool.get_parking_lot();
+    assert_eq!(parking_lot.len(), 1);
+    assert_eq!(parking_lot[0].address, address);
+    assert_eq!(parking_lot[0].sequence_numbers, vec![5]);
+
+    // Mempool is full, so a ready transaction evicts the parked one.
+    add_txn(&mut pool, TestTransaction::new(0, 0, 1)).unwrap();
+    // Too old to be accepted.
+    let txn = TestTransaction::new(0, 0, 1).make_signed_transaction();
+    let status = pool.add_txn(txn, 1, 1, TimelineState::NotReady, false);
+    assert_eq!(status.code, MempoolStatusCode::InvalidSeqNumber);
+
+    let rejected = pool.get_rejected_transactions();
+    assert_eq!(rejected.len(), 2);
+    assert_eq!(rejected[0].sender, TestTransaction::get_address(0));
+    assert_eq!(rejected[1].sender, address);
+    assert_eq!(rejected[1].sequence_number, 5);
+    assert!(pool.get_parking_lot().is_empty());
+}
+
 #[test]
 fn test_parking_lot_evict_only_for_ready_txn_insertion() {
     let mut config = NodeConfig::generate_random_config();
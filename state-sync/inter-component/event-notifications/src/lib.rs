@@ -7,6 +7,7 @@ use anyhow::Result;
 use aptos_channels::{aptos_channel, message_queues::QueueStyle};
 use aptos_id_generator::{IdGenerator, U64IdGenerator};
 use aptos_infallible::RwLock;
+use aptos_logger::warn;
 use aptos_state_view::account_with_state_view::AsAccountWithStateView;
 use aptos_storage_interface::{state_view::DbStateViewAtVersion, DbReader, DbReaderWriter};
 use aptos_types::{
@@ -40,6 +41,7 @@ mod tests;
 // will be retrieved using FIFO ordering.
 const EVENT_NOTIFICATION_CHANNEL_SIZE: usize = 100;
 const RECONFIG_NOTIFICATION_CHANNEL_SIZE: usize = 1;
+const COMMIT_NOTIFICATION_CHANNEL_SIZE: usize = 1;
 
 #[derive(Clone, Debug, Deserialize, Error, PartialEq, Eq, Serialize)]
 pub enum Error {
@@ -82,6 +84,9 @@ pub struct EventSubscriptionService {
     // Reconfig subscription registry
     reconfig_subscriptions: HashMap<SubscriptionId, ReconfigSubscription>,
 
+    // Commit subscription registry
+    commit_subscriptions: HashMap<SubscriptionId, CommitSubscription>,
+
     // Database to fetch on-chain configuration data
     storage: Arc<RwLock<DbReaderWriter>>,
 
@@ -95,6 +100,7 @@ impl EventSubscriptionService {
             event_key_subscriptions: HashMap::new(),
             subscription_id_to_event_subscription: HashMap::new(),
             reconfig_subscriptions: HashMap::new(),
+            commit_subscriptions: HashMap::new(),
             storage,
             subscription_id_generator: U64IdGenerator::new(),
         }
@@ -186,6 +192,39 @@ impl EventSubscriptionService {
         })
     }
 
+    /// Returns a CommitNotificationListener that can be monitored for newly
+    /// committed data. Subscribers will be sent a notification containing the
+    /// latest committed version every time state sync commits new transactions.
+    /// Note: only the latest notification is kept, so subscribers that fall
+    /// behind only observe the most recent version (and should read everything
+    /// up to it from storage).
+    pub fn subscribe_to_commits(&mut self) -> Result<CommitNotificationListener, Error> {
+        let (notification_sender, notification_receiver) =
+            aptos_channel::new(QueueStyle::KLAST, COMMIT_NOTIFICATION_CHANNEL_SIZE, None);
+
+        // Create a new commit subscription
+        let subscription_id = self.get_new_subscription_id();
+        let commit_subscription = CommitSubscription {
+            notification_sender,
+        };
+
+        // Store the new subscription
+        if self
+            .commit_subscriptions
+            .insert(subscription_id, commit_subscription)
+            .is_some()
+        {
+            return Err(Error::UnexpectedErrorEncountered(format!(
+                "Duplicate commit subscription found! This should not occur! ID: {}",
+                subscription_id,
+            )));
+        }
+
+        Ok(CommitNotificationListener {
+            notification_receiver,
+        })
+    }
+
     fn get_new_subscription_id(&mut self) -> u64 {
         self.subscription_id_generator.next()
     }
@@ -246,6 +285,19 @@ impl EventSubscriptionService {
         Ok(reconfig_event_found)
     }
 
+    /// This notifies all the commit subscribers of the latest committed version.
+    /// Commit notifications are best effort, so failures are only logged.
+    fn notify_commit_subscribers(&mut self, version: Version) {
+        for (subscription_id, commit_subscription) in self.commit_subscriptions.iter_mut() {
+            if let Err(error) = commit_subscription.notify_subscriber_of_commit(version) {
+                warn!(
+                    "Failed to notify commit subscriber {} of version {}: {:?}",
+                    subscription_id, version, error
+                );
+            }
+        }
+    }
+
     /// This notifies all the reconfiguration subscribers of the on-chain
     /// configurations at the specified version.
     fn notify_reconfiguration_subscribers(&mut self, version: Version) -> Result<(), Error> {
@@ -306,20 +358,20 @@ impl EventSubscriptionService {
 
 impl EventNotificationSender for EventSubscriptionService {
     fn notify_events(&mut self, version: Version, events: Vec<ContractEvent>) -> Result<(), Error> {
-        if events.is_empty() {
-            return Ok(()); // No events!
+        if !events.is_empty() {
+            // Notify event subscribers and check if a reconfiguration event was processed
+            let reconfig_event_processed = self.notify_event_subscribers(version, events)?;
+
+            // If a reconfiguration event was found, also notify the reconfig subscribers
+            // of the new configuration values.
+            if reconfig_event_processed {
+                self.notify_reconfiguration_subscribers(version)?;
+            }
         }
 
-        // Notify event subscribers and check if a reconfiguration event was processed
-        let reconfig_event_processed = self.notify_event_subscribers(version, events)?;
-
-        // If a reconfiguration event was found, also notify the reconfig subscribers
-        // of the new configuration values.
-        if reconfig_event_processed {
-            self.notify_reconfiguration_subscribers(version)
-        } else {
-            Ok(())
-        }
+        // Notify commit subscribers of the new version (regardless of any events)
+        self.notify_commit_subscribers(version);
+        Ok(())
     }
 
     fn notify_initial_configs(&mut self, version: Version) -> Result<(), Error> {
@@ -379,6 +431,20 @@ impl ReconfigSubscription {
     }
 }
 
+/// A single commit subscription, holding the channel to send the
+/// corresponding notifications.
+struct CommitSubscription {
+    pub notification_sender: aptos_channels::aptos_channel::Sender<(), CommitNotification>,
+}
+
+impl CommitSubscription {
+    fn notify_subscriber_of_commit(&mut self, version: Version) -> Result<(), Error> {
+        self.notification_sender
+            .push((), CommitNotification { version })
+            .map_err(|error| Error::UnexpectedErrorEncountered(format!("{:?}", error)))
+    }
+}
+
 #[derive(Clone)]
 pub struct DbBackedOnChainConfig {
     pub reader: Arc<dyn DbReader>,
@@ -421,12 +487,21 @@ pub struct ReconfigNotification<P: OnChainConfigProvider> {
     pub on_chain_configs: OnChainConfigPayload<P>,
 }
 
+/// A notification for newly committed data.
+#[derive(Debug)]
+pub struct CommitNotification {
+    pub version: Version,
+}
+
 /// A subscription listener for on-chain events.
 pub type EventNotificationListener = NotificationListener<EventNotification>;
 
 /// A subscription listener for reconfigurations.
 pub type ReconfigNotificationListener<P> = NotificationListener<ReconfigNotification<P>>;
 
+/// A subscription listener for commits.
+pub type CommitNotificationListener = NotificationListener<CommitNotification>;
+
 /// The component responsible for listening to subscription notifications.
 #[derive(Debug)]
 pub struct NotificationListener<T> {
@@ -375,6 +375,31 @@ fn test_no_events_no_subscribers() {
     notify_events(&mut event_service, 1, vec![]);
 }
 
+#[test]
+fn test_commit_subscribers() {
+    // Create subscription service and mock database
+    let mut event_service = create_event_subscription_service();
+
+    // Create a commit subscriber
+    let mut commit_listener = event_service.subscribe_to_commits().unwrap();
+
+    // Notify the service of several commits, with and without events
+    notify_events(&mut event_service, 5, vec![]);
+    notify_events(&mut event_service, 10, vec![create_test_event(
+        create_random_event_key(),
+    )]);
+
+    // Verify that only the latest commit notification was kept
+    let commit_notification = commit_listener.select_next_some().now_or_never().unwrap();
+    assert_eq!(commit_notification.version, 10);
+    assert!(commit_listener.select_next_some().now_or_never().is_none());
+
+    // Verify that new commits are delivered
+    notify_events(&mut event_service, 11, vec![]);
+    let commit_notification = commit_listener.select_next_some().now_or_never().unwrap();
+    assert_eq!(commit_notification.version, 11);
+}
+
 /// Defines a new on-chain config for test purposes.
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct TestOnChainConfig {
@@ -6,13 +6,14 @@ use crate::{
     event_store::EventStore,
     ledger_store::LedgerStore,
     metrics::{
-        BACKUP_EPOCH_ENDING_EPOCH, BACKUP_STATE_SNAPSHOT_LEAF_IDX, BACKUP_STATE_SNAPSHOT_VERSION,
-        BACKUP_TXN_VERSION,
+        BACKUP_EPOCH_ENDING_EPOCH, BACKUP_STATE_DELTA_LEAF_IDX, BACKUP_STATE_DELTA_VERSION,
+        BACKUP_STATE_SNAPSHOT_LEAF_IDX, BACKUP_STATE_SNAPSHOT_VERSION, BACKUP_TXN_VERSION,
     },
+    pruner::PrunerManager,
     state_store::StateStore,
     transaction_store::TransactionStore,
 };
-use anyhow::{anyhow, ensure, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use aptos_crypto::hash::HashValue;
 use aptos_types::{
     contract_event::ContractEvent,
@@ -133,6 +134,56 @@ impl BackupHandler {
         Ok(Box::new(iterator))
     }
 
+    /// Gets an iterator which yields the state keys whose values changed from `base_version` to
+    /// `version`, each with its value at `version`, or `None` if it was deleted. Only the parts of
+    /// the two trees that differ are traversed.
+    pub fn get_state_delta_iter(
+        &self,
+        base_version: Version,
+        version: Version,
+    ) -> Result<Box<dyn Iterator<Item = Result<(StateKey, Option<StateValue>)>> + Send + Sync>>
+    {
+        ensure!(
+            base_version < version,
+            "Base version {} is not before version {}.",
+            base_version,
+            version,
+        );
+        self.ensure_state_tree_readable(base_version)?;
+        self.ensure_state_tree_readable(version)?;
+
+        let iterator = self
+            .state_store
+            .get_state_delta_iter(base_version, version)?
+            .enumerate()
+            .map(move |(idx, res)| {
+                BACKUP_STATE_DELTA_VERSION.set(version as i64);
+                BACKUP_STATE_DELTA_LEAF_IDX.set(idx as i64);
+                res
+            });
+        Ok(Box::new(iterator))
+    }
+
+    /// The stale node indexes drive pruning of the state tree, a tree at a version is intact
+    /// as long as the pruners haven't progressed beyond it.
+    fn ensure_state_tree_readable(&self, version: Version) -> Result<()> {
+        let state_db = &self.state_store.state_db;
+        if version >= state_db.state_merkle_pruner.get_min_readable_version() {
+            return Ok(());
+        }
+        let min_readable_epoch_snapshot_version =
+            state_db.epoch_snapshot_pruner.get_min_readable_version();
+        if version >= min_readable_epoch_snapshot_version {
+            self.ledger_store.ensure_epoch_ending(version)
+        } else {
+            bail!(
+                "State tree at version {} is pruned. Epoch snapshots are available at >= {}.",
+                version,
+                min_readable_epoch_snapshot_version,
+            )
+        }
+    }
+
     /// Gets the proof that proves a range of accounts.
     pub fn get_account_state_range_proof(
         &self,
@@ -7,7 +7,7 @@ use crate::{
     db_metadata::{DbMetadataKey, DbMetadataSchema},
     event_store::EventStore,
     ledger_store::LedgerStore,
-    state_restore::{StateSnapshotRestore, StateSnapshotRestoreMode},
+    state_restore::{StateSnapshotRangeRestore, StateSnapshotRestore, StateSnapshotRestoreMode},
     state_store::StateStore,
     transaction_store::TransactionStore,
     AptosDB,
@@ -68,6 +68,22 @@ impl RestoreHandler {
         )
     }
 
+    /// Like `get_state_restore_receiver()`, but restoring disjoint key ranges in parallel.
+    pub fn get_state_range_restore(
+        &self,
+        version: Version,
+        expected_root_hash: HashValue,
+        restore_mode: StateSnapshotRestoreMode,
+    ) -> Result<StateSnapshotRangeRestore<StateKey, StateValue>> {
+        StateSnapshotRangeRestore::new(
+            &self.state_store.state_merkle_db,
+            &self.state_store,
+            version,
+            expected_root_hash,
+            restore_mode,
+        )
+    }
+
     pub fn reset_state_store(&self) {
         self.state_store.reset();
     }
@@ -159,8 +175,12 @@ impl RestoreHandler {
             .iter::<DbMetadataSchema>(Default::default())?;
         iter.seek_to_first();
         while let Some((k, _v)) = iter.next().transpose()? {
-            if let DbMetadataKey::StateSnapshotRestoreProgress(version) = k {
-                return Ok(Some(version));
+            match k {
+                DbMetadataKey::StateSnapshotRestoreProgress(version)
+                | DbMetadataKey::StateSnapshotRangeRestoreProgress(version, _) => {
+                    return Ok(Some(version))
+                },
+                _ => (),
             }
         }
         Ok(None)
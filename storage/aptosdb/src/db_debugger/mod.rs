@@ -5,6 +5,7 @@ pub mod checkpoint;
 mod common;
 mod examine;
 pub mod ledger;
+pub mod shell;
 pub mod state_tree;
 pub mod truncate;
 
@@ -31,6 +32,8 @@ pub enum Cmd {
 
     #[clap(subcommand)]
     Examine(examine::Cmd),
+
+    Shell(shell::Cmd),
 }
 
 impl Cmd {
@@ -41,6 +44,7 @@ impl Cmd {
             Cmd::Ledger(cmd) => cmd.run(),
             Cmd::Truncate(cmd) => cmd.run(),
             Cmd::Examine(cmd) => cmd.run(),
+            Cmd::Shell(cmd) => cmd.run(),
         }
     }
 }
@@ -0,0 +1,402 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    db_debugger::{
+        common::{parse_nibble_path, DbDir, PAGE_SIZE},
+        state_tree::get_path::print_path,
+    },
+    db_options::{
+        event_db_column_families, ledger_db_column_families, ledger_metadata_db_column_families,
+        state_kv_db_column_families, state_merkle_db_column_families,
+        transaction_accumulator_db_column_families, transaction_db_column_families,
+        transaction_info_db_column_families, write_set_db_column_families,
+    },
+    ledger_db::LedgerDb,
+    schema::{
+        db_metadata::DbMetadataSchema, epoch_by_version::EpochByVersionSchema, event::EventSchema,
+        event_accumulator::EventAccumulatorSchema, event_by_key::EventByKeySchema,
+        event_by_version::EventByVersionSchema, jellyfish_merkle_node::JellyfishMerkleNodeSchema,
+        ledger_info::LedgerInfoSchema, stale_node_index::StaleNodeIndexSchema,
+        stale_node_index_cross_epoch::StaleNodeIndexCrossEpochSchema,
+        stale_state_value_index::StaleStateValueIndexSchema, state_value::StateValueSchema,
+        state_value_index::StateValueIndexSchema, transaction::TransactionSchema,
+        transaction_accumulator::TransactionAccumulatorSchema,
+        transaction_by_account::TransactionByAccountSchema,
+        transaction_by_hash::TransactionByHashSchema, transaction_info::TransactionInfoSchema,
+        version_data::VersionDataSchema, write_set::WriteSetSchema,
+    },
+    state_kv_db::StateKvDb,
+    state_merkle_db::StateMerkleDb,
+    AptosDB, NUM_STATE_SHARDS,
+};
+use anyhow::{bail, Result};
+use aptos_config::config::RocksdbConfigs;
+use aptos_crypto::HashValue;
+use aptos_jellyfish_merkle::JellyfishMerkleTree;
+use aptos_schemadb::{
+    schema::{KeyCodec, Schema, SeekKeyCodec},
+    ColumnFamilyName, ReadOptions, DB, DEFAULT_COLUMN_FAMILY_NAME,
+};
+use aptos_types::{
+    nibble::nibble_path::NibblePath, state_store::state_key::StateKey, transaction::Version,
+};
+use clap::Parser;
+use owo_colors::OwoColorize;
+use std::io::{self, BufRead, Write};
+
+#[derive(Parser)]
+#[clap(about = "Open the DB read-only and start an interactive shell to inspect it.")]
+pub struct Cmd {
+    #[clap(flatten)]
+    db_dir: DbDir,
+}
+
+impl Cmd {
+    pub fn run(self) -> Result<()> {
+        let (ledger_db, state_merkle_db, state_kv_db) = AptosDB::open_dbs(
+            self.db_dir.clone(),
+            RocksdbConfigs {
+                enable_storage_sharding: self.db_dir.sharding_config.enable_storage_sharding,
+                ..Default::default()
+            },
+            /*readonly=*/ true,
+            /*max_num_nodes_per_lru_cache_shard=*/ 0,
+        )?;
+        let shell = Shell {
+            sharding: self.db_dir.sharding_config.enable_storage_sharding,
+            ledger_db,
+            state_merkle_db,
+            state_kv_db,
+        };
+
+        println!("DB opened read-only. Type `help` for commands.");
+        let mut lines = io::stdin().lock().lines();
+        loop {
+            print!("{}", "aptosdb> ".green());
+            io::stdout().flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+            let args: Vec<_> = line.split_whitespace().collect();
+            if args.is_empty() {
+                continue;
+            }
+            match Line::try_parse_from(args) {
+                Ok(Line::Exit) => break,
+                Ok(line) => {
+                    if let Err(e) = shell.execute(line) {
+                        println!("{}", format!("Error: {:?}", e).red());
+                    }
+                },
+                Err(e) => e.print()?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Calls `$m!` with all schemas in the DB.
+macro_rules! for_each_schema {
+    ($m:ident) => {
+        $m!(
+            DbMetadataSchema,
+            EpochByVersionSchema,
+            EventSchema,
+            EventAccumulatorSchema,
+            EventByKeySchema,
+            EventByVersionSchema,
+            JellyfishMerkleNodeSchema,
+            LedgerInfoSchema,
+            StaleNodeIndexSchema,
+            StaleNodeIndexCrossEpochSchema,
+            StaleStateValueIndexSchema,
+            StateValueSchema,
+            StateValueIndexSchema,
+            TransactionSchema,
+            TransactionAccumulatorSchema,
+            TransactionByAccountSchema,
+            TransactionByHashSchema,
+            TransactionInfoSchema,
+            VersionDataSchema,
+            WriteSetSchema,
+        )
+    };
+}
+
+/// A line of input to the shell.
+#[derive(Debug, Parser)]
+#[clap(no_binary_name = true)]
+enum Line {
+    #[clap(about = "List the column families of each DB, with their estimated number of keys.")]
+    Cfs,
+    #[clap(about = "Print the decoded entries of a schema, in key order.")]
+    Scan {
+        #[clap(help = "Schema to scan, by its column family name, e.g. `transaction`.")]
+        schema: String,
+        #[clap(
+            long,
+            value_parser = parse_key_prefix,
+            default_value = "",
+            help = "Only print entries whose encoded key starts with this, in hex."
+        )]
+        prefix: KeyPrefix,
+        #[clap(long, default_value_t = PAGE_SIZE)]
+        limit: usize,
+    },
+    #[clap(about = "Print the state tree nodes leading to a nibble path.")]
+    Path {
+        #[clap(long)]
+        before_version: Version,
+        #[clap(long, value_parser = parse_nibble_path)]
+        nibble_path: NibblePath,
+    },
+    #[clap(about = "Print and verify the state tree proof of a key at a snapshot version.")]
+    Proof {
+        #[clap(long)]
+        version: Version,
+        #[clap(long, help = "Hash of the state key, in hex.")]
+        key_hash: HashValue,
+    },
+    #[clap(alias = "quit", about = "Leave the shell.")]
+    Exit,
+}
+
+/// Encoded key prefix, used to seek a schema regardless of its key type.
+#[derive(Clone, Debug)]
+struct KeyPrefix(Vec<u8>);
+
+fn parse_key_prefix(src: &str) -> Result<KeyPrefix> {
+    Ok(KeyPrefix(hex::decode(src.trim_start_matches("0x"))?))
+}
+
+struct Shell {
+    sharding: bool,
+    ledger_db: LedgerDb,
+    state_merkle_db: StateMerkleDb,
+    state_kv_db: StateKvDb,
+}
+
+impl Shell {
+    fn execute(&self, line: Line) -> Result<()> {
+        match line {
+            Line::Cfs => self.print_cfs(),
+            Line::Scan {
+                schema,
+                prefix,
+                limit,
+            } => self.scan(&schema, &prefix, limit),
+            Line::Path {
+                before_version,
+                nibble_path,
+            } => print_path(&self.state_merkle_db, before_version, &nibble_path),
+            Line::Proof { version, key_hash } => self.print_proof(version, key_hash),
+            Line::Exit => Ok(()),
+        }
+    }
+
+    /// All underlying RocksDB instances, with the column families in each.
+    fn physical_dbs(&self) -> Vec<(String, &DB, Vec<ColumnFamilyName>)> {
+        if !self.sharding {
+            return vec![
+                (
+                    "ledger_db".to_string(),
+                    self.ledger_db.metadata_db(),
+                    ledger_db_column_families(),
+                ),
+                (
+                    "state_merkle_db".to_string(),
+                    self.state_merkle_db.metadata_db(),
+                    state_merkle_db_column_families(),
+                ),
+            ];
+        }
+
+        let mut dbs = vec![
+            (
+                "ledger_metadata_db".to_string(),
+                self.ledger_db.metadata_db(),
+                ledger_metadata_db_column_families(),
+            ),
+            (
+                "event_db".to_string(),
+                self.ledger_db.event_db(),
+                event_db_column_families(),
+            ),
+            (
+                "transaction_accumulator_db".to_string(),
+                self.ledger_db.transaction_accumulator_db(),
+                transaction_accumulator_db_column_families(),
+            ),
+            (
+                "transaction_db".to_string(),
+                self.ledger_db.transaction_db(),
+                transaction_db_column_families(),
+            ),
+            (
+                "transaction_info_db".to_string(),
+                self.ledger_db.transaction_info_db(),
+                transaction_info_db_column_families(),
+            ),
+            (
+                "write_set_db".to_string(),
+                self.ledger_db.write_set_db(),
+                write_set_db_column_families(),
+            ),
+            (
+                "state_merkle_metadata_db".to_string(),
+                self.state_merkle_db.metadata_db(),
+                state_merkle_db_column_families(),
+            ),
+            (
+                "state_kv_metadata_db".to_string(),
+                self.state_kv_db.metadata_db(),
+                state_kv_db_column_families(),
+            ),
+        ];
+        for shard_id in 0..NUM_STATE_SHARDS as u8 {
+            dbs.push((
+                format!("state_merkle_db_shard_{}", shard_id),
+                self.state_merkle_db.db_shard(shard_id),
+                state_merkle_db_column_families(),
+            ));
+            dbs.push((
+                format!("state_kv_db_shard_{}", shard_id),
+                self.state_kv_db.db_shard(shard_id),
+                state_kv_db_column_families(),
+            ));
+        }
+        dbs
+    }
+
+    fn print_cfs(&self) -> Result<()> {
+        for (name, db, cfs) in self.physical_dbs() {
+            println!("{}", name.yellow());
+            for cf in cfs {
+                if cf == DEFAULT_COLUMN_FAMILY_NAME {
+                    continue;
+                }
+                println!(
+                    "    {:32} ~{} keys",
+                    cf,
+                    db.get_property(cf, "rocksdb.estimate-num-keys")?
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn scan(&self, schema: &str, prefix: &KeyPrefix, limit: usize) -> Result<()> {
+        macro_rules! scan_if_matches {
+            ($($schema:ty),* $(,)?) => {
+                $(
+                    if schema == <$schema>::COLUMN_FAMILY_NAME {
+                        return self.scan_schema::<$schema>(prefix, limit);
+                    }
+                )*
+            };
+        }
+        for_each_schema!(scan_if_matches);
+        bail!(
+            "Unknown schema {}, see `cfs` for the column families.",
+            schema
+        )
+    }
+
+    /// Prints up to `limit` entries with the key prefix from each DB holding the schema.
+    fn scan_schema<S: Schema>(&self, prefix: &KeyPrefix, limit: usize) -> Result<()>
+    where
+        KeyPrefix: SeekKeyCodec<S>,
+    {
+        for (name, db, cfs) in self.physical_dbs() {
+            if !cfs.contains(&S::COLUMN_FAMILY_NAME) {
+                continue;
+            }
+            println!(
+                "{}",
+                format!("* {} in {}", S::COLUMN_FAMILY_NAME, name).yellow()
+            );
+
+            let mut iter = db.iter::<S>(ReadOptions::default())?;
+            iter.seek(prefix)?;
+            let mut num_printed = 0;
+            for res in iter {
+                let (key, value) = res?;
+                if !key.encode_key()?.starts_with(&prefix.0) {
+                    break;
+                }
+                if num_printed == limit {
+                    println!("...");
+                    break;
+                }
+                println!("{} {:#?}", "key:".green(), key);
+                println!("{} {:#?}", "value:".green(), value);
+                num_printed += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_proof(&self, version: Version, key_hash: HashValue) -> Result<()> {
+        let root_hash = self.state_merkle_db.get_root_hash(version)?;
+        let (leaf, proof) = JellyfishMerkleTree::<_, StateKey>::new(&self.state_merkle_db)
+            .get_with_proof_ext(key_hash, version)?;
+        println!("root hash: {}", root_hash);
+        match &leaf {
+            Some((value_hash, (state_key, value_version))) => println!(
+                "leaf: {:?}, value hash: {}, value version: {}",
+                state_key, value_hash, value_version
+            ),
+            None => println!("leaf: None (proof of non-existence)"),
+        }
+        println!("{:#?}", proof);
+
+        match proof.verify_by_hash(root_hash, key_hash, leaf.map(|(value_hash, _)| value_hash)) {
+            Ok(()) => println!("{}", "Proof verified.".green()),
+            Err(e) => println!("{}", format!("!!! Proof doesn't verify: {}", e).red()),
+        }
+        Ok(())
+    }
+}
+
+macro_rules! impl_seek_by_prefix {
+    ($($schema:ty),* $(,)?) => {
+        $(
+            impl SeekKeyCodec<$schema> for KeyPrefix {
+                fn encode_seek_key(&self) -> Result<Vec<u8>> {
+                    Ok(self.0.clone())
+                }
+            }
+        )*
+    };
+}
+
+for_each_schema!(impl_seek_by_prefix);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_line_parsing() {
+        Line::command().debug_assert();
+
+        match Line::try_parse_from(["scan", "transaction", "--prefix", "0x00ff"]).unwrap() {
+            Line::Scan {
+                schema,
+                prefix,
+                limit,
+            } => {
+                assert_eq!(schema, "transaction");
+                assert_eq!(prefix.0, vec![0, 255]);
+                assert_eq!(limit, PAGE_SIZE);
+            },
+            line => panic!("Unexpected {:?}", line),
+        }
+        assert!(matches!(Line::try_parse_from(["quit"]), Ok(Line::Exit)));
+        assert!(Line::try_parse_from(["scan"]).is_err());
+    }
+}
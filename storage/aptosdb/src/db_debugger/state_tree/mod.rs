@@ -1,7 +1,7 @@
 // Copyright © Aptos Foundation
 // SPDX-License-Identifier: Apache-2.0
 
-mod get_path;
+pub(crate) mod get_path;
 mod get_snapshots;
 
 use anyhow::Result;
@@ -81,7 +81,7 @@ use aptos_db_indexer::Indexer;
 use aptos_experimental_runtimes::thread_manager::THREAD_MANAGER;
 use aptos_infallible::Mutex;
 use aptos_logger::prelude::*;
-use aptos_schemadb::{SchemaBatch, DB};
+use aptos_schemadb::{ReadOptions, SchemaBatch, DB};
 use aptos_storage_interface::{
     cached_state_view::ShardedStateCache, state_delta::StateDelta, state_view::DbStateView,
     DbReader, DbWriter, ExecutedTrees, Order, StateSnapshotReceiver, MAX_REQUEST_LIMIT,
@@ -617,6 +617,22 @@ impl AptosDB {
         )
     }
 
+    /// This opens db in non-readonly mode, without the pruner, and with an indexer that only
+    /// indexes account transactions from `account_transactions_start_version` on
+    #[cfg(any(test, feature = "fuzzing"))]
+    pub fn new_for_test_with_indexer_from<P: AsRef<Path> + Clone>(
+        db_root_path: P,
+        account_transactions_start_version: Version,
+    ) -> Self {
+        Indexer::set_account_transactions_start_version(
+            db_root_path.clone(),
+            RocksdbConfigs::default().index_db_config,
+            account_transactions_start_version,
+        )
+        .expect("Unable to set up the indexer");
+        Self::new_for_test_with_indexer(db_root_path)
+    }
+
     /// This opens db in non-readonly mode, without the pruner.
     #[cfg(any(test, feature = "fuzzing"))]
     pub fn new_for_test_with_buffered_state_target_items<P: AsRef<Path> + Clone>(
@@ -761,6 +777,42 @@ impl AptosDB {
         )
     }
 
+    // ================================== Export APIs ===================================
+
+    /// Gets the entries of the state value schema written in `[first_version, last_version]`,
+    /// i.e. the value (or deletion) of a key as of each version it was written at, with one
+    /// iterator per shard of the state KV DB. Entries are ordered by key rather than by version,
+    /// so each iterator scans its whole shard, once.
+    pub fn get_state_value_history_iters(
+        &self,
+        first_version: Version,
+        last_version: Version,
+    ) -> Result<Vec<impl Iterator<Item = Result<(StateKey, Version, Option<StateValue>)>> + '_>>
+    {
+        let state_kv_db = &self.state_store.state_kv_db;
+        // Without sharding, all the shards are the ledger DB.
+        let dbs: Vec<&DB> = if state_kv_db.enabled_sharding() {
+            (0..state_kv_db.num_shards())
+                .map(|shard_id| state_kv_db.db_shard(shard_id))
+                .collect()
+        } else {
+            vec![state_kv_db.metadata_db()]
+        };
+        dbs.into_iter()
+            .map(|db| {
+                let mut iter = db
+                    .iter::<crate::schema::state_value::StateValueSchema>(ReadOptions::default())?;
+                iter.seek_to_first();
+                Ok(iter.filter_map(move |entry| match entry {
+                    Ok(((state_key, version), value)) => (first_version..=last_version)
+                        .contains(&version)
+                        .then_some(Ok((state_key, version, value))),
+                    Err(err) => Some(Err(err)),
+                }))
+            })
+            .collect::<Result<Vec<_>>>()
+    }
+
     /// Creates new physical DB checkpoint in directory specified by `path`.
     pub fn create_checkpoint(
         db_path: impl AsRef<Path>,
@@ -1581,6 +1633,15 @@ impl DbReader for AptosDB {
         })
     }
 
+    /// Get the first version at which state values can be read.
+    fn get_first_state_value_version(&self) -> Result<Option<Version>> {
+        gauged_api("get_first_state_value_version", || {
+            Ok(Some(
+                self.state_store.state_kv_pruner.get_min_readable_version(),
+            ))
+        })
+    }
+
     /// Returns a batch of transactions for the purpose of synchronizing state to another node.
     ///
     /// If any version beyond ledger_version is requested, it is ignored.
@@ -2062,6 +2123,37 @@ impl DbReader for AptosDB {
         self.indexer.is_some()
     }
 
+    fn get_account_transaction_versions(
+        &self,
+        address: AccountAddress,
+        start_version: Version,
+        limit: u64,
+        ledger_version: Version,
+    ) -> Result<Vec<Version>> {
+        gauged_api("get_account_transaction_versions", || {
+            error_if_too_many_requested(limit, MAX_REQUEST_LIMIT)?;
+
+            match &self.indexer {
+                Some(indexer) => indexer.get_account_transaction_versions(
+                    address,
+                    start_version,
+                    ledger_version,
+                    limit,
+                ),
+                None => bail!("Indexer not enabled."),
+            }
+        })
+    }
+
+    fn get_account_transactions_start_version(&self) -> Result<Version> {
+        gauged_api("get_account_transactions_start_version", || {
+            match &self.indexer {
+                Some(indexer) => Ok(indexer.account_transactions_start_version()),
+                None => bail!("Indexer not enabled."),
+            }
+        })
+    }
+
     fn get_state_storage_usage(&self, version: Option<Version>) -> Result<StateStorageUsage> {
         gauged_api("get_state_storage_usage", || {
             if let Some(v) = version {
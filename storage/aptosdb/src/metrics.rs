@@ -205,3 +205,19 @@ pub(crate) static BACKUP_STATE_SNAPSHOT_LEAF_IDX: Lazy<IntGauge> = Lazy::new(||
     )
     .unwrap()
 });
+
+pub(crate) static BACKUP_STATE_DELTA_VERSION: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_backup_handler_state_delta_version",
+        "Target version of requested state delta backup."
+    )
+    .unwrap()
+});
+
+pub(crate) static BACKUP_STATE_DELTA_LEAF_IDX: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_backup_handler_state_delta_leaf_index",
+        "Index of current changed leaf returned in a state delta backup."
+    )
+    .unwrap()
+});
@@ -27,7 +27,7 @@ use aptos_schemadb::{SchemaBatch, DB};
 #[cfg(test)]
 use aptos_scratchpad::get_state_shard_id;
 use aptos_types::{
-    nibble::{nibble_path::NibblePath, ROOT_NIBBLE_HEIGHT},
+    nibble::{nibble_path::NibblePath, Nibble, ROOT_NIBBLE_HEIGHT},
     proof::{SparseMerkleProofExt, SparseMerkleRangeProof},
     state_store::state_key::StateKey,
     transaction::Version,
@@ -835,6 +835,48 @@ impl TreeReader<StateKey> for StateMerkleDb {
 
         Ok(ret)
     }
+
+    fn get_rightmost_leaf_in_subtree(
+        &self,
+        version: Version,
+        nibble: Nibble,
+    ) -> Result<Option<(NodeKey, LeafNode)>> {
+        // Same as `get_rightmost_leaf_in_single_shard()`, except that the nodes under the other
+        // children of the root are in the same DB if sharding is not enabled, so for each number
+        // of nibbles we seek to the end of the nibble paths starting with `nibble` instead.
+        let shard_id = if self.enable_sharding {
+            u8::from(nibble)
+        } else {
+            0
+        };
+        let shard_db = self.db_shard(shard_id);
+        let mut ret: Option<(NodeKey, LeafNode)> = None;
+
+        for num_nibbles in 1..=ROOT_NIBBLE_HEIGHT {
+            let seek_path: NibblePath = std::iter::once(nibble)
+                .chain(std::iter::repeat(Nibble::from(15)).take(num_nibbles - 1))
+                .collect();
+            let mut iter = shard_db.iter::<JellyfishMerkleNodeSchema>(Default::default())?;
+            iter.seek_for_prev(&NodeKey::new(version, seek_path))?;
+
+            if let Some((node_key, node)) = iter.next().transpose()? {
+                if node_key.version() != version
+                    || node_key.nibble_path().num_nibbles() != num_nibbles
+                    || node_key.nibble_path().get_nibble(0) != nibble
+                {
+                    continue;
+                }
+                if let Node::Leaf(leaf_node) = node {
+                    if ret.as_ref().map_or(true, |(_, other)| {
+                        leaf_node.account_key() > other.account_key()
+                    }) {
+                        ret = Some((node_key, leaf_node));
+                    }
+                }
+            }
+        }
+        Ok(ret)
+    }
 }
 
 impl TreeWriter<StateKey> for StateMerkleDb {
@@ -2,19 +2,27 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::OTHER_TIMERS_SECONDS;
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use aptos_crypto::{hash::CryptoHash, HashValue};
 use aptos_infallible::Mutex;
-use aptos_jellyfish_merkle::{restore::JellyfishMerkleRestore, Key, TreeReader, TreeWriter, Value};
+use aptos_jellyfish_merkle::{
+    restore::{finish_subtrees, JellyfishMerkleRestore},
+    Key, TreeReader, TreeWriter, Value,
+};
 use aptos_storage_interface::StateSnapshotReceiver;
 use aptos_types::{
-    proof::SparseMerkleRangeProof, state_store::state_storage_usage::StateStorageUsage,
-    transaction::Version,
+    nibble::Nibble, proof::SparseMerkleRangeProof,
+    state_store::state_storage_usage::StateStorageUsage, transaction::Version,
 };
 use once_cell::sync::Lazy;
-use rayon::{ThreadPool, ThreadPoolBuilder};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, hash::Hash, str::FromStr, sync::Arc};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    str::FromStr,
+    sync::{mpsc, Arc},
+};
 
 #[cfg(test)]
 mod restore_test;
@@ -44,17 +52,23 @@ impl StateSnapshotProgress {
 }
 
 pub trait StateValueWriter<K, V>: Send + Sync {
-    /// Writes a kv batch into storage.
+    /// Writes a kv batch into storage, along with the progress of the restore, or of one of its
+    /// key ranges if `range` is set, see `StateSnapshotRangeRestore`.
     fn write_kv_batch(
         &self,
         version: Version,
         kv_batch: &StateValueBatch<K, Option<V>>,
         progress: StateSnapshotProgress,
+        range: Option<Nibble>,
     ) -> Result<()>;
 
     fn write_usage(&self, version: Version, usage: StateStorageUsage) -> Result<()>;
 
-    fn get_progress(&self, version: Version) -> Result<Option<StateSnapshotProgress>>;
+    fn get_progress(
+        &self,
+        version: Version,
+        range: Option<Nibble>,
+    ) -> Result<Option<StateSnapshotProgress>>;
 }
 
 #[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
@@ -92,16 +106,22 @@ impl FromStr for StateSnapshotRestoreMode {
 struct StateValueRestore<K, V> {
     version: Version,
     db: Arc<dyn StateValueWriter<K, V>>,
+    /// The key range restored, if not all the keys.
+    range: Option<Nibble>,
 }
 
 impl<K: Key + CryptoHash + Eq + Hash, V: Value> StateValueRestore<K, V> {
-    pub fn new<D: 'static + StateValueWriter<K, V>>(db: Arc<D>, version: Version) -> Self {
-        Self { version, db }
+    pub fn new<D: 'static + StateValueWriter<K, V>>(
+        db: Arc<D>,
+        version: Version,
+        range: Option<Nibble>,
+    ) -> Self {
+        Self { version, db, range }
     }
 
     pub fn add_chunk(&mut self, mut chunk: Vec<(K, V)>) -> Result<()> {
         // load progress
-        let progress_opt = self.db.get_progress(self.version)?;
+        let progress_opt = self.db.get_progress(self.version, self.range)?;
 
         // skip overlaps
         if let Some(progress) = progress_opt {
@@ -137,32 +157,76 @@ impl<K: Key + CryptoHash + Eq + Hash, V: Value> StateValueRestore<K, V> {
             self.version,
             &kv_batch,
             StateSnapshotProgress::new(last_key_hash, usage),
+            self.range,
         )
     }
 
     pub fn finish(self) -> Result<()> {
-        let progress = self.db.get_progress(self.version)?;
-        self.db.write_usage(
-            self.version,
-            progress.map_or(StateStorageUsage::zero(), |p| p.usage),
-        )
+        self.db.write_usage(self.version, self.usage()?)
+    }
+
+    /// The usage of the keys restored so far.
+    pub fn usage(&self) -> Result<StateStorageUsage> {
+        Ok(self
+            .db
+            .get_progress(self.version, self.range)?
+            .map_or(StateStorageUsage::zero(), |p| p.usage))
     }
 
     pub fn previous_key_hash(&self) -> Result<Option<HashValue>> {
         Ok(self
             .db
-            .get_progress(self.version)?
+            .get_progress(self.version, self.range)?
             .map(|progress| progress.key_hash))
     }
 }
 
+/// The key to resume a restore from, given those of the KV restore and of the tree restore. If
+/// both are restored, nothing is skipped unless both have made progress: the tree restore only
+/// persists a leaf once the next one is added, so it can lag behind the KV restore by one key.
+fn min_previous_key_hash(
+    restore_mode: StateSnapshotRestoreMode,
+    kv_hash_opt: Option<HashValue>,
+    tree_hash_opt: Option<HashValue>,
+) -> Option<HashValue> {
+    match restore_mode {
+        StateSnapshotRestoreMode::KvOnly => kv_hash_opt,
+        StateSnapshotRestoreMode::TreeOnly => tree_hash_opt,
+        StateSnapshotRestoreMode::Default => match (kv_hash_opt, tree_hash_opt) {
+            (Some(hash1), Some(hash2)) => Some(std::cmp::min(hash1, hash2)),
+            _ => None,
+        },
+    }
+}
+
+/// The result of applying a chunk in the background.
+type PendingWrite = mpsc::Receiver<Result<()>>;
+
+/// Restores the state KVs and the state tree at a version from chunks of leaves, in key hash
+/// order.
+///
+/// Progress is durable at chunk granularity: the KV restore records the last key written along
+/// with each batch, and the tree restore resumes from the rightmost leaf persisted. A restore
+/// that is killed and recreated picks up from `previous_key_hash()`, the smaller of the two, and
+/// chunks already applied are skipped.
+///
+/// Chunks are applied strictly in key order, since the tree is built left to right by freezing
+/// the frontier of the previous chunk. The parallelism is limited to overlapping KV writes, tree
+/// building and fetching the next chunk when pipelined, see `StateSnapshotRangeRestore` to
+/// restore disjoint key ranges in parallel.
 pub struct StateSnapshotRestore<K, V> {
     tree_restore: Arc<Mutex<Option<JellyfishMerkleRestore<K>>>>,
     kv_restore: Arc<Mutex<Option<StateValueRestore<K, V>>>>,
     restore_mode: StateSnapshotRestoreMode,
+    /// If set, chunks are applied in the background, see `add_chunk_impl()`.
+    pipelined: bool,
+    kv_pending: Option<PendingWrite>,
+    tree_pending: Option<PendingWrite>,
 }
 
-impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotRestore<K, V> {
+impl<K: Key + CryptoHash + Hash + Eq, V: Value + 'static> StateSnapshotRestore<K, V> {
+    /// With `async_commit`, chunks are also applied in the background, the error of applying a
+    /// chunk, if any, is returned by the next call to add a chunk or `finish()`.
     pub fn new<T: 'static + TreeReader<K> + TreeWriter<K>, S: 'static + StateValueWriter<K, V>>(
         tree_store: &Arc<T>,
         value_store: &Arc<S>,
@@ -181,8 +245,12 @@ impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotRestore<K, V> {
             kv_restore: Arc::new(Mutex::new(Some(StateValueRestore::new(
                 Arc::clone(value_store),
                 version,
+                None, /* range */
             )))),
             restore_mode,
+            pipelined: async_commit,
+            kv_pending: None,
+            tree_pending: None,
         })
     }
 
@@ -202,13 +270,18 @@ impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotRestore<K, V> {
             kv_restore: Arc::new(Mutex::new(Some(StateValueRestore::new(
                 Arc::clone(value_store),
                 version,
+                None, /* range */
             )))),
             restore_mode,
+            pipelined: false,
+            kv_pending: None,
+            tree_pending: None,
         })
     }
 
     pub fn previous_key_hash(&self) -> Result<Option<HashValue>> {
-        let hash_opt = match (
+        Ok(min_previous_key_hash(
+            self.restore_mode,
             self.kv_restore
                 .lock()
                 .as_ref()
@@ -219,48 +292,81 @@ impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotRestore<K, V> {
                 .as_ref()
                 .unwrap()
                 .previous_key_hash(),
-        ) {
-            (None, hash_opt) => hash_opt,
-            (hash_opt, None) => hash_opt,
-            (Some(hash1), Some(hash2)) => Some(std::cmp::min(hash1, hash2)),
-        };
-        Ok(hash_opt)
+        ))
     }
 
-    pub fn wait_for_async_commit(&self) -> Result<()> {
+    pub fn wait_for_async_commit(&mut self) -> Result<()> {
+        self.wait_for_pending_chunks()?;
         self.tree_restore
             .lock()
             .as_mut()
             .unwrap()
             .wait_for_async_commit()
     }
-}
 
-impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotReceiver<K, V>
-    for StateSnapshotRestore<K, V>
-{
-    fn add_chunk(&mut self, chunk: Vec<(K, V)>, proof: SparseMerkleRangeProof) -> Result<()> {
-        let kv_fn = || {
+    /// Adds a chunk that comes without a range proof, the tree is only checked against the
+    /// expected root hash in `finish()`. See `JellyfishMerkleRestore::add_chunk_without_proof`.
+    pub fn add_chunk_without_proof(&mut self, chunk: Vec<(K, V)>) -> Result<()> {
+        self.add_chunk_impl(chunk, None)
+    }
+
+    /// Each of the KV restore and the tree restore applies the chunks one by one in order. If
+    /// pipelined, both run in the background, so that the KV restore of a chunk overlaps with the
+    /// tree restore of the previous one, and both overlap with the caller fetching the next chunk.
+    /// Otherwise, the KV restore and the tree restore of the same chunk run in parallel and this
+    /// returns when both finish.
+    fn add_chunk_impl(
+        &mut self,
+        chunk: Vec<(K, V)>,
+        proof: Option<SparseMerkleRangeProof>,
+    ) -> Result<()> {
+        let kv_restore = Arc::clone(&self.kv_restore);
+        let kv_chunk = match self.restore_mode {
+            StateSnapshotRestoreMode::TreeOnly => Vec::new(),
+            StateSnapshotRestoreMode::KvOnly | StateSnapshotRestoreMode::Default => chunk.clone(),
+        };
+        let kv_fn = move || {
             let _timer = OTHER_TIMERS_SECONDS
                 .with_label_values(&["state_value_add_chunk"])
                 .start_timer();
-            self.kv_restore
-                .lock()
-                .as_mut()
-                .unwrap()
-                .add_chunk(chunk.clone())
+            kv_restore.lock().as_mut().unwrap().add_chunk(kv_chunk)
         };
 
-        let tree_fn = || {
+        let tree_restore = Arc::clone(&self.tree_restore);
+        let tree_fn = move || {
             let _timer = OTHER_TIMERS_SECONDS
                 .with_label_values(&["jmt_add_chunk"])
                 .start_timer();
-            self.tree_restore
-                .lock()
-                .as_mut()
-                .unwrap()
-                .add_chunk_impl(chunk.iter().map(|(k, v)| (k, v.hash())).collect(), proof)
+            let value_hashes: Vec<_> = chunk.par_iter().map(|(_k, v)| v.hash()).collect();
+            let leaves = chunk.iter().map(|(k, _v)| k).zip(value_hashes).collect();
+            let mut tree_restore = tree_restore.lock();
+            let tree_restore = tree_restore.as_mut().unwrap();
+            match proof {
+                Some(proof) => tree_restore.add_chunk_impl(leaves, proof),
+                None => tree_restore.add_chunk_without_proof(leaves),
+            }
         };
+
+        if self.pipelined {
+            match self.restore_mode {
+                StateSnapshotRestoreMode::KvOnly => {
+                    Self::wait_for(&mut self.kv_pending)?;
+                    self.kv_pending = Some(Self::spawn(kv_fn));
+                },
+                StateSnapshotRestoreMode::TreeOnly => {
+                    Self::wait_for(&mut self.tree_pending)?;
+                    self.tree_pending = Some(Self::spawn(tree_fn));
+                },
+                StateSnapshotRestoreMode::Default => {
+                    Self::wait_for(&mut self.kv_pending)?;
+                    self.kv_pending = Some(Self::spawn(kv_fn));
+                    Self::wait_for(&mut self.tree_pending)?;
+                    self.tree_pending = Some(Self::spawn(tree_fn));
+                },
+            }
+            return Ok(());
+        }
+
         // Write KV out first because we are likely to resume according to the rightmost key in the
         // tree after crashing.
         match self.restore_mode {
@@ -277,7 +383,51 @@ impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotReceiver<K, V>
         Ok(())
     }
 
-    fn finish(self) -> Result<()> {
+    fn spawn(f: impl FnOnce() -> Result<()> + Send + 'static) -> PendingWrite {
+        let (tx, rx) = mpsc::channel();
+        IO_POOL.spawn(move || {
+            // The receiver is gone only if the restore is dropped without waiting.
+            let _ = tx.send(f());
+        });
+        rx
+    }
+
+    fn wait_for(pending: &mut Option<PendingWrite>) -> Result<()> {
+        match pending.take() {
+            Some(rx) => rx.recv()?,
+            None => Ok(()),
+        }
+    }
+
+    fn wait_for_pending_chunks(&mut self) -> Result<()> {
+        let kv_res = Self::wait_for(&mut self.kv_pending);
+        let tree_res = Self::wait_for(&mut self.tree_pending);
+        kv_res.and(tree_res)
+    }
+}
+
+impl<K, V> Drop for StateSnapshotRestore<K, V> {
+    fn drop(&mut self) {
+        // Make sure nothing is written after the restore is dropped, e.g. when it's interrupted,
+        // so that a new restore can resume from the progress in the DB.
+        for pending in [self.kv_pending.take(), self.tree_pending.take()]
+            .into_iter()
+            .flatten()
+        {
+            let _ = pending.recv();
+        }
+    }
+}
+
+impl<K: Key + CryptoHash + Hash + Eq, V: Value + 'static> StateSnapshotReceiver<K, V>
+    for StateSnapshotRestore<K, V>
+{
+    fn add_chunk(&mut self, chunk: Vec<(K, V)>, proof: SparseMerkleRangeProof) -> Result<()> {
+        self.add_chunk_impl(chunk, Some(proof))
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.wait_for_pending_chunks()?;
         match self.restore_mode {
             StateSnapshotRestoreMode::KvOnly => self.kv_restore.lock().take().unwrap().finish()?,
             StateSnapshotRestoreMode::TreeOnly => {
@@ -292,7 +442,8 @@ impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotReceiver<K, V>
         Ok(())
     }
 
-    fn finish_box(self: Box<Self>) -> Result<()> {
+    fn finish_box(mut self: Box<Self>) -> Result<()> {
+        self.wait_for_pending_chunks()?;
         match self.restore_mode {
             StateSnapshotRestoreMode::KvOnly => self.kv_restore.lock().take().unwrap().finish()?,
             StateSnapshotRestoreMode::TreeOnly => {
@@ -307,3 +458,157 @@ impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotReceiver<K, V>
         Ok(())
     }
 }
+
+/// Restores the state KVs and the state tree at a version like `StateSnapshotRestore`, split in
+/// 16 key ranges that are restored independently, so that chunks of different ranges can be
+/// applied in parallel. The range of a key is the first nibble of its hash, so each range
+/// restores one of the subtrees under the root, which is only written by `finish()`.
+///
+/// Each range persists its progress the same way a `StateSnapshotRestore` does, a restore that
+/// is killed and recreated resumes each range from its own `previous_key_hash()`. Chunks are
+/// added without proofs, the tree is checked against the expected root hash in `finish()`,
+/// before its root is written, see `JellyfishMerkleRestore::new_subtree()`.
+pub struct StateSnapshotRangeRestore<K, V> {
+    ranges: Vec<Mutex<RangeRestore<K, V>>>,
+    tree_store: Arc<dyn TreeWriter<K>>,
+    value_store: Arc<dyn StateValueWriter<K, V>>,
+    version: Version,
+    expected_root_hash: HashValue,
+    restore_mode: StateSnapshotRestoreMode,
+}
+
+struct RangeRestore<K, V> {
+    tree_restore: JellyfishMerkleRestore<K>,
+    kv_restore: StateValueRestore<K, V>,
+}
+
+impl<K: Key + CryptoHash + Hash + Eq, V: Value> StateSnapshotRangeRestore<K, V> {
+    pub const NUM_RANGES: usize = 16;
+
+    pub fn new<T: 'static + TreeReader<K> + TreeWriter<K>, S: 'static + StateValueWriter<K, V>>(
+        tree_store: &Arc<T>,
+        value_store: &Arc<S>,
+        version: Version,
+        expected_root_hash: HashValue,
+        restore_mode: StateSnapshotRestoreMode,
+    ) -> Result<Self> {
+        let ranges = (0..Self::NUM_RANGES)
+            .map(|range| {
+                let nibble = Nibble::from(range as u8);
+                Ok(Mutex::new(RangeRestore {
+                    tree_restore: JellyfishMerkleRestore::new_subtree(
+                        Arc::clone(tree_store),
+                        version,
+                        nibble,
+                        expected_root_hash,
+                        true, /* async_commit */
+                    )?,
+                    kv_restore: StateValueRestore::new(
+                        Arc::clone(value_store),
+                        version,
+                        Some(nibble),
+                    ),
+                }))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            ranges,
+            tree_store: Arc::clone(tree_store) as Arc<dyn TreeWriter<K>>,
+            value_store: Arc::clone(value_store) as Arc<dyn StateValueWriter<K, V>>,
+            version,
+            expected_root_hash,
+            restore_mode,
+        })
+    }
+
+    /// The range a key belongs to, given its hash.
+    pub fn range_of(key_hash: &HashValue) -> usize {
+        key_hash.nibble(0) as usize
+    }
+
+    pub fn previous_key_hash(&self, range: usize) -> Result<Option<HashValue>> {
+        let range_restore = self.ranges[range].lock();
+        Ok(min_previous_key_hash(
+            self.restore_mode,
+            range_restore.kv_restore.previous_key_hash()?,
+            range_restore.tree_restore.previous_key_hash(),
+        ))
+    }
+
+    /// Adds a chunk of the keys in `range`, in key hash order. Chunks of the same range have to be
+    /// added in order, but chunks of different ranges can be added concurrently.
+    pub fn add_chunk(&self, range: usize, chunk: Vec<(K, V)>) -> Result<()> {
+        ensure!(
+            chunk
+                .iter()
+                .all(|(k, _v)| Self::range_of(&CryptoHash::hash(k)) == range),
+            "Chunk has keys out of range {}.",
+            range,
+        );
+        let mut range_restore = self.ranges[range].lock();
+        let RangeRestore {
+            tree_restore,
+            kv_restore,
+        } = &mut *range_restore;
+
+        let kv_chunk = match self.restore_mode {
+            StateSnapshotRestoreMode::TreeOnly => Vec::new(),
+            StateSnapshotRestoreMode::KvOnly | StateSnapshotRestoreMode::Default => chunk.clone(),
+        };
+        let kv_fn = move || {
+            let _timer = OTHER_TIMERS_SECONDS
+                .with_label_values(&["state_value_add_chunk"])
+                .start_timer();
+            kv_restore.add_chunk(kv_chunk)
+        };
+        let mut tree_fn = move || {
+            let _timer = OTHER_TIMERS_SECONDS
+                .with_label_values(&["jmt_add_chunk"])
+                .start_timer();
+            let value_hashes: Vec<_> = chunk.par_iter().map(|(_k, v)| v.hash()).collect();
+            let leaves = chunk.iter().map(|(k, _v)| k).zip(value_hashes).collect();
+            tree_restore.add_chunk_without_proof(leaves)
+        };
+
+        match self.restore_mode {
+            StateSnapshotRestoreMode::KvOnly => kv_fn(),
+            StateSnapshotRestoreMode::TreeOnly => tree_fn(),
+            StateSnapshotRestoreMode::Default => {
+                let (r1, r2) = IO_POOL.join(kv_fn, tree_fn);
+                r1.and(r2)
+            },
+        }
+    }
+
+    /// Finishes all the ranges, then writes the usage and the root of the tree.
+    pub fn finish(self) -> Result<()> {
+        let mut usage = StateStorageUsage::zero();
+        let mut subtrees = Vec::with_capacity(Self::NUM_RANGES);
+        for range_restore in self.ranges {
+            let RangeRestore {
+                tree_restore,
+                kv_restore,
+            } = range_restore.into_inner();
+            let range_usage = kv_restore.usage()?;
+            usage = StateStorageUsage::new(
+                usage.items() + range_usage.items(),
+                usage.bytes() + range_usage.bytes(),
+            );
+            subtrees.push(tree_restore.finish_subtree()?);
+        }
+
+        if self.restore_mode != StateSnapshotRestoreMode::TreeOnly {
+            self.value_store.write_usage(self.version, usage)?;
+        }
+        if self.restore_mode != StateSnapshotRestoreMode::KvOnly {
+            finish_subtrees(
+                self.tree_store.as_ref(),
+                self.version,
+                self.expected_root_hash,
+                subtrees,
+            )?;
+        }
+        Ok(())
+    }
+}
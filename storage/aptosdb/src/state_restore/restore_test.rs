@@ -2,8 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::state_restore::{
-    StateSnapshotProgress, StateSnapshotRestore, StateSnapshotRestoreMode, StateValueBatch,
-    StateValueWriter,
+    StateSnapshotProgress, StateSnapshotRangeRestore, StateSnapshotRestore,
+    StateSnapshotRestoreMode, StateValueBatch, StateValueWriter,
 };
 use anyhow::Result;
 use aptos_crypto::{hash::CryptoHash, HashValue};
@@ -15,7 +15,9 @@ use aptos_jellyfish_merkle::{
     JellyfishMerkleTree, NodeBatch, TestKey, TestValue, TreeReader, TreeWriter,
 };
 use aptos_storage_interface::StateSnapshotReceiver;
-use aptos_types::{state_store::state_storage_usage::StateStorageUsage, transaction::Version};
+use aptos_types::{
+    nibble::Nibble, state_store::state_storage_usage::StateStorageUsage, transaction::Version,
+};
 use proptest::{collection::btree_map, prelude::*};
 use std::{
     collections::{BTreeMap, HashMap},
@@ -27,7 +29,7 @@ struct MockSnapshotStore<K: TestKey, V: TestValue> {
     tree_store: MockTreeStore<K>,
     kv_store: RwLock<BTreeMap<(K, Version), V>>,
     usage_store: RwLock<HashMap<Version, StateStorageUsage>>,
-    progress_store: RwLock<HashMap<Version, StateSnapshotProgress>>,
+    progress_store: RwLock<HashMap<(Version, Option<Nibble>), StateSnapshotProgress>>,
 }
 
 impl<K, V> MockSnapshotStore<K, V>
@@ -77,6 +79,7 @@ where
         version: Version,
         kv_batch: &StateValueBatch<K, Option<V>>,
         progress: StateSnapshotProgress,
+        range: Option<Nibble>,
     ) -> Result<()> {
         for (k, v) in kv_batch {
             if let Some(v) = v {
@@ -85,7 +88,9 @@ where
                 self.kv_store.write().remove(k);
             }
         }
-        self.progress_store.write().insert(version, progress);
+        self.progress_store
+            .write()
+            .insert((version, range), progress);
         Ok(())
     }
 
@@ -94,8 +99,12 @@ where
         Ok(())
     }
 
-    fn get_progress(&self, version: Version) -> Result<Option<StateSnapshotProgress>> {
-        Ok(self.progress_store.read().get(&version).cloned())
+    fn get_progress(
+        &self,
+        version: Version,
+        range: Option<Nibble>,
+    ) -> Result<Option<StateSnapshotProgress>> {
+        Ok(self.progress_store.read().get(&(version, range)).cloned())
     }
 }
 
@@ -111,6 +120,15 @@ where
     fn get_rightmost_leaf(&self, version: Version) -> Result<Option<(NodeKey, LeafNode<K>)>> {
         self.tree_store.get_rightmost_leaf(version)
     }
+
+    fn get_rightmost_leaf_in_subtree(
+        &self,
+        version: Version,
+        nibble: Nibble,
+    ) -> Result<Option<(NodeKey, LeafNode<K>)>> {
+        self.tree_store
+            .get_rightmost_leaf_in_subtree(version, nibble)
+    }
 }
 
 impl<K, V> TreeWriter<K> for MockSnapshotStore<K, V>
@@ -217,6 +235,24 @@ proptest! {
         assert_success(&restore_db, expected_root_hash, &all, version);
     }
 
+    #[test]
+    fn test_pipelined_restore_reports_error_later(btree in arb_btree_map(2)) {
+        let (db, version) = init_mock_store(&btree.clone().into_values().collect());
+        let tree = JellyfishMerkleTree::new(&db);
+        let expected_root_hash = tree.get_root_hash(version).unwrap();
+        let first = btree.values().next().cloned().unwrap();
+        // Proof of a different range.
+        let bad_proof = tree
+            .get_range_proof(*btree.keys().next_back().unwrap(), version)
+            .unwrap();
+
+        let restore_db = Arc::new(MockSnapshotStore::default());
+        let mut restore = StateSnapshotRestore::new(&restore_db, &restore_db, version, expected_root_hash, true /* async_commit */, StateSnapshotRestoreMode::Default).unwrap();
+        // Applied in the background, the error is only known when finishing.
+        restore.add_chunk(vec![first], bad_proof).unwrap();
+        prop_assert!(restore.finish().is_err());
+    }
+
     #[test]
     fn test_overwrite(
         btree in arb_btree_map(1),
@@ -227,6 +263,137 @@ proptest! {
         // overwrite, an entirely different tree
         restore_without_interruption(&btree, target_version, &restore_db, false);
     }
+
+    #[test]
+    fn test_restore_without_proof(
+        btree in arb_btree_map(1),
+        target_version in 0u64..2000,
+        chunk_size in 1usize..100,
+    ) {
+        let (db, source_version) = init_mock_store(&btree.values().cloned().collect());
+        let expected_root_hash = JellyfishMerkleTree::new(&db).get_root_hash(source_version).unwrap();
+        let kvs: Vec<_> = btree.values().cloned().collect();
+
+        // Chunks adding up to a different tree are only caught at the end, and the root is not
+        // written, so the restore doesn't look complete.
+        let restore_db = Arc::new(MockSnapshotStore::default());
+        {
+            let mut restore = StateSnapshotRestore::new(&restore_db, &restore_db, target_version, expected_root_hash, true /* async_commit */, StateSnapshotRestoreMode::Default).unwrap();
+            for chunk in kvs[..kvs.len() - 1].chunks(chunk_size) {
+                restore.add_chunk_without_proof(chunk.to_vec()).unwrap();
+            }
+            prop_assert!(restore.finish().is_err());
+        }
+        prop_assert!(restore_db.get_node_option(&NodeKey::new_empty_path(target_version), "test").unwrap().is_none());
+
+        let restore_db = Arc::new(MockSnapshotStore::default());
+        let mut restore = StateSnapshotRestore::new(&restore_db, &restore_db, target_version, expected_root_hash, true /* async_commit */, StateSnapshotRestoreMode::Default).unwrap();
+        for chunk in kvs.chunks(chunk_size) {
+            restore.add_chunk_without_proof(chunk.to_vec()).unwrap();
+        }
+        restore.finish().unwrap();
+        assert_success(&restore_db, expected_root_hash, &btree, target_version);
+    }
+
+    #[test]
+    fn test_range_restore(
+        btree in arb_btree_map(1),
+        target_version in 0u64..2000,
+        chunk_size in 1usize..100,
+    ) {
+        let (db, source_version) = init_mock_store(&btree.values().cloned().collect());
+        let expected_root_hash = JellyfishMerkleTree::new(&db).get_root_hash(source_version).unwrap();
+
+        let restore_db = Arc::new(MockSnapshotStore::default());
+        let restore = StateSnapshotRangeRestore::new(&restore_db, &restore_db, target_version, expected_root_hash, StateSnapshotRestoreMode::Default).unwrap();
+        // Each range adds its chunks in order, concurrently with the other ranges.
+        std::thread::scope(|s| {
+            for (range, kvs) in split_in_ranges(&btree).into_iter().enumerate() {
+                let restore = &restore;
+                s.spawn(move || {
+                    for chunk in kvs.chunks(chunk_size) {
+                        restore.add_chunk(range, chunk.to_vec()).unwrap();
+                    }
+                });
+            }
+        });
+        restore.finish().unwrap();
+        assert_success(&restore_db, expected_root_hash, &btree, target_version);
+
+        // Already complete.
+        let restore = StateSnapshotRangeRestore::new(&restore_db, &restore_db, target_version, expected_root_hash, StateSnapshotRestoreMode::Default).unwrap();
+        restore.finish().unwrap();
+    }
+
+    #[test]
+    fn test_range_restore_with_interruption(
+        (all, batch1_size) in arb_btree_map(2)
+            .prop_flat_map(|btree| {
+                let len = btree.len();
+                (Just(btree), 1..len)
+            })
+    ) {
+        let (db, version) = init_mock_store(&all.clone().into_values().collect());
+        let expected_root_hash = JellyfishMerkleTree::new(&db).get_root_hash(version).unwrap();
+        let batch1: BTreeMap<_, _> = all.clone().into_iter().take(batch1_size).collect();
+
+        let restore_db = Arc::new(MockSnapshotStore::default());
+        {
+            let restore = StateSnapshotRangeRestore::new(&restore_db, &restore_db, version, expected_root_hash, StateSnapshotRestoreMode::Default).unwrap();
+            for (range, kvs) in split_in_ranges(&batch1).into_iter().enumerate() {
+                restore.add_chunk(range, kvs).unwrap();
+            }
+            // Do not call `finish`.
+        }
+
+        let restore = StateSnapshotRangeRestore::new(&restore_db, &restore_db, version, expected_root_hash, StateSnapshotRestoreMode::Default).unwrap();
+        let batch1_ranges = split_in_ranges(&batch1);
+        for (range, kvs) in split_in_ranges(&all).into_iter().enumerate() {
+            // Each range resumes from the last leaf persisted in the tree, which is the one before
+            // the last leaf added, since a leaf is only persisted once the next one is added.
+            let resume_point = restore.previous_key_hash(range).unwrap();
+            let batch1_keys: Vec<_> = batch1_ranges[range].iter().map(|(k, _v)| CryptoHash::hash(k)).collect();
+            prop_assert_eq!(resume_point, batch1_keys.len().checked_sub(2).map(|idx| batch1_keys[idx]));
+
+            let remaining: Vec<_> = kvs
+                .into_iter()
+                .filter(|(k, _v)| resume_point.map_or(true, |hash| CryptoHash::hash(k) > hash))
+                .collect();
+            restore.add_chunk(range, remaining).unwrap();
+        }
+        restore.finish().unwrap();
+
+        assert_success(&restore_db, expected_root_hash, &all, version);
+    }
+
+    #[test]
+    fn test_range_restore_wrong_root_hash(btree in arb_btree_map(2)) {
+        let (db, version) = init_mock_store(&btree.values().cloned().collect());
+        let expected_root_hash = JellyfishMerkleTree::new(&db).get_root_hash(version).unwrap();
+        let mut ranges = split_in_ranges(&btree);
+        // Drop a key.
+        ranges.iter_mut().find(|kvs| !kvs.is_empty()).unwrap().pop();
+
+        let restore_db = Arc::new(MockSnapshotStore::default());
+        let restore = StateSnapshotRangeRestore::new(&restore_db, &restore_db, version, expected_root_hash, StateSnapshotRestoreMode::Default).unwrap();
+        for (range, kvs) in ranges.into_iter().enumerate() {
+            restore.add_chunk(range, kvs).unwrap();
+        }
+        prop_assert!(restore.finish().is_err());
+        prop_assert!(restore_db.get_node_option(&NodeKey::new_empty_path(version), "test").unwrap().is_none());
+    }
+}
+
+/// Splits the key-values into the key ranges of a `StateSnapshotRangeRestore`.
+fn split_in_ranges<V>(btree: &BTreeMap<HashValue, (V, V)>) -> Vec<Vec<(V, V)>>
+where
+    V: TestKey + TestValue,
+{
+    let mut ranges = vec![vec![]; StateSnapshotRangeRestore::<V, V>::NUM_RANGES];
+    for (hashed_key, kv) in btree {
+        ranges[StateSnapshotRangeRestore::<V, V>::range_of(hashed_key)].push(kv.clone());
+    }
+    ranges
 }
 
 fn assert_success<V>(
@@ -23,9 +23,9 @@ use crate::{
         truncation_helper::{truncate_ledger_db, truncate_state_kv_db},
     },
     version_data::VersionDataSchema,
-    AptosDbError, LedgerStore, ShardedStateKvSchemaBatch, StaleNodeIndexCrossEpochSchema,
-    StaleNodeIndexSchema, StateKvPrunerManager, StateMerklePrunerManager, TransactionStore,
-    NUM_STATE_SHARDS, OTHER_TIMERS_SECONDS,
+    AptosDbError, LedgerStore, PrunerManager, ShardedStateKvSchemaBatch,
+    StaleNodeIndexCrossEpochSchema, StaleNodeIndexSchema, StateKvPrunerManager,
+    StateMerklePrunerManager, TransactionStore, NUM_STATE_SHARDS, OTHER_TIMERS_SECONDS,
 };
 use anyhow::{ensure, format_err, Context, Result};
 use aptos_crypto::{
@@ -35,7 +35,9 @@ use aptos_crypto::{
 use aptos_executor_types::in_memory_state_calculator::InMemoryStateCalculator;
 use aptos_experimental_runtimes::thread_manager::THREAD_MANAGER;
 use aptos_infallible::Mutex;
-use aptos_jellyfish_merkle::iterator::JellyfishMerkleIterator;
+use aptos_jellyfish_merkle::{
+    diff::JellyfishMerkleDiffIterator, iterator::JellyfishMerkleIterator,
+};
 use aptos_logger::info;
 use aptos_schemadb::{ReadOptions, SchemaBatch};
 use aptos_state_view::StateViewId;
@@ -46,6 +48,7 @@ use aptos_storage_interface::{
     DbReader, StateSnapshotReceiver,
 };
 use aptos_types::{
+    nibble::Nibble,
     proof::{definition::LeafCount, SparseMerkleProofExt, SparseMerkleRangeProof},
     state_store::{
         create_empty_sharded_state_updates,
@@ -1003,6 +1006,52 @@ impl StateStore {
         }))
     }
 
+    /// Gets the state keys whose values differ between `base_version` and `target_version`, in
+    /// ascending order of their hashes, each paired with its value at `target_version`, or `None`
+    /// if the key doesn't exist at `target_version`.
+    pub fn get_state_delta_iter(
+        self: &Arc<Self>,
+        base_version: Version,
+        target_version: Version,
+    ) -> Result<impl Iterator<Item = Result<(StateKey, Option<StateValue>)>> + Send + Sync> {
+        let min_readable_version = self.state_kv_pruner.get_min_readable_version();
+        ensure!(
+            base_version >= min_readable_version,
+            "State values at base version {} are pruned, min available version is {}.",
+            base_version,
+            min_readable_version,
+        );
+
+        let store = Arc::clone(self);
+        Ok(JellyfishMerkleDiffIterator::new(
+            Arc::clone(&self.state_merkle_db),
+            base_version,
+            target_version,
+        )?
+        .map(move |res| {
+            let diff = res?;
+            match diff.target {
+                Some(leaf) => {
+                    let (key, version) = leaf.value_index();
+                    Ok((
+                        key.clone(),
+                        Some(store.expect_value_by_version(key, *version)?),
+                    ))
+                },
+                None => {
+                    let base = diff.base.ok_or_else(|| {
+                        format_err!(
+                            "Diff between versions {} and {} has neither base nor target.",
+                            base_version,
+                            target_version,
+                        )
+                    })?;
+                    Ok((base.value_index().0.clone(), None))
+                },
+            }
+        }))
+    }
+
     pub fn get_value_chunk_with_proof(
         self: &Arc<Self>,
         version: Version,
@@ -1146,6 +1195,7 @@ impl StateValueWriter<StateKey, StateValue> for StateStore {
         version: Version,
         node_batch: &StateValueBatch,
         progress: StateSnapshotProgress,
+        range: Option<Nibble>,
     ) -> Result<()> {
         let _timer = OTHER_TIMERS_SECONDS
             .with_label_values(&["state_value_writer_write_chunk"])
@@ -1154,7 +1204,7 @@ impl StateValueWriter<StateKey, StateValue> for StateStore {
         let sharded_schema_batch = new_sharded_kv_schema_batch();
 
         batch.put::<DbMetadataSchema>(
-            &DbMetadataKey::StateSnapshotRestoreProgress(version),
+            &Self::state_snapshot_progress_key(version, range),
             &DbMetadataValue::StateSnapshotProgress(progress),
         )?;
 
@@ -1170,11 +1220,26 @@ impl StateValueWriter<StateKey, StateValue> for StateStore {
             .put::<VersionDataSchema>(&version, &usage.into())
     }
 
-    fn get_progress(&self, version: Version) -> Result<Option<StateSnapshotProgress>> {
+    fn get_progress(
+        &self,
+        version: Version,
+        range: Option<Nibble>,
+    ) -> Result<Option<StateSnapshotProgress>> {
         Ok(self
             .state_kv_db
             .metadata_db()
-            .get::<DbMetadataSchema>(&DbMetadataKey::StateSnapshotRestoreProgress(version))?
+            .get::<DbMetadataSchema>(&Self::state_snapshot_progress_key(version, range))?
             .map(|v| v.expect_state_snapshot_progress()))
     }
 }
+
+impl StateStore {
+    fn state_snapshot_progress_key(version: Version, range: Option<Nibble>) -> DbMetadataKey {
+        match range {
+            None => DbMetadataKey::StateSnapshotRestoreProgress(version),
+            Some(nibble) => {
+                DbMetadataKey::StateSnapshotRangeRestoreProgress(version, nibble.into())
+            },
+        }
+    }
+}
@@ -6,7 +6,7 @@ use super::*;
 use crate::{
     jellyfish_merkle_node::JellyfishMerkleNodeSchema,
     new_sharded_kv_schema_batch,
-    state_restore::StateSnapshotRestore,
+    state_restore::{StateSnapshotRangeRestore, StateSnapshotRestore},
     test_helper::{arb_state_kv_sets, update_store},
     AptosDB,
 };
@@ -518,6 +518,73 @@ proptest! {
         prop_assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_range_restore_with_interruption(
+        (input, batch1_size) in hash_map(any::<StateKey>(), any::<StateValue>(), 2..1000)
+            .prop_flat_map(|input| {
+                let len = input.len();
+                (Just(input), 1..len)
+            })
+    ) {
+        let tmp_dir1 = TempPath::new();
+        let db1 = AptosDB::new_for_test(&tmp_dir1);
+        let store1 = &db1.state_store;
+        init_store(store1, input.clone().into_iter());
+
+        let version = (input.len() - 1) as Version;
+        let expected_root_hash = store1.get_root_hash(version).unwrap();
+
+        let tmp_dir2 = TempPath::new();
+        let db2 = AptosDB::new_for_test(&tmp_dir2);
+        let store2 = &db2.state_store;
+
+        let mut ordered_input: Vec<_> = input.into_iter().collect();
+        ordered_input.sort_unstable_by_key(|(key, _value)| key.hash());
+        let mut ranges = vec![vec![]; StateSnapshotRangeRestore::<StateKey, StateValue>::NUM_RANGES];
+        for (idx, (key, value)) in ordered_input.into_iter().enumerate() {
+            let range = StateSnapshotRangeRestore::<StateKey, StateValue>::range_of(&key.hash());
+            ranges[range].push((idx < batch1_size, (key, value)));
+        }
+
+        {
+            let restore = StateSnapshotRangeRestore::new(&store2.state_merkle_db, store2, version, expected_root_hash, StateSnapshotRestoreMode::Default).unwrap();
+            for (range, kvs) in ranges.iter().enumerate() {
+                let batch1 = kvs.iter().filter(|(in_batch1, _)| *in_batch1).map(|(_, kv)| kv.clone()).collect();
+                restore.add_chunk(range, batch1).unwrap();
+            }
+            // Do not call `finish`.
+        }
+
+        let restore = StateSnapshotRangeRestore::new(&store2.state_merkle_db, store2, version, expected_root_hash, StateSnapshotRestoreMode::Default).unwrap();
+        for (range, kvs) in ranges.into_iter().enumerate() {
+            // A leaf is only persisted in the tree once the next leaf is added.
+            let batch1_keys: Vec<_> = kvs.iter().filter(|(in_batch1, _)| *in_batch1).map(|(_, (key, _value))| key.hash()).collect();
+            let expected = batch1_keys.len().checked_sub(2).map(|idx| batch1_keys[idx]);
+            let rightmost_leaf = store2
+                .state_merkle_db
+                .get_rightmost_leaf_in_subtree(version, Nibble::from(range as u8))
+                .unwrap();
+            prop_assert_eq!(rightmost_leaf.map(|(_node_key, leaf)| leaf.account_key()), expected);
+            let resume_point = restore.previous_key_hash(range).unwrap();
+            prop_assert_eq!(resume_point, expected);
+
+            let remaining = kvs
+                .into_iter()
+                .map(|(_, kv)| kv)
+                .filter(|(key, _value)| resume_point.map_or(true, |hash| key.hash() > hash))
+                .collect();
+            restore.add_chunk(range, remaining).unwrap();
+        }
+        restore.finish().unwrap();
+
+        let actual_root_hash = store2.get_root_hash(version).unwrap();
+        prop_assert_eq!(actual_root_hash, expected_root_hash);
+        prop_assert_eq!(
+            store2.get_value_count(version).unwrap(),
+            store1.get_value_count(version).unwrap()
+        );
+    }
+
     #[test]
     fn test_get_usage(
         input in arb_state_kv_sets(10, 5, 5)
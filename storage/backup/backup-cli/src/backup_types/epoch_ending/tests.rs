@@ -87,6 +87,7 @@ fn end_to_end() {
                 rocksdb_opt: RocksdbOpt::default(),
                 concurrent_downloads: ConcurrentDownloadsOpt::default(),
                 replay_concurrency_level: ReplayConcurrencyLevelOpt::default(),
+                state_snapshot_restore_in_ranges: false,
             }
             .try_into()
             .unwrap(),
@@ -219,6 +220,7 @@ async fn test_trusted_waypoints_impl(
             rocksdb_opt: RocksdbOpt::default(),
             concurrent_downloads: ConcurrentDownloadsOpt::default(),
             replay_concurrency_level: ReplayConcurrencyLevelOpt::default(),
+            state_snapshot_restore_in_ranges: false,
         }
         .try_into()
         .unwrap(),
@@ -240,6 +242,7 @@ async fn test_trusted_waypoints_impl(
             rocksdb_opt: RocksdbOpt::default(),
             concurrent_downloads: ConcurrentDownloadsOpt::default(),
             replay_concurrency_level: ReplayConcurrencyLevelOpt::default(),
+            state_snapshot_restore_in_ranges: false,
         }
         .try_into()
         .unwrap(),
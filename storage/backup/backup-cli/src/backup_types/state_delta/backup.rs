@@ -0,0 +1,292 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        state_delta::manifest::{StateDeltaBackup, StateDeltaChunk},
+        state_snapshot::backup::get_version_for_epoch_ending,
+    },
+    metadata::Metadata,
+    storage::{BackupHandleRef, BackupStorage, FileHandle, ShellSafeName},
+    utils::{
+        backup_service_client::BackupServiceClient, read_record_bytes::ReadRecordBytes,
+        should_cut_chunk, storage_ext::BackupStorageExt, GlobalBackupOpt,
+    },
+};
+use anyhow::{anyhow, ensure, Result};
+use aptos_crypto::{hash::CryptoHash, HashValue};
+use aptos_logger::prelude::*;
+use aptos_types::{
+    ledger_info::LedgerInfoWithSignatures,
+    proof::TransactionInfoWithProof,
+    state_store::{state_key::StateKey, state_value::StateValue},
+    transaction::Version,
+};
+use bytes::Bytes;
+use clap::Parser;
+use once_cell::sync::Lazy;
+use std::{convert::TryInto, str::FromStr, sync::Arc};
+use tokio::{io::AsyncWriteExt, time::Instant};
+
+#[derive(Parser)]
+pub struct StateDeltaBackupOpt {
+    #[clap(
+        long = "state-delta-base-epoch",
+        help = "Epoch at the end of which the base state is taken, usually the epoch of an \
+        existing state snapshot backup or the target epoch of an existing state delta backup."
+    )]
+    pub base_epoch: u64,
+
+    #[clap(
+        long = "state-delta-epoch",
+        help = "Epoch at the end of which the state changes since the base state are backed up."
+    )]
+    pub epoch: u64,
+}
+
+pub struct StateDeltaBackupController {
+    base_epoch: u64,
+    epoch: u64,
+    base_version: Option<Version>, // initialize before using
+    version: Option<Version>,      // initialize before using
+    max_chunk_size: usize,
+    client: Arc<BackupServiceClient>,
+    storage: Arc<dyn BackupStorage>,
+}
+
+impl StateDeltaBackupController {
+    pub fn new(
+        opt: StateDeltaBackupOpt,
+        global_opt: GlobalBackupOpt,
+        client: Arc<BackupServiceClient>,
+        storage: Arc<dyn BackupStorage>,
+    ) -> Self {
+        Self {
+            base_epoch: opt.base_epoch,
+            epoch: opt.epoch,
+            base_version: None,
+            version: None,
+            max_chunk_size: global_opt.max_chunk_size,
+            client,
+            storage,
+        }
+    }
+
+    pub async fn run(self) -> Result<FileHandle> {
+        info!(
+            "State delta backup started, from epoch {} to epoch {}.",
+            self.base_epoch, self.epoch
+        );
+        let ret = self
+            .run_impl()
+            .await
+            .map_err(|e| anyhow!("State delta backup failed: {}", e))?;
+        info!("State delta backup succeeded. Manifest: {}", ret);
+        Ok(ret)
+    }
+
+    async fn run_impl(mut self) -> Result<FileHandle> {
+        ensure!(
+            self.base_epoch < self.epoch,
+            "Base epoch {} is not before epoch {}.",
+            self.base_epoch,
+            self.epoch,
+        );
+        self.base_version =
+            Some(get_version_for_epoch_ending(&self.client, self.base_epoch).await?);
+        self.version = Some(get_version_for_epoch_ending(&self.client, self.epoch).await?);
+        let backup_handle = self
+            .storage
+            .create_backup_with_random_suffix(&self.backup_name())
+            .await?;
+
+        let mut chunks = vec![];
+
+        let mut state_delta_file = self
+            .client
+            .get_state_delta(self.base_version(), self.version())
+            .await?;
+        let mut chunk_bytes = vec![];
+        let mut chunk_first_key = HashValue::zero();
+        let mut chunk_last_key = HashValue::zero();
+        let mut current_idx: usize = 0;
+        let mut chunk_first_idx: usize = 0;
+
+        let start = Instant::now();
+        while let Some(record_bytes) = state_delta_file.read_record_bytes().await? {
+            if !chunk_bytes.is_empty()
+                && should_cut_chunk(&chunk_bytes, &record_bytes, self.max_chunk_size)
+            {
+                let chunk = self
+                    .write_chunk(
+                        &backup_handle,
+                        &chunk_bytes,
+                        chunk_first_idx,
+                        current_idx - 1,
+                        chunk_first_key,
+                        chunk_last_key,
+                    )
+                    .await?;
+                chunks.push(chunk);
+                chunk_bytes = vec![];
+                chunk_first_idx = current_idx;
+
+                info!(
+                    last_idx = current_idx - 1,
+                    changes_per_second =
+                        (current_idx as f64 / start.elapsed().as_secs_f64()) as u64,
+                    "Chunk written."
+                );
+            }
+
+            chunk_last_key = Self::parse_key(&record_bytes)?;
+            if chunk_bytes.is_empty() {
+                chunk_first_key = chunk_last_key;
+            }
+            current_idx += 1;
+            chunk_bytes.extend((record_bytes.len() as u32).to_be_bytes());
+            chunk_bytes.extend(&record_bytes);
+        }
+
+        // Unlike a state snapshot, a delta can legitimately be empty.
+        if !chunk_bytes.is_empty() {
+            let chunk = self
+                .write_chunk(
+                    &backup_handle,
+                    &chunk_bytes,
+                    chunk_first_idx,
+                    current_idx - 1,
+                    chunk_first_key,
+                    chunk_last_key,
+                )
+                .await?;
+            chunks.push(chunk);
+        }
+
+        self.write_manifest(&backup_handle, chunks).await
+    }
+}
+
+impl StateDeltaBackupController {
+    fn base_version(&self) -> Version {
+        self.base_version.unwrap()
+    }
+
+    fn version(&self) -> Version {
+        self.version.unwrap()
+    }
+
+    fn backup_name(&self) -> String {
+        format!(
+            "state_delta_epoch_{}_{}_ver_{}",
+            self.base_epoch,
+            self.epoch,
+            self.version()
+        )
+    }
+
+    fn manifest_name() -> &'static ShellSafeName {
+        static NAME: Lazy<ShellSafeName> =
+            Lazy::new(|| ShellSafeName::from_str("state_delta.manifest").unwrap());
+        &NAME
+    }
+
+    fn proof_name() -> &'static ShellSafeName {
+        static NAME: Lazy<ShellSafeName> =
+            Lazy::new(|| ShellSafeName::from_str("state_delta.proof").unwrap());
+        &NAME
+    }
+
+    fn chunk_name(first_idx: usize) -> ShellSafeName {
+        format!("{}-.chunk", first_idx).try_into().unwrap()
+    }
+
+    fn parse_key(record: &Bytes) -> Result<HashValue> {
+        let (key, _): (StateKey, Option<StateValue>) = bcs::from_bytes(record)?;
+        Ok(key.hash())
+    }
+
+    async fn write_chunk(
+        &self,
+        backup_handle: &BackupHandleRef,
+        chunk_bytes: &[u8],
+        first_idx: usize,
+        last_idx: usize,
+        first_key: HashValue,
+        last_key: HashValue,
+    ) -> Result<StateDeltaChunk> {
+        let (chunk_handle, mut chunk_file) = self
+            .storage
+            .create_for_write(backup_handle, &Self::chunk_name(first_idx))
+            .await?;
+        chunk_file.write_all(chunk_bytes).await?;
+        chunk_file.shutdown().await?;
+
+        Ok(StateDeltaChunk {
+            first_idx,
+            last_idx,
+            first_key,
+            last_key,
+            records: chunk_handle,
+        })
+    }
+
+    async fn get_root_hash(&self, version: Version) -> Result<(HashValue, Vec<u8>)> {
+        let proof_bytes = self.client.get_state_root_proof(version).await?;
+        let (txn_info, _): (TransactionInfoWithProof, LedgerInfoWithSignatures) =
+            bcs::from_bytes(&proof_bytes)?;
+        Ok((
+            txn_info.transaction_info().ensure_state_checkpoint_hash()?,
+            proof_bytes,
+        ))
+    }
+
+    async fn write_manifest(
+        &self,
+        backup_handle: &BackupHandleRef,
+        chunks: Vec<StateDeltaChunk>,
+    ) -> Result<FileHandle> {
+        let (base_root_hash, _) = self.get_root_hash(self.base_version()).await?;
+        let (root_hash, proof_bytes) = self.get_root_hash(self.version()).await?;
+
+        let (proof_handle, mut proof_file) = self
+            .storage
+            .create_for_write(backup_handle, Self::proof_name())
+            .await?;
+        proof_file.write_all(&proof_bytes).await?;
+        proof_file.shutdown().await?;
+
+        let manifest = StateDeltaBackup {
+            base_version: self.base_version(),
+            base_epoch: self.base_epoch,
+            base_root_hash,
+            version: self.version(),
+            epoch: self.epoch,
+            root_hash,
+            chunks,
+            proof: proof_handle,
+        };
+
+        let (manifest_handle, mut manifest_file) = self
+            .storage
+            .create_for_write(backup_handle, Self::manifest_name())
+            .await?;
+        manifest_file
+            .write_all(&serde_json::to_vec(&manifest)?)
+            .await?;
+        manifest_file.shutdown().await?;
+
+        let metadata = Metadata::new_state_delta_backup(
+            self.base_epoch,
+            self.base_version(),
+            self.epoch,
+            self.version(),
+            manifest_handle.clone(),
+        );
+        self.storage
+            .save_metadata_line(&metadata.name(), &metadata.to_text_line()?)
+            .await?;
+
+        Ok(manifest_handle)
+    }
+}
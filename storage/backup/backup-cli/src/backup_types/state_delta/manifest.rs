@@ -0,0 +1,50 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::storage::FileHandle;
+use aptos_crypto::HashValue;
+use aptos_types::transaction::Version;
+use serde::{Deserialize, Serialize};
+
+/// A chunk of a state delta manifest, representing the changed keys in the key range
+/// [`first_key`, `last_key`] (right side inclusive).
+#[derive(Deserialize, Serialize)]
+pub struct StateDeltaChunk {
+    /// index of the first change in this chunk over all changes.
+    pub first_idx: usize,
+    /// index of the last change in this chunk over all changes.
+    pub last_idx: usize,
+    /// key of the first change in this chunk.
+    pub first_key: HashValue,
+    /// key of the last change in this chunk.
+    pub last_key: HashValue,
+    /// Repeated `len(record) + record` where `record` is BCS serialized tuple
+    /// `(key, Option<state_value>)`, `None` meaning the key is deleted.
+    pub records: FileHandle,
+}
+
+/// State delta backup manifest, representing the changes to the state between two versions,
+/// which applied to the state at `base_version` results in the state at `version`.
+#[derive(Deserialize, Serialize)]
+pub struct StateDeltaBackup {
+    /// Version of the state the changes apply to.
+    pub base_version: Version,
+    /// Epoch at the end of which the base state is taken.
+    pub base_epoch: u64,
+    /// Hash of the state tree root at `base_version`.
+    pub base_root_hash: HashValue,
+    /// Version of the state resulted from the changes.
+    pub version: Version,
+    /// Epoch at the end of which the resulted state is taken.
+    pub epoch: u64,
+    /// Hash of the state tree root at `version`.
+    pub root_hash: HashValue,
+    /// All changes in chunks, ordered by key.
+    pub chunks: Vec<StateDeltaChunk>,
+    /// BCS serialized `Tuple(TransactionInfoWithProof, LedgerInfoWithSignatures)` at `version`,
+    /// carrying `root_hash`. See `StateSnapshotBackup::proof`.
+    ///
+    /// There's no proof for the chunks: a restore applies the changes to the base state and only
+    /// checks the root hash of the resulted tree against `root_hash`.
+    pub proof: FileHandle,
+}
@@ -0,0 +1,384 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        epoch_ending::restore::EpochHistory,
+        state_delta::manifest::{StateDeltaBackup, StateDeltaChunk},
+        state_snapshot::manifest::StateSnapshotBackup,
+    },
+    metrics::{
+        restore::{
+            STATE_SNAPSHOT_LEAF_INDEX, STATE_SNAPSHOT_TARGET_LEAF_INDEX, STATE_SNAPSHOT_VERSION,
+        },
+        verify::{
+            VERIFY_STATE_SNAPSHOT_LEAF_INDEX, VERIFY_STATE_SNAPSHOT_TARGET_LEAF_INDEX,
+            VERIFY_STATE_SNAPSHOT_VERSION,
+        },
+        OTHER_TIMERS_SECONDS,
+    },
+    storage::{BackupStorage, FileHandle},
+    utils::{
+        read_record_bytes::ReadRecordBytes, storage_ext::BackupStorageExt, GlobalRestoreOptions,
+        RestoreRunMode,
+    },
+};
+use anyhow::{anyhow, ensure, Result};
+use aptos_crypto::{hash::CryptoHash, HashValue};
+use aptos_db::state_restore::{StateSnapshotRestore, StateSnapshotRestoreMode};
+use aptos_infallible::Mutex;
+use aptos_logger::prelude::*;
+use aptos_storage_interface::StateSnapshotReceiver;
+use aptos_types::{
+    ledger_info::LedgerInfoWithSignatures,
+    proof::TransactionInfoWithProof,
+    state_store::{state_key::StateKey, state_value::StateValue},
+    transaction::Version,
+};
+use clap::Parser;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
+use tokio::time::Instant;
+
+#[derive(Parser)]
+pub struct StateDeltaRestoreOpt {
+    #[clap(long = "state-manifest", help = "Manifest of the base state snapshot.")]
+    pub snapshot_manifest_handle: FileHandle,
+    #[clap(
+        long = "state-delta-manifest",
+        help = "Manifests of the state deltas, in the order they apply. The first applies to the \
+        base state snapshot and each of the rest applies to the state resulted from the previous."
+    )]
+    pub delta_manifest_handles: Vec<FileHandle>,
+    #[clap(
+        long = "state-into-version",
+        help = "Version of the state resulted from the last delta."
+    )]
+    pub version: Version,
+    #[clap(long)]
+    pub restore_mode: StateSnapshotRestoreMode,
+}
+
+/// Restores the state at the end of a chain of state deltas, by streaming the base state snapshot
+/// chunk by chunk with the changes within each chunk's key range applied on the fly.
+///
+/// None of the merged chunks has a range proof, the restored tree is instead checked against the
+/// root hash of the last delta (which is proven against a ledger info) when finishing.
+pub struct StateDeltaRestoreController {
+    storage: Arc<dyn BackupStorage>,
+    run_mode: Arc<RestoreRunMode>,
+    /// State restores to this version.
+    version: Version,
+    snapshot_manifest_handle: FileHandle,
+    delta_manifest_handles: Vec<FileHandle>,
+    /// Global "target_version" for the entire restore process, if `version` is newer than this,
+    /// nothing will be done, otherwise, this has no effect.
+    target_version: Version,
+    epoch_history: Option<Arc<EpochHistory>>,
+    restore_mode: StateSnapshotRestoreMode,
+}
+
+impl StateDeltaRestoreController {
+    pub fn new(
+        opt: StateDeltaRestoreOpt,
+        global_opt: GlobalRestoreOptions,
+        storage: Arc<dyn BackupStorage>,
+        epoch_history: Option<Arc<EpochHistory>>,
+    ) -> Self {
+        Self {
+            storage,
+            run_mode: global_opt.run_mode,
+            version: opt.version,
+            snapshot_manifest_handle: opt.snapshot_manifest_handle,
+            delta_manifest_handles: opt.delta_manifest_handles,
+            target_version: global_opt.target_version,
+            epoch_history,
+            restore_mode: opt.restore_mode,
+        }
+    }
+
+    pub async fn run(self) -> Result<()> {
+        let name = self.name();
+        let start = Instant::now();
+        info!(
+            "{} started. Snapshot manifest: {}, delta manifests: {:?}",
+            name, self.snapshot_manifest_handle, self.delta_manifest_handles,
+        );
+        self.run_impl()
+            .await
+            .map_err(|e| anyhow!("{} failed: {}", name, e))?;
+        info!(time = start.elapsed().as_secs(), "{} succeeded.", name);
+        Ok(())
+    }
+}
+
+impl StateDeltaRestoreController {
+    fn name(&self) -> String {
+        format!("state delta {}", self.run_mode.name())
+    }
+
+    async fn run_impl(self) -> Result<()> {
+        if self.version > self.target_version {
+            warn!(
+                "Trying to restore state delta to version {}, which is newer than the target version {}, skipping.",
+                self.version,
+                self.target_version,
+            );
+            return Ok(());
+        }
+        ensure!(
+            self.restore_mode != StateSnapshotRestoreMode::KvOnly,
+            "KV only restore is not supported for state deltas, since nothing verifies the KVs \
+            without the tree.",
+        );
+        ensure!(
+            !self.delta_manifest_handles.is_empty(),
+            "No state delta manifest specified.",
+        );
+
+        let snapshot: StateSnapshotBackup = self
+            .storage
+            .load_json_file(&self.snapshot_manifest_handle)
+            .await?;
+        self.verify_root_hash(&snapshot.proof, snapshot.version, snapshot.root_hash)
+            .await?;
+
+        let mut deltas = Vec::with_capacity(self.delta_manifest_handles.len());
+        let (mut prev_version, mut prev_root_hash) = (snapshot.version, snapshot.root_hash);
+        for handle in &self.delta_manifest_handles {
+            let delta: StateDeltaBackup = self.storage.load_json_file(handle).await?;
+            ensure!(
+                delta.base_version == prev_version && delta.base_root_hash == prev_root_hash,
+                "State delta {} doesn't apply to the state at version {} with root hash {}, \
+                its base is at version {} with root hash {}.",
+                handle,
+                prev_version,
+                prev_root_hash,
+                delta.base_version,
+                delta.base_root_hash,
+            );
+            self.verify_root_hash(&delta.proof, delta.version, delta.root_hash)
+                .await?;
+            prev_version = delta.version;
+            prev_root_hash = delta.root_hash;
+            deltas.push(delta);
+        }
+        ensure!(
+            prev_version == self.version,
+            "Last state delta results in version {}, expecting {}.",
+            prev_version,
+            self.version,
+        );
+
+        let receiver = Arc::new(Mutex::new(Some(self.run_mode.get_state_restore_receiver(
+            self.version,
+            prev_root_hash,
+            self.restore_mode,
+        )?)));
+
+        let (ver_gauge, tgt_leaf_idx, leaf_idx) = if self.run_mode.is_verify() {
+            (
+                &VERIFY_STATE_SNAPSHOT_VERSION,
+                &VERIFY_STATE_SNAPSHOT_TARGET_LEAF_INDEX,
+                &VERIFY_STATE_SNAPSHOT_LEAF_INDEX,
+            )
+        } else {
+            (
+                &STATE_SNAPSHOT_VERSION,
+                &STATE_SNAPSHOT_TARGET_LEAF_INDEX,
+                &STATE_SNAPSHOT_LEAF_INDEX,
+            )
+        };
+        // Leaf indexes are those of the base snapshot, as the merged state isn't indexed.
+        ver_gauge.set(self.version as i64);
+        tgt_leaf_idx.set(snapshot.chunks.last().map_or(0, |c| c.last_idx as i64));
+
+        let mut cursors: Vec<_> = deltas
+            .into_iter()
+            .map(|delta| DeltaCursor::new(Arc::clone(&self.storage), delta.chunks))
+            .collect();
+
+        let total_chunks = snapshot.chunks.len();
+        let resume_point_opt = receiver.lock().as_mut().unwrap().previous_key_hash()?;
+        let chunks: Vec<_> = if let Some(resume_point) = resume_point_opt {
+            for cursor in cursors.iter_mut() {
+                cursor.take_until(Some(resume_point)).await?;
+            }
+            snapshot
+                .chunks
+                .into_iter()
+                .skip_while(|chunk| chunk.last_key <= resume_point)
+                .collect()
+        } else {
+            snapshot.chunks
+        };
+        if chunks.len() < total_chunks {
+            info!(
+                chunks_to_add = chunks.len(),
+                total_chunks = total_chunks,
+                "Resumed state delta restore."
+            )
+        };
+        let chunks_to_add = chunks.len();
+
+        let start = Instant::now();
+        for (chunk_idx, chunk) in chunks.into_iter().enumerate() {
+            let _timer = OTHER_TIMERS_SECONDS
+                .with_label_values(&["add_state_delta_chunk"])
+                .start_timer();
+            let mut merged: BTreeMap<_, _> = Self::read_state_value(&self.storage, &chunk.blobs)
+                .await?
+                .into_iter()
+                .map(|(key, value)| (key.hash(), (key, Some(value))))
+                .collect();
+            // Later deltas override earlier ones.
+            for cursor in cursors.iter_mut() {
+                merged.extend(cursor.take_until(Some(chunk.last_key)).await?);
+            }
+            Self::add_merged_chunk(&receiver, merged, resume_point_opt).await?;
+
+            leaf_idx.set(chunk.last_idx as i64);
+            info!(
+                chunk = chunk_idx,
+                chunks_to_add = chunks_to_add,
+                last_idx = chunk.last_idx,
+                chunks_per_second = ((chunk_idx + 1) as f64 / start.elapsed().as_secs_f64()) as u64,
+                "State chunk with deltas added.",
+            );
+        }
+
+        // Keys created after the last key in the base snapshot.
+        let mut merged = BTreeMap::new();
+        for cursor in cursors.iter_mut() {
+            merged.extend(cursor.take_until(None).await?);
+        }
+        Self::add_merged_chunk(&receiver, merged, resume_point_opt).await?;
+
+        tokio::task::spawn_blocking(move || receiver.lock().take().unwrap().finish()).await??;
+        self.run_mode.finish();
+        Ok(())
+    }
+
+    async fn add_merged_chunk(
+        receiver: &Arc<Mutex<Option<StateSnapshotRestore<StateKey, StateValue>>>>,
+        merged: BTreeMap<HashValue, (StateKey, Option<StateValue>)>,
+        resume_point_opt: Option<HashValue>,
+    ) -> Result<()> {
+        let blobs: Vec<_> = merged
+            .into_iter()
+            .filter(|(key_hash, _)| resume_point_opt.map_or(true, |p| *key_hash > p))
+            .filter_map(|(_, (key, value))| value.map(|value| (key, value)))
+            .collect();
+        if blobs.is_empty() {
+            return Ok(());
+        }
+
+        let receiver = receiver.clone();
+        tokio::task::spawn_blocking(move || {
+            receiver
+                .lock()
+                .as_mut()
+                .unwrap()
+                .add_chunk_without_proof(blobs)
+        })
+        .await?
+    }
+
+    async fn verify_root_hash(
+        &self,
+        proof: &FileHandle,
+        version: Version,
+        root_hash: HashValue,
+    ) -> Result<()> {
+        let (txn_info_with_proof, li): (TransactionInfoWithProof, LedgerInfoWithSignatures) =
+            self.storage.load_bcs_file(proof).await?;
+        txn_info_with_proof.verify(li.ledger_info(), version)?;
+        let state_root_hash = txn_info_with_proof
+            .transaction_info()
+            .ensure_state_checkpoint_hash()?;
+        ensure!(
+            state_root_hash == root_hash,
+            "Root hash mismatch with that in proof. root hash: {}, expected: {}",
+            root_hash,
+            state_root_hash,
+        );
+        if let Some(epoch_history) = self.epoch_history.as_ref() {
+            epoch_history.verify_ledger_info(&li)?;
+        }
+        Ok(())
+    }
+
+    async fn read_state_value(
+        storage: &Arc<dyn BackupStorage>,
+        file_handle: &FileHandle,
+    ) -> Result<Vec<(StateKey, StateValue)>> {
+        let mut file = storage.open_for_read(file_handle).await?;
+
+        let mut chunk = vec![];
+
+        while let Some(record_bytes) = file.read_record_bytes().await? {
+            chunk.push(bcs::from_bytes(&record_bytes)?);
+        }
+
+        Ok(chunk)
+    }
+}
+
+/// Reads the changes in a state delta in key order, loading a chunk only when needed.
+struct DeltaCursor {
+    storage: Arc<dyn BackupStorage>,
+    chunks: VecDeque<StateDeltaChunk>,
+    buffered: VecDeque<(HashValue, (StateKey, Option<StateValue>))>,
+}
+
+impl DeltaCursor {
+    fn new(storage: Arc<dyn BackupStorage>, chunks: Vec<StateDeltaChunk>) -> Self {
+        Self {
+            storage,
+            chunks: chunks.into(),
+            buffered: VecDeque::new(),
+        }
+    }
+
+    /// Takes all remaining changes with key hashes up to `bound` (inclusive), or all remaining
+    /// changes if `bound` is `None`.
+    async fn take_until(
+        &mut self,
+        bound: Option<HashValue>,
+    ) -> Result<Vec<(HashValue, (StateKey, Option<StateValue>))>> {
+        let mut res = vec![];
+        loop {
+            while let Some((key_hash, _)) = self.buffered.front() {
+                if bound.map_or(false, |b| *key_hash > b) {
+                    return Ok(res);
+                }
+                res.push(self.buffered.pop_front().unwrap());
+            }
+            match self.chunks.pop_front() {
+                Some(chunk) if bound.map_or(true, |b| chunk.first_key <= b) => {
+                    self.buffered = self.load_records(&chunk.records).await?.into();
+                },
+                Some(chunk) => {
+                    self.chunks.push_front(chunk);
+                    return Ok(res);
+                },
+                None => return Ok(res),
+            }
+        }
+    }
+
+    async fn load_records(
+        &self,
+        file_handle: &FileHandle,
+    ) -> Result<Vec<(HashValue, (StateKey, Option<StateValue>))>> {
+        let mut file = self.storage.open_for_read(file_handle).await?;
+        let mut records = vec![];
+        while let Some(record_bytes) = file.read_record_bytes().await? {
+            let (key, value): (StateKey, Option<StateValue>) = bcs::from_bytes(&record_bytes)?;
+            records.push((key.hash(), (key, value)));
+        }
+        Ok(records)
+    }
+}
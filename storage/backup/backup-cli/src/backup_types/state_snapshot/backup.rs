@@ -175,18 +175,7 @@ impl StateSnapshotBackupController {
     }
 
     async fn get_version_for_epoch_ending(&self, epoch: u64) -> Result<u64> {
-        let ledger_info: LedgerInfoWithSignatures = bcs::from_bytes(
-            self.client
-                .get_epoch_ending_ledger_infos(epoch, epoch + 1)
-                .await?
-                .read_record_bytes()
-                .await?
-                .ok_or_else(|| {
-                    anyhow!("Failed to get epoch ending ledger info for epoch {}", epoch)
-                })?
-                .as_ref(),
-        )?;
-        Ok(ledger_info.ledger_info().version())
+        get_version_for_epoch_ending(&self.client, epoch).await
     }
 
     async fn write_chunk(
@@ -273,3 +262,19 @@ impl StateSnapshotBackupController {
         Ok(manifest_handle)
     }
 }
+
+pub(crate) async fn get_version_for_epoch_ending(
+    client: &BackupServiceClient,
+    epoch: u64,
+) -> Result<Version> {
+    let ledger_info: LedgerInfoWithSignatures = bcs::from_bytes(
+        client
+            .get_epoch_ending_ledger_infos(epoch, epoch + 1)
+            .await?
+            .read_record_bytes()
+            .await?
+            .ok_or_else(|| anyhow!("Failed to get epoch ending ledger info for epoch {}", epoch))?
+            .as_ref(),
+    )?;
+    Ok(ledger_info.ledger_info().version())
+}
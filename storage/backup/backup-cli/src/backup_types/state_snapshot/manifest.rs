@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 
 /// A chunk of a state snapshot manifest, representing accounts in the key range
 /// [`first_key`, `last_key`] (right side inclusive).
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct StateSnapshotChunk {
     /// index of the first account in this chunk over all accounts.
     pub first_idx: usize,
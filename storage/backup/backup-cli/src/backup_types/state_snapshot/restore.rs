@@ -18,14 +18,16 @@ use crate::{
     },
     storage::{BackupStorage, FileHandle},
     utils::{
-        read_record_bytes::ReadRecordBytes, storage_ext::BackupStorageExt, stream::StreamX,
-        GlobalRestoreOptions, RestoreRunMode,
+        progress_bar, read_record_bytes::ReadRecordBytes, storage_ext::BackupStorageExt,
+        stream::StreamX, GlobalRestoreOptions, RestoreRunMode,
     },
 };
 use anyhow::{anyhow, ensure, Result};
-use aptos_db::state_restore::StateSnapshotRestoreMode;
+use aptos_crypto::{hash::CryptoHash, HashValue};
+use aptos_db::state_restore::{StateSnapshotRangeRestore, StateSnapshotRestoreMode};
 use aptos_infallible::Mutex;
 use aptos_logger::prelude::*;
+use aptos_push_metrics::IntGauge;
 use aptos_storage_interface::StateSnapshotReceiver;
 use aptos_types::{
     access_path::Path,
@@ -44,7 +46,7 @@ use futures::{stream, TryStreamExt};
 use move_binary_format::CompiledModule;
 use move_bytecode_verifier::verify_module_with_config;
 use std::sync::Arc;
-use tokio::time::Instant;
+use tokio::{task::JoinHandle, time::Instant};
 
 #[derive(Parser)]
 pub struct StateSnapshotRestoreOpt {
@@ -71,6 +73,7 @@ pub struct StateSnapshotRestoreController {
     concurrent_downloads: usize,
     validate_modules: bool,
     restore_mode: StateSnapshotRestoreMode,
+    restore_in_ranges: bool,
 }
 
 impl StateSnapshotRestoreController {
@@ -90,6 +93,7 @@ impl StateSnapshotRestoreController {
             concurrent_downloads: global_opt.concurrent_downloads,
             validate_modules: opt.validate_modules,
             restore_mode: opt.restore_mode,
+            restore_in_ranges: global_opt.state_snapshot_restore_in_ranges,
         }
     }
 
@@ -105,6 +109,12 @@ impl StateSnapshotRestoreController {
     }
 }
 
+const NUM_RANGES: usize = StateSnapshotRangeRestore::<StateKey, StateValue>::NUM_RANGES;
+
+fn range_of(key_hash: &HashValue) -> usize {
+    StateSnapshotRangeRestore::<StateKey, StateValue>::range_of(key_hash)
+}
+
 impl StateSnapshotRestoreController {
     fn name(&self) -> String {
         format!("state snapshot {}", self.run_mode.name())
@@ -138,12 +148,6 @@ impl StateSnapshotRestoreController {
             epoch_history.verify_ledger_info(&li)?;
         }
 
-        let receiver = Arc::new(Mutex::new(Some(self.run_mode.get_state_restore_receiver(
-            self.version,
-            manifest.root_hash,
-            self.restore_mode,
-        )?)));
-
         let (ver_gauge, tgt_leaf_idx, leaf_idx) = if self.run_mode.is_verify() {
             (
                 &VERIFY_STATE_SNAPSHOT_VERSION,
@@ -160,6 +164,37 @@ impl StateSnapshotRestoreController {
 
         ver_gauge.set(self.version as i64);
         tgt_leaf_idx.set(manifest.chunks.last().map_or(0, |c| c.last_idx as i64));
+
+        let range_restore = if self.restore_in_ranges {
+            self.run_mode.get_state_range_restore(
+                self.version,
+                manifest.root_hash,
+                self.restore_mode,
+            )?
+        } else {
+            None
+        };
+        if let Some(range_restore) = range_restore {
+            self.add_chunks_in_ranges(manifest, range_restore, leaf_idx)
+                .await?;
+        } else {
+            self.add_chunks_in_order(manifest, leaf_idx).await?;
+        }
+        self.run_mode.finish();
+        Ok(())
+    }
+
+    /// Adds the chunks one by one, verifying each of them with its proof.
+    async fn add_chunks_in_order(
+        &self,
+        manifest: StateSnapshotBackup,
+        leaf_idx: &IntGauge,
+    ) -> Result<()> {
+        let receiver = Arc::new(Mutex::new(Some(self.run_mode.get_state_restore_receiver(
+            self.version,
+            manifest.root_hash,
+            self.restore_mode,
+        )?)));
         let total_chunks = manifest.chunks.len();
 
         let resume_point_opt = receiver.lock().as_mut().unwrap().previous_key_hash()?;
@@ -182,6 +217,9 @@ impl StateSnapshotRestoreController {
         let chunks_to_add = chunks.len();
 
         let start_idx = chunks.first().map_or(0, |chunk| chunk.first_idx);
+        let total_values = chunks.last().map_or(start_idx, |chunk| chunk.last_idx + 1);
+        let bar = progress_bar(total_values as u64);
+        bar.set_position(start_idx as u64);
 
         let storage = self.storage.clone();
         let futs_iter = chunks.into_iter().enumerate().map(|(chunk_idx, chunk)| {
@@ -216,19 +254,151 @@ impl StateSnapshotRestoreController {
             })
             .await??;
             leaf_idx.set(chunk.last_idx as i64);
+            bar.set_position(chunk.last_idx as u64 + 1);
+            let values_per_second = (chunk.last_idx + 1 - start_idx) as f64
+                / start.as_ref().unwrap().elapsed().as_secs_f64();
             info!(
                 chunk = chunk_idx,
                 chunks_to_add = chunks_to_add,
                 last_idx = chunk.last_idx,
-                values_per_second = ((chunk.last_idx + 1 - start_idx) as f64
-                    / start.as_ref().unwrap().elapsed().as_secs_f64())
-                    as u64,
+                values_per_second = values_per_second as u64,
+                eta_secs = ((total_values - chunk.last_idx - 1) as f64 / values_per_second) as u64,
                 "State chunk added.",
             );
         }
+        bar.finish();
 
         tokio::task::spawn_blocking(move || receiver.lock().take().unwrap().finish()).await??;
-        self.run_mode.finish();
+        Ok(())
+    }
+
+    /// Adds the chunks to the key ranges they overlap, see `StateSnapshotRangeRestore`. The
+    /// chunks are downloaded taking turns between the ranges, so that the chunks of different
+    /// ranges are added in parallel. A chunk overlapping several ranges is downloaded for each of
+    /// them, keeping only its keys in the range.
+    ///
+    /// The chunks aren't verified against their proofs. Only the root hash of the whole tree is
+    /// checked in the end, and not at all when restoring the KV only.
+    async fn add_chunks_in_ranges(
+        &self,
+        manifest: StateSnapshotBackup,
+        range_restore: StateSnapshotRangeRestore<StateKey, StateValue>,
+        leaf_idx: &IntGauge,
+    ) -> Result<()> {
+        let range_restore = Arc::new(range_restore);
+        let total_values = manifest.chunks.last().map_or(0, |chunk| chunk.last_idx + 1);
+        let resume_points = (0..NUM_RANGES)
+            .map(|range| range_restore.previous_key_hash(range))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut range_chunks = vec![vec![]; NUM_RANGES];
+        let mut values_skipped = 0;
+        for chunk in manifest.chunks {
+            let mut skipped = true;
+            for range in range_of(&chunk.first_key)..=range_of(&chunk.last_key) {
+                if resume_points[range].map_or(true, |resume_point| chunk.last_key > resume_point) {
+                    range_chunks[range].push(chunk.clone());
+                    skipped = false;
+                }
+            }
+            if skipped {
+                values_skipped += chunk.last_idx + 1 - chunk.first_idx;
+            }
+        }
+        if values_skipped > 0 {
+            info!(
+                values_skipped = values_skipped,
+                total_values = total_values,
+                "Resumed state snapshot restore."
+            );
+        }
+
+        let max_chunks_per_range = range_chunks.iter().map(Vec::len).max().unwrap_or(0);
+        let mut chunks = vec![];
+        for idx in 0..max_chunks_per_range {
+            for (range, range_chunks) in range_chunks.iter().enumerate() {
+                if let Some(chunk) = range_chunks.get(idx) {
+                    chunks.push((range, chunk.clone()));
+                }
+            }
+        }
+        let chunks_to_add = chunks.len();
+
+        let bar = progress_bar(total_values as u64);
+        bar.set_position(values_skipped as u64);
+
+        let storage = self.storage.clone();
+        let validate_modules = self.validate_modules;
+        let futs_iter = chunks.into_iter().map(|(range, chunk)| {
+            let storage = storage.clone();
+            async move {
+                tokio::spawn(async move {
+                    let mut blobs = Self::read_state_value(&storage, chunk.blobs.clone()).await?;
+                    blobs.retain(|(key, _value)| range_of(&CryptoHash::hash(key)) == range);
+                    if validate_modules {
+                        blobs = tokio::task::spawn_blocking(move || {
+                            Self::validate_modules(&blobs);
+                            blobs
+                        })
+                        .await?;
+                    }
+                    Result::<_>::Ok((range, chunk, blobs))
+                })
+                .await?
+            }
+        });
+        let con = self.concurrent_downloads;
+        let mut futs_stream = stream::iter(futs_iter).buffered_x(con * 2, con);
+
+        // The chunks of a range are added in order, waiting for the previous one of the range to
+        // be added before adding the next.
+        let mut pending: Vec<Option<JoinHandle<Result<usize>>>> =
+            (0..NUM_RANGES).map(|_| None).collect();
+        let start = Instant::now();
+        let mut values_added = 0;
+        let mut chunks_added = 0;
+        let mut on_chunk_added = |num_values: usize| {
+            values_added += num_values;
+            chunks_added += 1;
+            bar.inc(num_values as u64);
+            leaf_idx.set((values_skipped + values_added) as i64);
+            let values_per_second = values_added as f64 / start.elapsed().as_secs_f64();
+            info!(
+                chunks_added = chunks_added,
+                chunks_to_add = chunks_to_add,
+                values_per_second = values_per_second as u64,
+                eta_secs = (total_values.saturating_sub(values_skipped + values_added) as f64
+                    / values_per_second) as u64,
+                "State chunk added.",
+            );
+        };
+        while let Some((range, chunk, blobs)) = futs_stream.try_next().await? {
+            if let Some(handle) = pending[range].take() {
+                on_chunk_added(handle.await??);
+            }
+            let range_restore = range_restore.clone();
+            pending[range] = Some(tokio::task::spawn_blocking(move || {
+                let _timer = OTHER_TIMERS_SECONDS
+                    .with_label_values(&["add_state_chunk"])
+                    .start_timer();
+                let num_values = blobs.len();
+                range_restore.add_chunk(range, blobs)?;
+                debug!(
+                    range = range,
+                    last_idx = chunk.last_idx,
+                    "State chunk added to range."
+                );
+                Ok(num_values)
+            }));
+        }
+        for handle in pending.into_iter().flatten() {
+            on_chunk_added(handle.await??);
+        }
+        bar.finish();
+
+        let range_restore = Arc::try_unwrap(range_restore)
+            .map_err(|_| anyhow!("State snapshot restore is still in use."))?;
+        tokio::task::spawn_blocking(move || range_restore.finish()).await??;
         Ok(())
     }
 
@@ -88,6 +88,7 @@ fn end_to_end() {
                 rocksdb_opt: RocksdbOpt::default(),
                 concurrent_downloads: ConcurrentDownloadsOpt::default(),
                 replay_concurrency_level: ReplayConcurrencyLevelOpt::default(),
+                state_snapshot_restore_in_ranges: true,
             }
             .try_into()
             .unwrap(),
@@ -0,0 +1,423 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Audits the backups against a live DB, proving the backups hold what the node committed.
+
+use crate::{
+    backup_types::{
+        epoch_ending::manifest::EpochEndingBackup, state_delta::manifest::StateDeltaBackup,
+        state_snapshot::manifest::StateSnapshotBackup, transaction::manifest::TransactionBackup,
+    },
+    metadata,
+    metadata::cache::MetadataCacheOpt,
+    storage::BackupStorage,
+    utils::{read_record_bytes::ReadRecordBytes, storage_ext::BackupStorageExt},
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use aptos_crypto::HashValue;
+use aptos_logger::prelude::*;
+use aptos_storage_interface::DbReader;
+use aptos_types::{
+    contract_event::ContractEvent,
+    ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+    transaction::{Transaction, TransactionInfo, Version},
+    write_set::WriteSet,
+};
+use std::{cmp::min, fmt, sync::Arc};
+use tokio::io::BufReader;
+
+/// The first place where the backup disagrees with the DB.
+#[derive(Debug)]
+pub enum Divergence {
+    EpochEnding {
+        epoch: u64,
+        backup: Box<LedgerInfo>,
+        db: Box<LedgerInfo>,
+    },
+    TransactionInfo {
+        version: Version,
+        backup: Box<TransactionInfo>,
+        db: Box<TransactionInfo>,
+    },
+    StateRootHash {
+        version: Version,
+        manifest: String,
+        backup: HashValue,
+        db: Option<HashValue>,
+    },
+}
+
+impl Divergence {
+    pub fn version(&self) -> Version {
+        match self {
+            Divergence::EpochEnding { backup, .. } => backup.version(),
+            Divergence::TransactionInfo { version, .. }
+            | Divergence::StateRootHash { version, .. } => *version,
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::EpochEnding { epoch, backup, db } => write!(
+                f,
+                "Epoch ending ledger info of epoch {} diverges. backup: {}, db: {}",
+                epoch, backup, db,
+            ),
+            Divergence::TransactionInfo {
+                version,
+                backup,
+                db,
+            } => write!(
+                f,
+                "Transaction info at version {} diverges. backup: {}, db: {}",
+                version, backup, db,
+            ),
+            Divergence::StateRootHash {
+                version,
+                manifest,
+                backup,
+                db,
+            } => write!(
+                f,
+                "State root hash at version {} diverges. manifest: {}, backup: {}, db: {:?}",
+                version, manifest, backup, db,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    pub epoch_endings_checked: usize,
+    pub transactions_checked: usize,
+    pub states_checked: usize,
+    pub first_divergence: Option<Divergence>,
+}
+
+impl AuditReport {
+    fn add_divergence(&mut self, divergence: Option<Divergence>) {
+        if let Some(divergence) = divergence {
+            if self
+                .first_divergence
+                .as_ref()
+                .map_or(true, |first| divergence.version() < first.version())
+            {
+                self.first_divergence = Some(divergence);
+            }
+        }
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Checked {} epoch ending ledger infos, {} transaction infos and {} state root hashes.",
+            self.epoch_endings_checked, self.transactions_checked, self.states_checked,
+        )?;
+        match &self.first_divergence {
+            Some(divergence) => write!(f, "First divergence: {}", divergence),
+            None => write!(f, "No divergence found."),
+        }
+    }
+}
+
+pub struct AuditCoordinator {
+    storage: Arc<dyn BackupStorage>,
+    metadata_cache_opt: MetadataCacheOpt,
+    concurrent_downloads: usize,
+    db: Arc<dyn DbReader>,
+    start_version: Version,
+    end_version: Version,
+}
+
+impl AuditCoordinator {
+    pub fn new(
+        storage: Arc<dyn BackupStorage>,
+        metadata_cache_opt: MetadataCacheOpt,
+        concurrent_downloads: usize,
+        db: Arc<dyn DbReader>,
+        start_version: Version,
+        end_version: Version,
+    ) -> Result<Self> {
+        ensure!(
+            start_version <= end_version,
+            "Bad version range: [{}, {}]",
+            start_version,
+            end_version,
+        );
+        Ok(Self {
+            storage,
+            metadata_cache_opt,
+            concurrent_downloads,
+            db,
+            start_version,
+            end_version,
+        })
+    }
+
+    pub async fn run(self) -> Result<AuditReport> {
+        info!(
+            start_version = self.start_version,
+            end_version = self.end_version,
+            "Backup audit started."
+        );
+        let ret = self.run_impl().await.context("Backup audit failed.")?;
+        info!(
+            first_divergence = ret.first_divergence.as_ref().map(|d| d.version()),
+            "Backup audit finished."
+        );
+        Ok(ret)
+    }
+
+    async fn run_impl(self) -> Result<AuditReport> {
+        let metadata_view = metadata::cache::sync_and_load(
+            &self.metadata_cache_opt,
+            Arc::clone(&self.storage),
+            self.concurrent_downloads,
+        )
+        .await?;
+        // Only what both the backup and the DB have can be compared.
+        let db_latest_version = self.db.get_latest_version()?;
+        let end_version = min(self.end_version, db_latest_version);
+        ensure!(
+            self.start_version <= end_version,
+            "DB is at version {}, before the start version {}. Nothing to audit.",
+            db_latest_version,
+            self.start_version,
+        );
+        let mut report = AuditReport::default();
+
+        for backup in metadata_view.epoch_ending_backups() {
+            if backup.last_version < self.start_version || backup.first_version > end_version {
+                continue;
+            }
+            let divergence = self
+                .audit_epoch_endings(&backup.manifest, end_version, &mut report)
+                .await?;
+            report.add_divergence(divergence);
+        }
+
+        for backup in metadata_view.transaction_backups() {
+            if backup.last_version < self.start_version || backup.first_version > end_version {
+                continue;
+            }
+            let divergence = self
+                .audit_transactions(&backup.manifest, end_version, &mut report)
+                .await?;
+            report.add_divergence(divergence);
+        }
+
+        for backup in metadata_view.state_snapshot_backups() {
+            if backup.version < self.start_version || backup.version > end_version {
+                continue;
+            }
+            let manifest: StateSnapshotBackup =
+                self.storage.load_json_file(&backup.manifest).await?;
+            let divergence =
+                self.audit_state_root(manifest.version, manifest.root_hash, &backup.manifest)?;
+            report.states_checked += 1;
+            report.add_divergence(divergence);
+        }
+
+        for backup in metadata_view.state_delta_backups() {
+            if backup.version < self.start_version || backup.version > end_version {
+                continue;
+            }
+            let manifest: StateDeltaBackup = self.storage.load_json_file(&backup.manifest).await?;
+            let divergence =
+                self.audit_state_root(manifest.version, manifest.root_hash, &backup.manifest)?;
+            report.states_checked += 1;
+            report.add_divergence(divergence);
+        }
+
+        Ok(report)
+    }
+
+    /// Returns the first epoch whose ending ledger info in the backup differs from the DB.
+    /// Signatures are not compared, since a different quorum can sign the same ledger info.
+    async fn audit_epoch_endings(
+        &self,
+        manifest_handle: &str,
+        end_version: Version,
+        report: &mut AuditReport,
+    ) -> Result<Option<Divergence>> {
+        let manifest: EpochEndingBackup = self.storage.load_json_file(manifest_handle).await?;
+        manifest.verify()?;
+
+        for chunk in manifest.chunks {
+            let mut file = self.storage.open_for_read(&chunk.ledger_infos).await?;
+            while let Some(record_bytes) = file.read_record_bytes().await? {
+                let backup = bcs::from_bytes::<LedgerInfoWithSignatures>(&record_bytes)?
+                    .ledger_info()
+                    .clone();
+                let version = backup.version();
+                if version < self.start_version {
+                    continue;
+                }
+                if version > end_version {
+                    return Ok(None);
+                }
+                let db = self
+                    .db
+                    .get_epoch_ending_ledger_info(version)?
+                    .ledger_info()
+                    .clone();
+                report.epoch_endings_checked += 1;
+                if backup != db {
+                    return Ok(Some(Divergence::EpochEnding {
+                        epoch: backup.epoch(),
+                        backup: Box::new(backup),
+                        db: Box::new(db),
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the first version whose transaction info in the backup differs from the DB.
+    async fn audit_transactions(
+        &self,
+        manifest_handle: &str,
+        end_version: Version,
+        report: &mut AuditReport,
+    ) -> Result<Option<Divergence>> {
+        let manifest: TransactionBackup = self.storage.load_json_file(manifest_handle).await?;
+        manifest.verify()?;
+
+        for chunk in manifest.chunks {
+            if chunk.last_version < self.start_version {
+                continue;
+            }
+            if chunk.first_version > end_version {
+                break;
+            }
+            let mut backup_txn_infos = Vec::new();
+            let mut file = BufReader::new(self.storage.open_for_read(&chunk.transactions).await?);
+            while let Some(record_bytes) = file.read_record_bytes().await? {
+                let (_, txn_info, _, _): (
+                    Transaction,
+                    TransactionInfo,
+                    Vec<ContractEvent>,
+                    WriteSet,
+                ) = bcs::from_bytes(&record_bytes)?;
+                backup_txn_infos.push(txn_info);
+            }
+            ensure!(
+                chunk.first_version + backup_txn_infos.len() as Version == chunk.last_version + 1,
+                "Number of items in chunk doesn't match that in manifest. chunk: {}",
+                chunk.transactions,
+            );
+
+            let first_version = chunk.first_version.max(self.start_version);
+            let last_version = chunk.last_version.min(end_version);
+            let num_txns = last_version - first_version + 1;
+            let db_txn_infos = self
+                .db
+                .get_transaction_info_iterator(first_version, num_txns)?
+                .collect::<Result<Vec<_>>>()?;
+            let backup_txn_infos = backup_txn_infos
+                .into_iter()
+                .skip((first_version - chunk.first_version) as usize)
+                .take(num_txns as usize);
+
+            report.transactions_checked += num_txns as usize;
+            if let Some(divergence) =
+                first_txn_info_divergence(first_version, backup_txn_infos, db_txn_infos)
+            {
+                return Ok(Some(divergence));
+            }
+        }
+        Ok(None)
+    }
+
+    fn audit_state_root(
+        &self,
+        version: Version,
+        backup: HashValue,
+        manifest_handle: &str,
+    ) -> Result<Option<Divergence>> {
+        let db = self
+            .db
+            .get_transaction_info_iterator(version, 1)?
+            .next()
+            .ok_or_else(|| anyhow!("Transaction info at version {} not found in DB.", version))??
+            .state_checkpoint_hash();
+        Ok((db != Some(backup)).then(|| Divergence::StateRootHash {
+            version,
+            manifest: manifest_handle.to_string(),
+            backup,
+            db,
+        }))
+    }
+}
+
+fn first_txn_info_divergence(
+    first_version: Version,
+    backup_txn_infos: impl IntoIterator<Item = TransactionInfo>,
+    db_txn_infos: impl IntoIterator<Item = TransactionInfo>,
+) -> Option<Divergence> {
+    backup_txn_infos
+        .into_iter()
+        .zip(db_txn_infos)
+        .enumerate()
+        .find(|(_, (backup, db))| backup != db)
+        .map(|(idx, (backup, db))| Divergence::TransactionInfo {
+            version: first_version + idx as Version,
+            backup: Box::new(backup),
+            db: Box::new(db),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_types::transaction::ExecutionStatus;
+
+    fn txn_info(gas_used: u64) -> TransactionInfo {
+        TransactionInfo::new(
+            HashValue::zero(),
+            HashValue::zero(),
+            HashValue::zero(),
+            None,
+            gas_used,
+            ExecutionStatus::Success,
+        )
+    }
+
+    #[test]
+    fn test_first_txn_info_divergence() {
+        let backup = vec![txn_info(1), txn_info(2), txn_info(3), txn_info(4)];
+        assert!(first_txn_info_divergence(10, backup.clone(), backup.clone()).is_none());
+
+        let db = vec![txn_info(1), txn_info(2), txn_info(0), txn_info(0)];
+        let divergence = first_txn_info_divergence(10, backup, db).unwrap();
+        assert_eq!(divergence.version(), 12);
+        assert!(matches!(
+            divergence,
+            Divergence::TransactionInfo { backup, .. } if backup.gas_used() == 3
+        ));
+    }
+
+    #[test]
+    fn test_report_keeps_earliest_divergence() {
+        let state_divergence = |version| Divergence::StateRootHash {
+            version,
+            manifest: "manifest".to_string(),
+            backup: HashValue::zero(),
+            db: None,
+        };
+
+        let mut report = AuditReport::default();
+        report.add_divergence(None);
+        assert!(report.first_divergence.is_none());
+        report.add_divergence(Some(state_divergence(20)));
+        report.add_divergence(Some(state_divergence(30)));
+        report.add_divergence(Some(state_divergence(10)));
+        report.add_divergence(None);
+        assert_eq!(report.first_divergence.unwrap().version(), 10);
+    }
+}
@@ -338,6 +338,7 @@ pub struct BackupCompactor {
     metadata_cache_opt: MetadataCacheOpt,
     epoch_ending_file_compact_factor: usize,
     state_snapshot_file_compact_factor: usize,
+    state_delta_file_compact_factor: usize,
     transaction_file_compact_factor: usize,
     concurrent_downloads: usize,
     remove_compacted_files_after_secs: u64,
@@ -347,6 +348,7 @@ impl BackupCompactor {
     pub fn new(
         epoch_ending_file_compact_factor: usize,
         state_snapshot_file_compact_factor: usize,
+        state_delta_file_compact_factor: usize,
         transaction_file_compact_factor: usize,
         metadata_cache_opt: MetadataCacheOpt,
         storage: Arc<dyn BackupStorage>,
@@ -358,6 +360,7 @@ impl BackupCompactor {
             metadata_cache_opt,
             epoch_ending_file_compact_factor,
             state_snapshot_file_compact_factor,
+            state_delta_file_compact_factor,
             transaction_file_compact_factor,
             concurrent_downloads,
             remove_compacted_files_after_secs,
@@ -448,6 +451,15 @@ impl BackupCompactor {
                 .await?;
             new_files.insert(file_handle);
         }
+        for range in metaview.compact_state_delta_backups(self.state_delta_file_compact_factor)? {
+            let (delta_range, file_name) =
+                Metadata::compact_state_delta_backup_range(range.to_vec())?;
+            let file_handle = self
+                .storage
+                .save_metadata_lines(&file_name, delta_range.as_slice())
+                .await?;
+            new_files.insert(file_handle);
+        }
 
         // Move expired files to the metadata backup folder
         let (to_move, compaction_meta) =
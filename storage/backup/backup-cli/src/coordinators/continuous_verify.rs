@@ -0,0 +1,558 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        epoch_ending::restore::{EpochHistory, EpochHistoryRestoreController},
+        state_delta::restore::{StateDeltaRestoreController, StateDeltaRestoreOpt},
+        state_snapshot::restore::{StateSnapshotRestoreController, StateSnapshotRestoreOpt},
+        transaction::restore::TransactionRestoreBatchController,
+    },
+    metadata,
+    metadata::{
+        cache::MetadataCacheOpt,
+        view::{MetadataView, StateSnapshotWithDeltas},
+        TransactionBackupMeta,
+    },
+    metrics::verify::{
+        CONTINUOUS_VERIFY_COVERAGE_GAPS, CONTINUOUS_VERIFY_FAILED,
+        CONTINUOUS_VERIFY_NEVER_VERIFIED, CONTINUOUS_VERIFY_ROUND, CONTINUOUS_VERIFY_ROUND_TS,
+        CONTINUOUS_VERIFY_SUCCEEDED,
+    },
+    storage::{BackupStorage, FileHandle},
+    utils::{
+        unix_timestamp_sec, ConcurrentDownloadsOpt, GlobalRestoreOptions, RestoreRunMode,
+        TrustedWaypointOpt,
+    },
+};
+use anyhow::{ensure, Result};
+use aptos_db::state_restore::StateSnapshotRestoreMode;
+use aptos_executor_types::VerifyExecutionMode;
+use aptos_logger::prelude::*;
+use aptos_types::transaction::Version;
+use clap::Parser;
+use rand::seq::SliceRandom;
+use serde::Serialize;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use tokio::time::{interval, Duration};
+
+const EPOCH_ENDING: &str = "epoch_ending";
+const STATE: &str = "state";
+const TRANSACTION: &str = "transaction";
+
+#[derive(Parser)]
+pub struct ContinuousVerifyOpt {
+    #[clap(flatten)]
+    pub metadata_cache_opt: MetadataCacheOpt,
+    #[clap(flatten)]
+    pub trusted_waypoints_opt: TrustedWaypointOpt,
+    #[clap(flatten)]
+    pub concurrent_downloads: ConcurrentDownloadsOpt,
+    #[clap(
+        long,
+        default_value_t = 3600,
+        help = "Seconds between the starts of two verification rounds."
+    )]
+    pub verify_interval_secs: u64,
+    #[clap(
+        long,
+        default_value_t = 4,
+        help = "Number of transaction backups to verify in each round. Backups that have not been \
+        verified for the longest time are picked first."
+    )]
+    pub transaction_samples_per_round: usize,
+    #[clap(
+        long,
+        default_value_t = 1,
+        help = "Number of states (a state snapshot, or a state snapshot with state deltas on top \
+        of it) to verify in each round. States that have not been verified for the longest time \
+        are picked first."
+    )]
+    pub state_samples_per_round: usize,
+    #[clap(
+        long,
+        value_parser,
+        help = "If specified, the report of the latest round is written to this file, in JSON."
+    )]
+    pub report_path: Option<PathBuf>,
+}
+
+/// A range of versions (or epochs, for epoch ending backups) not covered by any backup.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct CoverageGap {
+    pub backup_type: &'static str,
+    pub first: u64,
+    pub last: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupVerifyResult {
+    pub backup_type: &'static str,
+    /// The manifest of the backup, or that of the last state delta if a state is verified.
+    pub manifest: FileHandle,
+    pub version: Version,
+    /// `None` if the verification succeeded.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyRoundReport {
+    pub round: u64,
+    pub started_at: i64,
+    pub finished_at: i64,
+    /// Set if the round didn't finish, in which case the rest of the report can be partial.
+    pub error: Option<String>,
+    pub coverage_gaps: Vec<CoverageGap>,
+    pub results: Vec<BackupVerifyResult>,
+}
+
+impl VerifyRoundReport {
+    pub fn num_failures(&self) -> usize {
+        self.results.iter().filter(|r| r.error.is_some()).count()
+    }
+}
+
+/// Keeps verifying the backups in a storage by restoring them in verify mode, a few at a time, so
+/// that a broken backup is found before a restore depends on it.
+///
+/// In each round, the epoch ending backups are verified in full, against the trusted waypoints,
+/// so that the rest can be verified against the epoch ending ledger infos. Samples of the
+/// transaction backups and states are then verified, picking those not verified for the longest
+/// time first, so that over a number of rounds all backups get verified.
+pub struct ContinuousVerifyCoordinator {
+    storage: Arc<dyn BackupStorage>,
+    metadata_cache_opt: MetadataCacheOpt,
+    trusted_waypoints_opt: TrustedWaypointOpt,
+    concurrent_downloads: usize,
+    verify_interval_secs: u64,
+    transaction_samples_per_round: usize,
+    state_samples_per_round: usize,
+    report_path: Option<PathBuf>,
+    /// The round each backup was last verified in, by manifest.
+    last_verified: HashMap<FileHandle, u64>,
+    round: u64,
+}
+
+impl ContinuousVerifyCoordinator {
+    pub fn new(opt: ContinuousVerifyOpt, storage: Arc<dyn BackupStorage>) -> Result<Self> {
+        ensure!(
+            opt.verify_interval_secs > 0,
+            "Verify interval must be greater than 0."
+        );
+        Ok(Self {
+            storage,
+            metadata_cache_opt: opt.metadata_cache_opt,
+            trusted_waypoints_opt: opt.trusted_waypoints_opt,
+            concurrent_downloads: opt.concurrent_downloads.get(),
+            verify_interval_secs: opt.verify_interval_secs,
+            transaction_samples_per_round: opt.transaction_samples_per_round,
+            state_samples_per_round: opt.state_samples_per_round,
+            report_path: opt.report_path,
+            last_verified: HashMap::new(),
+            round: 0,
+        })
+    }
+
+    pub async fn run(mut self) -> Result<()> {
+        info!("Continuous verify coordinator started.");
+        let mut interval = interval(Duration::from_secs(self.verify_interval_secs));
+        loop {
+            interval.tick().await;
+            let report = self.run_round().await;
+            if let Err(e) = self.write_report(&report).await {
+                error!(error = ?e, "Failed to write verify report.");
+            }
+        }
+    }
+
+    /// Runs one round of verification, never fails but records errors in the report.
+    pub async fn run_round(&mut self) -> VerifyRoundReport {
+        self.round += 1;
+        info!(round = self.round, "Verify round started.");
+        let mut report = VerifyRoundReport {
+            round: self.round,
+            started_at: unix_timestamp_sec(),
+            ..Default::default()
+        };
+
+        if let Err(e) = self.run_round_impl(&mut report).await {
+            error!(round = self.round, error = ?e, "Verify round aborted.");
+            report.error = Some(format!("{:#}", e));
+        }
+        report.finished_at = unix_timestamp_sec();
+
+        for result in &report.results {
+            if result.error.is_some() {
+                CONTINUOUS_VERIFY_FAILED
+                    .with_label_values(&[result.backup_type])
+                    .inc();
+            } else {
+                CONTINUOUS_VERIFY_SUCCEEDED
+                    .with_label_values(&[result.backup_type])
+                    .inc();
+            }
+        }
+        CONTINUOUS_VERIFY_ROUND.set(self.round as i64);
+        CONTINUOUS_VERIFY_ROUND_TS.set(report.finished_at);
+        info!(
+            round = self.round,
+            coverage_gaps = report.coverage_gaps.len(),
+            verified = report.results.len(),
+            failed = report.num_failures(),
+            "Verify round finished."
+        );
+        report
+    }
+
+    async fn run_round_impl(&mut self, report: &mut VerifyRoundReport) -> Result<()> {
+        let metadata_view = metadata::cache::sync_and_load(
+            &self.metadata_cache_opt,
+            Arc::clone(&self.storage),
+            self.concurrent_downloads,
+        )
+        .await?;
+
+        report.coverage_gaps = find_coverage_gaps(&metadata_view);
+        for backup_type in [EPOCH_ENDING, TRANSACTION] {
+            CONTINUOUS_VERIFY_COVERAGE_GAPS
+                .with_label_values(&[backup_type])
+                .set(
+                    report
+                        .coverage_gaps
+                        .iter()
+                        .filter(|gap| gap.backup_type == backup_type)
+                        .count() as i64,
+                );
+        }
+        for gap in &report.coverage_gaps {
+            warn!(gap = ?gap, "Backups not covering a range.");
+        }
+
+        let global_opt = GlobalRestoreOptions {
+            target_version: Version::MAX,
+            trusted_waypoints: Arc::new(self.trusted_waypoints_opt.clone().verify()?),
+            run_mode: Arc::new(RestoreRunMode::Verify),
+            concurrent_downloads: self.concurrent_downloads,
+            replay_concurrency_level: 0, // won't replay, doesn't matter
+            state_snapshot_restore_in_ranges: false,
+        };
+
+        // Without a verified epoch history, there's nothing to verify the rest against.
+        let epoch_endings = metadata_view.select_epoch_ending_backups(Version::MAX)?;
+        let last_epoch_ending = epoch_endings.last().cloned();
+        let epoch_history = EpochHistoryRestoreController::new(
+            epoch_endings.into_iter().map(|b| b.manifest).collect(),
+            global_opt.clone(),
+            Arc::clone(&self.storage),
+        )
+        .run()
+        .await;
+        if let Some(last) = last_epoch_ending {
+            report.results.push(BackupVerifyResult {
+                backup_type: EPOCH_ENDING,
+                manifest: last.manifest,
+                version: last.last_version,
+                error: epoch_history.as_ref().err().map(|e| format!("{:#}", e)),
+            });
+        }
+        let epoch_history = Some(Arc::new(epoch_history?));
+
+        let transactions = pick_least_recently_verified(
+            metadata_view.transaction_backups().to_vec(),
+            |b| &b.manifest,
+            &self.last_verified,
+            self.transaction_samples_per_round,
+        );
+        CONTINUOUS_VERIFY_NEVER_VERIFIED
+            .with_label_values(&[TRANSACTION])
+            .set(transactions.never_verified as i64);
+        for backup in transactions.picked {
+            let res = self
+                .verify_transactions(&backup, global_opt.clone(), epoch_history.clone())
+                .await;
+            self.record(
+                report,
+                TRANSACTION,
+                backup.manifest,
+                backup.last_version,
+                res,
+            );
+        }
+
+        let states = pick_least_recently_verified(
+            metadata_view.states(),
+            last_manifest,
+            &self.last_verified,
+            self.state_samples_per_round,
+        );
+        CONTINUOUS_VERIFY_NEVER_VERIFIED
+            .with_label_values(&[STATE])
+            .set(states.never_verified as i64);
+        for state in states.picked {
+            let res = self
+                .verify_state(&state, global_opt.clone(), epoch_history.clone())
+                .await;
+            self.record(
+                report,
+                STATE,
+                last_manifest(&state).clone(),
+                state.version(),
+                res,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn record(
+        &mut self,
+        report: &mut VerifyRoundReport,
+        backup_type: &'static str,
+        manifest: FileHandle,
+        version: Version,
+        res: Result<()>,
+    ) {
+        if let Err(e) = &res {
+            error!(
+                backup_type = backup_type,
+                manifest = manifest,
+                error = ?e,
+                "Backup failed verification."
+            );
+        }
+        // Failed ones are put to the back of the queue as well, otherwise a broken backup blocks
+        // the verification of all the others. It's reported already anyway.
+        self.last_verified.insert(manifest.clone(), self.round);
+        report.results.push(BackupVerifyResult {
+            backup_type,
+            manifest,
+            version,
+            error: res.err().map(|e| format!("{:#}", e)),
+        });
+    }
+
+    async fn verify_transactions(
+        &self,
+        backup: &TransactionBackupMeta,
+        global_opt: GlobalRestoreOptions,
+        epoch_history: Option<Arc<EpochHistory>>,
+    ) -> Result<()> {
+        TransactionRestoreBatchController::new(
+            global_opt,
+            Arc::clone(&self.storage),
+            vec![backup.manifest.clone()],
+            None,
+            None, /* replay_from_version */
+            epoch_history,
+            VerifyExecutionMode::NoVerify,
+            None, /* output_transaction_analysis */
+        )
+        .run()
+        .await
+    }
+
+    async fn verify_state(
+        &self,
+        state: &StateSnapshotWithDeltas,
+        global_opt: GlobalRestoreOptions,
+        epoch_history: Option<Arc<EpochHistory>>,
+    ) -> Result<()> {
+        if state.deltas.is_empty() {
+            StateSnapshotRestoreController::new(
+                StateSnapshotRestoreOpt {
+                    manifest_handle: state.snapshot.manifest.clone(),
+                    version: state.snapshot.version,
+                    validate_modules: false,
+                    restore_mode: StateSnapshotRestoreMode::Default,
+                },
+                global_opt,
+                Arc::clone(&self.storage),
+                epoch_history,
+            )
+            .run()
+            .await
+        } else {
+            StateDeltaRestoreController::new(
+                StateDeltaRestoreOpt {
+                    snapshot_manifest_handle: state.snapshot.manifest.clone(),
+                    delta_manifest_handles: state
+                        .deltas
+                        .iter()
+                        .map(|d| d.manifest.clone())
+                        .collect(),
+                    version: state.version(),
+                    restore_mode: StateSnapshotRestoreMode::Default,
+                },
+                global_opt,
+                Arc::clone(&self.storage),
+                epoch_history,
+            )
+            .run()
+            .await
+        }
+    }
+
+    async fn write_report(&self, report: &VerifyRoundReport) -> Result<()> {
+        if let Some(path) = &self.report_path {
+            // Write to a temp file and rename, so that a reader never sees a partial report.
+            let tmp_path = path.with_extension("tmp");
+            tokio::fs::write(&tmp_path, serde_json::to_vec_pretty(report)?).await?;
+            tokio::fs::rename(&tmp_path, path).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds ranges not covered by the backups. Epoch ending backups are expected to cover all epochs
+/// from 0, while transaction backups are expected to be continuous from the first one, since
+/// older ones can have been pruned.
+pub(crate) fn find_coverage_gaps(view: &MetadataView) -> Vec<CoverageGap> {
+    let mut gaps = Vec::new();
+    find_gaps(
+        EPOCH_ENDING,
+        Some(0),
+        view.epoch_ending_backups()
+            .iter()
+            .map(|b| (b.first_epoch, b.last_epoch)),
+        &mut gaps,
+    );
+    find_gaps(
+        TRANSACTION,
+        None,
+        view.transaction_backups()
+            .iter()
+            .map(|b| (b.first_version, b.last_version)),
+        &mut gaps,
+    );
+    gaps
+}
+
+/// `ranges` are expected to be sorted.
+fn find_gaps(
+    backup_type: &'static str,
+    start: Option<u64>,
+    ranges: impl Iterator<Item = (u64, u64)>,
+    gaps: &mut Vec<CoverageGap>,
+) {
+    let mut next = start;
+    for (first, last) in ranges {
+        if let Some(next) = next {
+            if first > next {
+                gaps.push(CoverageGap {
+                    backup_type,
+                    first: next,
+                    last: first - 1,
+                });
+            }
+        }
+        next = Some(next.map_or(last + 1, |next| next.max(last + 1)));
+    }
+}
+
+fn last_manifest(state: &StateSnapshotWithDeltas) -> &FileHandle {
+    state
+        .deltas
+        .last()
+        .map_or(&state.snapshot.manifest, |d| &d.manifest)
+}
+
+struct Picked<T> {
+    picked: Vec<T>,
+    never_verified: usize,
+}
+
+/// Picks `n` of the candidates, the never verified ones first and then the ones verified the
+/// longest time ago, randomly among those verified at the same time.
+fn pick_least_recently_verified<T>(
+    mut candidates: Vec<T>,
+    manifest: impl Fn(&T) -> &FileHandle,
+    last_verified: &HashMap<FileHandle, u64>,
+    n: usize,
+) -> Picked<T> {
+    candidates.sort_by(|a, b| manifest(a).cmp(manifest(b)));
+    candidates.dedup_by(|a, b| manifest(a) == manifest(b));
+    candidates.shuffle(&mut rand::thread_rng());
+    // `None` sorts first.
+    candidates.sort_by_key(|c| last_verified.get(manifest(c)).cloned());
+    let never_verified = candidates
+        .iter()
+        .filter(|c| !last_verified.contains_key(manifest(c)))
+        .count();
+    candidates.truncate(n);
+    Picked {
+        picked: candidates,
+        never_verified,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Metadata;
+
+    #[test]
+    fn test_find_coverage_gaps() {
+        let view = MetadataView::new(
+            vec![
+                Metadata::new_epoch_ending_backup(0, 9, 0, 99, "e0".to_string()),
+                Metadata::new_epoch_ending_backup(5, 12, 50, 120, "e1".to_string()),
+                Metadata::new_epoch_ending_backup(15, 20, 150, 200, "e2".to_string()),
+                Metadata::new_transaction_backup(100, 199, "t0".to_string()),
+                Metadata::new_transaction_backup(200, 299, "t1".to_string()),
+                Metadata::new_transaction_backup(400, 499, "t2".to_string()),
+            ],
+            Vec::new(),
+        );
+        assert_eq!(find_coverage_gaps(&view), vec![
+            CoverageGap {
+                backup_type: EPOCH_ENDING,
+                first: 13,
+                last: 14,
+            },
+            CoverageGap {
+                backup_type: TRANSACTION,
+                first: 300,
+                last: 399,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_epoch_endings_not_starting_at_genesis() {
+        let view = MetadataView::new(
+            vec![Metadata::new_epoch_ending_backup(
+                3,
+                9,
+                30,
+                99,
+                "e0".to_string(),
+            )],
+            Vec::new(),
+        );
+        assert_eq!(find_coverage_gaps(&view), vec![CoverageGap {
+            backup_type: EPOCH_ENDING,
+            first: 0,
+            last: 2,
+        }]);
+    }
+
+    #[test]
+    fn test_pick_least_recently_verified() {
+        let candidates: Vec<FileHandle> = (0..5).map(|i| format!("m{}", i)).collect();
+        let mut last_verified = HashMap::new();
+        last_verified.insert("m0".to_string(), 2);
+        last_verified.insert("m1".to_string(), 1);
+        last_verified.insert("m3".to_string(), 3);
+
+        let picked = pick_least_recently_verified(candidates.clone(), |m| m, &last_verified, 3);
+        assert_eq!(picked.never_verified, 2);
+        let mut first_two = picked.picked[..2].to_vec();
+        first_two.sort();
+        assert_eq!(first_two, vec!["m2".to_string(), "m4".to_string()]);
+        assert_eq!(picked.picked[2], "m1");
+
+        let picked = pick_least_recently_verified(candidates, |m| m, &last_verified, 10);
+        assert_eq!(picked.picked.len(), 5);
+        assert_eq!(picked.picked[4], "m3");
+    }
+}
@@ -2,7 +2,11 @@
 // Parts of the project are originally copyright © Meta Platforms, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod audit;
 pub mod backup;
+pub mod continuous_verify;
+pub mod point_in_time;
 pub mod replay_verify;
 pub mod restore;
+pub mod retention;
 pub mod verify;
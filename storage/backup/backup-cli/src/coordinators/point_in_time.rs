@@ -0,0 +1,705 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        epoch_ending::restore::{EpochHistory, EpochHistoryRestoreController},
+        state_delta::{
+            manifest::StateDeltaBackup,
+            restore::{StateDeltaRestoreController, StateDeltaRestoreOpt},
+        },
+        state_snapshot::{
+            manifest::StateSnapshotBackup,
+            restore::{StateSnapshotRestoreController, StateSnapshotRestoreOpt},
+        },
+        transaction::{manifest::TransactionBackup, restore::TransactionRestoreBatchController},
+    },
+    metadata,
+    metadata::{
+        cache::MetadataCacheOpt,
+        view::{MetadataView, StateSnapshotWithDeltas},
+        TransactionBackupMeta,
+    },
+    metrics::restore::{
+        COORDINATOR_FAIL_TS, COORDINATOR_START_TS, COORDINATOR_SUCC_TS, COORDINATOR_TARGET_VERSION,
+    },
+    storage::{BackupStorage, FileHandle},
+    utils::{
+        read_record_bytes::ReadRecordBytes, storage_ext::BackupStorageExt, unix_timestamp_sec,
+        GlobalRestoreOptions, RestoreRunMode,
+    },
+};
+use anyhow::{anyhow, ensure, Result};
+use aptos_db::state_restore::StateSnapshotRestoreMode;
+use aptos_executor_types::VerifyExecutionMode;
+use aptos_logger::prelude::*;
+use aptos_types::{
+    contract_event::ContractEvent,
+    transaction::{Transaction, TransactionInfo, Version},
+    write_set::WriteSet,
+};
+use clap::Parser;
+use futures::{StreamExt, TryStreamExt};
+use std::{cmp::min, collections::HashMap, fmt, sync::Arc};
+use tokio::io::BufReader;
+
+/// Only this many of the latest states before the target version are considered, older states
+/// mean replaying more transactions and are very unlikely to be cheaper.
+const MAX_STATE_CANDIDATES: usize = 4;
+/// Replaying a transaction costs roughly this many times as much as restoring a state item or
+/// applying (saving without executing) a transaction.
+const REPLAY_COST_FACTOR: u64 = 10;
+
+#[derive(Parser)]
+pub struct PointInTimeRestoreOpt {
+    #[clap(flatten)]
+    pub metadata_cache_opt: MetadataCacheOpt,
+    #[clap(
+        long,
+        value_parser = parse_timestamp_usecs,
+        conflicts_with = "target_version",
+        help = "Restore the DB to the last version committed at or before this point in time, \
+        either a unix timestamp in seconds or an RFC 3339 date and time, \
+        e.g. 2023-05-01T00:00:00Z."
+    )]
+    pub target_timestamp: Option<u64>,
+}
+
+/// Cost of a restore plan, in items to download and write.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RestoreEstimate {
+    pub num_files: usize,
+    pub num_state_items: u64,
+    pub num_state_delta_records: u64,
+    /// Transactions saved without being executed, those before the state version.
+    pub num_transactions_to_apply: u64,
+    pub num_transactions_to_replay: u64,
+    /// Bytes to download, only filled in for the selected plan. `None` if the storage can't tell
+    /// the size of some of the files.
+    pub num_bytes: Option<u64>,
+}
+
+impl RestoreEstimate {
+    fn cost(&self) -> u64 {
+        self.num_state_items
+            + self.num_state_delta_records
+            + self.num_transactions_to_apply
+            + self.num_transactions_to_replay * REPLAY_COST_FACTOR
+    }
+}
+
+/// A restore to `target_version`: restore `state`, then apply the transactions up to the state
+/// version and replay the rest.
+#[derive(Clone, Debug)]
+pub struct RestorePlan {
+    pub target_version: Version,
+    pub state: StateSnapshotWithDeltas,
+    pub transactions: Vec<TransactionBackupMeta>,
+    pub estimate: RestoreEstimate,
+}
+
+impl RestorePlan {
+    fn first_transaction_version(&self) -> Version {
+        self.transactions
+            .first()
+            .map_or(self.state.version(), |t| t.first_version)
+    }
+
+    /// The cheapest among `plans`, the one with the latest state if the costs are the same.
+    fn cheapest(plans: Vec<Self>) -> Option<Self> {
+        plans
+            .into_iter()
+            .min_by_key(|p| (p.estimate.cost(), Version::MAX - p.state.version()))
+    }
+}
+
+impl fmt::Display for RestorePlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Restore plan to version {}:", self.target_version)?;
+        writeln!(
+            f,
+            "  State snapshot at version {}: {}",
+            self.state.snapshot.version, self.state.snapshot.manifest,
+        )?;
+        for delta in &self.state.deltas {
+            writeln!(
+                f,
+                "  State delta to version {}: {}",
+                delta.version, delta.manifest,
+            )?;
+        }
+        writeln!(
+            f,
+            "  Transactions applied in [{}, {}], replayed in [{}, {}], from {} backups.",
+            self.first_transaction_version(),
+            self.state.version(),
+            self.state.version() + 1,
+            self.target_version,
+            self.transactions.len(),
+        )?;
+        write!(
+            f,
+            "  Estimated: {} files, {} state items, {} state delta records, \
+            {} transactions to apply, {} transactions to replay.",
+            self.estimate.num_files,
+            self.estimate.num_state_items,
+            self.estimate.num_state_delta_records,
+            self.estimate.num_transactions_to_apply,
+            self.estimate.num_transactions_to_replay,
+        )?;
+        match self.estimate.num_bytes {
+            Some(num_bytes) => write!(
+                f,
+                "\n  Estimated download size: {:.2} GiB ({} bytes), not counting epoch endings.",
+                num_bytes as f64 / (1u64 << 30) as f64,
+                num_bytes,
+            ),
+            None => write!(
+                f,
+                "\n  Estimated download size: unknown, the backup storage doesn't report file sizes."
+            ),
+        }
+    }
+}
+
+/// Restores a DB from scratch to a target version or point in time, figuring out which backups to
+/// restore from the metadata.
+pub struct PointInTimeRestoreCoordinator {
+    storage: Arc<dyn BackupStorage>,
+    global_opt: GlobalRestoreOptions,
+    metadata_cache_opt: MetadataCacheOpt,
+    target_timestamp_usecs: Option<u64>,
+}
+
+impl PointInTimeRestoreCoordinator {
+    pub fn new(
+        opt: PointInTimeRestoreOpt,
+        global_opt: GlobalRestoreOptions,
+        storage: Arc<dyn BackupStorage>,
+    ) -> Self {
+        Self {
+            storage,
+            global_opt,
+            metadata_cache_opt: opt.metadata_cache_opt,
+            target_timestamp_usecs: opt.target_timestamp,
+        }
+    }
+
+    pub async fn run(self) -> Result<()> {
+        info!("Point-in-time restore coordinator started.");
+        COORDINATOR_START_TS.set(unix_timestamp_sec());
+
+        let ret = self.run_impl().await;
+
+        if let Err(e) = &ret {
+            error!(
+                error = ?e,
+                "Point-in-time restore coordinator failed."
+            );
+            COORDINATOR_FAIL_TS.set(unix_timestamp_sec());
+        } else {
+            info!("Point-in-time restore coordinator exiting with success.");
+            COORDINATOR_SUCC_TS.set(unix_timestamp_sec());
+        }
+
+        ret
+    }
+
+    async fn run_impl(self) -> Result<()> {
+        let db_next_version = self
+            .global_opt
+            .run_mode
+            .get_next_expected_transaction_version()?;
+        ensure!(
+            db_next_version == 0,
+            "Target DB is not empty, next version: {}. Point-in-time restore only restores a new \
+            DB, use bootstrap-db to resume an interrupted restore.",
+            db_next_version,
+        );
+
+        let metadata_view = metadata::cache::sync_and_load(
+            &self.metadata_cache_opt,
+            Arc::clone(&self.storage),
+            self.global_opt.concurrent_downloads,
+        )
+        .await?;
+        let max_txn_ver = metadata_view
+            .max_transaction_version()?
+            .ok_or_else(|| anyhow!("No transaction backup found."))?;
+        let target_version = match self.target_timestamp_usecs {
+            Some(timestamp_usecs) => {
+                self.resolve_timestamp(&metadata_view, timestamp_usecs, max_txn_ver)
+                    .await?
+            },
+            None => min(self.global_opt.target_version, max_txn_ver),
+        };
+        info!(target_version = target_version, "Target version decided.");
+
+        let plan = RestorePlanner::new(
+            &metadata_view,
+            Arc::clone(&self.storage),
+            self.global_opt.concurrent_downloads,
+        )
+        .plan(target_version)
+        .await?;
+        info!("Restore plan:\n{}", plan);
+        if self.global_opt.run_mode.is_verify() {
+            info!("Dry run, the plan is not executed.");
+            return Ok(());
+        }
+
+        COORDINATOR_TARGET_VERSION.set(plan.target_version as i64);
+        self.execute(&metadata_view, plan).await
+    }
+
+    async fn execute(self, metadata_view: &MetadataView, plan: RestorePlan) -> Result<()> {
+        let mut global_opt = self.global_opt.clone();
+        global_opt.target_version = plan.target_version;
+        let state_version = plan.state.version();
+
+        let epoch_history = Some(Arc::new(
+            EpochHistoryRestoreController::new(
+                metadata_view
+                    .select_epoch_ending_backups(plan.target_version)?
+                    .into_iter()
+                    .map(|b| b.manifest)
+                    .collect(),
+                global_opt.clone(),
+                Arc::clone(&self.storage),
+            )
+            .run()
+            .await?,
+        ));
+
+        if plan.state.deltas.is_empty() {
+            StateSnapshotRestoreController::new(
+                StateSnapshotRestoreOpt {
+                    manifest_handle: plan.state.snapshot.manifest.clone(),
+                    version: state_version,
+                    validate_modules: false,
+                    restore_mode: StateSnapshotRestoreMode::Default,
+                },
+                global_opt.clone(),
+                Arc::clone(&self.storage),
+                epoch_history.clone(),
+            )
+            .run()
+            .await?;
+        } else {
+            StateDeltaRestoreController::new(
+                StateDeltaRestoreOpt {
+                    snapshot_manifest_handle: plan.state.snapshot.manifest.clone(),
+                    delta_manifest_handles: plan
+                        .state
+                        .deltas
+                        .iter()
+                        .map(|d| d.manifest.clone())
+                        .collect(),
+                    version: state_version,
+                    restore_mode: StateSnapshotRestoreMode::Default,
+                },
+                global_opt.clone(),
+                Arc::clone(&self.storage),
+                epoch_history.clone(),
+            )
+            .run()
+            .await?;
+        }
+
+        TransactionRestoreBatchController::new(
+            global_opt,
+            self.storage,
+            plan.transactions.into_iter().map(|t| t.manifest).collect(),
+            None,
+            Some((state_version + 1, false /* replay entire txn */)),
+            epoch_history,
+            VerifyExecutionMode::NoVerify,
+            None,
+        )
+        .run()
+        .await
+    }
+
+    /// Finds the last version committed at or before `timestamp_usecs`, i.e. the version right
+    /// before the first block with a later timestamp.
+    async fn resolve_timestamp(
+        &self,
+        metadata_view: &MetadataView,
+        timestamp_usecs: u64,
+        max_txn_ver: Version,
+    ) -> Result<Version> {
+        // The epoch endings narrow it down to an epoch, without writing anything to the DB.
+        let mut verify_opt = self.global_opt.clone();
+        verify_opt.run_mode = Arc::new(RestoreRunMode::Verify);
+        let epoch_history = EpochHistoryRestoreController::new(
+            metadata_view
+                .select_epoch_ending_backups(Version::MAX)?
+                .into_iter()
+                .map(|b| b.manifest)
+                .collect(),
+            verify_opt,
+            Arc::clone(&self.storage),
+        )
+        .run()
+        .await?;
+        let (first_version, last_version) =
+            epoch_range_at(&epoch_history, timestamp_usecs, max_txn_ver);
+        if first_version > last_version {
+            // Before genesis ended, or after the end of the backups.
+            return Ok(last_version);
+        }
+
+        for backup in metadata_view.select_transaction_backups(first_version, last_version)? {
+            let manifest: TransactionBackup = self.storage.load_json_file(&backup.manifest).await?;
+            for chunk in manifest.chunks {
+                if chunk.last_version < first_version {
+                    continue;
+                }
+                if chunk.first_version > last_version {
+                    break;
+                }
+                let mut file =
+                    BufReader::new(self.storage.open_for_read(&chunk.transactions).await?);
+                let mut version = chunk.first_version;
+                while let Some(record_bytes) = file.read_record_bytes().await? {
+                    let (txn, _, _, _): (
+                        Transaction,
+                        TransactionInfo,
+                        Vec<ContractEvent>,
+                        WriteSet,
+                    ) = bcs::from_bytes(&record_bytes)?;
+                    if version >= first_version {
+                        if let Some(block) = txn.try_as_block_metadata() {
+                            if block.timestamp_usecs() > timestamp_usecs {
+                                return Ok(version.saturating_sub(1));
+                            }
+                        }
+                    }
+                    version += 1;
+                }
+            }
+        }
+        Ok(last_version)
+    }
+}
+
+/// The versions in the epoch `timestamp_usecs` is in, or an empty range ending at the version
+/// to restore to if it's outside the epoch history.
+fn epoch_range_at(
+    epoch_history: &EpochHistory,
+    timestamp_usecs: u64,
+    max_txn_ver: Version,
+) -> (Version, Version) {
+    let mut first_version = 0;
+    for li in &epoch_history.epoch_endings {
+        if li.timestamp_usecs() > timestamp_usecs {
+            return (first_version, min(li.version(), max_txn_ver));
+        }
+        first_version = li.version() + 1;
+    }
+    if first_version > max_txn_ver {
+        (max_txn_ver + 1, max_txn_ver)
+    } else {
+        (first_version, max_txn_ver)
+    }
+}
+
+/// Figures out the cheapest way to restore to a version.
+pub struct RestorePlanner<'a> {
+    metadata_view: &'a MetadataView,
+    storage: Arc<dyn BackupStorage>,
+    concurrent_downloads: usize,
+    /// Number of files in a transaction backup, by manifest.
+    transaction_files: HashMap<FileHandle, usize>,
+}
+
+impl<'a> RestorePlanner<'a> {
+    pub fn new(
+        metadata_view: &'a MetadataView,
+        storage: Arc<dyn BackupStorage>,
+        concurrent_downloads: usize,
+    ) -> Self {
+        Self {
+            metadata_view,
+            storage,
+            concurrent_downloads,
+            transaction_files: HashMap::new(),
+        }
+    }
+
+    pub async fn plan(mut self, target_version: Version) -> Result<RestorePlan> {
+        let mut state_versions: Vec<_> = self
+            .metadata_view
+            .state_snapshot_backups()
+            .iter()
+            .map(|s| s.version)
+            .chain(
+                self.metadata_view
+                    .state_delta_backups()
+                    .iter()
+                    .map(|d| d.version),
+            )
+            .filter(|v| *v <= target_version)
+            .collect();
+        state_versions.sort_unstable();
+        state_versions.dedup();
+
+        let mut plans = Vec::new();
+        for version in state_versions.into_iter().rev() {
+            if plans.len() >= MAX_STATE_CANDIDATES {
+                break;
+            }
+            let state = match self
+                .metadata_view
+                .expect_state_snapshot_with_deltas(version)
+            {
+                Ok(state) => state,
+                Err(_) => continue,
+            };
+            let transactions = match self
+                .metadata_view
+                .select_transaction_backups(version, target_version)
+            {
+                Ok(transactions)
+                    if transactions.last().map(|t| t.last_version) >= Some(target_version) =>
+                {
+                    transactions
+                },
+                Ok(_) | Err(_) => {
+                    info!(
+                        version = version,
+                        "State skipped, transaction backups don't cover the target version from it."
+                    );
+                    continue;
+                },
+            };
+            let estimate = self.estimate(&state, &transactions, target_version).await?;
+            plans.push(RestorePlan {
+                target_version,
+                state,
+                transactions,
+                estimate,
+            });
+        }
+
+        let mut plan = RestorePlan::cheapest(plans).ok_or_else(|| {
+            anyhow!(
+                "No state with transaction backups up to version {} found.",
+                target_version
+            )
+        })?;
+        plan.estimate.num_bytes = self.num_bytes(&plan).await?;
+        Ok(plan)
+    }
+
+    /// Total size of the files `plan` downloads, other than the epoch endings.
+    async fn num_bytes(&self, plan: &RestorePlan) -> Result<Option<u64>> {
+        let mut files = Vec::new();
+        let snapshot: StateSnapshotBackup = self
+            .storage
+            .load_json_file(&plan.state.snapshot.manifest)
+            .await?;
+        files.push(plan.state.snapshot.manifest.clone());
+        files.push(snapshot.proof);
+        for chunk in snapshot.chunks {
+            files.push(chunk.blobs);
+            files.push(chunk.proof);
+        }
+        for delta in &plan.state.deltas {
+            let manifest: StateDeltaBackup = self.storage.load_json_file(&delta.manifest).await?;
+            files.push(delta.manifest.clone());
+            files.push(manifest.proof);
+            files.extend(manifest.chunks.into_iter().map(|c| c.records));
+        }
+        for backup in &plan.transactions {
+            let manifest: TransactionBackup = self.storage.load_json_file(&backup.manifest).await?;
+            files.push(backup.manifest.clone());
+            for chunk in manifest.chunks {
+                files.push(chunk.transactions);
+                files.push(chunk.proof);
+            }
+        }
+
+        let sizes: Vec<Option<u64>> = futures::stream::iter(files)
+            .map(|file| {
+                let storage = Arc::clone(&self.storage);
+                async move { storage.file_size(&file).await }
+            })
+            .buffer_unordered(self.concurrent_downloads)
+            .try_collect()
+            .await?;
+        Ok(sizes.into_iter().sum())
+    }
+
+    async fn estimate(
+        &mut self,
+        state: &StateSnapshotWithDeltas,
+        transactions: &[TransactionBackupMeta],
+        target_version: Version,
+    ) -> Result<RestoreEstimate> {
+        let mut estimate = RestoreEstimate::default();
+
+        let snapshot: StateSnapshotBackup = self
+            .storage
+            .load_json_file(&state.snapshot.manifest)
+            .await?;
+        estimate.num_files += snapshot.chunks.len() * 2 + 2;
+        estimate.num_state_items = snapshot.chunks.last().map_or(0, |c| c.last_idx as u64 + 1);
+        for delta in &state.deltas {
+            let manifest: StateDeltaBackup = self.storage.load_json_file(&delta.manifest).await?;
+            estimate.num_files += manifest.chunks.len() + 2;
+            estimate.num_state_delta_records += manifest
+                .chunks
+                .iter()
+                .map(|c| (c.last_idx - c.first_idx + 1) as u64)
+                .sum::<u64>();
+        }
+
+        for backup in transactions {
+            if !self.transaction_files.contains_key(&backup.manifest) {
+                let manifest: TransactionBackup =
+                    self.storage.load_json_file(&backup.manifest).await?;
+                self.transaction_files
+                    .insert(backup.manifest.clone(), manifest.chunks.len() * 2 + 1);
+            }
+            estimate.num_files += self.transaction_files[&backup.manifest];
+        }
+        let first_version = transactions
+            .first()
+            .map_or(state.version(), |t| t.first_version);
+        estimate.num_transactions_to_apply = state.version() + 1 - first_version;
+        estimate.num_transactions_to_replay = target_version - state.version();
+
+        Ok(estimate)
+    }
+}
+
+/// Parses a unix timestamp in seconds or an RFC 3339 date and time into microseconds since the
+/// unix epoch.
+pub fn parse_timestamp_usecs(s: &str) -> Result<u64> {
+    if let Ok(secs) = s.parse::<u64>() {
+        return secs
+            .checked_mul(1_000_000)
+            .ok_or_else(|| anyhow!("Timestamp too large: {}", s));
+    }
+    let dt = chrono::DateTime::parse_from_rfc3339(s)
+        .map_err(|e| anyhow!("Expecting unix timestamp or RFC 3339 date time: {}", e))?;
+    ensure!(
+        dt.timestamp() >= 0,
+        "Timestamp before the unix epoch: {}",
+        s
+    );
+    Ok(dt.timestamp() as u64 * 1_000_000 + dt.timestamp_subsec_micros() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{StateDeltaBackupMeta, StateSnapshotBackupMeta};
+
+    fn plan(
+        snapshot_version: Version,
+        num_deltas: usize,
+        estimate: RestoreEstimate,
+    ) -> RestorePlan {
+        RestorePlan {
+            target_version: 1000,
+            state: StateSnapshotWithDeltas {
+                snapshot: StateSnapshotBackupMeta {
+                    epoch: 0,
+                    version: snapshot_version,
+                    manifest: format!("snapshot_{}", snapshot_version),
+                },
+                deltas: (0..num_deltas)
+                    .map(|i| StateDeltaBackupMeta {
+                        version: snapshot_version + i as Version + 1,
+                        epoch: 0,
+                        base_version: snapshot_version + i as Version,
+                        base_epoch: 0,
+                        manifest: format!("delta_{}_{}", snapshot_version, i),
+                    })
+                    .collect(),
+            },
+            transactions: Vec::new(),
+            estimate,
+        }
+    }
+
+    #[test]
+    fn test_cheapest_plan() {
+        // A long replay from an old snapshot loses to a restoring a few more items.
+        let old_snapshot = plan(100, 0, RestoreEstimate {
+            num_state_items: 1000,
+            num_transactions_to_replay: 900,
+            ..Default::default()
+        });
+        let recent_state = plan(800, 1, RestoreEstimate {
+            num_state_items: 1000,
+            num_state_delta_records: 5000,
+            num_transactions_to_replay: 199,
+            ..Default::default()
+        });
+        let cheapest = RestorePlan::cheapest(vec![old_snapshot.clone(), recent_state]).unwrap();
+        assert_eq!(cheapest.state.version(), 801);
+
+        // But not to restoring too many more items.
+        let huge_delta = plan(800, 1, RestoreEstimate {
+            num_state_items: 1000,
+            num_state_delta_records: 50000,
+            num_transactions_to_replay: 199,
+            ..Default::default()
+        });
+        let cheapest = RestorePlan::cheapest(vec![old_snapshot, huge_delta]).unwrap();
+        assert_eq!(cheapest.state.version(), 100);
+
+        // Latest state wins a tie.
+        let a = plan(100, 0, RestoreEstimate {
+            num_state_items: 10,
+            ..Default::default()
+        });
+        let b = plan(200, 0, RestoreEstimate {
+            num_state_items: 10,
+            ..Default::default()
+        });
+        assert_eq!(
+            RestorePlan::cheapest(vec![a, b]).unwrap().state.version(),
+            200
+        );
+
+        assert!(RestorePlan::cheapest(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_plan_shows_download_size() {
+        let known = plan(100, 0, RestoreEstimate {
+            num_bytes: Some(3 << 29),
+            ..Default::default()
+        });
+        assert!(known
+            .to_string()
+            .contains("Estimated download size: 1.50 GiB (1610612736 bytes)"));
+
+        let unknown = plan(100, 0, RestoreEstimate::default());
+        assert!(unknown
+            .to_string()
+            .contains("Estimated download size: unknown"));
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp_usecs("1682899200").unwrap(),
+            1682899200000000
+        );
+        assert_eq!(
+            parse_timestamp_usecs("2023-05-01T00:00:00Z").unwrap(),
+            1682899200000000
+        );
+        assert_eq!(
+            parse_timestamp_usecs("2023-05-01T02:00:00.5+02:00").unwrap(),
+            1682899200500000
+        );
+        assert!(parse_timestamp_usecs("yesterday").is_err());
+        assert!(parse_timestamp_usecs("1969-12-31T23:59:59Z").is_err());
+    }
+}
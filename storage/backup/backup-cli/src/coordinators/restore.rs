@@ -5,6 +5,7 @@
 use crate::{
     backup_types::{
         epoch_ending::restore::EpochHistoryRestoreController,
+        state_delta::restore::{StateDeltaRestoreController, StateDeltaRestoreOpt},
         state_snapshot::restore::{StateSnapshotRestoreController, StateSnapshotRestoreOpt},
         transaction::restore::TransactionRestoreBatchController,
     },
@@ -178,28 +179,30 @@ impl RestoreCoordinator {
             },
         };
 
+        // The tree snapshot can be a state snapshot with state deltas applied on top of it.
         let tree_snapshot = if let Some((latest_tree_version, _)) = latest_tree_version {
-            let snapshot = metadata_view.select_state_snapshot(latest_tree_version)?;
+            let snapshot = metadata_view.select_state_snapshot_with_deltas(latest_tree_version)?;
 
             ensure!(
-                snapshot.is_some() && snapshot.as_ref().unwrap().version == latest_tree_version,
+                snapshot.is_some() && snapshot.as_ref().unwrap().version() == latest_tree_version,
                 "cannot find tree snapshot {}",
                 latest_tree_version
             );
             snapshot.unwrap()
         } else {
             metadata_view
-                .select_state_snapshot(target_version)?
+                .select_state_snapshot_with_deltas(target_version)?
                 .expect("Cannot find tree snapshot before target version")
         };
+        let tree_snapshot_version = tree_snapshot.version();
 
         let do_phase_1 = if let Some(kv_snapshot) = kv_snapshot.as_ref() {
             // if we have a kv snapshot, we need to restore the state between lhs and rs
             // if the version are equal, we don't need to restore phase 1. we can directly restore a snapshot with both tree and KV, and then replay txn till the target_version
-            kv_snapshot.version < tree_snapshot.version
+            kv_snapshot.version < tree_snapshot_version
         } else {
             // if we don't have a kv snapshot, we need to restore the state between db_next_version and rs
-            db_next_version < tree_snapshot.version
+            db_next_version < tree_snapshot_version
         };
         let txn_start_version = if let Some(kv_snapshot) = kv_snapshot.as_ref() {
             kv_snapshot.version
@@ -232,7 +235,7 @@ impl RestoreCoordinator {
         if do_phase_1 {
             info!(
                 "Start restoring DB from version {} to tree snapshot version {}",
-                txn_start_version, tree_snapshot.version,
+                txn_start_version, tree_snapshot_version,
             );
 
             // phase 1.a: restore the kv snapshot
@@ -259,7 +262,7 @@ impl RestoreCoordinator {
             let txn_manifests = transaction_backups
                 .iter()
                 .filter(|e| {
-                    e.first_version <= tree_snapshot.version && e.last_version >= db_next_version
+                    e.first_version <= tree_snapshot_version && e.last_version >= db_next_version
                 })
                 .map(|e| e.manifest.clone())
                 .collect();
@@ -281,7 +284,7 @@ impl RestoreCoordinator {
             } else {
                 db_next_version
             };
-            transaction_restore_opt.target_version = tree_snapshot.version;
+            transaction_restore_opt.target_version = tree_snapshot_version;
             TransactionRestoreBatchController::new(
                 transaction_restore_opt,
                 Arc::clone(&self.storage),
@@ -295,7 +298,7 @@ impl RestoreCoordinator {
             .run()
             .await?;
             // update the expected version for the first phase restore
-            db_next_version = tree_snapshot.version;
+            db_next_version = tree_snapshot_version;
         }
 
         // Phase 2: restore the full tree snapshot and replay till the target version
@@ -318,24 +321,44 @@ impl RestoreCoordinator {
                 };
                 info!(
                     "Start restoring tree snapshot at {} with db_next_version {}",
-                    tree_snapshot.version, db_next_version
+                    tree_snapshot_version, db_next_version
                 );
 
-                StateSnapshotRestoreController::new(
-                    StateSnapshotRestoreOpt {
-                        manifest_handle: tree_snapshot.manifest.clone(),
-                        version: tree_snapshot.version,
-                        validate_modules: false,
-                        restore_mode,
-                    },
-                    self.global_opt.clone(),
-                    Arc::clone(&self.storage),
-                    epoch_history.clone(),
-                )
-                .run()
-                .await?;
+                if tree_snapshot.deltas.is_empty() {
+                    StateSnapshotRestoreController::new(
+                        StateSnapshotRestoreOpt {
+                            manifest_handle: tree_snapshot.snapshot.manifest.clone(),
+                            version: tree_snapshot_version,
+                            validate_modules: false,
+                            restore_mode,
+                        },
+                        self.global_opt.clone(),
+                        Arc::clone(&self.storage),
+                        epoch_history.clone(),
+                    )
+                    .run()
+                    .await?;
+                } else {
+                    StateDeltaRestoreController::new(
+                        StateDeltaRestoreOpt {
+                            snapshot_manifest_handle: tree_snapshot.snapshot.manifest.clone(),
+                            delta_manifest_handles: tree_snapshot
+                                .deltas
+                                .iter()
+                                .map(|d| d.manifest.clone())
+                                .collect(),
+                            version: tree_snapshot_version,
+                            restore_mode,
+                        },
+                        self.global_opt.clone(),
+                        Arc::clone(&self.storage),
+                        epoch_history.clone(),
+                    )
+                    .run()
+                    .await?;
+                }
                 replay_version = Some((
-                    tree_snapshot.version + 1,
+                    tree_snapshot_version + 1,
                     false, /*replay entire txn including update tree and KV*/
                 ));
             }
@@ -0,0 +1,754 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        epoch_ending::manifest::EpochEndingBackup, state_delta::manifest::StateDeltaBackup,
+        state_snapshot::manifest::StateSnapshotBackup, transaction::manifest::TransactionBackup,
+    },
+    metadata,
+    metadata::{
+        cache::MetadataCacheOpt,
+        view::{MetadataView, StateSnapshotWithDeltas},
+        Metadata, StateDeltaBackupMeta, StateSnapshotBackupMeta, TransactionBackupMeta,
+    },
+    storage::{BackupStorage, FileHandle},
+    utils::storage_ext::BackupStorageExt,
+};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use aptos_infallible::duration_since_epoch;
+use aptos_logger::prelude::*;
+use aptos_types::{
+    ledger_info::LedgerInfoWithSignatures, proof::TransactionInfoWithProof, transaction::Version,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
+
+const SECS_PER_DAY: u64 = 24 * 3600;
+const SECS_PER_WEEK: u64 = 7 * SECS_PER_DAY;
+
+/// How many of the most recent periods (days, weeks) to keep a state in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RetentionCount {
+    All,
+    Last(u64),
+}
+
+impl RetentionCount {
+    fn covers(&self, current_period: u64, period: u64) -> bool {
+        match self {
+            Self::All => true,
+            Self::Last(n) => current_period.saturating_sub(period) < *n,
+        }
+    }
+}
+
+impl FromStr for RetentionCount {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "all" {
+            Ok(Self::All)
+        } else {
+            Ok(Self::Last(s.parse().map_err(|_| {
+                anyhow!("Expecting a number or \"all\", got \"{}\".", s)
+            })?))
+        }
+    }
+}
+
+/// Which backups to keep. Anything that is not needed to restore to a version kept by the policy
+/// is subject to pruning, except for epoch ending backups, which are small and always needed to
+/// verify the ledger infos in other backups.
+///
+/// A "state" here is a state snapshot, or a state snapshot with a chain of state deltas on top of
+/// it. The latest state is always kept.
+#[derive(Clone, Debug, Default)]
+pub struct RetentionPolicy {
+    /// Keep the transaction backups needed to restore to any version in this many of the latest
+    /// epochs, together with the state to start replaying them from. `None` to keep all
+    /// transaction backups.
+    pub keep_transaction_epochs: Option<u64>,
+    /// Keep the latest state of each UTC day, in this many of the latest days.
+    pub keep_daily_states: Option<RetentionCount>,
+    /// Keep the latest state of each week, in this many of the latest weeks.
+    /// If neither this nor `keep_daily_states` is set, all states are kept.
+    pub keep_weekly_states: Option<RetentionCount>,
+}
+
+impl RetentionPolicy {
+    fn prunes_states(&self) -> bool {
+        self.keep_daily_states.is_some() || self.keep_weekly_states.is_some()
+    }
+}
+
+/// The outcome of applying a `RetentionPolicy` to the backups in a storage.
+#[derive(Debug)]
+pub struct RetentionPlan {
+    /// Versions of the states kept.
+    pub retained_state_versions: Vec<Version>,
+    /// Transactions since this version are kept.
+    pub transaction_start_version: Version,
+    pub expired_state_snapshots: Vec<StateSnapshotBackupMeta>,
+    pub expired_state_deltas: Vec<StateDeltaBackupMeta>,
+    pub expired_transactions: Vec<TransactionBackupMeta>,
+}
+
+impl RetentionPlan {
+    /// `timestamps` maps the version of each state to its timestamp in seconds, needed only if
+    /// the policy prunes states. `transaction_window_start` is the first version to keep
+    /// transactions since, according to the policy.
+    pub(crate) fn new(
+        policy: &RetentionPolicy,
+        view: &MetadataView,
+        timestamps: &HashMap<Version, u64>,
+        transaction_window_start: Option<Version>,
+        now_secs: u64,
+    ) -> Result<Self> {
+        let states = view.states();
+        let mut retained: BTreeMap<Version, &StateSnapshotWithDeltas> = BTreeMap::new();
+
+        if !policy.prunes_states() {
+            retained.extend(states.iter().map(|s| (s.version(), s)));
+        }
+        if let Some(latest) = states.last() {
+            retained.insert(latest.version(), latest);
+        }
+        for (count, period_secs) in [
+            (policy.keep_daily_states, SECS_PER_DAY),
+            (policy.keep_weekly_states, SECS_PER_WEEK),
+        ] {
+            let count = match count {
+                Some(count) => count,
+                None => continue,
+            };
+            let current_period = now_secs / period_secs;
+            let mut latest_in_period = BTreeMap::new();
+            // Ordered by version, so the latest in each period wins.
+            for state in &states {
+                let timestamp = timestamps.get(&state.version()).ok_or_else(|| {
+                    anyhow!("Timestamp of state at version {} unknown.", state.version())
+                })?;
+                let period = timestamp / period_secs;
+                if count.covers(current_period, period) {
+                    latest_in_period.insert(period, state);
+                }
+            }
+            retained.extend(latest_in_period.into_values().map(|s| (s.version(), s)));
+        }
+
+        // To restore to any version in the transaction window, replay from the latest state
+        // before the window.
+        let transaction_start_version = match transaction_window_start
+            .and_then(|start| states.iter().rev().find(|s| s.version() <= start))
+        {
+            Some(anchor) => {
+                retained.insert(anchor.version(), anchor);
+                anchor.version()
+            },
+            None => 0,
+        };
+
+        let retained_snapshots: HashSet<_> = retained
+            .values()
+            .map(|s| s.snapshot.manifest.clone())
+            .collect();
+        let retained_deltas: HashSet<_> = retained
+            .values()
+            .flat_map(|s| s.deltas.iter().map(|d| d.manifest.clone()))
+            .collect();
+        let state_versions: HashSet<_> = states.iter().map(|s| s.version()).collect();
+
+        let expired_state_snapshots = view
+            .state_snapshot_backups()
+            .iter()
+            .filter(|s| !retained_snapshots.contains(&s.manifest))
+            .cloned()
+            .collect();
+        let mut expired_state_deltas = Vec::new();
+        for delta in view.state_delta_backups() {
+            if retained_deltas.contains(&delta.manifest) {
+                continue;
+            }
+            if state_versions.contains(&delta.base_version) {
+                expired_state_deltas.push(delta.clone());
+            } else {
+                // Can't be restored anyway, but it's not ours to decide how it ended up so.
+                warn!(
+                    manifest = delta.manifest,
+                    base_version = delta.base_version,
+                    "State delta backup with no base state found, leaving it alone.",
+                );
+            }
+        }
+        let expired_transactions = view
+            .transaction_backups()
+            .iter()
+            .filter(|t| t.last_version < transaction_start_version)
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            retained_state_versions: retained.into_keys().collect(),
+            transaction_start_version,
+            expired_state_snapshots,
+            expired_state_deltas,
+            expired_transactions,
+        })
+    }
+
+    pub fn expired_manifests(&self) -> HashSet<FileHandle> {
+        self.expired_state_snapshots
+            .iter()
+            .map(|s| s.manifest.clone())
+            .chain(self.expired_state_deltas.iter().map(|d| d.manifest.clone()))
+            .chain(self.expired_transactions.iter().map(|t| t.manifest.clone()))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expired_state_snapshots.is_empty()
+            && self.expired_state_deltas.is_empty()
+            && self.expired_transactions.is_empty()
+    }
+
+    /// Double checks, with the same selection logic the restore uses, that every version the
+    /// policy keeps can still be restored from the backups left after pruning.
+    pub(crate) fn verify(&self, view: &MetadataView) -> Result<()> {
+        let expired = self.expired_manifests();
+        let remaining = MetadataView::new(
+            view.epoch_ending_backups()
+                .iter()
+                .cloned()
+                .map(Metadata::EpochEndingBackup)
+                .chain(
+                    view.state_snapshot_backups()
+                        .iter()
+                        .filter(|s| !expired.contains(&s.manifest))
+                        .cloned()
+                        .map(Metadata::StateSnapshotBackup),
+                )
+                .chain(
+                    view.state_delta_backups()
+                        .iter()
+                        .filter(|d| !expired.contains(&d.manifest))
+                        .cloned()
+                        .map(Metadata::StateDeltaBackup),
+                )
+                .chain(
+                    view.transaction_backups()
+                        .iter()
+                        .filter(|t| !expired.contains(&t.manifest))
+                        .cloned()
+                        .map(Metadata::TransactionBackup),
+                )
+                .collect(),
+            Vec::new(),
+        );
+
+        for version in &self.retained_state_versions {
+            remaining
+                .expect_state_snapshot_with_deltas(*version)
+                .with_context(|| format!("State at version {} would be lost.", version))?;
+        }
+        if !self.expired_transactions.is_empty() {
+            let max_version = view.max_transaction_version()?;
+            let backups = remaining
+                .select_transaction_backups(self.transaction_start_version, Version::MAX)
+                .context("Transactions in the retained window would be lost.")?;
+            ensure!(
+                backups.last().map(|t| t.last_version) == max_version,
+                "Transactions up to version {:?} would be lost.",
+                max_version,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Deletes backups falling out of a `RetentionPolicy`.
+///
+/// Metadata files are rewritten first, so that nothing selects the expired backups for restoring
+/// while they are being deleted.
+pub struct BackupPruner {
+    policy: RetentionPolicy,
+    metadata_cache_opt: MetadataCacheOpt,
+    storage: Arc<dyn BackupStorage>,
+    concurrent_downloads: usize,
+    dry_run: bool,
+}
+
+impl BackupPruner {
+    pub fn new(
+        policy: RetentionPolicy,
+        metadata_cache_opt: MetadataCacheOpt,
+        storage: Arc<dyn BackupStorage>,
+        concurrent_downloads: usize,
+        dry_run: bool,
+    ) -> Self {
+        Self {
+            policy,
+            metadata_cache_opt,
+            storage,
+            concurrent_downloads,
+            dry_run,
+        }
+    }
+
+    pub async fn run(self) -> Result<RetentionPlan> {
+        info!(policy = ?self.policy, dry_run = self.dry_run, "Backup pruning started.");
+        let metadata_files = metadata::cache::sync_and_load_by_file(
+            &self.metadata_cache_opt,
+            Arc::clone(&self.storage),
+            self.concurrent_downloads,
+        )
+        .await?;
+        let view = MetadataView::new(
+            metadata_files
+                .iter()
+                .flat_map(|(_, metadata)| metadata.iter().cloned())
+                .collect(),
+            metadata_files
+                .iter()
+                .map(|(file, _)| file.clone())
+                .collect(),
+        );
+
+        let transaction_window_start = self.transaction_window_start(&view).await?;
+        let timestamps = if self.policy.prunes_states() {
+            self.load_state_timestamps(&view).await?
+        } else {
+            HashMap::new()
+        };
+        let plan = RetentionPlan::new(
+            &self.policy,
+            &view,
+            &timestamps,
+            transaction_window_start,
+            duration_since_epoch().as_secs(),
+        )?;
+        plan.verify(&view)?;
+
+        info!(
+            retained_state_versions = ?plan.retained_state_versions,
+            transaction_start_version = plan.transaction_start_version,
+            expired_state_snapshots = plan.expired_state_snapshots.len(),
+            expired_state_deltas = plan.expired_state_deltas.len(),
+            expired_transactions = plan.expired_transactions.len(),
+            "Retention plan made.",
+        );
+        for manifest in plan.expired_manifests() {
+            info!(
+                manifest = manifest,
+                dry_run = self.dry_run,
+                "Backup expired."
+            );
+        }
+        if self.dry_run || plan.is_empty() {
+            return Ok(plan);
+        }
+
+        // Find out all files to delete before the backups are dropped from the metadata.
+        let mut files_to_delete = Vec::new();
+        for snapshot in &plan.expired_state_snapshots {
+            files_to_delete.push(self.state_snapshot_files(&snapshot.manifest).await?);
+        }
+        for delta in &plan.expired_state_deltas {
+            files_to_delete.push(self.state_delta_files(&delta.manifest).await?);
+        }
+        for txn in &plan.expired_transactions {
+            files_to_delete.push(self.transaction_files(&txn.manifest).await?);
+        }
+
+        self.rewrite_metadata_files(metadata_files, &plan.expired_manifests())
+            .await?;
+
+        let mut num_failures = 0;
+        for file in files_to_delete.into_iter().flatten() {
+            if let Err(e) = self.storage.delete_file(&file).await {
+                error!(file = file, error = ?e, "Failed to delete file, it's left orphaned.");
+                num_failures += 1;
+            }
+        }
+        if num_failures > 0 {
+            bail!("Failed to delete {} files.", num_failures);
+        }
+
+        info!("Backup pruning succeeded.");
+        Ok(plan)
+    }
+
+    async fn transaction_window_start(&self, view: &MetadataView) -> Result<Option<Version>> {
+        let keep_epochs = match self.policy.keep_transaction_epochs {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+        let latest_epoch = match view
+            .epoch_ending_backups()
+            .iter()
+            .map(|e| e.last_epoch)
+            .max()
+        {
+            Some(epoch) => epoch,
+            None => return Ok(None),
+        };
+        if latest_epoch < keep_epochs {
+            return Ok(None);
+        }
+
+        // The window starts at the end of the epoch before the kept ones.
+        let epoch = latest_epoch - keep_epochs;
+        let backup = view
+            .epoch_ending_backups()
+            .iter()
+            .find(|e| e.first_epoch <= epoch && epoch <= e.last_epoch)
+            .ok_or_else(|| anyhow!("Epoch ending backup for epoch {} not found.", epoch))?;
+        let manifest: EpochEndingBackup = self.storage.load_json_file(&backup.manifest).await?;
+        manifest.verify()?;
+        Ok(Some(
+            manifest.waypoints[(epoch - manifest.first_epoch) as usize].version(),
+        ))
+    }
+
+    async fn load_state_timestamps(&self, view: &MetadataView) -> Result<HashMap<Version, u64>> {
+        let mut timestamps = HashMap::new();
+        for state in view.states() {
+            let proof = match state.deltas.last() {
+                Some(delta) => {
+                    let manifest: StateDeltaBackup =
+                        self.storage.load_json_file(&delta.manifest).await?;
+                    manifest.proof
+                },
+                None => {
+                    let manifest: StateSnapshotBackup = self
+                        .storage
+                        .load_json_file(&state.snapshot.manifest)
+                        .await?;
+                    manifest.proof
+                },
+            };
+            let (_, li): (TransactionInfoWithProof, LedgerInfoWithSignatures) =
+                self.storage.load_bcs_file(&proof).await?;
+            timestamps.insert(
+                state.version(),
+                li.ledger_info().timestamp_usecs() / 1_000_000,
+            );
+        }
+        Ok(timestamps)
+    }
+
+    async fn state_snapshot_files(&self, manifest_handle: &FileHandle) -> Result<Vec<FileHandle>> {
+        let manifest: StateSnapshotBackup = self.storage.load_json_file(manifest_handle).await?;
+        Ok(manifest
+            .chunks
+            .into_iter()
+            .flat_map(|c| [c.blobs, c.proof])
+            .chain([manifest.proof, manifest_handle.clone()])
+            .collect())
+    }
+
+    async fn state_delta_files(&self, manifest_handle: &FileHandle) -> Result<Vec<FileHandle>> {
+        let manifest: StateDeltaBackup = self.storage.load_json_file(manifest_handle).await?;
+        Ok(manifest
+            .chunks
+            .into_iter()
+            .map(|c| c.records)
+            .chain([manifest.proof, manifest_handle.clone()])
+            .collect())
+    }
+
+    async fn transaction_files(&self, manifest_handle: &FileHandle) -> Result<Vec<FileHandle>> {
+        let manifest: TransactionBackup = self.storage.load_json_file(manifest_handle).await?;
+        Ok(manifest
+            .chunks
+            .into_iter()
+            .flat_map(|c| [c.transactions, c.proof])
+            .chain([manifest_handle.clone()])
+            .collect())
+    }
+
+    /// Drops the entries of expired backups from the metadata files. A metadata file with
+    /// entries left is replaced by a new one holding them.
+    async fn rewrite_metadata_files(
+        &self,
+        metadata_files: Vec<(FileHandle, Vec<Metadata>)>,
+        expired: &HashSet<FileHandle>,
+    ) -> Result<()> {
+        let now = duration_since_epoch().as_secs();
+        for (idx, (file_handle, metadata)) in metadata_files.into_iter().enumerate() {
+            let (dropped, kept): (Vec<_>, Vec<_>) = metadata.into_iter().partition(|m| match m {
+                Metadata::StateSnapshotBackup(s) => expired.contains(&s.manifest),
+                Metadata::StateDeltaBackup(d) => expired.contains(&d.manifest),
+                Metadata::TransactionBackup(t) => expired.contains(&t.manifest),
+                _ => false,
+            });
+            if dropped.is_empty() {
+                continue;
+            }
+            if !kept.is_empty() {
+                let lines = kept
+                    .iter()
+                    .map(Metadata::to_text_line)
+                    .collect::<Result<Vec<_>>>()?;
+                let name = format!("pruned_{}_{}.meta", now, idx).parse()?;
+                self.storage.save_metadata_lines(&name, &lines).await?;
+            }
+            info!(file = file_handle, "Backup metadata file.");
+            self.storage.backup_metadata_file(&file_handle).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1000 * SECS_PER_WEEK;
+
+    fn snapshot(version: Version) -> Metadata {
+        Metadata::new_state_snapshot_backup(0, version, format!("snapshot_{}", version))
+    }
+
+    fn delta(base_version: Version, version: Version) -> Metadata {
+        Metadata::new_state_delta_backup(
+            0,
+            base_version,
+            0,
+            version,
+            format!("delta_{}_{}", base_version, version),
+        )
+    }
+
+    fn transactions(first_version: Version, last_version: Version) -> Metadata {
+        Metadata::new_transaction_backup(
+            first_version,
+            last_version,
+            format!("txn_{}_{}", first_version, last_version),
+        )
+    }
+
+    fn view(metadata: Vec<Metadata>) -> MetadataView {
+        let mut metadata = metadata;
+        metadata.push(Metadata::new_epoch_ending_backup(
+            0,
+            0,
+            0,
+            0,
+            "epoch_0".to_string(),
+        ));
+        MetadataView::new(metadata, Vec::new())
+    }
+
+    fn manifests<'a>(expired: impl IntoIterator<Item = &'a FileHandle>) -> Vec<&'a str> {
+        let mut res: Vec<_> = expired.into_iter().map(String::as_str).collect();
+        res.sort_unstable();
+        res
+    }
+
+    fn make_plan(
+        policy: &RetentionPolicy,
+        view: &MetadataView,
+        timestamps: &[(Version, u64)],
+        transaction_window_start: Option<Version>,
+    ) -> RetentionPlan {
+        let plan = RetentionPlan::new(
+            policy,
+            view,
+            &timestamps.iter().cloned().collect(),
+            transaction_window_start,
+            NOW,
+        )
+        .unwrap();
+        plan.verify(view).unwrap();
+        plan
+    }
+
+    #[test]
+    fn test_default_policy_keeps_everything() {
+        let view = view(vec![
+            snapshot(0),
+            snapshot(100),
+            delta(100, 200),
+            transactions(0, 99),
+            transactions(100, 199),
+        ]);
+        let plan = make_plan(&RetentionPolicy::default(), &view, &[], None);
+        assert!(plan.is_empty());
+        assert_eq!(plan.retained_state_versions, vec![0, 100, 200]);
+    }
+
+    #[test]
+    fn test_transaction_window() {
+        let view = view(vec![
+            snapshot(0),
+            snapshot(100),
+            snapshot(200),
+            transactions(0, 99),
+            transactions(100, 199),
+            transactions(200, 299),
+        ]);
+        let policy = RetentionPolicy {
+            keep_transaction_epochs: Some(1),
+            ..Default::default()
+        };
+
+        // Replay starts from the snapshot at 100.
+        let plan = make_plan(&policy, &view, &[], Some(150));
+        assert_eq!(plan.transaction_start_version, 100);
+        assert_eq!(manifests(&plan.expired_manifests()), vec!["txn_0_99"]);
+
+        // Window starting right at a snapshot.
+        let plan = make_plan(&policy, &view, &[], Some(200));
+        assert_eq!(plan.transaction_start_version, 200);
+        assert_eq!(manifests(&plan.expired_manifests()), vec![
+            "txn_0_99",
+            "txn_100_199"
+        ]);
+    }
+
+    #[test]
+    fn test_transaction_window_without_state_before() {
+        let view = view(vec![
+            snapshot(100),
+            transactions(0, 99),
+            transactions(100, 199),
+        ]);
+        let policy = RetentionPolicy {
+            keep_transaction_epochs: Some(1),
+            ..Default::default()
+        };
+
+        // Nothing to replay from but genesis.
+        let plan = make_plan(&policy, &view, &[], Some(50));
+        assert_eq!(plan.transaction_start_version, 0);
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn test_daily_and_weekly_states() {
+        let view = view(vec![
+            snapshot(10),
+            snapshot(20),
+            snapshot(30),
+            snapshot(40),
+            snapshot(50),
+            snapshot(60),
+        ]);
+        let timestamps = [
+            // Weeks ago, in the same week.
+            (10, NOW - 3 * SECS_PER_WEEK),
+            (20, NOW - 3 * SECS_PER_WEEK + 1),
+            // Two days ago.
+            (30, NOW - 2 * SECS_PER_DAY),
+            // Yesterday, twice.
+            (40, NOW - SECS_PER_DAY),
+            (50, NOW - SECS_PER_DAY + 1),
+            // Today.
+            (60, NOW),
+        ];
+
+        let policy = RetentionPolicy {
+            keep_daily_states: Some(RetentionCount::Last(2)),
+            ..Default::default()
+        };
+        let plan = make_plan(&policy, &view, &timestamps, None);
+        assert_eq!(plan.retained_state_versions, vec![50, 60]);
+
+        let policy = RetentionPolicy {
+            keep_daily_states: Some(RetentionCount::Last(2)),
+            keep_weekly_states: Some(RetentionCount::All),
+            ..Default::default()
+        };
+        let plan = make_plan(&policy, &view, &timestamps, None);
+        // 60 is also the latest of this week, 30 and 40 are in the same week as 50.
+        assert_eq!(plan.retained_state_versions, vec![20, 50, 60]);
+        assert_eq!(manifests(&plan.expired_manifests()), vec![
+            "snapshot_10",
+            "snapshot_30",
+            "snapshot_40"
+        ]);
+    }
+
+    #[test]
+    fn test_state_delta_chain() {
+        let view = view(vec![
+            snapshot(0),
+            snapshot(50),
+            delta(0, 100),
+            delta(100, 200),
+            delta(50, 150),
+            // Orphan.
+            delta(120, 300),
+        ]);
+        let timestamps = [
+            (0, NOW - 10 * SECS_PER_DAY),
+            (50, NOW - 9 * SECS_PER_DAY),
+            (100, NOW - 8 * SECS_PER_DAY),
+            (150, NOW - 7 * SECS_PER_DAY),
+            (200, NOW),
+        ];
+        let policy = RetentionPolicy {
+            keep_daily_states: Some(RetentionCount::Last(1)),
+            ..Default::default()
+        };
+
+        let plan = make_plan(&policy, &view, &timestamps, None);
+        // The state at 200 needs the whole chain it's built on.
+        assert_eq!(plan.retained_state_versions, vec![200]);
+        assert_eq!(manifests(&plan.expired_manifests()), vec![
+            "delta_50_150",
+            "snapshot_50"
+        ]);
+    }
+
+    #[test]
+    fn test_states() {
+        let view = view(vec![
+            snapshot(100),
+            delta(0, 100),
+            snapshot(0),
+            delta(100, 200),
+            // Orphan.
+            delta(120, 300),
+        ]);
+        let versions: Vec<_> = view.states().iter().map(|s| s.version()).collect();
+        assert_eq!(versions, vec![0, 100, 200]);
+        // The state at 100 is the snapshot itself, not the delta on top of the one at 0.
+        assert!(view.states()[1].deltas.is_empty());
+    }
+
+    #[test]
+    fn test_verify_catches_lost_state() {
+        let view = view(vec![snapshot(0), delta(0, 100)]);
+        let plan = RetentionPlan {
+            retained_state_versions: vec![100],
+            transaction_start_version: 0,
+            expired_state_snapshots: vec![StateSnapshotBackupMeta {
+                epoch: 0,
+                version: 0,
+                manifest: "snapshot_0".to_string(),
+            }],
+            expired_state_deltas: Vec::new(),
+            expired_transactions: Vec::new(),
+        };
+        assert!(plan.verify(&view).is_err());
+    }
+
+    #[test]
+    fn test_parse_retention_count() {
+        assert_eq!(
+            "all".parse::<RetentionCount>().unwrap(),
+            RetentionCount::All
+        );
+        assert_eq!(
+            "30".parse::<RetentionCount>().unwrap(),
+            RetentionCount::Last(30)
+        );
+        assert!("forever".parse::<RetentionCount>().is_err());
+    }
+}
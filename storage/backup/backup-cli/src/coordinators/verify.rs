@@ -102,6 +102,7 @@ impl VerifyCoordinator {
             run_mode: Arc::new(RestoreRunMode::Verify),
             concurrent_downloads: self.concurrent_downloads,
             replay_concurrency_level: 0, // won't replay, doesn't matter
+            state_snapshot_restore_in_ranges: false,
         };
 
         let epoch_history = if self.skip_epoch_endings {
@@ -94,6 +94,26 @@ pub async fn sync_and_load(
     storage: Arc<dyn BackupStorage>,
     concurrent_downloads: usize,
 ) -> Result<MetadataView> {
+    let metadata_files = sync_and_load_by_file(opt, storage, concurrent_downloads).await?;
+    let file_handles = metadata_files
+        .iter()
+        .map(|(file_handle, _)| file_handle.clone())
+        .collect();
+    let metadata_vec = metadata_files
+        .into_iter()
+        .flat_map(|(_, metadata)| metadata)
+        .collect();
+
+    Ok(MetadataView::new(metadata_vec, file_handles))
+}
+
+/// Like `sync_and_load`, but keeps the metadata entries grouped by the metadata files holding
+/// them, for tools that rewrite metadata files.
+pub(crate) async fn sync_and_load_by_file(
+    opt: &MetadataCacheOpt,
+    storage: Arc<dyn BackupStorage>,
+    concurrent_downloads: usize,
+) -> Result<Vec<(FileHandle, Vec<Metadata>)>> {
     let timer = Instant::now();
     let cache_dir = opt.cache_dir();
     create_dir_all(&cache_dir).await.err_notes(&cache_dir)?; // create if not present already
@@ -185,10 +205,12 @@ pub async fn sync_and_load(
 
     info!("Loading all metadata files to memory.");
     // Load metadata from synced cache files.
-    let mut metadata_vec = Vec::new();
+    let mut metadata_files = Vec::new();
     for h in new_remote_hashes.into_iter().chain(up_to_date_local_hashes) {
         let cached_file = cache_dir.join(h);
-        metadata_vec.extend(
+        let file_handle = remote_file_handle_by_hash.get(h).expect("In map.");
+        metadata_files.push((
+            (*file_handle).clone(),
             OpenOptions::new()
                 .read(true)
                 .open(&cached_file)
@@ -196,16 +218,15 @@ pub async fn sync_and_load(
                 .err_notes(&cached_file)?
                 .load_metadata_lines()
                 .await
-                .err_notes(&cached_file)?
-                .into_iter(),
-        )
+                .err_notes(&cached_file)?,
+        ))
     }
     info!(
         total_time = timer.elapsed().as_secs(),
         "Metadata cache loaded.",
     );
 
-    Ok(MetadataView::new(metadata_vec, remote_file_handles))
+    Ok(metadata_files)
 }
 
 trait FileHandleHash {
@@ -12,11 +12,12 @@ use aptos_types::transaction::Version;
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, collections::HashMap, convert::TryInto};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[allow(clippy::enum_variant_names)] // to introduce: BackupperId, etc
 pub(crate) enum Metadata {
     EpochEndingBackup(EpochEndingBackupMeta),
     StateSnapshotBackup(StateSnapshotBackupMeta),
+    StateDeltaBackup(StateDeltaBackupMeta),
     TransactionBackup(TransactionBackupMeta),
     Identity(IdentityMeta),
     CompactionTimestamps(CompactionTimestampsMeta),
@@ -47,6 +48,22 @@ impl Metadata {
         })
     }
 
+    pub fn new_state_delta_backup(
+        base_epoch: u64,
+        base_version: Version,
+        epoch: u64,
+        version: Version,
+        manifest: FileHandle,
+    ) -> Self {
+        Self::StateDeltaBackup(StateDeltaBackupMeta {
+            base_epoch,
+            base_version,
+            epoch,
+            version,
+            manifest,
+        })
+    }
+
     pub fn new_transaction_backup(
         first_version: Version,
         last_version: Version,
@@ -112,6 +129,27 @@ impl Metadata {
         Ok((res, name.parse()?))
     }
 
+    pub fn compact_state_delta_backup_range(
+        backup_metas: Vec<StateDeltaBackupMeta>,
+    ) -> Result<(Vec<TextLine>, ShellSafeName)> {
+        ensure!(
+            !backup_metas.is_empty(),
+            "compacting an empty metadata vector"
+        );
+        // Several deltas can reach the same version from different bases.
+        let first = &backup_metas[0];
+        let last = &backup_metas[backup_metas.len() - 1];
+        let name = format!(
+            "state_delta_compacted_ver_{}-{}_{}-{}.meta",
+            first.base_version, first.version, last.base_version, last.version
+        );
+        let res: Vec<TextLine> = backup_metas
+            .into_iter()
+            .map(|d| Metadata::StateDeltaBackup(d).to_text_line())
+            .collect::<Result<_>>()?;
+        Ok((res, name.parse()?))
+    }
+
     pub fn compact_transaction_backup_range(
         backup_metas: Vec<TransactionBackupMeta>,
     ) -> Result<(Vec<TextLine>, ShellSafeName)> {
@@ -156,6 +194,9 @@ impl Metadata {
                 format!("epoch_ending_{}-{}.meta", e.first_epoch, e.last_epoch)
             },
             Self::StateSnapshotBackup(s) => format!("state_snapshot_ver_{}.meta", s.version),
+            Self::StateDeltaBackup(d) => {
+                format!("state_delta_ver_{}-{}.meta", d.base_version, d.version)
+            },
             Self::TransactionBackup(t) => {
                 format!("transaction_{}-{}.meta", t.first_version, t.last_version)
             },
@@ -189,6 +230,16 @@ pub struct StateSnapshotBackupMeta {
     pub manifest: FileHandle,
 }
 
+/// Ordered by `version` first, which is the order deltas can be chained in.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct StateDeltaBackupMeta {
+    pub version: Version,
+    pub epoch: u64,
+    pub base_version: Version,
+    pub base_epoch: u64,
+    pub manifest: FileHandle,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd)]
 pub struct TransactionBackupMeta {
     pub first_version: Version,
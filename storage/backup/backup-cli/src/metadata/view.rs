@@ -5,7 +5,7 @@
 use crate::{
     metadata::{
         CompactionTimestampsMeta, EpochEndingBackupMeta, IdentityMeta, Metadata,
-        StateSnapshotBackupMeta, TransactionBackupMeta,
+        StateDeltaBackupMeta, StateSnapshotBackupMeta, TransactionBackupMeta,
     },
     metrics::backup::COMPACTED_TXN_VERSION,
     storage::FileHandle,
@@ -14,12 +14,17 @@ use anyhow::{anyhow, ensure, Result};
 use aptos_infallible::duration_since_epoch;
 use aptos_types::transaction::Version;
 use itertools::Itertools;
-use std::{fmt, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
 
 #[derive(Debug)]
 pub struct MetadataView {
     epoch_ending_backups: Vec<EpochEndingBackupMeta>,
     state_snapshot_backups: Vec<StateSnapshotBackupMeta>,
+    state_delta_backups: Vec<StateDeltaBackupMeta>,
     transaction_backups: Vec<TransactionBackupMeta>,
     _identity: Option<IdentityMeta>,
     // The compaction timestamps of the file handles producing this view
@@ -30,6 +35,7 @@ impl MetadataView {
     pub(crate) fn new(metadata_vec: Vec<Metadata>, file_handles: Vec<FileHandle>) -> Self {
         let mut epoch_ending_backups = Vec::new();
         let mut state_snapshot_backups = Vec::new();
+        let mut state_delta_backups = Vec::new();
         let mut transaction_backups = Vec::new();
         let mut identity = None;
         let mut compaction_timestamps = Vec::new();
@@ -38,6 +44,7 @@ impl MetadataView {
             match meta {
                 Metadata::EpochEndingBackup(e) => epoch_ending_backups.push(e),
                 Metadata::StateSnapshotBackup(s) => state_snapshot_backups.push(s),
+                Metadata::StateDeltaBackup(d) => state_delta_backups.push(d),
                 Metadata::TransactionBackup(t) => transaction_backups.push(t),
                 Metadata::Identity(i) => identity = Some(i),
                 Metadata::CompactionTimestamps(t) => compaction_timestamps.push(t),
@@ -47,6 +54,8 @@ impl MetadataView {
         epoch_ending_backups.dedup();
         state_snapshot_backups.sort_unstable();
         state_snapshot_backups.dedup();
+        state_delta_backups.sort_unstable();
+        state_delta_backups.dedup();
         transaction_backups.sort_unstable();
         transaction_backups.dedup();
 
@@ -72,6 +81,7 @@ impl MetadataView {
         Self {
             epoch_ending_backups,
             state_snapshot_backups,
+            state_delta_backups,
             transaction_backups,
             _identity: identity,
             compaction_timestamps: compaction_meta_opt,
@@ -126,31 +136,101 @@ impl MetadataView {
             .ok_or_else(|| anyhow!("State snapshot not found at version {}", version))
     }
 
+    /// Selects the latest state at or before `target_version` that can be restored, either from a
+    /// state snapshot directly, or from a state snapshot with a chain of state deltas applied on
+    /// top of it. The chain with the fewest deltas is preferred among those reaching a version.
+    pub fn select_state_snapshot_with_deltas(
+        &self,
+        target_version: Version,
+    ) -> Result<Option<StateSnapshotWithDeltas>> {
+        let mut reachable: BTreeMap<Version, StateSnapshotWithDeltas> = self
+            .state_snapshot_backups
+            .iter()
+            .filter(|s| s.version <= target_version)
+            .map(|s| {
+                (s.version, StateSnapshotWithDeltas {
+                    snapshot: s.clone(),
+                    deltas: Vec::new(),
+                })
+            })
+            .collect();
+        // Sorted by version, so the base of a delta is settled before the delta is looked at.
+        for delta in &self.state_delta_backups {
+            if delta.version > target_version {
+                break;
+            }
+            if let Some(base) = reachable.get(&delta.base_version) {
+                let mut chain = base.clone();
+                chain.deltas.push(delta.clone());
+                match reachable.get(&delta.version) {
+                    Some(existing) if existing.deltas.len() <= chain.deltas.len() => (),
+                    _ => {
+                        reachable.insert(delta.version, chain);
+                    },
+                }
+            }
+        }
+
+        Ok(reachable.into_iter().next_back().map(|(_, chain)| chain))
+    }
+
+    pub fn expect_state_snapshot_with_deltas(
+        &self,
+        version: Version,
+    ) -> Result<StateSnapshotWithDeltas> {
+        self.select_state_snapshot_with_deltas(version)?
+            .filter(|chain| chain.version() == version)
+            .ok_or_else(|| anyhow!("State snapshot or delta not found at version {}", version))
+    }
+
+    /// All states that can be restored, ordered by version.
+    pub fn states(&self) -> Vec<StateSnapshotWithDeltas> {
+        let versions: BTreeSet<_> = self
+            .state_snapshot_backups
+            .iter()
+            .map(|s| s.version)
+            .chain(self.state_delta_backups.iter().map(|d| d.version))
+            .collect();
+        versions
+            .into_iter()
+            .filter_map(|version| self.expect_state_snapshot_with_deltas(version).ok())
+            .collect()
+    }
+
     pub fn select_transaction_backups(
         &self,
         start_version: Version,
         target_version: Version,
     ) -> Result<Vec<TransactionBackupMeta>> {
         // This can be more flexible, but for now we assume and check backups are continuous in
-        // range (which is always true when we backup from a single backup coordinator)
-        let mut next_ver = 0;
+        // range (which is always true when we backup from a single backup coordinator). Backups
+        // entirely before `start_version` are not needed and can have been pruned.
+        let mut next_ver = None;
         let mut res = Vec::new();
         for backup in self.transaction_backups.iter().sorted() {
             if backup.first_version > target_version {
                 break;
             }
-            ensure!(
-                backup.first_version == next_ver,
-                "Transaction backup ranges not continuous, expecting version {}, got {}.",
-                next_ver,
-                backup.first_version,
-            );
-
-            if backup.last_version >= start_version {
-                res.push(backup.clone());
+            if backup.last_version < start_version {
+                continue;
+            }
+            match next_ver {
+                Some(next_ver) => ensure!(
+                    backup.first_version == next_ver,
+                    "Transaction backup ranges not continuous, expecting version {}, got {}.",
+                    next_ver,
+                    backup.first_version,
+                ),
+                None => ensure!(
+                    backup.first_version <= start_version,
+                    "Transaction backups start at version {}, after the requested {}.",
+                    backup.first_version,
+                    start_version,
+                ),
             }
 
-            next_ver = backup.last_version + 1;
+            res.push(backup.clone());
+            next_ver = Some(backup.last_version + 1);
         }
 
         Ok(res)
@@ -235,6 +315,29 @@ impl MetadataView {
         Self::compact_backups(&self.state_snapshot_backups, compaction_cnt)
     }
 
+    pub fn compact_state_delta_backups(
+        &mut self,
+        compaction_cnt: usize,
+    ) -> Result<Vec<&[StateDeltaBackupMeta]>> {
+        Self::compact_backups(&self.state_delta_backups, compaction_cnt)
+    }
+
+    pub(crate) fn epoch_ending_backups(&self) -> &[EpochEndingBackupMeta] {
+        &self.epoch_ending_backups
+    }
+
+    pub(crate) fn state_snapshot_backups(&self) -> &[StateSnapshotBackupMeta] {
+        &self.state_snapshot_backups
+    }
+
+    pub fn state_delta_backups(&self) -> &[StateDeltaBackupMeta] {
+        &self.state_delta_backups
+    }
+
+    pub(crate) fn transaction_backups(&self) -> &[TransactionBackupMeta] {
+        &self.transaction_backups
+    }
+
     pub fn get_file_handles(&self) -> Vec<FileHandle> {
         self.select_latest_compaction_timestamps()
             .as_ref()
@@ -243,6 +346,22 @@ impl MetadataView {
     }
 }
 
+/// A state snapshot, and the state deltas to apply on top of it in order.
+#[derive(Clone, Debug)]
+pub struct StateSnapshotWithDeltas {
+    pub snapshot: StateSnapshotBackupMeta,
+    pub deltas: Vec<StateDeltaBackupMeta>,
+}
+
+impl StateSnapshotWithDeltas {
+    /// Version of the state after all deltas are applied.
+    pub fn version(&self) -> Version {
+        self.deltas
+            .last()
+            .map_or(self.snapshot.version, |delta| delta.version)
+    }
+}
+
 pub struct BackupStorageState {
     pub latest_epoch_ending_epoch: Option<u64>,
     pub latest_state_snapshot_epoch: Option<u64>,
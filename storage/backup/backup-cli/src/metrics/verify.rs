@@ -2,7 +2,10 @@
 // Parts of the project are originally copyright © Meta Platforms, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use aptos_push_metrics::{register_int_gauge, IntGauge};
+use aptos_push_metrics::{
+    register_int_counter_vec, register_int_gauge, register_int_gauge_vec, IntCounterVec, IntGauge,
+    IntGaugeVec,
+};
 use once_cell::sync::Lazy;
 
 pub static VERIFY_EPOCH_ENDING_EPOCH: Lazy<IntGauge> = Lazy::new(|| {
@@ -76,3 +79,55 @@ pub static VERIFY_COORDINATOR_FAIL_TS: Lazy<IntGauge> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub static CONTINUOUS_VERIFY_ROUND: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_db_backup_continuous_verify_round",
+        "Number of verification rounds the continuous verify coordinator has finished."
+    )
+    .unwrap()
+});
+
+pub static CONTINUOUS_VERIFY_ROUND_TS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_db_backup_continuous_verify_round_timestamp_s",
+        "Timestamp when the last verification round of the continuous verify coordinator finished."
+    )
+    .unwrap()
+});
+
+pub static CONTINUOUS_VERIFY_SUCCEEDED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "aptos_db_backup_continuous_verify_succeeded",
+        "Number of backups verified successfully by the continuous verify coordinator.",
+        &["backup_type"]
+    )
+    .unwrap()
+});
+
+pub static CONTINUOUS_VERIFY_FAILED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "aptos_db_backup_continuous_verify_failed",
+        "Number of backups failing verification by the continuous verify coordinator.",
+        &["backup_type"]
+    )
+    .unwrap()
+});
+
+pub static CONTINUOUS_VERIFY_COVERAGE_GAPS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "aptos_db_backup_continuous_verify_coverage_gaps",
+        "Number of gaps in the ranges covered by the backups in the metadata.",
+        &["backup_type"]
+    )
+    .unwrap()
+});
+
+pub static CONTINUOUS_VERIFY_NEVER_VERIFIED: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "aptos_db_backup_continuous_verify_never_verified",
+        "Number of backups the continuous verify coordinator hasn't got to verify yet.",
+        &["backup_type"]
+    )
+    .unwrap()
+});
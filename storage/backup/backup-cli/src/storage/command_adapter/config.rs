@@ -70,6 +70,11 @@ pub struct Commands {
     pub list_metadata_files: String,
     /// Command line to backup one metadata file to a metadata backup folder
     pub backup_metadata_file: Option<String>,
+    /// Command line to delete a file, needed only to prune backups.
+    /// input env vars:
+    ///     $FILE_HANDLE
+    /// expected to succeed if the file doesn't exist.
+    pub delete_file: Option<String>,
 }
 
 #[derive(Clone, Default, Deserialize)]
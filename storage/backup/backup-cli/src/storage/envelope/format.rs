@@ -0,0 +1,269 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Layout of an envelope:
+//!
+//! ```text
+//! header:   MAGIC (8 bytes) | salt (32 bytes) | name length (u16 BE) | name
+//! segments: ciphertext length (u32 BE) | last segment flag (u8) | ciphertext with tag
+//!           ... repeated, until a segment flagged as the last one
+//! trailer:  Ed25519 signature (64 bytes) over the name, the file handle (if bound), the hash of
+//!           the header and the hashes of the segments
+//! ```
+//!
+//! Each file is encrypted with its own key derived from the operator key and the random salt, so
+//! the segment index can serve as the AES-GCM nonce. The last segment flag is part of both the
+//! nonce and the associated data, so truncating a file at a segment boundary is detected.
+//!
+//! Every segment carries its own tag, so a reader releases the content of a segment as soon as it
+//! is authenticated, and never holds more than one segment in memory. The signature is only
+//! checked at the end, a reader reports its failure in place of the end of the content.
+//!
+//! The file handle is not stored in the envelope, the reader supplies the handle it reads from and
+//! the signature only verifies if that is the handle the file was written as.
+
+use anyhow::{bail, ensure, format_err, Result};
+use aptos_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+    HashValue, Signature, SigningKey,
+};
+use aptos_crypto_derive::{BCSCryptoHash, CryptoHasher};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, MAX_TAG_LEN, NONCE_LEN},
+    digest, hkdf,
+};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub(super) const MAGIC: &[u8; 8] = b"APTENV01";
+/// base64 of the first 6 bytes of `MAGIC`, which is how an armored (base64 encoded) envelope
+/// starts.
+pub(super) const ARMORED_MAGIC_PREFIX: &[u8; 8] = b"QVBURU5W";
+const SALT_LEN: usize = 32;
+const SEGMENT_SIZE: usize = 64 * 1024;
+const SIGNATURE_LEN: usize = 64;
+const KEY_DERIVATION_INFO: &[u8] = b"APTOS::BackupEnvelope::v1";
+
+/// What the trailer signature signs.
+#[derive(Deserialize, Serialize, CryptoHasher, BCSCryptoHash)]
+pub(super) struct EnvelopeDigest {
+    /// The name the file was created with, i.e. the last component of its file handle.
+    pub name: String,
+    /// The handle the file was written as. Not bound for metadata files, which are moved around
+    /// by `backup_metadata_file()`.
+    pub file_handle: Option<String>,
+    /// SHA-256 of the header.
+    pub header_hash: HashValue,
+    /// SHA-256 of the concatenated SHA-256 hashes of the segments, each over the segment's
+    /// length, flag and ciphertext.
+    pub segments_hash: HashValue,
+}
+
+fn file_key(encryption_key: &[u8; 32], salt: &[u8]) -> Result<LessSafeKey> {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(encryption_key);
+    let okm = prk
+        .expand(&[KEY_DERIVATION_INFO], &AES_256_GCM)
+        .map_err(|_| format_err!("Key derivation failed."))?;
+    Ok(LessSafeKey::new(UnboundKey::from(okm)))
+}
+
+fn nonce(segment_index: u64, is_last: bool) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..8].copy_from_slice(&segment_index.to_be_bytes());
+    nonce[NONCE_LEN - 1] = is_last as u8;
+    Nonce::assume_unique_for_key(nonce)
+}
+
+fn finish_hash(ctx: digest::Context) -> HashValue {
+    HashValue::from_slice(ctx.finish().as_ref()).expect("SHA-256 output is 32 bytes.")
+}
+
+fn sha256(data: &[u8]) -> HashValue {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(data);
+    finish_hash(ctx)
+}
+
+fn frame_header(ciphertext_len: usize, is_last: bool) -> Vec<u8> {
+    let mut frame_header = (ciphertext_len as u32).to_be_bytes().to_vec();
+    frame_header.push(is_last as u8);
+    frame_header
+}
+
+/// Reads until `buf` is full or EOF is hit, returns the number of bytes read.
+pub(super) async fn read_full(
+    reader: &mut (impl AsyncRead + Unpin),
+    buf: &mut [u8],
+) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = reader.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+/// Encrypts and signs everything read from `plaintext` into `output`.
+pub(super) async fn seal(
+    encryption_key: &[u8; 32],
+    signing_key: &Ed25519PrivateKey,
+    name: &str,
+    file_handle: Option<&str>,
+    mut plaintext: impl AsyncRead + Unpin,
+    mut output: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let key = file_key(encryption_key, &salt)?;
+
+    let mut header = MAGIC.to_vec();
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&u16::try_from(name.len())?.to_be_bytes());
+    header.extend_from_slice(name.as_bytes());
+    output.write_all(&header).await?;
+
+    let mut segments_hash_ctx = digest::Context::new(&digest::SHA256);
+    let mut segment_index = 0u64;
+    loop {
+        let mut segment = vec![0u8; SEGMENT_SIZE];
+        let len = read_full(&mut plaintext, &mut segment).await?;
+        segment.truncate(len);
+        // A segment not filled up means EOF, and the last segment can be empty.
+        let is_last = len < SEGMENT_SIZE;
+        key.seal_in_place_append_tag(
+            nonce(segment_index, is_last),
+            Aad::from([is_last as u8]),
+            &mut segment,
+        )
+        .map_err(|_| format_err!("Encryption failed."))?;
+
+        let frame_header = frame_header(segment.len(), is_last);
+        segments_hash_ctx
+            .update(sha256(&[frame_header.as_slice(), &segment[..]].concat()).as_ref());
+        output.write_all(&frame_header).await?;
+        output.write_all(&segment).await?;
+
+        if is_last {
+            break;
+        }
+        segment_index += 1;
+    }
+
+    let signature = signing_key.sign(&EnvelopeDigest {
+        name: name.to_string(),
+        file_handle: file_handle.map(str::to_string),
+        header_hash: sha256(&header),
+        segments_hash: finish_hash(segments_hash_ctx),
+    })?;
+    output.write_all(&signature.to_bytes()).await?;
+    output.shutdown().await?;
+    Ok(())
+}
+
+/// Verifies and decrypts an envelope read from `input` into `output`. `name` is the last
+/// component of the file handle being read, which must match the name the file was created with,
+/// and `file_handle` must match the handle it was sealed with.
+/// The content of each segment is written to `output` once its tag is verified, and the signature
+/// over the whole envelope is verified after the last segment, before `output` is shut down.
+pub(super) async fn open(
+    encryption_key: &[u8; 32],
+    verifying_key: &Ed25519PublicKey,
+    name: &str,
+    file_handle: Option<&str>,
+    mut input: impl AsyncRead + Unpin,
+    mut output: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let mut magic = [0u8; 8];
+    ensure!(
+        read_full(&mut input, &mut magic).await? == magic.len() && &magic == MAGIC,
+        "{} is not an envelope. Refusing to read unencrypted or unsigned backup file.",
+        name,
+    );
+    let mut salt = [0u8; SALT_LEN];
+    input.read_exact(&mut salt).await?;
+    let name_len = input.read_u16().await?;
+    let mut name_in_header = vec![0u8; name_len as usize];
+    input.read_exact(&mut name_in_header).await?;
+    ensure!(
+        name_in_header == name.as_bytes(),
+        "Envelope was created for {}, but is read as {}.",
+        String::from_utf8_lossy(&name_in_header),
+        name,
+    );
+    let header_hash = sha256(
+        &[
+            MAGIC.as_slice(),
+            &salt[..],
+            &name_len.to_be_bytes()[..],
+            &name_in_header[..],
+        ]
+        .concat(),
+    );
+    let key = file_key(encryption_key, &salt)?;
+
+    let mut segments_hash_ctx = digest::Context::new(&digest::SHA256);
+    let mut segment_index = 0u64;
+    loop {
+        let len = input.read_u32().await? as usize;
+        ensure!(
+            len <= SEGMENT_SIZE + MAX_TAG_LEN,
+            "Segment too large: {} bytes.",
+            len
+        );
+        let is_last = match input.read_u8().await? {
+            0 => false,
+            1 => true,
+            flag => bail!("Bad segment flag: {}", flag),
+        };
+        let mut segment = vec![0u8; len];
+        input.read_exact(&mut segment).await?;
+        segments_hash_ctx.update(
+            sha256(&[frame_header(len, is_last).as_slice(), &segment[..]].concat()).as_ref(),
+        );
+
+        let plaintext = key
+            .open_in_place(
+                nonce(segment_index, is_last),
+                Aad::from([is_last as u8]),
+                &mut segment,
+            )
+            .map_err(|_| {
+                format_err!(
+                    "Segment {} of {} failed authentication.",
+                    segment_index,
+                    name
+                )
+            })?;
+        output.write_all(plaintext).await?;
+
+        if is_last {
+            break;
+        }
+        segment_index += 1;
+    }
+
+    let mut signature = [0u8; SIGNATURE_LEN];
+    input.read_exact(&mut signature).await?;
+    ensure!(
+        input.read_u8().await.is_err(),
+        "Unexpected bytes after the signature of {}.",
+        name
+    );
+    Ed25519Signature::try_from(&signature[..])?
+        .verify(
+            &EnvelopeDigest {
+                name: name.to_string(),
+                file_handle: file_handle.map(str::to_string),
+                header_hash,
+                segments_hash: finish_hash(segments_hash_ctx),
+            },
+            verifying_key,
+        )
+        .map_err(|e| format_err!("Bad signature on {}: {}", name, e))?;
+
+    output.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,318 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+mod format;
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    storage::{
+        envelope::format::{open, read_full, seal, ARMORED_MAGIC_PREFIX},
+        BackupHandle, BackupHandleRef, BackupStorage, FileHandle, FileHandleRef, ShellSafeName,
+        TextLine,
+    },
+    utils::{
+        error_notes::ErrorNotes,
+        pipe::{PipedReader, PipedWriter},
+    },
+};
+use anyhow::{bail, ensure, format_err, Result};
+use aptos_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+    PrivateKey, ValidCryptoMaterialStringExt,
+};
+use async_trait::async_trait;
+use clap::Parser;
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const ARMOR_READ_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Default, Parser)]
+pub struct EnvelopeOpt {
+    #[clap(
+        long,
+        value_parser,
+        help = "File holding a hex encoded 32 bytes key. If set, every backup file and metadata \
+        file is encrypted with it (AES-256-GCM) when written, and is required to be a valid \
+        envelope when read."
+    )]
+    pub envelope_encryption_key_file: Option<PathBuf>,
+    #[clap(
+        long,
+        value_parser,
+        requires = "envelope_encryption_key_file",
+        help = "File holding a hex encoded Ed25519 private key, used to sign every file written. \
+        Required to create backups with --envelope-encryption-key-file."
+    )]
+    pub envelope_signing_key_file: Option<PathBuf>,
+    #[clap(
+        long,
+        requires = "envelope_encryption_key_file",
+        help = "Hex encoded Ed25519 public key files are verified against when read. Defaults to \
+        the public key of --envelope-signing-key-file, so restoring doesn't need the private key."
+    )]
+    pub envelope_verifying_key: Option<String>,
+}
+
+impl EnvelopeOpt {
+    pub fn enabled(&self) -> bool {
+        self.envelope_encryption_key_file.is_some()
+    }
+
+    pub async fn load_keys(&self) -> Result<EnvelopeKeys> {
+        let encryption_key_file = self
+            .envelope_encryption_key_file
+            .as_ref()
+            .ok_or_else(|| format_err!("--envelope-encryption-key-file not set."))?;
+        let encryption_key = hex::decode(read_key_file(encryption_key_file).await?)?;
+        ensure!(
+            encryption_key.len() == 32,
+            "Envelope encryption key must be 32 bytes, got {}.",
+            encryption_key.len(),
+        );
+
+        let signing_key = match &self.envelope_signing_key_file {
+            Some(file) => Some(Ed25519PrivateKey::from_encoded_string(
+                &read_key_file(file).await?,
+            )?),
+            None => None,
+        };
+        let verifying_key = match (&self.envelope_verifying_key, &signing_key) {
+            (Some(key), _) => Ed25519PublicKey::from_encoded_string(key)?,
+            (None, Some(signing_key)) => signing_key.public_key(),
+            (None, None) => {
+                bail!("Either --envelope-verifying-key or --envelope-signing-key-file is required.")
+            },
+        };
+
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&encryption_key);
+        Ok(EnvelopeKeys {
+            encryption_key: key,
+            signing_key,
+            verifying_key,
+        })
+    }
+
+    /// Wraps `storage` with `EnvelopeStorage` if enabled.
+    pub async fn wrap(&self, storage: Arc<dyn BackupStorage>) -> Result<Arc<dyn BackupStorage>> {
+        Ok(if self.enabled() {
+            Arc::new(EnvelopeStorage::new(storage, self.load_keys().await?))
+        } else {
+            storage
+        })
+    }
+}
+
+async fn read_key_file(path: &Path) -> Result<String> {
+    Ok(tokio::fs::read_to_string(path)
+        .await
+        .err_notes(path)?
+        .trim()
+        .to_string())
+}
+
+/// Decodes a base64 armored envelope from `armored` into `envelope`, a bounded piece at a time.
+async fn decode_armored(
+    mut armored: impl AsyncRead + Unpin,
+    mut envelope: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let mut buf = vec![0u8; ARMOR_READ_SIZE];
+    let mut pending = Vec::new();
+    loop {
+        let n = read_full(&mut armored, &mut buf).await?;
+        pending.extend(buf[..n].iter().filter(|b| !b.is_ascii_whitespace()));
+        // Only whole groups of 4 characters decode on their own, except at the end.
+        let decodable = if n < buf.len() {
+            pending.len()
+        } else {
+            pending.len() - pending.len() % 4
+        };
+        envelope
+            .write_all(&base64::decode(&pending[..decodable])?)
+            .await?;
+        pending.drain(..decodable);
+        if n < buf.len() {
+            break;
+        }
+    }
+    envelope.shutdown().await?;
+    Ok(())
+}
+
+pub struct EnvelopeKeys {
+    pub encryption_key: [u8; 32],
+    /// Only needed to write.
+    pub signing_key: Option<Ed25519PrivateKey>,
+    pub verifying_key: Ed25519PublicKey,
+}
+
+/// A BackupStorage that wraps every file going through another storage in an envelope, which is
+/// encrypted with an operator supplied key and signed by the operator, so backups can be kept in
+/// storage that is not trusted. See `format.rs` for the layout.
+///
+/// The signature of a backup file binds the file handle it was written as (and the name it was
+/// created with, which is required to be the last component of that handle, true for all storages
+/// in this crate and the sample command adapter configs). A file moved to another handle, be it in
+/// the same or another backup, is refused. Since the manifest of each backup is such a file too
+/// and lists every other file of the backup by handle, a restore starting from a manifest refuses
+/// files that are missing, swapped, tampered with, or not signed.
+///
+/// Reading a file streams its content one authenticated segment at a time, and verifies the
+/// signature over the whole of it before reporting EOF. A file that turns out to be forged ends in
+/// an error instead, and the restore code reads every file to EOF before acting on any of it.
+///
+/// Metadata files need to be lines of text, so envelopes of them are base64 encoded into a single
+/// line. Reading tells the two apart by the first bytes. Metadata files are moved around by
+/// `backup_metadata_file()`, so only their name is bound.
+pub struct EnvelopeStorage {
+    inner: Arc<dyn BackupStorage>,
+    keys: Arc<EnvelopeKeys>,
+}
+
+impl EnvelopeStorage {
+    const PIPE_BUFFER_SIZE: usize = 1024 * 1024;
+
+    pub fn new(inner: Arc<dyn BackupStorage>, keys: EnvelopeKeys) -> Self {
+        Self {
+            inner,
+            keys: Arc::new(keys),
+        }
+    }
+
+    fn signing_key(&self) -> Result<&Ed25519PrivateKey> {
+        self.keys
+            .signing_key
+            .as_ref()
+            .ok_or_else(|| format_err!("Writing envelopes requires a signing key."))
+    }
+
+    fn name_of(file_handle: &FileHandleRef) -> Result<String> {
+        Ok(Path::new(file_handle)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .ok_or_else(|| format_err!("cannot extract filename from {}", file_handle))?
+            .to_string())
+    }
+}
+
+#[async_trait]
+impl BackupStorage for EnvelopeStorage {
+    async fn create_backup(&self, name: &ShellSafeName) -> Result<BackupHandle> {
+        self.inner.create_backup(name).await
+    }
+
+    async fn create_for_write(
+        &self,
+        backup_handle: &BackupHandleRef,
+        name: &ShellSafeName,
+    ) -> Result<(FileHandle, Box<dyn AsyncWrite + Send + Unpin>)> {
+        // Fail early instead of when the writer is shut down.
+        self.signing_key()?;
+        let (file_handle, file) = self.inner.create_for_write(backup_handle, name).await?;
+        let keys = self.keys.clone();
+        let name = name.to_string();
+        let bound_handle = file_handle.clone();
+        let writer = PipedWriter::new(Self::PIPE_BUFFER_SIZE, |plaintext| async move {
+            let signing_key = keys.signing_key.as_ref().expect("Checked above.");
+            seal(
+                &keys.encryption_key,
+                signing_key,
+                &name,
+                Some(bound_handle.as_str()),
+                plaintext,
+                file,
+            )
+            .await
+        });
+        Ok((file_handle, Box::new(writer)))
+    }
+
+    async fn open_for_read(
+        &self,
+        file_handle: &FileHandleRef,
+    ) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        let name = Self::name_of(file_handle)?;
+        let file_handle = file_handle.to_string();
+        let mut file = self.inner.open_for_read(&file_handle).await?;
+        let keys = self.keys.clone();
+        let reader = PipedReader::new(Self::PIPE_BUFFER_SIZE, |plaintext| async move {
+            let mut prefix = vec![0u8; ARMORED_MAGIC_PREFIX.len()];
+            let n = read_full(&mut file, &mut prefix).await?;
+            // Anything too short falls through to `open`, which rejects it.
+            prefix.truncate(n);
+            if prefix == ARMORED_MAGIC_PREFIX {
+                let (envelope_writer, envelope) = tokio::io::duplex(Self::PIPE_BUFFER_SIZE);
+                tokio::try_join!(
+                    decode_armored(prefix.as_slice().chain(file), envelope_writer),
+                    open(
+                        &keys.encryption_key,
+                        &keys.verifying_key,
+                        &name,
+                        None,
+                        envelope,
+                        plaintext,
+                    ),
+                )?;
+                Ok(())
+            } else {
+                open(
+                    &keys.encryption_key,
+                    &keys.verifying_key,
+                    &name,
+                    Some(file_handle.as_str()),
+                    prefix.as_slice().chain(file),
+                    plaintext,
+                )
+                .await
+            }
+        });
+        Ok(Box::new(reader))
+    }
+
+    async fn list_metadata_files(&self) -> Result<Vec<FileHandle>> {
+        self.inner.list_metadata_files().await
+    }
+
+    async fn backup_metadata_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        self.inner.backup_metadata_file(file_handle).await
+    }
+
+    async fn delete_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        self.inner.delete_file(file_handle).await
+    }
+
+    async fn file_size(&self, file_handle: &FileHandleRef) -> Result<Option<u64>> {
+        self.inner.file_size(file_handle).await
+    }
+
+    async fn save_metadata_lines(
+        &self,
+        name: &ShellSafeName,
+        lines: &[TextLine],
+    ) -> Result<FileHandle> {
+        let content = lines
+            .iter()
+            .map(|e| e.as_ref())
+            .collect::<Vec<&str>>()
+            .join("");
+        let mut envelope = Vec::new();
+        seal(
+            &self.keys.encryption_key,
+            self.signing_key()?,
+            name.as_ref(),
+            None,
+            content.as_bytes(),
+            &mut envelope,
+        )
+        .await?;
+        let armored = TextLine::new(&base64::encode(&envelope))?;
+        self.inner.save_metadata_lines(name, &[armored]).await
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::storage::{
+    local_fs::LocalFs,
+    test_util::{
+        arb_backups, arb_metadata_files, test_save_and_list_metadata_files_impl,
+        test_write_and_read_impl,
+    },
+};
+use aptos_crypto::Uniform;
+use aptos_temppath::TempPath;
+use proptest::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::path::PathBuf;
+use tokio::{io::AsyncWriteExt, runtime::Runtime};
+
+fn keys(seed: u8) -> EnvelopeKeys {
+    let signing_key = Ed25519PrivateKey::generate(&mut StdRng::from_seed([seed; 32]));
+    EnvelopeKeys {
+        encryption_key: [seed; 32],
+        verifying_key: signing_key.public_key(),
+        signing_key: Some(signing_key),
+    }
+}
+
+fn envelope_store(dir: PathBuf, seed: u8) -> EnvelopeStorage {
+    EnvelopeStorage::new(Arc::new(LocalFs::new(dir)), keys(seed))
+}
+
+async fn write_file(store: &dyn BackupStorage, backup: &str, name: &str, content: &[u8]) -> String {
+    let backup_handle = store.create_backup(&backup.parse().unwrap()).await.unwrap();
+    let (file_handle, mut file) = store
+        .create_for_write(&backup_handle, &name.parse().unwrap())
+        .await
+        .unwrap();
+    file.write_all(content).await.unwrap();
+    file.shutdown().await.unwrap();
+    file_handle
+}
+
+async fn read_file(store: &dyn BackupStorage, file_handle: &str) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    store
+        .open_for_read(file_handle)
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(10))]
+
+    #[test]
+    fn test_write_and_read(
+        backups in arb_backups()
+    ) {
+        let tmpdir = TempPath::new();
+        tmpdir.create_as_dir().unwrap();
+        let store = envelope_store(tmpdir.path().to_path_buf(), 1);
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(test_write_and_read_impl(Box::new(store), backups));
+    }
+
+    #[test]
+    fn test_save_list_metadata_files(
+        input in arb_metadata_files(),
+    ) {
+        let tmpdir = TempPath::new();
+        tmpdir.create_as_dir().unwrap();
+        let store = envelope_store(tmpdir.path().to_path_buf(), 1);
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(test_save_and_list_metadata_files_impl(Box::new(store), input));
+    }
+}
+
+#[tokio::test]
+async fn test_multi_segment_round_trip() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let store = envelope_store(tmpdir.path().to_path_buf(), 1);
+
+    // Spans several segments, with a non-empty last segment.
+    let content = (0..300_000).map(|i| i as u8).collect::<Vec<_>>();
+    let file_handle = write_file(&store, "backup", "chunk", &content).await;
+    assert_eq!(read_file(&store, &file_handle).await.unwrap(), content);
+
+    // Exactly fills the segments, with an empty last segment.
+    let content = vec![7u8; 128 * 1024];
+    let file_handle = write_file(&store, "backup2", "chunk", &content).await;
+    assert_eq!(read_file(&store, &file_handle).await.unwrap(), content);
+}
+
+#[tokio::test]
+async fn test_read_streams_segments() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let store = envelope_store(tmpdir.path().to_path_buf(), 1);
+
+    let content = (0..1_000_000).map(|i| i as u8).collect::<Vec<_>>();
+    let file_handle = write_file(&store, "backup", "chunk", &content).await;
+    let path = tmpdir.path().join(&file_handle);
+    let raw = std::fs::read(&path).unwrap();
+
+    // Content is released as segments are authenticated, before the rest of the file is there.
+    std::fs::write(&path, &raw[..raw.len() / 2]).unwrap();
+    let mut file = store.open_for_read(&file_handle).await.unwrap();
+    let mut first_segment = vec![0u8; 64 * 1024];
+    file.read_exact(&mut first_segment).await.unwrap();
+    assert_eq!(first_segment, content[..first_segment.len()]);
+    assert!(file.read_to_end(&mut vec![]).await.is_err());
+
+    // Armored metadata files are decoded as they are read too.
+    let metadata = "x".repeat(200_000);
+    let metadata_handle = store
+        .save_metadata_line(
+            &"metadata_1.meta".parse().unwrap(),
+            &TextLine::new(&metadata).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(read_file(&store, &metadata_handle).await.unwrap()).unwrap(),
+        metadata + "\n"
+    );
+}
+
+#[tokio::test]
+async fn test_content_is_encrypted() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let store = envelope_store(tmpdir.path().to_path_buf(), 1);
+    let plain = LocalFs::new(tmpdir.path().to_path_buf());
+
+    let content = b"some very recognizable plaintext".repeat(10);
+    let file_handle = write_file(&store, "backup", "chunk", &content).await;
+    let raw = read_file(&plain, &file_handle).await.unwrap();
+    assert!(!raw
+        .windows(content.len())
+        .any(|window| window == content.as_slice()));
+
+    let metadata_handle = store
+        .save_metadata_line(
+            &"metadata_1.meta".parse().unwrap(),
+            &TextLine::new("some metadata").unwrap(),
+        )
+        .await
+        .unwrap();
+    let raw = String::from_utf8(read_file(&plain, &metadata_handle).await.unwrap()).unwrap();
+    assert!(!raw.contains("some metadata"));
+    assert_eq!(raw.lines().count(), 1);
+}
+
+#[tokio::test]
+async fn test_refuse_tampered() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let store = envelope_store(tmpdir.path().to_path_buf(), 1);
+
+    let content = vec![1u8; 100_000];
+    let file_handle = write_file(&store, "backup", "chunk", &content).await;
+    let path = tmpdir.path().join(&file_handle);
+    let raw = std::fs::read(&path).unwrap();
+
+    // Flipping any byte, in the header, a segment or the signature, is detected.
+    for pos in [10, 100, raw.len() / 2, raw.len() - 1] {
+        let mut tampered = raw.clone();
+        tampered[pos] ^= 1;
+        std::fs::write(&path, &tampered).unwrap();
+        assert!(read_file(&store, &file_handle).await.is_err());
+    }
+
+    // With a bad signature, the authenticated segments stream out, but the end of the content is
+    // reported as an error instead of EOF.
+    let mut tampered = raw.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    std::fs::write(&path, &tampered).unwrap();
+    let mut file = store.open_for_read(&file_handle).await.unwrap();
+    let mut buf = [0u8; 1];
+    assert_eq!(file.read(&mut buf).await.unwrap(), 1);
+    assert!(file.read_to_end(&mut vec![]).await.is_err());
+
+    // So is truncation and trailing garbage.
+    std::fs::write(&path, &raw[..raw.len() - 100]).unwrap();
+    assert!(read_file(&store, &file_handle).await.is_err());
+    std::fs::write(&path, [raw.as_slice(), b"x"].concat()).unwrap();
+    assert!(read_file(&store, &file_handle).await.is_err());
+
+    std::fs::write(&path, &raw).unwrap();
+    assert_eq!(read_file(&store, &file_handle).await.unwrap(), content);
+}
+
+#[tokio::test]
+async fn test_refuse_unsigned_or_misplaced() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let store = envelope_store(tmpdir.path().to_path_buf(), 1);
+    let plain = LocalFs::new(tmpdir.path().to_path_buf());
+
+    // Written without an envelope.
+    let file_handle = write_file(&plain, "plain", "chunk", b"plaintext").await;
+    assert!(read_file(&store, &file_handle).await.is_err());
+
+    // Signed by somebody else, or encrypted with a different key.
+    let other = envelope_store(tmpdir.path().to_path_buf(), 2);
+    let file_handle = write_file(&other, "other", "chunk", b"content").await;
+    assert!(read_file(&store, &file_handle).await.is_err());
+
+    // Moved to a file of a different name.
+    let file_handle = write_file(&store, "backup", "chunk1", b"content").await;
+    std::fs::copy(
+        tmpdir.path().join(&file_handle),
+        tmpdir.path().join("backup/chunk2"),
+    )
+    .unwrap();
+    assert!(read_file(&store, "backup/chunk2").await.is_err());
+    assert!(read_file(&store, &file_handle).await.is_ok());
+
+    // Moved to another backup, under the same name.
+    std::fs::create_dir_all(tmpdir.path().join("backup2")).unwrap();
+    std::fs::copy(
+        tmpdir.path().join(&file_handle),
+        tmpdir.path().join("backup2/chunk1"),
+    )
+    .unwrap();
+    assert!(read_file(&store, "backup2/chunk1").await.is_err());
+}
+
+#[tokio::test]
+async fn test_read_only_with_verifying_key() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let store = envelope_store(tmpdir.path().to_path_buf(), 1);
+    let file_handle = write_file(&store, "backup", "chunk", b"content").await;
+
+    let mut read_only_keys = keys(1);
+    read_only_keys.signing_key = None;
+    let read_only = EnvelopeStorage::new(
+        Arc::new(LocalFs::new(tmpdir.path().to_path_buf())),
+        read_only_keys,
+    );
+    assert_eq!(
+        read_file(&read_only, &file_handle).await.unwrap(),
+        b"content"
+    );
+    assert!(read_only
+        .create_for_write(&"backup".to_string(), &"chunk2".parse().unwrap())
+        .await
+        .is_err());
+}
@@ -10,7 +10,7 @@ use crate::{
     storage::{BackupStorage, ShellSafeName, TextLine},
     utils::{error_notes::ErrorNotes, path_exists, PathToString},
 };
-use anyhow::{bail, format_err, Result};
+use anyhow::{bail, ensure, format_err, Result};
 use aptos_logger::info;
 use async_trait::async_trait;
 use clap::Parser;
@@ -18,11 +18,11 @@ use serde::{Deserialize, Serialize};
 use std::{
     ffi::OsStr,
     io,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     str::FromStr,
 };
 use tokio::{
-    fs::{create_dir_all, read_dir, rename, OpenOptions},
+    fs::{create_dir_all, metadata, read_dir, remove_file, rename, OpenOptions},
     io::{AsyncRead, AsyncWrite, AsyncWriteExt},
 };
 
@@ -147,6 +147,27 @@ impl BackupStorage for LocalFs {
         Ok(())
     }
 
+    async fn delete_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        // Never delete anything outside of the backup dir, whatever the handle says.
+        ensure!(
+            Path::new(file_handle)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir)),
+            "Refusing to delete {}, which is not a relative path inside the backup dir.",
+            file_handle,
+        );
+        let path = self.dir.join(file_handle);
+        match remove_file(&path).await {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            res => Ok(res.err_notes(&path)?),
+        }
+    }
+
+    async fn file_size(&self, file_handle: &FileHandleRef) -> Result<Option<u64>> {
+        let path = self.dir.join(file_handle);
+        Ok(Some(metadata(&path).await.err_notes(&path)?.len()))
+    }
+
     async fn save_metadata_lines(
         &self,
         name: &ShellSafeName,
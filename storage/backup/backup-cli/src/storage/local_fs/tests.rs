@@ -4,7 +4,7 @@
 
 use super::*;
 use crate::storage::test_util::{
-    arb_backups, arb_metadata_files, test_save_and_list_metadata_files_impl,
+    arb_backups, arb_metadata_files, test_delete_file_impl, test_save_and_list_metadata_files_impl,
     test_write_and_read_impl,
 };
 use aptos_temppath::TempPath;
@@ -26,6 +26,18 @@ proptest! {
         rt.block_on(test_write_and_read_impl(Box::new(store), backups));
     }
 
+    #[test]
+    fn test_delete_file(
+        backups in arb_backups()
+    ) {
+        let tmpdir = TempPath::new();
+        tmpdir.create_as_dir().unwrap();
+        let store = LocalFs::new(tmpdir.path().to_path_buf());
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(test_delete_file_impl(Box::new(store), tmpdir.path(), backups));
+    }
+
     #[test]
     fn test_save_list_metadata_files(
         input in arb_metadata_files(),
@@ -38,3 +50,23 @@ proptest! {
         rt.block_on(test_save_and_list_metadata_files_impl(Box::new(store), input));
     }
 }
+
+#[tokio::test]
+async fn test_delete_file_refuses_paths_outside_dir() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let dir = tmpdir.path().join("backups");
+    let store = LocalFs::new(dir.clone());
+    let outside = tmpdir.path().join("outside");
+    std::fs::write(&outside, b"keep me").unwrap();
+
+    assert!(store.delete_file("../outside").await.is_err());
+    assert!(store.delete_file("backup/../../outside").await.is_err());
+    assert!(store.delete_file(outside.to_str().unwrap()).await.is_err());
+    assert!(outside.exists());
+
+    std::fs::create_dir_all(dir.join("backup")).unwrap();
+    std::fs::write(dir.join("backup/file"), b"delete me").unwrap();
+    store.delete_file("backup/file").await.unwrap();
+    assert!(!dir.join("backup/file").exists());
+}
@@ -0,0 +1,506 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    storage::s3::{
+        config::{S3Config, S3Credentials},
+        sign::{canonical_query, sha256_hex, sign, uri_encode_path, SigningRequest},
+    },
+    utils::error_notes::ErrorNotes,
+};
+use anyhow::{bail, ensure, format_err, Result};
+use aptos_logger::prelude::*;
+use bytes::Bytes;
+use chrono::Utc;
+use futures::TryStreamExt;
+use regex::Regex;
+use reqwest::{header::CONTENT_LENGTH, Method, Response, StatusCode};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_io_timeout::TimeoutReader;
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+/// Failure of a single attempt of a request.
+enum AttemptError {
+    /// Network errors, throttling and server side errors, worth another try.
+    Retryable(anyhow::Error),
+    /// Client side errors (bad credentials, missing key, etc.) which won't go away by retrying.
+    Fatal(anyhow::Error),
+}
+
+/// A minimal S3 REST API client covering the calls needed by `S3`.
+pub(super) struct S3Client {
+    config: S3Config,
+    credentials: S3Credentials,
+    http: reqwest::Client,
+}
+
+impl S3Client {
+    const BACKOFF_BASE_MS: u64 = 200;
+    const BACKOFF_MAX_MS: u64 = 10_000;
+    const EMPTY_PAYLOAD_SHA256: &'static str =
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    pub fn new(config: S3Config) -> Result<Self> {
+        let credentials = config.credentials()?;
+        let http = reqwest::Client::builder()
+            .no_proxy()
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+            .build()?;
+        Ok(Self {
+            config,
+            credentials,
+            http,
+        })
+    }
+
+    pub fn multipart_chunk_size(&self) -> usize {
+        self.config.multipart_chunk_size
+    }
+
+    /// Object key of a path relative to the configured prefix.
+    fn object_key(&self, path: &str) -> String {
+        let prefix = self.config.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}/{}", prefix, path)
+        }
+    }
+
+    /// The inverse of `object_key()`.
+    fn relative_path<'a>(&self, key: &'a str) -> Result<&'a str> {
+        let prefix = self.config.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            Ok(key)
+        } else {
+            key.strip_prefix(prefix)
+                .and_then(|k| k.strip_prefix('/'))
+                .ok_or_else(|| format_err!("Key {} not under prefix {}.", key, prefix))
+        }
+    }
+
+    /// Returns the scheme, the host and the absolute path (URI-encoded) of a key, or of the bucket
+    /// itself if `key` is empty.
+    fn host_and_path(&self, key: &str) -> Result<(String, String, String)> {
+        let endpoint = self.config.endpoint();
+        let (scheme, host) = endpoint
+            .split_once("://")
+            .ok_or_else(|| format_err!("Endpoint {} missing scheme.", endpoint))?;
+        let encoded_key = uri_encode_path(key);
+        Ok(if self.config.path_style {
+            (
+                scheme.to_string(),
+                host.to_string(),
+                format!("/{}/{}", self.config.bucket, encoded_key),
+            )
+        } else {
+            (
+                scheme.to_string(),
+                format!("{}.{}", self.config.bucket, host),
+                format!("/{}", encoded_key),
+            )
+        })
+    }
+
+    fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.request_timeout_secs)
+    }
+
+    /// `total_timeout` bounds the whole request including reading the response body. Regardless
+    /// of it, waiting for the response headers is bounded by the request timeout.
+    async fn attempt(
+        &self,
+        method: &Method,
+        key: &str,
+        query: &[(&str, &str)],
+        extra_headers: &BTreeMap<String, String>,
+        body: &Bytes,
+        total_timeout: Option<Duration>,
+    ) -> Result<Response, AttemptError> {
+        let (scheme, host, path) = self.host_and_path(key).map_err(AttemptError::Fatal)?;
+        let query = canonical_query(query);
+        let payload_sha256 = if body.is_empty() {
+            Self::EMPTY_PAYLOAD_SHA256.to_string()
+        } else {
+            sha256_hex(body)
+        };
+        let mut headers_to_sign = extra_headers.clone();
+        headers_to_sign.insert("host".to_string(), host.clone());
+        let headers = sign(
+            SigningRequest {
+                method: method.as_str(),
+                path: &path,
+                query: &query,
+                payload_sha256: &payload_sha256,
+                headers: headers_to_sign,
+            },
+            &self.config.region,
+            &self.credentials,
+            Utc::now(),
+        );
+
+        let url = if query.is_empty() {
+            format!("{}://{}{}", scheme, host, path)
+        } else {
+            format!("{}://{}{}?{}", scheme, host, path, query)
+        };
+        let mut request = self.http.request(method.clone(), &url).body(body.clone());
+        if let Some(timeout) = total_timeout {
+            request = request.timeout(timeout);
+        }
+        for (k, v) in headers {
+            request = request.header(k, v);
+        }
+
+        let response = tokio::time::timeout(self.request_timeout(), request.send())
+            .await
+            .map_err(|_| {
+                AttemptError::Retryable(format_err!(
+                    "{} {}: timed out waiting for response.",
+                    method,
+                    url
+                ))
+            })?
+            .map_err(|e| AttemptError::Retryable(format_err!("{} {}: {}", method, url, e)))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = tokio::time::timeout(self.request_timeout(), response.text())
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        let err = format_err!("{} {} failed with {}: {}", method, url, status, body);
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            Err(AttemptError::Retryable(err))
+        } else {
+            Err(AttemptError::Fatal(err))
+        }
+    }
+
+    /// Sends a request whose response body is small enough to be read within the request
+    /// timeout, see `request_with_timeout()`.
+    async fn request(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+        extra_headers: BTreeMap<String, String>,
+        body: Bytes,
+    ) -> Result<Response> {
+        let total_timeout = Some(self.request_timeout());
+        self.request_with_timeout(method, key, query, extra_headers, body, total_timeout)
+            .await
+    }
+
+    /// Sends a request, retrying with exponential backoff on retryable failures.
+    /// Only establishing the response is retried, errors while streaming the response body are
+    /// left to the caller.
+    async fn request_with_timeout(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+        extra_headers: BTreeMap<String, String>,
+        body: Bytes,
+        total_timeout: Option<Duration>,
+    ) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            match self
+                .attempt(&method, key, query, &extra_headers, &body, total_timeout)
+                .await
+            {
+                Ok(response) => return Ok(response),
+                Err(AttemptError::Fatal(err)) => return Err(err),
+                Err(AttemptError::Retryable(err)) => {
+                    if attempt >= self.config.max_retries {
+                        return Err(err.context(format!("Gave up after {} retries.", attempt)));
+                    }
+                    let backoff_ms = std::cmp::min(
+                        Self::BACKOFF_BASE_MS.saturating_mul(1 << attempt.min(16)),
+                        Self::BACKOFF_MAX_MS,
+                    );
+                    warn!(
+                        error = %err,
+                        attempt = attempt,
+                        backoff_ms = backoff_ms,
+                        "S3 request failed, retrying."
+                    );
+                    tokio::time::sleep(Duration::from_millis(backoff_ms)).await;
+                    attempt += 1;
+                },
+            }
+        }
+    }
+
+    pub async fn put_object(&self, path: &str, content: Bytes) -> Result<()> {
+        let key = self.object_key(path);
+        self.request(Method::PUT, &key, &[], BTreeMap::new(), content)
+            .await
+            .err_notes(&key)?;
+        Ok(())
+    }
+
+    pub async fn get_object(&self, path: &str) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        let key = self.object_key(path);
+        // No total timeout, a large object can legitimately take longer than any fixed limit.
+        let reader = self
+            .request_with_timeout(Method::GET, &key, &[], BTreeMap::new(), Bytes::new(), None)
+            .await
+            .err_notes(&key)?
+            .bytes_stream()
+            .map_err(|e| futures::io::Error::new(futures::io::ErrorKind::Other, e))
+            .into_async_read()
+            .compat();
+
+        // Guard against a stalled download instead, the response headers are already covered by
+        // the request timeout.
+        let mut reader_with_read_timeout = TimeoutReader::new(reader);
+        reader_with_read_timeout
+            .set_timeout(Some(Duration::from_secs(self.config.read_timeout_secs)));
+
+        Ok(Box::new(Box::pin(reader_with_read_timeout)))
+    }
+
+    pub async fn copy_object(&self, from_path: &str, to_path: &str) -> Result<()> {
+        let from_key = self.object_key(from_path);
+        let to_key = self.object_key(to_path);
+        let mut headers = BTreeMap::new();
+        headers.insert(
+            "x-amz-copy-source".to_string(),
+            format!("/{}/{}", self.config.bucket, uri_encode_path(&from_key)),
+        );
+        let response = self
+            .request(Method::PUT, &to_key, &[], headers, Bytes::new())
+            .await
+            .err_notes(&from_key)?;
+        // CopyObject can fail after responding 200, in which case the body holds an error.
+        let body = response.text().await?;
+        ensure!(
+            !body.contains("<Error>"),
+            "Failed copying {} to {}: {}",
+            from_key,
+            to_key,
+            body,
+        );
+        Ok(())
+    }
+
+    pub async fn object_size(&self, path: &str) -> Result<u64> {
+        let key = self.object_key(path);
+        let response = self
+            .request(Method::HEAD, &key, &[], BTreeMap::new(), Bytes::new())
+            .await
+            .err_notes(&key)?;
+        // Not `Response::content_length()`, which is about the (empty) body of a HEAD response.
+        response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| format_err!("No Content-Length in response to HEAD {}.", key))
+    }
+
+    pub async fn delete_object(&self, path: &str) -> Result<()> {
+        let key = self.object_key(path);
+        self.request(Method::DELETE, &key, &[], BTreeMap::new(), Bytes::new())
+            .await
+            .err_notes(&key)?;
+        Ok(())
+    }
+
+    /// Lists paths (relative to the configured prefix) of all objects under `dir`.
+    pub async fn list_objects(&self, dir: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/", self.object_key(dir.trim_end_matches('/')));
+        let mut res = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix.as_str())];
+            if let Some(token) = &continuation_token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let body = self
+                .request(Method::GET, "", &query, BTreeMap::new(), Bytes::new())
+                .await
+                .err_notes(&prefix)?
+                .text()
+                .await?;
+
+            for key in xml_values(&body, "Key") {
+                res.push(self.relative_path(&key)?.to_string());
+            }
+            match xml_values(&body, "IsTruncated").first().map(String::as_str) {
+                Some("true") => {
+                    continuation_token = Some(
+                        xml_values(&body, "NextContinuationToken")
+                            .pop()
+                            .ok_or_else(|| {
+                                format_err!("Truncated listing without continuation token.")
+                            })?,
+                    );
+                },
+                _ => break,
+            }
+        }
+        Ok(res)
+    }
+
+    /// Uploads everything `reader` yields to `path`, using a single PutObject if the content fits
+    /// in one chunk and multipart upload otherwise. At most one chunk is held in memory.
+    pub async fn upload(&self, path: &str, mut reader: impl AsyncRead + Unpin) -> Result<()> {
+        let chunk_size = self.multipart_chunk_size();
+        let first_chunk = read_chunk(&mut reader, chunk_size).await?;
+        if first_chunk.len() < chunk_size {
+            return self.put_object(path, first_chunk).await;
+        }
+
+        let key = self.object_key(path);
+        let upload_id = self.create_multipart_upload(&key).await?;
+        let res = self
+            .upload_parts(&key, &upload_id, first_chunk, &mut reader, chunk_size)
+            .await;
+        if res.is_err() {
+            // Best effort, otherwise the bucket lifecycle rules are expected to clean it up.
+            if let Err(e) = self.abort_multipart_upload(&key, &upload_id).await {
+                warn!(key = key, error = %e, "Failed to abort multipart upload.");
+            }
+        }
+        res
+    }
+
+    async fn upload_parts(
+        &self,
+        key: &str,
+        upload_id: &str,
+        first_chunk: Bytes,
+        reader: &mut (impl AsyncRead + Unpin),
+        chunk_size: usize,
+    ) -> Result<()> {
+        let mut etags = Vec::new();
+        let mut chunk = first_chunk;
+        while !chunk.is_empty() {
+            let part_number = (etags.len() + 1).to_string();
+            let response = self
+                .request(
+                    Method::PUT,
+                    key,
+                    &[("partNumber", &part_number), ("uploadId", upload_id)],
+                    BTreeMap::new(),
+                    chunk,
+                )
+                .await
+                .err_notes((key, &part_number))?;
+            let etag = response
+                .headers()
+                .get("etag")
+                .ok_or_else(|| format_err!("No ETag returned for part {}.", part_number))?
+                .to_str()?
+                .to_string();
+            etags.push(etag);
+            chunk = read_chunk(&mut *reader, chunk_size).await?;
+        }
+        self.complete_multipart_upload(key, upload_id, &etags).await
+    }
+
+    async fn create_multipart_upload(&self, key: &str) -> Result<String> {
+        let body = self
+            .request(
+                Method::POST,
+                key,
+                &[("uploads", "")],
+                BTreeMap::new(),
+                Bytes::new(),
+            )
+            .await
+            .err_notes(key)?
+            .text()
+            .await?;
+        xml_values(&body, "UploadId")
+            .pop()
+            .ok_or_else(|| format_err!("No UploadId in response: {}", body))
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        etags: &[String],
+    ) -> Result<()> {
+        let parts = etags
+            .iter()
+            .enumerate()
+            .map(|(i, etag)| {
+                format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                    i + 1,
+                    etag
+                )
+            })
+            .collect::<String>();
+        let payload = format!(
+            "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+            parts
+        );
+        let body = self
+            .request(
+                Method::POST,
+                key,
+                &[("uploadId", upload_id)],
+                BTreeMap::new(),
+                Bytes::from(payload),
+            )
+            .await
+            .err_notes(key)?
+            .text()
+            .await?;
+        // Like CopyObject, this can fail after responding 200.
+        if body.contains("<Error>") {
+            bail!("Failed completing multipart upload of {}: {}", key, body);
+        }
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        self.request(
+            Method::DELETE,
+            key,
+            &[("uploadId", upload_id)],
+            BTreeMap::new(),
+            Bytes::new(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Reads until `size` bytes are read or EOF is hit.
+async fn read_chunk(reader: &mut (impl AsyncRead + Unpin), size: usize) -> Result<Bytes> {
+    let mut buf = Vec::with_capacity(size);
+    (&mut *reader)
+        .take(size as u64)
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(Bytes::from(buf))
+}
+
+/// Extracts the text content of all elements with the given tag name. The responses of the few
+/// APIs we call are flat and simple enough for this not to warrant a full XML parser.
+pub(super) fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    Regex::new(&format!(r"<{0}>([^<]*)</{0}>", tag))
+        .expect("Tag names are static.")
+        .captures_iter(xml)
+        .map(|c| xml_unescape(&c[1]))
+        .collect()
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
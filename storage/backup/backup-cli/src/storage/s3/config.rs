@@ -0,0 +1,141 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::utils::error_notes::ErrorNotes;
+use anyhow::{ensure, format_err, Result};
+use serde::Deserialize;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    #[serde(default)]
+    pub session_token: Option<String>,
+}
+
+impl S3Credentials {
+    const ENV_ACCESS_KEY_ID: &'static str = "AWS_ACCESS_KEY_ID";
+    const ENV_SECRET_ACCESS_KEY: &'static str = "AWS_SECRET_ACCESS_KEY";
+    const ENV_SESSION_TOKEN: &'static str = "AWS_SESSION_TOKEN";
+
+    /// Reads credentials from the standard AWS environment variables.
+    pub fn from_env() -> Result<Self> {
+        let var = |key: &str| {
+            std::env::var(key).map_err(|_| format_err!("Environment variable {} not set.", key))
+        };
+        Ok(Self {
+            access_key_id: var(Self::ENV_ACCESS_KEY_ID)?,
+            secret_access_key: var(Self::ENV_SECRET_ACCESS_KEY)?,
+            session_token: std::env::var(Self::ENV_SESSION_TOKEN).ok(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct S3Config {
+    /// Name of the bucket holding the backups.
+    pub bucket: String,
+    /// Key prefix under which everything is stored, e.g. "backup1/e1". Can be empty.
+    #[serde(default)]
+    pub prefix: String,
+    /// Region used for request signing, e.g. "us-west-2".
+    pub region: String,
+    /// Service endpoint, like "https://s3.us-west-2.amazonaws.com" or "http://localhost:9000" for
+    /// an S3-compatible store. Defaults to the AWS endpoint of `region`.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Address the bucket as "endpoint/bucket/key" instead of "bucket.endpoint/key". Most
+    /// S3-compatible stores (MinIO, Ceph, etc.) require this.
+    #[serde(default)]
+    pub path_style: bool,
+    /// Files larger than this are uploaded in parts of this size. S3 requires parts (other than
+    /// the last one) to be at least 5MB.
+    #[serde(default = "S3Config::default_multipart_chunk_size")]
+    pub multipart_chunk_size: usize,
+    /// Number of times a failed request is retried before giving up.
+    #[serde(default = "S3Config::default_max_retries")]
+    pub max_retries: usize,
+    /// Timeout for establishing a TCP (and TLS) connection.
+    #[serde(default = "S3Config::default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Timeout for a whole request. Object downloads can be arbitrarily large, so for them this
+    /// only bounds the wait for the response headers and the body is guarded by
+    /// `read_timeout_secs` instead.
+    #[serde(default = "S3Config::default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Maximum time an object download is allowed to go without receiving any data.
+    #[serde(default = "S3Config::default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+    /// Static credentials. If absent, the standard AWS environment variables are used.
+    #[serde(default)]
+    pub credentials: Option<S3Credentials>,
+}
+
+impl S3Config {
+    pub const MIN_MULTIPART_CHUNK_SIZE: usize = 5 * 1024 * 1024;
+
+    fn default_multipart_chunk_size() -> usize {
+        64 * 1024 * 1024
+    }
+
+    fn default_max_retries() -> usize {
+        5
+    }
+
+    fn default_connect_timeout_secs() -> u64 {
+        10
+    }
+
+    fn default_request_timeout_secs() -> u64 {
+        60
+    }
+
+    fn default_read_timeout_secs() -> u64 {
+        60
+    }
+
+    pub async fn load_from_file(path: &Path) -> Result<Self> {
+        let path_str = path.to_str().unwrap_or_default();
+        let mut file = tokio::fs::File::open(path).await.err_notes(path_str)?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).await.err_notes(path_str)?;
+
+        let config: Self = serde_yaml::from_slice(&content)?;
+        config.sanity_check()?;
+        Ok(config)
+    }
+
+    pub fn load_from_str(content: &str) -> Result<Self> {
+        let config: Self = serde_yaml::from_str(content)?;
+        config.sanity_check()?;
+        Ok(config)
+    }
+
+    fn sanity_check(&self) -> Result<()> {
+        ensure!(!self.bucket.is_empty(), "S3 bucket name is empty.");
+        ensure!(
+            self.multipart_chunk_size >= Self::MIN_MULTIPART_CHUNK_SIZE,
+            "multipart_chunk_size must be at least {} bytes, got {}.",
+            Self::MIN_MULTIPART_CHUNK_SIZE,
+            self.multipart_chunk_size,
+        );
+        Ok(())
+    }
+
+    pub fn endpoint(&self) -> String {
+        self.endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", self.region))
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    pub fn credentials(&self) -> Result<S3Credentials> {
+        match &self.credentials {
+            Some(credentials) => Ok(credentials.clone()),
+            None => S3Credentials::from_env(),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+mod client;
+pub mod config;
+mod sign;
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    storage::{
+        s3::{client::S3Client, config::S3Config},
+        BackupHandle, BackupHandleRef, BackupStorage, FileHandle, FileHandleRef, ShellSafeName,
+        TextLine,
+    },
+    utils::{error_notes::ErrorNotes, pipe::PipedWriter, PathToString},
+};
+use anyhow::{format_err, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+#[derive(Parser, Clone, Debug, Serialize, Deserialize)]
+pub struct S3Opt {
+    #[clap(
+        long = "config",
+        help = "Config file for the S3 backup store. See a sample config here: \
+        https://github.com/aptos-labs/aptos-core/tree/main/storage/backup/backup-cli/src/storage/s3/sample_configs/"
+    )]
+    config: PathBuf,
+}
+
+impl FromStr for S3Opt {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(S3Opt {
+            config: PathBuf::from(s),
+        })
+    }
+}
+
+/// A BackupStorage that talks to S3, or any storage service compatible with the S3 API, directly
+/// over HTTP. Files are laid out the same way as `LocalFs` does under a configured bucket and
+/// prefix, so a backup can be moved between the two with plain file copying.
+pub struct S3 {
+    client: Arc<S3Client>,
+}
+
+impl S3 {
+    const METADATA_BACKUP_DIR: &'static str = "metadata_backup";
+    const METADATA_DIR: &'static str = "metadata";
+    /// Buffer between the writer handed out by `create_for_write()` and the upload task.
+    const PIPE_BUFFER_SIZE: usize = 1024 * 1024;
+
+    pub fn new(config: S3Config) -> Result<Self> {
+        Ok(Self {
+            client: Arc::new(S3Client::new(config)?),
+        })
+    }
+
+    pub async fn new_with_opt(opt: S3Opt) -> Result<Self> {
+        let config = S3Config::load_from_file(&opt.config).await?;
+
+        Self::new(config)
+    }
+}
+
+#[async_trait]
+impl BackupStorage for S3 {
+    async fn create_backup(&self, name: &ShellSafeName) -> Result<BackupHandle> {
+        // There are no directories in S3, nothing to create.
+        Ok(name.to_string())
+    }
+
+    async fn create_for_write(
+        &self,
+        backup_handle: &BackupHandleRef,
+        name: &ShellSafeName,
+    ) -> Result<(FileHandle, Box<dyn AsyncWrite + Send + Unpin>)> {
+        let file_handle = Path::new(backup_handle)
+            .join(name.as_ref())
+            .path_to_string()?;
+        let client = self.client.clone();
+        let path = file_handle.clone();
+        let writer = PipedWriter::new(Self::PIPE_BUFFER_SIZE, |pipe_reader| async move {
+            client.upload(&path, pipe_reader).await
+        });
+        Ok((file_handle, Box::new(writer)))
+    }
+
+    async fn open_for_read(
+        &self,
+        file_handle: &FileHandleRef,
+    ) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        self.client.get_object(file_handle).await
+    }
+
+    async fn list_metadata_files(&self) -> Result<Vec<FileHandle>> {
+        self.client.list_objects(Self::METADATA_DIR).await
+    }
+
+    /// file_handle are expected to be the return results from list_metadata_files
+    /// file_handle is a path with `metadata` in the path, Ex: metadata/epoch_ending_1.meta
+    async fn backup_metadata_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        let name = Path::new(file_handle)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .ok_or_else(|| format_err!("cannot extract filename from {}", file_handle))?;
+        let backup_path = Path::new(Self::METADATA_BACKUP_DIR)
+            .join(name)
+            .path_to_string()?;
+
+        // S3 has no "move", copy then delete.
+        self.client.copy_object(file_handle, &backup_path).await?;
+        self.client.delete_object(file_handle).await
+    }
+
+    async fn delete_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        // S3 doesn't complain about deleting a non-existent object.
+        self.client.delete_object(file_handle).await
+    }
+
+    async fn file_size(&self, file_handle: &FileHandleRef) -> Result<Option<u64>> {
+        Ok(Some(self.client.object_size(file_handle).await?))
+    }
+
+    async fn save_metadata_lines(
+        &self,
+        name: &ShellSafeName,
+        lines: &[TextLine],
+    ) -> Result<FileHandle> {
+        let content = lines
+            .iter()
+            .map(|e| e.as_ref())
+            .collect::<Vec<&str>>()
+            .join("");
+        let file_handle = Path::new(Self::METADATA_DIR)
+            .join(name.as_ref())
+            .path_to_string()?;
+        self.client
+            .put_object(&file_handle, Bytes::from(content))
+            .await
+            .err_notes(name)?;
+        Ok(file_handle)
+    }
+}
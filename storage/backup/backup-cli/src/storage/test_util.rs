@@ -48,6 +48,32 @@ pub async fn test_write_and_read_impl(
     }
 }
 
+/// `root` is where a file handle resolves to a path relative to.
+pub async fn test_delete_file_impl(
+    store: Box<dyn BackupStorage>,
+    root: &Path,
+    backups: HashMap<ShellSafeName, HashMap<ShellSafeName, Vec<u8>>>,
+) {
+    let mut handles = Vec::new();
+    for (backup_name, files) in &backups {
+        let backup_handle = store.create_backup(backup_name).await.unwrap();
+        for (name, content) in files {
+            let (handle, mut file) = store.create_for_write(&backup_handle, name).await.unwrap();
+            file.write_all(content).await.unwrap();
+            file.shutdown().await.unwrap();
+            handles.push(handle);
+        }
+    }
+
+    for handle in &handles {
+        assert!(root.join(handle).exists());
+        store.delete_file(handle).await.unwrap();
+        assert!(!root.join(handle).exists());
+        // Deleting again is fine.
+        store.delete_file(handle).await.unwrap();
+    }
+}
+
 pub fn arb_backups(
 ) -> impl Strategy<Value = HashMap<ShellSafeName, HashMap<ShellSafeName, Vec<u8>>>> {
     hash_map(
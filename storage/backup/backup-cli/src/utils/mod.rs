@@ -4,6 +4,7 @@
 
 pub mod backup_service_client;
 pub(crate) mod error_notes;
+pub(crate) mod pipe;
 pub mod read_record_bytes;
 pub mod storage_ext;
 pub(crate) mod stream;
@@ -20,8 +21,8 @@ use aptos_crypto::HashValue;
 use aptos_db::{
     backup::restore_handler::RestoreHandler,
     state_restore::{
-        StateSnapshotProgress, StateSnapshotRestore, StateSnapshotRestoreMode, StateValueBatch,
-        StateValueWriter,
+        StateSnapshotProgress, StateSnapshotRangeRestore, StateSnapshotRestore,
+        StateSnapshotRestoreMode, StateValueBatch, StateValueWriter,
     },
     AptosDB, GetRestoreHandler,
 };
@@ -29,6 +30,7 @@ use aptos_infallible::duration_since_epoch;
 use aptos_jellyfish_merkle::{NodeBatch, TreeWriter};
 use aptos_logger::info;
 use aptos_types::{
+    nibble::Nibble,
     state_store::{
         state_key::StateKey, state_storage_usage::StateStorageUsage, state_value::StateValue,
     },
@@ -36,6 +38,7 @@ use aptos_types::{
     waypoint::Waypoint,
 };
 use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
 use std::{
     collections::HashMap,
     convert::TryFrom,
@@ -149,6 +152,15 @@ pub struct GlobalRestoreOpt {
 
     #[clap(flatten)]
     pub replay_concurrency_level: ReplayConcurrencyLevelOpt,
+
+    #[clap(
+        long,
+        help = "Restore state snapshots by key ranges in parallel, which is faster, but does NOT \
+        verify the chunks against their proofs. Only the root hash of the whole tree is checked \
+        in the end, and not at all when restoring the KV only. Ignored with --dry-run, which \
+        always verifies the chunks one by one."
+    )]
+    pub state_snapshot_restore_in_ranges: bool,
 }
 
 pub enum RestoreRunMode {
@@ -170,6 +182,7 @@ impl StateValueWriter<StateKey, StateValue> for MockStore {
         _version: Version,
         _kv_batch: &StateValueBatch<StateKey, Option<StateValue>>,
         _progress: StateSnapshotProgress,
+        _range: Option<Nibble>,
     ) -> Result<()> {
         Ok(())
     }
@@ -178,7 +191,11 @@ impl StateValueWriter<StateKey, StateValue> for MockStore {
         Ok(())
     }
 
-    fn get_progress(&self, _version: Version) -> Result<Option<StateSnapshotProgress>> {
+    fn get_progress(
+        &self,
+        _version: Version,
+        _range: Option<Nibble>,
+    ) -> Result<Option<StateSnapshotProgress>> {
         Ok(None)
     }
 }
@@ -223,6 +240,23 @@ impl RestoreRunMode {
         }
     }
 
+    /// Returns `None` in verify mode, where the chunks are verified one by one with their proofs
+    /// by the receiver from `get_state_restore_receiver()` instead. The chunks added by ranges
+    /// aren't verified against their proofs, so this is only used when explicitly asked for.
+    pub fn get_state_range_restore(
+        &self,
+        version: Version,
+        expected_root_hash: HashValue,
+        restore_mode: StateSnapshotRestoreMode,
+    ) -> Result<Option<StateSnapshotRangeRestore<StateKey, StateValue>>> {
+        match self {
+            Self::Restore { restore_handler } => restore_handler
+                .get_state_range_restore(version, expected_root_hash, restore_mode)
+                .map(Some),
+            Self::Verify => Ok(None),
+        }
+    }
+
     pub fn finish(&self) {
         match self {
             Self::Restore { restore_handler } => {
@@ -270,6 +304,7 @@ pub struct GlobalRestoreOptions {
     pub run_mode: Arc<RestoreRunMode>,
     pub concurrent_downloads: usize,
     pub replay_concurrency_level: usize,
+    pub state_snapshot_restore_in_ranges: bool,
 }
 
 impl TryFrom<GlobalRestoreOpt> for GlobalRestoreOptions {
@@ -302,6 +337,7 @@ impl TryFrom<GlobalRestoreOpt> for GlobalRestoreOptions {
             run_mode: Arc::new(run_mode),
             concurrent_downloads,
             replay_concurrency_level,
+            state_snapshot_restore_in_ranges: opt.state_snapshot_restore_in_ranges,
         })
     }
 }
@@ -403,6 +439,15 @@ impl<T: AsRef<Path>> PathToString for T {
     }
 }
 
+/// A progress bar on stderr showing speed and ETA.
+pub(crate) fn progress_bar(total: u64) -> ProgressBar {
+    let bar = ProgressBar::new(total);
+    bar.set_style(ProgressStyle::default_bar().template(
+        "[{elapsed_precise} {per_sec}] {bar:60.cyan/blue} {pos}/{len} {percent}% ETA {eta_precise}",
+    ));
+    bar
+}
+
 pub(crate) fn unix_timestamp_sec() -> i64 {
     duration_since_epoch().as_secs() as i64
 }
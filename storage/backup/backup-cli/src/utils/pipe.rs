@@ -0,0 +1,165 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Adapters which turn an async task consuming or producing a byte stream into an `AsyncWrite` or
+//! `AsyncRead`, with the task's result surfaced through the IO interface. This lets storages
+//! implement `create_for_write()` and `open_for_read()` with plain sequential async code.
+
+use anyhow::Result;
+use futures::{
+    task::{Context, Poll},
+    Future,
+};
+use std::pin::Pin;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf},
+    task::JoinHandle,
+};
+
+fn to_io_result(res: Result<Result<()>, tokio::task::JoinError>) -> tokio::io::Result<()> {
+    match res {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(tokio::io::Error::new(tokio::io::ErrorKind::Other, e)),
+        Err(e) => Err(tokio::io::Error::new(tokio::io::ErrorKind::Other, e)),
+    }
+}
+
+/// Bytes written are piped to a background task, and `shutdown()` only returns after the task
+/// finishes, with the task's error if any. Dropping the writer without `shutdown()` aborts the
+/// task, so it never mistakes an abandoned write for a complete one.
+pub(crate) struct PipedWriter {
+    pipe: Option<DuplexStream>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl PipedWriter {
+    pub fn new<F, Fut>(buffer_size: usize, consume: F) -> Self
+    where
+        F: FnOnce(DuplexStream) -> Fut,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let (pipe, pipe_reader) = tokio::io::duplex(buffer_size);
+        Self {
+            pipe: Some(pipe),
+            task: tokio::spawn(consume(pipe_reader)),
+        }
+    }
+
+    fn poll_task(&mut self, cx: &mut Context<'_>) -> Poll<tokio::io::Result<()>> {
+        Pin::new(&mut self.task).poll(cx).map(to_io_result)
+    }
+}
+
+impl Drop for PipedWriter {
+    fn drop(&mut self) {
+        if self.pipe.is_some() {
+            // Abort before the pipe closes, lest the task takes the EOF as the end of the content.
+            self.task.abort();
+        }
+    }
+}
+
+impl AsyncWrite for PipedWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, tokio::io::Error>> {
+        match self.pipe.as_mut() {
+            Some(pipe) => match Pin::new(pipe).poll_write(cx, buf) {
+                // The task quit early, report its error instead of the broken pipe.
+                Poll::Ready(Err(e)) if e.kind() == tokio::io::ErrorKind::BrokenPipe => {
+                    self.poll_task(cx).map(|res| res.and(Err(e)))
+                },
+                res => res,
+            },
+            None => Poll::Ready(Err(tokio::io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), tokio::io::Error>> {
+        match self.pipe.as_mut() {
+            Some(pipe) => Pin::new(pipe).poll_flush(cx),
+            None => Poll::Ready(Err(tokio::io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), tokio::io::Error>> {
+        if let Some(pipe) = self.pipe.as_mut() {
+            let res = Pin::new(pipe).poll_shutdown(cx);
+            if let Poll::Ready(Ok(_)) = res {
+                // EOF signaled to the task, drop our end and wait for the task.
+                self.pipe = None;
+            } else {
+                return res;
+            }
+        }
+
+        self.poll_task(cx)
+    }
+}
+
+/// Reads what a background task writes into the pipe. EOF is only reported after the task
+/// finishes successfully, otherwise the task's error is returned instead. Dropping the reader
+/// aborts the task.
+pub(crate) struct PipedReader {
+    pipe: DuplexStream,
+    task: Option<JoinHandle<Result<()>>>,
+}
+
+impl PipedReader {
+    pub fn new<F, Fut>(buffer_size: usize, produce: F) -> Self
+    where
+        F: FnOnce(DuplexStream) -> Fut,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let (pipe, pipe_writer) = tokio::io::duplex(buffer_size);
+        Self {
+            pipe,
+            task: Some(tokio::spawn(produce(pipe_writer))),
+        }
+    }
+}
+
+impl Drop for PipedReader {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+impl AsyncRead for PipedReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<tokio::io::Result<()>> {
+        let filled_before_poll = buf.filled().len();
+        let res = Pin::new(&mut self.pipe).poll_read(cx, buf);
+        match res {
+            Poll::Ready(Ok(()))
+                if buf.filled().len() == filled_before_poll && buf.remaining() > 0 =>
+            {
+                // Hit EOF, which is only genuine if the task succeeded.
+                match self.task.as_mut() {
+                    Some(task) => {
+                        let res = Pin::new(task).poll(cx).map(to_io_result);
+                        if res.is_ready() {
+                            self.task = None;
+                        }
+                        res
+                    },
+                    None => Poll::Ready(Ok(())),
+                }
+            },
+            _ => res,
+        }
+    }
+}
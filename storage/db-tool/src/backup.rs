@@ -2,24 +2,32 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::utils::parse_maxable_u64;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use aptos_backup_cli::{
     backup_types::{
         epoch_ending::backup::{EpochEndingBackupController, EpochEndingBackupOpt},
+        state_delta::backup::{StateDeltaBackupController, StateDeltaBackupOpt},
         state_snapshot::backup::{StateSnapshotBackupController, StateSnapshotBackupOpt},
         transaction::backup::{TransactionBackupController, TransactionBackupOpt},
     },
     coordinators::{
+        audit::AuditCoordinator,
         backup::{BackupCoordinator, BackupCoordinatorOpt},
+        continuous_verify::{ContinuousVerifyCoordinator, ContinuousVerifyOpt},
         verify::VerifyCoordinator,
     },
     metadata::{cache, cache::MetadataCacheOpt},
     storage::DBToolStorageOpt,
     utils::{
         backup_service_client::{BackupServiceClient, BackupServiceClientOpt},
-        ConcurrentDownloadsOpt, GlobalBackupOpt, TrustedWaypointOpt,
+        ConcurrentDownloadsOpt, GlobalBackupOpt, RocksdbOpt, TrustedWaypointOpt,
     },
 };
+use aptos_config::config::{
+    BUFFERED_STATE_TARGET_ITEMS, DEFAULT_MAX_NUM_NODES_PER_LRU_CACHE_SHARD,
+    NO_OP_STORAGE_PRUNER_CONFIG,
+};
+use aptos_db::AptosDB;
 use aptos_types::transaction::Version;
 use clap::{Parser, Subcommand};
 use std::{path::PathBuf, sync::Arc};
@@ -41,6 +49,16 @@ pub enum Command {
     Query(OneShotQueryType),
     #[clap(about = "verify the backup through restoring with the backup files")]
     Verify(VerifyOpt),
+    #[clap(
+        about = "Keep verifying the backups, a few at a time, reporting the results via metrics \
+        and a report file."
+    )]
+    VerifyContinuously(ContinuousVerifyRunOpt),
+    #[clap(
+        about = "Compare the backups against a local DB, reporting the first version where the \
+        transaction infos, epoch ending ledger infos or state root hashes diverge."
+    )]
+    Audit(AuditOpt),
 }
 
 #[derive(Parser)]
@@ -98,6 +116,12 @@ enum BackupType {
         #[clap[flatten]]
         storage: DBToolStorageOpt,
     },
+    StateDelta {
+        #[clap(flatten)]
+        opt: StateDeltaBackupOpt,
+        #[clap[flatten]]
+        storage: DBToolStorageOpt,
+    },
     Transaction {
         #[clap(flatten)]
         opt: TransactionBackupOpt,
@@ -121,6 +145,15 @@ pub struct CoordinatorRunOpt {
     storage: DBToolStorageOpt,
 }
 
+#[derive(Parser)]
+pub struct ContinuousVerifyRunOpt {
+    #[clap(flatten)]
+    opt: ContinuousVerifyOpt,
+
+    #[clap(flatten)]
+    storage: DBToolStorageOpt,
+}
+
 #[derive(Parser)]
 pub struct VerifyOpt {
     #[clap(flatten)]
@@ -165,6 +198,31 @@ pub struct VerifyOpt {
     output_transaction_analysis: Option<PathBuf>,
 }
 
+#[derive(Parser)]
+pub struct AuditOpt {
+    #[clap(flatten)]
+    metadata_cache_opt: MetadataCacheOpt,
+    #[clap(flatten)]
+    storage: DBToolStorageOpt,
+    #[clap(flatten)]
+    concurrent_downloads: ConcurrentDownloadsOpt,
+    #[clap(
+        long = "db-dir",
+        value_parser,
+        help = "Path to the DB to audit the backups against."
+    )]
+    db_dir: PathBuf,
+    #[clap(flatten)]
+    rocksdb_opt: RocksdbOpt,
+    #[clap(long, help = "The first version to audit. [Defaults to 0]")]
+    start_version: Option<Version>,
+    #[clap(
+        long,
+        help = "The last version to audit. [Defaults to the latest version in the DB]"
+    )]
+    end_version: Option<Version>,
+}
+
 impl Command {
     pub async fn run(self) -> Result<()> {
         match self {
@@ -193,6 +251,16 @@ impl Command {
                         .run()
                         .await?;
                     },
+                    BackupType::StateDelta { opt, storage } => {
+                        StateDeltaBackupController::new(
+                            opt,
+                            global_opt,
+                            client,
+                            storage.init_storage().await?,
+                        )
+                        .run()
+                        .await?;
+                    },
                     BackupType::Transaction { opt, storage } => {
                         TransactionBackupController::new(
                             opt,
@@ -250,6 +318,36 @@ impl Command {
                 .run()
                 .await?
             },
+            Command::VerifyContinuously(opt) => {
+                ContinuousVerifyCoordinator::new(opt.opt, opt.storage.init_storage().await?)?
+                    .run()
+                    .await?
+            },
+            Command::Audit(opt) => {
+                let db = Arc::new(AptosDB::open(
+                    opt.db_dir,
+                    true,                        /* read_only */
+                    NO_OP_STORAGE_PRUNER_CONFIG, /* pruner config */
+                    opt.rocksdb_opt.into(),
+                    false,
+                    BUFFERED_STATE_TARGET_ITEMS,
+                    DEFAULT_MAX_NUM_NODES_PER_LRU_CACHE_SHARD,
+                )?);
+                let report = AuditCoordinator::new(
+                    opt.storage.init_storage().await?,
+                    opt.metadata_cache_opt,
+                    opt.concurrent_downloads.get(),
+                    db,
+                    opt.start_version.unwrap_or(0),
+                    opt.end_version.unwrap_or(Version::MAX),
+                )?
+                .run()
+                .await?;
+                println!("{}", report);
+                if report.first_divergence.is_some() {
+                    bail!("Backups diverge from the DB.");
+                }
+            },
         }
         Ok(())
     }
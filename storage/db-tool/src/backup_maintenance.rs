@@ -2,18 +2,25 @@
 // SPDX-License-Identifier: Apache-2.0
 use anyhow::Result;
 use aptos_backup_cli::{
-    coordinators::backup::BackupCompactor, metadata::cache::MetadataCacheOpt,
-    storage::DBToolStorageOpt, utils::ConcurrentDownloadsOpt,
+    coordinators::{
+        backup::BackupCompactor,
+        retention::{BackupPruner, RetentionCount, RetentionPolicy},
+    },
+    metadata::cache::MetadataCacheOpt,
+    storage::DBToolStorageOpt,
+    utils::ConcurrentDownloadsOpt,
 };
 use clap::{Parser, Subcommand};
 
-/// Support compacting and cleaning obsolete metadata files
+/// Support compacting and cleaning obsolete metadata files, and pruning old backups
 #[derive(Subcommand)]
 pub enum Command {
     #[clap(about = "Compact metdata files")]
     Compact(CompactionOpt),
     #[clap(about = "Cleanup the backup metadata files")]
     Cleanup(CleanupOpt),
+    #[clap(about = "Delete backups falling out of the retention policy")]
+    Prune(PruneOpt),
 }
 
 #[derive(Parser)]
@@ -24,6 +31,9 @@ pub struct CompactionOpt {
     /// Specify how many state snapshot files to be merged in one compacted state snapshot metadata file
     #[clap(long, default_value_t = 1)]
     pub state_snapshot_file_compact_factor: usize,
+    /// Specify how many state delta files to be merged in one compacted state delta metadata file
+    #[clap(long, default_value_t = 1)]
+    pub state_delta_file_compact_factor: usize,
     /// Specify how many transaction files to be merged in one transaction metadata file
     #[clap(long, default_value_t = 1)]
     pub transaction_file_compact_factor: usize,
@@ -48,6 +58,32 @@ pub struct CleanupOpt {
     pub storage: DBToolStorageOpt,
 }
 
+#[derive(Parser)]
+pub struct PruneOpt {
+    /// Keep the transaction backups needed to restore to any version in this many of the latest
+    /// epochs. All transaction backups are kept if not specified.
+    #[clap(long)]
+    pub keep_transaction_epochs: Option<u64>,
+    /// Keep the latest state snapshot (or state delta) of each day, in this many of the latest
+    /// days, or "all".
+    #[clap(long)]
+    pub keep_daily_state_snapshots: Option<RetentionCount>,
+    /// Keep the latest state snapshot (or state delta) of each week, in this many of the latest
+    /// weeks, or "all". All states are kept if neither this nor --keep-daily-state-snapshots is
+    /// specified.
+    #[clap(long)]
+    pub keep_weekly_state_snapshots: Option<RetentionCount>,
+    /// Only log what would be deleted.
+    #[clap(long)]
+    pub dry_run: bool,
+    #[clap(flatten)]
+    pub metadata_cache_opt: MetadataCacheOpt,
+    #[clap(flatten)]
+    pub storage: DBToolStorageOpt,
+    #[clap(flatten)]
+    pub concurrent_downloads: ConcurrentDownloadsOpt,
+}
+
 impl Command {
     pub async fn run(self) -> Result<()> {
         match self {
@@ -55,6 +91,7 @@ impl Command {
                 let compactor = BackupCompactor::new(
                     opt.epoch_ending_file_compact_factor,
                     opt.state_snapshot_file_compact_factor,
+                    opt.state_delta_file_compact_factor,
                     opt.transaction_file_compact_factor,
                     opt.metadata_cache_opt,
                     opt.storage.init_storage().await?,
@@ -63,6 +100,20 @@ impl Command {
                 );
                 compactor.run().await?
             },
+            Command::Prune(opt) => {
+                let pruner = BackupPruner::new(
+                    RetentionPolicy {
+                        keep_transaction_epochs: opt.keep_transaction_epochs,
+                        keep_daily_states: opt.keep_daily_state_snapshots,
+                        keep_weekly_states: opt.keep_weekly_state_snapshots,
+                    },
+                    opt.metadata_cache_opt,
+                    opt.storage.init_storage().await?,
+                    opt.concurrent_downloads.get(),
+                    opt.dry_run,
+                );
+                pruner.run().await?;
+            },
             Command::Cleanup(_) => {
                 // TODO: add cleanup logic for removing obsolete metadata files
             },
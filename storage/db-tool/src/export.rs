@@ -0,0 +1,592 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{ensure, Result};
+use aptos_backup_cli::utils::RocksdbOpt;
+use aptos_config::config::{
+    BUFFERED_STATE_TARGET_ITEMS, DEFAULT_MAX_NUM_NODES_PER_LRU_CACHE_SHARD,
+    NO_OP_STORAGE_PRUNER_CONFIG,
+};
+use aptos_crypto::hash::CryptoHash;
+use aptos_db::AptosDB;
+use aptos_storage_interface::DbReader;
+use aptos_types::{
+    access_path::Path as AccessPathKind,
+    state_store::{
+        state_key::{StateKey, StateKeyInner},
+        state_value::StateValue,
+    },
+    transaction::{Transaction, TransactionPayload, Version},
+    write_set::WriteOp,
+};
+use arrow::{
+    array::{ArrayRef, BinaryBuilder, StringBuilder, UInt64Builder},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use clap::{Parser, ValueEnum};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use std::{
+    cmp::{max, min},
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Number of versions read from the DB and written as one record batch.
+const BATCH_SIZE: u64 = 10_000;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Table {
+    Transaction,
+    TransactionInfo,
+    Event,
+    WriteSet,
+    StateValue,
+}
+
+impl Table {
+    fn name(&self) -> &'static str {
+        match self {
+            Table::Transaction => "transaction",
+            Table::TransactionInfo => "transaction_info",
+            Table::Event => "event",
+            Table::WriteSet => "write_set",
+            Table::StateValue => "state_value",
+        }
+    }
+
+    fn schema(&self) -> SchemaRef {
+        let version = Field::new("version", DataType::UInt64, false);
+        let fields = match self {
+            Table::Transaction => vec![
+                version,
+                Field::new("hash", DataType::Utf8, false),
+                Field::new("type", DataType::Utf8, false),
+                Field::new("sender", DataType::Utf8, true),
+                Field::new("sequence_number", DataType::UInt64, true),
+                Field::new("entry_function", DataType::Utf8, true),
+                Field::new("max_gas_amount", DataType::UInt64, true),
+                Field::new("gas_unit_price", DataType::UInt64, true),
+                Field::new("expiration_timestamp_secs", DataType::UInt64, true),
+                Field::new("block_epoch", DataType::UInt64, true),
+                Field::new("block_round", DataType::UInt64, true),
+                Field::new("block_proposer", DataType::Utf8, true),
+                Field::new("block_timestamp_usecs", DataType::UInt64, true),
+                Field::new("bcs", DataType::Binary, false),
+            ],
+            Table::TransactionInfo => vec![
+                version,
+                Field::new("transaction_hash", DataType::Utf8, false),
+                Field::new("state_change_hash", DataType::Utf8, false),
+                Field::new("event_root_hash", DataType::Utf8, false),
+                Field::new("state_checkpoint_hash", DataType::Utf8, true),
+                Field::new("gas_used", DataType::UInt64, false),
+                Field::new("status", DataType::Utf8, false),
+            ],
+            Table::Event => vec![
+                version,
+                Field::new("index", DataType::UInt64, false),
+                Field::new("key", DataType::Utf8, true),
+                Field::new("sequence_number", DataType::UInt64, true),
+                Field::new("type_tag", DataType::Utf8, false),
+                Field::new("data", DataType::Binary, false),
+            ],
+            Table::WriteSet => [vec![version], StateKeyColumns::fields(), vec![
+                Field::new("op", DataType::Utf8, false),
+                Field::new("data", DataType::Binary, true),
+            ]]
+            .concat(),
+            Table::StateValue => [vec![version], StateKeyColumns::fields(), vec![
+                Field::new("bytes", DataType::Binary, true),
+                Field::new("metadata", DataType::Binary, true),
+            ]]
+            .concat(),
+        };
+        Arc::new(Schema::new(fields))
+    }
+}
+
+/// Export ledger data to Parquet files, one directory per table, one file per version range.
+/// State values are exported in one file per shard of the state KV DB, covering all the exported
+/// versions, since they're stored by key rather than by version.
+#[derive(Parser)]
+pub struct Opt {
+    #[clap(long, value_parser)]
+    db_dir: PathBuf,
+
+    #[clap(flatten)]
+    rocksdb_opt: RocksdbOpt,
+
+    #[clap(long, value_parser)]
+    output_dir: PathBuf,
+
+    #[clap(long, default_value_t = 0)]
+    start_version: Version,
+
+    #[clap(long, help = "[Defaults to the latest version in the DB]")]
+    end_version: Option<Version>,
+
+    #[clap(
+        long,
+        default_value_t = 1_000_000,
+        help = "Number of versions in each output file, except for state values. Files cover \
+        version ranges aligned to multiples of this, so that full partitions are identical across \
+        exports."
+    )]
+    partition_size: u64,
+
+    #[clap(long, value_enum, num_args = 1.., help = "[Defaults to all tables]")]
+    tables: Vec<Table>,
+}
+
+impl Opt {
+    pub fn run(self) -> Result<()> {
+        ensure!(self.partition_size > 0, "--partition-size can't be 0.");
+        let db = AptosDB::open(
+            &self.db_dir,
+            true,                        /* read_only */
+            NO_OP_STORAGE_PRUNER_CONFIG, /* pruner config */
+            self.rocksdb_opt.into(),
+            false,
+            BUFFERED_STATE_TARGET_ITEMS,
+            DEFAULT_MAX_NUM_NODES_PER_LRU_CACHE_SHARD,
+        )?;
+        let end_version = min(
+            self.end_version.unwrap_or(Version::MAX),
+            db.get_latest_version()?,
+        );
+        ensure!(
+            self.start_version <= end_version,
+            "Nothing to export, start_version {} is beyond end_version {}.",
+            self.start_version,
+            end_version,
+        );
+        let tables = if self.tables.is_empty() {
+            Table::value_variants().to_vec()
+        } else {
+            self.tables
+        };
+
+        let (state_value_tables, version_tables): (Vec<_>, Vec<_>) = tables
+            .into_iter()
+            .partition(|table| matches!(table, Table::StateValue));
+
+        let mut partition_first = self.start_version / self.partition_size * self.partition_size;
+        while !version_tables.is_empty() && partition_first <= end_version {
+            let partition_last = partition_first.saturating_add(self.partition_size - 1);
+            let first = max(partition_first, self.start_version);
+            let last = min(partition_last, end_version);
+            for table in &version_tables {
+                let path = self
+                    .output_dir
+                    .join(table.name())
+                    .join(format!("{:020}-{:020}.parquet", first, last));
+                export_partition(&db, *table, first, last, &path)?;
+                println!("Exported {:?}", path);
+            }
+            partition_first = match partition_last.checked_add(1) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        if !state_value_tables.is_empty() {
+            let iters = db.get_state_value_history_iters(self.start_version, end_version)?;
+            for (shard_id, entries) in iters.into_iter().enumerate() {
+                let path = self.output_dir.join(Table::StateValue.name()).join(format!(
+                    "{:020}-{:020}-{:02}.parquet",
+                    self.start_version, end_version, shard_id
+                ));
+                export_state_values(entries, &path)?;
+                println!("Exported {:?}", path);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn export_partition(
+    db: &AptosDB,
+    table: Table,
+    first: Version,
+    last: Version,
+    path: &Path,
+) -> Result<()> {
+    write_parquet(table, path, |writer| {
+        let mut batch_first = first;
+        while batch_first <= last {
+            let limit = min(BATCH_SIZE, last - batch_first + 1);
+            writer.write(&read_batch(db, table, batch_first, limit)?)?;
+            batch_first += limit;
+        }
+        Ok(())
+    })
+}
+
+fn export_state_values(
+    entries: impl Iterator<Item = Result<(StateKey, Version, Option<StateValue>)>>,
+    path: &Path,
+) -> Result<()> {
+    let mut entries = entries.peekable();
+    write_parquet(Table::StateValue, path, |writer| {
+        while entries.peek().is_some() {
+            let chunk = entries
+                .by_ref()
+                .take(BATCH_SIZE as usize)
+                .collect::<Result<Vec<_>>>()?;
+            let columns = state_value_columns(chunk)?;
+            writer.write(&RecordBatch::try_new(Table::StateValue.schema(), columns)?)?;
+        }
+        Ok(())
+    })
+}
+
+/// Writes to a temporary file first, so an interrupted export never leaves a partial file behind.
+fn write_parquet(
+    table: Table,
+    path: &Path,
+    write: impl FnOnce(&mut ArrowWriter<File>) -> Result<()>,
+) -> Result<()> {
+    fs::create_dir_all(path.parent().expect("Always has a parent."))?;
+    let tmp_path = path.with_extension("parquet.tmp");
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(&tmp_path)?, table.schema(), Some(props))?;
+    write(&mut writer)?;
+    writer.close()?;
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn read_batch(
+    db: &dyn DbReader,
+    table: Table,
+    first_version: Version,
+    limit: u64,
+) -> Result<RecordBatch> {
+    let columns = match table {
+        Table::Transaction => transaction_columns(db, first_version, limit)?,
+        Table::TransactionInfo => transaction_info_columns(db, first_version, limit)?,
+        Table::Event => event_columns(db, first_version, limit)?,
+        Table::WriteSet => write_set_columns(db, first_version, limit)?,
+        Table::StateValue => unreachable!("State values are read by key, not by version."),
+    };
+    Ok(RecordBatch::try_new(table.schema(), columns)?)
+}
+
+fn transaction_columns(
+    db: &dyn DbReader,
+    first_version: Version,
+    limit: u64,
+) -> Result<Vec<ArrayRef>> {
+    let mut version = UInt64Builder::new();
+    let mut hash = StringBuilder::new();
+    let mut typ = StringBuilder::new();
+    let mut sender = StringBuilder::new();
+    let mut sequence_number = UInt64Builder::new();
+    let mut entry_function = StringBuilder::new();
+    let mut max_gas_amount = UInt64Builder::new();
+    let mut gas_unit_price = UInt64Builder::new();
+    let mut expiration_timestamp_secs = UInt64Builder::new();
+    let mut block_epoch = UInt64Builder::new();
+    let mut block_round = UInt64Builder::new();
+    let mut block_proposer = StringBuilder::new();
+    let mut block_timestamp_usecs = UInt64Builder::new();
+    let mut bcs_bytes = BinaryBuilder::new();
+
+    for (idx, txn) in db
+        .get_transaction_iterator(first_version, limit)?
+        .enumerate()
+    {
+        let txn = txn?;
+        version.append_value(first_version + idx as Version);
+        hash.append_value(txn.hash().to_hex_literal());
+        typ.append_value(match &txn {
+            Transaction::UserTransaction(_) => "user_transaction",
+            Transaction::GenesisTransaction(_) => "genesis_transaction",
+            Transaction::BlockMetadata(_) => "block_metadata",
+            Transaction::StateCheckpoint(_) => "state_checkpoint",
+        });
+
+        let user_txn = txn.try_as_signed_user_txn();
+        sender.append_option(user_txn.map(|t| t.sender().to_hex_literal()));
+        sequence_number.append_option(user_txn.map(|t| t.sequence_number()));
+        entry_function.append_option(user_txn.and_then(|t| match t.payload() {
+            TransactionPayload::EntryFunction(f) => {
+                Some(format!("{}::{}", f.module(), f.function()))
+            },
+            _ => None,
+        }));
+        max_gas_amount.append_option(user_txn.map(|t| t.max_gas_amount()));
+        gas_unit_price.append_option(user_txn.map(|t| t.gas_unit_price()));
+        expiration_timestamp_secs.append_option(user_txn.map(|t| t.expiration_timestamp_secs()));
+
+        let block = txn.try_as_block_metadata();
+        block_epoch.append_option(block.map(|b| b.epoch()));
+        block_round.append_option(block.map(|b| b.round()));
+        block_proposer.append_option(block.map(|b| b.proposer().to_hex_literal()));
+        block_timestamp_usecs.append_option(block.map(|b| b.timestamp_usecs()));
+
+        bcs_bytes.append_value(bcs::to_bytes(&txn)?);
+    }
+
+    Ok(vec![
+        Arc::new(version.finish()),
+        Arc::new(hash.finish()),
+        Arc::new(typ.finish()),
+        Arc::new(sender.finish()),
+        Arc::new(sequence_number.finish()),
+        Arc::new(entry_function.finish()),
+        Arc::new(max_gas_amount.finish()),
+        Arc::new(gas_unit_price.finish()),
+        Arc::new(expiration_timestamp_secs.finish()),
+        Arc::new(block_epoch.finish()),
+        Arc::new(block_round.finish()),
+        Arc::new(block_proposer.finish()),
+        Arc::new(block_timestamp_usecs.finish()),
+        Arc::new(bcs_bytes.finish()),
+    ])
+}
+
+fn transaction_info_columns(
+    db: &dyn DbReader,
+    first_version: Version,
+    limit: u64,
+) -> Result<Vec<ArrayRef>> {
+    let mut version = UInt64Builder::new();
+    let mut transaction_hash = StringBuilder::new();
+    let mut state_change_hash = StringBuilder::new();
+    let mut event_root_hash = StringBuilder::new();
+    let mut state_checkpoint_hash = StringBuilder::new();
+    let mut gas_used = UInt64Builder::new();
+    let mut status = StringBuilder::new();
+
+    for (idx, txn_info) in db
+        .get_transaction_info_iterator(first_version, limit)?
+        .enumerate()
+    {
+        let txn_info = txn_info?;
+        version.append_value(first_version + idx as Version);
+        transaction_hash.append_value(txn_info.transaction_hash().to_hex_literal());
+        state_change_hash.append_value(txn_info.state_change_hash().to_hex_literal());
+        event_root_hash.append_value(txn_info.event_root_hash().to_hex_literal());
+        state_checkpoint_hash.append_option(
+            txn_info
+                .state_checkpoint_hash()
+                .map(|hash| hash.to_hex_literal()),
+        );
+        gas_used.append_value(txn_info.gas_used());
+        status.append_value(format!("{:?}", txn_info.status()));
+    }
+
+    Ok(vec![
+        Arc::new(version.finish()),
+        Arc::new(transaction_hash.finish()),
+        Arc::new(state_change_hash.finish()),
+        Arc::new(event_root_hash.finish()),
+        Arc::new(state_checkpoint_hash.finish()),
+        Arc::new(gas_used.finish()),
+        Arc::new(status.finish()),
+    ])
+}
+
+fn event_columns(db: &dyn DbReader, first_version: Version, limit: u64) -> Result<Vec<ArrayRef>> {
+    let mut version = UInt64Builder::new();
+    let mut index = UInt64Builder::new();
+    let mut key = StringBuilder::new();
+    let mut sequence_number = UInt64Builder::new();
+    let mut type_tag = StringBuilder::new();
+    let mut data = BinaryBuilder::new();
+
+    for (idx, events) in db.get_events_iterator(first_version, limit)?.enumerate() {
+        for (event_idx, event) in events?.iter().enumerate() {
+            version.append_value(first_version + idx as Version);
+            index.append_value(event_idx as u64);
+            let event_v1 = event.v1().ok();
+            key.append_option(event_v1.map(|e| e.key().to_string()));
+            sequence_number.append_option(event_v1.map(|e| e.sequence_number()));
+            type_tag.append_value(event.type_tag().to_string());
+            data.append_value(event.event_data());
+        }
+    }
+
+    Ok(vec![
+        Arc::new(version.finish()),
+        Arc::new(index.finish()),
+        Arc::new(key.finish()),
+        Arc::new(sequence_number.finish()),
+        Arc::new(type_tag.finish()),
+        Arc::new(data.finish()),
+    ])
+}
+
+/// The columns identifying a state key: its hash, its kind, and the parts relevant to the kind.
+struct StateKeyColumns {
+    hash: StringBuilder,
+    kind: StringBuilder,
+    address: StringBuilder,
+    struct_tag: StringBuilder,
+    module: StringBuilder,
+    table_handle: StringBuilder,
+    key: StringBuilder,
+}
+
+impl StateKeyColumns {
+    fn new() -> Self {
+        Self {
+            hash: StringBuilder::new(),
+            kind: StringBuilder::new(),
+            address: StringBuilder::new(),
+            struct_tag: StringBuilder::new(),
+            module: StringBuilder::new(),
+            table_handle: StringBuilder::new(),
+            key: StringBuilder::new(),
+        }
+    }
+
+    fn fields() -> Vec<Field> {
+        vec![
+            Field::new("state_key_hash", DataType::Utf8, false),
+            Field::new("state_key_kind", DataType::Utf8, false),
+            Field::new("state_key_address", DataType::Utf8, true),
+            Field::new("state_key_struct_tag", DataType::Utf8, true),
+            Field::new("state_key_module", DataType::Utf8, true),
+            Field::new("state_key_table_handle", DataType::Utf8, true),
+            Field::new("state_key_bytes", DataType::Utf8, true),
+        ]
+    }
+
+    fn append(&mut self, state_key: &StateKey) {
+        self.hash.append_value(state_key.hash().to_hex_literal());
+        let (kind, address, struct_tag, module, table_handle, key) = match state_key.inner() {
+            StateKeyInner::AccessPath(access_path) => {
+                let address = Some(access_path.address.to_hex_literal());
+                match bcs::from_bytes::<AccessPathKind>(&access_path.path) {
+                    Ok(AccessPathKind::Resource(struct_tag)) => {
+                        ("resource", address, Some(struct_tag), None, None, None)
+                    },
+                    Ok(AccessPathKind::ResourceGroup(struct_tag)) => (
+                        "resource_group",
+                        address,
+                        Some(struct_tag),
+                        None,
+                        None,
+                        None,
+                    ),
+                    Ok(AccessPathKind::Code(module_id)) => {
+                        ("module", address, None, Some(module_id), None, None)
+                    },
+                    // Keep the raw path, so no data is lost.
+                    Err(_) => (
+                        "access_path",
+                        address,
+                        None,
+                        None,
+                        None,
+                        Some(&access_path.path),
+                    ),
+                }
+            },
+            StateKeyInner::TableItem { handle, key } => (
+                "table_item",
+                None,
+                None,
+                None,
+                Some(handle.0.to_hex_literal()),
+                Some(key),
+            ),
+            StateKeyInner::Raw(bytes) => ("raw", None, None, None, None, Some(bytes)),
+        };
+        self.kind.append_value(kind);
+        self.address.append_option(address);
+        self.struct_tag
+            .append_option(struct_tag.map(|struct_tag| struct_tag.to_canonical_string()));
+        self.module
+            .append_option(module.map(|module_id| module_id.short_str_lossless()));
+        self.table_handle.append_option(table_handle);
+        self.key
+            .append_option(key.map(|bytes| format!("0x{}", hex::encode(bytes))));
+    }
+
+    fn finish(mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.hash.finish()),
+            Arc::new(self.kind.finish()),
+            Arc::new(self.address.finish()),
+            Arc::new(self.struct_tag.finish()),
+            Arc::new(self.module.finish()),
+            Arc::new(self.table_handle.finish()),
+            Arc::new(self.key.finish()),
+        ]
+    }
+}
+
+fn write_set_columns(
+    db: &dyn DbReader,
+    first_version: Version,
+    limit: u64,
+) -> Result<Vec<ArrayRef>> {
+    let mut version = UInt64Builder::new();
+    let mut state_key = StateKeyColumns::new();
+    let mut op = StringBuilder::new();
+    let mut data = BinaryBuilder::new();
+
+    for (idx, write_set) in db.get_write_set_iterator(first_version, limit)?.enumerate() {
+        for (key, write_op) in write_set?.iter() {
+            version.append_value(first_version + idx as Version);
+            state_key.append(key);
+            op.append_value(match write_op {
+                WriteOp::Creation(_) => "creation",
+                WriteOp::Modification(_) => "modification",
+                WriteOp::Deletion => "deletion",
+                WriteOp::CreationWithMetadata { .. } => "creation_with_metadata",
+                WriteOp::ModificationWithMetadata { .. } => "modification_with_metadata",
+                WriteOp::DeletionWithMetadata { .. } => "deletion_with_metadata",
+            });
+            data.append_option(write_op.bytes());
+        }
+    }
+
+    Ok([
+        vec![Arc::new(version.finish()) as ArrayRef],
+        state_key.finish(),
+        vec![Arc::new(op.finish()), Arc::new(data.finish())],
+    ]
+    .concat())
+}
+
+/// The entries of the `state_value` schema, i.e. the value of a key as of the version it was
+/// written at. A null `bytes` means the key was deleted.
+fn state_value_columns(
+    entries: Vec<(StateKey, Version, Option<StateValue>)>,
+) -> Result<Vec<ArrayRef>> {
+    let mut version = UInt64Builder::new();
+    let mut state_key = StateKeyColumns::new();
+    let mut bytes = BinaryBuilder::new();
+    let mut metadata = BinaryBuilder::new();
+
+    for (key, key_version, value) in entries {
+        version.append_value(key_version);
+        state_key.append(&key);
+        let (value_metadata, value_bytes) = match value {
+            Some(value) => {
+                let (value_metadata, value_bytes) = value.into();
+                (value_metadata, Some(value_bytes))
+            },
+            None => (None, None),
+        };
+        bytes.append_option(value_bytes);
+        metadata.append_option(value_metadata.as_ref().map(bcs::to_bytes).transpose()?);
+    }
+
+    Ok([
+        vec![Arc::new(version.finish()) as ArrayRef],
+        state_key.finish(),
+        vec![Arc::new(bytes.finish()), Arc::new(metadata.finish())],
+    ]
+    .concat())
+}
@@ -0,0 +1,403 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, ensure, Result};
+use aptos_backup_cli::{
+    backup_types::{
+        epoch_ending::manifest::EpochEndingBackup, transaction::manifest::TransactionBackup,
+    },
+    metadata::{cache, cache::MetadataCacheOpt},
+    storage::{BackupStorage, DBToolStorageOpt},
+    utils::{
+        read_record_bytes::ReadRecordBytes, storage_ext::BackupStorageExt, ConcurrentDownloadsOpt,
+        RocksdbOpt,
+    },
+};
+use aptos_config::config::{
+    BUFFERED_STATE_TARGET_ITEMS, DEFAULT_MAX_NUM_NODES_PER_LRU_CACHE_SHARD,
+    NO_OP_STORAGE_PRUNER_CONFIG,
+};
+use aptos_db::AptosDB;
+use aptos_leader_reputation::backtest::{
+    BacktestConfig, BacktestHistory, BacktestReport, EpochValidators, HistoricalBlock,
+};
+use aptos_storage_interface::{DbReader, Order, MAX_REQUEST_LIMIT};
+use aptos_types::{
+    account_config::{new_block_event_key, NewBlockEvent},
+    contract_event::ContractEvent,
+    ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+    transaction::{Transaction, TransactionInfo, Version},
+    write_set::WriteSet,
+};
+use clap::{Parser, Subcommand};
+use std::{fs, path::PathBuf, sync::Arc};
+use tokio::io::BufReader;
+
+/// Replays the committed blocks of a range of epochs through alternative leader reputation
+/// configs, and reports the proposers each config would have elected next to the ones elected
+/// on chain.
+#[derive(Parser)]
+pub struct Opt {
+    #[clap(
+        long,
+        value_parser,
+        num_args = 1..,
+        help = "YAML files, each holding a config to evaluate: its `name`, `exclude_round` and \
+        `leader_reputation_type`, in the format of the on chain consensus config."
+    )]
+    configs: Vec<PathBuf>,
+
+    #[clap(long, help = "The first epoch to report on.")]
+    start_epoch: u64,
+
+    #[clap(
+        long,
+        help = "The last epoch to report on. [Defaults to the last complete epoch]"
+    )]
+    end_epoch: Option<u64>,
+
+    #[clap(
+        long,
+        default_value_t = 5,
+        help = "Number of epochs before --start-epoch loaded as reputation history only, so that \
+        the first rounds reported on are elected with full reputation windows."
+    )]
+    history_epochs: u64,
+
+    #[clap(
+        long,
+        value_parser,
+        help = "Also writes the reports to this file, as YAML."
+    )]
+    output_file: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    source: HistorySource,
+}
+
+#[derive(Subcommand)]
+enum HistorySource {
+    #[clap(about = "Read the committed blocks from a DB.")]
+    Db {
+        #[clap(long, value_parser)]
+        db_dir: PathBuf,
+        #[clap(flatten)]
+        rocksdb_opt: RocksdbOpt,
+    },
+    #[clap(
+        about = "Read the committed blocks from the epoch ending and transaction backups. \
+        Backups don't hold the accumulator root hashes, so configs seeding the election with them \
+        elect proposers with the same weights, but don't reproduce the actual draws."
+    )]
+    Backup {
+        #[clap(flatten)]
+        metadata_cache_opt: MetadataCacheOpt,
+        #[clap(flatten)]
+        storage: DBToolStorageOpt,
+        #[clap(flatten)]
+        concurrent_downloads: ConcurrentDownloadsOpt,
+    },
+}
+
+impl Opt {
+    pub async fn run(self) -> Result<()> {
+        let configs = self
+            .configs
+            .iter()
+            .map(|path| {
+                serde_yaml::from_slice::<BacktestConfig>(&fs::read(path)?)
+                    .map_err(|e| anyhow!("Failed to parse config {:?}: {}", path, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Epoch 0 is genesis, which has no blocks.
+        let first_epoch = self.start_epoch.saturating_sub(self.history_epochs).max(1);
+
+        let (epoch_endings, blocks, end_epoch) = match self.source {
+            HistorySource::Db {
+                db_dir,
+                rocksdb_opt,
+            } => {
+                let db = AptosDB::open(
+                    db_dir,
+                    true,                        /* read_only */
+                    NO_OP_STORAGE_PRUNER_CONFIG, /* pruner config */
+                    rocksdb_opt.into(),
+                    false,
+                    BUFFERED_STATE_TARGET_ITEMS,
+                    DEFAULT_MAX_NUM_NODES_PER_LRU_CACHE_SHARD,
+                )?;
+                let last_sealed_epoch = db
+                    .get_latest_ledger_info()?
+                    .ledger_info()
+                    .next_block_epoch()
+                    - 1;
+                let end_epoch = self.end_epoch.unwrap_or(last_sealed_epoch);
+                ensure!(
+                    end_epoch <= last_sealed_epoch,
+                    "Epoch {} is not complete in the DB, the last complete epoch is {}.",
+                    end_epoch,
+                    last_sealed_epoch,
+                );
+                let epoch_endings = load_epoch_endings_from_db(&db, first_epoch, end_epoch)?;
+                let blocks = load_blocks_from_db(&db, &epoch_endings)?;
+                (epoch_endings, blocks, end_epoch)
+            },
+            HistorySource::Backup {
+                metadata_cache_opt,
+                storage,
+                concurrent_downloads,
+            } => {
+                let storage = storage.init_storage().await?;
+                let metadata_view = cache::sync_and_load(
+                    &metadata_cache_opt,
+                    Arc::clone(&storage),
+                    concurrent_downloads.get(),
+                )
+                .await?;
+                let epoch_ending_backups =
+                    metadata_view.select_epoch_ending_backups(Version::MAX)?;
+                let last_backed_up_epoch = epoch_ending_backups
+                    .last()
+                    .map(|backup| backup.last_epoch)
+                    .ok_or_else(|| anyhow!("No epoch ending backups."))?;
+                let end_epoch = self.end_epoch.unwrap_or(last_backed_up_epoch);
+                ensure!(
+                    end_epoch <= last_backed_up_epoch,
+                    "Epoch {} is not complete in the backups, the last complete epoch is {}.",
+                    end_epoch,
+                    last_backed_up_epoch,
+                );
+
+                let mut epoch_endings = vec![];
+                for backup in epoch_ending_backups {
+                    if backup.last_epoch + 1 < first_epoch || backup.first_epoch > end_epoch {
+                        continue;
+                    }
+                    epoch_endings.extend(
+                        load_epoch_endings_from_backup(
+                            &storage,
+                            &backup.manifest,
+                            first_epoch,
+                            end_epoch,
+                        )
+                        .await?,
+                    );
+                }
+                let (first_version, last_version) = version_range(&epoch_endings)?;
+                let mut blocks = vec![];
+                for backup in
+                    metadata_view.select_transaction_backups(first_version, last_version)?
+                {
+                    blocks.extend(
+                        load_blocks_from_backup(
+                            &storage,
+                            &backup.manifest,
+                            first_version,
+                            last_version,
+                        )
+                        .await?,
+                    );
+                }
+                (epoch_endings, blocks, end_epoch)
+            },
+        };
+        ensure!(
+            self.start_epoch <= end_epoch,
+            "Nothing to report on, --start-epoch {} is beyond the last epoch {}.",
+            self.start_epoch,
+            end_epoch,
+        );
+
+        let history = BacktestHistory::new(
+            epoch_endings
+                .iter()
+                .map(epoch_validators)
+                .collect::<Result<_>>()?,
+            blocks,
+        )?;
+        let epochs = self.start_epoch..=end_epoch;
+        let reports: Vec<BacktestReport> = std::iter::once(history.observed_report(epochs.clone()))
+            .chain(
+                configs
+                    .iter()
+                    .map(|config| history.backtest(config, epochs.clone())),
+            )
+            .collect();
+        for report in &reports {
+            println!("{}", report);
+        }
+        if let Some(output_file) = self.output_file {
+            fs::write(output_file, serde_yaml::to_string(&reports)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// The validator set of the epoch following the one the ledger info ends.
+fn epoch_validators(epoch_ending: &LedgerInfo) -> Result<EpochValidators> {
+    let epoch_state = epoch_ending.next_epoch_state().ok_or_else(|| {
+        anyhow!(
+            "Ledger info of epoch {} doesn't end the epoch.",
+            epoch_ending.epoch()
+        )
+    })?;
+    let validators: Vec<_> = epoch_state
+        .verifier
+        .get_ordered_account_addresses_iter()
+        .collect();
+    let voting_powers = validators
+        .iter()
+        .map(|validator| {
+            epoch_state
+                .verifier
+                .get_voting_power(validator)
+                .expect("Validator comes from the verifier.")
+        })
+        .collect();
+    Ok(EpochValidators {
+        epoch: epoch_state.epoch,
+        validators,
+        voting_powers,
+    })
+}
+
+/// The versions of the epochs whose validator sets are given by the epoch endings, which start
+/// with the ending of the epoch before the first one.
+fn version_range(epoch_endings: &[LedgerInfo]) -> Result<(Version, Version)> {
+    ensure!(
+        epoch_endings.len() >= 2,
+        "At least one complete epoch is needed."
+    );
+    for window in epoch_endings.windows(2) {
+        ensure!(
+            window[0].epoch() + 1 == window[1].epoch(),
+            "Missing the ending of epoch {}.",
+            window[0].epoch() + 1
+        );
+    }
+    Ok((
+        epoch_endings.first().unwrap().version() + 1,
+        epoch_endings.last().unwrap().version(),
+    ))
+}
+
+/// Endings of the epochs from the one before `first_epoch` to `end_epoch`.
+fn load_epoch_endings_from_db(
+    db: &dyn DbReader,
+    first_epoch: u64,
+    end_epoch: u64,
+) -> Result<Vec<LedgerInfo>> {
+    let mut epoch_endings = vec![];
+    let mut next_epoch = first_epoch - 1;
+    while next_epoch <= end_epoch {
+        let proof = db.get_epoch_ending_ledger_infos(next_epoch, end_epoch + 1)?;
+        ensure!(
+            !proof.ledger_info_with_sigs.is_empty(),
+            "Missing the ending of epoch {}.",
+            next_epoch
+        );
+        next_epoch += proof.ledger_info_with_sigs.len() as u64;
+        epoch_endings.extend(
+            proof
+                .ledger_info_with_sigs
+                .into_iter()
+                .map(|li| li.ledger_info().clone()),
+        );
+    }
+    Ok(epoch_endings)
+}
+
+/// The blocks of the epochs, read in the order the new block events were emitted in, the
+/// sequence number of which is the height of the block.
+fn load_blocks_from_db(
+    db: &dyn DbReader,
+    epoch_endings: &[LedgerInfo],
+) -> Result<Vec<HistoricalBlock>> {
+    let (first_version, last_version) = version_range(epoch_endings)?;
+    let mut next_height = db.get_block_info_by_version(first_version)?.2.height();
+    let mut blocks = vec![];
+    loop {
+        let events = db.get_events(
+            &new_block_event_key(),
+            next_height,
+            Order::Ascending,
+            MAX_REQUEST_LIMIT,
+            last_version,
+        )?;
+        let num_events = events.len() as u64;
+        for event in events {
+            if event.transaction_version > last_version {
+                return Ok(blocks);
+            }
+            blocks.push(HistoricalBlock {
+                event: bcs::from_bytes::<NewBlockEvent>(event.event.event_data())?,
+                accumulator_root_hash: Some(
+                    db.get_accumulator_root_hash(event.transaction_version)?,
+                ),
+            });
+        }
+        if num_events < MAX_REQUEST_LIMIT {
+            return Ok(blocks);
+        }
+        next_height += num_events;
+    }
+}
+
+async fn load_epoch_endings_from_backup(
+    storage: &Arc<dyn BackupStorage>,
+    manifest_handle: &str,
+    first_epoch: u64,
+    end_epoch: u64,
+) -> Result<Vec<LedgerInfo>> {
+    let manifest: EpochEndingBackup = storage.load_json_file(manifest_handle).await?;
+    manifest.verify()?;
+
+    let mut epoch_endings = vec![];
+    for chunk in manifest.chunks {
+        let mut file = storage.open_for_read(&chunk.ledger_infos).await?;
+        while let Some(record_bytes) = file.read_record_bytes().await? {
+            let ledger_info = bcs::from_bytes::<LedgerInfoWithSignatures>(&record_bytes)?
+                .ledger_info()
+                .clone();
+            if ledger_info.epoch() + 1 >= first_epoch && ledger_info.epoch() <= end_epoch {
+                epoch_endings.push(ledger_info);
+            }
+        }
+    }
+    Ok(epoch_endings)
+}
+
+async fn load_blocks_from_backup(
+    storage: &Arc<dyn BackupStorage>,
+    manifest_handle: &str,
+    first_version: Version,
+    last_version: Version,
+) -> Result<Vec<HistoricalBlock>> {
+    let manifest: TransactionBackup = storage.load_json_file(manifest_handle).await?;
+    manifest.verify()?;
+
+    let mut blocks = vec![];
+    for chunk in manifest.chunks {
+        if chunk.last_version < first_version || chunk.first_version > last_version {
+            continue;
+        }
+        let mut file = BufReader::new(storage.open_for_read(&chunk.transactions).await?);
+        let mut version = chunk.first_version;
+        while let Some(record_bytes) = file.read_record_bytes().await? {
+            let (_, _, events, _): (Transaction, TransactionInfo, Vec<ContractEvent>, WriteSet) =
+                bcs::from_bytes(&record_bytes)?;
+            if (first_version..=last_version).contains(&version) {
+                for event in events {
+                    if event.event_key() == Some(&new_block_event_key()) {
+                        blocks.push(HistoricalBlock {
+                            event: bcs::from_bytes::<NewBlockEvent>(event.event_data())?,
+                            accumulator_root_hash: None,
+                        });
+                    }
+                }
+            }
+            version += 1;
+        }
+    }
+    Ok(blocks)
+}
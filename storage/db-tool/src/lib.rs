@@ -6,6 +6,8 @@ extern crate core;
 mod backup;
 mod backup_maintenance;
 mod debugger;
+mod export;
+mod leader_reputation_backtest;
 mod replay_verify;
 pub mod restore;
 #[cfg(test)]
@@ -27,6 +29,8 @@ pub enum DBTool {
     Debug(debugger::Command),
     #[clap(subcommand)]
     BackupMaintenance(backup_maintenance::Command),
+    Export(export::Opt),
+    LeaderReputationBacktest(leader_reputation_backtest::Opt),
 }
 
 impl DBTool {
@@ -37,6 +41,8 @@ impl DBTool {
             DBTool::ReplayVerify(cmd) => cmd.run().await,
             DBTool::BackupMaintenance(cmd) => cmd.run().await,
             DBTool::Debug(cmd) => cmd.run(),
+            DBTool::Export(cmd) => cmd.run(),
+            DBTool::LeaderReputationBacktest(cmd) => cmd.run().await,
         }
     }
 }
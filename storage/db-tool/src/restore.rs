@@ -5,10 +5,14 @@ use anyhow::Result;
 use aptos_backup_cli::{
     backup_types::{
         epoch_ending::restore::{EpochEndingRestoreController, EpochEndingRestoreOpt},
+        state_delta::restore::{StateDeltaRestoreController, StateDeltaRestoreOpt},
         state_snapshot::restore::{StateSnapshotRestoreController, StateSnapshotRestoreOpt},
         transaction::restore::{TransactionRestoreController, TransactionRestoreOpt},
     },
-    coordinators::restore::{RestoreCoordinator, RestoreCoordinatorOpt},
+    coordinators::{
+        point_in_time::{PointInTimeRestoreCoordinator, PointInTimeRestoreOpt},
+        restore::{RestoreCoordinator, RestoreCoordinatorOpt},
+    },
     storage::DBToolStorageOpt,
     utils::GlobalRestoreOpt,
 };
@@ -22,6 +26,11 @@ pub enum Command {
     BootstrapDB(BootstrapDB),
     #[clap(subcommand)]
     Oneoff(Oneoff),
+    #[clap(
+        about = "Restore a new DB to a target version or point in time, choosing the backups to \
+        restore from automatically. With --dry-run, only log the plan."
+    )]
+    PointInTime(PointInTime),
 }
 
 #[derive(Parser)]
@@ -34,6 +43,16 @@ pub struct BootstrapDB {
     global: GlobalRestoreOpt,
 }
 
+#[derive(Parser)]
+pub struct PointInTime {
+    #[clap(flatten)]
+    storage: DBToolStorageOpt,
+    #[clap(flatten)]
+    opt: PointInTimeRestoreOpt,
+    #[clap(flatten)]
+    global: GlobalRestoreOpt,
+}
+
 #[derive(Parser)]
 pub enum Oneoff {
     EpochEnding {
@@ -52,6 +71,14 @@ pub enum Oneoff {
         #[clap(flatten)]
         global: GlobalRestoreOpt,
     },
+    StateDelta {
+        #[clap(flatten)]
+        storage: DBToolStorageOpt,
+        #[clap(flatten)]
+        opt: StateDeltaRestoreOpt,
+        #[clap(flatten)]
+        global: GlobalRestoreOpt,
+    },
     Transaction {
         #[clap(flatten)]
         storage: DBToolStorageOpt,
@@ -94,6 +121,20 @@ impl Command {
                         .run()
                         .await?;
                     },
+                    Oneoff::StateDelta {
+                        storage,
+                        opt,
+                        global,
+                    } => {
+                        StateDeltaRestoreController::new(
+                            opt,
+                            global.try_into()?,
+                            storage.init_storage().await?,
+                            None, /* epoch_history */
+                        )
+                        .run()
+                        .await?;
+                    },
                     Oneoff::Transaction {
                         storage,
                         opt,
@@ -120,6 +161,15 @@ impl Command {
                 .run()
                 .await?;
             },
+            Command::PointInTime(point_in_time) => {
+                PointInTimeRestoreCoordinator::new(
+                    point_in_time.opt,
+                    point_in_time.global.try_into()?,
+                    point_in_time.storage.init_storage().await?,
+                )
+                .run()
+                .await?;
+            },
         }
 
         Ok(())
@@ -47,6 +47,42 @@ fn test_various_cmd_parsing() {
     ]);
 
     run_cmd(&["aptos-db-tool", "backup", "verify", "--local-fs-dir", "."]);
+    run_cmd(&[
+        "aptos-db-tool",
+        "export",
+        "--db-dir",
+        ".",
+        "--output-dir",
+        ".",
+        "--tables",
+        "transaction",
+        "state-value",
+    ]);
+    run_cmd(&[
+        "aptos-db-tool",
+        "leader-reputation-backtest",
+        "--configs",
+        "a.yaml",
+        "b.yaml",
+        "--start-epoch",
+        "10",
+        "db",
+        "--db-dir",
+        ".",
+    ]);
+    run_cmd(&[
+        "aptos-db-tool",
+        "leader-reputation-backtest",
+        "--configs",
+        "a.yaml",
+        "--start-epoch",
+        "10",
+        "--end-epoch",
+        "20",
+        "backup",
+        "--local-fs-dir",
+        ".",
+    ]);
     run_cmd(&[
         "aptos-db-tool",
         "replay-verify",
@@ -90,7 +126,9 @@ mod dbtool_tests {
         state_store::{state_key::StateKeyTag::AccessPath, state_key_prefix::StateKeyPrefix},
         transaction::Version,
     };
+    use arrow::array::{Array, BinaryArray, StringArray, UInt64Array};
     use clap::Parser;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
     use std::{
         default::Default,
         fs,
@@ -108,7 +146,8 @@ mod dbtool_tests {
                 && view1.select_epoch_ending_backups(Version::MAX).unwrap()
                     == view2.select_epoch_ending_backups(Version::MAX).unwrap()
                 && view1.select_state_snapshot(Version::MAX).unwrap()
-                    == view2.select_state_snapshot(Version::MAX).unwrap(),
+                    == view2.select_state_snapshot(Version::MAX).unwrap()
+                && view1.state_delta_backups() == view2.state_delta_backups(),
             "Metadata views are not equal"
         );
     }
@@ -199,6 +238,27 @@ mod dbtool_tests {
             .run(),
         )
         .unwrap();
+        for (base_epoch, epoch) in [("0", "1"), ("1", "2")] {
+            rt.block_on(
+                DBTool::try_parse_from([
+                    "aptos-db-tool",
+                    "backup",
+                    "oneoff",
+                    "--backup-service-address",
+                    server_addr.as_str(),
+                    "state-delta",
+                    "--state-delta-base-epoch",
+                    base_epoch,
+                    "--state-delta-epoch",
+                    epoch,
+                    "--local-fs-dir",
+                    backup_dir.path().to_str().unwrap(),
+                ])
+                .unwrap()
+                .run(),
+            )
+            .unwrap();
+        }
         rt.block_on(
             DBTool::try_parse_from([
                 "aptos-db-tool",
@@ -248,9 +308,10 @@ mod dbtool_tests {
                 1,
             ))
             .unwrap();
+        assert_eq!(old_metaview.state_delta_backups().len(), 2);
         let og_list = rt.block_on(store.list_metadata_files()).unwrap();
         let compactor =
-            BackupCompactor::new(2, 2, 2, metadata_opt.clone(), Arc::clone(&store), 1, 1);
+            BackupCompactor::new(2, 2, 2, 2, metadata_opt.clone(), Arc::clone(&store), 1, 1);
         rt.block_on(compactor.run()).unwrap();
         // assert the original files are still present
         let mut after_list = rt.block_on(store.list_metadata_files()).unwrap();
@@ -261,7 +322,7 @@ mod dbtool_tests {
         std::thread::sleep(std::time::Duration::from_secs(2));
         // run the compaction again
         let compactor =
-            BackupCompactor::new(2, 2, 2, metadata_opt.clone(), Arc::clone(&store), 1, 1);
+            BackupCompactor::new(2, 2, 2, 2, metadata_opt.clone(), Arc::clone(&store), 1, 1);
         rt.block_on(compactor.run()).unwrap();
         let final_list = rt.block_on(store.list_metadata_files()).unwrap();
         // assert og list has no overlap with final list
@@ -622,6 +683,147 @@ mod dbtool_tests {
         rt.shutdown_timeout(Duration::from_secs(1));
     }
 
+    #[test]
+    fn test_export_is_deterministic() {
+        let db_dir = TempPath::new();
+        let _db = test_execution_with_storage_impl_inner(false, db_dir.path());
+        let rt = Runtime::new().unwrap();
+
+        let export = |output_dir: &Path| {
+            rt.block_on(
+                DBTool::try_parse_from([
+                    "aptos-db-tool",
+                    "export",
+                    "--db-dir",
+                    db_dir.path().to_str().unwrap(),
+                    "--output-dir",
+                    output_dir.to_str().unwrap(),
+                    "--start-version",
+                    "3",
+                    "--end-version",
+                    "12",
+                    "--partition-size",
+                    "5",
+                ])
+                .unwrap()
+                .run(),
+            )
+            .unwrap();
+        };
+        let output_dir_1 = TempPath::new();
+        let output_dir_2 = TempPath::new();
+        export(output_dir_1.path());
+        export(output_dir_2.path());
+
+        let mut files = vec![];
+        for table in ["transaction", "transaction_info", "event", "write_set"] {
+            for file in [
+                "00000000000000000003-00000000000000000004.parquet",
+                "00000000000000000005-00000000000000000009.parquet",
+                "00000000000000000010-00000000000000000012.parquet",
+            ] {
+                files.push(Path::new(table).join(file));
+            }
+        }
+        files.push(
+            Path::new("state_value").join("00000000000000000003-00000000000000000012-00.parquet"),
+        );
+        for file in files {
+            let path_1 = output_dir_1.path().join(&file);
+            let path_2 = output_dir_2.path().join(&file);
+            assert_eq!(fs::read(path_1).unwrap(), fs::read(path_2).unwrap());
+        }
+    }
+
+    /// Reads the `(version, state_key_hash, state_key_kind, bytes)` rows of an exported file.
+    fn read_exported_state_rows(
+        path: &Path,
+        bytes_column: &str,
+    ) -> Vec<(Version, String, String, Option<Vec<u8>>)> {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(fs::File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut rows = vec![];
+        for batch in reader {
+            let batch = batch.unwrap();
+            let column = |name: &str| batch.column(batch.schema().index_of(name).unwrap()).clone();
+            let versions = column("version");
+            let versions = versions.as_any().downcast_ref::<UInt64Array>().unwrap();
+            let hashes = column("state_key_hash");
+            let hashes = hashes.as_any().downcast_ref::<StringArray>().unwrap();
+            let kinds = column("state_key_kind");
+            let kinds = kinds.as_any().downcast_ref::<StringArray>().unwrap();
+            let bytes = column(bytes_column);
+            let bytes = bytes.as_any().downcast_ref::<BinaryArray>().unwrap();
+            for idx in 0..batch.num_rows() {
+                rows.push((
+                    versions.value(idx),
+                    hashes.value(idx).to_string(),
+                    kinds.value(idx).to_string(),
+                    (!bytes.is_null(idx)).then(|| bytes.value(idx).to_vec()),
+                ));
+            }
+        }
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn test_export_state_values() {
+        let db_dir = TempPath::new();
+        let _db = test_execution_with_storage_impl_inner(false, db_dir.path());
+        let output_dir = TempPath::new();
+        Runtime::new()
+            .unwrap()
+            .block_on(
+                DBTool::try_parse_from([
+                    "aptos-db-tool",
+                    "export",
+                    "--db-dir",
+                    db_dir.path().to_str().unwrap(),
+                    "--output-dir",
+                    output_dir.path().to_str().unwrap(),
+                    "--start-version",
+                    "3",
+                    "--end-version",
+                    "12",
+                    "--partition-size",
+                    "100",
+                    "--tables",
+                    "write-set",
+                    "state-value",
+                ])
+                .unwrap()
+                .run(),
+            )
+            .unwrap();
+
+        // The DB isn't sharded, so all the state values are in the file of shard 0.
+        let state_values = read_exported_state_rows(
+            &output_dir
+                .path()
+                .join("state_value")
+                .join("00000000000000000003-00000000000000000012-00.parquet"),
+            "bytes",
+        );
+        let write_sets = read_exported_state_rows(
+            &output_dir
+                .path()
+                .join("write_set")
+                .join("00000000000000000003-00000000000000000012.parquet"),
+            "data",
+        );
+        // Nothing is pruned, so the state values written in the range are exactly the writes.
+        assert!(!state_values.is_empty());
+        assert_eq!(state_values, write_sets);
+        assert!(state_values
+            .iter()
+            .all(|(version, _, kind, _)| (3..=12).contains(version)
+                && ["resource", "resource_group", "module", "table_item"]
+                    .contains(&kind.as_str())));
+    }
+
     fn dir_size<P: AsRef<Path>>(path: P) -> u64 {
         let mut size = 0;
 
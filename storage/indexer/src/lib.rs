@@ -9,7 +9,8 @@ use crate::{
     db::INDEX_DB_NAME,
     metadata::{MetadataKey, MetadataValue},
     schema::{
-        column_families, indexer_metadata::IndexerMetadataSchema, table_info::TableInfoSchema,
+        account_transactions::AccountTransactionsSchema, column_families,
+        indexer_metadata::IndexerMetadataSchema, table_info::TableInfoSchema,
     },
 };
 use anyhow::{bail, ensure, Result};
@@ -37,7 +38,7 @@ use move_core_types::{
 };
 use move_resource_viewer::{AnnotatedMoveValue, MoveValueAnnotator};
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     convert::TryInto,
     sync::{atomic::Ordering, Arc},
 };
@@ -45,6 +46,7 @@ use std::{
 pub struct Indexer {
     db: DB,
     next_version: AtomicVersion,
+    account_transactions_start_version: Version,
 }
 
 impl Indexer {
@@ -52,25 +54,63 @@ impl Indexer {
         db_root_path: impl AsRef<std::path::Path>,
         rocksdb_config: RocksdbConfig,
     ) -> Result<Self> {
-        let db_path = db_root_path.as_ref().join(INDEX_DB_NAME);
-
-        let db = DB::open(
-            db_path,
-            "index_db",
-            column_families(),
-            &gen_rocksdb_options(&rocksdb_config, false),
-        )?;
+        let db = Self::open_db(db_root_path, rocksdb_config)?;
 
         let next_version = db
             .get::<IndexerMetadataSchema>(&MetadataKey::LatestVersion)?
             .map_or(0, |v| v.expect_version());
 
+        // The index of account transactions starts wherever the indexer was when it was added.
+        let account_transactions_start_version =
+            match db.get::<IndexerMetadataSchema>(&MetadataKey::AccountTransactionsStartVersion)? {
+                Some(v) => v.expect_version(),
+                None => {
+                    db.put::<IndexerMetadataSchema>(
+                        &MetadataKey::AccountTransactionsStartVersion,
+                        &MetadataValue::Version(next_version),
+                    )?;
+                    next_version
+                },
+            };
+
         Ok(Self {
             db,
             next_version: AtomicVersion::new(next_version),
+            account_transactions_start_version,
         })
     }
 
+    /// Makes a new indexer only index account transactions from `version` on, like an indexer
+    /// that was running before it started tracking them. Meant for tests.
+    pub fn set_account_transactions_start_version(
+        db_root_path: impl AsRef<std::path::Path>,
+        rocksdb_config: RocksdbConfig,
+        version: Version,
+    ) -> Result<()> {
+        let db = Self::open_db(db_root_path, rocksdb_config)?;
+        ensure!(
+            db.get::<IndexerMetadataSchema>(&MetadataKey::AccountTransactionsStartVersion)?
+                .is_none(),
+            "The indexer already tracks account transactions.",
+        );
+        db.put::<IndexerMetadataSchema>(
+            &MetadataKey::AccountTransactionsStartVersion,
+            &MetadataValue::Version(version),
+        )
+    }
+
+    fn open_db(
+        db_root_path: impl AsRef<std::path::Path>,
+        rocksdb_config: RocksdbConfig,
+    ) -> Result<DB> {
+        DB::open(
+            db_root_path.as_ref().join(INDEX_DB_NAME),
+            "index_db",
+            column_families(),
+            &gen_rocksdb_options(&rocksdb_config, false),
+        )
+    }
+
     pub fn index(
         &self,
         db_reader: Arc<dyn DbReader>,
@@ -111,14 +151,22 @@ impl Indexer {
             return Ok(());
         }
 
+        let mut batch = SchemaBatch::new();
         let mut table_info_parser = TableInfoParser::new(self, annotator);
-        for write_set in write_sets {
+        for (idx, write_set) in write_sets.iter().enumerate() {
+            let version = first_version + idx as Version;
+            let mut touched_accounts = BTreeSet::new();
             for (state_key, write_op) in write_set.iter() {
                 table_info_parser.parse_write_op(state_key, write_op)?;
+                if let StateKeyInner::AccessPath(access_path) = state_key.inner() {
+                    touched_accounts.insert(access_path.address);
+                }
+            }
+            for address in touched_accounts {
+                batch.put::<AccountTransactionsSchema>(&(address, version), &())?;
             }
         }
 
-        let mut batch = SchemaBatch::new();
         match table_info_parser.finish(&mut batch) {
             Ok(_) => {},
             Err(err) => {
@@ -149,6 +197,44 @@ impl Indexer {
     pub fn get_table_info(&self, handle: TableHandle) -> Result<Option<TableInfo>> {
         self.db.get::<TableInfoSchema>(&handle)
     }
+
+    /// Returns the versions of up to `limit` transactions that touched the account, in
+    /// `[start_version, end_version]`.
+    pub fn get_account_transaction_versions(
+        &self,
+        address: AccountAddress,
+        start_version: Version,
+        end_version: Version,
+        limit: u64,
+    ) -> Result<Vec<Version>> {
+        ensure!(
+            start_version >= self.account_transactions_start_version,
+            "Account transactions are only indexed since version {}, requested from version {}.",
+            self.account_transactions_start_version,
+            start_version,
+        );
+
+        let mut iter = self
+            .db
+            .iter::<AccountTransactionsSchema>(Default::default())?;
+        iter.seek(&(address, start_version))?;
+
+        let mut versions = vec![];
+        while versions.len() < limit as usize {
+            match iter.next().transpose()? {
+                Some(((account, version), ())) if account == address && version <= end_version => {
+                    versions.push(version)
+                },
+                _ => break,
+            }
+        }
+        Ok(versions)
+    }
+
+    /// Returns the first version whose account transactions are indexed.
+    pub fn account_transactions_start_version(&self) -> Version {
+        self.account_transactions_start_version
+    }
 }
 
 struct TableInfoParser<'a, R> {
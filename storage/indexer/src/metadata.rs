@@ -22,4 +22,7 @@ impl MetadataValue {
 #[cfg_attr(any(test, feature = "fuzzing"), derive(proptest_derive::Arbitrary))]
 pub(crate) enum MetadataKey {
     LatestVersion,
+    // First version indexed in `AccountTransactionsSchema`, which was added after the indexer
+    // might have already been running.
+    AccountTransactionsStartVersion,
 }
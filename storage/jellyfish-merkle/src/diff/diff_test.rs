@@ -0,0 +1,156 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    diff::JellyfishMerkleDiffIterator, mock_tree_store::MockTreeStore, test_helper::ValueBlob,
+    JellyfishMerkleTree,
+};
+use anyhow::Result;
+use aptos_crypto::HashValue;
+use aptos_types::transaction::Version;
+use proptest::{collection::vec, prelude::*};
+use std::{collections::BTreeMap, sync::Arc};
+
+type State = BTreeMap<HashValue, (HashValue, ValueBlob)>;
+
+/// Applies each batch of updates as a version, returns the state after each version.
+fn build_versions(
+    db: &MockTreeStore<ValueBlob>,
+    batches: Vec<Vec<(HashValue, Option<(HashValue, ValueBlob)>)>>,
+) -> Vec<State> {
+    let tree = JellyfishMerkleTree::new(db);
+    let mut state = State::new();
+    let mut states = Vec::new();
+    for (version, batch) in batches.into_iter().enumerate() {
+        let batch: BTreeMap<_, _> = batch.into_iter().collect();
+        let (_root_hash, update_batch) = tree
+            .put_value_set_test(
+                batch.iter().map(|(k, v)| (*k, v.as_ref())).collect(),
+                version as Version,
+            )
+            .unwrap();
+        db.write_tree_update_batch(update_batch).unwrap();
+        for (key, value) in batch {
+            match value {
+                Some(value) => state.insert(key, value),
+                None => state.remove(&key),
+            };
+        }
+        states.push(state.clone());
+    }
+    states
+}
+
+fn expected_diff(base: &State, target: &State) -> Vec<(HashValue, Option<HashValue>)> {
+    let mut diff = BTreeMap::new();
+    for (key, (value_hash, _)) in target {
+        if base.get(key).map(|(h, _)| h) != Some(value_hash) {
+            diff.insert(*key, Some(*value_hash));
+        }
+    }
+    for key in base.keys() {
+        if !target.contains_key(key) {
+            diff.insert(*key, None);
+        }
+    }
+    diff.into_iter().collect()
+}
+
+fn actual_diff(
+    db: &Arc<MockTreeStore<ValueBlob>>,
+    base_version: Version,
+    target_version: Version,
+    base: &State,
+) -> Vec<(HashValue, Option<HashValue>)> {
+    JellyfishMerkleDiffIterator::new(Arc::clone(db), base_version, target_version)
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap()
+        .into_iter()
+        .map(|diff| {
+            let key = diff.account_key();
+            // The base side must be what the base version had.
+            assert_eq!(
+                diff.base.as_ref().map(|leaf| leaf.value_hash()),
+                base.get(&key).map(|(h, _)| *h)
+            );
+            (key, diff.target.map(|leaf| leaf.value_hash()))
+        })
+        .collect()
+}
+
+fn arb_batches() -> impl Strategy<Value = Vec<Vec<(HashValue, Option<(HashValue, ValueBlob)>)>>> {
+    // A small key space, so keys get updated and deleted across versions.
+    let key = (0u64..64).prop_map(HashValue::from_u64);
+    let random_key = any::<HashValue>();
+    let value = any::<HashValue>().prop_map(|h| (h, ValueBlob::from(h.to_vec())));
+    vec(
+        vec(
+            (
+                prop_oneof![key, random_key],
+                proptest::option::weighted(0.8, value),
+            ),
+            1..20,
+        ),
+        2..6,
+    )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(20))]
+
+    #[test]
+    fn test_diff_between_versions(batches in arb_batches()) {
+        let db = Arc::new(MockTreeStore::default());
+        let states = build_versions(&db, batches);
+        for base_version in 0..states.len() {
+            for target_version in 0..states.len() {
+                prop_assert_eq!(
+                    actual_diff(
+                        &db,
+                        base_version as Version,
+                        target_version as Version,
+                        &states[base_version],
+                    ),
+                    expected_diff(&states[base_version], &states[target_version])
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_diff_same_version_is_empty() {
+    let db = Arc::new(MockTreeStore::default());
+    let batch = (0..100)
+        .map(|i| {
+            let value_hash = HashValue::random();
+            (
+                HashValue::from_u64(i),
+                Some((value_hash, ValueBlob::from(value_hash.to_vec()))),
+            )
+        })
+        .collect();
+    build_versions(&db, vec![batch]);
+    let mut iter = JellyfishMerkleDiffIterator::new(Arc::clone(&db), 0, 0).unwrap();
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_diff_to_and_from_empty_tree() {
+    let db = Arc::new(MockTreeStore::default());
+    let value_hash = HashValue::random();
+    let key = HashValue::from_u64(1);
+    let states = build_versions(&db, vec![
+        vec![(
+            key,
+            Some((value_hash, ValueBlob::from(value_hash.to_vec()))),
+        )],
+        vec![(key, None)],
+    ]);
+    assert_eq!(actual_diff(&db, 0, 1, &states[0]), vec![(key, None)]);
+    assert_eq!(actual_diff(&db, 1, 0, &states[1]), vec![(
+        key,
+        Some(value_hash)
+    )]);
+}
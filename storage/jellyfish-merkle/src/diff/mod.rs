@@ -0,0 +1,238 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements `JellyfishMerkleDiffIterator`. Initialized with two versions of the
+//! tree, the iterator generates all the leaves that differ between them in ascending key order,
+//! by performing a depth first traversal on both trees at the same time. Subtrees whose hashes
+//! are the same in both versions are skipped without being read, so the cost is proportional to
+//! the size of the difference instead of the size of the tree.
+
+#[cfg(test)]
+mod diff_test;
+
+use crate::{
+    node_type::{InternalNode, LeafNode, Node, NodeKey},
+    TreeReader,
+};
+use anyhow::{bail, Result};
+use aptos_crypto::HashValue;
+use aptos_types::{nibble::nibble_path::NibblePath, transaction::Version};
+use std::{collections::VecDeque, sync::Arc};
+
+/// A key whose leaf is different in the two versions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LeafDiff<K> {
+    /// The leaf in the base version, `None` if the key was created after it.
+    pub base: Option<LeafNode<K>>,
+    /// The leaf in the target version, `None` if the key was deleted before it.
+    pub target: Option<LeafNode<K>>,
+}
+
+impl<K: crate::Key> LeafDiff<K> {
+    /// The hashed key of the leaf.
+    pub fn account_key(&self) -> HashValue {
+        self.target
+            .as_ref()
+            .or(self.base.as_ref())
+            .expect("At least one side exists.")
+            .account_key()
+    }
+}
+
+/// A node yet to be compared against its counterpart in the other version.
+enum DiffNode<K> {
+    Internal(NodeKey, InternalNode),
+    Leaf(LeafNode<K>),
+}
+
+/// A child of a node being expanded, which is only read if it needs to be compared.
+enum ChildRef<K> {
+    Unread(NodeKey, HashValue),
+    Leaf(LeafNode<K>),
+}
+
+impl<K: crate::Key> ChildRef<K> {
+    fn hash(&self) -> HashValue {
+        match self {
+            ChildRef::Unread(_, hash) => *hash,
+            ChildRef::Leaf(leaf) => leaf.hash(),
+        }
+    }
+}
+
+/// The `JellyfishMerkleDiffIterator` implementation.
+pub struct JellyfishMerkleDiffIterator<R, K> {
+    /// The storage engine from which we can read nodes using node keys.
+    reader: Arc<R>,
+
+    /// Pairs of nodes at the same position in the two versions, yet to be compared. The top of
+    /// the stack covers the smallest keys.
+    stack: Vec<(Option<DiffNode<K>>, Option<DiffNode<K>>)>,
+
+    /// Diffs found but not yet returned, in key order.
+    pending: VecDeque<LeafDiff<K>>,
+
+    /// Whether the iteration has finished, either exhausted or failed.
+    done: bool,
+}
+
+impl<R, K> JellyfishMerkleDiffIterator<R, K>
+where
+    R: TreeReader<K>,
+    K: crate::Key,
+{
+    /// Constructs a new iterator over the difference between `base_version` and
+    /// `target_version`. Both versions of the tree must not have been pruned.
+    pub fn new(reader: Arc<R>, base_version: Version, target_version: Version) -> Result<Self> {
+        let base = Self::read_node(&*reader, NodeKey::new_empty_path(base_version))?;
+        let target = Self::read_node(&*reader, NodeKey::new_empty_path(target_version))?;
+        Ok(Self {
+            reader,
+            stack: vec![(base, target)],
+            pending: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    fn read_node(reader: &R, node_key: NodeKey) -> Result<Option<DiffNode<K>>> {
+        Ok(match reader.get_node(&node_key)? {
+            Node::Internal(node) => Some(DiffNode::Internal(node_key, node)),
+            Node::Leaf(leaf) => Some(DiffNode::Leaf(leaf)),
+            Node::Null => None,
+        })
+    }
+
+    fn resolve(&self, child: Option<ChildRef<K>>) -> Result<Option<DiffNode<K>>> {
+        Ok(match child {
+            Some(ChildRef::Unread(node_key, _hash)) => match self.reader.get_node(&node_key)? {
+                Node::Internal(node) => Some(DiffNode::Internal(node_key, node)),
+                Node::Leaf(leaf) => Some(DiffNode::Leaf(leaf)),
+                Node::Null => bail!("Null node referenced as child at {:?}.", node_key),
+            },
+            Some(ChildRef::Leaf(leaf)) => Some(DiffNode::Leaf(leaf)),
+            None => None,
+        })
+    }
+
+    /// The children of one side of a pair being expanded at `depth`. A leaf, which sits higher up
+    /// in this version than the internal node on the other side, is treated as the only child on
+    /// the path of its key.
+    fn children(node: Option<DiffNode<K>>, depth: usize) -> Vec<Option<ChildRef<K>>> {
+        let mut children: Vec<_> = (0..16).map(|_| None).collect();
+        match node {
+            Some(DiffNode::Internal(node_key, node)) => {
+                for (nibble, child) in node.children_sorted() {
+                    children[u8::from(*nibble) as usize] = Some(ChildRef::Unread(
+                        node_key.gen_child_node_key(child.version, *nibble),
+                        child.hash,
+                    ));
+                }
+            },
+            Some(DiffNode::Leaf(leaf)) => {
+                let nibble = NibblePath::new_even(leaf.account_key().to_vec()).get_nibble(depth);
+                children[u8::from(nibble) as usize] = Some(ChildRef::Leaf(leaf));
+            },
+            None => (),
+        }
+        children
+    }
+
+    /// Expands a pair in which at least one side is an internal node, pushing the pairs of
+    /// children that differ onto the stack.
+    fn expand(&mut self, base: Option<DiffNode<K>>, target: Option<DiffNode<K>>) -> Result<()> {
+        let depth = match (&base, &target) {
+            (Some(DiffNode::Internal(node_key, _)), _)
+            | (_, Some(DiffNode::Internal(node_key, _))) => node_key.nibble_path().num_nibbles(),
+            _ => unreachable!("One side must be an internal node."),
+        };
+        let pairs = Self::children(base, depth)
+            .into_iter()
+            .zip(Self::children(target, depth))
+            .collect::<Vec<_>>();
+        // Pushed in reverse, so the smallest nibble is on the top of the stack.
+        for (base_child, target_child) in pairs.into_iter().rev() {
+            if let (Some(base_child), Some(target_child)) = (&base_child, &target_child) {
+                if base_child.hash() == target_child.hash() {
+                    continue;
+                }
+            }
+            if base_child.is_none() && target_child.is_none() {
+                continue;
+            }
+            let pair = (self.resolve(base_child)?, self.resolve(target_child)?);
+            self.stack.push(pair);
+        }
+        Ok(())
+    }
+
+    fn next_impl(&mut self) -> Result<Option<LeafDiff<K>>> {
+        loop {
+            if let Some(diff) = self.pending.pop_front() {
+                return Ok(Some(diff));
+            }
+            let (base, target) = match self.stack.pop() {
+                Some(pair) => pair,
+                None => return Ok(None),
+            };
+            match (base, target) {
+                (None, None) => (),
+                (Some(DiffNode::Leaf(base)), Some(DiffNode::Leaf(target))) => {
+                    if base.account_key() == target.account_key() {
+                        if base.value_hash() != target.value_hash() {
+                            return Ok(Some(LeafDiff {
+                                base: Some(base),
+                                target: Some(target),
+                            }));
+                        }
+                    } else {
+                        let deleted = LeafDiff {
+                            base: Some(base),
+                            target: None,
+                        };
+                        let created = LeafDiff {
+                            base: None,
+                            target: Some(target),
+                        };
+                        if deleted.account_key() < created.account_key() {
+                            self.pending.extend([deleted, created]);
+                        } else {
+                            self.pending.extend([created, deleted]);
+                        }
+                    }
+                },
+                (Some(DiffNode::Leaf(base)), None) => {
+                    return Ok(Some(LeafDiff {
+                        base: Some(base),
+                        target: None,
+                    }))
+                },
+                (None, Some(DiffNode::Leaf(target))) => {
+                    return Ok(Some(LeafDiff {
+                        base: None,
+                        target: Some(target),
+                    }))
+                },
+                (base, target) => self.expand(base, target)?,
+            }
+        }
+    }
+}
+
+impl<R, K> Iterator for JellyfishMerkleDiffIterator<R, K>
+where
+    R: TreeReader<K>,
+    K: crate::Key,
+{
+    type Item = Result<LeafDiff<K>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.next_impl();
+        if !matches!(res, Ok(Some(_))) {
+            self.done = true;
+        }
+        res.transpose()
+    }
+}
@@ -72,6 +72,7 @@
 //! [`InternalNode`]: node_type/struct.InternalNode.html
 //! [`LeafNode`]: node_type/struct.LeafNode.html
 
+pub mod diff;
 pub mod iterator;
 #[cfg(test)]
 mod jellyfish_merkle_test;
@@ -140,6 +141,15 @@ pub trait TreeReader<K> {
     /// Gets the rightmost leaf at a version. Note that this assumes we are in the process of
     /// restoring the tree and all nodes are at the same version.
     fn get_rightmost_leaf(&self, version: Version) -> Result<Option<(NodeKey, LeafNode<K>)>>;
+
+    /// Gets the rightmost leaf at a version among those under the root's child at `nibble`, i.e.
+    /// whose key hash starts with `nibble`. Like `get_rightmost_leaf`, this assumes we are in the
+    /// process of restoring the tree.
+    fn get_rightmost_leaf_in_subtree(
+        &self,
+        version: Version,
+        nibble: Nibble,
+    ) -> Result<Option<(NodeKey, LeafNode<K>)>>;
 }
 
 pub trait TreeWriter<K>: Send + Sync {
@@ -4,11 +4,11 @@
 
 use crate::{
     node_type::{LeafNode, Node, NodeKey},
-    NodeBatch, StaleNodeIndex, TreeReader, TreeUpdateBatch, TreeWriter,
+    NibbleExt, NodeBatch, StaleNodeIndex, TreeReader, TreeUpdateBatch, TreeWriter,
 };
 use anyhow::{bail, ensure, Result};
 use aptos_infallible::RwLock;
-use aptos_types::transaction::Version;
+use aptos_types::{nibble::Nibble, transaction::Version};
 use std::collections::{hash_map::Entry, BTreeSet, HashMap};
 
 pub struct MockTreeStore<K> {
@@ -51,6 +51,27 @@ where
 
         Ok(node_key_and_node)
     }
+
+    fn get_rightmost_leaf_in_subtree(
+        &self,
+        version: Version,
+        nibble: Nibble,
+    ) -> Result<Option<(NodeKey, LeafNode<K>)>> {
+        let locked = self.data.read();
+        Ok(locked
+            .0
+            .iter()
+            .filter_map(|(key, value)| match value {
+                Node::Leaf(leaf_node)
+                    if key.version() == version
+                        && leaf_node.account_key().get_nibble(0) == nibble =>
+                {
+                    Some((key.clone(), leaf_node.clone()))
+                },
+                _ => None,
+            })
+            .max_by_key(|(_key, leaf_node)| leaf_node.account_key()))
+    }
 }
 
 impl<K> TreeWriter<K> for MockTreeStore<K>
@@ -37,6 +37,14 @@ impl TreeReader<StateKey> for DummyReader {
     ) -> anyhow::Result<Option<(NodeKey, LeafNode<StateKey>)>> {
         unimplemented!()
     }
+
+    fn get_rightmost_leaf_in_subtree(
+        &self,
+        _version: Version,
+        _nibble: Nibble,
+    ) -> anyhow::Result<Option<(NodeKey, LeafNode<StateKey>)>> {
+        unimplemented!()
+    }
 }
 
 fn hash_internal(left: HashValue, right: HashValue) -> HashValue {
@@ -12,7 +12,7 @@ use crate::{
     },
     NibbleExt, TreeReader, TreeWriter, ROOT_NIBBLE_HEIGHT,
 };
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
 use aptos_crypto::{
     hash::{CryptoHash, SPARSE_MERKLE_PLACEHOLDER_HASH},
     HashValue,
@@ -176,6 +176,10 @@ pub struct JellyfishMerkleRestore<K> {
     /// Already finished, deem all chunks overlap.
     finished: bool,
 
+    /// If set, only the subtree under the root's child at this nibble is restored, see
+    /// `new_subtree()`.
+    subtree: Option<Nibble>,
+
     async_commit: bool,
     async_commit_result: Option<Receiver<Result<()>>>,
 }
@@ -227,6 +231,78 @@ where
             num_keys_received: 0,
             expected_root_hash,
             finished,
+            subtree: None,
+            async_commit,
+            async_commit_result: None,
+        })
+    }
+
+    /// Restores only the subtree under the root's child at `nibble`, i.e. the leaves whose key
+    /// hashes start with `nibble`, so that the subtrees can be restored independently, in
+    /// parallel. Nothing above the subtree is written: once all the subtrees are restored, the
+    /// root is written by `finish_subtrees()`.
+    ///
+    /// Chunks are added without proofs, since the proofs can't be verified without the leaves of
+    /// the subtrees on the left, and the root hash is checked in `finish_subtrees()`. Like
+    /// `new()`, this resumes from the rightmost leaf persisted, in the subtree.
+    pub fn new_subtree<D: 'static + TreeReader<K> + TreeWriter<K>>(
+        store: Arc<D>,
+        version: Version,
+        nibble: Nibble,
+        expected_root_hash: HashValue,
+        async_commit: bool,
+    ) -> Result<Self> {
+        let tree_reader = Arc::clone(&store);
+        let root_node_key = NodeKey::new_empty_path(version);
+        let child_index = u8::from(nibble) as usize;
+        let (finished, partial_nodes, previous_leaf) =
+            if let Some(root_node) = tree_reader.get_node_option(&root_node_key, "restore")? {
+                info!("Previous restore is complete, checking root hash.");
+                ensure!(
+                    root_node.hash() == expected_root_hash,
+                    "Previous completed restore has root hash {}, expecting {}",
+                    root_node.hash(),
+                    expected_root_hash,
+                );
+                (true, vec![], None)
+            } else if let Some(Node::Internal(internal_node)) = tree_reader.get_node_option(
+                &root_node_key.gen_child_node_key(version, nibble),
+                "restore",
+            )? {
+                // The root of the subtree is written last, when the subtree is finished.
+                info!(nibble = nibble, "Previous restore of subtree is complete.");
+                let mut root = InternalInfo::new_empty(root_node_key);
+                root.set_child(child_index, ChildInfo::Internal {
+                    hash: Some(internal_node.hash()),
+                    leaf_count: Some(internal_node.leaf_count()),
+                });
+                (true, vec![root], None)
+            } else if let Some((node_key, leaf_node)) =
+                tree_reader.get_rightmost_leaf_in_subtree(version, nibble)?
+            {
+                let mut partial_nodes =
+                    Self::recover_partial_nodes(tree_reader.as_ref(), version, node_key)?;
+                // The other children of the root are subtrees restored independently, if any.
+                for (index, child_info) in partial_nodes[0].children.iter_mut().enumerate() {
+                    if index != child_index {
+                        *child_info = None;
+                    }
+                }
+                (false, partial_nodes, Some(leaf_node))
+            } else {
+                (false, vec![InternalInfo::new_empty(root_node_key)], None)
+            };
+
+        Ok(Self {
+            store,
+            version,
+            partial_nodes,
+            frozen_nodes: HashMap::new(),
+            previous_leaf,
+            num_keys_received: 0,
+            expected_root_hash,
+            finished,
+            subtree: Some(nibble),
             async_commit,
             async_commit_result: None,
         })
@@ -246,6 +322,7 @@ where
             num_keys_received: 0,
             expected_root_hash,
             finished: false,
+            subtree: None,
             async_commit: false,
             async_commit_result: None,
         })
@@ -336,12 +413,40 @@ where
     /// error will be returned and nothing will be written to storage.
     pub fn add_chunk_impl(
         &mut self,
-        mut chunk: Vec<(&K, HashValue)>,
+        chunk: Vec<(&K, HashValue)>,
         proof: SparseMerkleRangeProof,
     ) -> Result<()> {
+        ensure!(
+            self.subtree.is_none(),
+            "Chunks of a subtree can't be verified with proofs."
+        );
+        if !self.add_leaves(chunk)? {
+            return Ok(());
+        }
+
+        // Verify what we have added so far is all correct.
+        self.verify(proof)?;
+
+        self.write_frozen_nodes()
+    }
+
+    /// Restores a chunk of accounts without a proof, for when the chunks are assembled from
+    /// sources that can't prove each of them, i.e. a state snapshot with deltas applied. Nothing
+    /// is verified until `finish_impl`, which refuses to write the root node unless the whole tree
+    /// adds up to the expected root hash, so such a restore never appears complete otherwise.
+    pub fn add_chunk_without_proof(&mut self, chunk: Vec<(&K, HashValue)>) -> Result<()> {
+        if !self.add_leaves(chunk)? {
+            return Ok(());
+        }
+        self.write_frozen_nodes()
+    }
+
+    /// Adds the leaves in a chunk, skipping those already restored. Returns false if there's
+    /// nothing new in the chunk.
+    fn add_leaves(&mut self, mut chunk: Vec<(&K, HashValue)>) -> Result<bool> {
         if self.finished {
             info!("State snapshot restore already finished, ignoring entire chunk.");
-            return Ok(());
+            return Ok(false);
         }
 
         if let Some(prev_leaf) = &self.previous_leaf {
@@ -351,7 +456,7 @@ where
             chunk = match skip_until {
                 None => {
                     info!("Skipping entire chunk.");
-                    return Ok(());
+                    return Ok(false);
                 },
                 Some((0, _)) => chunk,
                 Some((num_to_skip, next_leaf)) => {
@@ -365,11 +470,19 @@ where
             }
         };
         if chunk.is_empty() {
-            return Ok(());
+            return Ok(false);
         }
 
         for (key, value_hash) in chunk {
             let hashed_key = key.hash();
+            if let Some(nibble) = self.subtree {
+                ensure!(
+                    hashed_key.get_nibble(0) == nibble,
+                    "Account key {} is not in the subtree {:?}.",
+                    hashed_key,
+                    nibble,
+                );
+            }
             if let Some(ref prev_leaf) = self.previous_leaf {
                 ensure!(
                     hashed_key > prev_leaf.account_key(),
@@ -385,10 +498,11 @@ where
             self.num_keys_received += 1;
         }
 
-        // Verify what we have added so far is all correct.
-        self.verify(proof)?;
+        Ok(true)
+    }
 
-        // Write the frozen nodes to storage.
+    /// Writes the frozen nodes to storage.
+    fn write_frozen_nodes(&mut self) -> Result<()> {
         if self.async_commit {
             self.wait_for_async_commit()?;
             let (tx, rx) = channel();
@@ -744,6 +858,10 @@ where
     /// Finishes the restoration process. This tells the code that there is no more account,
     /// otherwise we can not freeze the rightmost leaf and its ancestors.
     pub fn finish_impl(mut self) -> Result<()> {
+        ensure!(
+            self.subtree.is_none(),
+            "A subtree restore is finished by finish_subtree()."
+        );
         self.wait_for_async_commit()?;
         // Deal with the special case when the entire tree has a single leaf or null node.
         if self.partial_nodes.len() == 1 {
@@ -763,6 +881,7 @@ where
                     let node_key = NodeKey::new_empty_path(self.version);
                     assert!(self.frozen_nodes.is_empty());
                     self.frozen_nodes.insert(node_key, Node::Null);
+                    self.ensure_root_hash()?;
                     self.store.write_node_batch(&self.frozen_nodes)?;
                     return Ok(());
                 },
@@ -771,6 +890,7 @@ where
                         let node_key = NodeKey::new_empty_path(self.version);
                         assert!(self.frozen_nodes.is_empty());
                         self.frozen_nodes.insert(node_key, node.into());
+                        self.ensure_root_hash()?;
                         self.store.write_node_batch(&self.frozen_nodes)?;
                         return Ok(());
                     }
@@ -780,9 +900,123 @@ where
         }
 
         self.freeze(0);
+        self.ensure_root_hash()?;
         self.store.write_node_batch(&self.frozen_nodes)?;
         Ok(())
     }
+
+    /// Finishes the restoration of a subtree, see `new_subtree()`, writing everything in the
+    /// subtree except for a leaf alone in it, which `finish_subtrees()` writes.
+    pub fn finish_subtree(mut self) -> Result<RestoredSubtree<K>> {
+        let nibble = match self.subtree {
+            Some(nibble) => nibble,
+            None => bail!("Not a subtree restore."),
+        };
+        self.wait_for_async_commit()?;
+        if self.partial_nodes.is_empty() {
+            return Ok(RestoredSubtree {
+                nibble,
+                child: None,
+                tree_complete: true,
+            });
+        }
+
+        // With a single partial node, the subtree is either complete already, empty or a single
+        // leaf. Otherwise, freeze everything up to the root of the subtree.
+        if self.partial_nodes.len() > 1 {
+            self.freeze(1);
+            self.store.write_node_batch(&self.frozen_nodes)?;
+        }
+        Ok(RestoredSubtree {
+            nibble,
+            child: self.partial_nodes[0].children[u8::from(nibble) as usize].take(),
+            tree_complete: false,
+        })
+    }
+
+    /// Checks the root node about to be written, if any, against the expected root hash. Chunks
+    /// with proofs are verified as they come, but those added without are only verified here.
+    fn ensure_root_hash(&self) -> Result<()> {
+        if let Some(root_node) = self
+            .frozen_nodes
+            .get(&NodeKey::new_empty_path(self.version))
+        {
+            ensure!(
+                root_node.hash() == self.expected_root_hash,
+                "Restored tree has root hash {}, expecting {}",
+                root_node.hash(),
+                self.expected_root_hash,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A subtree restored by `JellyfishMerkleRestore::new_subtree()`.
+pub struct RestoredSubtree<K> {
+    nibble: Nibble,
+    /// The root of the subtree, `None` if it's empty.
+    child: Option<ChildInfo<K>>,
+    /// Whether the whole tree was already restored by a previous attempt.
+    tree_complete: bool,
+}
+
+/// Writes the root of a tree whose subtrees were restored by
+/// `JellyfishMerkleRestore::new_subtree()`, after checking its hash. A subtree with a single leaf
+/// is written here as well, because the leaf becomes the root if it's the only one in the tree.
+pub fn finish_subtrees<K>(
+    store: &dyn TreeWriter<K>,
+    version: Version,
+    expected_root_hash: HashValue,
+    subtrees: Vec<RestoredSubtree<K>>,
+) -> Result<()>
+where
+    K: crate::Key + CryptoHash,
+{
+    if subtrees.iter().any(|subtree| subtree.tree_complete) {
+        // The root hash was checked when the restore was created.
+        return Ok(());
+    }
+
+    let mut root = InternalInfo::new_empty(NodeKey::new_empty_path(version));
+    for subtree in subtrees {
+        if let Some(child_info) = subtree.child {
+            let index = u8::from(subtree.nibble) as usize;
+            ensure!(
+                root.children[index].is_none(),
+                "Subtree {:?} is restored more than once.",
+                subtree.nibble,
+            );
+            root.set_child(index, child_info);
+        }
+    }
+
+    let mut node_batch = HashMap::new();
+    let num_children = root.children.iter().flatten().count();
+    let root_node: Node<K> = match root.children.iter().flatten().next() {
+        None => Node::Null,
+        Some(ChildInfo::Leaf(node)) if num_children == 1 => node.clone().into(),
+        _ => {
+            for (index, child_info) in root.children.iter().enumerate() {
+                if let Some(ChildInfo::Leaf(node)) = child_info {
+                    node_batch.insert(
+                        root.node_key
+                            .gen_child_node_key(version, (index as u8).into()),
+                        node.clone().into(),
+                    );
+                }
+            }
+            root.into_internal_node(version).1.into()
+        },
+    };
+    ensure!(
+        root_node.hash() == expected_root_hash,
+        "Restored tree has root hash {}, expecting {}",
+        root_node.hash(),
+        expected_root_hash,
+    );
+    node_batch.insert(NodeKey::new_empty_path(version), root_node);
+    store.write_node_batch(&node_batch)
 }
 
 impl<K> Drop for JellyfishMerkleRestore<K> {
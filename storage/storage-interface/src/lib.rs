@@ -179,6 +179,11 @@ pub trait DbReader: Send + Sync {
         /// [AptosDB::get_first_write_set_version]: ../aptosdb/struct.AptosDB.html#method.get_first_write_set_version
         fn get_first_write_set_version(&self) -> Result<Option<Version>>;
 
+        /// See [AptosDB::get_first_state_value_version].
+        ///
+        /// [AptosDB::get_first_state_value_version]: ../aptosdb/struct.AptosDB.html#method.get_first_state_value_version
+        fn get_first_state_value_version(&self) -> Result<Option<Version>>;
+
         /// See [AptosDB::get_transaction_outputs].
         ///
         /// [AptosDB::get_transaction_outputs]: ../aptosdb/struct.AptosDB.html#method.get_transaction_outputs
@@ -439,6 +444,20 @@ pub trait DbReader: Send + Sync {
         /// Returns whether the internal indexer DB has been enabled or not
         fn indexer_enabled(&self) -> bool;
 
+        /// Get the versions of up to `limit` transactions in `[start_version, ledger_version]`
+        /// that touched the account (i.e. changed a resource or module under its address), from
+        /// the internal indexer.
+        fn get_account_transaction_versions(
+            &self,
+            address: AccountAddress,
+            start_version: Version,
+            limit: u64,
+            ledger_version: Version,
+        ) -> Result<Vec<Version>>;
+
+        /// Get the first version whose account transactions are indexed by the internal indexer.
+        fn get_account_transactions_start_version(&self) -> Result<Version>;
+
         /// Returns state storage usage at the end of an epoch.
         fn get_state_storage_usage(&self, version: Option<Version>) -> Result<StateStorageUsage>;
     ); // end delegated
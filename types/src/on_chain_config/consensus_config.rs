@@ -14,6 +14,7 @@ pub enum OnChainConsensusConfig {
     V1(ConsensusConfigV1),
     V2(ConsensusConfigV1),
     DagV1(DagConsensusConfigV1),
+    DagV2(DagConsensusConfigV2),
 }
 
 /// The public interface that exposes all values with safe fallback.
@@ -58,17 +59,23 @@ impl OnChainConsensusConfig {
         match &self {
             OnChainConsensusConfig::V1(_config) => false,
             OnChainConsensusConfig::V2(_config) => true,
-            OnChainConsensusConfig::DagV1(_) => false,
+            OnChainConsensusConfig::DagV1(_) | OnChainConsensusConfig::DagV2(_) => false,
         }
     }
 
     pub fn is_dag_enabled(&self) -> bool {
-        matches!(self, OnChainConsensusConfig::DagV1(_))
+        matches!(
+            self,
+            OnChainConsensusConfig::DagV1(_) | OnChainConsensusConfig::DagV2(_)
+        )
     }
 
-    pub fn unwrap_dag_config_v1(&self) -> &DagConsensusConfigV1 {
+    /// The DAG config, with the fields missing from older versions set to what those versions
+    /// behave like.
+    pub fn unwrap_dag_config(&self) -> DagConsensusConfigV2 {
         match &self {
-            OnChainConsensusConfig::DagV1(config) => config,
+            OnChainConsensusConfig::DagV1(config) => config.clone().into(),
+            OnChainConsensusConfig::DagV2(config) => config.clone(),
             _ => unreachable!("not a dag config"),
         }
     }
@@ -218,6 +225,52 @@ impl Default for DagConsensusConfigV1 {
     }
 }
 
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct DagConsensusConfigV2 {
+    pub dag_ordering_causal_history_window: usize,
+    pub anchor_election_mode: AnchorElectionMode,
+}
+
+impl Default for DagConsensusConfigV2 {
+    fn default() -> Self {
+        Self {
+            dag_ordering_causal_history_window: 1,
+            anchor_election_mode: AnchorElectionMode::LeaderReputation(
+                LeaderReputationType::ProposerAndVoterV2(ProposerAndVoterConfig {
+                    active_weight: 1000,
+                    inactive_weight: 10,
+                    failed_weight: 1,
+                    failure_threshold_percent: 10,
+                    proposer_window_num_validators_multiplier: 10,
+                    voter_window_num_validators_multiplier: 1,
+                    weight_by_voting_power: true,
+                    use_history_from_previous_epoch_max_count: 5,
+                }),
+            ),
+        }
+    }
+}
+
+/// V1 always elects anchors round robin.
+impl From<DagConsensusConfigV1> for DagConsensusConfigV2 {
+    fn from(config: DagConsensusConfigV1) -> Self {
+        Self {
+            dag_ordering_causal_history_window: config.dag_ordering_causal_history_window,
+            anchor_election_mode: AnchorElectionMode::RoundRobin,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorElectionMode {
+    // Round robin rotation of anchors, in validator order
+    RoundRobin,
+    // Committed anchor history based anchor election. As anchors are picked from the
+    // ordered history alone, the seed doesn't include the root hash for any version.
+    LeaderReputation(LeaderReputationType),
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -282,4 +335,25 @@ mod test {
             ProposerElectionType::RoundProposer(_value)
         ));
     }
+
+    #[test]
+    fn test_dag_config_v1_layout_and_anchor_election() {
+        let config = OnChainConsensusConfig::DagV1(DagConsensusConfigV1 {
+            dag_ordering_causal_history_window: 3,
+        });
+
+        // V1 only holds the causal history window, after the variant index
+        let bytes = bcs::to_bytes(&config).unwrap();
+        assert_eq!(bytes, [vec![2], 3u64.to_le_bytes().to_vec()].concat());
+        assert_eq!(
+            bcs::from_bytes::<OnChainConsensusConfig>(&bytes).unwrap(),
+            config
+        );
+
+        // and it keeps electing anchors round robin
+        assert_eq!(config.unwrap_dag_config(), DagConsensusConfigV2 {
+            dag_ordering_causal_history_window: 3,
+            anchor_election_mode: AnchorElectionMode::RoundRobin,
+        });
+    }
 }
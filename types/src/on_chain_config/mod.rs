@@ -37,8 +37,8 @@ pub use self::{
         Version, APTOS_MAX_KNOWN_VERSION, APTOS_VERSION_2, APTOS_VERSION_3, APTOS_VERSION_4,
     },
     consensus_config::{
-        ConsensusConfigV1, LeaderReputationType, OnChainConsensusConfig, ProposerAndVoterConfig,
-        ProposerElectionType,
+        AnchorElectionMode, ConsensusConfigV1, DagConsensusConfigV1, DagConsensusConfigV2,
+        LeaderReputationType, OnChainConsensusConfig, ProposerAndVoterConfig, ProposerElectionType,
     },
     execution_config::{
         ExecutionConfigV1, ExecutionConfigV2, OnChainExecutionConfig, TransactionDeduperType,